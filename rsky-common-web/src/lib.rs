@@ -0,0 +1,216 @@
+//! Error responses shared by rsky's XRPC services. Every failed XRPC call answers with a JSON
+//! body of the form `{"error": "<Name>", "message": "<details>"}`, where the name is either one
+//! of the protocol-wide [`XrpcErrorKind`]s, which each map to an HTTP status, or an error the
+//! method's lexicon declares (e.g. `UnknownFeed`), which is sent with 400.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The error names the XRPC spec defines for all methods, alongside their statuses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum XrpcErrorKind {
+    InvalidRequest,
+    AuthenticationRequired,
+    Forbidden,
+    XRPCNotSupported,
+    NotAcceptable,
+    PayloadTooLarge,
+    UnsupportedMediaType,
+    RateLimitExceeded,
+    InternalServerError,
+    MethodNotImplemented,
+    UpstreamFailure,
+    NotEnoughResources,
+    UpstreamTimeout,
+}
+
+impl XrpcErrorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            XrpcErrorKind::InvalidRequest => "InvalidRequest",
+            XrpcErrorKind::AuthenticationRequired => "AuthenticationRequired",
+            XrpcErrorKind::Forbidden => "Forbidden",
+            XrpcErrorKind::XRPCNotSupported => "XRPCNotSupported",
+            XrpcErrorKind::NotAcceptable => "NotAcceptable",
+            XrpcErrorKind::PayloadTooLarge => "PayloadTooLarge",
+            XrpcErrorKind::UnsupportedMediaType => "UnsupportedMediaType",
+            XrpcErrorKind::RateLimitExceeded => "RateLimitExceeded",
+            XrpcErrorKind::InternalServerError => "InternalServerError",
+            XrpcErrorKind::MethodNotImplemented => "MethodNotImplemented",
+            XrpcErrorKind::UpstreamFailure => "UpstreamFailure",
+            XrpcErrorKind::NotEnoughResources => "NotEnoughResources",
+            XrpcErrorKind::UpstreamTimeout => "UpstreamTimeout",
+        }
+    }
+
+    pub fn status(&self) -> u16 {
+        match self {
+            XrpcErrorKind::InvalidRequest => 400,
+            XrpcErrorKind::AuthenticationRequired => 401,
+            XrpcErrorKind::Forbidden => 403,
+            XrpcErrorKind::XRPCNotSupported => 404,
+            XrpcErrorKind::NotAcceptable => 406,
+            XrpcErrorKind::PayloadTooLarge => 413,
+            XrpcErrorKind::UnsupportedMediaType => 415,
+            XrpcErrorKind::RateLimitExceeded => 429,
+            XrpcErrorKind::InternalServerError => 500,
+            XrpcErrorKind::MethodNotImplemented => 501,
+            XrpcErrorKind::UpstreamFailure => 502,
+            XrpcErrorKind::NotEnoughResources => 503,
+            XrpcErrorKind::UpstreamTimeout => 504,
+        }
+    }
+
+    /// The kind a bare HTTP status stands for, e.g. when relaying an upstream failure that
+    /// came without a body. Unknown 4xx are `InvalidRequest` and anything else is
+    /// `InternalServerError`.
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 => XrpcErrorKind::AuthenticationRequired,
+            403 => XrpcErrorKind::Forbidden,
+            404 => XrpcErrorKind::XRPCNotSupported,
+            406 => XrpcErrorKind::NotAcceptable,
+            413 => XrpcErrorKind::PayloadTooLarge,
+            415 => XrpcErrorKind::UnsupportedMediaType,
+            429 => XrpcErrorKind::RateLimitExceeded,
+            501 => XrpcErrorKind::MethodNotImplemented,
+            502 => XrpcErrorKind::UpstreamFailure,
+            503 => XrpcErrorKind::NotEnoughResources,
+            504 => XrpcErrorKind::UpstreamTimeout,
+            400..=499 => XrpcErrorKind::InvalidRequest,
+            _ => XrpcErrorKind::InternalServerError,
+        }
+    }
+}
+
+impl fmt::Display for XrpcErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The JSON body of a failed XRPC call.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct XrpcErrorBody {
+    pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// An error body with the status it's sent with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XrpcError {
+    pub status: u16,
+    pub body: XrpcErrorBody,
+}
+
+impl XrpcError {
+    pub fn new(kind: XrpcErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            status: kind.status(),
+            body: XrpcErrorBody {
+                error: kind.name().to_string(),
+                message: Some(message.into()),
+            },
+        }
+    }
+
+    /// An error declared by the method's lexicon, sent with 400.
+    pub fn custom(error: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            status: 400,
+            body: XrpcErrorBody {
+                error: error.into(),
+                message: Some(message.into()),
+            },
+        }
+    }
+
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self::new(XrpcErrorKind::InvalidRequest, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(XrpcErrorKind::InternalServerError, message)
+    }
+
+    pub fn error(&self) -> &str {
+        &self.body.error
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.body.message.as_deref()
+    }
+}
+
+impl fmt::Display for XrpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.body.message {
+            Some(message) => write!(f, "{}: {message}", self.body.error),
+            None => f.write_str(&self.body.error),
+        }
+    }
+}
+
+impl std::error::Error for XrpcError {}
+
+impl From<XrpcErrorKind> for XrpcError {
+    fn from(kind: XrpcErrorKind) -> Self {
+        Self {
+            status: kind.status(),
+            body: XrpcErrorBody {
+                error: kind.name().to_string(),
+                message: None,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "rocket")]
+impl<'r, 'o: 'r> rocket::response::Responder<'r, 'o> for XrpcError {
+    fn respond_to(self, req: &'r rocket::Request<'_>) -> rocket::response::Result<'o> {
+        use rocket::http::Status;
+        use rocket::response::Responder;
+
+        let status = Status::from_code(self.status).unwrap_or(Status::InternalServerError);
+        let mut res = rocket::serde::json::Json(self.body).respond_to(req)?;
+        res.set_status(status);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xrpc_error() {
+        let error = XrpcError::invalid_request("Missing feed");
+        assert_eq!(error.status, 400);
+        assert_eq!(
+            serde_json::to_value(&error.body).unwrap(),
+            serde_json::json!({"error": "InvalidRequest", "message": "Missing feed"})
+        );
+        let error = XrpcError::from(XrpcErrorKind::RateLimitExceeded);
+        assert_eq!(error.status, 429);
+        assert_eq!(
+            serde_json::to_value(&error.body).unwrap(),
+            serde_json::json!({"error": "RateLimitExceeded"})
+        );
+        let error = XrpcError::custom("UnknownFeed", "Unknown feed");
+        assert_eq!((error.status, error.error()), (400, "UnknownFeed"));
+
+        for status in [401, 403, 404, 413, 429, 500, 501, 502, 503, 504] {
+            assert_eq!(XrpcErrorKind::from_status(status).status(), status);
+        }
+        assert_eq!(
+            XrpcErrorKind::from_status(418),
+            XrpcErrorKind::InvalidRequest
+        );
+    }
+}
@@ -0,0 +1,304 @@
+//! Checks that a block is canonical DAG-CBOR as atproto requires it:
+//! definite lengths, minimal integer/length encodings, text map keys sorted length-first
+//! without duplicates, no floats, and no tags other than 42 (CID links).
+//! https://atproto.com/specs/data-model#data-representations
+
+use lexicon_cid::Cid;
+use rayon::prelude::*;
+use thiserror::Error;
+
+const DAGCBORCODEC: u64 = 0x71;
+const CID_TAG: u64 = 42;
+// deeper than any record the lexicons allow, but keeps a hostile block from blowing the stack
+const MAX_DEPTH: usize = 128;
+// below this, handing work to the pool costs more than the check itself
+const MIN_PARALLEL_BATCH: usize = 64;
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_NEGATIVE: u8 = 1;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_TAG: u8 = 6;
+const MAJOR_SIMPLE: u8 = 7;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CanonicalCborError {
+    #[error("unexpected end of input at offset {0}")]
+    UnexpectedEof(usize),
+    #[error("{0} trailing bytes after top-level item")]
+    TrailingBytes(usize),
+    #[error("reserved additional info at offset {0}")]
+    Malformed(usize),
+    #[error("indefinite length item at offset {0}")]
+    IndefiniteLength(usize),
+    #[error("non-minimal integer or length encoding at offset {0}")]
+    NonMinimalEncoding(usize),
+    #[error("floating point value at offset {0}")]
+    Float(usize),
+    #[error("unsupported simple value {value} at offset {offset}")]
+    UnsupportedSimpleValue { value: u64, offset: usize },
+    #[error("unsupported tag {tag} at offset {offset}")]
+    UnsupportedTag { tag: u64, offset: usize },
+    #[error("malformed CID link at offset {0}")]
+    InvalidCidLink(usize),
+    #[error("invalid utf-8 in text string at offset {0}")]
+    InvalidUtf8(usize),
+    #[error("map key is not a text string at offset {0}")]
+    NonTextMapKey(usize),
+    #[error("map keys out of canonical order at offset {0}")]
+    MapKeyOrder(usize),
+    #[error("duplicate map key at offset {0}")]
+    DuplicateMapKey(usize),
+    #[error("nesting deeper than {MAX_DEPTH} levels at offset {0}")]
+    TooDeep(usize),
+}
+
+struct Head {
+    major: u8,
+    arg: u64,
+    offset: usize,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: u64) -> Result<&'a [u8], CanonicalCborError> {
+        let start = self.pos;
+        let end = usize::try_from(len)
+            .ok()
+            .and_then(|len| start.checked_add(len))
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(CanonicalCborError::UnexpectedEof(start))?;
+        self.pos = end;
+        Ok(&self.bytes[start..end])
+    }
+
+    fn read_uint(&mut self, len: u64) -> Result<u64, CanonicalCborError> {
+        Ok(self
+            .take(len)?
+            .iter()
+            .fold(0u64, |acc, byte| (acc << 8) | *byte as u64))
+    }
+
+    fn read_head(&mut self) -> Result<Head, CanonicalCborError> {
+        let offset = self.pos;
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        if major == MAJOR_SIMPLE {
+            return match info {
+                20..=22 => Ok(Head {
+                    major,
+                    arg: info as u64,
+                    offset,
+                }),
+                25..=27 => Err(CanonicalCborError::Float(offset)),
+                31 => Err(CanonicalCborError::IndefiniteLength(offset)),
+                24 => Err(CanonicalCborError::UnsupportedSimpleValue {
+                    value: self.read_uint(1)?,
+                    offset,
+                }),
+                28..=30 => Err(CanonicalCborError::Malformed(offset)),
+                value => Err(CanonicalCborError::UnsupportedSimpleValue {
+                    value: value as u64,
+                    offset,
+                }),
+            };
+        }
+        let (arg, minimum) = match info {
+            0..=23 => (info as u64, 0),
+            24 => (self.read_uint(1)?, 24),
+            25 => (self.read_uint(2)?, 0x100),
+            26 => (self.read_uint(4)?, 0x1_0000),
+            27 => (self.read_uint(8)?, 0x1_0000_0000),
+            31 => return Err(CanonicalCborError::IndefiniteLength(offset)),
+            _ => return Err(CanonicalCborError::Malformed(offset)),
+        };
+        if arg < minimum {
+            return Err(CanonicalCborError::NonMinimalEncoding(offset));
+        }
+        Ok(Head { major, arg, offset })
+    }
+
+    fn read_text(&mut self, head: &Head) -> Result<&'a [u8], CanonicalCborError> {
+        let text = self.take(head.arg)?;
+        std::str::from_utf8(text).map_err(|_| CanonicalCborError::InvalidUtf8(head.offset))?;
+        Ok(text)
+    }
+
+    fn check_item(&mut self, depth: usize) -> Result<(), CanonicalCborError> {
+        let head = self.read_head()?;
+        if depth > MAX_DEPTH {
+            return Err(CanonicalCborError::TooDeep(head.offset));
+        }
+        match head.major {
+            MAJOR_UNSIGNED | MAJOR_NEGATIVE | MAJOR_SIMPLE => Ok(()),
+            MAJOR_BYTES => self.take(head.arg).map(|_| ()),
+            MAJOR_TEXT => self.read_text(&head).map(|_| ()),
+            MAJOR_ARRAY => (0..head.arg).try_for_each(|_| self.check_item(depth + 1)),
+            MAJOR_MAP => {
+                let mut prev_key: Option<&[u8]> = None;
+                for _ in 0..head.arg {
+                    let key_head = self.read_head()?;
+                    if key_head.major != MAJOR_TEXT {
+                        return Err(CanonicalCborError::NonTextMapKey(key_head.offset));
+                    }
+                    let key = self.read_text(&key_head)?;
+                    if let Some(prev) = prev_key {
+                        // DAG-CBOR sorts keys by encoded length first, then bytewise
+                        match (prev.len(), prev).cmp(&(key.len(), key)) {
+                            std::cmp::Ordering::Less => {}
+                            std::cmp::Ordering::Equal => {
+                                return Err(CanonicalCborError::DuplicateMapKey(key_head.offset))
+                            }
+                            std::cmp::Ordering::Greater => {
+                                return Err(CanonicalCborError::MapKeyOrder(key_head.offset))
+                            }
+                        }
+                    }
+                    prev_key = Some(key);
+                    self.check_item(depth + 1)?;
+                }
+                Ok(())
+            }
+            MAJOR_TAG => {
+                if head.arg != CID_TAG {
+                    return Err(CanonicalCborError::UnsupportedTag {
+                        tag: head.arg,
+                        offset: head.offset,
+                    });
+                }
+                // a link is a byte string holding the binary CID behind a 0x00 multibase prefix
+                let link = self.read_head()?;
+                if link.major != MAJOR_BYTES {
+                    return Err(CanonicalCborError::InvalidCidLink(link.offset));
+                }
+                let bytes = self.take(link.arg)?;
+                match bytes.split_first() {
+                    Some((0x00, cid)) if Cid::read_bytes(cid).is_ok() => Ok(()),
+                    _ => Err(CanonicalCborError::InvalidCidLink(link.offset)),
+                }
+            }
+            _ => unreachable!("major type is three bits"),
+        }
+    }
+}
+
+/// Verifies `bytes` holds exactly one canonically encoded DAG-CBOR item.
+pub fn check_canonical_cbor(bytes: &[u8]) -> Result<(), CanonicalCborError> {
+    let mut reader = Reader { bytes, pos: 0 };
+    reader.check_item(0)?;
+    match bytes.len() - reader.pos {
+        0 => Ok(()),
+        trailing => Err(CanonicalCborError::TrailingBytes(trailing)),
+    }
+}
+
+/// Checks every DAG-CBOR block in `blocks` on the rayon pool, returning the first block that
+/// isn't canonical. Blocks with other codecs (e.g. raw) are skipped.
+pub fn find_noncanonical_block(blocks: &[(Cid, &[u8])]) -> Option<(Cid, CanonicalCborError)> {
+    blocks
+        .par_iter()
+        .with_min_len(MIN_PARALLEL_BATCH)
+        .filter(|(cid, _)| cid.codec() == DAGCBORCODEC)
+        .find_map_first(|(cid, bytes)| check_canonical_cbor(bytes).err().map(|err| (*cid, err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipld::cid_for_cbor_bytes;
+    use ipld_core::ipld::Ipld;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_accepts_encoder_output() {
+        let cid = cid_for_cbor_bytes(b"\xa0").unwrap();
+        let record: BTreeMap<&str, Ipld> = BTreeMap::from([
+            ("text", Ipld::String("hello".to_string())),
+            (
+                "createdAt",
+                Ipld::String("2024-01-01T00:00:00.000Z".to_string()),
+            ),
+            ("langs", Ipld::List(vec![Ipld::String("en".to_string())])),
+            ("count", Ipld::Integer(300)),
+            ("negative", Ipld::Integer(-70000)),
+            ("reply", Ipld::Null),
+            ("ok", Ipld::Bool(true)),
+            ("link", Ipld::Link(cid)),
+            ("bytes", Ipld::Bytes(vec![1, 2, 3])),
+        ]);
+        let bytes = crate::struct_to_cbor(&record).unwrap();
+        assert_eq!(check_canonical_cbor(&bytes), Ok(()));
+    }
+
+    #[test]
+    fn test_rejects_non_canonical() {
+        // {"bb": 1, "a": 2}: longer key sorted first
+        let unordered = [0xa2, 0x62, b'b', b'b', 0x01, 0x61, b'a', 0x02];
+        assert_eq!(
+            check_canonical_cbor(&unordered),
+            Err(CanonicalCborError::MapKeyOrder(5))
+        );
+        // {"a": 1, "a": 2}
+        let duplicate = [0xa2, 0x61, b'a', 0x01, 0x61, b'a', 0x02];
+        assert_eq!(
+            check_canonical_cbor(&duplicate),
+            Err(CanonicalCborError::DuplicateMapKey(4))
+        );
+        // 1.5 as a half float
+        assert_eq!(
+            check_canonical_cbor(&[0xf9, 0x3e, 0x00]),
+            Err(CanonicalCborError::Float(0))
+        );
+        // indefinite length array [1]
+        assert_eq!(
+            check_canonical_cbor(&[0x9f, 0x01, 0xff]),
+            Err(CanonicalCborError::IndefiniteLength(0))
+        );
+        // 1 encoded in a one byte argument
+        assert_eq!(
+            check_canonical_cbor(&[0x18, 0x01]),
+            Err(CanonicalCborError::NonMinimalEncoding(0))
+        );
+        // {1: 1}
+        assert_eq!(
+            check_canonical_cbor(&[0xa1, 0x01, 0x01]),
+            Err(CanonicalCborError::NonTextMapKey(1))
+        );
+        // tag 1 (epoch time)
+        assert_eq!(
+            check_canonical_cbor(&[0xc1, 0x01]),
+            Err(CanonicalCborError::UnsupportedTag { tag: 1, offset: 0 })
+        );
+        assert_eq!(
+            check_canonical_cbor(&[0x01, 0x01]),
+            Err(CanonicalCborError::TrailingBytes(1))
+        );
+        assert_eq!(
+            check_canonical_cbor(&[0x62, b'a']),
+            Err(CanonicalCborError::UnexpectedEof(1))
+        );
+    }
+
+    #[test]
+    fn test_find_noncanonical_block() {
+        let good = crate::struct_to_cbor(&BTreeMap::from([("a", 1)])).unwrap();
+        let bad = vec![0xf9, 0x3e, 0x00];
+        let good_cid = cid_for_cbor_bytes(&good).unwrap();
+        let bad_cid = cid_for_cbor_bytes(&bad).unwrap();
+        let raw_cid = crate::ipld::sha256_to_cid(vec![0; 32]);
+        assert_eq!(
+            find_noncanonical_block(&[(good_cid, good.as_slice()), (raw_cid, bad.as_slice())]),
+            None
+        );
+        assert_eq!(
+            find_noncanonical_block(&[(good_cid, good.as_slice()), (bad_cid, bad.as_slice())]),
+            Some((bad_cid, CanonicalCborError::Float(0)))
+        );
+    }
+}
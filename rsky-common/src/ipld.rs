@@ -2,6 +2,7 @@ use anyhow::Result;
 use ipld_core::codec::Codec;
 use lexicon_cid::Cid;
 use multihash::Multihash;
+use rayon::prelude::*;
 use serde::Serialize;
 use sha2::{Digest, Sha256};
 
@@ -9,17 +10,74 @@ const SHA2_256: u64 = 0x12;
 const DAGCBORCODEC: u64 = 0x71;
 // https://docs.rs/libipld-core/0.16.0/src/libipld_core/raw.rs.html#19
 const RAWCODEC: u64 = 0x77;
+// below this, handing work to the pool costs more than the hashing itself
+const MIN_PARALLEL_BATCH: usize = 64;
+
+/// Reusable SHA-256 context for computing many CIDs on one thread.
+#[derive(Clone, Default)]
+pub struct CidHasher {
+    sha: Sha256,
+}
+
+impl CidHasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// CID of an already DAG-CBOR encoded block.
+    pub fn cid_for_cbor_bytes(&mut self, bytes: &[u8]) -> Result<Cid> {
+        self.cid_for_bytes(DAGCBORCODEC, bytes)
+    }
+
+    pub fn cid_for_bytes(&mut self, codec: u64, bytes: &[u8]) -> Result<Cid> {
+        self.sha.update(bytes);
+        let hash = self.sha.finalize_reset();
+        Ok(Cid::new_v1(
+            codec,
+            Multihash::<64>::wrap(SHA2_256, hash.as_slice())?,
+        ))
+    }
+
+    /// Encodes `data` as DAG-CBOR, returning its CID along with the encoded bytes.
+    pub fn cid_for_cbor<T: Serialize>(&mut self, data: &T) -> Result<(Cid, Vec<u8>)> {
+        let bytes = crate::struct_to_cbor(data)?;
+        Ok((self.cid_for_cbor_bytes(&bytes)?, bytes))
+    }
+}
 
 pub fn cid_for_cbor<T: Serialize>(data: &T) -> Result<Cid> {
-    let bytes = crate::struct_to_cbor(data)?;
-    let mut sha = Sha256::new();
-    sha.update(&bytes);
-    let hash = sha.finalize();
-    let cid = Cid::new_v1(
-        DAGCBORCODEC,
-        Multihash::<64>::wrap(SHA2_256, hash.as_slice())?,
-    );
-    Ok(cid)
+    Ok(CidHasher::new().cid_for_cbor(data)?.0)
+}
+
+pub fn cid_for_cbor_bytes(bytes: &[u8]) -> Result<Cid> {
+    CidHasher::new().cid_for_cbor_bytes(bytes)
+}
+
+/// Encodes and hashes `items` on the rayon pool, returning each item's CID and DAG-CBOR bytes
+/// in the same order.
+pub fn cids_for_cbor_batch<T: Serialize + Sync>(items: &[T]) -> Result<Vec<(Cid, Vec<u8>)>> {
+    items
+        .par_iter()
+        .with_min_len(MIN_PARALLEL_BATCH)
+        .map_init(CidHasher::new, |hasher, item| hasher.cid_for_cbor(item))
+        .collect()
+}
+
+/// Recomputes the CID of each `(cid, bytes)` block with the codec its claimed CID uses,
+/// returning the claimed CIDs that don't match their bytes.
+pub fn find_mismatched_cids(blocks: &[(Cid, &[u8])]) -> Result<Vec<Cid>> {
+    let checked: Vec<Option<Cid>> = blocks
+        .par_iter()
+        .with_min_len(MIN_PARALLEL_BATCH)
+        .map_init(CidHasher::new, |hasher, (cid, bytes)| {
+            if cid.hash().code() != SHA2_256 {
+                return Ok(None);
+            }
+            let computed = hasher.cid_for_bytes(cid.codec(), bytes)?;
+            Ok((computed.hash() != cid.hash()).then_some(*cid))
+        })
+        .collect::<Result<Vec<Option<Cid>>>>()?;
+    Ok(checked.into_iter().flatten().collect())
 }
 
 pub fn sha256_to_cid(hash: Vec<u8>) -> Cid {
@@ -29,3 +87,47 @@ pub fn sha256_to_cid(hash: Vec<u8>) -> Cid {
     );
     cid
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn record(i: usize) -> BTreeMap<String, String> {
+        BTreeMap::from([("text".to_string(), format!("post {i}"))])
+    }
+
+    #[test]
+    fn test_batch_matches_single() {
+        let records: Vec<_> = (0..200).map(record).collect();
+        let batch = cids_for_cbor_batch(&records).unwrap();
+        assert_eq!(batch.len(), records.len());
+        for (record, (cid, bytes)) in records.iter().zip(batch) {
+            assert_eq!(cid, cid_for_cbor(record).unwrap());
+            assert_eq!(bytes, crate::struct_to_cbor(record).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_hasher_resets_between_blocks() {
+        let mut hasher = CidHasher::new();
+        let first = hasher.cid_for_cbor(&record(1)).unwrap().0;
+        let second = hasher.cid_for_cbor(&record(1)).unwrap().0;
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_find_mismatched_cids() {
+        let good = crate::struct_to_cbor(&record(1)).unwrap();
+        let bad = crate::struct_to_cbor(&record(2)).unwrap();
+        let good_cid = cid_for_cbor_bytes(&good).unwrap();
+        let bad_cid = cid_for_cbor_bytes(&good).unwrap();
+        let raw_cid = sha256_to_cid(Sha256::digest(&bad).to_vec());
+        let blocks = vec![
+            (good_cid, good.as_slice()),
+            (bad_cid, bad.as_slice()),
+            (raw_cid, bad.as_slice()),
+        ];
+        assert_eq!(find_mismatched_cids(&blocks).unwrap(), vec![bad_cid]);
+    }
+}
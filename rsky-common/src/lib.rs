@@ -166,6 +166,7 @@ pub fn validate_url(url_str: &String) -> Option<String> {
 }
 
 pub mod r#async;
+pub mod cbor;
 pub mod env;
 pub mod explicit_slurs;
 pub mod ipld;
@@ -1,11 +1,15 @@
 use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
+use std::sync::{LazyLock, Mutex, PoisonError};
 use std::time::SystemTime;
 
 const TID_LEN: usize = 13;
 const S32_CHAR: &str = "234567abcdefghijklmnopqrstuvwxyz";
 
+// one clock for the whole process so TIDs minted by concurrent requests never repeat
+static TICKER: LazyLock<Mutex<Ticker>> = LazyLock::new(|| Mutex::new(Ticker::new()));
+
 pub fn s32encode(mut i: usize) -> String {
     let mut s: String = "".to_owned();
     while i > 0 {
@@ -76,6 +80,14 @@ impl TID {
         self.compare_to(other) < 0
     }
 
+    /// Next TID from the process-wide ticker; always newer than any TID it handed out before.
+    pub fn next() -> Self {
+        TICKER
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .next(None)
+    }
+
     pub fn next_str(prev: Option<String>) -> Result<String> {
         let prev = match prev {
             None => None,
@@ -140,3 +152,19 @@ impl Default for Ticker {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_ticker_is_monotonic() {
+        let mut prev = TID::next();
+        for _ in 0..1000 {
+            let next = TID::next();
+            assert!(next.newer_than(&prev));
+            assert_eq!(next.clock_id(), prev.clock_id());
+            prev = next;
+        }
+    }
+}
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS repost;
+DROP INDEX IF EXISTS post_replyrootauthor_idx;
+ALTER TABLE post
+DROP COLUMN IF EXISTS "replyRootAuthor";
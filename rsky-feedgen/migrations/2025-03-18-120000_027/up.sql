@@ -0,0 +1,19 @@
+-- Your SQL goes here
+ALTER TABLE post
+ADD COLUMN "replyRootAuthor" VARCHAR;
+
+CREATE INDEX IF NOT EXISTS post_replyrootauthor_idx ON post("replyRootAuthor");
+
+CREATE TABLE IF NOT EXISTS repost (
+    uri VARCHAR PRIMARY KEY,
+    cid VARCHAR NOT NULL,
+    author VARCHAR NOT NULL,
+    "subjectCid" VARCHAR NOT NULL,
+    "subjectUri" VARCHAR NOT NULL,
+    "createdAt" TIMESTAMPTZ NOT NULL,
+    "indexedAt" TIMESTAMPTZ NOT NULL,
+    prev VARCHAR,
+    sequence BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS repost_author_createdat_idx ON repost(author, "createdAt" DESC, cid DESC);
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS experiment_exposure (
+    id SERIAL PRIMARY KEY,
+    did VARCHAR NOT NULL,
+    feed VARCHAR NOT NULL,
+    experiment VARCHAR NOT NULL,
+    variant VARCHAR NOT NULL,
+    exposed_at VARCHAR NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS experiment_exposure_experiment_idx ON experiment_exposure(experiment, variant);
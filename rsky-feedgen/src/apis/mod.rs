@@ -1,14 +1,16 @@
 use crate::db::*;
 use crate::models::create_request::CreateRecord;
-use crate::models::Lexicon::{AppBskyFeedFollow, AppBskyFeedLike, AppBskyFeedPost};
+use crate::models::Lexicon::{
+    AppBskyFeedFollow, AppBskyFeedLike, AppBskyFeedPost, AppBskyFeedRepost,
+};
 use crate::models::*;
-use crate::{FeedGenConfig, ReadReplicaConn, WriteDbConn};
+use crate::storage::{
+    Collection, FeedCursor, FeedStorage, MediaKind, MembershipFilter, PostQuery, StorageResult,
+    TrendingQuery,
+};
+use crate::{FeedGenConfig, ReadReplicaConn, WriteConn};
 use chrono::offset::Utc as UtcOffset;
 use chrono::{DateTime, NaiveDateTime, Utc};
-use diesel::dsl::sql;
-use diesel::prelude::*;
-use diesel::sql_query;
-use diesel::sql_types::{Array, Bool, Nullable, Text};
 use moka::future::Cache;
 use once_cell::sync::Lazy;
 use rand::Rng;
@@ -21,113 +23,137 @@ use rsky_lexicon::app::bsky::feed::PostLabels;
 use std::collections::HashSet;
 use std::time::{Duration, SystemTime};
 
+pub use crate::storage::{ThreadFilter, TrendingMedia};
+
+/// A feed entry with the keys it is paged by.
+#[derive(Clone, Debug, PartialEq)]
+struct FeedItem {
+    created_at: DateTime<Utc>,
+    indexed_at: DateTime<Utc>,
+    cid: String,
+    result: PostResult,
+}
+
+/// Interleaves posts and reposts newest first, keeping at most `limit`.
+fn merge_feed_items(
+    mut items: Vec<FeedItem>,
+    reposts: Vec<FeedItem>,
+    limit: usize,
+) -> Vec<FeedItem> {
+    items.extend(reposts);
+    items.sort_by(|a, b| (&b.created_at, &b.cid).cmp(&(&a.created_at, &a.cid)));
+    items.truncate(limit);
+    items
+}
+
+/// Parses a `<timestamp_millis>::<cid>` cursor. A cursor whose timestamp does not parse is
+/// ignored rather than rejected.
 #[allow(deprecated)]
+fn parse_cursor(
+    params_cursor: Option<&str>,
+) -> Result<Option<FeedCursor>, ValidationErrorMessageResponse> {
+    let Some(cursor_str) = params_cursor else {
+        return Ok(None);
+    };
+    let v = cursor_str.split("::").take(2).collect::<Vec<_>>();
+    if let [created_at_c, cid_c] = &v[..] {
+        if let Ok(timestamp) = created_at_c.parse::<i64>() {
+            let nanoseconds = 230 * 1000000;
+            let datetime = DateTime::<Utc>::from_utc(
+                NaiveDateTime::from_timestamp(timestamp / 1000, nanoseconds),
+                Utc,
+            );
+            return Ok(Some(FeedCursor {
+                created_at: datetime,
+                cid: cid_c.to_string(),
+            }));
+        }
+        Ok(None)
+    } else {
+        let validation_error = ValidationErrorMessageResponse {
+            code: Some(ErrorCode::ValidationError),
+            message: Some("malformed cursor".into()),
+        };
+        Err(validation_error)
+    }
+}
+
 pub async fn get_posts_by_membership(
     lang: Option<String>,
     limit: Option<i64>,
     params_cursor: Option<&str>,
-    only_posts: bool,
+    threads: ThreadFilter,
+    include_reposts: bool,
     list: String,
     hashtags: Vec<String>,
     connection: ReadReplicaConn,
     config: &State<FeedGenConfig>,
 ) -> Result<AlgoResponse, ValidationErrorMessageResponse> {
-    use crate::schema::membership::dsl as MembershipSchema;
-    use crate::schema::post::dsl as PostSchema;
-    use diesel::dsl::any;
-
     let show_sponsored_post = config.show_sponsored_post.clone();
     let sponsored_post_uri = config.sponsored_post_uri.clone();
     let sponsored_post_probability = config.sponsored_post_probability.clone();
 
-    let params_cursor = match params_cursor {
-        None => None,
-        Some(params_cursor) => Some(params_cursor.to_string()),
-    };
+    let cursor_at = parse_cursor(params_cursor)?;
     let result = connection
         .run(move |conn| {
-            let mut query = PostSchema::post
-                .left_join(
-                    MembershipSchema::membership.on(PostSchema::author
-                        .eq(MembershipSchema::did)
-                        .and(MembershipSchema::list.eq(list.clone()))
-                        .and(MembershipSchema::included.eq(true))),
-                )
-                .limit(limit.unwrap_or(30))
-                .select(Post::as_select())
-                .order((PostSchema::createdAt.desc(), PostSchema::cid.desc()))
-                .into_boxed();
-
-            if let Some(lang) = lang {
-                query = query.filter(PostSchema::lang.like(format!("%{}%", lang)));
-            }
-
-            if let Some(cursor_str) = params_cursor {
-                let v = cursor_str
-                    .split("::")
-                    .take(2)
-                    .map(String::from)
-                    .collect::<Vec<_>>();
-                if let [created_at_c, cid_c] = &v[..] {
-                    if let Ok(timestamp) = created_at_c.parse::<i64>() {
-                        let nanoseconds = 230 * 1000000;
-                        let datetime = DateTime::<Utc>::from_utc(
-                            NaiveDateTime::from_timestamp(timestamp / 1000, nanoseconds),
-                            Utc,
-                        );
-                        query = query.filter(
-                            PostSchema::createdAt.lt(datetime).or(PostSchema::createdAt
-                                .eq(datetime)
-                                .and(PostSchema::cid.lt(cid_c.to_owned()))),
-                        );
-                    }
-                } else {
-                    let validation_error = ValidationErrorMessageResponse {
-                        code: Some(ErrorCode::ValidationError),
-                        message: Some("malformed cursor".into()),
-                    };
-                    return Err(validation_error);
-                }
-            }
-            if only_posts {
-                query = query
-                    .filter(PostSchema::replyParent.is_null())
-                    .filter(PostSchema::replyRoot.is_null());
-            }
+            let limit = limit.unwrap_or(30);
+            let query = PostQuery {
+                limit,
+                cursor: cursor_at.clone(),
+                lang,
+                threads,
+                membership: Some(MembershipFilter {
+                    list: list.clone(),
+                    hashtags,
+                }),
+                unlabeled: false,
+                without_media: false,
+            };
 
-            // Adjust the filtering logic
-            if hashtags.is_empty() {
-                // No hashtags provided, include only posts where author is in the list
-                query = query.filter(MembershipSchema::did.is_not_null());
-            } else {
-                let hashtag_patterns: Vec<String> = hashtags
-                    .iter()
-                    .map(|hashtag| format!("%#{}%", hashtag))
+            let results = conn.feed_posts(&query).expect("Error loading post records");
+            let items = results
+                .into_iter()
+                .map(|result| FeedItem {
+                    created_at: result.created_at,
+                    indexed_at: result.indexed_at,
+                    cid: result.cid,
+                    result: PostResult {
+                        post: result.uri,
+                        reason: None,
+                    },
+                })
+                .collect::<Vec<_>>();
+
+            let mut reposts = Vec::new();
+            if include_reposts {
+                reposts = conn
+                    .feed_reposts(&list, limit, cursor_at.as_ref())
+                    .expect("Error loading repost records")
+                    .into_iter()
+                    .map(|repost| FeedItem {
+                        created_at: repost.created_at,
+                        indexed_at: repost.indexed_at,
+                        cid: repost.cid,
+                        result: PostResult {
+                            post: repost.subject_uri,
+                            reason: Some(SkeletonReason::Repost { repost: repost.uri }),
+                        },
+                    })
                     .collect();
-                query = query.filter(
-                    MembershipSchema::did
-                        .is_not_null()
-                        .or(PostSchema::text.ilike(any(hashtag_patterns))),
-                );
             }
+            let items = merge_feed_items(items, reposts, limit as usize);
 
-            let results = query.load(conn).expect("Error loading post records");
-
-            let mut post_results = Vec::new();
             let mut cursor: Option<String> = None;
 
-            if let Some(last_post) = results.last() {
-                let timestamp_millis = last_post.indexed_at.timestamp_millis();
-                cursor = Some(format!("{}::{}", timestamp_millis, last_post.cid));
+            if let Some(last_item) = items.last() {
+                let timestamp_millis = last_item.indexed_at.timestamp_millis();
+                cursor = Some(format!("{}::{}", timestamp_millis, last_item.cid));
             }
 
-            results
+            let mut post_results = items
                 .into_iter()
-                .map(|result| {
-                    let post_result = PostResult { post: result.uri };
-                    post_results.push(post_result);
-                })
-                .for_each(drop);
+                .map(|item| item.result)
+                .collect::<Vec<_>>();
 
             // Insert the sponsored post if the conditions are met
             if show_sponsored_post && post_results.len() >= 3 && !sponsored_post_uri.is_empty() {
@@ -143,6 +169,7 @@ pub async fn get_posts_by_membership(
                     // Replace a random post with the sponsored post
                     post_results[replace_index] = PostResult {
                         post: sponsored_post_uri.clone(),
+                        reason: None,
                     };
                 }
             }
@@ -158,13 +185,6 @@ pub async fn get_posts_by_membership(
     result
 }
 
-#[derive(Clone)]
-pub enum TrendingMedia {
-    OnlyVideo,
-    OnlyImage,
-}
-
-#[allow(deprecated)]
 pub async fn get_blacksky_trending(
     limit: Option<i64>,
     params_cursor: Option<&str>,
@@ -174,111 +194,22 @@ pub async fn get_blacksky_trending(
 ) -> Result<AlgoResponse, ValidationErrorMessageResponse> {
     // Get the minimum trending percentile from config (e.g. 0.95)
     let trending_percentile_min = config.trending_percentile_min;
-    let mut base_time_clause = "CURRENT_TIMESTAMP".to_string();
-    let mut cursor_filter = String::new();
-
-    // If a cursor is provided, extract its timestamp and cid parts.
-    // We assume the cursor format is "<timestamp_millis>::<cid>".
-    let params_cursor = params_cursor.map(|s| s.to_string());
-    if let Some(ref cursor_str) = params_cursor {
-        let parts: Vec<&str> = cursor_str.split("::").take(2).collect();
-        if parts.len() == 2 {
-            let timestamp_str = parts[0];
-            let cid_cursor = parts[1];
-            if let Ok(timestamp) = timestamp_str.parse::<i64>() {
-                // Convert milliseconds to a proper timestamp.
-                let nanoseconds = 230 * 1_000_000;
-                let datetime = DateTime::<Utc>::from_utc(
-                    NaiveDateTime::from_timestamp(timestamp / 1000, nanoseconds),
-                    Utc,
-                );
-                // Format the timestamp in a SQL-friendly format.
-                let timestr = datetime.format("%Y-%m-%d %H:%M:%S%.3f").to_string();
-                base_time_clause = format!("'{}'", timestr);
-                // Build the cursor filter clause.
-                cursor_filter = format!(
-                    " AND ((\"indexedAt\" < {base_time_clause}) OR (\"indexedAt\" = {base_time_clause} AND cid < '{cid}'))",
-                    base_time_clause = base_time_clause,
-                    cid = cid_cursor
-                );
-            }
-        } else {
-            return Err(ValidationErrorMessageResponse {
-                code: Some(ErrorCode::ValidationError),
-                message: Some("malformed cursor".into()),
-            });
-        }
-    }
+    let cursor = parse_cursor(params_cursor)?;
 
     // Compute a random percentile threshold between trending_percentile_min and 1.0.
     let random_percentile: f64 = rand::thread_rng().gen_range(trending_percentile_min..=1.0);
 
-    // Build the media join clause.
-    let media_join = match media {
-        Some(TrendingMedia::OnlyImage) => "JOIN image i ON p.uri = i.\"postUri\"",
-        Some(TrendingMedia::OnlyVideo) => "JOIN video v ON p.uri = v.\"postUri\"",
-        None => "",
+    let query = TrendingQuery {
+        limit: limit.unwrap_or(30),
+        cursor,
+        percentile: random_percentile,
+        media,
     };
 
-    // Construct the final SQL query string.
-    // We add an extra CTE "filtered_posts" that discards rows where like_count is <= 1.
-    let query_str = format!(
-        "WITH recent_posts AS (
-            SELECT p.*
-            FROM post p
-            {media_join}
-            WHERE p.\"indexedAt\" >= ({base_time_clause}::timestamp - INTERVAL '1 days')
-              AND COALESCE(array_length(p.labels, 1), 0) = 0
-        ), recent_likes AS (
-            SELECT \"subjectUri\", COUNT(*) AS like_count
-            FROM public.like
-            WHERE \"indexedAt\" >= ({base_time_clause}::timestamp - INTERVAL '12 hours')
-            GROUP BY \"subjectUri\"
-            HAVING COUNT(*) > 1
-        ), posts_with_likes AS (
-            SELECT p.*, COALESCE(l.like_count, 0) AS like_count
-            FROM recent_posts p
-            JOIN recent_likes l ON l.\"subjectUri\" = p.uri
-        ), ranked_posts AS (
-            SELECT *,
-                   PERCENT_RANK() OVER (ORDER BY like_count) AS percentile_rank
-            FROM posts_with_likes
-        )
-        SELECT
-            uri,
-            cid,
-            \"replyParent\",
-            \"replyRoot\",
-            \"indexedAt\",
-            prev,
-            \"sequence\",
-            text,
-            lang,
-            author,
-            \"externalUri\",
-            \"externalTitle\",
-            \"externalDescription\",
-            \"externalThumb\",
-            \"quoteCid\",
-            \"quoteUri\",
-            \"createdAt\",
-            labels
-        FROM ranked_posts
-        WHERE percentile_rank >= {random_percentile:.4}
-          {cursor_filter}
-        ORDER BY \"indexedAt\" DESC, cid DESC
-        LIMIT {limit_val};",
-        media_join = media_join,
-        base_time_clause = base_time_clause,
-        random_percentile = random_percentile,
-        cursor_filter = cursor_filter,
-        limit_val = limit.unwrap_or(30)
-    );
-
     let result = connection
         .run(move |conn| {
-            let results = sql_query(query_str)
-                .load::<Post>(conn)
+            let results = conn
+                .trending_posts(&query)
                 .expect("Error loading post records");
 
             let mut post_results = Vec::new();
@@ -290,7 +221,10 @@ pub async fn get_blacksky_trending(
             }
 
             for post in results {
-                post_results.push(PostResult { post: post.uri });
+                post_results.push(PostResult {
+                    post: post.uri,
+                    reason: None,
+                });
             }
 
             Ok(AlgoResponse {
@@ -353,7 +287,6 @@ pub async fn get_blacksky_trending_cached(
     Ok(result)
 }
 
-#[allow(deprecated)]
 pub async fn get_all_posts(
     lang: Option<String>,
     limit: Option<i64>,
@@ -362,7 +295,6 @@ pub async fn get_all_posts(
     connection: ReadReplicaConn,
     config: &State<FeedGenConfig>,
 ) -> Result<AlgoResponse, ValidationErrorMessageResponse> {
-    use crate::schema::post::dsl as PostSchema;
     use chrono::Timelike;
     use chrono_tz::America::New_York;
 
@@ -372,78 +304,38 @@ pub async fn get_all_posts(
     let blackout_enabled = env_bool("FEEDGEN_MEDIA_BLACKOUT_ENABLED").unwrap_or(false);
     let blackout_start_hour = env_int("FEEDGEN_MEDIA_BLACKOUT_START").unwrap_or(22) as u32;
     let blackout_end_hour = env_int("FEEDGEN_MEDIA_BLACKOUT_END").unwrap_or(4) as u32;
-    let params_cursor = params_cursor.map(|s| s.to_string());
-
-    let result = connection
-        .run(move |conn| {
-            let mut query = PostSchema::post
-                .limit(limit.unwrap_or(30))
-                .select(Post::as_select())
-                .order((PostSchema::createdAt.desc(), PostSchema::cid.desc()))
-                .filter(sql::<Bool>("COALESCE(array_length(labels, 1), 0) = 0"))
-                .into_boxed();
-
-            // Apply the media exclusion filters only if blackout is enabled, and we're within the blackout period.
-            if blackout_enabled {
-                let now_et = Utc::now().with_timezone(&New_York);
-                let now_hour = now_et.hour();
-
-                let in_blackout = if blackout_start_hour < blackout_end_hour {
-                    now_hour >= blackout_start_hour && now_hour < blackout_end_hour
-                } else {
-                    // For periods spanning midnight, e.g. 22:00 to 04:00.
-                    now_hour >= blackout_start_hour || now_hour < blackout_end_hour
-                };
 
-                if in_blackout {
-                    query = query
-                        .filter(sql::<Bool>(
-                            "NOT EXISTS (SELECT 1 FROM image WHERE image.\"postUri\" = post.uri)",
-                        ))
-                        .filter(sql::<Bool>(
-                            "NOT EXISTS (SELECT 1 FROM video WHERE video.\"postUri\" = post.uri)",
-                        ));
-                }
-            }
+    // Apply the media exclusion filters only if blackout is enabled, and we're within the blackout period.
+    let mut in_blackout = false;
+    if blackout_enabled {
+        let now_et = Utc::now().with_timezone(&New_York);
+        let now_hour = now_et.hour();
 
-            if let Some(lang) = lang {
-                query = query.filter(PostSchema::lang.like(format!("%{}%", lang)));
-            }
+        in_blackout = if blackout_start_hour < blackout_end_hour {
+            now_hour >= blackout_start_hour && now_hour < blackout_end_hour
+        } else {
+            // For periods spanning midnight, e.g. 22:00 to 04:00.
+            now_hour >= blackout_start_hour || now_hour < blackout_end_hour
+        };
+    }
 
-            if let Some(cursor_str) = params_cursor {
-                let v = cursor_str
-                    .split("::")
-                    .take(2)
-                    .map(String::from)
-                    .collect::<Vec<_>>();
-                if let [created_at_c, cid_c] = &v[..] {
-                    if let Ok(timestamp) = created_at_c.parse::<i64>() {
-                        let nanoseconds = 230 * 1000000;
-                        let datetime = DateTime::<Utc>::from_utc(
-                            NaiveDateTime::from_timestamp(timestamp / 1000, nanoseconds),
-                            Utc,
-                        );
-                        query = query.filter(
-                            PostSchema::createdAt.lt(datetime).or(PostSchema::createdAt
-                                .eq(datetime)
-                                .and(PostSchema::cid.lt(cid_c.to_owned()))),
-                        );
-                    }
-                } else {
-                    let validation_error = ValidationErrorMessageResponse {
-                        code: Some(ErrorCode::ValidationError),
-                        message: Some("malformed cursor".into()),
-                    };
-                    return Err(validation_error);
-                }
-            }
+    let query = PostQuery {
+        limit: limit.unwrap_or(30),
+        cursor: parse_cursor(params_cursor)?,
+        lang,
+        threads: if only_posts {
+            ThreadFilter::TopLevel
+        } else {
+            ThreadFilter::All
+        },
+        membership: None,
+        unlabeled: true,
+        without_media: in_blackout,
+    };
 
-            if only_posts {
-                query = query
-                    .filter(PostSchema::replyParent.is_null())
-                    .filter(PostSchema::replyRoot.is_null());
-            }
-            let results = query.load(conn).expect("Error loading post records");
+    let result = connection
+        .run(move |conn| {
+            let results = conn.feed_posts(&query).expect("Error loading post records");
 
             let mut post_results = Vec::new();
             let mut cursor: Option<String> = None;
@@ -455,7 +347,10 @@ pub async fn get_all_posts(
             }
 
             for result in results {
-                post_results.push(PostResult { post: result.uri });
+                post_results.push(PostResult {
+                    post: result.uri,
+                    reason: None,
+                });
             }
 
             // Insert the sponsored post if the conditions are met
@@ -472,6 +367,7 @@ pub async fn get_all_posts(
                     // Replace a random post with the sponsored post
                     post_results[replace_index] = PostResult {
                         post: sponsored_post_uri.clone(),
+                        reason: None,
                     };
                 }
             }
@@ -487,39 +383,47 @@ pub async fn get_all_posts(
     result
 }
 
-pub fn is_included(
-    dids: Vec<&String>,
-    conn: &mut PgConnection,
-) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    use crate::schema::membership::dsl::*;
+/// Whether `did` may read a private feed open to members of `list`.
+pub async fn is_feed_member(
+    did: String,
+    list: String,
+    connection: &ReadReplicaConn,
+) -> StorageResult<bool> {
+    connection
+        .run(move |conn| {
+            let result = conn.memberships(&[did])?;
+            Ok(result
+                .iter()
+                .any(|m| m.list == list && m.included && !m.excluded))
+        })
+        .await
+}
 
-    let result = membership
-        .filter(did.eq_any(dids))
-        .filter(included.eq(true))
-        .select(Membership::as_select())
-        .load(conn)?;
+pub fn is_included(dids: Vec<&String>, conn: &mut dyn FeedStorage) -> StorageResult<Vec<String>> {
+    let dids = dids.into_iter().cloned().collect::<Vec<_>>();
+    let result = conn.memberships(&dids)?;
 
-    Ok(result.into_iter().map(|m| m.list).collect::<Vec<String>>())
+    Ok(result
+        .into_iter()
+        .filter(|m| m.included)
+        .map(|m| m.list)
+        .collect::<Vec<String>>())
 }
 
-pub fn is_excluded(
-    dids: Vec<&String>,
-    conn: &mut PgConnection,
-) -> Result<bool, Box<dyn std::error::Error>> {
-    use crate::schema::membership::dsl::*;
-
-    let result = membership
-        .filter(did.eq_any(dids))
-        .filter(excluded.eq(true))
-        .limit(1)
-        .select(Membership::as_select())
-        .load(conn)?;
-
-    if result.len() > 0 {
-        Ok(result[0].excluded)
-    } else {
-        Ok(false)
-    }
+pub fn is_excluded(dids: Vec<&String>, conn: &mut dyn FeedStorage) -> StorageResult<bool> {
+    let dids = dids.into_iter().cloned().collect::<Vec<_>>();
+    let result = conn.memberships(&dids)?;
+
+    Ok(result.iter().any(|m| m.excluded))
+}
+
+/// The DID that authored the record at `uri`.
+fn uri_author(uri: &str) -> Option<String> {
+    uri.strip_prefix("at://")?
+        .split('/')
+        .next()
+        .filter(|did| did.starts_with("did:"))
+        .map(String::from)
 }
 
 fn extract_hashtags(input: &str) -> HashSet<&str> {
@@ -530,18 +434,33 @@ fn extract_hashtags(input: &str) -> HashSet<&str> {
     RE.find_iter(input).map(|mat| mat.as_str()).collect()
 }
 
+/// An image or video blob embedded in `post`.
+fn post_media(cid: String, alt: Option<String>, post: &Post) -> Media {
+    Media {
+        cid,
+        alt,
+        post_cid: post.cid.clone(),
+        post_uri: post.uri.clone(),
+        created_at: post.created_at,
+        indexed_at: post.indexed_at,
+        labels: vec![],
+    }
+}
+
+fn new_member(did: &str, list: &str) -> Membership {
+    Membership {
+        did: did.to_string(),
+        included: true,
+        excluded: false,
+        list: list.to_string(),
+    }
+}
+
 pub async fn queue_creation(
     lex: String,
     body: Vec<CreateRequest>,
-    connection: WriteDbConn,
+    connection: WriteConn,
 ) -> Result<(), String> {
-    use crate::schema::follow::dsl as FollowSchema;
-    use crate::schema::image::dsl as ImageSchema;
-    use crate::schema::like::dsl as LikeSchema;
-    use crate::schema::membership::dsl as MembershipSchema;
-    use crate::schema::post::dsl as PostSchema;
-    use crate::schema::video::dsl as VideoSchema;
-
     let result = connection.run( move |conn| {
         if lex == "posts" {
             let mut new_posts = Vec::new();
@@ -552,12 +471,12 @@ pub async fn queue_creation(
 
             body
                 .into_iter()
-                .map(|req| {
+                .map(|req| -> Result<(), String> {
                     let system_time = SystemTime::now();
                     let dt: DateTime<UtcOffset> = system_time.into();
                     // let mut root_author = String::new();
-                    let member_of = is_included(vec![&req.author].into(), conn).unwrap_or_else(|_| vec![]);
-                    let is_blocked = is_excluded(vec![&req.author].into(), conn).unwrap_or(false);
+                    let member_of = is_included(vec![&req.author].into(), conn).map_err(|error| error.to_string())?;
+                    let is_blocked = is_excluded(vec![&req.author].into(), conn).map_err(|error| error.to_string())?;
                     let mut post_text = String::new();
                     let mut post_text_original = String::new();
                     let mut post_images = Vec::new();
@@ -580,7 +499,8 @@ pub async fn queue_creation(
                         quote_cid: None,
                         quote_uri: None,
                         created_at: dt, // use now() as a default
-                        labels: vec![]
+                        labels: vec![],
+                        reply_root_author: None
                     };
 
                     if let CreateRecord::Lexicon(AppBskyFeedPost(post_record)) = req.record {
@@ -593,7 +513,7 @@ pub async fn queue_creation(
                             new_post.created_at = new_post.indexed_at.clone();
                         }
                         if let Some(reply) = post_record.reply {
-                            //root_author = reply.root.uri[5..37].into();
+                            new_post.reply_root_author = uri_author(&reply.root.uri);
                             new_post.reply_parent = Some(reply.parent.uri);
                             new_post.reply_root = Some(reply.root.uri);
                         }
@@ -607,62 +527,24 @@ pub async fn queue_creation(
                             match embed {
                                 Embeds::Images(e) => {
                                     for image in e.images {
-                                        let labels: Vec<Option<String>> = vec![];
                                         match (&image.image.cid, image.image.r#ref) {
                                             (Some(image_cid), _) => {
-                                                let new_image = (
-                                                    ImageSchema::cid.eq(image_cid.clone()),
-                                                    ImageSchema::alt.eq(image.alt),
-                                                    ImageSchema::postCid.eq(new_post.cid.clone()),
-                                                    ImageSchema::postUri.eq(new_post.uri.clone()),
-                                                    ImageSchema::indexedAt.eq(new_post.indexed_at.clone()),
-                                                    ImageSchema::createdAt.eq(new_post.created_at.clone()),
-                                                    ImageSchema::labels.eq(labels),
-                                                );
-                                                post_images.push(new_image);
+                                                post_images.push(post_media(image_cid.clone(), Some(image.alt), &new_post));
                                             },
                                             (_, Some(image_ref)) => {
-                                                let new_image = (
-                                                    ImageSchema::cid.eq(image_ref.to_string()),
-                                                    ImageSchema::alt.eq(image.alt),
-                                                    ImageSchema::postCid.eq(new_post.cid.clone()),
-                                                    ImageSchema::postUri.eq(new_post.uri.clone()),
-                                                    ImageSchema::indexedAt.eq(new_post.indexed_at.clone()),
-                                                    ImageSchema::createdAt.eq(new_post.created_at.clone()),
-                                                    ImageSchema::labels.eq(labels),
-                                                );
-                                                post_images.push(new_image);
+                                                post_images.push(post_media(image_ref.to_string(), Some(image.alt), &new_post));
                                             },
                                             _ => eprintln!("Unknown image type: {image:?}")
                                         }
                                     }
                                 },
                                 Embeds::Video(ref e) => {
-                                    let labels: Vec<Option<String>> = vec![];
                                     match (&e.video.cid, e.video.r#ref) {
                                         (Some(video_cid), _) => {
-                                            let new_video = (
-                                                VideoSchema::cid.eq(video_cid.clone()),
-                                                VideoSchema::alt.eq(e.alt.clone()),
-                                                VideoSchema::postCid.eq(new_post.cid.clone()),
-                                                VideoSchema::postUri.eq(new_post.uri.clone()),
-                                                VideoSchema::indexedAt.eq(new_post.indexed_at.clone()),
-                                                VideoSchema::createdAt.eq(new_post.created_at.clone()),
-                                                VideoSchema::labels.eq(labels),
-                                            );
-                                            post_videos.push(new_video);
+                                            post_videos.push(post_media(video_cid.clone(), e.alt.clone(), &new_post));
                                         },
                                         (_, Some(video_ref)) => {
-                                            let new_video = (
-                                                VideoSchema::cid.eq(video_ref.to_string()),
-                                                VideoSchema::alt.eq(e.alt.clone()),
-                                                VideoSchema::postCid.eq(new_post.cid.clone()),
-                                                VideoSchema::postUri.eq(new_post.uri.clone()),
-                                                VideoSchema::indexedAt.eq(new_post.indexed_at.clone()),
-                                                VideoSchema::createdAt.eq(new_post.created_at.clone()),
-                                                VideoSchema::labels.eq(labels),
-                                            );
-                                            post_videos.push(new_video);
+                                            post_videos.push(post_media(video_ref.to_string(), e.alt.clone(), &new_post));
                                         },
                                         _ => eprintln!("Unknown video type: {e:?}")
                                     };
@@ -673,62 +555,24 @@ pub async fn queue_creation(
                                     match e.media {
                                         MediaUnion::Images(m) => {
                                             for image in m.images {
-                                                let labels: Vec<Option<String>> = vec![];
                                                 match (&image.image.cid, image.image.r#ref) {
                                                     (Some(image_cid), _) => {
-                                                        let new_image = (
-                                                            ImageSchema::cid.eq(image_cid.clone()),
-                                                            ImageSchema::alt.eq(image.alt),
-                                                            ImageSchema::postCid.eq(new_post.cid.clone()),
-                                                            ImageSchema::postUri.eq(new_post.uri.clone()),
-                                                            ImageSchema::indexedAt.eq(new_post.indexed_at.clone()),
-                                                            ImageSchema::createdAt.eq(new_post.created_at.clone()),
-                                                            ImageSchema::labels.eq(labels),
-                                                        );
-                                                        post_images.push(new_image);
+                                                        post_images.push(post_media(image_cid.clone(), Some(image.alt), &new_post));
                                                     },
                                                     (_, Some(image_ref)) => {
-                                                        let new_image = (
-                                                            ImageSchema::cid.eq(image_ref.to_string()),
-                                                            ImageSchema::alt.eq(image.alt),
-                                                            ImageSchema::postCid.eq(new_post.cid.clone()),
-                                                            ImageSchema::postUri.eq(new_post.uri.clone()),
-                                                            ImageSchema::indexedAt.eq(new_post.indexed_at.clone()),
-                                                            ImageSchema::createdAt.eq(new_post.created_at.clone()),
-                                                            ImageSchema::labels.eq(labels),
-                                                        );
-                                                        post_images.push(new_image);
+                                                        post_images.push(post_media(image_ref.to_string(), Some(image.alt), &new_post));
                                                     },
                                                     _ => eprintln!("Unknown image type: {image:?}")
                                                 }
                                             }
                                         },
                                         MediaUnion::Video(ref v) => {
-                                            let labels: Vec<Option<String>> = vec![];
                                             match (&v.video.cid, v.video.r#ref) {
                                                 (Some(video_cid), _) => {
-                                                    let new_video = (
-                                                        VideoSchema::cid.eq(video_cid.clone()),
-                                                        VideoSchema::alt.eq(v.alt.clone()),
-                                                        VideoSchema::postCid.eq(new_post.cid.clone()),
-                                                        VideoSchema::postUri.eq(new_post.uri.clone()),
-                                                        VideoSchema::indexedAt.eq(new_post.indexed_at.clone()),
-                                                        VideoSchema::createdAt.eq(new_post.created_at.clone()),
-                                                        VideoSchema::labels.eq(labels),
-                                                    );
-                                                    post_videos.push(new_video);
+                                                    post_videos.push(post_media(video_cid.clone(), v.alt.clone(), &new_post));
                                                 },
                                                 (_, Some(video_ref)) => {
-                                                    let new_video = (
-                                                        VideoSchema::cid.eq(video_ref.to_string()),
-                                                        VideoSchema::alt.eq(v.alt.clone()),
-                                                        VideoSchema::postCid.eq(new_post.cid.clone()),
-                                                        VideoSchema::postUri.eq(new_post.uri.clone()),
-                                                        VideoSchema::indexedAt.eq(new_post.indexed_at.clone()),
-                                                        VideoSchema::createdAt.eq(new_post.created_at.clone()),
-                                                        VideoSchema::labels.eq(labels),
-                                                    );
-                                                    post_videos.push(new_video);
+                                                    post_videos.push(post_media(video_ref.to_string(), v.alt.clone(), &new_post));
                                                 },
                                                 _ => eprintln!("Unknown video type: {v:?}")
                                             };
@@ -743,6 +587,7 @@ pub async fn queue_creation(
                                                 };
                                             };
                                         },
+                                        MediaUnion::Unknown(_) => (),
                                     }
                                 },
                                 Embeds::External(e) => {
@@ -759,6 +604,7 @@ pub async fn queue_creation(
                                     new_post.quote_cid = Some(e.record.cid);
                                     new_post.quote_uri = Some(e.record.uri);
                                 },
+                                Embeds::Unknown(_) => (),
                             }
                         }
                     }
@@ -790,82 +636,32 @@ pub async fn queue_creation(
                         let seq_ = &new_post.sequence;
                         println!("Sequence: {seq_:?} | Uri: {uri_:?} | Member: {member_of:?} | Hashtags: {hashtags:?}");
 
-                        let new_post = (
-                            PostSchema::uri.eq(new_post.uri),
-                            PostSchema::cid.eq(new_post.cid),
-                            PostSchema::replyParent.eq(new_post.reply_parent),
-                            PostSchema::replyRoot.eq(new_post.reply_root),
-                            PostSchema::indexedAt.eq(new_post.indexed_at),
-                            PostSchema::prev.eq(new_post.prev),
-                            PostSchema::sequence.eq(new_post.sequence),
-                            PostSchema::text.eq(new_post.text),
-                            PostSchema::lang.eq(new_post.lang),
-                            PostSchema::author.eq(new_post.author),
-                            PostSchema::externalUri.eq(new_post.external_uri),
-                            PostSchema::externalTitle.eq(new_post.external_title),
-                            PostSchema::externalDescription.eq(new_post.external_description),
-                            PostSchema::externalThumb.eq(new_post.external_thumb),
-                            PostSchema::quoteCid.eq(new_post.quote_cid),
-                            PostSchema::quoteUri.eq(new_post.quote_uri),
-                            PostSchema::createdAt.eq(new_post.created_at),
-                            PostSchema::labels.eq(new_post.labels),
-                        );
                         new_posts.push(new_post);
                         new_images.extend(post_images);
                         new_videos.extend(post_videos);
 
                         if hashtags.contains("#addtoblacksky") && !member_of.contains(&"blacksky".to_string()) {
                             println!("New Blacksky member: {:?}", &req.author);
-                            let new_member = (
-                                MembershipSchema::did.eq(req.author.clone()),
-                                MembershipSchema::included.eq(true),
-                                MembershipSchema::excluded.eq(false),
-                                MembershipSchema::list.eq("blacksky")
-                            );
-                            new_members.push(new_member);
+                            new_members.push(new_member(&req.author, "blacksky"));
                         }
                         if hashtags.contains("#addtoblackskytravel") && !member_of.contains(&"blacksky-travel".to_string()) {
                             println!("New BlackskyTravel member: {:?}", &req.author);
-                            let new_member = (
-                                MembershipSchema::did.eq(req.author.clone()),
-                                MembershipSchema::included.eq(true),
-                                MembershipSchema::excluded.eq(false),
-                                MembershipSchema::list.eq("blacksky-travel")
-                            );
-                            new_members.push(new_member);
+                            new_members.push(new_member(&req.author, "blacksky-travel"));
                         }
                         if hashtags.contains("#addtoblackmedsky") && !member_of.contains(&"blacksky-med".to_string()) {
                             println!("New BlackMedSky member: {:?}", &req.author);
-                            let new_member = (
-                                MembershipSchema::did.eq(req.author.clone()),
-                                MembershipSchema::included.eq(true),
-                                MembershipSchema::excluded.eq(false),
-                                MembershipSchema::list.eq("blacksky-med")
-                            );
-                            new_members.push(new_member);
+                            new_members.push(new_member(&req.author, "blacksky-med"));
                         }
                         if hashtags.contains("#addtoblackedusky") && !member_of.contains(&"blacksky-scholastic".to_string()) {
                             println!("New BlackEduSky member: {:?}", &req.author);
-                            let new_member = (
-                                MembershipSchema::did.eq(req.author.clone()),
-                                MembershipSchema::included.eq(true),
-                                MembershipSchema::excluded.eq(false),
-                                MembershipSchema::list.eq("blacksky-scholastic")
-                            );
-                            new_members.push(new_member);
+                            new_members.push(new_member(&req.author, "blacksky-scholastic"));
                         }
                         /* TEMP REMOVING THIS FEATURE AS IT'S CREATING SPAM
                         if hashtags.contains("#addtoblacksky") &&
                             !member_of.is_empty() &&
                             !root_author.is_empty() {
                             println!("New member: {:?}", &root_author);
-                            let new_member = (
-                                MembershipSchema::did.eq(root_author),
-                                MembershipSchema::included.eq(true),
-                                MembershipSchema::excluded.eq(false),
-                                MembershipSchema::list.eq("blacksky")
-                            );
-                            new_members.push(new_member);
+                            new_members.push(new_member(&root_author, "blacksky"));
                         }*/
                     }
                     if !member_of.is_empty() &&
@@ -873,45 +669,28 @@ pub async fn queue_creation(
                         println!("Removing member: {:?}", &req.author);
                         members_to_rm.push(req.author.clone());
                     }
+                    Ok(())
                 })
-                .for_each(drop);
+                .collect::<Result<(), String>>()?;
 
             if !new_posts.is_empty() {
-                diesel::insert_into(PostSchema::post)
-                    .values(&new_posts)
-                    .on_conflict(PostSchema::uri)
-                    .do_nothing()
-                    .execute(conn)
+                conn.insert_posts(&new_posts)
                     .expect("Error inserting post records");
             }
             if !new_images.is_empty() {
-                diesel::insert_into(ImageSchema::image)
-                    .values(&new_images)
-                    .on_conflict(ImageSchema::cid)
-                    .do_nothing()
-                    .execute(conn)
+                conn.insert_media(MediaKind::Image, &new_images)
                     .expect("Error inserting image records");
             }
             if !new_videos.is_empty() {
-                diesel::insert_into(VideoSchema::video)
-                    .values(&new_videos)
-                    .on_conflict(VideoSchema::cid)
-                    .do_nothing()
-                    .execute(conn)
+                conn.insert_media(MediaKind::Video, &new_videos)
                     .expect("Error inserting video records");
             }
             if !new_members.is_empty() {
-                diesel::insert_into(MembershipSchema::membership)
-                    .values(&new_members)
-                    .on_conflict((MembershipSchema::did,MembershipSchema::list))
-                    .do_nothing()
-                    .execute(conn)
+                conn.add_members(&new_members)
                     .expect("Error inserting member records");
             }
             if !members_to_rm.is_empty() {
-                diesel::delete(MembershipSchema::membership
-                    .filter(MembershipSchema::did.eq_any(&members_to_rm)))
-                    .execute(conn)
+                conn.remove_members(&members_to_rm)
                     .expect("Error deleting member records");
             }
             Ok(())
@@ -920,70 +699,95 @@ pub async fn queue_creation(
 
             body
                 .into_iter()
-                .map(|req| {
+                .map(|req| -> Result<(), String> {
                     if let CreateRecord::Lexicon(AppBskyFeedLike(like_record)) = req.record {
                         let subject_author: &String = &like_record.subject.uri[5..37].into(); // parse DID:PLC from URI
-                        let member_of = is_included(vec![&req.author, subject_author].into(), conn).unwrap_or_else(|_| vec![]);
+                        let member_of = is_included(vec![&req.author, subject_author].into(), conn).map_err(|error| error.to_string())?;
                         if !member_of.is_empty() {
                             let system_time = SystemTime::now();
                             let dt: DateTime<UtcOffset> = system_time.into();
-                            let new_like = (
-                                LikeSchema::uri.eq(req.uri),
-                                LikeSchema::cid.eq(req.cid),
-                                LikeSchema::author.eq(req.author),
-                                LikeSchema::subjectCid.eq(like_record.subject.cid),
-                                LikeSchema::subjectUri.eq(like_record.subject.uri),
-                                LikeSchema::createdAt.eq(like_record.created_at),
-                                LikeSchema::indexedAt.eq(dt),
-                                LikeSchema::prev.eq(req.prev),
-                                LikeSchema::sequence.eq(req.sequence)
-                            );
-                            new_likes.push(new_like);
+                            new_likes.push(Like {
+                                uri: req.uri,
+                                cid: req.cid,
+                                author: req.author,
+                                subject_cid: like_record.subject.cid,
+                                subject_uri: like_record.subject.uri,
+                                created_at: like_record.created_at,
+                                indexed_at: dt,
+                                prev: req.prev,
+                                sequence: req.sequence
+                            });
                         }
                     }
+                    Ok(())
                 })
-                .for_each(drop);
+                .collect::<Result<(), String>>()?;
             if !new_likes.is_empty() {
-                diesel::insert_into(LikeSchema::like)
-                    .values(&new_likes)
-                    .on_conflict(LikeSchema::uri)
-                    .do_nothing()
-                    .execute(conn)
+                conn.insert_likes(&new_likes)
                     .expect("Error inserting like records");
             }
             Ok(())
+        } else if lex == "reposts" {
+            let mut new_reposts = Vec::new();
+
+            body
+                .into_iter()
+                .map(|req| -> Result<(), String> {
+                    if let CreateRecord::Lexicon(AppBskyFeedRepost(repost_record)) = req.record {
+                        // only member reposts can surface in a feed
+                        let member_of = is_included(vec![&req.author].into(), conn).map_err(|error| error.to_string())?;
+                        if !member_of.is_empty() {
+                            let system_time = SystemTime::now();
+                            let dt: DateTime<UtcOffset> = system_time.into();
+                            new_reposts.push(Repost {
+                                uri: req.uri,
+                                cid: req.cid,
+                                author: req.author,
+                                subject_cid: repost_record.subject.cid,
+                                subject_uri: repost_record.subject.uri,
+                                created_at: repost_record.created_at.min(dt),
+                                indexed_at: dt,
+                                prev: req.prev,
+                                sequence: req.sequence
+                            });
+                        }
+                    }
+                    Ok(())
+                })
+                .collect::<Result<(), String>>()?;
+            if !new_reposts.is_empty() {
+                conn.insert_reposts(&new_reposts)
+                    .expect("Error inserting repost records");
+            }
+            Ok(())
         } else if lex == "follows" {
             let mut new_follows = Vec::new();
 
             body
                 .into_iter()
-                .map(|req| {
+                .map(|req| -> Result<(), String> {
                     if let CreateRecord::Lexicon(AppBskyFeedFollow(follow_record)) = req.record {
-                        let member_of = is_included(vec![&req.author, &follow_record.subject].into(), conn).unwrap_or_else(|_| vec![]);
+                        let member_of = is_included(vec![&req.author, &follow_record.subject].into(), conn).map_err(|error| error.to_string())?;
                         if !member_of.is_empty() {
                             let system_time = SystemTime::now();
                             let dt: DateTime<UtcOffset> = system_time.into();
-                            let new_follow = (
-                                FollowSchema::uri.eq(req.uri),
-                                FollowSchema::cid.eq(req.cid),
-                                FollowSchema::author.eq(req.author),
-                                FollowSchema::subject.eq(follow_record.subject),
-                                FollowSchema::createdAt.eq(follow_record.created_at),
-                                FollowSchema::indexedAt.eq(format!("{}", dt.format("%+"))),
-                                FollowSchema::prev.eq(req.prev),
-                                FollowSchema::sequence.eq(req.sequence)
-                            );
-                            new_follows.push(new_follow);
+                            new_follows.push(Follow {
+                                uri: req.uri,
+                                cid: req.cid,
+                                author: req.author,
+                                subject: follow_record.subject,
+                                created_at: follow_record.created_at,
+                                indexed_at: format!("{}", dt.format("%+")),
+                                prev: req.prev,
+                                sequence: req.sequence
+                            });
                         }
                     }
+                    Ok(())
                 })
-                .for_each(drop);
+                .collect::<Result<(), String>>()?;
             if !new_follows.is_empty() {
-                diesel::insert_into(FollowSchema::follow)
-                    .values(&new_follows)
-                    .on_conflict(FollowSchema::uri)
-                    .do_nothing()
-                    .execute(conn)
+                conn.insert_follows(&new_follows)
                     .expect("Error inserting like records");
             }
             Ok(())
@@ -996,13 +800,8 @@ pub async fn queue_creation(
                         if req.uri.starts_with("did:plc:") {
                             ()
                         } else {
-                            // Raw SQL query
-                            let updated_rows = sql_query(
-                                "UPDATE post SET labels = labels || $1 WHERE uri = $2"
-                            )
-                                .bind::<Array<Nullable<Text>>, _>(vec![Some(&label.val)])
-                                .bind::<Text, _>(&req.uri)
-                                .execute(conn)
+                            let updated_rows = conn
+                                .add_post_label(&req.uri, &label.val)
                                 .expect("Error updating labels");
                             if updated_rows > 0 {
                                 println!("@LOG: applied {} to {}", label.val, req.uri);
@@ -1022,12 +821,8 @@ pub async fn queue_creation(
 pub async fn queue_deletion(
     lex: String,
     body: Vec<DeleteRequest>,
-    connection: WriteDbConn,
+    connection: WriteConn,
 ) -> Result<(), String> {
-    use crate::schema::follow::dsl as FollowSchema;
-    use crate::schema::like::dsl as LikeSchema;
-    use crate::schema::post::dsl as PostSchema;
-
     let result = connection
         .run(move |conn| {
             let mut delete_rows = Vec::new();
@@ -1037,16 +832,16 @@ pub async fn queue_deletion(
                 })
                 .for_each(drop);
             if lex == "posts" {
-                diesel::delete(PostSchema::post.filter(PostSchema::uri.eq_any(delete_rows)))
-                    .execute(conn)
+                conn.delete_records(Collection::Posts, &delete_rows)
                     .expect("Error deleting post records");
             } else if lex == "likes" {
-                diesel::delete(LikeSchema::like.filter(LikeSchema::uri.eq_any(delete_rows)))
-                    .execute(conn)
+                conn.delete_records(Collection::Likes, &delete_rows)
                     .expect("Error deleting like records");
+            } else if lex == "reposts" {
+                conn.delete_records(Collection::Reposts, &delete_rows)
+                    .expect("Error deleting repost records");
             } else if lex == "follows" {
-                diesel::delete(FollowSchema::follow.filter(FollowSchema::uri.eq_any(delete_rows)))
-                    .execute(conn)
+                conn.delete_records(Collection::Follows, &delete_rows)
                     .expect("Error deleting follow records");
             } else {
                 eprintln!("Unknown lexicon received {lex:?}");
@@ -1060,20 +855,11 @@ pub async fn queue_deletion(
 pub async fn update_cursor(
     service_: String,
     sequence: i64,
-    connection: WriteDbConn,
+    connection: WriteConn,
 ) -> Result<(), String> {
-    use crate::schema::sub_state::dsl::*;
-
     let result = connection
         .run(move |conn| {
-            let update_state = (service.eq(service_), cursor.eq(&sequence));
-
-            diesel::insert_into(sub_state)
-                .values(&update_state)
-                .on_conflict(service)
-                .do_update()
-                .set(cursor.eq(&sequence))
-                .execute(conn)
+            conn.update_cursor(&service_, sequence)
                 .expect("Error updating cursor records");
             Ok(())
         })
@@ -1082,62 +868,44 @@ pub async fn update_cursor(
     result
 }
 
-pub fn add_visitor(
-    user: String,
-    service: String,
-    requested_feed: String,
-) -> Result<(), Box<dyn std::error::Error>> {
-    use crate::schema::visitor::dsl::*;
-
-    let connection = &mut establish_connection()?;
+pub fn add_visitor(user: String, service: String, requested_feed: String) -> StorageResult<()> {
+    let mut connection = establish_connection()?;
 
     let system_time = SystemTime::now();
     let dt: DateTime<UtcOffset> = system_time.into();
-    let new_visitor = (
-        did.eq(user),
-        web.eq(service),
-        visited_at.eq(format!("{}", dt.format("%+"))),
-        feed.eq(requested_feed),
-    );
-
-    diesel::insert_into(visitor)
-        .values(&new_visitor)
-        .execute(connection)?;
-    Ok(())
+    connection.add_visitor(&user, &service, &requested_feed, dt)
 }
 
-pub fn is_banned_from_tv(subject: &String) -> Result<bool, Box<dyn std::error::Error>> {
-    use crate::schema::banned_from_tv::dsl::*;
+pub fn add_exposure(
+    user: String,
+    requested_feed: String,
+    experiment: String,
+    variant: String,
+) -> StorageResult<()> {
+    let mut connection = establish_connection()?;
 
-    let connection = &mut establish_connection()?;
+    let system_time = SystemTime::now();
+    let dt: DateTime<UtcOffset> = system_time.into();
+    connection.add_exposure(&user, &requested_feed, &experiment, &variant, dt)
+}
 
-    let subject_to_check = subject.clone();
-    let count = banned_from_tv
-        .filter(did.eq(subject_to_check))
-        .count()
-        .get_result(connection)
-        .unwrap_or(0);
+pub fn is_banned_from_tv(subject: &String) -> StorageResult<bool> {
+    let mut connection = establish_connection()?;
 
-    return if count > 0 { Ok(true) } else { Ok(false) };
+    Ok(connection.is_banned_from_tv(subject).unwrap_or(false))
 }
 
 pub async fn get_cursor(
     service_: String,
     connection: ReadReplicaConn,
 ) -> Result<SubState, PathUnknownErrorMessageResponse> {
-    use crate::schema::sub_state::dsl::*;
-
     let result = connection
         .run(move |conn| {
-            let mut result = sub_state
-                .filter(service.eq(service_))
-                .order(cursor.desc())
-                .limit(1)
-                .select(SubState::as_select())
-                .load(conn)
+            let result = conn
+                .get_cursor(&service_)
                 .expect("Error loading cursor records");
 
-            if let Some(cursor_) = result.pop() {
+            if let Some(cursor_) = result {
                 Ok(cursor_)
             } else {
                 let not_found_error = crate::models::PathUnknownErrorMessageResponse {
@@ -1156,7 +924,7 @@ pub async fn get_cursor(
 mod tests {
     use super::*;
     use crate::routes::{index, BLACKSKY};
-    use crate::{ReadReplicaConn1, ReadReplicaConn2};
+    use crate::{ReadReplicaConn1, ReadReplicaConn2, WriteDbConn};
     use rocket::figment::map;
     use rocket::figment::value::{Map, Value};
     use rocket::http::Status;
@@ -1206,6 +974,67 @@ mod tests {
             .manage(config)
     }
 
+    fn feed_item(created_at: &str, cid: &str, post: &str, repost: Option<&str>) -> FeedItem {
+        let created_at = created_at.parse::<DateTime<Utc>>().unwrap();
+        FeedItem {
+            created_at,
+            indexed_at: created_at,
+            cid: cid.to_string(),
+            result: PostResult {
+                post: post.to_string(),
+                reason: repost.map(|repost| SkeletonReason::Repost {
+                    repost: repost.to_string(),
+                }),
+            },
+        }
+    }
+
+    #[test]
+    fn test_merge_feed_items_interleaves_reposts_newest_first() {
+        let posts = vec![
+            feed_item("2025-01-03T00:00:00Z", "c3", "at://a/post/3", None),
+            feed_item("2025-01-01T00:00:00Z", "c1", "at://a/post/1", None),
+        ];
+        let reposts = vec![
+            feed_item(
+                "2025-01-02T00:00:00Z",
+                "r2",
+                "at://b/post/9",
+                Some("at://a/repost/2"),
+            ),
+            feed_item(
+                "2024-12-31T00:00:00Z",
+                "r0",
+                "at://b/post/8",
+                Some("at://a/repost/0"),
+            ),
+        ];
+        let merged = merge_feed_items(posts, reposts, 3);
+        let cids: Vec<&str> = merged.iter().map(|item| item.cid.as_str()).collect();
+        assert_eq!(cids, vec!["c3", "r2", "c1"]);
+        assert_eq!(merged[1].result.post, "at://b/post/9");
+        assert_eq!(
+            serde_json::to_value(&merged[1].result).unwrap(),
+            serde_json::json!({
+                "post": "at://b/post/9",
+                "reason": {
+                    "$type": "app.bsky.feed.defs#skeletonReasonRepost",
+                    "repost": "at://a/repost/2"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_uri_author() {
+        assert_eq!(
+            uri_author("at://did:plc:abc123/app.bsky.feed.post/3k"),
+            Some("did:plc:abc123".to_string())
+        );
+        assert_eq!(uri_author("at://alice.test/app.bsky.feed.post/3k"), None);
+        assert_eq!(uri_author("did:plc:abc123"), None);
+    }
+
     #[rocket::async_test]
     #[ignore]
     async fn test_no_sponsored_post_when_show_sponsored_post_is_false() {
@@ -1227,6 +1056,9 @@ mod tests {
                     sponsored_post_uri: "at://did:example/sponsored-post".to_string(),
                     sponsored_post_probability: 1.0,
                     trending_percentile_min: 0.9,
+                    private_feeds: Default::default(),
+                    private_feed_empty: false,
+                    experiments: Default::default(),
                 };
                 let rocket = before(config.clone());
 
@@ -1286,6 +1118,9 @@ mod tests {
                     sponsored_post_uri: "at://did:example/sponsored-post".to_string(),
                     sponsored_post_probability: 1.0,
                     trending_percentile_min: 0.9,
+                    private_feeds: Default::default(),
+                    private_feed_empty: false,
+                    experiments: Default::default(),
                 };
                 let rocket = before(config.clone());
 
@@ -1344,6 +1179,9 @@ mod tests {
                     sponsored_post_uri: "at://did:example/sponsored-post".to_string(),
                     sponsored_post_probability: 1.0,
                     trending_percentile_min: 0.9,
+                    private_feeds: Default::default(),
+                    private_feed_empty: false,
+                    experiments: Default::default(),
                 };
                 let rocket = before(config.clone());
 
@@ -1403,6 +1241,9 @@ mod tests {
                     sponsored_post_uri: "at://did:example/sponsored-post".to_string(),
                     sponsored_post_probability: 0.5,
                     trending_percentile_min: 0.9,
+                    private_feeds: Default::default(),
+                    private_feed_empty: false,
+                    experiments: Default::default(),
                 };
                 let rocket = before(config.clone());
 
@@ -1474,6 +1315,9 @@ mod tests {
                     sponsored_post_uri: "at://did:example/sponsored-post".to_string(),
                     sponsored_post_probability: 1.0,
                     trending_percentile_min: 0.9,
+                    private_feeds: Default::default(),
+                    private_feed_empty: false,
+                    experiments: Default::default(),
                 };
                 let rocket = before(config.clone());
 
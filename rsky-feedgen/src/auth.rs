@@ -1,6 +1,16 @@
 use crate::models::JwtParts;
 use base64::{engine::general_purpose, Engine as _};
+use rsky_common::get_verification_material;
+use rsky_crypto::constants::{P256_JWT_ALG, SECP256K1_JWT_ALG};
+use rsky_crypto::did::parse_did_key;
+use rsky_crypto::types::VerifyOptions;
+use rsky_crypto::{p256, secp256k1};
+use rsky_identity::did::atproto_data::get_did_key_from_multibase;
+use rsky_identity::did::did_resolver::DidResolver;
+use rsky_identity::types::DidDocument;
+use sha2::{Digest, Sha256};
 use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
 
 pub fn verify_jwt(jwtstr: &String, service_did: &String) -> Result<String, String> {
     let parts = jwtstr.split(".").map(String::from).collect::<Vec<_>>();
@@ -37,3 +47,75 @@ pub fn verify_jwt(jwtstr: &String, service_did: &String) -> Result<String, Strin
         Err("error parsing payload".into())
     }
 }
+
+/// Checks a JWT's signature against the `atproto` key in the DID document of `iss`. Only the
+/// claims are checked by [`verify_jwt`], so anything that trusts `iss` for access needs this too.
+/// The cached document is tried first, and refetched only if the signature doesn't match it, in
+/// case the key was rotated since.
+pub async fn verify_jwt_signature(
+    jwtstr: &str,
+    iss: &str,
+    resolver: &RwLock<DidResolver>,
+) -> Result<(), String> {
+    let Some((signed, sig)) = jwtstr.rsplit_once('.') else {
+        return Err("poorly formatted jwt".into());
+    };
+    let sig = general_purpose::URL_SAFE_NO_PAD
+        .decode(sig)
+        .map_err(|_| "poorly formatted jwt signature".to_string())?;
+    // users sign as their bare did, services as `<did>#<service id>`
+    let did = iss.split('#').next().unwrap_or(iss).to_string();
+    let doc = resolve_iss(resolver, did.clone(), false).await?;
+    if verify_signature(&doc, signed, &sig).is_ok() {
+        return Ok(());
+    }
+    let doc = resolve_iss(resolver, did, true).await?;
+    verify_signature(&doc, signed, &sig)
+}
+
+async fn resolve_iss(
+    resolver: &RwLock<DidResolver>,
+    did: String,
+    force_refresh: bool,
+) -> Result<DidDocument, String> {
+    match resolver
+        .write()
+        .await
+        .resolve(did, Some(force_refresh))
+        .await
+    {
+        Ok(Some(doc)) => Ok(doc),
+        Ok(None) => Err("could not resolve iss did".into()),
+        Err(error) => Err(format!("could not resolve iss did: {error}")),
+    }
+}
+
+fn verify_signature(doc: &DidDocument, signed: &str, sig: &[u8]) -> Result<(), String> {
+    let did_key = match get_verification_material(doc, "atproto") {
+        Some(key) => get_did_key_from_multibase(key).map_err(|error| error.to_string())?,
+        None => None,
+    };
+    let Some(did_key) = did_key else {
+        return Err("missing or bad key in did doc".into());
+    };
+    let key = parse_did_key(&did_key).map_err(|error| error.to_string())?;
+    let opts = Some(VerifyOptions {
+        allow_malleable_sig: Some(true),
+    });
+    let valid = match key.jwt_alg.as_str() {
+        // the secp256k1 check takes the digest, the p256 one hashes the message itself
+        SECP256K1_JWT_ALG => secp256k1::operations::verify_sig(
+            &key.key_bytes,
+            &Sha256::digest(signed.as_bytes()),
+            sig,
+            opts,
+        ),
+        P256_JWT_ALG => p256::operations::verify_sig(&key.key_bytes, signed.as_bytes(), sig, opts),
+        alg => return Err(format!("unsupported signature alg: {alg}")),
+    };
+    match valid {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("jwt signature does not match jwt issuer".into()),
+        Err(error) => Err(format!("could not verify jwt signature: {error}")),
+    }
+}
@@ -1,16 +1,30 @@
+use crate::storage::{DatabaseBackend, FeedStorage, StorageResult};
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
 use dotenvy::dotenv;
 use std::env;
 
-pub fn establish_connection() -> Result<PgConnection, Box<dyn std::error::Error>> {
+pub fn establish_connection() -> StorageResult<Box<dyn FeedStorage + Send>> {
     dotenv().ok();
 
     let database_url = env::var("DATABASE_URL").unwrap_or("".into());
-    let result = PgConnection::establish(&database_url).map_err(|_| {
-        eprintln!("Error connecting to {database_url:?}");
-        "Internal error"
-    })?;
-
-    Ok(result)
+    match DatabaseBackend::from_env() {
+        DatabaseBackend::Postgres => {
+            let result = PgConnection::establish(&database_url).map_err(|_| {
+                eprintln!("Error connecting to {database_url:?}");
+                "Internal error"
+            })?;
+            Ok(Box::new(result))
+        }
+        #[cfg(feature = "sqlite")]
+        DatabaseBackend::Sqlite => {
+            let result = crate::storage::SqliteDb::connect(&database_url).map_err(|_| {
+                eprintln!("Error connecting to {database_url:?}");
+                "Internal error"
+            })?;
+            Ok(Box::new(result))
+        }
+        #[cfg(not(feature = "sqlite"))]
+        DatabaseBackend::Sqlite => Err("rsky-feedgen was built without the sqlite feature".into()),
+    }
 }
@@ -0,0 +1,113 @@
+//! A/B experiments on feed ranking. Each experiment splits the requesters of one feed into
+//! weighted variants, each served by its own algorithm, and logs which variant every requester
+//! was shown so the variants' engagement can be compared.
+//!
+//! Assignment hashes the experiment name with the requester's DID, so a requester stays in the
+//! same variant across requests and replicas, and paging through a feed never switches
+//! algorithms mid-scroll. Anonymous requesters always get the feed's own algorithm.
+
+use std::collections::HashMap;
+use std::fs;
+
+/// Loaded from the JSON file at `EXPERIMENTS_PATH`, a list of experiments.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Experiment {
+    pub name: String,
+    /// URI of the feed being experimented on.
+    pub feed: String,
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Variant {
+    pub name: String,
+    /// Relative share of requesters.
+    pub weight: u64,
+    /// URI of the feed whose algorithm this variant serves. Unset serves the experiment's own
+    /// feed, as a control.
+    #[serde(default)]
+    pub algorithm: Option<String>,
+}
+
+impl Experiment {
+    /// The variant `did` is bucketed into, if any variant has weight.
+    pub fn assign(&self, did: &str) -> Option<&Variant> {
+        let total: u64 = self.variants.iter().map(|variant| variant.weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut bucket = fnv1a(format!("{}:{did}", self.name).as_bytes()) % total;
+        for variant in &self.variants {
+            if bucket < variant.weight {
+                return Some(variant);
+            }
+            bucket -= variant.weight;
+        }
+        None
+    }
+}
+
+/// 64-bit FNV-1a, which unlike `DefaultHasher` is stable across builds, so assignments survive
+/// redeploys.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Reads the experiments at `path`, keyed by the feed they run on. A feed runs at most one
+/// experiment at a time; later entries for the same feed are ignored.
+pub fn load_experiments(path: &str) -> Result<HashMap<String, Experiment>, String> {
+    let contents = fs::read_to_string(path).map_err(|error| format!("{path}: {error}"))?;
+    let experiments: Vec<Experiment> =
+        serde_json::from_str(&contents).map_err(|error| format!("{path}: {error}"))?;
+    let mut by_feed = HashMap::new();
+    for experiment in experiments {
+        if by_feed.contains_key(&experiment.feed) {
+            eprintln!(
+                "Ignoring experiment {:?}: {} already runs one",
+                experiment.name, experiment.feed
+            );
+            continue;
+        }
+        by_feed.insert(experiment.feed.clone(), experiment);
+    }
+    Ok(by_feed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assign_is_deterministic_and_weighted() {
+        let experiment: Experiment = serde_json::from_str(
+            r#"{
+                "name": "trend-photos",
+                "feed": "at://did:example/app.bsky.feed.generator/trend",
+                "variants": [
+                    {"name": "control", "weight": 3},
+                    {"name": "photos", "weight": 1, "algorithm": "at://did:example/app.bsky.feed.generator/photos"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let did = "did:plc:requester";
+        assert_eq!(experiment.assign(did), experiment.assign(did));
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for n in 0..4000 {
+            let variant = experiment.assign(&format!("did:plc:{n}")).unwrap();
+            *counts.entry(variant.name.as_str()).or_default() += 1;
+        }
+        // roughly 3:1
+        assert!((2700..3300).contains(&counts["control"]));
+        assert!((700..1300).contains(&counts["photos"]));
+
+        let unweighted = Experiment {
+            variants: vec![],
+            ..experiment
+        };
+        assert_eq!(unweighted.assign(did), None);
+    }
+}
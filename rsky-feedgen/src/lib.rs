@@ -10,6 +10,10 @@ use diesel::pg::PgConnection;
 use rocket::request::{FromRequest, Outcome};
 use rocket::Request;
 use rocket_sync_db_pools::database;
+use std::collections::HashMap;
+#[cfg(feature = "sqlite")]
+use storage::SqliteDb;
+use storage::{FeedStorage, TaskError};
 
 #[database("pg_db")]
 pub struct WriteDbConn(PgConnection);
@@ -25,6 +29,8 @@ use rand::Rng;
 pub enum ReadReplicaConn {
     Conn1(ReadReplicaConn1),
     Conn2(ReadReplicaConn2),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteDb),
 }
 
 #[rocket::async_trait]
@@ -32,6 +38,10 @@ impl<'r> FromRequest<'r> for ReadReplicaConn {
     type Error = ();
 
     async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        #[cfg(feature = "sqlite")]
+        if let Some(db) = req.rocket().state::<SqliteDb>() {
+            return Outcome::Success(ReadReplicaConn::Sqlite(db.clone()));
+        }
         let n: u8 = {
             let mut rng = rand::thread_rng();
             rng.gen_range(0..2)
@@ -56,14 +66,55 @@ impl<'r> FromRequest<'r> for ReadReplicaConn {
 }
 
 impl ReadReplicaConn {
-    pub async fn run<F, R>(&self, f: F) -> R
+    pub async fn run<F, T, E>(&self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut dyn FeedStorage) -> Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: From<TaskError> + Send + 'static,
+    {
+        match self {
+            ReadReplicaConn::Conn1(conn1) => conn1.run(move |conn| f(conn)).await,
+            ReadReplicaConn::Conn2(conn2) => conn2.run(move |conn| f(conn)).await,
+            #[cfg(feature = "sqlite")]
+            ReadReplicaConn::Sqlite(db) => db.run(f).await,
+        }
+    }
+}
+
+/// The connection records are written through: the Postgres primary, or the SQLite file when
+/// one is managed.
+pub enum WriteConn {
+    Postgres(WriteDbConn),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteDb),
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for WriteConn {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        #[cfg(feature = "sqlite")]
+        if let Some(db) = req.rocket().state::<SqliteDb>() {
+            return Outcome::Success(WriteConn::Sqlite(db.clone()));
+        }
+        WriteDbConn::from_request(req)
+            .await
+            .map(WriteConn::Postgres)
+    }
+}
+
+impl WriteConn {
+    pub async fn run<F, T, E>(&self, f: F) -> Result<T, E>
     where
-        F: FnOnce(&mut PgConnection) -> R + Send + 'static,
-        R: Send + 'static,
+        F: FnOnce(&mut dyn FeedStorage) -> Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: From<TaskError> + Send + 'static,
     {
         match self {
-            ReadReplicaConn::Conn1(conn1) => conn1.run(f).await,
-            ReadReplicaConn::Conn2(conn2) => conn2.run(f).await,
+            WriteConn::Postgres(conn) => conn.run(move |conn| f(conn)).await,
+            #[cfg(feature = "sqlite")]
+            WriteConn::Sqlite(db) => db.run(f).await,
         }
     }
 }
@@ -74,11 +125,50 @@ pub struct FeedGenConfig {
     pub sponsored_post_uri: String,
     pub sponsored_post_probability: f64,
     pub trending_percentile_min: f64,
+    /// Feeds only members of a list may read: feed URI to the membership list allowed in.
+    /// Requesters are identified by the `getFeedSkeleton` JWT, whose signature is checked with
+    /// [`auth::verify_jwt_signature`] before membership is looked up.
+    pub private_feeds: HashMap<String, String>,
+    /// Serve everyone else an empty skeleton instead of a 403.
+    pub private_feed_empty: bool,
+    /// Running experiments, by the URI of the feed each one splits.
+    pub experiments: HashMap<String, experiments::Experiment>,
+}
+
+/// Parses `PRIVATE_FEEDS`, comma-separated `<feed-uri>=<list>` pairs.
+pub fn parse_private_feeds(value: &str) -> HashMap<String, String> {
+    value
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(feed, list)| (feed.trim().to_string(), list.trim().to_string()))
+        .filter(|(feed, list)| !feed.is_empty() && !list.is_empty())
+        .collect()
 }
 
 pub mod apis;
 pub mod auth;
 pub mod db;
+pub mod experiments;
 pub mod models;
 pub mod routes;
 pub mod schema;
+pub mod storage;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_private_feeds() {
+        let feeds = parse_private_feeds(
+            "at://did:plc:a/app.bsky.feed.generator/members=members, at://did:plc:a/app.bsky.feed.generator/x=,junk",
+        );
+        assert_eq!(feeds.len(), 1);
+        assert_eq!(
+            feeds
+                .get("at://did:plc:a/app.bsky.feed.generator/members")
+                .map(String::as_str),
+            Some("members")
+        );
+    }
+}
@@ -8,10 +8,17 @@ use rocket::figment::{
 };
 use rocket::http::Header;
 use rocket::serde::json::Json;
-use rocket::{Request, Response};
+use rocket::{Build, Request, Response, Rocket};
+use rsky_feedgen::experiments::load_experiments;
 use rsky_feedgen::routes::*;
-use rsky_feedgen::{FeedGenConfig, ReadReplicaConn1, ReadReplicaConn2, WriteDbConn};
+use rsky_feedgen::storage::DatabaseBackend;
+use rsky_feedgen::{
+    parse_private_feeds, FeedGenConfig, ReadReplicaConn1, ReadReplicaConn2, WriteDbConn,
+};
+use rsky_identity::did::did_resolver::DidResolver;
+use rsky_identity::types::{DidCache, DidResolverOpts};
 use std::env;
+use tokio::sync::RwLock;
 
 pub struct CORS;
 
@@ -89,10 +96,7 @@ impl Fairing for CORS {
     }
 }
 
-#[launch]
-fn rocket() -> _ {
-    dotenv().ok();
-
+fn postgres() -> Rocket<Build> {
     let write_database_url = env::var("DATABASE_URL").unwrap_or("".into());
     let read_database_url_1 = env::var("READ_REPLICA_URL_1").unwrap_or("".into());
     let read_database_url_2 = env::var("READ_REPLICA_URL_2").unwrap_or("".into());
@@ -124,6 +128,30 @@ fn rocket() -> _ {
         ],
     ));
 
+    rocket::custom(figment)
+        .attach(WriteDbConn::fairing())
+        .attach(ReadReplicaConn1::fairing())
+        .attach(ReadReplicaConn2::fairing())
+}
+
+/// A single SQLite file at `DATABASE_URL` serves both reads and writes.
+#[cfg(feature = "sqlite")]
+fn sqlite() -> Rocket<Build> {
+    let database_url = env::var("DATABASE_URL").unwrap_or("feedgen.db".into());
+    let db = rsky_feedgen::storage::SqliteDb::open(&database_url)
+        .unwrap_or_else(|error| panic!("Error opening {database_url:?}: {error}"));
+    rocket::build().manage(db)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn sqlite() -> Rocket<Build> {
+    panic!("FEEDGEN_DATABASE_BACKEND=sqlite requires building with `--features sqlite`");
+}
+
+#[launch]
+fn rocket() -> _ {
+    dotenv().ok();
+
     let feedgen_config = FeedGenConfig {
         show_sponsored_post: env::var("SHOW_SPONSORED_POST").unwrap_or("0".to_string()) == "1",
         sponsored_post_uri: env::var("SPONSORED_POST_URI").unwrap_or("".to_string()),
@@ -141,9 +169,21 @@ fn rocket() -> _ {
                 Ok(percentile) => percentile,
             },
         },
+        private_feeds: parse_private_feeds(&env::var("PRIVATE_FEEDS").unwrap_or_default()),
+        private_feed_empty: env::var("PRIVATE_FEED_DENY_EMPTY").unwrap_or("0".to_string()) == "1",
+        experiments: match env::var("EXPERIMENTS_PATH") {
+            Err(_) => Default::default(),
+            Ok(path) => load_experiments(&path)
+                .unwrap_or_else(|error| panic!("Error loading experiments: {error}")),
+        },
     };
 
-    rocket::custom(figment)
+    let server = match DatabaseBackend::from_env() {
+        DatabaseBackend::Postgres => postgres(),
+        DatabaseBackend::Sqlite => sqlite(),
+    };
+
+    server
         .mount(
             "/",
             routes![
@@ -167,8 +207,10 @@ fn rocket() -> _ {
             ],
         )
         .attach(CORS)
-        .attach(WriteDbConn::fairing())
-        .attach(ReadReplicaConn1::fairing())
-        .attach(ReadReplicaConn2::fairing())
         .manage(feedgen_config)
+        .manage(RwLock::new(DidResolver::new(DidResolverOpts {
+            timeout: None,
+            plc_url: env::var("DID_PLC_URL").ok(),
+            did_cache: DidCache::new(None, None),
+        })))
 }
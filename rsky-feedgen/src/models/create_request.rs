@@ -7,6 +7,11 @@ pub enum Lexicon {
     AppBskyFeedPost(rsky_lexicon::app::bsky::feed::Post),
     #[serde(rename(deserialize = "app.bsky.feed.like", serialize = "app.bsky.feed.like"))]
     AppBskyFeedLike(rsky_lexicon::app::bsky::feed::like::Like),
+    #[serde(rename(
+        deserialize = "app.bsky.feed.repost",
+        serialize = "app.bsky.feed.repost"
+    ))]
+    AppBskyFeedRepost(rsky_lexicon::app::bsky::feed::Repost),
     #[serde(rename(
         deserialize = "app.bsky.graph.follow",
         serialize = "app.bsky.graph.follow"
@@ -0,0 +1,19 @@
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Follow {
+    #[serde(rename = "uri")]
+    pub uri: String,
+    #[serde(rename = "cid")]
+    pub cid: String,
+    #[serde(rename = "author")]
+    pub author: String,
+    #[serde(rename = "subject")]
+    pub subject: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "indexedAt")]
+    pub indexed_at: String,
+    #[serde(rename = "prev", skip_serializing_if = "Option::is_none")]
+    pub prev: Option<String>,
+    #[serde(rename = "sequence")]
+    pub sequence: Option<i64>,
+}
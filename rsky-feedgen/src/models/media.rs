@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+
+/// An image or video embedded in a post.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Media {
+    #[serde(rename = "cid")]
+    pub cid: String,
+    #[serde(rename = "alt", skip_serializing_if = "Option::is_none")]
+    pub alt: Option<String>,
+    #[serde(rename = "postCid")]
+    pub post_cid: String,
+    #[serde(rename = "postUri")]
+    pub post_uri: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "indexedAt")]
+    pub indexed_at: DateTime<Utc>,
+    pub labels: Vec<Option<String>>,
+}
@@ -14,6 +14,7 @@ pub mod post;
 pub use self::post::Post;
 pub mod post_result;
 pub use self::post_result::PostResult;
+pub use self::post_result::SkeletonReason;
 pub mod algo_response;
 pub use self::algo_response::AlgoResponse;
 pub mod sub_state;
@@ -25,6 +26,14 @@ pub mod delete_request;
 pub use self::delete_request::DeleteRequest;
 pub mod membership;
 pub use self::membership::Membership;
+pub mod like;
+pub use self::like::Like;
+pub mod repost;
+pub use self::repost::Repost;
+pub mod follow;
+pub use self::follow::Follow;
+pub mod media;
+pub use self::media::Media;
 pub mod well_known;
 pub use self::well_known::WellKnown;
 pub mod known_service;
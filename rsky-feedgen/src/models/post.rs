@@ -48,6 +48,8 @@ pub struct Post {
     #[serde(rename = "createdAt")]
     pub created_at: DateTime<Utc>,
     pub labels: Vec<Option<String>>,
+    #[serde(rename = "replyRootAuthor", skip_serializing_if = "Option::is_none")]
+    pub reply_root_author: Option<String>,
 }
 
 impl Queryable<post::SqlType, DB> for Post {
@@ -70,6 +72,7 @@ impl Queryable<post::SqlType, DB> for Post {
         Option<String>,
         DateTime<Utc>,
         Vec<Option<String>>,
+        Option<String>,
     );
 
     fn build(row: Self::Row) -> deserialize::Result<Self> {
@@ -92,6 +95,7 @@ impl Queryable<post::SqlType, DB> for Post {
             quote_uri: row.15,
             created_at: row.16,
             labels: row.17,
+            reply_root_author: row.18,
         })
     }
 }
@@ -119,6 +123,7 @@ where
         post::quoteUri,
         post::createdAt,
         post::labels,
+        post::replyRootAuthor,
     );
 
     fn construct_selection() -> Self::SelectExpression {
@@ -141,6 +146,7 @@ where
             post::quoteUri,
             post::createdAt,
             post::labels,
+            post::replyRootAuthor,
         )
     }
 }
@@ -187,6 +193,10 @@ where
         let created_at =
             NamedRow::get::<diesel::dsl::SqlTypeOf<post::createdAt>, _>(row, "createdAt")?;
         let labels = NamedRow::get::<diesel::dsl::SqlTypeOf<post::labels>, _>(row, "labels")?;
+        let reply_root_author = NamedRow::get::<diesel::dsl::SqlTypeOf<post::replyRootAuthor>, _>(
+            row,
+            "replyRootAuthor",
+        )?;
         Ok(Self {
             uri,
             cid,
@@ -206,6 +216,7 @@ where
             quote_uri,
             created_at,
             labels,
+            reply_root_author,
         })
     }
 }
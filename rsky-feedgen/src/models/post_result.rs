@@ -2,4 +2,17 @@
 pub struct PostResult {
     #[serde(rename = "post")]
     pub post: String,
+    #[serde(rename = "reason", skip_serializing_if = "Option::is_none", default)]
+    pub reason: Option<SkeletonReason>,
+}
+
+/// Why a post is in the feed, when it isn't there in its own right.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "$type")]
+pub enum SkeletonReason {
+    #[serde(rename = "app.bsky.feed.defs#skeletonReasonRepost")]
+    Repost {
+        #[serde(rename = "repost")]
+        repost: String,
+    },
 }
@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Repost {
+    #[serde(rename = "uri")]
+    pub uri: String,
+    #[serde(rename = "cid")]
+    pub cid: String,
+    #[serde(rename = "author")]
+    pub author: String,
+    #[serde(rename = "subjectCid")]
+    pub subject_cid: String,
+    #[serde(rename = "subjectUri")]
+    pub subject_uri: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "indexedAt")]
+    pub indexed_at: DateTime<Utc>,
+    #[serde(rename = "prev", skip_serializing_if = "Option::is_none")]
+    pub prev: Option<String>,
+    #[serde(rename = "sequence")]
+    pub sequence: Option<i64>,
+}
@@ -1,19 +1,23 @@
-use crate::apis::TrendingMedia;
+use crate::apis::{ThreadFilter, TrendingMedia};
 use crate::models::JwtParts;
-use crate::{FeedGenConfig, ReadReplicaConn, WriteDbConn};
+use crate::{FeedGenConfig, ReadReplicaConn, WriteConn};
 use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome};
 use rocket::response::status;
 use rocket::serde::json::Json;
 use rocket::{Request, State};
+use rsky_common_web::{XrpcError, XrpcErrorKind};
+use rsky_identity::did::did_resolver::DidResolver;
 use std::env;
 use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
 
 #[allow(dead_code)]
 pub struct ApiKey<'r>(&'r str);
 
+/// The verified claims as JSON, and the raw token for checks that need its signature.
 #[derive(Debug)]
-pub struct AccessToken(String);
+pub struct AccessToken(String, String);
 
 #[derive(Debug)]
 pub enum ApiKeyError {
@@ -65,7 +69,7 @@ impl<'r> FromRequest<'r> for AccessToken {
                 let jwt = token.split(" ").map(String::from).collect::<Vec<_>>();
                 if let Some(jwtstr) = jwt.last() {
                     match crate::auth::verify_jwt(&jwtstr, &service_did) {
-                        Ok(jwt_object) => Outcome::Success(AccessToken(jwt_object)),
+                        Ok(jwt_object) => Outcome::Success(AccessToken(jwt_object, jwtstr.clone())),
                         Err(error) => {
                             eprintln!("Error decoding jwt. {error:?}");
                             Outcome::Error((Status::Unauthorized, AccessTokenError::Invalid))
@@ -117,27 +121,59 @@ fn get_banned_response() -> crate::models::AlgoResponse {
     banned_response
 }
 
+/// What requesters who aren't on a private feed's list get instead of its skeleton.
+fn get_private_feed_response(
+    config: &FeedGenConfig,
+) -> Result<Json<crate::models::AlgoResponse>, XrpcError> {
+    if config.private_feed_empty {
+        return Ok(Json(crate::models::AlgoResponse::default()));
+    }
+    Err(XrpcError::new(
+        XrpcErrorKind::Forbidden,
+        "This feed is only available to its members.",
+    ))
+}
+
+/// The `threads` param of the membership feeds: `all`, `top-level` (the default) or `member`
+/// for top-level posts plus replies in threads a member started.
+fn parse_thread_filter(threads: Option<&str>) -> Result<ThreadFilter, XrpcError> {
+    match threads {
+        None | Some("top-level") => Ok(ThreadFilter::TopLevel),
+        Some("all") => Ok(ThreadFilter::All),
+        Some("member") => Ok(ThreadFilter::MemberThreads),
+        Some(other) => Err(XrpcError::invalid_request(format!(
+            "Unknown threads filter: {other}"
+        ))),
+    }
+}
+
 #[rocket::get(
-    "/xrpc/app.bsky.feed.getFeedSkeleton?<feed>&<limit>&<cursor>",
+    "/xrpc/app.bsky.feed.getFeedSkeleton?<feed>&<limit>&<cursor>&<threads>&<reposts>",
     format = "json"
 )]
 pub async fn index(
     feed: Option<&str>,
     limit: Option<i64>,
     cursor: Option<&str>,
+    threads: Option<&str>,
+    reposts: Option<bool>,
     connection: ReadReplicaConn,
     config: &State<FeedGenConfig>,
+    did_resolver: &State<RwLock<DidResolver>>,
     _token: Result<AccessToken, AccessTokenError>,
-) -> Result<
-    Json<crate::models::AlgoResponse>,
-    status::Custom<Json<crate::models::InternalErrorMessageResponse>>,
-> {
+) -> Result<Json<crate::models::AlgoResponse>, XrpcError> {
+    let threads = parse_thread_filter(threads)?;
+    let include_reposts = reposts.unwrap_or(false);
     let mut is_banned = false;
+    let mut requester = None;
+    let mut requester_jwt = None;
     let feed = feed.unwrap_or("".into());
     if let Ok(jwt) = _token {
+        requester_jwt = Some(jwt.1);
         match serde_json::from_str::<JwtParts>(&jwt.0) {
             Ok(jwt_obj) => {
                 let did = jwt_obj.iss;
+                requester = Some(did.clone());
                 match crate::apis::add_visitor(did.clone(), jwt_obj.aud, feed.to_string()) {
                     Ok(_) => {
                         is_banned = crate::apis::is_banned_from_tv(&did).unwrap_or(false);
@@ -155,20 +191,58 @@ pub async fn index(
             Err(_) => eprintln!("Failed to write anonymous visitor."),
         }
     }
+    if let Some(list) = config.private_feeds.get(feed) {
+        let allowed = match (&requester, &requester_jwt) {
+            // membership rests on who signed the JWT, not just who it claims to be from
+            (Some(did), Some(jwt)) => {
+                match crate::auth::verify_jwt_signature(jwt, did, did_resolver).await {
+                    Ok(()) => {
+                        match crate::apis::is_feed_member(did.clone(), list.clone(), &connection)
+                            .await
+                        {
+                            Ok(allowed) => allowed,
+                            Err(error) => {
+                                eprintln!("Failed to check feed access: {error}");
+                                false
+                            }
+                        }
+                    }
+                    Err(error) => {
+                        eprintln!("Rejected private feed requester {did}: {error}");
+                        false
+                    }
+                }
+            }
+            _ => false,
+        };
+        if !allowed {
+            return get_private_feed_response(config);
+        }
+    }
+    let feed = match (config.experiments.get(feed), &requester) {
+        (Some(experiment), Some(did)) => match experiment.assign(did) {
+            Some(variant) => {
+                if let Err(error) = crate::apis::add_exposure(
+                    did.clone(),
+                    feed.to_string(),
+                    experiment.name.clone(),
+                    variant.name.clone(),
+                ) {
+                    eprintln!("Failed to write exposure. {error}");
+                }
+                variant.algorithm.as_deref().unwrap_or(feed)
+            }
+            None => feed,
+        },
+        _ => feed,
+    };
     match feed {
         _blacksky if _blacksky == BLACKSKY && !is_banned => {
             match crate::apis::get_all_posts(None, limit, cursor, true, connection, config).await {
                 Ok(response) => Ok(Json(response)),
                 Err(error) => {
                     eprintln!("Internal Error: {error}");
-                    let internal_error = crate::models::InternalErrorMessageResponse {
-                        code: Some(crate::models::InternalErrorCode::InternalError),
-                        message: Some(error.to_string()),
-                    };
-                    Err(status::Custom(
-                        Status::InternalServerError,
-                        Json(internal_error),
-                    ))
+                    Err(XrpcError::internal(error.to_string()))
                 }
             }
         }
@@ -177,14 +251,7 @@ pub async fn index(
                 Ok(response) => Ok(Json(response)),
                 Err(error) => {
                     eprintln!("Internal Error: {error}");
-                    let internal_error = crate::models::InternalErrorMessageResponse {
-                        code: Some(crate::models::InternalErrorCode::InternalError),
-                        message: Some(error.to_string()),
-                    };
-                    Err(status::Custom(
-                        Status::InternalServerError,
-                        Json(internal_error),
-                    ))
+                    Err(XrpcError::internal(error.to_string()))
                 }
             }
         }
@@ -195,14 +262,7 @@ pub async fn index(
                 Ok(response) => Ok(Json(response)),
                 Err(error) => {
                     eprintln!("Internal Error: {error}");
-                    let internal_error = crate::models::InternalErrorMessageResponse {
-                        code: Some(crate::models::InternalErrorCode::InternalError),
-                        message: Some(error.to_string()),
-                    };
-                    Err(status::Custom(
-                        Status::InternalServerError,
-                        Json(internal_error),
-                    ))
+                    Err(XrpcError::internal(error.to_string()))
                 }
             }
         }
@@ -219,14 +279,7 @@ pub async fn index(
                 Ok(response) => Ok(Json(response)),
                 Err(error) => {
                     eprintln!("Internal Error: {error}");
-                    let internal_error = crate::models::InternalErrorMessageResponse {
-                        code: Some(crate::models::InternalErrorCode::InternalError),
-                        message: Some(error.to_string()),
-                    };
-                    Err(status::Custom(
-                        Status::InternalServerError,
-                        Json(internal_error),
-                    ))
+                    Err(XrpcError::internal(error.to_string()))
                 }
             }
         }
@@ -243,14 +296,7 @@ pub async fn index(
                 Ok(response) => Ok(Json(response)),
                 Err(error) => {
                     eprintln!("Internal Error: {error}");
-                    let internal_error = crate::models::InternalErrorMessageResponse {
-                        code: Some(crate::models::InternalErrorCode::InternalError),
-                        message: Some(error.to_string()),
-                    };
-                    Err(status::Custom(
-                        Status::InternalServerError,
-                        Json(internal_error),
-                    ))
+                    Err(XrpcError::internal(error.to_string()))
                 }
             }
         }
@@ -259,7 +305,8 @@ pub async fn index(
                 None,
                 limit,
                 cursor,
-                true,
+                threads,
+                include_reposts,
                 "blacksky-edu".into(),
                 vec!["#blackademics".into()],
                 connection,
@@ -270,14 +317,7 @@ pub async fn index(
                 Ok(response) => Ok(Json(response)),
                 Err(error) => {
                     eprintln!("Internal Error: {error}");
-                    let internal_error = crate::models::InternalErrorMessageResponse {
-                        code: Some(crate::models::InternalErrorCode::InternalError),
-                        message: Some(error.to_string()),
-                    };
-                    Err(status::Custom(
-                        Status::InternalServerError,
-                        Json(internal_error),
-                    ))
+                    Err(XrpcError::internal(error.to_string()))
                 }
             }
         }
@@ -286,7 +326,8 @@ pub async fn index(
                 None,
                 limit,
                 cursor,
-                true,
+                threads,
+                include_reposts,
                 "blacksky-travel".into(),
                 vec!["blackskytravel".into()],
                 connection,
@@ -297,14 +338,7 @@ pub async fn index(
                 Ok(response) => Ok(Json(response)),
                 Err(error) => {
                     eprintln!("Internal Error: {error}");
-                    let internal_error = crate::models::InternalErrorMessageResponse {
-                        code: Some(crate::models::InternalErrorCode::InternalError),
-                        message: Some(error.to_string()),
-                    };
-                    Err(status::Custom(
-                        Status::InternalServerError,
-                        Json(internal_error),
-                    ))
+                    Err(XrpcError::internal(error.to_string()))
                 }
             }
         }
@@ -313,7 +347,8 @@ pub async fn index(
                 None,
                 limit,
                 cursor,
-                true,
+                threads,
+                include_reposts,
                 "blacksky-med".into(),
                 vec!["blackmedsky".into()],
                 connection,
@@ -324,14 +359,7 @@ pub async fn index(
                 Ok(response) => Ok(Json(response)),
                 Err(error) => {
                     eprintln!("Internal Error: {error}");
-                    let internal_error = crate::models::InternalErrorMessageResponse {
-                        code: Some(crate::models::InternalErrorCode::InternalError),
-                        message: Some(error.to_string()),
-                    };
-                    Err(status::Custom(
-                        Status::InternalServerError,
-                        Json(internal_error),
-                    ))
+                    Err(XrpcError::internal(error.to_string()))
                 }
             }
         }
@@ -340,7 +368,8 @@ pub async fn index(
                 None,
                 limit,
                 cursor,
-                true,
+                threads,
+                include_reposts,
                 "blacksky-scholastic".into(),
                 vec!["blackedusky".into()],
                 connection,
@@ -351,14 +380,7 @@ pub async fn index(
                 Ok(response) => Ok(Json(response)),
                 Err(error) => {
                     eprintln!("Internal Error: {error}");
-                    let internal_error = crate::models::InternalErrorMessageResponse {
-                        code: Some(crate::models::InternalErrorCode::InternalError),
-                        message: Some(error.to_string()),
-                    };
-                    Err(status::Custom(
-                        Status::InternalServerError,
-                        Json(internal_error),
-                    ))
+                    Err(XrpcError::internal(error.to_string()))
                 }
             }
         }
@@ -398,16 +420,7 @@ pub async fn index(
             let banned_response = get_banned_response();
             Ok(Json(banned_response))
         }
-        _ => {
-            let internal_error = crate::models::InternalErrorMessageResponse {
-                code: Some(crate::models::InternalErrorCode::InternalError),
-                message: Some("Not Found".to_string()),
-            };
-            Err(status::Custom(
-                Status::InternalServerError,
-                Json(internal_error),
-            ))
-        }
+        _ => Err(XrpcError::custom("UnknownFeed", "Unknown feed")),
     }
 }
 
@@ -416,20 +429,13 @@ pub async fn update_cursor(
     service: &str,
     sequence: i64,
     _key: ApiKey<'_>,
-    connection: WriteDbConn,
-) -> Result<(), status::Custom<Json<crate::models::InternalErrorMessageResponse>>> {
+    connection: WriteConn,
+) -> Result<(), XrpcError> {
     match crate::apis::update_cursor(service.to_string(), sequence, connection).await {
         Ok(_) => Ok(()),
         Err(error) => {
             eprintln!("Internal Error: {error}");
-            let internal_error = crate::models::InternalErrorMessageResponse {
-                code: Some(crate::models::InternalErrorCode::InternalError),
-                message: Some(error.to_string()),
-            };
-            Err(status::Custom(
-                Status::InternalServerError,
-                Json(internal_error),
-            ))
+            Err(XrpcError::internal(error.to_string()))
         }
     }
 }
@@ -461,20 +467,13 @@ pub async fn queue_creation(
     lex: &str,
     body: Json<Vec<crate::models::CreateRequest>>,
     _key: ApiKey<'_>,
-    connection: WriteDbConn,
-) -> Result<(), status::Custom<Json<crate::models::InternalErrorMessageResponse>>> {
+    connection: WriteConn,
+) -> Result<(), XrpcError> {
     match crate::apis::queue_creation(lex.to_string(), body.into_inner(), connection).await {
         Ok(_) => Ok(()),
         Err(error) => {
             eprintln!("Internal Error: {error}");
-            let internal_error = crate::models::InternalErrorMessageResponse {
-                code: Some(crate::models::InternalErrorCode::InternalError),
-                message: Some(error.to_string()),
-            };
-            Err(status::Custom(
-                Status::InternalServerError,
-                Json(internal_error),
-            ))
+            Err(XrpcError::internal(error.to_string()))
         }
     }
 }
@@ -484,20 +483,13 @@ pub async fn queue_deletion(
     lex: &str,
     body: Json<Vec<crate::models::DeleteRequest>>,
     _key: ApiKey<'_>,
-    connection: WriteDbConn,
-) -> Result<(), status::Custom<Json<crate::models::InternalErrorMessageResponse>>> {
+    connection: WriteConn,
+) -> Result<(), XrpcError> {
     match crate::apis::queue_deletion(lex.to_string(), body.into_inner(), connection).await {
         Ok(_) => Ok(()),
         Err(error) => {
             eprintln!("Internal Error: {error}");
-            let internal_error = crate::models::InternalErrorMessageResponse {
-                code: Some(crate::models::InternalErrorCode::InternalError),
-                message: Some(error.to_string()),
-            };
-            Err(status::Custom(
-                Status::InternalServerError,
-                Json(internal_error),
-            ))
+            Err(XrpcError::internal(error.to_string()))
         }
     }
 }
@@ -9,6 +9,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    experiment_exposure (id) {
+        id -> Int4,
+        did -> Varchar,
+        feed -> Varchar,
+        experiment -> Varchar,
+        variant -> Varchar,
+        exposed_at -> Varchar,
+    }
+}
+
 diesel::table! {
     follow (uri) {
         uri -> Varchar,
@@ -94,6 +105,21 @@ diesel::table! {
         quoteUri -> Nullable<Varchar>,
         createdAt -> Timestamptz,
         labels -> Array<Nullable<Text>>,
+        replyRootAuthor -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    repost (uri) {
+        uri -> Varchar,
+        cid -> Varchar,
+        author -> Varchar,
+        subjectCid -> Varchar,
+        subjectUri -> Varchar,
+        createdAt -> Timestamptz,
+        indexedAt -> Timestamptz,
+        prev -> Nullable<Varchar>,
+        sequence -> Nullable<Int8>,
     }
 }
 
@@ -128,6 +154,7 @@ diesel::table! {
 
 diesel::allow_tables_to_appear_in_same_query!(
     banned_from_tv,
+    experiment_exposure,
     follow,
     image,
     kysely_migration,
@@ -135,6 +162,7 @@ diesel::allow_tables_to_appear_in_same_query!(
     like,
     membership,
     post,
+    repost,
     sub_state,
     video,
     visitor,
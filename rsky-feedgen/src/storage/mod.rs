@@ -0,0 +1,204 @@
+//! Storage backends. The feed logic in [`crate::apis`] only talks to a [`FeedStorage`], which is
+//! implemented over Postgres with Diesel and, with the `sqlite` feature, over a single SQLite
+//! file for deployments too small to justify running Postgres.
+
+use crate::models::{
+    Follow, Like, Media, Membership, PathUnknownErrorMessageResponse, Post, Repost, SubState,
+    ValidationErrorMessageResponse,
+};
+use chrono::{DateTime, Utc};
+use std::{env, fmt};
+
+mod postgres;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::SqliteDb;
+
+pub type StorageResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// A storage task that never returned, because it panicked or its runtime shut down. Converts
+/// into each error type the feed handlers return.
+#[derive(Debug)]
+pub struct TaskError(pub String);
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "storage task failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for TaskError {}
+
+impl From<TaskError> for String {
+    fn from(error: TaskError) -> Self {
+        error.to_string()
+    }
+}
+
+impl From<TaskError> for ValidationErrorMessageResponse {
+    fn from(error: TaskError) -> Self {
+        ValidationErrorMessageResponse {
+            code: None,
+            message: Some(error.to_string()),
+        }
+    }
+}
+
+impl From<TaskError> for PathUnknownErrorMessageResponse {
+    fn from(error: TaskError) -> Self {
+        PathUnknownErrorMessageResponse {
+            code: None,
+            message: Some(error.to_string()),
+        }
+    }
+}
+
+/// Which database the feed generator runs against, from `FEEDGEN_DATABASE_BACKEND`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DatabaseBackend {
+    Postgres,
+    Sqlite,
+}
+
+impl DatabaseBackend {
+    pub fn from_env() -> Self {
+        match env::var("FEEDGEN_DATABASE_BACKEND").as_deref() {
+            Ok("sqlite") => DatabaseBackend::Sqlite,
+            _ => DatabaseBackend::Postgres,
+        }
+    }
+}
+
+/// Position in a feed, from a `<timestamp_millis>::<cid>` cursor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeedCursor {
+    pub created_at: DateTime<Utc>,
+    pub cid: String,
+}
+
+/// Which replies a feed keeps, judged by the thread they belong to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ThreadFilter {
+    /// Posts and replies alike.
+    All,
+    /// Top-level posts only.
+    TopLevel,
+    /// Top-level posts, plus replies in threads a list member started. Without a
+    /// [`MembershipFilter`] this keeps top-level posts only.
+    MemberThreads,
+}
+
+#[derive(Clone)]
+pub enum TrendingMedia {
+    OnlyVideo,
+    OnlyImage,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MediaKind {
+    Image,
+    Video,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Collection {
+    Posts,
+    Likes,
+    Reposts,
+    Follows,
+}
+
+/// Posts by members of `list`, or carrying one of `hashtags`.
+#[derive(Clone, Debug)]
+pub struct MembershipFilter {
+    pub list: String,
+    pub hashtags: Vec<String>,
+}
+
+/// A page of posts, newest first.
+#[derive(Clone, Debug)]
+pub struct PostQuery {
+    pub limit: i64,
+    pub cursor: Option<FeedCursor>,
+    pub lang: Option<String>,
+    pub threads: ThreadFilter,
+    pub membership: Option<MembershipFilter>,
+    /// Skip posts carrying any label.
+    pub unlabeled: bool,
+    /// Skip posts with images or video.
+    pub without_media: bool,
+}
+
+/// A page of the most liked recent posts.
+#[derive(Clone)]
+pub struct TrendingQuery {
+    pub limit: i64,
+    pub cursor: Option<FeedCursor>,
+    /// Like-count percentile a post must reach.
+    pub percentile: f64,
+    pub media: Option<TrendingMedia>,
+}
+
+pub trait FeedStorage {
+    /// Memberships of any of `dids`, across every list.
+    fn memberships(&mut self, dids: &[String]) -> StorageResult<Vec<Membership>>;
+
+    /// Adds members, leaving existing memberships untouched.
+    fn add_members(&mut self, members: &[Membership]) -> StorageResult<()>;
+
+    /// Removes `dids` from every list.
+    fn remove_members(&mut self, dids: &[String]) -> StorageResult<()>;
+
+    fn insert_posts(&mut self, posts: &[Post]) -> StorageResult<()>;
+
+    fn insert_media(&mut self, kind: MediaKind, media: &[Media]) -> StorageResult<()>;
+
+    fn insert_likes(&mut self, likes: &[Like]) -> StorageResult<()>;
+
+    fn insert_reposts(&mut self, reposts: &[Repost]) -> StorageResult<()>;
+
+    fn insert_follows(&mut self, follows: &[Follow]) -> StorageResult<()>;
+
+    fn delete_records(&mut self, collection: Collection, uris: &[String]) -> StorageResult<()>;
+
+    /// Appends `label` to the post at `uri`, returning how many posts matched.
+    fn add_post_label(&mut self, uri: &str, label: &str) -> StorageResult<usize>;
+
+    fn feed_posts(&mut self, query: &PostQuery) -> StorageResult<Vec<Post>>;
+
+    /// Reposts by members of `list`, newest first.
+    fn feed_reposts(
+        &mut self,
+        list: &str,
+        limit: i64,
+        cursor: Option<&FeedCursor>,
+    ) -> StorageResult<Vec<Repost>>;
+
+    fn trending_posts(&mut self, query: &TrendingQuery) -> StorageResult<Vec<Post>>;
+
+    fn update_cursor(&mut self, service: &str, sequence: i64) -> StorageResult<()>;
+
+    fn get_cursor(&mut self, service: &str) -> StorageResult<Option<SubState>>;
+
+    fn add_visitor(
+        &mut self,
+        did: &str,
+        web: &str,
+        feed: &str,
+        visited_at: DateTime<Utc>,
+    ) -> StorageResult<()>;
+
+    /// Records that `did` was served `variant` of `experiment` on `feed`.
+    fn add_exposure(
+        &mut self,
+        did: &str,
+        feed: &str,
+        experiment: &str,
+        variant: &str,
+        exposed_at: DateTime<Utc>,
+    ) -> StorageResult<()>;
+
+    fn is_banned_from_tv(&mut self, did: &str) -> StorageResult<bool>;
+}
@@ -0,0 +1,577 @@
+use super::{
+    Collection, FeedCursor, FeedStorage, MediaKind, PostQuery, StorageResult, ThreadFilter,
+    TrendingMedia, TrendingQuery,
+};
+use crate::models::{Follow, Like, Media, Membership, Post, Repost, SubState};
+use chrono::{DateTime, Utc};
+use diesel::dsl::sql;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{Array, Bool, Nullable, Text};
+
+impl FeedStorage for PgConnection {
+    fn memberships(&mut self, dids: &[String]) -> StorageResult<Vec<Membership>> {
+        use crate::schema::membership::dsl::*;
+
+        Ok(membership
+            .filter(did.eq_any(dids))
+            .select(Membership::as_select())
+            .load(self)?)
+    }
+
+    fn add_members(&mut self, members: &[Membership]) -> StorageResult<()> {
+        use crate::schema::membership::dsl as MembershipSchema;
+
+        let new_members = members
+            .iter()
+            .map(|member| {
+                (
+                    MembershipSchema::did.eq(&member.did),
+                    MembershipSchema::included.eq(member.included),
+                    MembershipSchema::excluded.eq(member.excluded),
+                    MembershipSchema::list.eq(&member.list),
+                )
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(MembershipSchema::membership)
+            .values(&new_members)
+            .on_conflict((MembershipSchema::did, MembershipSchema::list))
+            .do_nothing()
+            .execute(self)?;
+        Ok(())
+    }
+
+    fn remove_members(&mut self, dids: &[String]) -> StorageResult<()> {
+        use crate::schema::membership::dsl as MembershipSchema;
+
+        diesel::delete(MembershipSchema::membership.filter(MembershipSchema::did.eq_any(dids)))
+            .execute(self)?;
+        Ok(())
+    }
+
+    fn insert_posts(&mut self, posts: &[Post]) -> StorageResult<()> {
+        use crate::schema::post::dsl as PostSchema;
+
+        let new_posts = posts
+            .iter()
+            .map(|post| {
+                (
+                    PostSchema::uri.eq(&post.uri),
+                    PostSchema::cid.eq(&post.cid),
+                    PostSchema::replyParent.eq(&post.reply_parent),
+                    PostSchema::replyRoot.eq(&post.reply_root),
+                    PostSchema::indexedAt.eq(post.indexed_at),
+                    PostSchema::prev.eq(&post.prev),
+                    PostSchema::sequence.eq(post.sequence),
+                    PostSchema::text.eq(&post.text),
+                    PostSchema::lang.eq(&post.lang),
+                    PostSchema::author.eq(&post.author),
+                    PostSchema::externalUri.eq(&post.external_uri),
+                    PostSchema::externalTitle.eq(&post.external_title),
+                    PostSchema::externalDescription.eq(&post.external_description),
+                    PostSchema::externalThumb.eq(&post.external_thumb),
+                    PostSchema::quoteCid.eq(&post.quote_cid),
+                    PostSchema::quoteUri.eq(&post.quote_uri),
+                    PostSchema::createdAt.eq(post.created_at),
+                    PostSchema::labels.eq(&post.labels),
+                    PostSchema::replyRootAuthor.eq(&post.reply_root_author),
+                )
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(PostSchema::post)
+            .values(&new_posts)
+            .on_conflict(PostSchema::uri)
+            .do_nothing()
+            .execute(self)?;
+        Ok(())
+    }
+
+    fn insert_media(&mut self, kind: MediaKind, media: &[Media]) -> StorageResult<()> {
+        use crate::schema::image::dsl as ImageSchema;
+        use crate::schema::video::dsl as VideoSchema;
+
+        match kind {
+            MediaKind::Image => {
+                let new_images = media
+                    .iter()
+                    .map(|image| {
+                        (
+                            ImageSchema::cid.eq(&image.cid),
+                            ImageSchema::alt.eq(&image.alt),
+                            ImageSchema::postCid.eq(&image.post_cid),
+                            ImageSchema::postUri.eq(&image.post_uri),
+                            ImageSchema::indexedAt.eq(image.indexed_at),
+                            ImageSchema::createdAt.eq(image.created_at),
+                            ImageSchema::labels.eq(&image.labels),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                diesel::insert_into(ImageSchema::image)
+                    .values(&new_images)
+                    .on_conflict(ImageSchema::cid)
+                    .do_nothing()
+                    .execute(self)?;
+            }
+            MediaKind::Video => {
+                let new_videos = media
+                    .iter()
+                    .map(|video| {
+                        (
+                            VideoSchema::cid.eq(&video.cid),
+                            VideoSchema::alt.eq(&video.alt),
+                            VideoSchema::postCid.eq(&video.post_cid),
+                            VideoSchema::postUri.eq(&video.post_uri),
+                            VideoSchema::indexedAt.eq(video.indexed_at),
+                            VideoSchema::createdAt.eq(video.created_at),
+                            VideoSchema::labels.eq(&video.labels),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                diesel::insert_into(VideoSchema::video)
+                    .values(&new_videos)
+                    .on_conflict(VideoSchema::cid)
+                    .do_nothing()
+                    .execute(self)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_likes(&mut self, likes: &[Like]) -> StorageResult<()> {
+        use crate::schema::like::dsl as LikeSchema;
+
+        let new_likes = likes
+            .iter()
+            .map(|like| {
+                (
+                    LikeSchema::uri.eq(&like.uri),
+                    LikeSchema::cid.eq(&like.cid),
+                    LikeSchema::author.eq(&like.author),
+                    LikeSchema::subjectCid.eq(&like.subject_cid),
+                    LikeSchema::subjectUri.eq(&like.subject_uri),
+                    LikeSchema::createdAt.eq(like.created_at),
+                    LikeSchema::indexedAt.eq(like.indexed_at),
+                    LikeSchema::prev.eq(&like.prev),
+                    LikeSchema::sequence.eq(like.sequence),
+                )
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(LikeSchema::like)
+            .values(&new_likes)
+            .on_conflict(LikeSchema::uri)
+            .do_nothing()
+            .execute(self)?;
+        Ok(())
+    }
+
+    fn insert_reposts(&mut self, reposts: &[Repost]) -> StorageResult<()> {
+        use crate::schema::repost::dsl as RepostSchema;
+
+        let new_reposts = reposts
+            .iter()
+            .map(|repost| {
+                (
+                    RepostSchema::uri.eq(&repost.uri),
+                    RepostSchema::cid.eq(&repost.cid),
+                    RepostSchema::author.eq(&repost.author),
+                    RepostSchema::subjectCid.eq(&repost.subject_cid),
+                    RepostSchema::subjectUri.eq(&repost.subject_uri),
+                    RepostSchema::createdAt.eq(repost.created_at),
+                    RepostSchema::indexedAt.eq(repost.indexed_at),
+                    RepostSchema::prev.eq(&repost.prev),
+                    RepostSchema::sequence.eq(repost.sequence),
+                )
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(RepostSchema::repost)
+            .values(&new_reposts)
+            .on_conflict(RepostSchema::uri)
+            .do_nothing()
+            .execute(self)?;
+        Ok(())
+    }
+
+    fn insert_follows(&mut self, follows: &[Follow]) -> StorageResult<()> {
+        use crate::schema::follow::dsl as FollowSchema;
+
+        let new_follows = follows
+            .iter()
+            .map(|follow| {
+                (
+                    FollowSchema::uri.eq(&follow.uri),
+                    FollowSchema::cid.eq(&follow.cid),
+                    FollowSchema::author.eq(&follow.author),
+                    FollowSchema::subject.eq(&follow.subject),
+                    FollowSchema::createdAt.eq(&follow.created_at),
+                    FollowSchema::indexedAt.eq(&follow.indexed_at),
+                    FollowSchema::prev.eq(&follow.prev),
+                    FollowSchema::sequence.eq(follow.sequence),
+                )
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(FollowSchema::follow)
+            .values(&new_follows)
+            .on_conflict(FollowSchema::uri)
+            .do_nothing()
+            .execute(self)?;
+        Ok(())
+    }
+
+    fn delete_records(&mut self, collection: Collection, uris: &[String]) -> StorageResult<()> {
+        use crate::schema::follow::dsl as FollowSchema;
+        use crate::schema::like::dsl as LikeSchema;
+        use crate::schema::post::dsl as PostSchema;
+        use crate::schema::repost::dsl as RepostSchema;
+
+        match collection {
+            Collection::Posts => {
+                diesel::delete(PostSchema::post.filter(PostSchema::uri.eq_any(uris)))
+                    .execute(self)?
+            }
+            Collection::Likes => {
+                diesel::delete(LikeSchema::like.filter(LikeSchema::uri.eq_any(uris)))
+                    .execute(self)?
+            }
+            Collection::Reposts => {
+                diesel::delete(RepostSchema::repost.filter(RepostSchema::uri.eq_any(uris)))
+                    .execute(self)?
+            }
+            Collection::Follows => {
+                diesel::delete(FollowSchema::follow.filter(FollowSchema::uri.eq_any(uris)))
+                    .execute(self)?
+            }
+        };
+        Ok(())
+    }
+
+    fn add_post_label(&mut self, uri: &str, label: &str) -> StorageResult<usize> {
+        Ok(
+            sql_query("UPDATE post SET labels = labels || $1 WHERE uri = $2")
+                .bind::<Array<Nullable<Text>>, _>(vec![Some(label)])
+                .bind::<Text, _>(uri)
+                .execute(self)?,
+        )
+    }
+
+    #[allow(deprecated)]
+    fn feed_posts(&mut self, query: &PostQuery) -> StorageResult<Vec<Post>> {
+        use crate::schema::membership::dsl as MembershipSchema;
+        use crate::schema::post::dsl as PostSchema;
+        use diesel::dsl::any;
+
+        let mut posts = PostSchema::post
+            .limit(query.limit)
+            .select(Post::as_select())
+            .order((PostSchema::createdAt.desc(), PostSchema::cid.desc()))
+            .into_boxed();
+
+        if let Some(lang) = &query.lang {
+            posts = posts.filter(PostSchema::lang.like(format!("%{}%", lang)));
+        }
+        if let Some(cursor) = &query.cursor {
+            posts = posts.filter(
+                PostSchema::createdAt
+                    .lt(cursor.created_at)
+                    .or(PostSchema::createdAt
+                        .eq(cursor.created_at)
+                        .and(PostSchema::cid.lt(cursor.cid.clone()))),
+            );
+        }
+        if query.unlabeled {
+            posts = posts.filter(sql::<Bool>("COALESCE(array_length(labels, 1), 0) = 0"));
+        }
+        if query.without_media {
+            posts = posts
+                .filter(sql::<Bool>(
+                    "NOT EXISTS (SELECT 1 FROM image WHERE image.\"postUri\" = post.uri)",
+                ))
+                .filter(sql::<Bool>(
+                    "NOT EXISTS (SELECT 1 FROM video WHERE video.\"postUri\" = post.uri)",
+                ));
+        }
+        match (query.threads, &query.membership) {
+            (ThreadFilter::All, _) => (),
+            (ThreadFilter::MemberThreads, Some(membership)) => {
+                posts = posts.filter(
+                    PostSchema::replyRoot.is_null().or(sql::<Bool>(
+                        "EXISTS (SELECT 1 FROM membership m WHERE m.did = post.\"replyRootAuthor\" AND m.included AND m.list = ",
+                    )
+                    .bind::<Text, _>(membership.list.clone())
+                    .sql(")")),
+                );
+            }
+            (ThreadFilter::TopLevel, _) | (ThreadFilter::MemberThreads, None) => {
+                posts = posts
+                    .filter(PostSchema::replyParent.is_null())
+                    .filter(PostSchema::replyRoot.is_null());
+            }
+        }
+        if let Some(membership) = &query.membership {
+            let members = MembershipSchema::membership
+                .filter(MembershipSchema::list.eq(membership.list.clone()))
+                .filter(MembershipSchema::included.eq(true))
+                .select(MembershipSchema::did);
+            if membership.hashtags.is_empty() {
+                // No hashtags provided, include only posts where author is in the list
+                posts = posts.filter(PostSchema::author.eq_any(members));
+            } else {
+                let hashtag_patterns: Vec<String> = membership
+                    .hashtags
+                    .iter()
+                    .map(|hashtag| format!("%#{}%", hashtag))
+                    .collect();
+                posts = posts.filter(
+                    PostSchema::author
+                        .eq_any(members)
+                        .or(PostSchema::text.ilike(any(hashtag_patterns))),
+                );
+            }
+        }
+        Ok(posts.load(self)?)
+    }
+
+    fn feed_reposts(
+        &mut self,
+        list: &str,
+        limit: i64,
+        cursor: Option<&FeedCursor>,
+    ) -> StorageResult<Vec<Repost>> {
+        use crate::schema::membership::dsl as MembershipSchema;
+        use crate::schema::repost::dsl as RepostSchema;
+
+        let mut query = RepostSchema::repost
+            .inner_join(
+                MembershipSchema::membership.on(RepostSchema::author
+                    .eq(MembershipSchema::did)
+                    .and(MembershipSchema::list.eq(list.to_owned()))
+                    .and(MembershipSchema::included.eq(true))),
+            )
+            .limit(limit)
+            .select((
+                RepostSchema::uri,
+                RepostSchema::cid,
+                RepostSchema::author,
+                RepostSchema::subjectCid,
+                RepostSchema::subjectUri,
+                RepostSchema::createdAt,
+                RepostSchema::indexedAt,
+                RepostSchema::prev,
+                RepostSchema::sequence,
+            ))
+            .order((RepostSchema::createdAt.desc(), RepostSchema::cid.desc()))
+            .into_boxed();
+        if let Some(cursor) = cursor {
+            query = query.filter(
+                RepostSchema::createdAt
+                    .lt(cursor.created_at)
+                    .or(RepostSchema::createdAt
+                        .eq(cursor.created_at)
+                        .and(RepostSchema::cid.lt(cursor.cid.clone()))),
+            );
+        }
+        let reposts = query
+            .load::<(
+                String,
+                String,
+                String,
+                String,
+                String,
+                DateTime<Utc>,
+                DateTime<Utc>,
+                Option<String>,
+                Option<i64>,
+            )>(self)?
+            .into_iter()
+            .map(
+                |(
+                    uri,
+                    cid,
+                    author,
+                    subject_cid,
+                    subject_uri,
+                    created_at,
+                    indexed_at,
+                    prev,
+                    sequence,
+                )| {
+                    Repost {
+                        uri,
+                        cid,
+                        author,
+                        subject_cid,
+                        subject_uri,
+                        created_at,
+                        indexed_at,
+                        prev,
+                        sequence,
+                    }
+                },
+            )
+            .collect();
+        Ok(reposts)
+    }
+
+    fn trending_posts(&mut self, query: &TrendingQuery) -> StorageResult<Vec<Post>> {
+        let mut base_time_clause = "CURRENT_TIMESTAMP".to_string();
+        let mut cursor_filter = String::new();
+        if let Some(cursor) = &query.cursor {
+            // Format the timestamp in a SQL-friendly format.
+            let timestr = cursor
+                .created_at
+                .format("%Y-%m-%d %H:%M:%S%.3f")
+                .to_string();
+            base_time_clause = format!("'{}'", timestr);
+            // Build the cursor filter clause.
+            cursor_filter = format!(
+                " AND ((\"indexedAt\" < {base_time_clause}) OR (\"indexedAt\" = {base_time_clause} AND cid < '{cid}'))",
+                base_time_clause = base_time_clause,
+                cid = cursor.cid
+            );
+        }
+
+        // Build the media join clause.
+        let media_join = match query.media {
+            Some(TrendingMedia::OnlyImage) => "JOIN image i ON p.uri = i.\"postUri\"",
+            Some(TrendingMedia::OnlyVideo) => "JOIN video v ON p.uri = v.\"postUri\"",
+            None => "",
+        };
+
+        // Construct the final SQL query string.
+        // We add an extra CTE "filtered_posts" that discards rows where like_count is <= 1.
+        let query_str = format!(
+            "WITH recent_posts AS (
+                SELECT p.*
+                FROM post p
+                {media_join}
+                WHERE p.\"indexedAt\" >= ({base_time_clause}::timestamp - INTERVAL '1 days')
+                  AND COALESCE(array_length(p.labels, 1), 0) = 0
+            ), recent_likes AS (
+                SELECT \"subjectUri\", COUNT(*) AS like_count
+                FROM public.like
+                WHERE \"indexedAt\" >= ({base_time_clause}::timestamp - INTERVAL '12 hours')
+                GROUP BY \"subjectUri\"
+                HAVING COUNT(*) > 1
+            ), posts_with_likes AS (
+                SELECT p.*, COALESCE(l.like_count, 0) AS like_count
+                FROM recent_posts p
+                JOIN recent_likes l ON l.\"subjectUri\" = p.uri
+            ), ranked_posts AS (
+                SELECT *,
+                       PERCENT_RANK() OVER (ORDER BY like_count) AS percentile_rank
+                FROM posts_with_likes
+            )
+            SELECT
+                uri,
+                cid,
+                \"replyParent\",
+                \"replyRoot\",
+                \"indexedAt\",
+                prev,
+                \"sequence\",
+                text,
+                lang,
+                author,
+                \"externalUri\",
+                \"externalTitle\",
+                \"externalDescription\",
+                \"externalThumb\",
+                \"quoteCid\",
+                \"quoteUri\",
+                \"createdAt\",
+                labels,
+                \"replyRootAuthor\"
+            FROM ranked_posts
+            WHERE percentile_rank >= {random_percentile:.4}
+              {cursor_filter}
+            ORDER BY \"indexedAt\" DESC, cid DESC
+            LIMIT {limit_val};",
+            media_join = media_join,
+            base_time_clause = base_time_clause,
+            random_percentile = query.percentile,
+            cursor_filter = cursor_filter,
+            limit_val = query.limit
+        );
+
+        Ok(sql_query(query_str).load::<Post>(self)?)
+    }
+
+    fn update_cursor(&mut self, service_: &str, sequence: i64) -> StorageResult<()> {
+        use crate::schema::sub_state::dsl::*;
+
+        let update_state = (service.eq(service_), cursor.eq(&sequence));
+        diesel::insert_into(sub_state)
+            .values(&update_state)
+            .on_conflict(service)
+            .do_update()
+            .set(cursor.eq(&sequence))
+            .execute(self)?;
+        Ok(())
+    }
+
+    fn get_cursor(&mut self, service_: &str) -> StorageResult<Option<SubState>> {
+        use crate::schema::sub_state::dsl::*;
+
+        let mut result = sub_state
+            .filter(service.eq(service_))
+            .order(cursor.desc())
+            .limit(1)
+            .select(SubState::as_select())
+            .load(self)?;
+        Ok(result.pop())
+    }
+
+    fn add_visitor(
+        &mut self,
+        user: &str,
+        service: &str,
+        requested_feed: &str,
+        visited: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        use crate::schema::visitor::dsl::*;
+
+        let new_visitor = (
+            did.eq(user),
+            web.eq(service),
+            visited_at.eq(format!("{}", visited.format("%+"))),
+            feed.eq(requested_feed),
+        );
+        diesel::insert_into(visitor)
+            .values(&new_visitor)
+            .execute(self)?;
+        Ok(())
+    }
+
+    fn add_exposure(
+        &mut self,
+        user: &str,
+        requested_feed: &str,
+        experiment_name: &str,
+        variant_name: &str,
+        exposed: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        use crate::schema::experiment_exposure::dsl::*;
+
+        let new_exposure = (
+            did.eq(user),
+            feed.eq(requested_feed),
+            experiment.eq(experiment_name),
+            variant.eq(variant_name),
+            exposed_at.eq(format!("{}", exposed.format("%+"))),
+        );
+        diesel::insert_into(experiment_exposure)
+            .values(&new_exposure)
+            .execute(self)?;
+        Ok(())
+    }
+
+    fn is_banned_from_tv(&mut self, subject: &str) -> StorageResult<bool> {
+        use crate::schema::banned_from_tv::dsl::*;
+
+        let count: i64 = banned_from_tv
+            .filter(did.eq(subject))
+            .count()
+            .get_result(self)?;
+        Ok(count > 0)
+    }
+}
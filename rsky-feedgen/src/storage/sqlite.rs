@@ -0,0 +1,802 @@
+use super::{
+    Collection, FeedCursor, FeedStorage, MediaKind, PostQuery, StorageResult, TaskError,
+    ThreadFilter, TrendingMedia, TrendingQuery,
+};
+use crate::models::{Follow, Like, Media, Membership, Post, Repost, SubState};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use rusqlite::types::Type;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use std::sync::{Arc, Mutex};
+
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+const POST_COLUMNS: &str = "uri, cid, replyParent, replyRoot, indexedAt, prev, sequence, text, \
+    lang, author, externalUri, externalTitle, externalDescription, externalThumb, quoteCid, \
+    quoteUri, createdAt, labels, replyRootAuthor";
+
+/// A single SQLite connection shared by every request, standing in for the Postgres pools.
+#[derive(Clone)]
+pub struct SqliteDb {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteDb {
+    pub fn open(path: &str) -> rusqlite::Result<Self> {
+        Ok(Self {
+            conn: Arc::new(Mutex::new(Self::connect(path)?)),
+        })
+    }
+
+    /// Opens a standalone connection to `path`, creating the schema if it is missing.
+    pub fn connect(path: &str) -> rusqlite::Result<Connection> {
+        let conn = Connection::open(path.trim_start_matches("sqlite://"))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        init(&conn)?;
+        Ok(conn)
+    }
+
+    pub async fn run<F, T, E>(&self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut dyn FeedStorage) -> Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: From<TaskError> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        rocket::tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            f(&mut *conn)
+        })
+        .await
+        .unwrap_or_else(|error| Err(TaskError(error.to_string()).into()))
+    }
+}
+
+pub fn init(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "
+            CREATE TABLE IF NOT EXISTS post (
+                uri TEXT PRIMARY KEY,
+                cid TEXT NOT NULL,
+                replyParent TEXT,
+                replyRoot TEXT,
+                indexedAt TEXT NOT NULL,
+                prev TEXT,
+                sequence INTEGER,
+                text TEXT,
+                lang TEXT,
+                author TEXT NOT NULL,
+                externalUri TEXT,
+                externalTitle TEXT,
+                externalDescription TEXT,
+                externalThumb TEXT,
+                quoteCid TEXT,
+                quoteUri TEXT,
+                createdAt TEXT NOT NULL,
+                labels TEXT NOT NULL DEFAULT '[]',
+                replyRootAuthor TEXT
+            );
+            CREATE INDEX IF NOT EXISTS post_createdat_cid_idx ON post(createdAt DESC, cid DESC);
+            CREATE INDEX IF NOT EXISTS post_indexedat_idx ON post(indexedAt);
+            CREATE INDEX IF NOT EXISTS post_author_idx ON post(author);
+            CREATE TABLE IF NOT EXISTS image (
+                cid TEXT PRIMARY KEY,
+                alt TEXT,
+                postCid TEXT NOT NULL,
+                postUri TEXT NOT NULL,
+                createdAt TEXT NOT NULL,
+                indexedAt TEXT NOT NULL,
+                labels TEXT
+            );
+            CREATE INDEX IF NOT EXISTS image_posturi_idx ON image(postUri);
+            CREATE TABLE IF NOT EXISTS video (
+                cid TEXT PRIMARY KEY,
+                alt TEXT,
+                postCid TEXT NOT NULL,
+                postUri TEXT NOT NULL,
+                createdAt TEXT NOT NULL,
+                indexedAt TEXT NOT NULL,
+                labels TEXT
+            );
+            CREATE INDEX IF NOT EXISTS video_posturi_idx ON video(postUri);
+            CREATE TABLE IF NOT EXISTS \"like\" (
+                uri TEXT PRIMARY KEY,
+                cid TEXT NOT NULL,
+                author TEXT NOT NULL,
+                subjectCid TEXT NOT NULL,
+                subjectUri TEXT NOT NULL,
+                createdAt TEXT NOT NULL,
+                indexedAt TEXT NOT NULL,
+                prev TEXT,
+                sequence INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS like_indexedat_idx ON \"like\"(indexedAt, subjectUri);
+            CREATE TABLE IF NOT EXISTS repost (
+                uri TEXT PRIMARY KEY,
+                cid TEXT NOT NULL,
+                author TEXT NOT NULL,
+                subjectCid TEXT NOT NULL,
+                subjectUri TEXT NOT NULL,
+                createdAt TEXT NOT NULL,
+                indexedAt TEXT NOT NULL,
+                prev TEXT,
+                sequence INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS repost_author_createdat_idx
+                ON repost(author, createdAt DESC, cid DESC);
+            CREATE TABLE IF NOT EXISTS follow (
+                uri TEXT PRIMARY KEY,
+                cid TEXT NOT NULL,
+                author TEXT NOT NULL,
+                subject TEXT NOT NULL,
+                createdAt TEXT NOT NULL,
+                indexedAt TEXT NOT NULL,
+                prev TEXT,
+                sequence INTEGER
+            );
+            CREATE TABLE IF NOT EXISTS membership (
+                did TEXT NOT NULL,
+                included INTEGER NOT NULL,
+                excluded INTEGER NOT NULL,
+                list TEXT NOT NULL,
+                PRIMARY KEY (did, list)
+            );
+            CREATE TABLE IF NOT EXISTS sub_state (
+                service TEXT PRIMARY KEY,
+                cursor INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS visitor (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                did TEXT NOT NULL,
+                web TEXT NOT NULL,
+                visited_at TEXT NOT NULL,
+                feed TEXT
+            );
+            CREATE TABLE IF NOT EXISTS experiment_exposure (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                did TEXT NOT NULL,
+                feed TEXT NOT NULL,
+                experiment TEXT NOT NULL,
+                variant TEXT NOT NULL,
+                exposed_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS banned_from_tv (
+                did TEXT PRIMARY KEY,
+                reason TEXT,
+                createdAt TEXT,
+                tags TEXT
+            );
+        ",
+    )
+}
+
+/// Timestamps are stored as fixed-width RFC 3339 text so they sort and compare as strings.
+fn timestamp(datetime: &DateTime<Utc>) -> String {
+    datetime.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn timestamp_at(row: &Row, idx: usize) -> rusqlite::Result<DateTime<Utc>> {
+    let value: String = row.get(idx)?;
+    DateTime::parse_from_rfc3339(&value)
+        .map(|datetime| datetime.with_timezone(&Utc))
+        .map_err(|err| rusqlite::Error::FromSqlConversionFailure(idx, Type::Text, Box::new(err)))
+}
+
+fn post_from_row(row: &Row) -> rusqlite::Result<Post> {
+    let labels: String = row.get(17)?;
+    Ok(Post {
+        uri: row.get(0)?,
+        cid: row.get(1)?,
+        reply_parent: row.get(2)?,
+        reply_root: row.get(3)?,
+        indexed_at: timestamp_at(row, 4)?,
+        prev: row.get(5)?,
+        sequence: row.get(6)?,
+        text: row.get(7)?,
+        lang: row.get(8)?,
+        author: row.get(9)?,
+        external_uri: row.get(10)?,
+        external_title: row.get(11)?,
+        external_description: row.get(12)?,
+        external_thumb: row.get(13)?,
+        quote_cid: row.get(14)?,
+        quote_uri: row.get(15)?,
+        created_at: timestamp_at(row, 16)?,
+        labels: serde_json::from_str(&labels).map_err(|err| {
+            rusqlite::Error::FromSqlConversionFailure(17, Type::Text, Box::new(err))
+        })?,
+        reply_root_author: row.get(18)?,
+    })
+}
+
+fn placeholders(count: usize) -> String {
+    vec!["?"; count].join(", ")
+}
+
+impl FeedStorage for Connection {
+    fn memberships(&mut self, dids: &[String]) -> StorageResult<Vec<Membership>> {
+        let mut stmt = self.prepare(&format!(
+            "SELECT did, included, excluded, list FROM membership WHERE did IN ({})",
+            placeholders(dids.len())
+        ))?;
+        let members = stmt
+            .query_map(params_from_iter(dids), |row| {
+                Ok(Membership {
+                    did: row.get(0)?,
+                    included: row.get(1)?,
+                    excluded: row.get(2)?,
+                    list: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(members)
+    }
+
+    fn add_members(&mut self, members: &[Membership]) -> StorageResult<()> {
+        let tx = self.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR IGNORE INTO membership (did, included, excluded, list)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for member in members {
+                stmt.execute(params![
+                    member.did,
+                    member.included,
+                    member.excluded,
+                    member.list
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn remove_members(&mut self, dids: &[String]) -> StorageResult<()> {
+        self.execute(
+            &format!(
+                "DELETE FROM membership WHERE did IN ({})",
+                placeholders(dids.len())
+            ),
+            params_from_iter(dids),
+        )?;
+        Ok(())
+    }
+
+    fn insert_posts(&mut self, posts: &[Post]) -> StorageResult<()> {
+        let tx = self.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(&format!(
+                "INSERT OR IGNORE INTO post ({POST_COLUMNS})
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                         ?17, ?18, ?19)"
+            ))?;
+            for post in posts {
+                stmt.execute(params![
+                    post.uri,
+                    post.cid,
+                    post.reply_parent,
+                    post.reply_root,
+                    timestamp(&post.indexed_at),
+                    post.prev,
+                    post.sequence,
+                    post.text,
+                    post.lang,
+                    post.author,
+                    post.external_uri,
+                    post.external_title,
+                    post.external_description,
+                    post.external_thumb,
+                    post.quote_cid,
+                    post.quote_uri,
+                    timestamp(&post.created_at),
+                    serde_json::to_string(&post.labels)?,
+                    post.reply_root_author,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn insert_media(&mut self, kind: MediaKind, media: &[Media]) -> StorageResult<()> {
+        let table = match kind {
+            MediaKind::Image => "image",
+            MediaKind::Video => "video",
+        };
+        let tx = self.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(&format!(
+                "INSERT OR IGNORE INTO {table}
+                    (cid, alt, postCid, postUri, createdAt, indexedAt, labels)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+            ))?;
+            for item in media {
+                stmt.execute(params![
+                    item.cid,
+                    item.alt,
+                    item.post_cid,
+                    item.post_uri,
+                    timestamp(&item.created_at),
+                    timestamp(&item.indexed_at),
+                    serde_json::to_string(&item.labels)?,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn insert_likes(&mut self, likes: &[Like]) -> StorageResult<()> {
+        let tx = self.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR IGNORE INTO \"like\"
+                    (uri, cid, author, subjectCid, subjectUri, createdAt, indexedAt, prev, sequence)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            for like in likes {
+                stmt.execute(params![
+                    like.uri,
+                    like.cid,
+                    like.author,
+                    like.subject_cid,
+                    like.subject_uri,
+                    timestamp(&like.created_at),
+                    timestamp(&like.indexed_at),
+                    like.prev,
+                    like.sequence,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn insert_reposts(&mut self, reposts: &[Repost]) -> StorageResult<()> {
+        let tx = self.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR IGNORE INTO repost
+                    (uri, cid, author, subjectCid, subjectUri, createdAt, indexedAt, prev, sequence)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            for repost in reposts {
+                stmt.execute(params![
+                    repost.uri,
+                    repost.cid,
+                    repost.author,
+                    repost.subject_cid,
+                    repost.subject_uri,
+                    timestamp(&repost.created_at),
+                    timestamp(&repost.indexed_at),
+                    repost.prev,
+                    repost.sequence,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn insert_follows(&mut self, follows: &[Follow]) -> StorageResult<()> {
+        let tx = self.transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR IGNORE INTO follow
+                    (uri, cid, author, subject, createdAt, indexedAt, prev, sequence)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )?;
+            for follow in follows {
+                stmt.execute(params![
+                    follow.uri,
+                    follow.cid,
+                    follow.author,
+                    follow.subject,
+                    follow.created_at,
+                    follow.indexed_at,
+                    follow.prev,
+                    follow.sequence,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn delete_records(&mut self, collection: Collection, uris: &[String]) -> StorageResult<()> {
+        let table = match collection {
+            Collection::Posts => "post",
+            Collection::Likes => "\"like\"",
+            Collection::Reposts => "repost",
+            Collection::Follows => "follow",
+        };
+        self.execute(
+            &format!(
+                "DELETE FROM {table} WHERE uri IN ({})",
+                placeholders(uris.len())
+            ),
+            params_from_iter(uris),
+        )?;
+        Ok(())
+    }
+
+    fn add_post_label(&mut self, uri: &str, label: &str) -> StorageResult<usize> {
+        Ok(self.execute(
+            "UPDATE post SET labels = json_insert(labels, '$[#]', ?1) WHERE uri = ?2",
+            params![label, uri],
+        )?)
+    }
+
+    fn feed_posts(&mut self, query: &PostQuery) -> StorageResult<Vec<Post>> {
+        let mut sql = format!("SELECT {POST_COLUMNS} FROM post WHERE 1 = 1");
+        let mut binds = Vec::new();
+
+        if let Some(lang) = &query.lang {
+            sql.push_str(" AND lang LIKE ?");
+            binds.push(format!("%{}%", lang));
+        }
+        if let Some(cursor) = &query.cursor {
+            sql.push_str(" AND (createdAt < ? OR (createdAt = ? AND cid < ?))");
+            binds.push(timestamp(&cursor.created_at));
+            binds.push(timestamp(&cursor.created_at));
+            binds.push(cursor.cid.clone());
+        }
+        if query.unlabeled {
+            sql.push_str(" AND json_array_length(labels) = 0");
+        }
+        if query.without_media {
+            sql.push_str(
+                " AND NOT EXISTS (SELECT 1 FROM image WHERE image.postUri = post.uri)
+                  AND NOT EXISTS (SELECT 1 FROM video WHERE video.postUri = post.uri)",
+            );
+        }
+        match (query.threads, &query.membership) {
+            (ThreadFilter::All, _) => (),
+            (ThreadFilter::MemberThreads, Some(membership)) => {
+                sql.push_str(
+                    " AND (replyRoot IS NULL OR EXISTS (SELECT 1 FROM membership m
+                      WHERE m.did = post.replyRootAuthor AND m.included AND m.list = ?))",
+                );
+                binds.push(membership.list.clone());
+            }
+            (ThreadFilter::TopLevel, _) | (ThreadFilter::MemberThreads, None) => {
+                sql.push_str(" AND replyParent IS NULL AND replyRoot IS NULL");
+            }
+        }
+        if let Some(membership) = &query.membership {
+            sql.push_str(
+                " AND (author IN (SELECT did FROM membership WHERE list = ? AND included)",
+            );
+            binds.push(membership.list.clone());
+            // LIKE is case-insensitive for ASCII, matching the ILIKE used on Postgres
+            for hashtag in &membership.hashtags {
+                sql.push_str(" OR text LIKE ?");
+                binds.push(format!("%#{}%", hashtag));
+            }
+            sql.push(')');
+        }
+        sql.push_str(&format!(
+            " ORDER BY createdAt DESC, cid DESC LIMIT {}",
+            query.limit
+        ));
+
+        let mut stmt = self.prepare(&sql)?;
+        let posts = stmt
+            .query_map(params_from_iter(binds), post_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(posts)
+    }
+
+    fn feed_reposts(
+        &mut self,
+        list: &str,
+        limit: i64,
+        cursor: Option<&FeedCursor>,
+    ) -> StorageResult<Vec<Repost>> {
+        let mut stmt = self.prepare_cached(
+            "SELECT r.uri, r.cid, r.author, r.subjectCid, r.subjectUri, r.createdAt, r.indexedAt,
+                    r.prev, r.sequence
+             FROM repost r
+             JOIN membership m ON m.did = r.author AND m.list = ?1 AND m.included
+             WHERE ?2 IS NULL OR r.createdAt < ?2 OR (r.createdAt = ?2 AND r.cid < ?3)
+             ORDER BY r.createdAt DESC, r.cid DESC
+             LIMIT ?4",
+        )?;
+        let reposts = stmt
+            .query_map(
+                params![
+                    list,
+                    cursor.map(|cursor| timestamp(&cursor.created_at)),
+                    cursor.map(|cursor| cursor.cid.as_str()),
+                    limit
+                ],
+                |row| {
+                    Ok(Repost {
+                        uri: row.get(0)?,
+                        cid: row.get(1)?,
+                        author: row.get(2)?,
+                        subject_cid: row.get(3)?,
+                        subject_uri: row.get(4)?,
+                        created_at: timestamp_at(row, 5)?,
+                        indexed_at: timestamp_at(row, 6)?,
+                        prev: row.get(7)?,
+                        sequence: row.get(8)?,
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(reposts)
+    }
+
+    fn trending_posts(&mut self, query: &TrendingQuery) -> StorageResult<Vec<Post>> {
+        let base_time = query
+            .cursor
+            .as_ref()
+            .map_or_else(Utc::now, |cursor| cursor.created_at);
+        let media_join = match query.media {
+            Some(TrendingMedia::OnlyImage) => "JOIN image i ON p.uri = i.postUri",
+            Some(TrendingMedia::OnlyVideo) => "JOIN video v ON p.uri = v.postUri",
+            None => "",
+        };
+        let mut stmt = self.prepare(&format!(
+            "WITH recent_posts AS (
+                SELECT p.*
+                FROM post p
+                {media_join}
+                WHERE p.indexedAt >= ?1
+                  AND json_array_length(p.labels) = 0
+            ), recent_likes AS (
+                SELECT subjectUri, COUNT(*) AS like_count
+                FROM \"like\"
+                WHERE indexedAt >= ?2
+                GROUP BY subjectUri
+                HAVING COUNT(*) > 1
+            ), posts_with_likes AS (
+                SELECT p.*, l.like_count
+                FROM recent_posts p
+                JOIN recent_likes l ON l.subjectUri = p.uri
+            ), ranked_posts AS (
+                SELECT *,
+                       PERCENT_RANK() OVER (ORDER BY like_count) AS percentile_rank
+                FROM posts_with_likes
+            )
+            SELECT {POST_COLUMNS}
+            FROM ranked_posts
+            WHERE percentile_rank >= ?3
+              AND (?4 IS NULL OR indexedAt < ?4 OR (indexedAt = ?4 AND cid < ?5))
+            ORDER BY indexedAt DESC, cid DESC
+            LIMIT ?6"
+        ))?;
+        let posts = stmt
+            .query_map(
+                params![
+                    timestamp(&(base_time - Duration::days(1))),
+                    timestamp(&(base_time - Duration::hours(12))),
+                    query.percentile,
+                    query
+                        .cursor
+                        .as_ref()
+                        .map(|cursor| timestamp(&cursor.created_at)),
+                    query.cursor.as_ref().map(|cursor| cursor.cid.as_str()),
+                    query.limit
+                ],
+                post_from_row,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(posts)
+    }
+
+    fn update_cursor(&mut self, service: &str, sequence: i64) -> StorageResult<()> {
+        self.execute(
+            "INSERT INTO sub_state (service, cursor) VALUES (?1, ?2)
+             ON CONFLICT (service) DO UPDATE SET cursor = excluded.cursor",
+            params![service, sequence],
+        )?;
+        Ok(())
+    }
+
+    fn get_cursor(&mut self, service: &str) -> StorageResult<Option<SubState>> {
+        Ok(self
+            .query_row(
+                "SELECT service, cursor FROM sub_state WHERE service = ?1",
+                params![service],
+                |row| {
+                    Ok(SubState {
+                        service: row.get(0)?,
+                        cursor: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    fn add_visitor(
+        &mut self,
+        did: &str,
+        web: &str,
+        feed: &str,
+        visited_at: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        self.execute(
+            "INSERT INTO visitor (did, web, visited_at, feed) VALUES (?1, ?2, ?3, ?4)",
+            params![did, web, format!("{}", visited_at.format("%+")), feed],
+        )?;
+        Ok(())
+    }
+
+    fn add_exposure(
+        &mut self,
+        did: &str,
+        feed: &str,
+        experiment: &str,
+        variant: &str,
+        exposed_at: DateTime<Utc>,
+    ) -> StorageResult<()> {
+        self.execute(
+            "INSERT INTO experiment_exposure (did, feed, experiment, variant, exposed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                did,
+                feed,
+                experiment,
+                variant,
+                format!("{}", exposed_at.format("%+"))
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn is_banned_from_tv(&mut self, did: &str) -> StorageResult<bool> {
+        let count: i64 = self.query_row(
+            "SELECT COUNT(*) FROM banned_from_tv WHERE did = ?1",
+            params![did],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MembershipFilter;
+
+    fn storage() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init(&conn).unwrap();
+        conn
+    }
+
+    fn post(uri: &str, author: &str, created_at: &str, text: &str) -> Post {
+        let created_at = created_at.parse::<DateTime<Utc>>().unwrap();
+        Post {
+            uri: uri.to_string(),
+            cid: format!("cid-{uri}"),
+            indexed_at: created_at,
+            text: Some(text.to_string()),
+            author: author.to_string(),
+            created_at,
+            ..Default::default()
+        }
+    }
+
+    fn member(did: &str, list: &str) -> Membership {
+        Membership {
+            did: did.to_string(),
+            included: true,
+            excluded: false,
+            list: list.to_string(),
+        }
+    }
+
+    fn membership_query(cursor: Option<FeedCursor>) -> PostQuery {
+        PostQuery {
+            limit: 2,
+            cursor,
+            lang: None,
+            threads: ThreadFilter::TopLevel,
+            membership: Some(MembershipFilter {
+                list: "blacksky-travel".to_string(),
+                hashtags: vec!["travel".to_string()],
+            }),
+            unlabeled: false,
+            without_media: false,
+        }
+    }
+
+    #[test]
+    fn test_feed_posts_pages_members_and_hashtags() {
+        let mut conn = storage();
+        conn.add_members(&[member("did:plc:alice", "blacksky-travel")])
+            .unwrap();
+        let mut reply = post(
+            "at://alice/post/4",
+            "did:plc:alice",
+            "2025-01-04T00:00:00Z",
+            "re",
+        );
+        reply.reply_root = Some("at://carol/post/0".to_string());
+        reply.reply_parent = reply.reply_root.clone();
+        conn.insert_posts(&[
+            post(
+                "at://alice/post/1",
+                "did:plc:alice",
+                "2025-01-01T00:00:00Z",
+                "hi",
+            ),
+            post(
+                "at://bob/post/2",
+                "did:plc:bob",
+                "2025-01-02T00:00:00Z",
+                "#Travel",
+            ),
+            post(
+                "at://bob/post/3",
+                "did:plc:bob",
+                "2025-01-03T00:00:00Z",
+                "nope",
+            ),
+            reply,
+        ])
+        .unwrap();
+
+        let first = conn.feed_posts(&membership_query(None)).unwrap();
+        let uris: Vec<&str> = first.iter().map(|post| post.uri.as_str()).collect();
+        assert_eq!(uris, vec!["at://bob/post/2", "at://alice/post/1"]);
+
+        let last = first.last().unwrap();
+        let cursor = FeedCursor {
+            created_at: last.created_at,
+            cid: last.cid.clone(),
+        };
+        assert!(conn
+            .feed_posts(&membership_query(Some(cursor)))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_add_post_label_hides_post_from_unlabeled_feeds() {
+        let mut conn = storage();
+        conn.insert_posts(&[post(
+            "at://alice/post/1",
+            "did:plc:alice",
+            "2025-01-01T00:00:00Z",
+            "hi",
+        )])
+        .unwrap();
+        assert_eq!(conn.add_post_label("at://alice/post/1", "porn").unwrap(), 1);
+
+        let mut query = membership_query(None);
+        query.membership = None;
+        let posts = conn.feed_posts(&query).unwrap();
+        assert_eq!(posts[0].labels, vec![Some("porn".to_string())]);
+        query.unlabeled = true;
+        assert!(conn.feed_posts(&query).unwrap().is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn test_run_returns_error_when_task_panics() {
+        let db = SqliteDb::open(":memory:").unwrap();
+        let res: Result<(), String> = db.run(|_| panic!("boom")).await;
+        assert!(res.unwrap_err().contains("storage task failed"));
+        // the connection is still usable afterwards
+        let res: StorageResult<Option<SubState>> = db.run(|conn| conn.get_cursor("none")).await;
+        assert!(res.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_update_cursor_upserts() {
+        let mut conn = storage();
+        conn.update_cursor("bsky.network", 1).unwrap();
+        conn.update_cursor("bsky.network", 2).unwrap();
+        assert_eq!(
+            conn.get_cursor("bsky.network")
+                .unwrap()
+                .map(|state| state.cursor),
+            Some(2)
+        );
+        assert!(conn.get_cursor("other").unwrap().is_none());
+    }
+}
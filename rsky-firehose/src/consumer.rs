@@ -0,0 +1,326 @@
+use crate::firehose;
+use crate::verify::CommitVerifier;
+use anyhow::Result;
+use futures::StreamExt as _;
+use rsky_lexicon::com::atproto::sync::SubscribeRepos;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::protocol::Message;
+use url::Url;
+
+pub const SUBSCRIBE_REPOS_PATH: &str = "/xrpc/com.atproto.sync.subscribeRepos";
+
+/// Persists the sequence number of the last handled event so a consumer
+/// can resume from where it left off after a restart or reconnect.
+pub trait CursorStore: Send + Sync {
+    fn load(&self) -> impl Future<Output = Result<Option<i64>>> + Send;
+    fn store(&self, seq: i64) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Keeps the cursor in memory only; reconnects resume, restarts do not.
+#[derive(Debug)]
+pub struct MemoryCursorStore(AtomicI64);
+
+impl MemoryCursorStore {
+    pub fn new(cursor: Option<i64>) -> Self {
+        Self(AtomicI64::new(cursor.unwrap_or(-1)))
+    }
+}
+
+impl Default for MemoryCursorStore {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl CursorStore for MemoryCursorStore {
+    async fn load(&self) -> Result<Option<i64>> {
+        let seq = self.0.load(Ordering::Relaxed);
+        Ok((seq >= 0).then_some(seq))
+    }
+
+    async fn store(&self, seq: i64) -> Result<()> {
+        self.0.store(seq, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Keeps the cursor as a plain decimal number in a file on disk.
+#[derive(Debug, Clone)]
+pub struct FileCursorStore {
+    path: PathBuf,
+}
+
+impl FileCursorStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl CursorStore for FileCursorStore {
+    async fn load(&self) -> Result<Option<i64>> {
+        match tokio::fs::read_to_string(&self.path).await {
+            Ok(contents) => Ok(Some(contents.trim().parse()?)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn store(&self, seq: i64) -> Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, seq.to_string()).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+/// A `com.atproto.sync.subscribeRepos` client that decodes frames into the
+/// `SubscribeRepos*` lexicon types, reconnects with backoff and persists its
+/// cursor through a [`CursorStore`].
+///
+/// ```no_run
+/// # use rsky_firehose::consumer::{FileCursorStore, FirehoseConsumerBuilder};
+/// # async fn example() -> anyhow::Result<()> {
+/// let consumer = FirehoseConsumerBuilder::default()
+///     .relay_url("wss://bsky.network")
+///     .cursor_store(FileCursorStore::new("cursor.txt"))
+///     .collection("app.bsky.feed.post")
+///     .build()?;
+/// consumer
+///     .run(|event| async move {
+///         println!("{event:?}");
+///         Ok(())
+///     })
+///     .await
+/// # }
+/// ```
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+pub struct FirehoseConsumer<C: CursorStore> {
+    /// Base URL of the relay or PDS, e.g. `wss://bsky.network`.
+    #[builder(setter(into), default = "\"wss://bsky.network\".to_string()")]
+    relay_url: String,
+    cursor_store: C,
+    /// Only commit ops in these collections are yielded. Commits left without
+    /// ops are dropped. An empty list disables filtering.
+    #[builder(setter(each(name = "collection", into)), default)]
+    collections: Vec<String>,
+    /// Persist the cursor after this many handled events.
+    #[builder(default = "20")]
+    cursor_interval: u64,
+    #[builder(default = "Duration::from_millis(500)")]
+    min_backoff: Duration,
+    #[builder(default = "Duration::from_secs(60)")]
+    max_backoff: Duration,
+    /// Verify commit signatures and MST proofs before yielding `#commit`
+    /// events. Commits that fail verification are dropped.
+    #[builder(setter(strip_option), default)]
+    verifier: Option<CommitVerifier>,
+}
+
+impl<C: CursorStore> FirehoseConsumer<C> {
+    /// Consumes the firehose forever, calling `handler` for every decoded event.
+    /// Returns only if the handler or the cursor store fails.
+    pub async fn run<F, Fut>(mut self, mut handler: F) -> Result<()>
+    where
+        F: FnMut(SubscribeRepos) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let mut backoff = self.min_backoff;
+        loop {
+            let url = self.websocket_url(self.cursor_store.load().await?)?;
+            let mut socket = match connect_async(&url).await {
+                Ok((socket, _response)) => socket,
+                Err(error) => {
+                    eprintln!("@LOG: Error connecting to {url}. Waiting to reconnect: {error:?}");
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(self.max_backoff);
+                    continue;
+                }
+            };
+            println!("@LOG: Connected to {url}");
+            backoff = self.min_backoff;
+
+            let mut last_seq = None;
+            let mut pending = 0u64;
+            while let Some(msg_result) = socket.next().await {
+                let message = match msg_result {
+                    Ok(Message::Binary(message)) => message,
+                    Ok(Message::Close(_)) => {
+                        println!("@LOG: WebSocket connection closed by server.");
+                        break;
+                    }
+                    Ok(_) => continue,
+                    Err(error) => {
+                        eprintln!("@LOG: WebSocket error: {error}");
+                        break;
+                    }
+                };
+                let Some(event) = self.decode(&message) else {
+                    continue;
+                };
+                let seq = seq(&event);
+                if let Some(event) = self.filter(event) {
+                    if self.verify(&event).await {
+                        handler(event).await?;
+                    }
+                }
+                last_seq = Some(seq);
+                pending += 1;
+                if pending >= self.cursor_interval {
+                    self.cursor_store.store(seq).await?;
+                    pending = 0;
+                }
+            }
+            if let Some(seq) = last_seq {
+                self.cursor_store.store(seq).await?;
+            }
+
+            tokio::time::sleep(self.min_backoff).await;
+        }
+    }
+
+    fn websocket_url(&self, cursor: Option<i64>) -> Result<Url> {
+        let mut url = Url::parse(self.relay_url.trim_end_matches('/'))?;
+        url.set_path(SUBSCRIBE_REPOS_PATH);
+        {
+            let mut query = url.query_pairs_mut();
+            query.clear();
+            if let Some(cursor) = cursor {
+                query.append_pair("cursor", &cursor.to_string());
+            }
+        }
+        if url.query() == Some("") {
+            url.set_query(None);
+        }
+        Ok(url)
+    }
+
+    fn decode(&self, message: &[u8]) -> Option<SubscribeRepos> {
+        match firehose::read(message) {
+            Ok((_header, body)) => Some(body),
+            Err(error) => {
+                eprintln!("@LOG: Error unwrapping message and header: {error}");
+                None
+            }
+        }
+    }
+
+    async fn verify(&mut self, event: &SubscribeRepos) -> bool {
+        let (Some(verifier), SubscribeRepos::Commit(commit)) = (self.verifier.as_mut(), event)
+        else {
+            return true;
+        };
+        match verifier.verify(commit).await {
+            Ok(()) => true,
+            Err(error) => {
+                eprintln!(
+                    "@LOG: Dropping unverified commit from {} (seq {}): {error}",
+                    commit.repo, commit.seq
+                );
+                false
+            }
+        }
+    }
+
+    fn filter(&self, event: SubscribeRepos) -> Option<SubscribeRepos> {
+        match event {
+            SubscribeRepos::Commit(mut commit) if !self.collections.is_empty() => {
+                commit.ops.retain(|op| {
+                    let collection = op.path.split('/').next().unwrap_or_default();
+                    self.collections.iter().any(|c| c == collection)
+                });
+                (!commit.ops.is_empty()).then_some(SubscribeRepos::Commit(commit))
+            }
+            event => Some(event),
+        }
+    }
+}
+
+fn seq(event: &SubscribeRepos) -> i64 {
+    match event {
+        SubscribeRepos::Commit(commit) => commit.seq,
+        SubscribeRepos::Identity(identity) => identity.seq,
+        SubscribeRepos::Account(account) => account.seq,
+        SubscribeRepos::Handle(handle) => handle.seq,
+        SubscribeRepos::Tombstone(tombstone) => tombstone.seq,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lexicon_cid::Cid;
+    use rsky_lexicon::com::atproto::sync::{SubscribeReposCommit, SubscribeReposCommitOperation};
+    use std::str::FromStr;
+
+    fn consumer() -> FirehoseConsumer<MemoryCursorStore> {
+        FirehoseConsumerBuilder::default()
+            .relay_url("wss://bsky.network/")
+            .cursor_store(MemoryCursorStore::default())
+            .collection("app.bsky.feed.post")
+            .build()
+            .unwrap()
+    }
+
+    fn op(path: &str) -> SubscribeReposCommitOperation {
+        SubscribeReposCommitOperation {
+            path: path.to_string(),
+            action: "create".to_string(),
+            cid: None,
+        }
+    }
+
+    fn commit(ops: Vec<SubscribeReposCommitOperation>) -> SubscribeRepos {
+        SubscribeRepos::Commit(SubscribeReposCommit {
+            seq: 1,
+            time: chrono::Utc::now(),
+            rebase: false,
+            too_big: false,
+            repo: "did:plc:abc".to_string(),
+            commit: Cid::from_str("bafyreigw5ufnkavdzcczl2dusa3bcnkckhi4tscp6qsrsmg76s3ckseney")
+                .unwrap(),
+            prev: None,
+            rev: "3lauicnwejh2f".to_string(),
+            since: None,
+            blocks: Vec::new(),
+            ops,
+            blobs: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_websocket_url() {
+        let consumer = consumer();
+        assert_eq!(
+            consumer.websocket_url(None).unwrap().as_str(),
+            "wss://bsky.network/xrpc/com.atproto.sync.subscribeRepos"
+        );
+        assert_eq!(
+            consumer.websocket_url(Some(42)).unwrap().as_str(),
+            "wss://bsky.network/xrpc/com.atproto.sync.subscribeRepos?cursor=42"
+        );
+    }
+
+    #[test]
+    fn test_filter_collections() {
+        let consumer = consumer();
+        let filtered = consumer.filter(commit(vec![
+            op("app.bsky.feed.post/3lauicnw5op2f"),
+            op("app.bsky.feed.like/3lauicnw5op2g"),
+        ]));
+        match filtered {
+            Some(SubscribeRepos::Commit(commit)) => {
+                assert_eq!(commit.ops.len(), 1);
+                assert_eq!(commit.ops[0].path, "app.bsky.feed.post/3lauicnw5op2f");
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+        assert!(consumer
+            .filter(commit(vec![op("app.bsky.graph.follow/3lauicnw5op2f")]))
+            .is_none());
+    }
+}
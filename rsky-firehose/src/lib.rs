@@ -1,9 +1,13 @@
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
+extern crate derive_builder;
 
 extern crate serde;
 extern crate serde_json;
 
 pub mod car;
+pub mod consumer;
 pub mod firehose;
 pub mod models;
+pub mod verify;
@@ -3,8 +3,9 @@
 use dotenvy::dotenv;
 use futures::StreamExt as _;
 use lexicon_cid::Cid;
+use rsky_firehose::consumer::{CursorStore, FirehoseConsumerBuilder, MemoryCursorStore};
 use rsky_lexicon::app::bsky::feed::like::Like;
-use rsky_lexicon::app::bsky::feed::Post;
+use rsky_lexicon::app::bsky::feed::{Post, Repost};
 use rsky_lexicon::app::bsky::graph::follow::Follow;
 use rsky_lexicon::com::atproto::sync::SubscribeRepos;
 use serde::Deserialize;
@@ -27,6 +28,8 @@ enum Lexicon {
     AppBskyFeedPost(Post),
     #[serde(rename(deserialize = "app.bsky.feed.like"))]
     AppBskyFeedLike(Like),
+    #[serde(rename(deserialize = "app.bsky.feed.repost"))]
+    AppBskyFeedRepost(Repost),
     #[serde(rename(deserialize = "app.bsky.graph.follow"))]
     AppBskyFeedFollow(Follow),
 }
@@ -89,189 +92,200 @@ async fn update_cursor(
     Ok(())
 }
 
-async fn process(message: Vec<u8>, client: &reqwest::Client) {
+async fn process(body: SubscribeRepos, client: &reqwest::Client) {
     let default_queue_path =
         env::var("FEEDGEN_QUEUE_ENDPOINT").unwrap_or("https://[::1]:8081".into());
-    let subscriber_base_path =
-        env::var("FEEDGEN_SUBSCRIPTION_PATH").unwrap_or("wss://bsky.network".into());
 
-    match rsky_firehose::firehose::read(&message) {
-        Ok((_header, body)) => {
-            let mut posts_to_delete = Vec::new();
-            let mut posts_to_create = Vec::new();
-            let mut likes_to_delete = Vec::new();
-            let mut likes_to_create = Vec::new();
-            let mut follows_to_delete = Vec::new();
-            let mut follows_to_create = Vec::new();
+    let mut posts_to_delete = Vec::new();
+    let mut posts_to_create = Vec::new();
+    let mut likes_to_delete = Vec::new();
+    let mut likes_to_create = Vec::new();
+    let mut reposts_to_delete = Vec::new();
+    let mut reposts_to_create = Vec::new();
+    let mut follows_to_delete = Vec::new();
+    let mut follows_to_create = Vec::new();
 
-            match body {
-                SubscribeRepos::Commit(commit) => {
-                    if commit.ops.is_empty() {
-                        println!("Operations empty.");
-                    }
-                    if commit.too_big {
-                        println!("Too big.");
-                    }
-                    // update stored cursor every 20 events or so
-                    if (&commit.seq).rem_euclid(20) == 0 {
-                        let cursor_endpoint = format!("{}/cursor", default_queue_path);
-                        let resp = update_cursor(
-                            cursor_endpoint,
-                            subscriber_base_path,
-                            &commit.seq,
-                            client,
-                        )
-                        .await;
-                        match resp {
-                            Ok(()) => (),
-                            Err(error) => eprintln!("@LOG: Failed to update cursor: {error:?}"),
-                        };
-                    }
-                    commit.ops
-                        .into_iter()
-                        .filter(|operation|
-                        operation.path.starts_with("app.bsky.feed.post/") ||
-                            operation.path.starts_with("app.bsky.feed.like/") ||
-                            operation.path.starts_with("app.bsky.graph.follow/"))
-                        .map(|operation| {
-                            let uri = format!("at://{}/{}",commit.repo,operation.path);
-                            match operation.action.as_str() {
-                                "update" => {},
-                                "create" => {
-                                    if let Some(cid) = operation.cid {
-                                        let mut car_reader = Cursor::new(&commit.blocks);
-                                        let _car_header = rsky_firehose::car::read_header(&mut car_reader).unwrap();
-                                        let car_blocks = rsky_firehose::car::read_blocks(&mut car_reader).unwrap();
+    match body {
+        SubscribeRepos::Commit(commit) => {
+            if commit.ops.is_empty() {
+                println!("Operations empty.");
+            }
+            if commit.too_big {
+                println!("Too big.");
+            }
+            commit.ops
+                .into_iter()
+                .filter(|operation|
+                operation.path.starts_with("app.bsky.feed.post/") ||
+                    operation.path.starts_with("app.bsky.feed.like/") ||
+                    operation.path.starts_with("app.bsky.feed.repost/") ||
+                    operation.path.starts_with("app.bsky.graph.follow/"))
+                .map(|operation| {
+                    let uri = format!("at://{}/{}",commit.repo,operation.path);
+                    match operation.action.as_str() {
+                        "update" => {},
+                        "create" => {
+                            if let Some(cid) = operation.cid {
+                                let mut car_reader = Cursor::new(&commit.blocks);
+                                let _car_header = rsky_firehose::car::read_header(&mut car_reader).unwrap();
+                                let car_blocks = rsky_firehose::car::read_blocks(&mut car_reader).unwrap();
 
-                                        let record_reader = Cursor::new(car_blocks.get(&cid).unwrap());
-                                        match serde_cbor::from_reader(record_reader) {
-                                            Ok(Lexicon::AppBskyFeedPost(r)) => {
-                                                let post: Post = r;
-                                                let mut create = rsky_firehose::models::CreateOp {
-                                                    uri: uri.to_owned(),
-                                                    cid: cid.to_string(),
-                                                    sequence: commit.seq,
-                                                    prev: None,
-                                                    author: commit.repo.to_owned(),
-                                                    record: post
-                                                };
-                                                if let Some(ref prev) = commit.prev {
-                                                    create.prev = Some(prev.to_string());
-                                                }
-                                                posts_to_create.push(create);
-                                            },
-                                            Ok(Lexicon::AppBskyFeedLike(r)) => {
-                                                let like: Like = r;
-                                                let mut create = rsky_firehose::models::CreateOp {
-                                                    uri: uri.to_owned(),
-                                                    cid: cid.to_string(),
-                                                    sequence: commit.seq,
-                                                    prev: None,
-                                                    author: commit.repo.to_owned(),
-                                                    record: like
-                                                };
-                                                if let Some(ref prev) = commit.prev {
-                                                    create.prev = Some(prev.to_string());
-                                                }
-                                                likes_to_create.push(create);
-                                            },
-                                            Ok(Lexicon::AppBskyFeedFollow(r)) => {
-                                                let follow: Follow = r;
-                                                let mut create = rsky_firehose::models::CreateOp {
-                                                    uri: uri.to_owned(),
-                                                    cid: cid.to_string(),
-                                                    sequence: commit.seq,
-                                                    prev: None,
-                                                    author: commit.repo.to_owned(),
-                                                    record: follow
-                                                };
-                                                if let Some(ref prev) = commit.prev {
-                                                    create.prev = Some(prev.to_string());
-                                                }
-                                                follows_to_create.push(create);
-                                            },
-                                            Err(error) => {
-                                                eprintln!("@LOG: Failed to deserialize record: {:?}. Received error {:?}. Sequence {:?}", uri, error, commit.seq);
-                                            }
+                                let record_reader = Cursor::new(car_blocks.get(&cid).unwrap());
+                                match serde_cbor::from_reader(record_reader) {
+                                    Ok(Lexicon::AppBskyFeedPost(r)) => {
+                                        let post: Post = r;
+                                        let mut create = rsky_firehose::models::CreateOp {
+                                            uri: uri.to_owned(),
+                                            cid: cid.to_string(),
+                                            sequence: commit.seq,
+                                            prev: None,
+                                            author: commit.repo.to_owned(),
+                                            record: post
+                                        };
+                                        if let Some(ref prev) = commit.prev {
+                                            create.prev = Some(prev.to_string());
                                         }
+                                        posts_to_create.push(create);
+                                    },
+                                    Ok(Lexicon::AppBskyFeedLike(r)) => {
+                                        let like: Like = r;
+                                        let mut create = rsky_firehose::models::CreateOp {
+                                            uri: uri.to_owned(),
+                                            cid: cid.to_string(),
+                                            sequence: commit.seq,
+                                            prev: None,
+                                            author: commit.repo.to_owned(),
+                                            record: like
+                                        };
+                                        if let Some(ref prev) = commit.prev {
+                                            create.prev = Some(prev.to_string());
+                                        }
+                                        likes_to_create.push(create);
+                                    },
+                                    Ok(Lexicon::AppBskyFeedRepost(r)) => {
+                                        let repost: Repost = r;
+                                        let mut create = rsky_firehose::models::CreateOp {
+                                            uri: uri.to_owned(),
+                                            cid: cid.to_string(),
+                                            sequence: commit.seq,
+                                            prev: None,
+                                            author: commit.repo.to_owned(),
+                                            record: repost
+                                        };
+                                        if let Some(ref prev) = commit.prev {
+                                            create.prev = Some(prev.to_string());
+                                        }
+                                        reposts_to_create.push(create);
+                                    },
+                                    Ok(Lexicon::AppBskyFeedFollow(r)) => {
+                                        let follow: Follow = r;
+                                        let mut create = rsky_firehose::models::CreateOp {
+                                            uri: uri.to_owned(),
+                                            cid: cid.to_string(),
+                                            sequence: commit.seq,
+                                            prev: None,
+                                            author: commit.repo.to_owned(),
+                                            record: follow
+                                        };
+                                        if let Some(ref prev) = commit.prev {
+                                            create.prev = Some(prev.to_string());
+                                        }
+                                        follows_to_create.push(create);
+                                    },
+                                    Err(error) => {
+                                        eprintln!("@LOG: Failed to deserialize record: {:?}. Received error {:?}. Sequence {:?}", uri, error, commit.seq);
                                     }
-                                },
-                                "delete" => {
-                                    let del = rsky_firehose::models::DeleteOp {
-                                        uri: uri.to_owned()
-                                    };
-                                    let collection = &operation.path
-                                        .split("/")
-                                        .map(String::from)
-                                        .collect::<Vec<_>>()[0];
-                                    if collection == "app.bsky.feed.post" {
-                                        posts_to_delete.push(del);
-                                    } else if collection == "app.bsky.feed.like" {
-                                        likes_to_delete.push(del);
-                                    } else if collection == "app.bsky.graph.follow" {
-                                        follows_to_delete.push(del);
-                                    }
-                                },
-                                _ => {}
+                                }
                             }
-                        })
-                        .for_each(drop);
-                }
-                _ => println!("@LOG: Saw non-commit event: {body:?}"),
-            }
-            if posts_to_create.len() > 0 {
-                let queue_endpoint = format!("{}/queue/{}/create", default_queue_path, "posts");
-                let resp = queue_create(queue_endpoint, posts_to_create, client).await;
-                match resp {
-                    Ok(()) => (),
-                    Err(error) => eprintln!("Records failed to queue: {error:?}"),
-                };
-            }
-            if posts_to_delete.len() > 0 {
-                let queue_endpoint = format!("{}/queue/{}/delete", default_queue_path, "posts");
-                let resp = queue_delete(queue_endpoint, posts_to_delete, client).await;
-                match resp {
-                    Ok(()) => (),
-                    Err(error) => eprintln!("Records failed to queue: {error:?}"),
-                };
-            }
-            if likes_to_create.len() > 0 {
-                let queue_endpoint = format!("{}/queue/{}/create", default_queue_path, "likes");
-                let resp = queue_create(queue_endpoint, likes_to_create, client).await;
-                match resp {
-                    Ok(()) => (),
-                    Err(error) => eprintln!("Records failed to queue: {error:?}"),
-                };
-            }
-            if likes_to_delete.len() > 0 {
-                let queue_endpoint = format!("{}/queue/{}/delete", default_queue_path, "likes");
-                let resp = queue_delete(queue_endpoint, likes_to_delete, client).await;
-                match resp {
-                    Ok(()) => (),
-                    Err(error) => eprintln!("Records failed to queue: {error:?}"),
-                };
-            }
-            if follows_to_create.len() > 0 {
-                let queue_endpoint = format!("{}/queue/{}/create", default_queue_path, "follows");
-                let resp = queue_create(queue_endpoint, follows_to_create, client).await;
-                match resp {
-                    Ok(()) => (),
-                    Err(error) => eprintln!("Records failed to queue: {error:?}"),
-                };
-            }
-            if follows_to_delete.len() > 0 {
-                let queue_endpoint = format!("{}/queue/{}/delete", default_queue_path, "follows");
-                let resp = queue_delete(queue_endpoint, follows_to_delete, client).await;
-                match resp {
-                    Ok(()) => (),
-                    Err(error) => eprintln!("Records failed to queue: {error:?}"),
-                };
-            }
+                        },
+                        "delete" => {
+                            let del = rsky_firehose::models::DeleteOp {
+                                uri: uri.to_owned()
+                            };
+                            let collection = &operation.path
+                                .split("/")
+                                .map(String::from)
+                                .collect::<Vec<_>>()[0];
+                            if collection == "app.bsky.feed.post" {
+                                posts_to_delete.push(del);
+                            } else if collection == "app.bsky.feed.like" {
+                                likes_to_delete.push(del);
+                            } else if collection == "app.bsky.feed.repost" {
+                                reposts_to_delete.push(del);
+                            } else if collection == "app.bsky.graph.follow" {
+                                follows_to_delete.push(del);
+                            }
+                        },
+                        _ => {}
+                    }
+                })
+                .for_each(drop);
         }
-        Err(error) => eprintln!(
-            "@LOG: Error unwrapping message and header: {}",
-            error.to_string()
-        ),
+        _ => println!("@LOG: Saw non-commit event: {body:?}"),
+    }
+    if posts_to_create.len() > 0 {
+        let queue_endpoint = format!("{}/queue/{}/create", default_queue_path, "posts");
+        let resp = queue_create(queue_endpoint, posts_to_create, client).await;
+        match resp {
+            Ok(()) => (),
+            Err(error) => eprintln!("Records failed to queue: {error:?}"),
+        };
+    }
+    if posts_to_delete.len() > 0 {
+        let queue_endpoint = format!("{}/queue/{}/delete", default_queue_path, "posts");
+        let resp = queue_delete(queue_endpoint, posts_to_delete, client).await;
+        match resp {
+            Ok(()) => (),
+            Err(error) => eprintln!("Records failed to queue: {error:?}"),
+        };
+    }
+    if likes_to_create.len() > 0 {
+        let queue_endpoint = format!("{}/queue/{}/create", default_queue_path, "likes");
+        let resp = queue_create(queue_endpoint, likes_to_create, client).await;
+        match resp {
+            Ok(()) => (),
+            Err(error) => eprintln!("Records failed to queue: {error:?}"),
+        };
+    }
+    if likes_to_delete.len() > 0 {
+        let queue_endpoint = format!("{}/queue/{}/delete", default_queue_path, "likes");
+        let resp = queue_delete(queue_endpoint, likes_to_delete, client).await;
+        match resp {
+            Ok(()) => (),
+            Err(error) => eprintln!("Records failed to queue: {error:?}"),
+        };
+    }
+    if reposts_to_create.len() > 0 {
+        let queue_endpoint = format!("{}/queue/{}/create", default_queue_path, "reposts");
+        let resp = queue_create(queue_endpoint, reposts_to_create, client).await;
+        match resp {
+            Ok(()) => (),
+            Err(error) => eprintln!("Records failed to queue: {error:?}"),
+        };
+    }
+    if reposts_to_delete.len() > 0 {
+        let queue_endpoint = format!("{}/queue/{}/delete", default_queue_path, "reposts");
+        let resp = queue_delete(queue_endpoint, reposts_to_delete, client).await;
+        match resp {
+            Ok(()) => (),
+            Err(error) => eprintln!("Records failed to queue: {error:?}"),
+        };
+    }
+    if follows_to_create.len() > 0 {
+        let queue_endpoint = format!("{}/queue/{}/create", default_queue_path, "follows");
+        let resp = queue_create(queue_endpoint, follows_to_create, client).await;
+        match resp {
+            Ok(()) => (),
+            Err(error) => eprintln!("Records failed to queue: {error:?}"),
+        };
+    }
+    if follows_to_delete.len() > 0 {
+        let queue_endpoint = format!("{}/queue/{}/delete", default_queue_path, "follows");
+        let resp = queue_delete(queue_endpoint, follows_to_delete, client).await;
+        match resp {
+            Ok(()) => (),
+            Err(error) => eprintln!("Records failed to queue: {error:?}"),
+        };
     }
 }
 
@@ -345,35 +359,91 @@ fn websocket_url(
     ws_url
 }
 
-#[tokio::main]
-async fn main() {
-    // Load environment variables from .env file
-    dotenv().ok();
+/// Persists the subscribeRepos cursor to the feedgen's `/cursor` endpoint,
+/// starting from `FEEDGEN_SUBSCRIPTION_CURSOR` if one is set.
+struct QueueCursorStore {
+    client: Arc<reqwest::Client>,
+    cursor_endpoint: String,
+    service: String,
+    cursor: MemoryCursorStore,
+}
 
-    // Retrieve the subscription endpoint from environment variables or use default
-    let subscriber_base_path =
-        env::var("FEEDGEN_SUBSCRIPTION_PATH").unwrap_or_else(|_| "wss://bsky.network".to_string());
-    let subscriber_endpoint = env::var("FEEDGEN_SUBSCRIPTION_ENDPOINT")
-        .unwrap_or_else(|_| "com.atproto.sync.subscribeRepos".to_string());
-    let subscriber_cursor = env::var("FEEDGEN_SUBSCRIPTION_CURSOR").ok();
+impl CursorStore for QueueCursorStore {
+    async fn load(&self) -> anyhow::Result<Option<i64>> {
+        self.cursor.load().await
+    }
 
-    // Configure the reqwest client with connection pooling settings
-    let client = Arc::new(
-        reqwest::Client::builder()
-            .pool_max_idle_per_host(10) // Max idle connections per host
-            .pool_idle_timeout(Duration::from_secs(30)) // Idle timeout
-            .timeout(Duration::from_secs(10)) // Request timeout
-            .build()
-            .expect("Failed to build reqwest client"),
-    );
+    async fn store(&self, seq: i64) -> anyhow::Result<()> {
+        self.cursor.store(seq).await?;
+        let resp = update_cursor(
+            self.cursor_endpoint.clone(),
+            self.service.clone(),
+            &seq,
+            &self.client,
+        )
+        .await;
+        if let Err(error) = resp {
+            eprintln!("@LOG: Failed to update cursor: {error:?}");
+        }
+        Ok(())
+    }
+}
 
-    // Create a semaphore to limit the number of concurrent processing tasks
-    let semaphore = Arc::new(Semaphore::new(100)); // Adjust the limit as needed
+async fn subscribe_repos(
+    subscriber_base_path: String,
+    subscriber_cursor: Option<String>,
+    client: Arc<reqwest::Client>,
+    semaphore: Arc<Semaphore>,
+) {
+    let default_queue_path =
+        env::var("FEEDGEN_QUEUE_ENDPOINT").unwrap_or("https://[::1]:8081".into());
+    let cursor_store = QueueCursorStore {
+        client: Arc::clone(&client),
+        cursor_endpoint: format!("{}/cursor", default_queue_path),
+        service: subscriber_base_path.clone(),
+        cursor: MemoryCursorStore::new(subscriber_cursor.and_then(|c| c.parse().ok())),
+    };
+    let consumer = FirehoseConsumerBuilder::default()
+        .relay_url(subscriber_base_path)
+        .cursor_store(cursor_store)
+        .collection("app.bsky.feed.post")
+        .collection("app.bsky.feed.like")
+        .collection("app.bsky.feed.repost")
+        .collection("app.bsky.graph.follow")
+        .build()
+        .expect("Failed to build firehose consumer");
+
+    let result = consumer
+        .run(|event| {
+            let client = Arc::clone(&client);
+            let semaphore = Arc::clone(&semaphore);
+            async move {
+                // Acquire a permit before spawning a new task
+                let permit = semaphore.acquire_owned().await?;
+                tokio::spawn(async move {
+                    process(event, &client).await;
+                    // Permit is automatically released when it goes out of scope
+                    drop(permit);
+                });
+                Ok(())
+            }
+        })
+        .await;
+    if let Err(error) = result {
+        eprintln!("@LOG: Firehose consumer stopped: {error:?}");
+    }
+}
 
+async fn subscribe_labels(
+    subscriber_base_path: String,
+    subscriber_cursor: Option<String>,
+    client: Arc<reqwest::Client>,
+    semaphore: Arc<Semaphore>,
+) {
     // Construct the WebSocket URL
     let ws_url = websocket_url(
         &subscriber_base_path,
-        &subscriber_endpoint,
+        "com.atproto.label.subscribeLabels",
         subscriber_cursor.as_deref(),
     );
 
@@ -402,22 +472,7 @@ async fn main() {
                             // Spawn a new asynchronous task to process the message
                             tokio::spawn(async move {
                                 // The permit is held for the duration of the task
-                                let subscriber_endpoint = env::var("FEEDGEN_SUBSCRIPTION_ENDPOINT")
-                                    .unwrap_or_else(|_| {
-                                        "com.atproto.sync.subscribeRepos".to_string()
-                                    });
-
-                                match subscriber_endpoint.as_str() {
-                                    "com.atproto.sync.subscribeRepos" => {
-                                        process(message, &client).await
-                                    }
-                                    "com.atproto.label.subscribeLabels" => {
-                                        process_labels(message, &client).await
-                                    }
-                                    _ => panic!(
-                                        "Unexpected subscription endpoint: {subscriber_endpoint}"
-                                    ),
-                                };
+                                process_labels(message, &client).await;
                                 // Permit is automatically released when it goes out of scope
                                 drop(permit);
                             });
@@ -451,3 +506,39 @@ async fn main() {
         tokio::time::sleep(Duration::from_secs(1)).await;
     }
 }
+
+#[tokio::main]
+async fn main() {
+    // Load environment variables from .env file
+    dotenv().ok();
+
+    // Retrieve the subscription endpoint from environment variables or use default
+    let subscriber_base_path =
+        env::var("FEEDGEN_SUBSCRIPTION_PATH").unwrap_or_else(|_| "wss://bsky.network".to_string());
+    let subscriber_endpoint = env::var("FEEDGEN_SUBSCRIPTION_ENDPOINT")
+        .unwrap_or_else(|_| "com.atproto.sync.subscribeRepos".to_string());
+    let subscriber_cursor = env::var("FEEDGEN_SUBSCRIPTION_CURSOR").ok();
+
+    // Configure the reqwest client with connection pooling settings
+    let client = Arc::new(
+        reqwest::Client::builder()
+            .pool_max_idle_per_host(10) // Max idle connections per host
+            .pool_idle_timeout(Duration::from_secs(30)) // Idle timeout
+            .timeout(Duration::from_secs(10)) // Request timeout
+            .build()
+            .expect("Failed to build reqwest client"),
+    );
+
+    // Create a semaphore to limit the number of concurrent processing tasks
+    let semaphore = Arc::new(Semaphore::new(100)); // Adjust the limit as needed
+
+    match subscriber_endpoint.as_str() {
+        "com.atproto.sync.subscribeRepos" => {
+            subscribe_repos(subscriber_base_path, subscriber_cursor, client, semaphore).await
+        }
+        "com.atproto.label.subscribeLabels" => {
+            subscribe_labels(subscriber_base_path, subscriber_cursor, client, semaphore).await
+        }
+        _ => panic!("Unexpected subscription endpoint: {subscriber_endpoint}"),
+    }
+}
@@ -0,0 +1,259 @@
+use anyhow::{bail, Result};
+use rsky_common::get_verification_material;
+use rsky_identity::did::atproto_data::get_did_key_from_multibase;
+use rsky_identity::IdResolver;
+use rsky_lexicon::com::atproto::sync::SubscribeReposCommit;
+use rsky_repo::car::read_car_with_root;
+use rsky_repo::sync::consumer::{verify_proofs, ConsumerError};
+use rsky_repo::types::RecordCidClaim;
+use std::collections::HashMap;
+
+/// Checks `#commit` events against the repo's signing key and MST before a
+/// consumer yields them, so an indexer does not have to trust the relay.
+pub struct CommitVerifier {
+    id_resolver: IdResolver,
+    /// Rev of the last verified commit for each repo.
+    revs: HashMap<String, String>,
+}
+
+impl CommitVerifier {
+    pub fn new(id_resolver: IdResolver) -> Self {
+        Self {
+            id_resolver,
+            revs: HashMap::new(),
+        }
+    }
+
+    /// Verifies that the commit is signed by the repo's current `atproto` key,
+    /// that every op is proven by the MST blocks included in the event and that
+    /// its rev is newer than both `since` and the last commit verified for the repo.
+    pub async fn verify(&mut self, commit: &SubscribeReposCommit) -> Result<()> {
+        if commit.too_big {
+            bail!(ConsumerError::RepoVerificationError(format!(
+                "Commit {} is too big to verify",
+                commit.commit
+            )));
+        }
+        self.check_rev(commit)?;
+        let car = read_car_with_root(commit.blocks.clone()).await?;
+        if car.root != commit.commit {
+            bail!(ConsumerError::RepoVerificationError(format!(
+                "Commit root {} does not match event commit {}",
+                car.root, commit.commit
+            )));
+        }
+
+        let claims = commit
+            .ops
+            .iter()
+            .map(|op| {
+                let Some((collection, rkey)) = op.path.split_once('/') else {
+                    bail!(ConsumerError::RepoVerificationError(format!(
+                        "Invalid op path: {}",
+                        op.path
+                    )));
+                };
+                Ok(RecordCidClaim {
+                    collection: collection.to_string(),
+                    rkey: rkey.to_string(),
+                    cid: match op.action.as_str() {
+                        "delete" => None,
+                        _ => op.cid,
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let key = self.signing_key(&commit.repo, false).await?;
+        let verified =
+            match verify_proofs(commit.blocks.clone(), claims.clone(), &commit.repo, &key).await {
+                Ok(verified) => verified,
+                Err(_) => {
+                    // the key may have been rotated since it was cached
+                    let key = self.signing_key(&commit.repo, true).await?;
+                    verify_proofs(commit.blocks.clone(), claims, &commit.repo, &key).await?
+                }
+            };
+        if !verified.unverified.is_empty() {
+            bail!(ConsumerError::RepoVerificationError(format!(
+                "Unverified ops in commit {}: {:?}",
+                commit.commit, verified.unverified
+            )));
+        }
+        self.revs.insert(commit.repo.clone(), commit.rev.clone());
+        Ok(())
+    }
+
+    // revs are TIDs, which sort lexicographically in time order
+    fn check_rev(&self, commit: &SubscribeReposCommit) -> Result<()> {
+        if let Some(since) = &commit.since {
+            if *since >= commit.rev {
+                bail!(ConsumerError::RepoVerificationError(format!(
+                    "Commit {} has rev {} not newer than since {since}",
+                    commit.commit, commit.rev
+                )));
+            }
+        }
+        if let Some(last) = self.revs.get(&commit.repo) {
+            if *last >= commit.rev {
+                bail!(ConsumerError::RepoVerificationError(format!(
+                    "Commit {} has rev {} not newer than last seen rev {last}",
+                    commit.commit, commit.rev
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    async fn signing_key(&mut self, did: &String, force_refresh: bool) -> Result<String> {
+        let doc = self
+            .id_resolver
+            .did
+            .ensure_resolve(did, Some(force_refresh))
+            .await?;
+        match get_verification_material(&doc, "atproto") {
+            None => bail!("missing or bad key in did doc"),
+            Some(material) => match get_did_key_from_multibase(material)? {
+                None => bail!("missing or bad key in did doc"),
+                Some(did_key) => Ok(did_key),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsky_common::ipld::cid_for_cbor;
+    use rsky_common::{cbor_to_struct, struct_to_cbor};
+    use rsky_identity::types::{DidCache, DidDocument, IdentityResolverOpts, VerificationMethod};
+    use rsky_lexicon::com::atproto::sync::SubscribeReposCommitOperation;
+    use rsky_repo::car::blocks_to_car_file;
+    use rsky_repo::test_helpers::{commit_car, RepoBuilder, RepoFixture};
+    use rsky_repo::types::{Commit, CommitData};
+    use std::time::Duration;
+
+    const COLL_NAME: &str = "app.bsky.feed.post";
+
+    /// A verifier whose cache already holds `did` with `did_key` as its signing key.
+    /// Refreshes go to an unreachable PLC directory, so they always fail.
+    async fn verifier(did: &str, did_key: &str) -> CommitVerifier {
+        let mut id_resolver = IdResolver::new(IdentityResolverOpts {
+            timeout: Some(Duration::from_millis(100)),
+            plc_url: Some("http://127.0.0.1:1".to_string()),
+            did_cache: Some(DidCache::new(None, None)),
+            handle_cache: None,
+            backup_nameservers: None,
+        });
+        let doc = DidDocument {
+            context: None,
+            id: did.to_string(),
+            also_known_as: None,
+            verification_method: Some(vec![VerificationMethod {
+                id: format!("{did}#atproto"),
+                r#type: "Multikey".to_string(),
+                controller: did.to_string(),
+                public_key_multibase: did_key.strip_prefix("did:key:").map(str::to_string),
+            }]),
+            service: None,
+        };
+        id_resolver
+            .did
+            .cache
+            .as_mut()
+            .unwrap()
+            .cache_did(did.to_string(), doc)
+            .await
+            .unwrap();
+        CommitVerifier::new(id_resolver)
+    }
+
+    async fn event(
+        fixture: &mut RepoFixture,
+        rkey: &str,
+        commit: &CommitData,
+    ) -> SubscribeReposCommit {
+        let path = format!("{COLL_NAME}/{rkey}");
+        let cid = fixture.repo.data.get(&path).await.unwrap();
+        SubscribeReposCommit {
+            seq: 1,
+            time: chrono::Utc::now(),
+            rebase: false,
+            too_big: false,
+            repo: fixture.did.clone(),
+            commit: commit.cid,
+            prev: None,
+            rev: commit.rev.clone(),
+            since: commit.since.clone(),
+            blocks: commit_car(commit).await.unwrap(),
+            ops: vec![SubscribeReposCommitOperation {
+                path,
+                action: "create".to_string(),
+                cid,
+            }],
+            blobs: Vec::new(),
+        }
+    }
+
+    async fn created(fixture: &mut RepoFixture) -> SubscribeReposCommit {
+        let (rkey, commit) = fixture.create_record(COLL_NAME).await.unwrap();
+        event(fixture, &rkey, &commit).await
+    }
+
+    #[tokio::test]
+    async fn test_verify_valid_commit() {
+        let mut fixture = RepoBuilder::new(1).build().await.unwrap();
+        let mut verifier = verifier(&fixture.did, &fixture.did_key()).await;
+        let first = created(&mut fixture).await;
+        let second = created(&mut fixture).await;
+        verifier.verify(&first).await.unwrap();
+        verifier.verify(&second).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_verify_bad_signature() {
+        let mut fixture = RepoBuilder::new(2).build().await.unwrap();
+        let mut verifier = verifier(&fixture.did, &fixture.did_key()).await;
+        let (rkey, mut commit) = fixture.create_record(COLL_NAME).await.unwrap();
+
+        let bytes = commit.new_blocks.get(commit.cid).unwrap().clone();
+        let mut signed: Commit = cbor_to_struct(bytes).unwrap();
+        signed.sig = vec![0; signed.sig.len()];
+        commit.new_blocks.delete(commit.cid).unwrap();
+        commit.cid = cid_for_cbor(&signed).unwrap();
+        commit
+            .new_blocks
+            .set(commit.cid, struct_to_cbor(&signed).unwrap());
+
+        let mut event = event(&mut fixture, &rkey, &commit).await;
+        event.blocks = blocks_to_car_file(Some(&commit.cid), commit.new_blocks)
+            .await
+            .unwrap();
+        assert!(verifier.verify(&event).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_verify_mismatched_did_key() {
+        let mut fixture = RepoBuilder::new(3).build().await.unwrap();
+        let other = RepoBuilder::new(4).build().await.unwrap();
+        let mut verifier = verifier(&fixture.did, &other.did_key()).await;
+        let event = created(&mut fixture).await;
+        assert!(verifier.verify(&event).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_verify_out_of_order_rev() {
+        let mut fixture = RepoBuilder::new(5).build().await.unwrap();
+        let mut verifier = verifier(&fixture.did, &fixture.did_key()).await;
+        let first = created(&mut fixture).await;
+        let second = created(&mut fixture).await;
+        verifier.verify(&second).await.unwrap();
+        // older than the last verified commit, and a replay of it
+        assert!(verifier.verify(&first).await.is_err());
+        assert!(verifier.verify(&second).await.is_err());
+
+        let mut third = created(&mut fixture).await;
+        third.since = Some(third.rev.clone());
+        assert!(verifier.verify(&third).await.is_err());
+    }
+}
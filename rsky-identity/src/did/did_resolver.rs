@@ -108,6 +108,9 @@ impl DidResolver {
         match self.cache {
             None => (),
             Some(ref cache) if !force_refresh => {
+                if cache.is_missing(&did) {
+                    return Ok(None);
+                }
                 from_cache = cache.check_cache(did.clone())?;
                 match from_cache {
                     None => (),
@@ -126,7 +129,7 @@ impl DidResolver {
         match self.resolve_no_cache(&did).await? {
             None => {
                 if let Some(ref mut cache) = self.cache {
-                    cache.clear_entry(did)?;
+                    cache.cache_missing(did);
                 }
                 Ok(None)
             }
@@ -1,4 +1,4 @@
-use crate::types::HandleResolverOpts;
+use crate::types::{HandleCache, HandleResolverOpts};
 use anyhow::Result;
 use hickory_resolver::config::*;
 use hickory_resolver::error::ResolveResult;
@@ -14,6 +14,7 @@ pub const PREFIX: &str = "did=";
 #[derive(Clone, Debug)]
 pub struct HandleResolver {
     pub timeout: Duration,
+    pub cache: Option<HandleCache>,
     backup_nameservers: Option<Vec<String>>,
     backup_nameserver_ips: Option<Vec<IpAddr>>,
 }
@@ -22,12 +23,30 @@ impl HandleResolver {
     pub fn new(opts: HandleResolverOpts) -> Self {
         Self {
             timeout: opts.timeout.unwrap_or(Duration::from_millis(3000)),
+            cache: opts.cache,
             backup_nameservers: opts.backup_nameservers,
             backup_nameserver_ips: None,
         }
     }
 
     pub async fn resolve(&mut self, handle: &String) -> Result<Option<String>> {
+        if let Some(did) = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.check_cache(handle))
+        {
+            return Ok(did);
+        }
+        let did = self.resolve_no_cache(handle).await?;
+        if let Some(ref mut cache) = self.cache {
+            cache.cache_handle(handle, did.clone());
+        }
+        Ok(did)
+    }
+
+    /// Resolves `handle` without reading or filling the cache, for checks that have to see
+    /// the handle's current DID.
+    pub async fn resolve_no_cache(&mut self, handle: &String) -> Result<Option<String>> {
         let dns_future = self.resolve_dns(handle);
         let http_future = self.resolve_http(handle);
 
@@ -17,19 +17,18 @@ impl IdResolver {
             timeout,
             plc_url,
             did_cache,
+            handle_cache,
             backup_nameservers,
         } = opts;
         let timeout = timeout.unwrap_or_else(|| Duration::from_millis(3000));
-        let did_cache = did_cache.unwrap_or_else(|| DidCache {
-            stale_ttl: Default::default(),
-            max_ttl: Default::default(),
-            cache: Default::default(),
-        });
+        let did_cache =
+            did_cache.unwrap_or_else(|| DidCache::new(Some(Duration::ZERO), Some(Duration::ZERO)));
 
         Self {
             handle: HandleResolver::new(HandleResolverOpts {
                 timeout: Some(timeout),
                 backup_nameservers,
+                cache: handle_cache,
             }),
             did: DidResolver::new(DidResolverOpts {
                 timeout: Some(timeout),
@@ -38,6 +37,30 @@ impl IdResolver {
             }),
         }
     }
+
+    /// Drops whatever is cached for `did`, so the next resolution goes to the network.
+    pub fn purge_did(&mut self, did: &str) -> anyhow::Result<()> {
+        match self.did.cache {
+            Some(ref mut cache) => cache.clear_entry(did.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn purge_handle(&mut self, handle: &str) {
+        if let Some(ref mut cache) = self.handle.cache {
+            cache.clear_entry(handle);
+        }
+    }
+
+    pub fn purge_all(&mut self) -> anyhow::Result<()> {
+        if let Some(ref mut cache) = self.handle.cache {
+            cache.clear();
+        }
+        match self.did.cache {
+            Some(ref mut cache) => cache.clear(),
+            None => Ok(()),
+        }
+    }
 }
 
 pub mod common;
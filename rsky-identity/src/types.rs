@@ -1,4 +1,4 @@
-use crate::common::{DAY, HOUR};
+use crate::common::{DAY, HOUR, MINUTE};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
@@ -40,12 +40,14 @@ pub struct IdentityResolverOpts {
     pub timeout: Option<Duration>,
     pub plc_url: Option<String>,
     pub did_cache: Option<DidCache>,
+    pub handle_cache: Option<HandleCache>,
     pub backup_nameservers: Option<Vec<String>>,
 }
 
 pub struct HandleResolverOpts {
     pub timeout: Option<Duration>,
     pub backup_nameservers: Option<Vec<String>>,
+    pub cache: Option<HandleCache>,
 }
 
 pub struct DidResolverOpts {
@@ -75,28 +77,83 @@ pub struct CacheVal {
     pub updated_at: u128,
 }
 
+/// Default bound on the entries of each in-memory cache.
+pub const MAX_CACHE_ENTRIES: usize = 100_000;
+
+fn now_micros() -> u128 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("timestamp in micros since UNIX epoch")
+        .as_micros()
+}
+
+/// Makes room for one more entry in a cache bounded to `max_entries`: drops the entries that
+/// have expired and, if that isn't enough, those closest to expiring, down to three quarters of
+/// the bound so the next full pass is a while off.
+fn prune<V>(cache: &mut BTreeMap<String, V>, max_entries: usize, expires_at: impl Fn(&V) -> u128) {
+    if cache.len() < max_entries {
+        return;
+    }
+    let now = now_micros();
+    cache.retain(|_, val| expires_at(val) >= now);
+    if cache.len() < max_entries {
+        return;
+    }
+    let mut by_expiry: Vec<(u128, String)> = cache
+        .iter()
+        .map(|(key, val)| (expires_at(val), key.clone()))
+        .collect();
+    by_expiry.sort_unstable();
+    let excess = cache.len() - max_entries * 3 / 4;
+    for (_, key) in by_expiry.into_iter().take(excess) {
+        cache.remove(&key);
+    }
+}
+
 /// MemoryCache implementation of DidCache
 #[derive(Clone, Debug)]
 pub struct DidCache {
     pub stale_ttl: Duration,
     pub max_ttl: Duration,
+    /// How long a DID that positively doesn't exist is remembered as missing. Zero turns
+    /// negative caching off.
+    pub negative_ttl: Duration,
+    /// Most entries kept in `cache`, and in `missing`.
+    pub max_entries: usize,
     pub cache: BTreeMap<String, CacheVal>,
+    /// DIDs found not to exist, with when that was found.
+    pub missing: BTreeMap<String, u128>,
 }
 
 impl DidCache {
     pub fn new(stale_ttl: Option<Duration>, max_ttl: Option<Duration>) -> Self {
         Self {
-            stale_ttl: stale_ttl.unwrap_or_else(|| Duration::new(HOUR as u64, 0)),
-            max_ttl: max_ttl.unwrap_or_else(|| Duration::new(DAY as u64, 0)),
+            stale_ttl: stale_ttl.unwrap_or_else(|| Duration::from_millis(HOUR as u64)),
+            max_ttl: max_ttl.unwrap_or_else(|| Duration::from_millis(DAY as u64)),
+            negative_ttl: Duration::ZERO,
+            max_entries: MAX_CACHE_ENTRIES,
             cache: BTreeMap::new(),
+            missing: BTreeMap::new(),
         }
     }
 
+    pub fn with_negative_ttl(mut self, negative_ttl: Duration) -> Self {
+        self.negative_ttl = negative_ttl;
+        self
+    }
+
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
     pub async fn cache_did(&mut self, did: String, doc: DidDocument) -> Result<()> {
-        let now = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .expect("timestamp in micros since UNIX epoch")
-            .as_micros();
+        let now = now_micros();
+        self.missing.remove(&did);
+        let max_ttl = self.max_ttl.as_micros();
+        prune(&mut self.cache, self.max_entries, |val| {
+            val.updated_at + max_ttl
+        });
         self.cache.insert(
             did,
             CacheVal {
@@ -121,10 +178,7 @@ impl DidCache {
         match self.cache.get(&did) {
             None => Ok(None),
             Some(val) => {
-                let now = SystemTime::now()
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .expect("timestamp in micros since UNIX epoch")
-                    .as_micros();
+                let now = now_micros();
                 let expired = now > val.updated_at + self.max_ttl.as_micros();
                 let stale = now > val.updated_at + self.stale_ttl.as_micros();
                 let CacheVal { doc, updated_at } = val.clone();
@@ -139,12 +193,187 @@ impl DidCache {
         }
     }
 
+    /// Remembers `did` as not existing, for `negative_ttl`.
+    pub fn cache_missing(&mut self, did: String) {
+        self.cache.remove(&did);
+        if !self.negative_ttl.is_zero() {
+            let negative_ttl = self.negative_ttl.as_micros();
+            prune(&mut self.missing, self.max_entries, |found_at| {
+                found_at + negative_ttl
+            });
+            self.missing.insert(did, now_micros());
+        }
+    }
+
+    pub fn is_missing(&self, did: &str) -> bool {
+        match self.missing.get(did) {
+            None => false,
+            Some(found_at) => now_micros() <= found_at + self.negative_ttl.as_micros(),
+        }
+    }
+
     pub fn clear_entry(&mut self, did: String) -> Result<()> {
+        self.missing.remove(&did);
         self.cache.remove(&did);
         Ok(())
     }
 
     pub fn clear(&mut self) -> Result<()> {
+        self.missing.clear();
         Ok(self.cache.clear())
     }
 }
+
+#[derive(Clone, Debug)]
+pub struct HandleCacheVal {
+    /// `None` when the handle positively didn't resolve.
+    pub did: Option<String>,
+    pub updated_at: u128,
+}
+
+/// MemoryCache of handle to DID resolutions. Handles that don't resolve are kept for
+/// `negative_ttl` instead of `ttl`, so they're retried sooner.
+#[derive(Clone, Debug)]
+pub struct HandleCache {
+    pub ttl: Duration,
+    pub negative_ttl: Duration,
+    pub max_entries: usize,
+    pub cache: BTreeMap<String, HandleCacheVal>,
+}
+
+impl HandleCache {
+    pub fn new(ttl: Option<Duration>, negative_ttl: Option<Duration>) -> Self {
+        Self {
+            ttl: ttl.unwrap_or_else(|| Duration::from_millis(10 * MINUTE as u64)),
+            negative_ttl: negative_ttl.unwrap_or_else(|| Duration::from_millis(MINUTE as u64)),
+            max_entries: MAX_CACHE_ENTRIES,
+            cache: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries.max(1);
+        self
+    }
+
+    pub fn cache_handle(&mut self, handle: &str, did: Option<String>) {
+        let (ttl, negative_ttl) = (self.ttl.as_micros(), self.negative_ttl.as_micros());
+        prune(&mut self.cache, self.max_entries, |val| {
+            val.updated_at
+                + match val.did {
+                    Some(_) => ttl,
+                    None => negative_ttl,
+                }
+        });
+        self.cache.insert(
+            handle.to_lowercase(),
+            HandleCacheVal {
+                did,
+                updated_at: now_micros(),
+            },
+        );
+    }
+
+    /// `None` on a miss or an expired entry, `Some(None)` for a handle cached as not resolving.
+    pub fn check_cache(&self, handle: &str) -> Option<Option<String>> {
+        let val = self.cache.get(&handle.to_lowercase())?;
+        let ttl = match val.did {
+            Some(_) => self.ttl,
+            None => self.negative_ttl,
+        };
+        match now_micros() <= val.updated_at + ttl.as_micros() {
+            true => Some(val.did.clone()),
+            false => None,
+        }
+    }
+
+    pub fn clear_entry(&mut self, handle: &str) {
+        self.cache.remove(&handle.to_lowercase());
+    }
+
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::sleep;
+
+    #[test]
+    fn test_did_cache_missing() {
+        let mut cache = DidCache::new(None, None);
+        cache.cache_missing("did:plc:gone".to_string());
+        // negative caching is off by default
+        assert!(!cache.is_missing("did:plc:gone"));
+
+        let mut cache = cache.with_negative_ttl(Duration::from_millis(20));
+        cache.cache_missing("did:plc:gone".to_string());
+        assert!(cache.is_missing("did:plc:gone"));
+        assert!(!cache.is_missing("did:plc:other"));
+        sleep(Duration::from_millis(30));
+        assert!(!cache.is_missing("did:plc:gone"));
+
+        cache.cache_missing("did:plc:gone".to_string());
+        cache.clear_entry("did:plc:gone".to_string()).unwrap();
+        assert!(!cache.is_missing("did:plc:gone"));
+    }
+
+    #[test]
+    fn test_did_cache_missing_is_bounded() {
+        let mut cache = DidCache::new(None, None)
+            .with_negative_ttl(Duration::from_secs(60))
+            .with_max_entries(8);
+        for i in 0..100 {
+            cache.cache_missing(format!("did:plc:{i}"));
+            assert!(cache.missing.len() <= 8);
+        }
+        // the latest is kept, the oldest made room for it
+        assert!(cache.is_missing("did:plc:99"));
+        assert!(!cache.is_missing("did:plc:0"));
+    }
+
+    #[test]
+    fn test_handle_cache_ttl() {
+        let mut cache = HandleCache::new(
+            Some(Duration::from_millis(200)),
+            Some(Duration::from_millis(20)),
+        );
+        cache.cache_handle("Alice.example.com", Some("did:plc:alice".to_string()));
+        cache.cache_handle("bob.example.com", None);
+        assert_eq!(
+            cache.check_cache("alice.example.com"),
+            Some(Some("did:plc:alice".to_string()))
+        );
+        assert_eq!(cache.check_cache("bob.example.com"), Some(None));
+        assert_eq!(cache.check_cache("carol.example.com"), None);
+
+        // a handle that didn't resolve is retried sooner than one that did
+        sleep(Duration::from_millis(30));
+        assert_eq!(cache.check_cache("bob.example.com"), None);
+        assert!(cache.check_cache("alice.example.com").is_some());
+    }
+
+    #[test]
+    fn test_handle_cache_is_bounded() {
+        let mut cache = HandleCache::new(Some(Duration::from_secs(60)), None).with_max_entries(8);
+        for i in 0..100 {
+            cache.cache_handle(&format!("user{i}.example.com"), None);
+            assert!(cache.cache.len() <= 8);
+        }
+        assert_eq!(cache.check_cache("user99.example.com"), Some(None));
+
+        // expired entries go before live ones
+        let mut cache = HandleCache::new(Some(Duration::from_secs(60)), Some(Duration::ZERO))
+            .with_max_entries(4);
+        cache.cache_handle("alice.example.com", Some("did:plc:alice".to_string()));
+        for i in 0..3 {
+            cache.cache_handle(&format!("gone{i}.example.com"), None);
+        }
+        sleep(Duration::from_millis(1));
+        cache.cache_handle("bob.example.com", Some("did:plc:bob".to_string()));
+        assert_eq!(cache.cache.len(), 2);
+        assert!(cache.check_cache("alice.example.com").is_some());
+    }
+}
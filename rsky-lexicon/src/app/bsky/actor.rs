@@ -29,11 +29,11 @@ pub struct Profile {
     pub created_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
-#[serde(tag = "$type")]
-pub enum ProfileLabels {
-    #[serde(rename = "com.atproto.label.defs#selfLabels")]
-    SelfLabels(SelfLabels),
+open_union! {
+    pub enum ProfileLabels {
+        #[serde(rename = "com.atproto.label.defs#selfLabels")]
+        SelfLabels(SelfLabels),
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -0,0 +1,50 @@
+use crate::app::bsky::embed::EmbedError;
+use crate::com::atproto::repo::Blob;
+
+/// width:height represents an aspect ratio. It may be approximate,
+/// and may not correspond to absolute dimensions in any given unit.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AspectRatio {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl AspectRatio {
+    pub fn validate(&self) -> Result<(), EmbedError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(EmbedError::InvalidAspectRatio {
+                width: self.width,
+                height: self.height,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Checks a blob against the `accept` and `maxSize` constraints of the field holding it.
+/// An `accept` ending in `/*` matches any subtype. Blobs without a size pass the size check.
+pub(crate) fn validate_blob(
+    field: &'static str,
+    blob: &Blob,
+    accept: &str,
+    max_size: i64,
+) -> Result<(), EmbedError> {
+    let accepted = match accept.strip_suffix('*') {
+        Some(prefix) => blob.mime_type.starts_with(prefix),
+        None => blob.mime_type == accept,
+    };
+    if !accepted {
+        return Err(EmbedError::InvalidMimeType {
+            field,
+            mime_type: blob.mime_type.clone(),
+        });
+    }
+    match blob.size {
+        Some(size) if size > max_size => Err(EmbedError::BlobTooLarge {
+            field,
+            size,
+            max: max_size,
+        }),
+        _ => Ok(()),
+    }
+}
@@ -1,5 +1,9 @@
+use crate::app::bsky::embed::defs::validate_blob;
+use crate::app::bsky::embed::EmbedError;
 use crate::com::atproto::repo::Blob;
 
+pub const MAX_THUMB_SIZE: i64 = 1_000_000;
+
 /// A representation of some externally linked content (eg, a URL and 'card'),
 /// embedded in a Bluesky record (eg, a post).
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -8,12 +12,22 @@ pub struct External {
     pub external: ExternalObject,
 }
 
+impl External {
+    pub fn validate(&self) -> Result<(), EmbedError> {
+        match &self.external.thumb {
+            Some(thumb) => validate_blob("thumb", thumb, "image/*", MAX_THUMB_SIZE),
+            None => Ok(()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ExternalObject {
     pub uri: String,
     pub title: String,
     pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub thumb: Option<Blob>,
 }
 
@@ -31,5 +45,6 @@ pub struct ViewExternal {
     pub uri: String,
     pub title: String,
     pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub thumb: Option<String>,
 }
@@ -1,5 +1,11 @@
+use crate::app::bsky::embed::defs::validate_blob;
+pub use crate::app::bsky::embed::defs::AspectRatio;
+use crate::app::bsky::embed::EmbedError;
 use crate::com::atproto::repo::Blob;
 
+pub const MAX_IMAGES: usize = 4;
+pub const MAX_IMAGE_SIZE: i64 = 1_000_000;
+
 /// A set of images embedded in a Bluesky record (eg, a post).
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -7,22 +13,31 @@ pub struct Images {
     pub images: Vec<Image>,
 }
 
+impl Images {
+    pub fn validate(&self) -> Result<(), EmbedError> {
+        if self.images.len() > MAX_IMAGES {
+            return Err(EmbedError::TooManyImages(self.images.len()));
+        }
+        for image in &self.images {
+            validate_blob("image", &image.image, "image/*", MAX_IMAGE_SIZE)?;
+            if let Some(aspect_ratio) = &image.aspect_ratio {
+                aspect_ratio.validate()?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Image {
     pub image: Blob,
     /// Alt text description of the image, for accessibility
     pub alt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub aspect_ratio: Option<AspectRatio>,
 }
 
-/// width:height represents an aspect ratio. It may be approximate,
-/// and may not correspond to absolute dimensions in any given unit.
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
-pub struct AspectRatio {
-    pub width: usize,
-    pub height: usize,
-}
-
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(tag = "$type")]
 #[serde(rename = "app.bsky.embed.images#view")]
@@ -42,5 +57,6 @@ pub struct ViewImage {
     pub fullsize: String,
     /// Alt text description of the image, for accessibility.
     pub alt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub aspect_ratio: Option<AspectRatio>,
 }
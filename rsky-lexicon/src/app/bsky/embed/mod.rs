@@ -1,3 +1,4 @@
+pub mod defs;
 pub mod external;
 pub mod images;
 pub mod record;
@@ -9,49 +10,99 @@ use crate::app::bsky::embed::images::{Images, View as ImagesView};
 use crate::app::bsky::embed::record::{Record, View as RecordView};
 use crate::app::bsky::embed::record_with_media::{RecordWithMedia, View as RecordWithMediaView};
 use crate::app::bsky::embed::video::{Video, View as VideoView};
+use thiserror::Error;
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
-#[serde(tag = "$type")]
-pub enum MediaUnion {
-    #[serde(rename = "app.bsky.embed.images")]
-    Images(Images),
-    #[serde(rename = "app.bsky.embed.video")]
-    Video(Video),
-    #[serde(rename = "app.bsky.embed.external")]
-    External(External),
+/// A lexicon constraint an embed doesn't meet.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum EmbedError {
+    #[error("{0} images, at most {max} allowed", max = images::MAX_IMAGES)]
+    TooManyImages(usize),
+    #[error("{0} captions, at most {max} allowed", max = video::MAX_CAPTIONS)]
+    TooManyCaptions(usize),
+    #[error("alt text is {0} bytes, at most {max} allowed", max = video::MAX_VIDEO_ALT_LENGTH)]
+    AltTooLong(usize),
+    #[error("{field} has unexpected mime type `{mime_type}`")]
+    InvalidMimeType {
+        field: &'static str,
+        mime_type: String,
+    },
+    #[error("{field} is {size} bytes, at most {max} allowed")]
+    BlobTooLarge {
+        field: &'static str,
+        size: i64,
+        max: i64,
+    },
+    #[error("invalid aspect ratio {width}:{height}")]
+    InvalidAspectRatio { width: usize, height: usize },
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
-#[serde(tag = "$type")]
-pub enum MediaViewUnion {
-    #[serde(rename = "app.bsky.embed.images#view")]
-    ImagesView(ImagesView),
-    #[serde(rename = "app.bsky.embed.video#view")]
-    VideoView(VideoView),
-    #[serde(rename = "app.bsky.embed.external#view")]
-    ExternalView(ExternalView),
+open_union! {
+    pub enum MediaUnion {
+        #[serde(rename = "app.bsky.embed.images")]
+        Images(Images),
+        #[serde(rename = "app.bsky.embed.video")]
+        Video(Video),
+        #[serde(rename = "app.bsky.embed.external")]
+        External(External),
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
-#[serde(tag = "$type")]
-pub enum Embeds {
-    #[serde(rename = "app.bsky.embed.images")]
-    Images(Images),
+impl MediaUnion {
+    pub fn validate(&self) -> Result<(), EmbedError> {
+        match self {
+            MediaUnion::Images(images) => images.validate(),
+            MediaUnion::Video(video) => video.validate(),
+            MediaUnion::External(external) => external.validate(),
+            MediaUnion::Unknown(_) => Ok(()),
+        }
+    }
+}
+
+open_union! {
+    pub enum MediaViewUnion {
+        #[serde(rename = "app.bsky.embed.images#view")]
+        ImagesView(ImagesView),
+        #[serde(rename = "app.bsky.embed.video#view")]
+        VideoView(VideoView),
+        #[serde(rename = "app.bsky.embed.external#view")]
+        ExternalView(ExternalView),
+    }
+}
 
-    #[serde(rename = "app.bsky.embed.video")]
-    Video(Video),
+open_union! {
+    pub enum Embeds {
+        #[serde(rename = "app.bsky.embed.images")]
+        Images(Images),
 
-    #[serde(
-        alias = "app.bsky.embed.external",
-        alias = "app.bsky.embed.external#main"
-    )]
-    External(External),
+        #[serde(rename = "app.bsky.embed.video")]
+        Video(Video),
 
-    #[serde(rename = "app.bsky.embed.record")]
-    Record(Record),
+        #[serde(
+            rename = "app.bsky.embed.external",
+            alias = "app.bsky.embed.external#main"
+        )]
+        External(External),
+
+        #[serde(rename = "app.bsky.embed.record")]
+        Record(Record),
+
+        #[serde(rename = "app.bsky.embed.recordWithMedia")]
+        RecordWithMedia(RecordWithMedia),
+    }
+}
 
-    #[serde(rename = "app.bsky.embed.recordWithMedia")]
-    RecordWithMedia(RecordWithMedia),
+impl Embeds {
+    /// Checks the embed against its lexicon's limits before it's written to a record.
+    pub fn validate(&self) -> Result<(), EmbedError> {
+        match self {
+            Embeds::Images(images) => images.validate(),
+            Embeds::Video(video) => video.validate(),
+            Embeds::External(external) => external.validate(),
+            Embeds::Record(_) => Ok(()),
+            Embeds::RecordWithMedia(record_with_media) => record_with_media.validate(),
+            Embeds::Unknown(_) => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -1,5 +1,5 @@
 use crate::app::bsky::embed::record::{Record, View as ViewRecord};
-use crate::app::bsky::embed::{MediaUnion, MediaViewUnion};
+use crate::app::bsky::embed::{EmbedError, MediaUnion, MediaViewUnion};
 
 /// A representation of a record embedded in a Bluesky record (eg, a post),
 /// alongside other compatible embeds. For example, a quote post and image,
@@ -11,6 +11,12 @@ pub struct RecordWithMedia {
     pub media: MediaUnion,
 }
 
+impl RecordWithMedia {
+    pub fn validate(&self) -> Result<(), EmbedError> {
+        self.media.validate()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(tag = "$type")]
 #[serde(rename = "app.bsky.embed.recordWithMedia#view")]
@@ -1,16 +1,51 @@
-use crate::app::bsky::embed::images::AspectRatio;
+use crate::app::bsky::embed::defs::{validate_blob, AspectRatio};
+use crate::app::bsky::embed::EmbedError;
 use crate::com::atproto::repo::Blob;
 
+pub const MAX_VIDEO_SIZE: i64 = 100_000_000;
+pub const MAX_CAPTIONS: usize = 20;
+pub const MAX_CAPTION_SIZE: i64 = 20_000;
+/// Lexicon `maxLength` of the alt text, in UTF-8 bytes.
+pub const MAX_VIDEO_ALT_LENGTH: usize = 10_000;
+
+/// A video embedded in a Bluesky record (eg, a post).
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Video {
+    /// The mp4 video file. May be up to 100mb, formerly limited to 50mb.
     pub video: Blob,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub captions: Option<Vec<Caption>>,
     /// Alt text description of video image, for accessibility
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub alt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub aspect_ratio: Option<AspectRatio>,
 }
 
+impl Video {
+    pub fn validate(&self) -> Result<(), EmbedError> {
+        validate_blob("video", &self.video, "video/mp4", MAX_VIDEO_SIZE)?;
+        if let Some(captions) = &self.captions {
+            if captions.len() > MAX_CAPTIONS {
+                return Err(EmbedError::TooManyCaptions(captions.len()));
+            }
+            for caption in captions {
+                validate_blob("caption", &caption.file, "text/vtt", MAX_CAPTION_SIZE)?;
+            }
+        }
+        if let Some(alt) = &self.alt {
+            if alt.len() > MAX_VIDEO_ALT_LENGTH {
+                return Err(EmbedError::AltTooLong(alt.len()));
+            }
+        }
+        if let Some(aspect_ratio) = &self.aspect_ratio {
+            aspect_ratio.validate()?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Caption {
     pub lang: String,
@@ -24,7 +59,10 @@ pub struct Caption {
 pub struct View {
     pub cid: String,
     pub playlist: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub thumbnail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub alt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub aspect_ratio: Option<AspectRatio>,
 }
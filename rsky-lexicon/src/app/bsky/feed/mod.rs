@@ -37,11 +37,11 @@ pub struct Post {
     pub tags: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
-#[serde(tag = "$type")]
-pub enum PostLabels {
-    #[serde(rename = "com.atproto.label.defs#selfLabels")]
-    SelfLabels(SelfLabels),
+open_union! {
+    pub enum PostLabels {
+        #[serde(rename = "com.atproto.label.defs#selfLabels")]
+        SelfLabels(SelfLabels),
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
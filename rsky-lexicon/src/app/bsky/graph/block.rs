@@ -0,0 +1,13 @@
+use chrono::{DateTime, Utc};
+
+/// Record declaring a 'block' relationship against another account. NOTE: blocks are public in
+/// Bluesky; see blog posts for details.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "$type")]
+#[serde(rename = "app.bsky.graph.block")]
+#[serde(rename_all = "camelCase")]
+pub struct Block {
+    /// DID of the account to be blocked.
+    pub subject: String,
+    pub created_at: DateTime<Utc>,
+}
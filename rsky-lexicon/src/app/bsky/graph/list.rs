@@ -0,0 +1,34 @@
+use crate::app::bsky::graph::ListPurpose;
+use crate::app::bsky::richtext::Facet;
+use crate::com::atproto::label::SelfLabels;
+use crate::com::atproto::repo::Blob;
+use chrono::{DateTime, Utc};
+
+/// Record representing a list of accounts (actors). Scope includes both moderation-oriented
+/// lists and curration-oriented lists.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "$type")]
+#[serde(rename = "app.bsky.graph.list")]
+#[serde(rename_all = "camelCase")]
+pub struct List {
+    /// Defines the purpose of the list (aka, moderation-oriented or curration-oriented)
+    pub purpose: ListPurpose,
+    /// Display name for list; can not be empty.
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_facets: Option<Vec<Facet>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar: Option<Blob>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<ListLabels>,
+    pub created_at: DateTime<Utc>,
+}
+
+open_union! {
+    pub enum ListLabels {
+        #[serde(rename = "com.atproto.label.defs#selfLabels")]
+        SelfLabels(SelfLabels),
+    }
+}
@@ -0,0 +1,12 @@
+use chrono::{DateTime, Utc};
+
+/// Record representing a block relationship against an entire list of accounts (actors).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "$type")]
+#[serde(rename = "app.bsky.graph.listblock")]
+#[serde(rename_all = "camelCase")]
+pub struct ListBlock {
+    /// Reference (AT-URI) to the mod list record.
+    pub subject: String,
+    pub created_at: DateTime<Utc>,
+}
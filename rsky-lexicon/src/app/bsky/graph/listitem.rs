@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+
+/// Record representing an account's inclusion on a specific list. The AppView will ignore
+/// duplicate listitem records.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "$type")]
+#[serde(rename = "app.bsky.graph.listitem")]
+#[serde(rename_all = "camelCase")]
+pub struct ListItem {
+    /// The account which is included on the list.
+    pub subject: String,
+    /// Reference (AT-URI) to the list record (app.bsky.graph.list).
+    pub list: String,
+    pub created_at: DateTime<Utc>,
+}
@@ -1,6 +1,12 @@
+pub mod block;
 pub mod follow;
+pub mod list;
+pub mod listblock;
+pub mod listitem;
+pub mod starterpack;
 
 use crate::app::bsky::actor::{ProfileView, ProfileViewBasic};
+use crate::app::bsky::feed::GeneratorView;
 use crate::app::bsky::richtext::Facet;
 use crate::com::atproto::label::Label;
 use serde_json::Value;
@@ -54,6 +60,33 @@ pub struct StarterPackViewBasic {
     pub indexed_at: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "$type")]
+#[serde(rename = "app.bsky.graph.defs#starterPackView")]
+#[serde(rename_all = "camelCase")]
+pub struct StarterPackView {
+    pub uri: String,
+    pub cid: String,
+    pub record: Value,
+    pub creator: ProfileViewBasic,
+    pub list: Option<ListViewBasic>,
+    pub list_items_sample: Option<Vec<ListItemView>>,
+    pub feeds: Option<Vec<GeneratorView>>,
+    pub joined_week_count: Option<usize>,
+    pub joined_all_time_count: Option<usize>,
+    pub labels: Option<Vec<Label>>,
+    pub indexed_at: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "$type")]
+#[serde(rename = "app.bsky.graph.defs#listItemView")]
+#[serde(rename_all = "camelCase")]
+pub struct ListItemView {
+    pub uri: String,
+    pub subject: ProfileView,
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum ListPurpose {
     /// A list of actors to apply an aggregate moderation action (mute/block) on.
@@ -72,3 +105,107 @@ pub struct ListViewerState {
     pub muted: Option<bool>,
     pub blocked: Option<String>,
 }
+
+/// Indicates that a handle or DID could not be resolved.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotFoundActor {
+    pub actor: String,
+    pub not_found: bool,
+}
+
+/// Lists the bi-directional graph relationships between one actor (not indicated in the object),
+/// and the target actors (the DID included in the object).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Relationship {
+    pub did: String,
+    /// If the actor follows this DID, this is the AT-URI of the follow record.
+    pub following: Option<String>,
+    /// If the actor is followed by this DID, contains the AT-URI of the follow record.
+    pub followed_by: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "$type")]
+pub enum RelationshipUnion {
+    #[serde(rename = "app.bsky.graph.defs#relationship")]
+    Relationship(Relationship),
+    #[serde(rename = "app.bsky.graph.defs#notFoundActor")]
+    NotFoundActor(NotFoundActor),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GetFollowsOutput {
+    pub subject: ProfileView,
+    pub cursor: Option<String>,
+    pub follows: Vec<ProfileView>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GetFollowersOutput {
+    pub subject: ProfileView,
+    pub cursor: Option<String>,
+    pub followers: Vec<ProfileView>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GetBlocksOutput {
+    pub cursor: Option<String>,
+    pub blocks: Vec<ProfileView>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GetMutesOutput {
+    pub cursor: Option<String>,
+    pub mutes: Vec<ProfileView>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GetRelationshipsOutput {
+    pub actor: Option<String>,
+    pub relationships: Vec<RelationshipUnion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GetListOutput {
+    pub cursor: Option<String>,
+    pub list: ListView,
+    pub items: Vec<ListItemView>,
+}
+
+/// Output of getLists, getListBlocks and getListMutes.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GetListsOutput {
+    pub cursor: Option<String>,
+    pub lists: Vec<ListView>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetStarterPackOutput {
+    pub starter_pack: StarterPackView,
+}
+
+/// Output of getStarterPacks and getActorStarterPacks.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetStarterPacksOutput {
+    pub cursor: Option<String>,
+    pub starter_packs: Vec<StarterPackViewBasic>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MuteActorInput {
+    pub actor: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MuteActorListInput {
+    pub list: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MuteThreadInput {
+    pub root: String,
+}
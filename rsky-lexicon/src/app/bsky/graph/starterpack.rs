@@ -0,0 +1,26 @@
+use crate::app::bsky::richtext::Facet;
+use chrono::{DateTime, Utc};
+
+/// Record defining a starter pack of actors and feeds for new users.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "$type")]
+#[serde(rename = "app.bsky.graph.starterpack")]
+#[serde(rename_all = "camelCase")]
+pub struct Starterpack {
+    /// Display name for starter pack; can not be empty.
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_facets: Option<Vec<Facet>>,
+    /// Reference (AT-URI) to the list record.
+    pub list: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feeds: Option<Vec<FeedItem>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FeedItem {
+    pub uri: String,
+}
@@ -1,3 +1,7 @@
+use crate::app::bsky::actor::ProfileView;
+use crate::com::atproto::label::Label;
+use serde_json::Value;
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RegisterPushInput {
@@ -6,3 +10,48 @@ pub struct RegisterPushInput {
     pub platform: String,
     pub app_id: String,
 }
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub uri: String,
+    pub cid: String,
+    pub author: ProfileView,
+    /// Expected values are 'like', 'repost', 'follow', 'mention', 'reply', 'quote',
+    /// and 'starterpack-joined'.
+    pub reason: String,
+    pub reason_subject: Option<String>,
+    pub record: Value,
+    pub is_read: bool,
+    pub indexed_at: String,
+    pub labels: Option<Vec<Label>>,
+}
+
+/// Enumerate notifications for the requesting account. Requires auth.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListNotificationsOutput {
+    pub cursor: Option<String>,
+    pub notifications: Vec<Notification>,
+    pub priority: Option<bool>,
+    pub seen_at: Option<String>,
+}
+
+/// Count the number of unread notifications for the requesting account. Requires auth.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GetUnreadCountOutput {
+    pub count: usize,
+}
+
+/// Notify server that the requesting account has seen notifications. Requires auth.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSeenInput {
+    pub seen_at: String,
+}
+
+/// Set notification-related preferences for an account. Requires auth.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PutPreferencesInput {
+    pub priority: bool,
+}
@@ -1,18 +1,25 @@
+pub mod offsets;
+
+use crate::app::bsky::richtext::offsets::{
+    check_char_boundary, grapheme_to_utf8, utf16_to_utf8, utf8_to_grapheme, utf8_to_utf16,
+    OffsetError,
+};
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Facet {
     pub index: ByteSlice,
     pub features: Vec<Features>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
-#[serde(tag = "$type")]
-pub enum Features {
-    #[serde(rename = "app.bsky.richtext.facet#mention")]
-    Mention(Mention),
-    #[serde(rename = "app.bsky.richtext.facet#link")]
-    Link(Link),
-    #[serde(rename = "app.bsky.richtext.facet#tag")]
-    Tag(Tag),
+open_union! {
+    pub enum Features {
+        #[serde(rename = "app.bsky.richtext.facet#mention")]
+        Mention(Mention),
+        #[serde(rename = "app.bsky.richtext.facet#link")]
+        Link(Link),
+        #[serde(rename = "app.bsky.richtext.facet#tag")]
+        Tag(Tag),
+    }
 }
 
 /// Facet feature for mention of another account. The text is usually a handle, including a '@'
@@ -48,3 +55,61 @@ pub struct ByteSlice {
     #[serde(rename = "byteEnd")]
     pub byte_end: usize,
 }
+
+impl ByteSlice {
+    /// Checks the range is in order, within `text` and on character boundaries, so slicing
+    /// `text` by it can't panic.
+    pub fn validate(&self, text: &str) -> Result<(), OffsetError> {
+        if self.byte_start > self.byte_end {
+            return Err(OffsetError::Backwards {
+                start: self.byte_start,
+                end: self.byte_end,
+            });
+        }
+        check_char_boundary(text, self.byte_start)?;
+        check_char_boundary(text, self.byte_end)
+    }
+
+    /// The part of `text` the facet covers.
+    pub fn slice<'a>(&self, text: &'a str) -> Result<&'a str, OffsetError> {
+        self.validate(text)?;
+        Ok(&text[self.byte_start..self.byte_end])
+    }
+
+    /// From a range of UTF-16 code units, as Javascript string indices count.
+    pub fn from_utf16(text: &str, start: usize, end: usize) -> Result<Self, OffsetError> {
+        let slice = ByteSlice {
+            byte_start: utf16_to_utf8(text, start)?,
+            byte_end: utf16_to_utf8(text, end)?,
+        };
+        slice.validate(text)?;
+        Ok(slice)
+    }
+
+    pub fn to_utf16(&self, text: &str) -> Result<(usize, usize), OffsetError> {
+        self.validate(text)?;
+        Ok((
+            utf8_to_utf16(text, self.byte_start)?,
+            utf8_to_utf16(text, self.byte_end)?,
+        ))
+    }
+
+    /// From a range of grapheme clusters, i.e. characters as the user sees them.
+    pub fn from_graphemes(text: &str, start: usize, end: usize) -> Result<Self, OffsetError> {
+        let slice = ByteSlice {
+            byte_start: grapheme_to_utf8(text, start)?,
+            byte_end: grapheme_to_utf8(text, end)?,
+        };
+        slice.validate(text)?;
+        Ok(slice)
+    }
+
+    /// Fails when either end splits a grapheme cluster, e.g. an emoji with a skin tone.
+    pub fn to_graphemes(&self, text: &str) -> Result<(usize, usize), OffsetError> {
+        self.validate(text)?;
+        Ok((
+            utf8_to_grapheme(text, self.byte_start)?,
+            utf8_to_grapheme(text, self.byte_end)?,
+        ))
+    }
+}
@@ -0,0 +1,135 @@
+//! Conversions between the offsets facets are indexed by (UTF-8 bytes) and the ones editors and
+//! other languages hand out: UTF-16 code units, as in Javascript strings, and grapheme clusters,
+//! which is what users see as characters. Every conversion checks that the offset lands on a
+//! boundary of both encodings instead of panicking or silently shifting the range.
+
+use thiserror::Error;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum OffsetError {
+    #[error("offset {offset} is past the end of the text ({len})")]
+    OutOfBounds { offset: usize, len: usize },
+    #[error("range {start}..{end} ends before it starts")]
+    Backwards { start: usize, end: usize },
+    #[error("byte offset {0} is inside a character")]
+    NotCharBoundary(usize),
+    #[error("UTF-16 offset {0} is inside a surrogate pair")]
+    SplitSurrogatePair(usize),
+    #[error("byte offset {0} is inside a grapheme cluster")]
+    NotGraphemeBoundary(usize),
+}
+
+/// Checks `byte` is in `text` and starts a character (or is its end).
+pub fn check_char_boundary(text: &str, byte: usize) -> Result<(), OffsetError> {
+    if byte > text.len() {
+        return Err(OffsetError::OutOfBounds {
+            offset: byte,
+            len: text.len(),
+        });
+    }
+    if !text.is_char_boundary(byte) {
+        return Err(OffsetError::NotCharBoundary(byte));
+    }
+    Ok(())
+}
+
+pub fn utf8_to_utf16(text: &str, byte: usize) -> Result<usize, OffsetError> {
+    check_char_boundary(text, byte)?;
+    Ok(text[..byte].encode_utf16().count())
+}
+
+pub fn utf16_to_utf8(text: &str, utf16: usize) -> Result<usize, OffsetError> {
+    let mut units = 0;
+    for (byte, c) in text.char_indices() {
+        if units == utf16 {
+            return Ok(byte);
+        }
+        if units > utf16 {
+            return Err(OffsetError::SplitSurrogatePair(utf16));
+        }
+        units += c.len_utf16();
+    }
+    match units {
+        units if units == utf16 => Ok(text.len()),
+        units if units > utf16 => Err(OffsetError::SplitSurrogatePair(utf16)),
+        units => Err(OffsetError::OutOfBounds {
+            offset: utf16,
+            len: units,
+        }),
+    }
+}
+
+/// Number of grapheme clusters in `text`, which is how post length limits are counted.
+pub fn grapheme_len(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+pub fn utf8_to_grapheme(text: &str, byte: usize) -> Result<usize, OffsetError> {
+    check_char_boundary(text, byte)?;
+    let mut count = 0;
+    for (start, _) in text.grapheme_indices(true) {
+        if start == byte {
+            return Ok(count);
+        }
+        if start > byte {
+            return Err(OffsetError::NotGraphemeBoundary(byte));
+        }
+        count += 1;
+    }
+    match byte == text.len() {
+        true => Ok(count),
+        false => Err(OffsetError::NotGraphemeBoundary(byte)),
+    }
+}
+
+pub fn grapheme_to_utf8(text: &str, grapheme: usize) -> Result<usize, OffsetError> {
+    let mut count = 0;
+    for (start, _) in text.grapheme_indices(true) {
+        if count == grapheme {
+            return Ok(start);
+        }
+        count += 1;
+    }
+    match count == grapheme {
+        true => Ok(text.len()),
+        false => Err(OffsetError::OutOfBounds {
+            offset: grapheme,
+            len: count,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offsets() {
+        // 'é' is 2 bytes, the flag 8 bytes / 4 UTF-16 units / 1 grapheme
+        let text = "é 🇯🇵 #tag";
+        assert_eq!(utf8_to_utf16(text, 3), Ok(2));
+        assert_eq!(utf16_to_utf8(text, 2), Ok(3));
+        assert_eq!(utf8_to_utf16(text, 11), Ok(6));
+        assert_eq!(utf16_to_utf8(text, 6), Ok(11));
+        assert_eq!(utf16_to_utf8(text, 11), Ok(text.len()));
+        assert_eq!(utf8_to_grapheme(text, 11), Ok(3));
+        assert_eq!(grapheme_to_utf8(text, 4), Ok(12));
+        assert_eq!(grapheme_len(text), 8);
+
+        assert_eq!(utf8_to_utf16(text, 1), Err(OffsetError::NotCharBoundary(1)));
+        assert_eq!(
+            utf16_to_utf8(text, 3),
+            Err(OffsetError::SplitSurrogatePair(3))
+        );
+        // between the two regional indicators of the flag
+        assert_eq!(
+            utf8_to_grapheme(text, 7),
+            Err(OffsetError::NotGraphemeBoundary(7))
+        );
+        assert_eq!(
+            grapheme_to_utf8(text, 9),
+            Err(OffsetError::OutOfBounds { offset: 9, len: 8 })
+        );
+    }
+}
@@ -41,6 +41,13 @@ pub struct CreateAccountInput {
     pub recovery_key: Option<String>,
     #[serde(rename(deserialize = "plcOp", serialize = "plcOp"))]
     pub plc_op: Option<String>,
+    /// Not part of the lexicon; seeds the bootstrapped profile record when the PDS enables it.
+    #[serde(
+        rename(deserialize = "displayName", serialize = "displayName"),
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub display_name: Option<String>,
 }
 
 /// Create an App Password
@@ -48,6 +55,10 @@ pub struct CreateAccountInput {
 pub struct CreateAppPasswordInput {
     /// A short name for the App Password, to help distinguish them.
     pub name: String,
+    /// If an app password has 'privileged' access to possibly sensitive account state. Meant for
+    /// use with trusted clients.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub privileged: Option<bool>,
 }
 
 /// Create an authentication session.
@@ -56,6 +67,13 @@ pub struct CreateSessionInput {
     /// Handle or other identifier supported by the server for the authenticating user.
     pub identifier: String,
     pub password: String,
+    /// Label for the session, such as the device it was created on.
+    #[serde(rename = "sessionName", skip_serializing_if = "Option::is_none")]
+    pub session_name: Option<String>,
+    /// Code from an authenticator app, or a recovery code, for accounts with two-factor
+    /// authentication.
+    #[serde(rename = "authFactorToken", skip_serializing_if = "Option::is_none")]
+    pub auth_factor_token: Option<String>,
 }
 
 /// Delete an actor's account with a token and password. Can only be called after
@@ -137,6 +155,8 @@ pub struct CreateAppPasswordOutput {
     pub password: String,
     #[serde(rename = "createdAt")]
     pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub privileged: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -236,6 +256,14 @@ pub struct ListAppPasswordsOutput {
     pub passwords: Vec<AppPassword>,
 }
 
+/// Optional body for refreshSession.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RefreshSessionInput {
+    /// Renames the session; the existing name is kept if unset.
+    #[serde(rename = "sessionName", skip_serializing_if = "Option::is_none")]
+    pub session_name: Option<String>,
+}
+
 /// Refresh an authentication session. Requires auth using the 'refreshJwt' (not the 'accessJwt').
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct RefreshSessionOutput {
@@ -300,4 +328,6 @@ pub struct AppPassword {
     pub name: String,
     #[serde(rename = "createdAt")]
     pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub privileged: Option<bool>,
 }
@@ -79,6 +79,14 @@ pub struct ListReposOutput {
     pub repos: Vec<RefRepo>,
 }
 
+/// Enumerates all the DIDs which have records with the given collection NSID.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListReposByCollectionOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    pub repos: Vec<RefRepoByCollection>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum RepoStatus {
@@ -179,6 +187,11 @@ pub struct RefRepo {
     pub status: Option<RepoStatus>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RefRepoByCollection {
+    pub did: String,
+}
+
 pub fn deserialize_cid_v1<'de, D>(deserializer: D) -> Result<Cid, D::Error>
 where
     D: Deserializer<'de>,
@@ -0,0 +1,63 @@
+use crate::com::atproto::label::Label;
+use serde::{Deserialize, Serialize};
+
+/// Check accounts location in signup queue.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckSignupQueueOutput {
+    pub activated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub place_in_queue: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_time_ms: Option<i64>,
+}
+
+/// DEPRECATED: use queryLabels or subscribeLabels instead -- Fetch all labels from a labeler
+/// created after a certain date.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct FetchLabelsOutput {
+    pub labels: Vec<Label>,
+}
+
+/// Request a verification code to be sent to the supplied phone number
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestPhoneVerificationInput {
+    pub phone_number: String,
+}
+
+/// Checks whether the provided handle is available. If the handle is not available, available
+/// suggestions will be returned.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CheckHandleAvailabilityOutput {
+    /// Echo of the input handle, normalized.
+    pub handle: String,
+    pub result: CheckHandleAvailabilityResult,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "$type")]
+pub enum CheckHandleAvailabilityResult {
+    #[serde(rename = "com.atproto.temp.checkHandleAvailability#resultAvailable")]
+    Available(ResultAvailable),
+    #[serde(rename = "com.atproto.temp.checkHandleAvailability#resultUnavailable")]
+    Unavailable(ResultUnavailable),
+}
+
+/// Indicates the provided handle is available.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ResultAvailable {}
+
+/// Indicates the provided handle is unavailable and gives suggestions of available handles.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ResultUnavailable {
+    /// List of suggested handles based on the provided inputs.
+    pub suggestions: Vec<Suggestion>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Suggestion {
+    pub handle: String,
+    /// Method used to build this suggestion. Should be considered opaque to clients.
+    pub method: String,
+}
@@ -1,10 +1,19 @@
+//! Lexicon unions that records are free to extend, like embeds, facet features and self-labels,
+//! are open: besides their known variants they have an `Unknown(UnknownType)` variant that keeps
+//! any other `$type` as-is, so a new kind of embed doesn't fail the whole post. A known `$type`
+//! whose body is malformed is still an error rather than an `Unknown`.
+
 #[macro_use]
 extern crate serde_derive;
 
 extern crate serde;
 extern crate serde_json;
 
+#[macro_use]
+pub mod open_union;
+
 pub mod app;
 pub mod blob_refs;
 pub mod chat;
 pub mod com;
+pub mod xyz;
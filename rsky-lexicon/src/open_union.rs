@@ -0,0 +1,126 @@
+/// A member of an open union whose `$type` isn't one of the union's known variants, kept as-is
+/// so it round-trips unchanged.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct UnknownType {
+    #[serde(rename = "$type")]
+    pub r#type: String,
+    #[serde(flatten)]
+    pub data: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Declares an open union: an internally tagged enum of the given known variants plus
+/// `Unknown(UnknownType)` for any other `$type`. Serde would also fall back to `Unknown` when a
+/// known variant's body doesn't parse, so deserializing rejects an `Unknown` whose `$type` is
+/// one of the known ones instead of silently accepting the malformed member.
+macro_rules! open_union {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $(
+                #[serde(rename = $nsid:literal $(, alias = $alias:literal)*)]
+                $variant:ident($ty:ty),
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Serialize)]
+        #[serde(tag = "$type")]
+        pub enum $name {
+            $(
+                #[serde(rename = $nsid)]
+                $variant($ty),
+            )*
+            #[serde(untagged)]
+            Unknown($crate::open_union::UnknownType),
+        }
+
+        impl $name {
+            /// Every `$type` this union parses into a known variant.
+            pub const KNOWN_TYPES: &'static [&'static str] = &[$($nsid, $($alias,)*)*];
+        }
+
+        impl<'de> ::serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: ::serde::Deserializer<'de>,
+            {
+                #[derive(Deserialize)]
+                #[serde(tag = "$type")]
+                enum Members {
+                    $(
+                        #[serde(rename = $nsid $(, alias = $alias)*)]
+                        $variant($ty),
+                    )*
+                    #[serde(untagged)]
+                    Unknown($crate::open_union::UnknownType),
+                }
+
+                match Members::deserialize(deserializer)? {
+                    $(Members::$variant(member) => Ok($name::$variant(member)),)*
+                    Members::Unknown(unknown) => {
+                        if $name::KNOWN_TYPES.contains(&unknown.r#type.as_str()) {
+                            return Err(<D::Error as ::serde::de::Error>::custom(format_args!(
+                                "malformed `{}` member of {}",
+                                unknown.r#type,
+                                stringify!($name)
+                            )));
+                        }
+                        Ok($name::Unknown(unknown))
+                    }
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::bsky::embed::Embeds;
+    use crate::app::bsky::richtext::{Features, Mention};
+    use serde_json::json;
+
+    #[test]
+    fn test_known_member_round_trips() {
+        let value = json!({"$type": "app.bsky.richtext.facet#mention", "did": "did:plc:abc"});
+        let feature: Features = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(
+            feature,
+            Features::Mention(Mention {
+                did: "did:plc:abc".to_string()
+            })
+        );
+        assert_eq!(serde_json::to_value(&feature).unwrap(), value);
+    }
+
+    #[test]
+    fn test_unknown_member_round_trips() {
+        let value = json!({"$type": "com.example.facet#spoiler", "hidden": true});
+        let feature: Features = serde_json::from_value(value.clone()).unwrap();
+        match &feature {
+            Features::Unknown(unknown) => {
+                assert_eq!(unknown.r#type, "com.example.facet#spoiler");
+                assert_eq!(unknown.data.get("hidden"), Some(&json!(true)));
+            }
+            other => panic!("expected an unknown member, got {other:?}"),
+        }
+        assert_eq!(serde_json::to_value(&feature).unwrap(), value);
+    }
+
+    #[test]
+    fn test_malformed_known_member_is_rejected() {
+        let value =
+            json!({"$type": "app.bsky.richtext.facet#mention", "uri": "https://example.com"});
+        let err = serde_json::from_value::<Features>(value).unwrap_err();
+        assert!(err.to_string().contains("app.bsky.richtext.facet#mention"));
+
+        // aliases of a known variant are known too
+        let value = json!({"$type": "app.bsky.embed.external#main", "external": "nope"});
+        assert!(serde_json::from_value::<Embeds>(value).is_err());
+    }
+
+    #[test]
+    fn test_member_without_type_is_rejected() {
+        let value = json!({"did": "did:plc:abc"});
+        assert!(serde_json::from_value::<Features>(value).is_err());
+    }
+}
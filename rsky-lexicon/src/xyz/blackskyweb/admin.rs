@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Erase everything the PDS stores about an account, as an administrator.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ErasePersonalDataInput {
+    pub did: String,
+}
+
+/// Whether the PDS is turning away writes for maintenance.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceMode {
+    pub read_only: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Whether an xrpc method is switched off on this PDS.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteFlag {
+    pub nsid: String,
+    pub disabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct GetRouteFlagsOutput {
+    pub flags: Vec<RouteFlag>,
+}
+
+/// A name held back from handles on the PDS's service domains, e.g. `support` for
+/// `support.example.com`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReservedHandle {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct AddReservedHandleInput {
+    pub name: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct RemoveReservedHandleInput {
+    pub name: String,
+}
+
+/// Names reserved at runtime. The built-in and configured ones aren't listed.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct GetReservedHandlesOutput {
+    pub handles: Vec<ReservedHandle>,
+}
+
+/// Turn off two-factor authentication for an account that has lost its authenticator app
+/// and recovery codes.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ResetTotpInput {
+    pub did: String,
+}
+
+/// An account and the accounts that signed up with its invite codes.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteReferral {
+    pub did: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
+    /// Code this account signed up with, if it used one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invite_code: Option<String>,
+    pub taken_down: bool,
+    /// Accounts that signed up with this account's codes.
+    pub invited_count: i64,
+    /// Accounts anywhere below this one in the tree.
+    pub descendant_count: i64,
+    /// Of those, how many are taken down.
+    pub taken_down_descendant_count: i64,
+    /// Empty past the requested depth, even if `invited_count` isn't.
+    pub invited: Vec<InviteReferral>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GetInviteReferralTreeOutput {
+    /// Owner of the code the root account signed up with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invited_by: Option<String>,
+    pub tree: InviteReferral,
+    /// Set when the tree was too large to walk fully, so counts are lower bounds.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GetRepoCommitHistoryOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    pub commits: Vec<RepoCommitView>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GetDeletedRecordsOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    pub records: Vec<DeletedRecordView>,
+}
+
+/// Re-sign an account's DID document and repo with the server's current keys.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RotateAccountKeysInput {
+    pub did: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RotateAccountKeysOutput {
+    pub did: String,
+    #[serde(rename = "signingKey")]
+    pub signing_key: String,
+    #[serde(rename = "rotationKeys")]
+    pub rotation_keys: Vec<String>,
+    /// Rev of the re-signed repo commit.
+    pub rev: String,
+}
+
+/// Rebuild an account's repo tree and record index from its records, as a fresh commit.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SquashRepoInput {
+    pub did: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SquashRepoOutput {
+    pub did: String,
+    /// CID of the rebuilt repo commit.
+    pub cid: String,
+    /// Rev of the rebuilt repo commit.
+    pub rev: String,
+}
+
+/// Recompute record to blob associations from record contents, for one account or a batch of
+/// them in DID order.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RepairRecordBlobsInput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub did: Option<String>,
+    /// Without `did`, repair the accounts after this one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RepairRecordBlobsOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    pub repos: Vec<RecordBlobRepair>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RecordBlobRepair {
+    pub did: String,
+    /// Associations the records have that were missing.
+    pub added: i64,
+    /// Associations no record has any more.
+    pub removed: i64,
+}
+
+/// Drop cached identity resolutions so they're fetched fresh. With neither field set, the
+/// whole cache is purged.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PurgeIdentityCacheInput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub did: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
+}
+
+// Defs
+// ----
+
+/// A record its owner deleted, kept for moderators until `expiresAt`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DeletedRecordView {
+    pub uri: String,
+    pub cid: String,
+    pub value: Value,
+    #[serde(rename = "repoRev", skip_serializing_if = "Option::is_none")]
+    pub repo_rev: Option<String>,
+    #[serde(rename = "indexedAt")]
+    pub indexed_at: String,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+/// A commit retained in a repo's commit history.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RepoCommitView {
+    pub rev: String,
+    pub cid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    pub creates: i32,
+    pub updates: i32,
+    pub deletes: i32,
+    #[serde(rename = "committedAt")]
+    pub committed_at: String,
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+/// A secret for an authenticator app. Two-factor authentication isn't on until a first code
+/// from the app is confirmed with `confirmTotp`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CreateTotpSecretOutput {
+    /// Base32, for apps that take the secret typed in.
+    pub secret: String,
+    /// `otpauth://` URI, for apps that scan it as a QR code.
+    pub uri: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ConfirmTotpInput {
+    pub code: String,
+}
+
+/// Single-use codes that stand in for the authenticator app, shown only this once.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmTotpOutput {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Turn off two-factor authentication, with a current or recovery code.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DisableTotpInput {
+    pub code: String,
+}
+
+/// Choose which emails the PDS sends to the account.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UpdateEmailPreferenceInput {
+    /// `all`, or `security` to only receive mail needed to secure or recover the account.
+    pub preference: String,
+}
+
+/// Choose the language of mail from the PDS.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UpdateLocaleInput {
+    /// A language tag such as `de` or `pt-BR`. Unset, mail follows the requesting client's
+    /// Accept-Language.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+/// A signed-in session on the account. Returned by listSessions.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SessionView {
+    pub id: String,
+    #[serde(rename = "sessionName", skip_serializing_if = "Option::is_none")]
+    pub session_name: Option<String>,
+    #[serde(rename = "appPasswordName", skip_serializing_if = "Option::is_none")]
+    pub app_password_name: Option<String>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ListSessionsOutput {
+    pub sessions: Vec<SessionView>,
+}
+
+/// A sign-in attempt against the account. Returned by listLoginAttempts.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct LoginAttemptView {
+    pub success: bool,
+    /// `password` or `app-password`.
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(rename = "userAgent", skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ListLoginAttemptsOutput {
+    pub attempts: Vec<LoginAttemptView>,
+}
+
+/// Status of an account takeout archive. Returned by requestAccountExport and getAccountExport.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AccountExportView {
+    pub id: String,
+    /// `pending`, `complete` or `failed`.
+    pub status: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "completedAt", skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+    /// Expiring link to the archive, once complete.
+    #[serde(rename = "downloadUrl", skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Whether this is a final export taken with the account frozen for migration.
+    #[serde(default)]
+    pub migration: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RequestAccountExportInput {
+    /// Deactivate the account first, so the archive is its final state, and keep it from being
+    /// deleted until the user has had time to move.
+    pub migration: Option<bool>,
+}
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS pds.record_value_json_idx;
+ALTER TABLE pds.record
+    DROP COLUMN IF EXISTS "valueJson";
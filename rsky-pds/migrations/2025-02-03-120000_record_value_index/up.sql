@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE pds.record
+    ADD COLUMN "valueJson" jsonb;
+CREATE INDEX record_value_json_idx
+    ON pds.record USING GIN ("valueJson" jsonb_path_ops);
@@ -0,0 +1,13 @@
+-- Create Repo Commit Table
+CREATE TABLE IF NOT EXISTS pds.repo_commit (
+    did character varying NOT NULL,
+    rev character varying NOT NULL,
+    cid character varying NOT NULL,
+    since character varying,
+    creates integer NOT NULL DEFAULT 0,
+    updates integer NOT NULL DEFAULT 0,
+    deletes integer NOT NULL DEFAULT 0,
+    "committedAt" character varying NOT NULL
+);
+ALTER TABLE ONLY pds.repo_commit
+    ADD CONSTRAINT repo_commit_pkey PRIMARY KEY (did, rev);
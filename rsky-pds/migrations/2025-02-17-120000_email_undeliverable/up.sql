@@ -0,0 +1,6 @@
+-- Create Email Undeliverable Table
+CREATE TABLE IF NOT EXISTS pds.email_undeliverable (
+    email character varying PRIMARY KEY,
+    reason character varying NOT NULL,
+    "createdAt" character varying NOT NULL
+);
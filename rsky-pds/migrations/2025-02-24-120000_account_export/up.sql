@@ -0,0 +1,10 @@
+-- Create Account Export Table
+CREATE TABLE IF NOT EXISTS pds.account_export (
+    id character varying PRIMARY KEY,
+    did character varying NOT NULL,
+    status character varying NOT NULL,
+    "createdAt" character varying NOT NULL,
+    "completedAt" character varying,
+    error character varying
+);
+CREATE INDEX IF NOT EXISTS account_export_did_idx ON pds.account_export (did, "createdAt");
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS pds.push_registration;
@@ -0,0 +1,11 @@
+-- Create Push Registration Table
+CREATE TABLE IF NOT EXISTS pds.push_registration (
+    did character varying NOT NULL,
+    "serviceDid" character varying NOT NULL,
+    token character varying NOT NULL,
+    platform character varying NOT NULL,
+    "appId" character varying NOT NULL,
+    "createdAt" character varying NOT NULL,
+    "updatedAt" character varying NOT NULL,
+    CONSTRAINT push_registration_pkey PRIMARY KEY (did, token)
+);
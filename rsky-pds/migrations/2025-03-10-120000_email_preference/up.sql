@@ -0,0 +1,6 @@
+-- Create Email Preference Table
+CREATE TABLE IF NOT EXISTS pds.email_preference (
+    did character varying PRIMARY KEY,
+    preference character varying NOT NULL,
+    "updatedAt" character varying NOT NULL
+);
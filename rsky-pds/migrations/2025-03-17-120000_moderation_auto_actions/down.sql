@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS pds.moderation_audit;
+DROP TABLE IF EXISTS pds.moderation_report;
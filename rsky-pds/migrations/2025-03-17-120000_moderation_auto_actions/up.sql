@@ -0,0 +1,28 @@
+-- Create Moderation Report Table
+CREATE TABLE IF NOT EXISTS pds.moderation_report (
+    id bigserial PRIMARY KEY,
+    "subjectDid" character varying NOT NULL,
+    "subjectUri" character varying,
+    "reasonType" character varying NOT NULL,
+    reason character varying,
+    "reportedBy" character varying NOT NULL,
+    "createdAt" character varying NOT NULL
+);
+CREATE INDEX moderation_report_subject_created_at_idx -- for counting recent reports against an account
+	ON pds.moderation_report("subjectDid", "createdAt");
+
+-- Create Moderation Audit Table
+CREATE TABLE IF NOT EXISTS pds.moderation_audit (
+    id bigserial PRIMARY KEY,
+    "subjectDid" character varying NOT NULL,
+    action character varying NOT NULL,
+    rule character varying NOT NULL,
+    "reportCount" bigint NOT NULL,
+    "expiresAt" character varying,
+    "resolvedAt" character varying,
+    "createdAt" character varying NOT NULL
+);
+CREATE INDEX moderation_audit_subject_created_at_idx
+	ON pds.moderation_audit("subjectDid", "createdAt");
+CREATE INDEX moderation_audit_expires_at_idx -- for lifting expired automatic takedowns
+	ON pds.moderation_audit("expiresAt") WHERE "resolvedAt" IS NULL;
@@ -0,0 +1,3 @@
+ALTER TABLE pds.moderation_audit
+    DROP COLUMN IF EXISTS comment,
+    DROP COLUMN IF EXISTS "createdBy";
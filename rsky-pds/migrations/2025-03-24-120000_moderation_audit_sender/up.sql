@@ -0,0 +1,4 @@
+-- Who took a manual moderation action, and their note on it
+ALTER TABLE pds.moderation_audit
+    ADD COLUMN IF NOT EXISTS "createdBy" character varying,
+    ADD COLUMN IF NOT EXISTS comment character varying;
@@ -0,0 +1,12 @@
+-- Create Login Attempt Table
+CREATE TABLE IF NOT EXISTS pds.login_attempt (
+    id bigserial PRIMARY KEY,
+    did character varying NOT NULL,
+    success boolean NOT NULL,
+    method character varying NOT NULL,
+    ip character varying,
+    "userAgent" character varying,
+    "createdAt" character varying NOT NULL
+);
+CREATE INDEX login_attempt_did_id_idx -- for listing and trimming an account's history
+	ON pds.login_attempt(did, id);
@@ -0,0 +1 @@
+ALTER TABLE pds.refresh_token DROP COLUMN IF EXISTS "sessionName";
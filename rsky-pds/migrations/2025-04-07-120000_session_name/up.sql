@@ -0,0 +1,2 @@
+-- Client-chosen label for a session, carried across refreshes
+ALTER TABLE pds.refresh_token ADD COLUMN IF NOT EXISTS "sessionName" character varying;
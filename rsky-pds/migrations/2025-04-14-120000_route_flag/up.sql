@@ -0,0 +1,7 @@
+-- Create Route Flag Table
+CREATE TABLE IF NOT EXISTS pds.route_flag (
+    nsid character varying PRIMARY KEY,
+    disabled boolean NOT NULL,
+    reason character varying,
+    "updatedAt" character varying NOT NULL
+);
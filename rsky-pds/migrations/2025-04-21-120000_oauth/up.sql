@@ -0,0 +1,28 @@
+-- Pushed authorization requests, until their code is exchanged
+CREATE TABLE IF NOT EXISTS pds.oauth_request (
+    id character varying PRIMARY KEY,
+    "clientId" character varying NOT NULL,
+    "clientAuth" character varying NOT NULL,
+    parameters text NOT NULL,
+    "dpopJkt" character varying,
+    did character varying,
+    code character varying UNIQUE,
+    "createdAt" character varying NOT NULL,
+    "expiresAt" character varying NOT NULL
+);
+
+-- OAuth sessions; access tokens carry the id, clients refresh with "refreshToken"
+CREATE TABLE IF NOT EXISTS pds.oauth_token (
+    id character varying PRIMARY KEY,
+    did character varying NOT NULL,
+    "clientId" character varying NOT NULL,
+    "clientAuth" character varying NOT NULL,
+    scope character varying NOT NULL,
+    "dpopJkt" character varying NOT NULL,
+    "refreshToken" character varying NOT NULL UNIQUE,
+    "createdAt" character varying NOT NULL,
+    "updatedAt" character varying NOT NULL,
+    "expiresAt" character varying NOT NULL
+);
+CREATE INDEX oauth_token_did_idx -- for revoking an account's sessions
+	ON pds.oauth_token(did);
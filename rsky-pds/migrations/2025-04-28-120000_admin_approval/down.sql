@@ -0,0 +1 @@
+DROP TABLE IF EXISTS pds.admin_approval;
@@ -0,0 +1,10 @@
+-- Create Admin Approval Table
+CREATE TABLE IF NOT EXISTS pds.admin_approval (
+    action character varying NOT NULL,
+    subject character varying NOT NULL,
+    "requestedBy" character varying NOT NULL,
+    justification character varying NOT NULL,
+    "createdAt" character varying NOT NULL,
+    "expiresAt" character varying NOT NULL,
+    PRIMARY KEY (action, subject)
+);
@@ -0,0 +1 @@
+ALTER TABLE pds.app_password DROP COLUMN IF EXISTS privileged;
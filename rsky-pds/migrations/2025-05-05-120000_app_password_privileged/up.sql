@@ -0,0 +1,2 @@
+-- Privileged app passwords may also reach DMs and other privileged methods
+ALTER TABLE pds.app_password ADD COLUMN IF NOT EXISTS privileged boolean NOT NULL DEFAULT false;
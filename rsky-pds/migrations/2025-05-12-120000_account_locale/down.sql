@@ -0,0 +1 @@
+DROP TABLE IF EXISTS pds.account_locale;
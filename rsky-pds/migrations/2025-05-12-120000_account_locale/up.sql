@@ -0,0 +1,6 @@
+-- Create Account Locale Table
+CREATE TABLE IF NOT EXISTS pds.account_locale (
+    did character varying PRIMARY KEY,
+    locale character varying NOT NULL,
+    "updatedAt" character varying NOT NULL
+);
@@ -0,0 +1,19 @@
+-- Create Deleted Record Table
+CREATE TABLE IF NOT EXISTS pds.deleted_record (
+    uri character varying NOT NULL,
+    cid character varying NOT NULL,
+    did character varying NOT NULL,
+    collection character varying NOT NULL,
+    rkey character varying NOT NULL,
+    "repoRev" character varying,
+    "indexedAt" character varying NOT NULL,
+    content bytea NOT NULL,
+    "deletedAt" character varying NOT NULL,
+    "expiresAt" character varying NOT NULL,
+    CONSTRAINT deleted_record_pkey PRIMARY KEY (uri, cid)
+);
+
+CREATE INDEX IF NOT EXISTS deleted_record_did_deleted_at_idx
+    ON pds.deleted_record(did, "deletedAt");
+CREATE INDEX IF NOT EXISTS deleted_record_expires_at_idx
+    ON pds.deleted_record("expiresAt");
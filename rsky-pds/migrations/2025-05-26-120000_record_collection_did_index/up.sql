@@ -0,0 +1,3 @@
+-- Your SQL goes here
+CREATE INDEX IF NOT EXISTS record_collection_did_idx
+    ON pds.record(collection, did);
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS pds.repo_commit_block;
@@ -0,0 +1,9 @@
+-- Create Repo Commit Block Table
+-- Which blocks each commit added, so getRepo?since can be answered without diffing trees
+CREATE TABLE IF NOT EXISTS pds.repo_commit_block (
+    did character varying NOT NULL,
+    rev character varying NOT NULL,
+    cid character varying NOT NULL
+);
+ALTER TABLE ONLY pds.repo_commit_block
+    ADD CONSTRAINT repo_commit_block_pkey PRIMARY KEY (did, rev, cid);
@@ -0,0 +1 @@
+ALTER TABLE pds.account_export DROP COLUMN IF EXISTS migration;
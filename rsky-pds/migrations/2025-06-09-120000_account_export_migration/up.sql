@@ -0,0 +1,2 @@
+-- Migration exports freeze the account and hold off its deletion until the user has moved
+ALTER TABLE pds.account_export ADD COLUMN IF NOT EXISTS migration boolean NOT NULL DEFAULT false;
@@ -0,0 +1,6 @@
+-- Create Reserved Handle Table
+CREATE TABLE IF NOT EXISTS pds.reserved_handle (
+    name character varying PRIMARY KEY,
+    reason character varying,
+    "createdAt" character varying NOT NULL
+);
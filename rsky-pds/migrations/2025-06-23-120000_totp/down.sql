@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS pds.totp_recovery_code;
+DROP TABLE IF EXISTS pds.totp_secret;
@@ -0,0 +1,19 @@
+-- Create TOTP Secret Table
+CREATE TABLE IF NOT EXISTS pds.totp_secret (
+    did character varying PRIMARY KEY,
+    -- encrypted with the server's TOTP key
+    secret character varying NOT NULL,
+    "lastUsedStep" bigint,
+    "createdAt" character varying NOT NULL,
+    "confirmedAt" character varying,
+    "failedAttempts" integer NOT NULL DEFAULT 0,
+    "lockedUntil" character varying
+);
+
+-- Create TOTP Recovery Code Table
+CREATE TABLE IF NOT EXISTS pds.totp_recovery_code (
+    did character varying NOT NULL,
+    "codeHash" character varying NOT NULL,
+    "usedAt" character varying,
+    PRIMARY KEY (did, "codeHash")
+);
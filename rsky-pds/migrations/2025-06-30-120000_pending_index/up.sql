@@ -0,0 +1,7 @@
+-- Create Pending Index Table
+-- Repos whose record index trails a tooBig commit that's being indexed after the response
+CREATE TABLE IF NOT EXISTS pds.pending_index (
+    did character varying PRIMARY KEY,
+    rev character varying NOT NULL,
+    "createdAt" character varying NOT NULL
+);
@@ -0,0 +1 @@
+DROP INDEX IF EXISTS pds.account_export_pending_idx;
@@ -0,0 +1,14 @@
+-- At most one export per account is pending at a time; settle any duplicates before enforcing it
+UPDATE pds.account_export
+SET status = 'failed',
+    "completedAt" = to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.MS"Z"'),
+    error = 'Export failed'
+WHERE status = 'pending'
+  AND id NOT IN (
+    SELECT DISTINCT ON (did) id
+    FROM pds.account_export
+    WHERE status = 'pending'
+    ORDER BY did, "createdAt" DESC
+  );
+CREATE UNIQUE INDEX IF NOT EXISTS account_export_pending_idx
+    ON pds.account_export (did) WHERE status = 'pending';
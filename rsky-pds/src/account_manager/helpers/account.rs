@@ -42,6 +42,20 @@ pub enum AccountStatus {
     Throttled,
 }
 
+/// A change to an account's hosting status, applied through
+/// `AccountManager::update_account_status`.
+#[derive(Debug, Clone)]
+pub enum AccountStatusTransition {
+    Activate,
+    Deactivate {
+        delete_after: Option<String>,
+    },
+    /// Applies or lifts a takedown depending on `applied`.
+    Takedown(StatusAttr),
+    /// Removes the account rows. The caller destroys the actor store first.
+    Delete,
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct FormattedAccountStatus {
     pub active: bool,
@@ -155,6 +169,19 @@ pub async fn get_account(
     Ok(found)
 }
 
+/// Returns which of `handles` belong to an account, including taken down and deactivated ones.
+pub async fn get_taken_handles(handles: Vec<String>, db: &DbConn) -> Result<Vec<String>> {
+    let taken = db
+        .run(move |conn| {
+            ActorSchema::actor
+                .filter(ActorSchema::handle.eq_any(handles))
+                .select(ActorSchema::handle)
+                .load::<Option<String>>(conn)
+        })
+        .await?;
+    Ok(taken.into_iter().flatten().collect())
+}
+
 pub async fn get_account_by_email(
     _email: &str,
     flags: Option<AvailabilityFlags>,
@@ -270,9 +297,15 @@ pub async fn register_account(
 }
 
 pub async fn delete_account(did: &str, db: &DbConn) -> Result<()> {
+    use crate::schema::pds::account_locale::dsl as AccountLocaleSchema;
+    use crate::schema::pds::email_preference::dsl as EmailPreferenceSchema;
     use crate::schema::pds::email_token::dsl as EmailTokenSchema;
+    use crate::schema::pds::oauth_token::dsl as OAuthTokenSchema;
+    use crate::schema::pds::push_registration::dsl as PushRegistrationSchema;
     use crate::schema::pds::refresh_token::dsl as RefreshTokenSchema;
     use crate::schema::pds::repo_root::dsl as RepoRootSchema;
+    use crate::schema::pds::totp_recovery_code::dsl as TotpRecoveryCodeSchema;
+    use crate::schema::pds::totp_secret::dsl as TotpSecretSchema;
 
     let did = did.to_owned();
     db.run(move |conn| {
@@ -285,6 +318,24 @@ pub async fn delete_account(did: &str, db: &DbConn) -> Result<()> {
         delete(RefreshTokenSchema::refresh_token)
             .filter(RefreshTokenSchema::did.eq(&did))
             .execute(conn)?;
+        delete(OAuthTokenSchema::oauth_token)
+            .filter(OAuthTokenSchema::did.eq(&did))
+            .execute(conn)?;
+        delete(PushRegistrationSchema::push_registration)
+            .filter(PushRegistrationSchema::did.eq(&did))
+            .execute(conn)?;
+        delete(EmailPreferenceSchema::email_preference)
+            .filter(EmailPreferenceSchema::did.eq(&did))
+            .execute(conn)?;
+        delete(AccountLocaleSchema::account_locale)
+            .filter(AccountLocaleSchema::did.eq(&did))
+            .execute(conn)?;
+        delete(TotpRecoveryCodeSchema::totp_recovery_code)
+            .filter(TotpRecoveryCodeSchema::did.eq(&did))
+            .execute(conn)?;
+        delete(TotpSecretSchema::totp_secret)
+            .filter(TotpSecretSchema::did.eq(&did))
+            .execute(conn)?;
         delete(AccountSchema::account)
             .filter(AccountSchema::did.eq(&did))
             .execute(conn)?;
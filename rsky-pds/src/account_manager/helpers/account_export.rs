@@ -0,0 +1,131 @@
+use crate::db::DbConn;
+use crate::models::models::AccountExport;
+use anyhow::Result;
+use diesel::*;
+use rsky_common;
+use rsky_common::time::{from_micros_to_str, HOUR};
+
+pub const EXPORT_PENDING: &str = "pending";
+pub const EXPORT_COMPLETE: &str = "complete";
+pub const EXPORT_FAILED: &str = "failed";
+
+/// Pending exports older than this are assumed to have died with the process that ran them.
+pub const EXPORT_JOB_TIMEOUT: i32 = HOUR;
+
+/// Start a new export for `did`, or `None` when one is already pending. A pending export that
+/// outlived [`EXPORT_JOB_TIMEOUT`] is marked failed first, so it doesn't block the account forever.
+pub async fn create_export(
+    did: &str,
+    migration: bool,
+    db: &DbConn,
+) -> Result<Option<AccountExport>> {
+    use crate::schema::pds::account_export::dsl as AccountExportSchema;
+
+    let export = AccountExport {
+        id: rsky_common::get_random_str(),
+        did: did.to_owned(),
+        status: EXPORT_PENDING.to_owned(),
+        created_at: rsky_common::now(),
+        completed_at: None,
+        error: None,
+        migration,
+    };
+    let stale_before = from_micros_to_str(
+        chrono::Utc::now().timestamp_micros() - EXPORT_JOB_TIMEOUT as i64 * 1000,
+    );
+    let row = export.clone();
+    // the partial unique index on pending exports settles concurrent requests
+    let inserted = db
+        .run(move |conn| {
+            conn.transaction::<_, result::Error, _>(|conn| {
+                update(AccountExportSchema::account_export)
+                    .filter(AccountExportSchema::did.eq(&row.did))
+                    .filter(AccountExportSchema::status.eq(EXPORT_PENDING))
+                    .filter(AccountExportSchema::createdAt.le(stale_before))
+                    .set((
+                        AccountExportSchema::status.eq(EXPORT_FAILED),
+                        AccountExportSchema::completedAt.eq(rsky_common::now()),
+                        AccountExportSchema::error.eq("Export failed"),
+                    ))
+                    .execute(conn)?;
+                insert_into(AccountExportSchema::account_export)
+                    .values(&row)
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+            })
+        })
+        .await?;
+    Ok(match inserted {
+        0 => None,
+        _ => Some(export),
+    })
+}
+
+/// The requested export, or the most recent one when `id` is `None`.
+pub async fn get_export(
+    did: &str,
+    id: Option<String>,
+    db: &DbConn,
+) -> Result<Option<AccountExport>> {
+    use crate::schema::pds::account_export::dsl as AccountExportSchema;
+
+    let did = did.to_owned();
+    let res = db
+        .run(move |conn| {
+            let mut builder = AccountExportSchema::account_export
+                .filter(AccountExportSchema::did.eq(did))
+                .into_boxed();
+            if let Some(id) = id {
+                builder = builder.filter(AccountExportSchema::id.eq(id));
+            }
+            builder
+                .order(AccountExportSchema::createdAt.desc())
+                .select(AccountExport::as_select())
+                .first(conn)
+                .optional()
+        })
+        .await?;
+    Ok(res)
+}
+
+/// Whether `did` took a migration export at or after `since` that didn't fail.
+pub async fn has_migration_export_since(did: &str, since: String, db: &DbConn) -> Result<bool> {
+    use crate::schema::pds::account_export::dsl as AccountExportSchema;
+
+    let did = did.to_owned();
+    let res = db
+        .run(move |conn| {
+            select(dsl::exists(
+                AccountExportSchema::account_export
+                    .filter(AccountExportSchema::did.eq(did))
+                    .filter(AccountExportSchema::migration.eq(true))
+                    .filter(AccountExportSchema::status.ne(EXPORT_FAILED))
+                    .filter(AccountExportSchema::createdAt.ge(since)),
+            ))
+            .get_result(conn)
+        })
+        .await?;
+    Ok(res)
+}
+
+pub async fn complete_export(id: &str, error: Option<String>, db: &DbConn) -> Result<()> {
+    use crate::schema::pds::account_export::dsl as AccountExportSchema;
+
+    let id = id.to_owned();
+    let status = match error {
+        None => EXPORT_COMPLETE,
+        Some(_) => EXPORT_FAILED,
+    };
+    db.run(move |conn| {
+        update(AccountExportSchema::account_export)
+            .filter(AccountExportSchema::id.eq(id))
+            .set((
+                AccountExportSchema::status.eq(status),
+                AccountExportSchema::completedAt.eq(rsky_common::now()),
+                AccountExportSchema::error.eq(error),
+            ))
+            .execute(conn)
+    })
+    .await?;
+    Ok(())
+}
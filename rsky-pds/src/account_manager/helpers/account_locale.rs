@@ -0,0 +1,55 @@
+use crate::db::DbConn;
+use crate::locale;
+use anyhow::{bail, Result};
+use diesel::*;
+use rsky_common;
+
+/// The locale the account chose for its mail, if any.
+pub async fn get_locale(did: &str, db: &DbConn) -> Result<Option<String>> {
+    use crate::schema::pds::account_locale::dsl as AccountLocaleSchema;
+
+    let did = did.to_owned();
+    let res = db
+        .run(move |conn| {
+            AccountLocaleSchema::account_locale
+                .find(did)
+                .select(AccountLocaleSchema::locale)
+                .first::<String>(conn)
+                .optional()
+        })
+        .await?;
+    Ok(res)
+}
+
+/// Stores the account's locale, or forgets it when `locale` is None.
+pub async fn set_locale(did: &str, locale: Option<&str>, db: &DbConn) -> Result<()> {
+    use crate::schema::pds::account_locale::dsl as AccountLocaleSchema;
+
+    let did = did.to_owned();
+    let locale = match locale {
+        None => None,
+        Some(tag) => match locale::normalize(tag) {
+            Some(locale) => Some(locale),
+            None => bail!("Invalid locale: {tag}"),
+        },
+    };
+    let now = rsky_common::now();
+    db.run(move |conn| match locale {
+        None => delete(AccountLocaleSchema::account_locale.find(&did)).execute(conn),
+        Some(locale) => insert_into(AccountLocaleSchema::account_locale)
+            .values((
+                AccountLocaleSchema::did.eq(&did),
+                AccountLocaleSchema::locale.eq(&locale),
+                AccountLocaleSchema::updatedAt.eq(&now),
+            ))
+            .on_conflict(AccountLocaleSchema::did)
+            .do_update()
+            .set((
+                AccountLocaleSchema::locale.eq(&locale),
+                AccountLocaleSchema::updatedAt.eq(&now),
+            ))
+            .execute(conn),
+    })
+    .await?;
+    Ok(())
+}
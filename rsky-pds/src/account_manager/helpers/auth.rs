@@ -159,11 +159,10 @@ pub async fn create_service_jwt(params: ServiceJwtParams) -> Result<String> {
     } = params;
     let now = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
-        .expect("timestamp in micros since UNIX epoch")
-        .as_micros() as usize;
-    let exp = params
-        .exp
-        .unwrap_or(((now + MINUTE as usize) / 1000) as u64);
+        .expect("timestamp in seconds since UNIX epoch")
+        .as_secs();
+    // exp is in seconds; MINUTE is in milliseconds
+    let exp = params.exp.unwrap_or(now + (MINUTE / 1000) as u64);
     let lxm = params.lxm;
     let jti = get_random_str();
     let header = ServiceJwtHeader {
@@ -217,6 +216,7 @@ pub fn decode_refresh_token(jwt: String, jwt_key: Keypair) -> Result<RefreshToke
 pub async fn store_refresh_token(
     payload: RefreshToken,
     app_password_name: Option<String>,
+    session_name: Option<String>,
     db: &DbConn,
 ) -> Result<()> {
     use crate::schema::pds::refresh_token::dsl as RefreshTokenSchema;
@@ -229,6 +229,7 @@ pub async fn store_refresh_token(
                 RefreshTokenSchema::id.eq(payload.jti),
                 RefreshTokenSchema::did.eq(payload.sub),
                 RefreshTokenSchema::appPasswordName.eq(app_password_name),
+                RefreshTokenSchema::sessionName.eq(session_name),
                 RefreshTokenSchema::expiresAt.eq(format!("{}", exp.format(RFC3339_VARIANT))),
             ))
             .on_conflict_do_nothing() // E.g. when re-granting during a refresh grace period
@@ -251,15 +252,41 @@ pub async fn revoke_refresh_token(id: String, db: &DbConn) -> Result<bool> {
     .await
 }
 
+/// Revoke every session of the account, OAuth ones included.
 pub async fn revoke_refresh_tokens_by_did(did: &str, db: &DbConn) -> Result<bool> {
+    use crate::schema::pds::oauth_token::dsl as OAuthTokenSchema;
     use crate::schema::pds::refresh_token::dsl as RefreshTokenSchema;
     let did = did.to_owned();
     db.run(move |conn| {
         let deleted_rows = delete(RefreshTokenSchema::refresh_token)
-            .filter(RefreshTokenSchema::did.eq(did))
+            .filter(RefreshTokenSchema::did.eq(&did))
             .get_results::<models::RefreshToken>(conn)?;
+        let deleted_oauth = delete(OAuthTokenSchema::oauth_token)
+            .filter(OAuthTokenSchema::did.eq(&did))
+            .execute(conn)?;
 
-        Ok(!deleted_rows.is_empty())
+        Ok(!deleted_rows.is_empty() || deleted_oauth > 0)
+    })
+    .await
+}
+
+/// Revoke the sessions that were logged in with the account password, OAuth ones included
+/// since clients are approved with it. Rotated tokens carry the app password name of the
+/// session they descend from, so app password sessions are left alone.
+pub async fn revoke_password_refresh_tokens_by_did(did: &str, db: &DbConn) -> Result<bool> {
+    use crate::schema::pds::oauth_token::dsl as OAuthTokenSchema;
+    use crate::schema::pds::refresh_token::dsl as RefreshTokenSchema;
+    let did = did.to_owned();
+    db.run(move |conn| {
+        let deleted_rows = delete(RefreshTokenSchema::refresh_token)
+            .filter(RefreshTokenSchema::did.eq(&did))
+            .filter(RefreshTokenSchema::appPasswordName.is_null())
+            .get_results::<models::RefreshToken>(conn)?;
+        let deleted_oauth = delete(OAuthTokenSchema::oauth_token)
+            .filter(OAuthTokenSchema::did.eq(&did))
+            .execute(conn)?;
+
+        Ok(!deleted_rows.is_empty() || deleted_oauth > 0)
     })
     .await
 }
@@ -296,6 +323,25 @@ pub async fn get_refresh_token(id: &str, db: &DbConn) -> Result<Option<models::R
     .await
 }
 
+pub async fn get_active_refresh_tokens(
+    did: &str,
+    now: String,
+    db: &DbConn,
+) -> Result<Vec<models::RefreshToken>> {
+    use crate::schema::pds::refresh_token::dsl as RefreshTokenSchema;
+    let did = did.to_owned();
+    db.run(move |conn| {
+        Ok(RefreshTokenSchema::refresh_token
+            .filter(RefreshTokenSchema::did.eq(did))
+            .filter(RefreshTokenSchema::nextId.is_null())
+            .filter(RefreshTokenSchema::expiresAt.gt(now))
+            .order(RefreshTokenSchema::expiresAt.desc())
+            .select(models::RefreshToken::as_select())
+            .load(conn)?)
+    })
+    .await
+}
+
 pub async fn delete_expired_refresh_tokens(did: &str, now: String, db: &DbConn) -> Result<()> {
     use crate::schema::pds::refresh_token::dsl as RefreshTokenSchema;
     let did = did.to_owned();
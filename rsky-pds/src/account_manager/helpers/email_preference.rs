@@ -0,0 +1,70 @@
+use crate::db::DbConn;
+use anyhow::{bail, Result};
+use diesel::*;
+use rsky_common;
+
+/// Every email the PDS sends. The default for accounts without a stored preference.
+pub const EMAIL_PREFERENCE_ALL: &str = "all";
+/// Only mail needed to secure or recover the account, e.g. password reset tokens.
+pub const EMAIL_PREFERENCE_SECURITY: &str = "security";
+
+pub fn assert_valid_preference(preference: &str) -> Result<()> {
+    match preference {
+        EMAIL_PREFERENCE_ALL | EMAIL_PREFERENCE_SECURITY => Ok(()),
+        _ => bail!("Invalid email preference: {preference}"),
+    }
+}
+
+pub async fn get_preference(did: &str, db: &DbConn) -> Result<String> {
+    use crate::schema::pds::email_preference::dsl as EmailPreferenceSchema;
+
+    let did = did.to_owned();
+    let res = db
+        .run(move |conn| {
+            EmailPreferenceSchema::email_preference
+                .filter(EmailPreferenceSchema::did.eq(did))
+                .select(EmailPreferenceSchema::preference)
+                .first::<String>(conn)
+                .optional()
+        })
+        .await?;
+    Ok(res.unwrap_or_else(|| EMAIL_PREFERENCE_ALL.to_owned()))
+}
+
+pub async fn set_preference(did: &str, preference: &str, db: &DbConn) -> Result<()> {
+    use crate::schema::pds::email_preference::dsl as EmailPreferenceSchema;
+
+    assert_valid_preference(preference)?;
+    let did = did.to_owned();
+    let preference = preference.to_owned();
+    let now = rsky_common::now();
+    db.run(move |conn| {
+        insert_into(EmailPreferenceSchema::email_preference)
+            .values((
+                EmailPreferenceSchema::did.eq(&did),
+                EmailPreferenceSchema::preference.eq(&preference),
+                EmailPreferenceSchema::updatedAt.eq(&now),
+            ))
+            .on_conflict(EmailPreferenceSchema::did)
+            .do_update()
+            .set((
+                EmailPreferenceSchema::preference.eq(&preference),
+                EmailPreferenceSchema::updatedAt.eq(&now),
+            ))
+            .execute(conn)
+    })
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assert_valid_preference() {
+        assert!(assert_valid_preference(EMAIL_PREFERENCE_ALL).is_ok());
+        assert!(assert_valid_preference(EMAIL_PREFERENCE_SECURITY).is_ok());
+        assert!(assert_valid_preference("none").is_err());
+    }
+}
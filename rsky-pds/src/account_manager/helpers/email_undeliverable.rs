@@ -0,0 +1,59 @@
+use crate::db::DbConn;
+use anyhow::Result;
+use diesel::dsl::exists;
+use diesel::*;
+use rsky_common;
+
+pub async fn mark_undeliverable(email: &str, reason: &str, db: &DbConn) -> Result<()> {
+    use crate::schema::pds::email_undeliverable::dsl as EmailUndeliverableSchema;
+    let now = rsky_common::now();
+
+    let email = email.to_lowercase();
+    let reason = reason.to_owned();
+    db.run(move |conn| {
+        insert_into(EmailUndeliverableSchema::email_undeliverable)
+            .values((
+                EmailUndeliverableSchema::email.eq(&email),
+                EmailUndeliverableSchema::reason.eq(&reason),
+                EmailUndeliverableSchema::createdAt.eq(&now),
+            ))
+            .on_conflict(EmailUndeliverableSchema::email)
+            .do_update()
+            .set((
+                EmailUndeliverableSchema::reason.eq(&reason),
+                EmailUndeliverableSchema::createdAt.eq(&now),
+            ))
+            .execute(conn)
+    })
+    .await?;
+    Ok(())
+}
+
+pub async fn clear_undeliverable(email: &str, db: &DbConn) -> Result<()> {
+    use crate::schema::pds::email_undeliverable::dsl as EmailUndeliverableSchema;
+
+    let email = email.to_lowercase();
+    db.run(move |conn| {
+        delete(EmailUndeliverableSchema::email_undeliverable)
+            .filter(EmailUndeliverableSchema::email.eq(email))
+            .execute(conn)
+    })
+    .await?;
+    Ok(())
+}
+
+pub async fn is_undeliverable(email: &str, db: &DbConn) -> Result<bool> {
+    use crate::schema::pds::email_undeliverable::dsl as EmailUndeliverableSchema;
+
+    let email = email.to_lowercase();
+    let res = db
+        .run(move |conn| {
+            select(exists(
+                EmailUndeliverableSchema::email_undeliverable
+                    .filter(EmailUndeliverableSchema::email.eq(email)),
+            ))
+            .get_result(conn)
+        })
+        .await?;
+    Ok(res)
+}
@@ -277,6 +277,58 @@ pub async fn get_invited_by_for_accounts(
     ))
 }
 
+/// An account that signed up with an invite code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Referral {
+    /// Owner of the code.
+    pub inviter: String,
+    pub code: String,
+    pub did: String,
+    pub handle: Option<String>,
+    pub taken_down: bool,
+}
+
+/// Accounts that signed up with codes belonging to any of `dids`.
+pub async fn get_referrals(dids: Vec<String>, db: &DbConn) -> Result<Vec<Referral>> {
+    if dids.is_empty() {
+        return Ok(Vec::new());
+    }
+    use crate::schema::pds::actor::dsl as ActorSchema;
+    use crate::schema::pds::invite_code::dsl as InviteCodeSchema;
+    use crate::schema::pds::invite_code_use::dsl as InviteCodeUseSchema;
+
+    let rows: Vec<(String, String, String, Option<String>, Option<String>)> = db
+        .run(|conn| {
+            InviteCodeUseSchema::invite_code_use
+                .inner_join(
+                    InviteCodeSchema::invite_code
+                        .on(InviteCodeSchema::code.eq(InviteCodeUseSchema::code)),
+                )
+                .left_join(ActorSchema::actor.on(ActorSchema::did.eq(InviteCodeUseSchema::usedBy)))
+                .filter(InviteCodeSchema::forAccount.eq_any(dids))
+                .order_by(InviteCodeUseSchema::usedAt.asc())
+                .select((
+                    InviteCodeSchema::forAccount,
+                    InviteCodeUseSchema::code,
+                    InviteCodeUseSchema::usedBy,
+                    ActorSchema::handle.nullable(),
+                    ActorSchema::takedownRef.nullable(),
+                ))
+                .get_results(conn)
+        })
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(inviter, code, did, handle, takedown_ref)| Referral {
+            inviter,
+            code,
+            did,
+            handle,
+            taken_down: takedown_ref.is_some(),
+        })
+        .collect())
+}
+
 pub async fn set_account_invites_disabled(did: &str, disabled: bool, db: &DbConn) -> Result<()> {
     use crate::schema::pds::account::dsl as AccountSchema;
 
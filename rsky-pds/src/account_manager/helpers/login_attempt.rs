@@ -0,0 +1,77 @@
+use crate::db::DbConn;
+use crate::models::models::LoginAttempt;
+use anyhow::Result;
+use diesel::*;
+use rsky_common;
+
+/// Attempts kept per account; older ones are trimmed as new ones are recorded.
+pub const MAX_LOGIN_ATTEMPTS: i64 = 50;
+
+/// Signed in with the account password.
+pub const LOGIN_METHOD_PASSWORD: &str = "password";
+/// Signed in with an app password.
+pub const LOGIN_METHOD_APP_PASSWORD: &str = "app-password";
+/// Signed in with the account password to approve an OAuth client.
+pub const LOGIN_METHOD_OAUTH: &str = "oauth";
+
+pub struct LoginAttemptOpts {
+    pub did: String,
+    pub success: bool,
+    pub method: &'static str,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+pub async fn record_attempt(opts: LoginAttemptOpts, db: &DbConn) -> Result<()> {
+    use crate::schema::pds::login_attempt::dsl as LoginAttemptSchema;
+
+    let now = rsky_common::now();
+    db.run(move |conn| {
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            insert_into(LoginAttemptSchema::login_attempt)
+                .values((
+                    LoginAttemptSchema::did.eq(&opts.did),
+                    LoginAttemptSchema::success.eq(opts.success),
+                    LoginAttemptSchema::method.eq(opts.method),
+                    LoginAttemptSchema::ip.eq(opts.ip),
+                    LoginAttemptSchema::userAgent.eq(opts.user_agent),
+                    LoginAttemptSchema::createdAt.eq(now),
+                ))
+                .execute(conn)?;
+            let oldest_kept: Option<i64> = LoginAttemptSchema::login_attempt
+                .filter(LoginAttemptSchema::did.eq(&opts.did))
+                .order(LoginAttemptSchema::id.desc())
+                .offset(MAX_LOGIN_ATTEMPTS - 1)
+                .select(LoginAttemptSchema::id)
+                .first(conn)
+                .optional()?;
+            if let Some(oldest_kept) = oldest_kept {
+                delete(LoginAttemptSchema::login_attempt)
+                    .filter(LoginAttemptSchema::did.eq(&opts.did))
+                    .filter(LoginAttemptSchema::id.lt(oldest_kept))
+                    .execute(conn)?;
+            }
+            Ok(())
+        })
+    })
+    .await?;
+    Ok(())
+}
+
+/// An account's recorded attempts, newest first.
+pub async fn get_attempts(did: &str, db: &DbConn) -> Result<Vec<LoginAttempt>> {
+    use crate::schema::pds::login_attempt::dsl as LoginAttemptSchema;
+
+    let did = did.to_owned();
+    let res = db
+        .run(move |conn| {
+            LoginAttemptSchema::login_attempt
+                .filter(LoginAttemptSchema::did.eq(did))
+                .order(LoginAttemptSchema::id.desc())
+                .limit(MAX_LOGIN_ATTEMPTS)
+                .select(LoginAttempt::as_select())
+                .load(conn)
+        })
+        .await?;
+    Ok(res)
+}
@@ -1,6 +1,15 @@
 pub mod account;
+pub mod account_export;
+pub mod account_locale;
 pub mod auth;
+pub mod email_preference;
 pub mod email_token;
+pub mod email_undeliverable;
 pub mod invite;
+pub mod login_attempt;
+pub mod moderation;
 pub mod password;
+pub mod personal_data;
+pub mod push_registration;
 pub mod repo;
+pub mod totp;
@@ -0,0 +1,196 @@
+use crate::account_manager::helpers::account::AccountStatus;
+use crate::db::DbConn;
+use crate::models::models::{ModerationAudit, ModerationReport};
+use anyhow::Result;
+use diesel::dsl::count_distinct;
+use diesel::*;
+use rsky_common;
+
+/// A rule's report threshold was met and the account was left for a moderator to review.
+pub const AUDIT_ACTION_FLAG: &str = "flag";
+/// A rule took the account down until `expiresAt`.
+pub const AUDIT_ACTION_TAKEDOWN: &str = "takedown";
+/// An automatic takedown expired and was lifted by the sweeper.
+pub const AUDIT_ACTION_TAKEDOWN_LIFTED: &str = "takedown-lifted";
+/// A moderator emailed the account through com.atproto.admin.sendEmail; `rule` holds the subject.
+pub const AUDIT_ACTION_EMAIL: &str = "email";
+
+pub struct CreateReportOpts {
+    pub subject_did: String,
+    pub subject_uri: Option<String>,
+    pub reason_type: String,
+    pub reason: Option<String>,
+    pub reported_by: String,
+}
+
+pub struct AuditEventOpts {
+    pub subject_did: String,
+    pub action: &'static str,
+    pub rule: String,
+    pub report_count: i64,
+    pub expires_at: Option<String>,
+    /// Did of the moderator behind a manual action; `None` for automatic ones.
+    pub created_by: Option<String>,
+    pub comment: Option<String>,
+}
+
+/// The takedown ref an automatic takedown stores on the actor, tying it to its audit row.
+pub fn auto_takedown_ref(audit_id: i64) -> String {
+    format!("auto-takedown:{audit_id}")
+}
+
+pub async fn create_report(opts: CreateReportOpts, db: &DbConn) -> Result<ModerationReport> {
+    use crate::schema::pds::moderation_report::dsl as ModerationReportSchema;
+
+    let now = rsky_common::now();
+    let res = db
+        .run(move |conn| {
+            insert_into(ModerationReportSchema::moderation_report)
+                .values((
+                    ModerationReportSchema::subjectDid.eq(opts.subject_did),
+                    ModerationReportSchema::subjectUri.eq(opts.subject_uri),
+                    ModerationReportSchema::reasonType.eq(opts.reason_type),
+                    ModerationReportSchema::reason.eq(opts.reason),
+                    ModerationReportSchema::reportedBy.eq(opts.reported_by),
+                    ModerationReportSchema::createdAt.eq(now),
+                ))
+                .returning(ModerationReport::as_returning())
+                .get_result(conn)
+        })
+        .await?;
+    Ok(res)
+}
+
+/// Distinct accounts that reported `subject_did` at or after `since`, so one reporter filing
+/// repeatedly can't trip a rule alone.
+pub async fn count_reporters_since(subject_did: &str, since: String, db: &DbConn) -> Result<i64> {
+    use crate::schema::pds::moderation_report::dsl as ModerationReportSchema;
+
+    let subject_did = subject_did.to_owned();
+    let res = db
+        .run(move |conn| {
+            ModerationReportSchema::moderation_report
+                .filter(ModerationReportSchema::subjectDid.eq(subject_did))
+                .filter(ModerationReportSchema::createdAt.ge(since))
+                .select(count_distinct(ModerationReportSchema::reportedBy))
+                .get_result(conn)
+        })
+        .await?;
+    Ok(res)
+}
+
+/// Whether `rule` already acted on `subject_did` at or after `since`.
+pub async fn has_audit_event_since(
+    subject_did: &str,
+    rule: String,
+    since: String,
+    db: &DbConn,
+) -> Result<bool> {
+    use crate::schema::pds::moderation_audit::dsl as ModerationAuditSchema;
+
+    let subject_did = subject_did.to_owned();
+    let res = db
+        .run(move |conn| {
+            select(dsl::exists(
+                ModerationAuditSchema::moderation_audit
+                    .filter(ModerationAuditSchema::subjectDid.eq(subject_did))
+                    .filter(ModerationAuditSchema::rule.eq(rule))
+                    .filter(ModerationAuditSchema::createdAt.ge(since)),
+            ))
+            .get_result(conn)
+        })
+        .await?;
+    Ok(res)
+}
+
+pub async fn record_audit_event(opts: AuditEventOpts, db: &DbConn) -> Result<i64> {
+    let now = rsky_common::now();
+    let res = db
+        .run(move |conn| insert_audit_event(opts, now, conn))
+        .await?;
+    Ok(res)
+}
+
+fn insert_audit_event(
+    opts: AuditEventOpts,
+    now: String,
+    conn: &mut PgConnection,
+) -> QueryResult<i64> {
+    use crate::schema::pds::moderation_audit::dsl as ModerationAuditSchema;
+
+    insert_into(ModerationAuditSchema::moderation_audit)
+        .values((
+            ModerationAuditSchema::subjectDid.eq(opts.subject_did),
+            ModerationAuditSchema::action.eq(opts.action),
+            ModerationAuditSchema::rule.eq(opts.rule),
+            ModerationAuditSchema::reportCount.eq(opts.report_count),
+            ModerationAuditSchema::expiresAt.eq(opts.expires_at),
+            ModerationAuditSchema::createdAt.eq(now),
+            ModerationAuditSchema::createdBy.eq(opts.created_by),
+            ModerationAuditSchema::comment.eq(opts.comment),
+        ))
+        .returning(ModerationAuditSchema::id)
+        .get_result(conn)
+}
+
+/// Automatic takedowns whose `expiresAt` has passed and haven't been lifted yet. Takes a plain
+/// connection since the sweeper runs outside of any request.
+pub fn expired_takedowns(now: &str, conn: &mut PgConnection) -> Result<Vec<ModerationAudit>> {
+    use crate::schema::pds::moderation_audit::dsl as ModerationAuditSchema;
+
+    let res = ModerationAuditSchema::moderation_audit
+        .filter(ModerationAuditSchema::action.eq(AUDIT_ACTION_TAKEDOWN))
+        .filter(ModerationAuditSchema::resolvedAt.is_null())
+        .filter(ModerationAuditSchema::expiresAt.le(now))
+        .order(ModerationAuditSchema::expiresAt.asc())
+        .select(ModerationAudit::as_select())
+        .load(conn)?;
+    Ok(res)
+}
+
+/// Resolves an expired automatic takedown. The actor's takedown is only cleared, and the lift
+/// recorded, if it's still the one this rule applied; a takedown made since is left alone.
+/// Returns the account's status once lifted, or `None` if nothing was cleared.
+pub fn lift_takedown(
+    audit: &ModerationAudit,
+    conn: &mut PgConnection,
+) -> Result<Option<AccountStatus>> {
+    use crate::schema::pds::actor::dsl as ActorSchema;
+    use crate::schema::pds::moderation_audit::dsl as ModerationAuditSchema;
+
+    let now = rsky_common::now();
+    let res = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let cleared: Option<Option<String>> = update(ActorSchema::actor)
+            .filter(ActorSchema::did.eq(&audit.subject_did))
+            .filter(ActorSchema::takedownRef.eq(auto_takedown_ref(audit.id)))
+            .set(ActorSchema::takedownRef.eq::<Option<String>>(None))
+            .returning(ActorSchema::deactivatedAt)
+            .get_result(conn)
+            .optional()?;
+        update(ModerationAuditSchema::moderation_audit)
+            .filter(ModerationAuditSchema::id.eq(audit.id))
+            .set(ModerationAuditSchema::resolvedAt.eq(&now))
+            .execute(conn)?;
+        let Some(deactivated_at) = cleared else {
+            return Ok(None);
+        };
+        insert_audit_event(
+            AuditEventOpts {
+                subject_did: audit.subject_did.clone(),
+                action: AUDIT_ACTION_TAKEDOWN_LIFTED,
+                rule: audit.rule.clone(),
+                report_count: audit.report_count,
+                expires_at: None,
+                created_by: None,
+                comment: None,
+            },
+            now.clone(),
+            conn,
+        )?;
+        Ok(Some(match deactivated_at {
+            Some(_) => AccountStatus::Deactivated,
+            None => AccountStatus::Active,
+        }))
+    })?;
+    Ok(res)
+}
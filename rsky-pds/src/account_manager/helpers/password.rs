@@ -1,3 +1,4 @@
+use crate::auth_verifier::AuthScope;
 use crate::db::DbConn;
 use crate::models;
 use crate::models::AppPassword;
@@ -12,6 +13,24 @@ use rsky_common::{get_random_str, now};
 use rsky_lexicon::com::atproto::server::CreateAppPasswordOutput;
 use sha2::{Digest, Sha256};
 
+/// The app password a session was logged in with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppPassDescript {
+    pub name: String,
+    pub privileged: bool,
+}
+
+/// The scope of a session's access tokens: full access when logged in with the account password,
+/// else that of the app password, which reaches DMs and other privileged methods only if
+/// `privileged`.
+pub fn session_scope(app_password_privileged: Option<bool>) -> AuthScope {
+    match app_password_privileged {
+        None => AuthScope::Access,
+        Some(true) => AuthScope::AppPassPrivileged,
+        Some(false) => AuthScope::AppPass,
+    }
+}
+
 pub struct UpdateUserPasswordOpts {
     pub did: String,
     pub password_encrypted: String,
@@ -37,7 +56,11 @@ pub async fn verify_account_password(did: &str, password: &String, db: &DbConn)
     }
 }
 
-pub async fn verify_app_password(did: &str, password: &str, db: &DbConn) -> Result<Option<String>> {
+pub async fn verify_app_password(
+    did: &str,
+    password: &str,
+    db: &DbConn,
+) -> Result<Option<AppPassDescript>> {
     use crate::schema::pds::app_password::dsl as AppPasswordSchema;
 
     let did = did.to_owned();
@@ -53,11 +76,29 @@ pub async fn verify_app_password(did: &str, password: &str, db: &DbConn) -> Resu
                 .optional()
         })
         .await?;
-    if let Some(found) = found {
-        Ok(Some(found.name))
-    } else {
-        Ok(None)
-    }
+    Ok(found.map(|found| AppPassDescript {
+        name: found.name,
+        privileged: found.privileged,
+    }))
+}
+
+/// Whether the app password `name` of `did` is privileged; false once it has been revoked.
+pub async fn is_privileged_app_password(did: &str, name: &str, db: &DbConn) -> Result<bool> {
+    use crate::schema::pds::app_password::dsl as AppPasswordSchema;
+
+    let did = did.to_owned();
+    let name = name.to_owned();
+    let privileged = db
+        .run(move |conn| {
+            AppPasswordSchema::app_password
+                .filter(AppPasswordSchema::did.eq(did))
+                .filter(AppPasswordSchema::name.eq(name))
+                .select(AppPasswordSchema::privileged)
+                .first(conn)
+                .optional()
+        })
+        .await?;
+    Ok(privileged.unwrap_or(false))
 }
 
 // We use Argon because it's 3x faster than scrypt.
@@ -100,6 +141,7 @@ pub async fn hash_app_password(did: &String, password: &String) -> Result<String
 pub async fn create_app_password(
     did: String,
     name: String,
+    privileged: bool,
     db: &DbConn,
 ) -> Result<CreateAppPasswordOutput> {
     let str = &get_random_str()[0..16].to_lowercase();
@@ -118,6 +160,7 @@ pub async fn create_app_password(
                 AppPasswordSchema::name.eq(&name),
                 AppPasswordSchema::password.eq(password_encrypted),
                 AppPasswordSchema::createdAt.eq(&created_at),
+                AppPasswordSchema::privileged.eq(privileged),
             ))
             .returning(AppPassword::as_select())
             .get_result(conn)
@@ -127,6 +170,7 @@ pub async fn create_app_password(
                 name,
                 password,
                 created_at,
+                privileged: Some(privileged),
             })
         } else {
             bail!("could not create app-specific password")
@@ -135,14 +179,18 @@ pub async fn create_app_password(
     .await
 }
 
-pub async fn list_app_passwords(did: &str, db: &DbConn) -> Result<Vec<(String, String)>> {
+pub async fn list_app_passwords(did: &str, db: &DbConn) -> Result<Vec<(String, String, bool)>> {
     use crate::schema::pds::app_password::dsl as AppPasswordSchema;
 
     let did = did.to_owned();
     db.run(move |conn| {
         Ok(AppPasswordSchema::app_password
             .filter(AppPasswordSchema::did.eq(did))
-            .select((AppPasswordSchema::name, AppPasswordSchema::createdAt))
+            .select((
+                AppPasswordSchema::name,
+                AppPasswordSchema::createdAt,
+                AppPasswordSchema::privileged,
+            ))
             .get_results(conn)?)
     })
     .await
@@ -175,3 +223,16 @@ pub async fn delete_app_password(did: &str, name: &str, db: &DbConn) -> Result<(
     })
     .await
 }
+
+pub async fn delete_app_passwords_by_did(did: &str, db: &DbConn) -> Result<()> {
+    use crate::schema::pds::app_password::dsl as AppPasswordSchema;
+
+    let did = did.to_owned();
+    db.run(move |conn| {
+        delete(AppPasswordSchema::app_password)
+            .filter(AppPasswordSchema::did.eq(did))
+            .execute(conn)?;
+        Ok(())
+    })
+    .await
+}
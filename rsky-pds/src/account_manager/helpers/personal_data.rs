@@ -0,0 +1,633 @@
+use crate::actor_store::repo::sqlite_repo::SqliteRepoStore;
+use crate::db::DbConn;
+use crate::models::models::{
+    Account, AccountExport, AccountPref, Actor, AppPassword, Blob, EmailToken, InviteCode,
+    InviteCodeUse, LoginAttempt, ModerationAudit, ModerationReport, PushRegistration, RefreshToken,
+    RepoRoot,
+};
+use anyhow::Result;
+use diesel::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Everything the PDS stores about an account, for subject access requests. Secrets such as
+/// password hashes, email and OAuth tokens and the TOTP secret are left out; their existence is
+/// still listed.
+#[derive(Debug, Serialize)]
+pub struct PersonalData {
+    pub did: String,
+    #[serde(rename = "generatedAt")]
+    pub generated_at: String,
+    pub actor: Option<Actor>,
+    pub account: Option<AccountData>,
+    #[serde(rename = "appPasswords")]
+    pub app_passwords: Vec<AppPasswordData>,
+    pub sessions: Vec<RefreshToken>,
+    #[serde(rename = "oauthSessions")]
+    pub oauth_sessions: Vec<OAuthSessionData>,
+    #[serde(rename = "oauthRequests")]
+    pub oauth_requests: Vec<OAuthRequestData>,
+    pub totp: Option<TotpData>,
+    #[serde(rename = "totpRecoveryCodes")]
+    pub totp_recovery_codes: Vec<TotpRecoveryCodeData>,
+    #[serde(rename = "emailTokens")]
+    pub email_tokens: Vec<EmailTokenData>,
+    #[serde(rename = "emailPreference")]
+    pub email_preference: Option<String>,
+    pub locale: Option<String>,
+    /// Why mail to the account's address stopped, if it bounced or was marked as spam.
+    #[serde(rename = "emailUndeliverable")]
+    pub email_undeliverable: Option<EmailUndeliverableData>,
+    pub preferences: Vec<AccountPref>,
+    #[serde(rename = "inviteCodes")]
+    pub invite_codes: Vec<InviteCode>,
+    #[serde(rename = "inviteCodeUses")]
+    pub invite_code_uses: Vec<InviteCodeUse>,
+    #[serde(rename = "loginAttempts")]
+    pub login_attempts: Vec<LoginAttempt>,
+    #[serde(rename = "pushRegistrations")]
+    pub push_registrations: Vec<PushRegistration>,
+    #[serde(rename = "accountExports")]
+    pub account_exports: Vec<AccountExport>,
+    #[serde(rename = "reportsFiled")]
+    pub reports_filed: Vec<ModerationReport>,
+    #[serde(rename = "reportsReceived")]
+    pub reports_received: Vec<ModerationReport>,
+    #[serde(rename = "moderationAudit")]
+    pub moderation_audit: Vec<ModerationAudit>,
+    #[serde(rename = "repoRoot")]
+    pub repo_root: Option<RepoRoot>,
+    pub blobs: Vec<Blob>,
+    #[serde(rename = "recordCount")]
+    pub record_count: i64,
+    #[serde(rename = "repoBlockCount")]
+    pub repo_block_count: i64,
+    #[serde(rename = "repoCommitCount")]
+    pub repo_commit_count: i64,
+    #[serde(rename = "backlinkCount")]
+    pub backlink_count: i64,
+    #[serde(rename = "sequencedEventCount")]
+    pub sequenced_event_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountData {
+    pub email: String,
+    #[serde(rename = "emailConfirmedAt")]
+    pub email_confirmed_at: Option<String>,
+    #[serde(rename = "recoveryKey")]
+    pub recovery_key: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "invitesDisabled")]
+    pub invites_disabled: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppPasswordData {
+    pub name: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    pub privileged: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmailTokenData {
+    pub purpose: &'static str,
+    #[serde(rename = "requestedAt")]
+    pub requested_at: String,
+}
+
+#[derive(Debug, Serialize, Queryable)]
+pub struct OAuthSessionData {
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    pub scope: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+#[derive(Debug, Serialize, Queryable)]
+pub struct OAuthRequestData {
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+#[derive(Debug, Serialize, Queryable)]
+pub struct TotpData {
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "confirmedAt")]
+    pub confirmed_at: Option<String>,
+    #[serde(rename = "lockedUntil")]
+    pub locked_until: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TotpRecoveryCodeData {
+    #[serde(rename = "usedAt")]
+    pub used_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Queryable)]
+pub struct EmailUndeliverableData {
+    pub reason: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl PersonalData {
+    /// Sections that still hold data. The account's deletion event is expected to outlive an
+    /// erasure, since relays need it to drop the account too, so one sequenced event is allowed.
+    pub fn remaining_after_erasure(&self) -> Vec<&'static str> {
+        let sections = [
+            ("actor", self.actor.is_some()),
+            ("account", self.account.is_some()),
+            ("appPasswords", !self.app_passwords.is_empty()),
+            ("sessions", !self.sessions.is_empty()),
+            ("oauthSessions", !self.oauth_sessions.is_empty()),
+            ("oauthRequests", !self.oauth_requests.is_empty()),
+            ("totp", self.totp.is_some()),
+            ("totpRecoveryCodes", !self.totp_recovery_codes.is_empty()),
+            ("emailTokens", !self.email_tokens.is_empty()),
+            ("emailPreference", self.email_preference.is_some()),
+            ("locale", self.locale.is_some()),
+            ("emailUndeliverable", self.email_undeliverable.is_some()),
+            ("preferences", !self.preferences.is_empty()),
+            ("inviteCodes", !self.invite_codes.is_empty()),
+            ("inviteCodeUses", !self.invite_code_uses.is_empty()),
+            ("loginAttempts", !self.login_attempts.is_empty()),
+            ("pushRegistrations", !self.push_registrations.is_empty()),
+            ("accountExports", !self.account_exports.is_empty()),
+            ("reportsFiled", !self.reports_filed.is_empty()),
+            ("reportsReceived", !self.reports_received.is_empty()),
+            ("moderationAudit", !self.moderation_audit.is_empty()),
+            ("repoRoot", self.repo_root.is_some()),
+            ("blobs", !self.blobs.is_empty()),
+            ("recordCount", self.record_count > 0),
+            ("repoBlockCount", self.repo_block_count > 0),
+            ("repoCommitCount", self.repo_commit_count > 0),
+            ("backlinkCount", self.backlink_count > 0),
+            ("sequencedEventCount", self.sequenced_event_count > 1),
+        ];
+        sections
+            .into_iter()
+            .filter(|(_, present)| *present)
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+fn backlink_pattern(did: &str) -> String {
+    format!("at://{did}/%")
+}
+
+pub async fn get_personal_data(did: &str, db: &DbConn) -> Result<PersonalData> {
+    use crate::schema::pds::account::dsl as AccountSchema;
+    use crate::schema::pds::account_export::dsl as AccountExportSchema;
+    use crate::schema::pds::account_locale::dsl as AccountLocaleSchema;
+    use crate::schema::pds::account_pref::dsl as AccountPrefSchema;
+    use crate::schema::pds::actor::dsl as ActorSchema;
+    use crate::schema::pds::app_password::dsl as AppPasswordSchema;
+    use crate::schema::pds::backlink::dsl as BacklinkSchema;
+    use crate::schema::pds::blob::dsl as BlobSchema;
+    use crate::schema::pds::email_preference::dsl as EmailPreferenceSchema;
+    use crate::schema::pds::email_token::dsl as EmailTokenSchema;
+    use crate::schema::pds::email_undeliverable::dsl as EmailUndeliverableSchema;
+    use crate::schema::pds::invite_code::dsl as InviteCodeSchema;
+    use crate::schema::pds::invite_code_use::dsl as InviteCodeUseSchema;
+    use crate::schema::pds::login_attempt::dsl as LoginAttemptSchema;
+    use crate::schema::pds::moderation_audit::dsl as ModerationAuditSchema;
+    use crate::schema::pds::moderation_report::dsl as ModerationReportSchema;
+    use crate::schema::pds::oauth_request::dsl as OAuthRequestSchema;
+    use crate::schema::pds::oauth_token::dsl as OAuthTokenSchema;
+    use crate::schema::pds::push_registration::dsl as PushRegistrationSchema;
+    use crate::schema::pds::record::dsl as RecordSchema;
+    use crate::schema::pds::refresh_token::dsl as RefreshTokenSchema;
+    use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
+    use crate::schema::pds::repo_commit::dsl as RepoCommitSchema;
+    use crate::schema::pds::repo_root::dsl as RepoRootSchema;
+    use crate::schema::pds::repo_seq::dsl as RepoSeqSchema;
+    use crate::schema::pds::totp_recovery_code::dsl as TotpRecoveryCodeSchema;
+    use crate::schema::pds::totp_secret::dsl as TotpSecretSchema;
+
+    let did = did.to_owned();
+    let sqlite = SqliteRepoStore::for_did(&did);
+    let mut res = db
+        .run(move |conn| {
+            conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let account: Option<Account> = AccountSchema::account
+                    .find(&did)
+                    .select(Account::as_select())
+                    .first(conn)
+                    .optional()?;
+                let email_undeliverable: Option<EmailUndeliverableData> = match &account {
+                    None => None,
+                    Some(account) => EmailUndeliverableSchema::email_undeliverable
+                        .find(account.email.to_lowercase())
+                        .select((
+                            EmailUndeliverableSchema::reason,
+                            EmailUndeliverableSchema::createdAt,
+                        ))
+                        .first(conn)
+                        .optional()?,
+                };
+                Ok(PersonalData {
+                    did: did.clone(),
+                    generated_at: rsky_common::now(),
+                    actor: ActorSchema::actor
+                        .find(&did)
+                        .select(Actor::as_select())
+                        .first(conn)
+                        .optional()?,
+                    account: account.map(|account| AccountData {
+                        email: account.email,
+                        email_confirmed_at: account.email_confirmed_at,
+                        recovery_key: account.recovery_key,
+                        created_at: account.created_at,
+                        invites_disabled: account.invites_disabled == 1,
+                    }),
+                    app_passwords: AppPasswordSchema::app_password
+                        .filter(AppPasswordSchema::did.eq(&did))
+                        .select(AppPassword::as_select())
+                        .load(conn)?
+                        .into_iter()
+                        .map(|app_password| AppPasswordData {
+                            name: app_password.name,
+                            created_at: app_password.created_at,
+                            privileged: app_password.privileged,
+                        })
+                        .collect(),
+                    sessions: RefreshTokenSchema::refresh_token
+                        .filter(RefreshTokenSchema::did.eq(&did))
+                        .select(RefreshToken::as_select())
+                        .load(conn)?,
+                    oauth_sessions: OAuthTokenSchema::oauth_token
+                        .filter(OAuthTokenSchema::did.eq(&did))
+                        .select((
+                            OAuthTokenSchema::clientId,
+                            OAuthTokenSchema::scope,
+                            OAuthTokenSchema::createdAt,
+                            OAuthTokenSchema::updatedAt,
+                            OAuthTokenSchema::expiresAt,
+                        ))
+                        .load(conn)?,
+                    oauth_requests: OAuthRequestSchema::oauth_request
+                        .filter(OAuthRequestSchema::did.eq(&did))
+                        .select((
+                            OAuthRequestSchema::clientId,
+                            OAuthRequestSchema::createdAt,
+                            OAuthRequestSchema::expiresAt,
+                        ))
+                        .load(conn)?,
+                    totp: TotpSecretSchema::totp_secret
+                        .find(&did)
+                        .select((
+                            TotpSecretSchema::createdAt,
+                            TotpSecretSchema::confirmedAt,
+                            TotpSecretSchema::lockedUntil,
+                        ))
+                        .first(conn)
+                        .optional()?,
+                    totp_recovery_codes: TotpRecoveryCodeSchema::totp_recovery_code
+                        .filter(TotpRecoveryCodeSchema::did.eq(&did))
+                        .select(TotpRecoveryCodeSchema::usedAt)
+                        .load::<Option<String>>(conn)?
+                        .into_iter()
+                        .map(|used_at| TotpRecoveryCodeData { used_at })
+                        .collect(),
+                    email_tokens: EmailTokenSchema::email_token
+                        .filter(EmailTokenSchema::did.eq(&did))
+                        .select(EmailToken::as_select())
+                        .load(conn)?
+                        .into_iter()
+                        .map(|token| EmailTokenData {
+                            purpose: token.purpose.as_str(),
+                            requested_at: token.requested_at,
+                        })
+                        .collect(),
+                    email_preference: EmailPreferenceSchema::email_preference
+                        .find(&did)
+                        .select(EmailPreferenceSchema::preference)
+                        .first(conn)
+                        .optional()?,
+                    locale: AccountLocaleSchema::account_locale
+                        .find(&did)
+                        .select(AccountLocaleSchema::locale)
+                        .first(conn)
+                        .optional()?,
+                    email_undeliverable,
+                    preferences: AccountPrefSchema::account_pref
+                        .filter(AccountPrefSchema::did.eq(&did))
+                        .select(AccountPref::as_select())
+                        .load(conn)?,
+                    invite_codes: InviteCodeSchema::invite_code
+                        .filter(InviteCodeSchema::forAccount.eq(&did))
+                        .select(InviteCode::as_select())
+                        .load(conn)?,
+                    invite_code_uses: InviteCodeUseSchema::invite_code_use
+                        .filter(InviteCodeUseSchema::usedBy.eq(&did))
+                        .select(InviteCodeUse::as_select())
+                        .load(conn)?,
+                    login_attempts: LoginAttemptSchema::login_attempt
+                        .filter(LoginAttemptSchema::did.eq(&did))
+                        .select(LoginAttempt::as_select())
+                        .load(conn)?,
+                    push_registrations: PushRegistrationSchema::push_registration
+                        .filter(PushRegistrationSchema::did.eq(&did))
+                        .select(PushRegistration::as_select())
+                        .load(conn)?,
+                    account_exports: AccountExportSchema::account_export
+                        .filter(AccountExportSchema::did.eq(&did))
+                        .select(AccountExport::as_select())
+                        .load(conn)?,
+                    reports_filed: ModerationReportSchema::moderation_report
+                        .filter(ModerationReportSchema::reportedBy.eq(&did))
+                        .select(ModerationReport::as_select())
+                        .load(conn)?,
+                    reports_received: ModerationReportSchema::moderation_report
+                        .filter(ModerationReportSchema::subjectDid.eq(&did))
+                        .select(ModerationReport::as_select())
+                        .load(conn)?,
+                    moderation_audit: ModerationAuditSchema::moderation_audit
+                        .filter(ModerationAuditSchema::subjectDid.eq(&did))
+                        .select(ModerationAudit::as_select())
+                        .load(conn)?,
+                    repo_root: RepoRootSchema::repo_root
+                        .find(&did)
+                        .select(RepoRoot::as_select())
+                        .first(conn)
+                        .optional()?,
+                    blobs: BlobSchema::blob
+                        .filter(BlobSchema::did.eq(&did))
+                        .select(Blob::as_select())
+                        .load(conn)?,
+                    record_count: RecordSchema::record
+                        .filter(RecordSchema::did.eq(&did))
+                        .count()
+                        .get_result(conn)?,
+                    repo_block_count: RepoBlockSchema::repo_block
+                        .filter(RepoBlockSchema::did.eq(&did))
+                        .count()
+                        .get_result(conn)?,
+                    repo_commit_count: RepoCommitSchema::repo_commit
+                        .filter(RepoCommitSchema::did.eq(&did))
+                        .count()
+                        .get_result(conn)?,
+                    backlink_count: BacklinkSchema::backlink
+                        .filter(BacklinkSchema::uri.like(backlink_pattern(&did)))
+                        .count()
+                        .get_result(conn)?,
+                    sequenced_event_count: RepoSeqSchema::repo_seq
+                        .filter(RepoSeqSchema::did.eq(&did))
+                        .count()
+                        .get_result(conn)?,
+                })
+            })
+        })
+        .await?;
+    if let Some(sqlite) = sqlite.filter(|sqlite| sqlite.path.exists()) {
+        let (blocks, commits) = sqlite.counts().await?;
+        res.repo_block_count += blocks;
+        res.repo_commit_count += commits;
+    }
+    Ok(res)
+}
+
+/// Deletes every row tied to `did`, returning how many were removed from each table. Blobs
+/// must already be gone from the blobstore, and the repo_seq history is left for the sequencer
+/// to purge once it has emitted the deletion event.
+pub async fn erase_personal_data(did: &str, db: &DbConn) -> Result<BTreeMap<&'static str, usize>> {
+    use crate::schema::pds::account::dsl as AccountSchema;
+    use crate::schema::pds::account_export::dsl as AccountExportSchema;
+    use crate::schema::pds::account_locale::dsl as AccountLocaleSchema;
+    use crate::schema::pds::account_pref::dsl as AccountPrefSchema;
+    use crate::schema::pds::actor::dsl as ActorSchema;
+    use crate::schema::pds::app_password::dsl as AppPasswordSchema;
+    use crate::schema::pds::backlink::dsl as BacklinkSchema;
+    use crate::schema::pds::blob::dsl as BlobSchema;
+    use crate::schema::pds::email_preference::dsl as EmailPreferenceSchema;
+    use crate::schema::pds::email_token::dsl as EmailTokenSchema;
+    use crate::schema::pds::email_undeliverable::dsl as EmailUndeliverableSchema;
+    use crate::schema::pds::invite_code::dsl as InviteCodeSchema;
+    use crate::schema::pds::invite_code_use::dsl as InviteCodeUseSchema;
+    use crate::schema::pds::login_attempt::dsl as LoginAttemptSchema;
+    use crate::schema::pds::moderation_audit::dsl as ModerationAuditSchema;
+    use crate::schema::pds::moderation_report::dsl as ModerationReportSchema;
+    use crate::schema::pds::oauth_request::dsl as OAuthRequestSchema;
+    use crate::schema::pds::oauth_token::dsl as OAuthTokenSchema;
+    use crate::schema::pds::push_registration::dsl as PushRegistrationSchema;
+    use crate::schema::pds::record::dsl as RecordSchema;
+    use crate::schema::pds::record_blob::dsl as RecordBlobSchema;
+    use crate::schema::pds::refresh_token::dsl as RefreshTokenSchema;
+    use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
+    use crate::schema::pds::repo_commit::dsl as RepoCommitSchema;
+    use crate::schema::pds::repo_commit_block::dsl as RepoCommitBlockSchema;
+    use crate::schema::pds::repo_root::dsl as RepoRootSchema;
+    use crate::schema::pds::totp_recovery_code::dsl as TotpRecoveryCodeSchema;
+    use crate::schema::pds::totp_secret::dsl as TotpSecretSchema;
+
+    let did = did.to_owned();
+    let sqlite = SqliteRepoStore::for_did(&did);
+    let mut res = db
+        .run(move |conn| {
+            conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                let mut deleted = BTreeMap::new();
+                let email: Option<String> = AccountSchema::account
+                    .find(&did)
+                    .select(AccountSchema::email)
+                    .first(conn)
+                    .optional()?;
+                if let Some(email) = email {
+                    deleted.insert(
+                        "email_undeliverable",
+                        delete(EmailUndeliverableSchema::email_undeliverable)
+                            .filter(EmailUndeliverableSchema::email.eq(email.to_lowercase()))
+                            .execute(conn)?,
+                    );
+                }
+                deleted.insert(
+                    "backlink",
+                    delete(BacklinkSchema::backlink)
+                        .filter(BacklinkSchema::uri.like(backlink_pattern(&did)))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "record_blob",
+                    delete(RecordBlobSchema::record_blob)
+                        .filter(RecordBlobSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "record",
+                    delete(RecordSchema::record)
+                        .filter(RecordSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "blob",
+                    delete(BlobSchema::blob)
+                        .filter(BlobSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "repo_block",
+                    delete(RepoBlockSchema::repo_block)
+                        .filter(RepoBlockSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "repo_commit",
+                    delete(RepoCommitSchema::repo_commit)
+                        .filter(RepoCommitSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "repo_commit_block",
+                    delete(RepoCommitBlockSchema::repo_commit_block)
+                        .filter(RepoCommitBlockSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "repo_root",
+                    delete(RepoRootSchema::repo_root)
+                        .filter(RepoRootSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "account_pref",
+                    delete(AccountPrefSchema::account_pref)
+                        .filter(AccountPrefSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "app_password",
+                    delete(AppPasswordSchema::app_password)
+                        .filter(AppPasswordSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "refresh_token",
+                    delete(RefreshTokenSchema::refresh_token)
+                        .filter(RefreshTokenSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "oauth_token",
+                    delete(OAuthTokenSchema::oauth_token)
+                        .filter(OAuthTokenSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "oauth_request",
+                    delete(OAuthRequestSchema::oauth_request)
+                        .filter(OAuthRequestSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "email_token",
+                    delete(EmailTokenSchema::email_token)
+                        .filter(EmailTokenSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "email_preference",
+                    delete(EmailPreferenceSchema::email_preference)
+                        .filter(EmailPreferenceSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "account_locale",
+                    delete(AccountLocaleSchema::account_locale)
+                        .filter(AccountLocaleSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "invite_code_use",
+                    delete(InviteCodeUseSchema::invite_code_use)
+                        .filter(InviteCodeUseSchema::usedBy.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "invite_code",
+                    delete(InviteCodeSchema::invite_code)
+                        .filter(InviteCodeSchema::forAccount.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "login_attempt",
+                    delete(LoginAttemptSchema::login_attempt)
+                        .filter(LoginAttemptSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "push_registration",
+                    delete(PushRegistrationSchema::push_registration)
+                        .filter(PushRegistrationSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "totp_recovery_code",
+                    delete(TotpRecoveryCodeSchema::totp_recovery_code)
+                        .filter(TotpRecoveryCodeSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "totp_secret",
+                    delete(TotpSecretSchema::totp_secret)
+                        .filter(TotpSecretSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "account_export",
+                    delete(AccountExportSchema::account_export)
+                        .filter(AccountExportSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "moderation_report",
+                    delete(ModerationReportSchema::moderation_report)
+                        .filter(
+                            ModerationReportSchema::reportedBy
+                                .eq(&did)
+                                .or(ModerationReportSchema::subjectDid.eq(&did)),
+                        )
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "moderation_audit",
+                    delete(ModerationAuditSchema::moderation_audit)
+                        .filter(ModerationAuditSchema::subjectDid.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "account",
+                    delete(AccountSchema::account)
+                        .filter(AccountSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                deleted.insert(
+                    "actor",
+                    delete(ActorSchema::actor)
+                        .filter(ActorSchema::did.eq(&did))
+                        .execute(conn)?,
+                );
+                Ok(deleted)
+            })
+        })
+        .await?;
+    if let Some(sqlite) = sqlite {
+        let (blocks, commits) = sqlite.destroy().await?;
+        *res.entry("repo_block").or_default() += blocks;
+        *res.entry("repo_commit").or_default() += commits;
+    }
+    Ok(res)
+}
@@ -0,0 +1,61 @@
+use crate::db::DbConn;
+use crate::models::models::PushRegistration;
+use anyhow::Result;
+use diesel::*;
+use rsky_common;
+
+pub struct RegisterPushOpts {
+    pub did: String,
+    pub service_did: String,
+    pub token: String,
+    pub platform: String,
+    pub app_id: String,
+}
+
+/// Record a device token for `did`. Re-registering a token refreshes its service, platform and
+/// app, keeping the original creation time.
+pub async fn upsert_registration(opts: RegisterPushOpts, db: &DbConn) -> Result<()> {
+    use crate::schema::pds::push_registration::dsl as PushRegistrationSchema;
+
+    let now = rsky_common::now();
+    let row = PushRegistration {
+        did: opts.did,
+        service_did: opts.service_did,
+        token: opts.token,
+        platform: opts.platform,
+        app_id: opts.app_id,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    db.run(move |conn| {
+        insert_into(PushRegistrationSchema::push_registration)
+            .values(&row)
+            .on_conflict((PushRegistrationSchema::did, PushRegistrationSchema::token))
+            .do_update()
+            .set((
+                PushRegistrationSchema::serviceDid.eq(&row.service_did),
+                PushRegistrationSchema::platform.eq(&row.platform),
+                PushRegistrationSchema::appId.eq(&row.app_id),
+                PushRegistrationSchema::updatedAt.eq(&row.updated_at),
+            ))
+            .execute(conn)
+    })
+    .await?;
+    Ok(())
+}
+
+pub async fn get_registrations(did: &str, db: &DbConn) -> Result<Vec<PushRegistration>> {
+    use crate::schema::pds::push_registration::dsl as PushRegistrationSchema;
+
+    let did = did.to_owned();
+    let res = db
+        .run(move |conn| {
+            PushRegistrationSchema::push_registration
+                .filter(PushRegistrationSchema::did.eq(did))
+                .order(PushRegistrationSchema::updatedAt.desc())
+                .select(PushRegistration::as_select())
+                .load(conn)
+        })
+        .await?;
+    Ok(res)
+}
@@ -0,0 +1,493 @@
+//! Authenticator app codes (RFC 6238 TOTP) as a second factor for account password logins.
+//! Enrollment stores a secret unconfirmed until the user proves their app has it with a first
+//! code; from then on `createSession` and OAuth sign-in ask for a code, or for one of the
+//! single-use recovery codes handed out on confirmation.
+//!
+//! Secrets are stored encrypted with a server key, `PDS_TOTP_ENCRYPTION_KEY` (32 hex encoded
+//! bytes) or else one derived from `PDS_JWT_KEY_K256_PRIVATE_KEY_HEX`. After
+//! `MAX_FAILED_ATTEMPTS` wrong codes in a row the account's second factor is locked for
+//! `LOCKOUT_SECS`, which also keeps guesses at recovery codes from tying up the CPU hashing them.
+
+use crate::account_manager::helpers::password::hash_app_password;
+use crate::apis::ApiError;
+use crate::db::DbConn;
+use crate::models::TotpSecret;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+use chrono::{Duration, Utc};
+use data_encoding::BASE32_NOPAD;
+use diesel::*;
+use hmac::{Hmac, Mac};
+use rsky_common::env::env_str;
+use rsky_common::{get_random_str, now, RFC3339_VARIANT};
+use sha1::Sha1;
+use sha2::Sha256;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds each code is valid for.
+pub const TOTP_PERIOD: u64 = 30;
+pub const TOTP_DIGITS: usize = 6;
+/// Steps either side of the current one a code is accepted from, for clock drift.
+pub const TOTP_SKEW: u64 = 1;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+/// Wrong codes in a row before the second factor is locked.
+pub const MAX_FAILED_ATTEMPTS: i32 = 5;
+pub const LOCKOUT_SECS: i64 = 15 * 60;
+const NONCE_LEN: usize = 12;
+
+/// A new random secret, base32 encoded as authenticator apps expect it.
+pub fn generate_secret() -> String {
+    BASE32_NOPAD.encode(&rand::random::<[u8; 20]>())
+}
+
+fn encryption_key() -> Result<Key> {
+    if let Some(key) = env_str("PDS_TOTP_ENCRYPTION_KEY") {
+        let key = hex::decode(key.trim()).context("PDS_TOTP_ENCRYPTION_KEY is not valid hex")?;
+        if key.len() != 32 {
+            bail!("PDS_TOTP_ENCRYPTION_KEY must be 32 bytes");
+        }
+        return Ok(*Key::from_slice(&key));
+    }
+    let Some(jwt_key) = env_str("PDS_JWT_KEY_K256_PRIVATE_KEY_HEX") else {
+        bail!("PDS_TOTP_ENCRYPTION_KEY is not configured");
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(jwt_key.trim().as_bytes())
+        .expect("HMAC accepts any key length");
+    mac.update(b"rsky-pds totp secret");
+    Ok(mac.finalize().into_bytes())
+}
+
+/// Encrypts `secret` for storage, bound to `did` so it can't be moved to another account.
+pub fn encrypt_secret(did: &str, secret: &str) -> Result<String> {
+    let cipher = ChaCha20Poly1305::new(&encryption_key()?);
+    let nonce = rand::random::<[u8; NONCE_LEN]>();
+    let payload = Payload {
+        msg: secret.as_bytes(),
+        aad: did.as_bytes(),
+    };
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), payload)
+        .map_err(|_| anyhow!("failed to encrypt two-factor secret"))?;
+    Ok(STANDARD.encode([nonce.as_slice(), &ciphertext].concat()))
+}
+
+pub fn decrypt_secret(did: &str, encrypted: &str) -> Result<String> {
+    let bytes = STANDARD.decode(encrypted)?;
+    if bytes.len() < NONCE_LEN {
+        bail!("two-factor secret is malformed");
+    }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(&encryption_key()?);
+    let payload = Payload {
+        msg: ciphertext,
+        aad: did.as_bytes(),
+    };
+    let secret = cipher
+        .decrypt(Nonce::from_slice(nonce), payload)
+        .map_err(|_| anyhow!("failed to decrypt two-factor secret"))?;
+    Ok(String::from_utf8(secret)?)
+}
+
+/// The `otpauth://` URI authenticator apps enroll from, usually shown as a QR code.
+pub fn provisioning_uri(secret: &str, account: &str, issuer: &str) -> String {
+    let label: String =
+        url::form_urlencoded::byte_serialize(format!("{issuer}:{account}").as_bytes()).collect();
+    let issuer: String = url::form_urlencoded::byte_serialize(issuer.as_bytes()).collect();
+    format!(
+        "otpauth://totp/{label}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={TOTP_DIGITS}&period={TOTP_PERIOD}"
+    )
+}
+
+/// The code for `secret` in time step `step`.
+pub fn code_at(secret: &[u8], step: u64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    // dynamic truncation, RFC 4226 section 5.3
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset],
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]) & 0x7fff_ffff;
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_DIGITS as u32),
+        width = TOTP_DIGITS
+    )
+}
+
+/// The step `code` belongs to around `now` (seconds since the epoch), skipping steps at or
+/// before `last_used_step` so an accepted code can't be used again.
+pub fn matching_step(
+    secret: &str,
+    code: &str,
+    now: u64,
+    last_used_step: Option<i64>,
+) -> Option<u64> {
+    if code.len() != TOTP_DIGITS || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let secret = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let current = now / TOTP_PERIOD;
+    (current.saturating_sub(TOTP_SKEW)..=current + TOTP_SKEW)
+        .filter(|step| last_used_step.is_none_or(|last| *step as i64 > last))
+        .find(|step| code_at(&secret, *step) == code)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Whether `token` could be a recovery code at all, so only those are hashed and looked up.
+fn is_recovery_code(token: &str) -> bool {
+    token.len() == 11
+        && token.bytes().enumerate().all(|(i, b)| {
+            if i == 5 {
+                b == b'-'
+            } else {
+                b.is_ascii_alphanumeric()
+            }
+        })
+}
+
+/// Recovery codes, with format: abcde-12345
+fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let str = get_random_str()[0..10].to_lowercase();
+            format!("{}-{}", &str[0..5], &str[5..10])
+        })
+        .collect()
+}
+
+pub async fn get_totp(did: &str, db: &DbConn) -> Result<Option<TotpSecret>> {
+    use crate::schema::pds::totp_secret::dsl as TotpSecretSchema;
+
+    let did = did.to_owned();
+    let res = db
+        .run(move |conn| {
+            TotpSecretSchema::totp_secret
+                .filter(TotpSecretSchema::did.eq(did))
+                .select(TotpSecret::as_select())
+                .first(conn)
+                .optional()
+        })
+        .await?;
+    Ok(res)
+}
+
+/// Fails while the second factor of `totp` is locked after too many wrong codes.
+fn check_not_locked(totp: &TotpSecret) -> Result<()> {
+    match &totp.locked_until {
+        Some(locked_until) if *locked_until > now() => bail!(ApiError::RateLimitExceeded(
+            "Too many wrong two-factor codes, try again later".to_string()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Counts a wrong code against `did`, locking its second factor once there are too many.
+async fn record_failure(did: &str, db: &DbConn) -> Result<()> {
+    use crate::schema::pds::totp_secret::dsl as TotpSecretSchema;
+
+    let did = did.to_owned();
+    let locked_until = (Utc::now() + Duration::seconds(LOCKOUT_SECS))
+        .format(RFC3339_VARIANT)
+        .to_string();
+    db.run(move |conn| {
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let failed_attempts: i32 = update(TotpSecretSchema::totp_secret)
+                .filter(TotpSecretSchema::did.eq(&did))
+                .set(TotpSecretSchema::failedAttempts.eq(TotpSecretSchema::failedAttempts + 1))
+                .returning(TotpSecretSchema::failedAttempts)
+                .get_result(conn)?;
+            if failed_attempts >= MAX_FAILED_ATTEMPTS {
+                tracing::warn!("@LOG: locked two-factor authentication for {did}");
+                update(TotpSecretSchema::totp_secret)
+                    .filter(TotpSecretSchema::did.eq(&did))
+                    .set((
+                        TotpSecretSchema::failedAttempts.eq(0),
+                        TotpSecretSchema::lockedUntil.eq(&locked_until),
+                    ))
+                    .execute(conn)?;
+            }
+            Ok(())
+        })
+    })
+    .await?;
+    Ok(())
+}
+
+async fn reset_failures(did: &str, db: &DbConn) -> Result<()> {
+    use crate::schema::pds::totp_secret::dsl as TotpSecretSchema;
+
+    let did = did.to_owned();
+    db.run(move |conn| {
+        update(TotpSecretSchema::totp_secret)
+            .filter(TotpSecretSchema::did.eq(&did))
+            .filter(TotpSecretSchema::failedAttempts.gt(0))
+            .set(TotpSecretSchema::failedAttempts.eq(0))
+            .execute(conn)
+    })
+    .await?;
+    Ok(())
+}
+
+/// Starts enrollment with a new secret, replacing any that wasn't confirmed. Returns the
+/// secret in the clear, for the user's app.
+pub async fn create_totp_secret(did: &str, db: &DbConn) -> Result<String> {
+    use crate::schema::pds::totp_secret::dsl as TotpSecretSchema;
+
+    let secret = generate_secret();
+    let row = TotpSecret {
+        did: did.to_owned(),
+        secret: encrypt_secret(did, &secret)?,
+        last_used_step: None,
+        created_at: now(),
+        confirmed_at: None,
+        failed_attempts: 0,
+        locked_until: None,
+    };
+    db.run(move |conn| {
+        conn.transaction::<_, anyhow::Error, _>(|conn| {
+            let existing = TotpSecretSchema::totp_secret
+                .filter(TotpSecretSchema::did.eq(&row.did))
+                .select(TotpSecret::as_select())
+                .for_update()
+                .first(conn)
+                .optional()?;
+            if let Some(existing) = existing {
+                if existing.confirmed_at.is_some() {
+                    bail!(ApiError::InvalidRequest(
+                        "Two-factor authentication is already enabled".to_string()
+                    ));
+                }
+                // a new secret doesn't get a fresh set of guesses
+                check_not_locked(&existing)?;
+            }
+            insert_into(TotpSecretSchema::totp_secret)
+                .values(&row)
+                .on_conflict(TotpSecretSchema::did)
+                .do_update()
+                .set((
+                    TotpSecretSchema::secret.eq(&row.secret),
+                    TotpSecretSchema::createdAt.eq(&row.created_at),
+                    TotpSecretSchema::lastUsedStep.eq(None::<i64>),
+                ))
+                .execute(conn)?;
+            Ok(())
+        })
+    })
+    .await?;
+    Ok(secret)
+}
+
+/// Enables the secret from [`create_totp_secret`] once `code` shows the user's app has it,
+/// returning recovery codes to replace any earlier ones.
+pub async fn confirm_totp(did: &str, code: &str, db: &DbConn) -> Result<Vec<String>> {
+    use crate::schema::pds::totp_recovery_code::dsl as TotpRecoveryCodeSchema;
+    use crate::schema::pds::totp_secret::dsl as TotpSecretSchema;
+
+    let Some(totp) = get_totp(did, db).await? else {
+        bail!(ApiError::InvalidRequest(
+            "No two-factor secret to confirm, create one first".to_string()
+        ));
+    };
+    if totp.confirmed_at.is_some() {
+        bail!(ApiError::InvalidRequest(
+            "Two-factor authentication is already enabled".to_string()
+        ));
+    }
+    check_not_locked(&totp)?;
+    let secret = decrypt_secret(did, &totp.secret)?;
+    let Some(step) = matching_step(&secret, code.trim(), unix_now(), None) else {
+        record_failure(did, db).await?;
+        bail!(ApiError::InvalidToken);
+    };
+
+    let did = did.to_owned();
+    let recovery_codes = generate_recovery_codes();
+    let mut hashes = Vec::with_capacity(recovery_codes.len());
+    for recovery_code in recovery_codes.iter() {
+        hashes.push(hash_app_password(&did, recovery_code).await?);
+    }
+    let confirmed_at = now();
+    db.run(move |conn| {
+        conn.transaction::<_, anyhow::Error, _>(|conn| {
+            // only the secret the code was checked against, if it's still unconfirmed
+            let confirmed = update(TotpSecretSchema::totp_secret)
+                .filter(TotpSecretSchema::did.eq(&did))
+                .filter(TotpSecretSchema::secret.eq(&totp.secret))
+                .filter(TotpSecretSchema::confirmedAt.is_null())
+                .set((
+                    TotpSecretSchema::confirmedAt.eq(&confirmed_at),
+                    TotpSecretSchema::lastUsedStep.eq(step as i64),
+                    TotpSecretSchema::failedAttempts.eq(0),
+                ))
+                .execute(conn)?;
+            if confirmed == 0 {
+                bail!(ApiError::InvalidRequest(
+                    "Two-factor secret changed while confirming it, try again".to_string()
+                ));
+            }
+            delete(TotpRecoveryCodeSchema::totp_recovery_code)
+                .filter(TotpRecoveryCodeSchema::did.eq(&did))
+                .execute(conn)?;
+            let rows = hashes
+                .iter()
+                .map(|hash| {
+                    (
+                        TotpRecoveryCodeSchema::did.eq(&did),
+                        TotpRecoveryCodeSchema::codeHash.eq(hash),
+                    )
+                })
+                .collect::<Vec<_>>();
+            insert_into(TotpRecoveryCodeSchema::totp_recovery_code)
+                .values(&rows)
+                .execute(conn)?;
+            Ok(())
+        })
+    })
+    .await?;
+    Ok(recovery_codes)
+}
+
+/// Checks the second factor of an account password login. Accounts without two-factor
+/// authentication pass; the others need a current code or an unused recovery code, each of
+/// which is accepted once.
+pub async fn verify_auth_factor(did: &str, token: Option<String>, db: &DbConn) -> Result<()> {
+    use crate::schema::pds::totp_recovery_code::dsl as TotpRecoveryCodeSchema;
+    use crate::schema::pds::totp_secret::dsl as TotpSecretSchema;
+
+    let totp = match get_totp(did, db).await? {
+        Some(totp) if totp.confirmed_at.is_some() => totp,
+        _ => return Ok(()),
+    };
+    let token = match token.map(|token| token.trim().to_lowercase()) {
+        Some(token) if !token.is_empty() => token,
+        _ => bail!(ApiError::AuthFactorRequired),
+    };
+    check_not_locked(&totp)?;
+    let secret = decrypt_secret(did, &totp.secret)?;
+    let owned_did = did.to_owned();
+    let used = match matching_step(&secret, &token, unix_now(), totp.last_used_step) {
+        Some(step) => {
+            let step = step as i64;
+            db.run(move |conn| {
+                // conditional, so two logins racing with the same code can't both get in
+                update(TotpSecretSchema::totp_secret)
+                    .filter(TotpSecretSchema::did.eq(&owned_did))
+                    .filter(
+                        TotpSecretSchema::lastUsedStep
+                            .is_null()
+                            .or(TotpSecretSchema::lastUsedStep.lt(step)),
+                    )
+                    .set(TotpSecretSchema::lastUsedStep.eq(step))
+                    .execute(conn)
+            })
+            .await?
+        }
+        None if is_recovery_code(&token) => {
+            let hash = hash_app_password(&owned_did, &token).await?;
+            let used_at = now();
+            db.run(move |conn| {
+                update(TotpRecoveryCodeSchema::totp_recovery_code)
+                    .filter(TotpRecoveryCodeSchema::did.eq(&owned_did))
+                    .filter(TotpRecoveryCodeSchema::codeHash.eq(&hash))
+                    .filter(TotpRecoveryCodeSchema::usedAt.is_null())
+                    .set(TotpRecoveryCodeSchema::usedAt.eq(&used_at))
+                    .execute(conn)
+            })
+            .await?
+        }
+        None => 0,
+    };
+    if used == 0 {
+        record_failure(did, db).await?;
+        bail!(ApiError::InvalidToken);
+    }
+    if totp.failed_attempts > 0 {
+        reset_failures(did, db).await?;
+    }
+    Ok(())
+}
+
+/// Removes the secret and recovery codes of `did`, returning whether it had a secret.
+pub async fn delete_totp(did: &str, db: &DbConn) -> Result<bool> {
+    use crate::schema::pds::totp_recovery_code::dsl as TotpRecoveryCodeSchema;
+    use crate::schema::pds::totp_secret::dsl as TotpSecretSchema;
+
+    let did = did.to_owned();
+    let deleted = db
+        .run(move |conn| {
+            conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                delete(TotpRecoveryCodeSchema::totp_recovery_code)
+                    .filter(TotpRecoveryCodeSchema::did.eq(&did))
+                    .execute(conn)?;
+                delete(TotpSecretSchema::totp_secret)
+                    .filter(TotpSecretSchema::did.eq(&did))
+                    .execute(conn)
+            })
+        })
+        .await?;
+    Ok(deleted > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_totp_codes() {
+        // RFC 6238 appendix B, SHA1 secret, last 6 digits
+        let secret = b"12345678901234567890";
+        assert_eq!(code_at(secret, 59 / TOTP_PERIOD), "287082");
+        assert_eq!(code_at(secret, 1111111109 / TOTP_PERIOD), "081804");
+        assert_eq!(code_at(secret, 2000000000 / TOTP_PERIOD), "279037");
+
+        let encoded = BASE32_NOPAD.encode(secret);
+        let now = 1111111109;
+        assert_eq!(matching_step(&encoded, "081804", now, None), Some(now / 30));
+        // the previous step's code is still accepted, but not once it has been used
+        assert_eq!(
+            matching_step(&encoded, "081804", now + 30, None),
+            Some(now / 30)
+        );
+        assert_eq!(
+            matching_step(&encoded, "081804", now, Some((now / 30) as i64)),
+            None
+        );
+        assert_eq!(matching_step(&encoded, "081804", now + 90, None), None);
+        assert_eq!(matching_step(&encoded, "81804", now, None), None);
+
+        assert!(is_recovery_code("abcde-12345"));
+        assert!(!is_recovery_code("081804"));
+        assert!(!is_recovery_code("abcde12345x"));
+
+        assert_eq!(
+            provisioning_uri("ABC", "alice.example.com", "example.com"),
+            "otpauth://totp/example.com%3Aalice.example.com?secret=ABC&issuer=example.com&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+
+    #[test]
+    fn test_secret_encryption() {
+        std::env::set_var("PDS_TOTP_ENCRYPTION_KEY", hex::encode([7u8; 32]));
+        let secret = generate_secret();
+        let encrypted = encrypt_secret("did:plc:alice", &secret).unwrap();
+        assert!(!encrypted.contains(&secret));
+        assert_ne!(encrypted, encrypt_secret("did:plc:alice", &secret).unwrap());
+        assert_eq!(decrypt_secret("did:plc:alice", &encrypted).unwrap(), secret);
+        // bound to the account it was created for
+        assert!(decrypt_secret("did:plc:mallory", &encrypted).is_err());
+        assert!(decrypt_secret("did:plc:alice", "AAAA").is_err());
+    }
+}
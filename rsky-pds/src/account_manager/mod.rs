@@ -1,20 +1,36 @@
 use crate::account_manager::helpers::account::{
-    AccountStatus, ActorAccount, AvailabilityFlags, GetAccountAdminStatusOutput,
+    AccountStatus, AccountStatusTransition, ActorAccount, AvailabilityFlags,
+    GetAccountAdminStatusOutput,
 };
 use crate::account_manager::helpers::auth::{
     AuthHelperError, CreateTokensOpts, RefreshGracePeriodOpts,
 };
-use crate::account_manager::helpers::invite::CodeDetail;
-use crate::account_manager::helpers::password::UpdateUserPasswordOpts;
+use crate::account_manager::helpers::invite::{CodeDetail, Referral};
+use crate::account_manager::helpers::login_attempt::LoginAttemptOpts;
+use crate::account_manager::helpers::moderation::{AuditEventOpts, CreateReportOpts};
+use crate::account_manager::helpers::password::{AppPassDescript, UpdateUserPasswordOpts};
+use crate::account_manager::helpers::personal_data::PersonalData;
+use crate::account_manager::helpers::push_registration::RegisterPushOpts;
 use crate::account_manager::helpers::repo;
 use crate::auth_verifier::AuthScope;
 use crate::db::DbConn;
-use crate::models::models::EmailTokenPurpose;
-use anyhow::Result;
+use crate::locale::RequestLocale;
+use crate::mailer::EmailCategory;
+use crate::models::models::{
+    AccountExport, EmailTokenPurpose, LoginAttempt, ModerationReport, PushRegistration,
+    RefreshToken, TotpSecret,
+};
+use crate::webhooks::{self, WebhookEvent};
+use crate::{sequencer, SharedSequencer};
+use anyhow::{bail, Result};
 use chrono::offset::Utc as UtcOffset;
 use chrono::DateTime;
 use futures::try_join;
-use helpers::{account, auth, email_token, invite, password};
+use helpers::{
+    account, account_export, account_locale, auth, email_preference, email_token,
+    email_undeliverable, invite, login_attempt, moderation, password, personal_data,
+    push_registration, totp,
+};
 use lexicon_cid::Cid;
 use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome};
@@ -51,11 +67,15 @@ pub struct ConfirmEmailOpts<'em> {
 pub struct ResetPasswordOpts {
     pub password: String,
     pub token: String,
+    pub revoke_app_passwords: bool,
 }
 
 pub struct UpdateAccountPasswordOpts {
     pub did: String,
     pub password: String,
+    /// Also delete the account's app passwords. Sessions are always revoked, except those
+    /// descending from a surviving app password.
+    pub revoke_app_passwords: bool,
 }
 
 pub struct UpdateEmailOpts {
@@ -93,6 +113,11 @@ impl AccountManager {
         account::get_account(handle_or_did, flags, db.as_ref()).await
     }
 
+    pub async fn get_taken_handles(&self, handles: Vec<String>) -> Result<Vec<String>> {
+        let db = self.db.clone();
+        account::get_taken_handles(handles, db.as_ref()).await
+    }
+
     pub async fn get_account_by_email(
         &self,
         email: &str,
@@ -166,13 +191,14 @@ impl AccountManager {
         if let Some(invite_code) = invite_code.clone() {
             invite::ensure_invite_is_available(invite_code, db.as_ref()).await?;
         }
-        account::register_actor(did.clone(), handle, deactivated, db.as_ref()).await?;
+        account::register_actor(did.clone(), handle.clone(), deactivated, db.as_ref()).await?;
         if let (Some(email), Some(password_encrypted)) = (email, password_encrypted) {
             account::register_account(did.clone(), email, password_encrypted, db.as_ref()).await?;
         }
         invite::record_invite_use(did.clone(), invite_code, now, db.as_ref()).await?;
-        auth::store_refresh_token(refresh_payload, None, db.as_ref()).await?;
-        repo::update_root(did, repo_cid, repo_rev, db.as_ref()).await?;
+        auth::store_refresh_token(refresh_payload, None, None, db.as_ref()).await?;
+        repo::update_root(did.clone(), repo_cid, repo_rev, db.as_ref()).await?;
+        webhooks::notify(WebhookEvent::AccountCreated { did, handle });
         Ok((access_jwt, refresh_jwt))
     }
 
@@ -191,21 +217,36 @@ impl AccountManager {
 
     pub async fn delete_account(&self, did: &str) -> Result<()> {
         let db = self.db.clone();
-        account::delete_account(did, db.as_ref()).await
+        account::delete_account(did, db.as_ref()).await?;
+        webhooks::notify(WebhookEvent::AccountDeleted {
+            did: did.to_owned(),
+        });
+        Ok(())
     }
 
     pub async fn takedown_account(&self, did: &str, takedown: StatusAttr) -> Result<()> {
+        let event = WebhookEvent::AccountTakedown {
+            did: did.to_owned(),
+            applied: takedown.applied,
+            r#ref: takedown.r#ref.clone(),
+        };
         (_, _) = try_join!(
             account::update_account_takedown_status(did, takedown, self.db.as_ref()),
             auth::revoke_refresh_tokens_by_did(did, self.db.as_ref())
         )?;
+        webhooks::notify(event);
         Ok(())
     }
 
     // @NOTE should always be paired with a sequenceHandle().
     pub async fn update_handle(&self, did: &str, handle: &str) -> Result<()> {
         let db = self.db.clone();
-        account::update_handle(did, handle, db.as_ref()).await
+        account::update_handle(did, handle, db.as_ref()).await?;
+        webhooks::notify(WebhookEvent::HandleUpdated {
+            did: did.to_owned(),
+            handle: handle.to_owned(),
+        });
+        Ok(())
     }
 
     pub async fn deactivate_account(&self, did: &str, delete_after: Option<String>) -> Result<()> {
@@ -234,23 +275,64 @@ impl AccountManager {
         }
     }
 
+    /// Applies `transitions` in order, then sequences a single `#account` event with the
+    /// resulting status. Every account status change should go through here so the firehose
+    /// sees exactly one event per change, and one that reflects what's actually stored.
+    pub async fn update_account_status(
+        &self,
+        did: &str,
+        transitions: Vec<AccountStatusTransition>,
+        sequencer: &SharedSequencer,
+    ) -> Result<AccountStatus> {
+        for transition in transitions {
+            match transition {
+                AccountStatusTransition::Activate => self.activate_account(did).await?,
+                AccountStatusTransition::Deactivate { delete_after } => {
+                    self.deactivate_account(did, delete_after).await?
+                }
+                AccountStatusTransition::Takedown(takedown) => {
+                    self.takedown_account(did, takedown).await?
+                }
+                AccountStatusTransition::Delete => self.delete_account(did).await?,
+            }
+        }
+        self.sequence_account_status(did, sequencer).await
+    }
+
+    /// Sequences an `#account` event with the account's stored status. Once an account is
+    /// deleted, its other events are purged from the sequencer.
+    pub async fn sequence_account_status(
+        &self,
+        did: &str,
+        sequencer: &SharedSequencer,
+    ) -> Result<AccountStatus> {
+        let status = self.get_account_status(did).await?;
+        let seq = {
+            let lock = sequencer.sequencer.read().await;
+            lock.sequence_account_evt(did.to_owned(), status.clone())
+                .await?
+        };
+        if status == AccountStatus::Deleted {
+            sequencer::delete_all_for_user(&did.to_owned(), Some(vec![seq])).await?;
+        }
+        Ok(status)
+    }
+
     // Auth
     // ----------
     pub async fn create_session(
         &self,
         did: String,
-        app_password_name: Option<String>,
+        app_password: Option<AppPassDescript>,
+        session_name: Option<String>,
     ) -> Result<(String, String)> {
         let db = self.db.clone();
         let secp = Secp256k1::new();
         let private_key = env::var("PDS_JWT_KEY_K256_PRIVATE_KEY_HEX")?;
         let secret_key = SecretKey::from_slice(&hex::decode(private_key.as_bytes())?)?;
         let jwt_key = Keypair::from_secret_key(&secp, &secret_key);
-        let scope = if app_password_name.is_none() {
-            AuthScope::Access
-        } else {
-            AuthScope::AppPass
-        };
+        let scope =
+            password::session_scope(app_password.as_ref().map(|app_pass| app_pass.privileged));
         let (access_jwt, refresh_jwt) = auth::create_tokens(CreateTokensOpts {
             did,
             jwt_key,
@@ -260,11 +342,22 @@ impl AccountManager {
             expires_in: None,
         })?;
         let refresh_payload = auth::decode_refresh_token(refresh_jwt.clone(), jwt_key)?;
-        auth::store_refresh_token(refresh_payload, app_password_name, db.as_ref()).await?;
+        auth::store_refresh_token(
+            refresh_payload,
+            app_password.map(|app_pass| app_pass.name),
+            session_name,
+            db.as_ref(),
+        )
+        .await?;
         Ok((access_jwt, refresh_jwt))
     }
 
-    pub async fn rotate_refresh_token(&self, id: &String) -> Result<Option<(String, String)>> {
+    /// Rotate the refresh token, renaming the session if `session_name` is given.
+    pub async fn rotate_refresh_token(
+        &self,
+        id: &String,
+        session_name: Option<String>,
+    ) -> Result<Option<(String, String)>> {
         let token = auth::get_refresh_token(id, self.db.as_ref()).await?;
         if let Some(token) = token {
             let system_time = SystemTime::now();
@@ -295,6 +388,13 @@ impl AccountManager {
             // Determine the next refresh token id: upon refresh token
             // reuse you always receive a refresh token with the same id.
             let next_id = token.next_id.unwrap_or_else(auth::get_refresh_token_id);
+            let privileged = match &token.app_password_name {
+                Some(name) => Some(
+                    password::is_privileged_app_password(&token.did, name, self.db.as_ref())
+                        .await?,
+                ),
+                None => None,
+            };
 
             let secp = Secp256k1::new();
             let private_key = env::var("PDS_JWT_KEY_K256_PRIVATE_KEY_HEX").unwrap();
@@ -306,11 +406,7 @@ impl AccountManager {
                 did: token.did,
                 jwt_key,
                 service_did: env::var("PDS_SERVICE_DID").unwrap(),
-                scope: Some(if token.app_password_name.is_none() {
-                    AuthScope::Access
-                } else {
-                    AuthScope::AppPass
-                }),
+                scope: Some(password::session_scope(privileged)),
                 jti: Some(next_id.clone()),
                 expires_in: None,
             })?;
@@ -327,13 +423,14 @@ impl AccountManager {
                 auth::store_refresh_token(
                     refresh_payload,
                     token.app_password_name,
+                    session_name.clone().or(token.session_name),
                     self.db.as_ref()
                 )
             ) {
                 Ok(_) => Ok(Some((access_jwt, refresh_jwt))),
                 Err(e) => match e.downcast_ref() {
                     Some(AuthHelperError::ConcurrentRefresh) => {
-                        Box::pin(self.rotate_refresh_token(id)).await
+                        Box::pin(self.rotate_refresh_token(id, session_name)).await
                     }
                     _ => Err(e),
                 },
@@ -343,6 +440,12 @@ impl AccountManager {
         }
     }
 
+    /// The account's signed-in sessions: unexpired refresh tokens that haven't been rotated.
+    pub async fn list_sessions(&self, did: &str) -> Result<Vec<RefreshToken>> {
+        let now = rsky_common::now();
+        auth::get_active_refresh_tokens(did, now, self.db.as_ref()).await
+    }
+
     pub async fn revoke_refresh_token(&self, id: String) -> Result<bool> {
         auth::revoke_refresh_token(id, self.db.as_ref()).await
     }
@@ -389,6 +492,10 @@ impl AccountManager {
         invite::get_invited_by_for_accounts(dids, db.as_ref()).await
     }
 
+    pub async fn get_referrals(&self, dids: Vec<String>) -> Result<Vec<Referral>> {
+        invite::get_referrals(dids, self.db.as_ref()).await
+    }
+
     pub async fn set_account_invites_disabled(&self, did: &str, disabled: bool) -> Result<()> {
         invite::set_account_invites_disabled(did, disabled, self.db.as_ref()).await
     }
@@ -404,11 +511,12 @@ impl AccountManager {
         &self,
         did: String,
         name: String,
+        privileged: bool,
     ) -> Result<CreateAppPasswordOutput> {
-        password::create_app_password(did, name, self.db.as_ref()).await
+        password::create_app_password(did, name, privileged, self.db.as_ref()).await
     }
 
-    pub async fn list_app_passwords(&self, did: &str) -> Result<Vec<(String, String)>> {
+    pub async fn list_app_passwords(&self, did: &str) -> Result<Vec<(String, String, bool)>> {
         password::list_app_passwords(did, self.db.as_ref()).await
     }
 
@@ -421,7 +529,7 @@ impl AccountManager {
         &self,
         did: &str,
         password_str: &str,
-    ) -> Result<Option<String>> {
+    ) -> Result<Option<AppPassDescript>> {
         let db = self.db.clone();
         password::verify_app_password(did, password_str, db.as_ref()).await
     }
@@ -438,13 +546,18 @@ impl AccountManager {
         self.update_account_password(UpdateAccountPasswordOpts {
             did,
             password: opts.password,
+            revoke_app_passwords: opts.revoke_app_passwords,
         })
         .await
     }
 
     pub async fn update_account_password(&self, opts: UpdateAccountPasswordOpts) -> Result<()> {
         let db = self.db.clone();
-        let UpdateAccountPasswordOpts { did, .. } = opts;
+        let UpdateAccountPasswordOpts {
+            did,
+            revoke_app_passwords,
+            ..
+        } = opts;
         let password_encrypted = password::gen_salt_and_hash(opts.password)?;
         try_join!(
             password::update_user_password(
@@ -455,8 +568,15 @@ impl AccountManager {
                 self.db.as_ref()
             ),
             email_token::delete_email_token(&did, EmailTokenPurpose::ResetPassword, db.as_ref()),
-            auth::revoke_refresh_tokens_by_did(&did, self.db.as_ref())
         )?;
+        if revoke_app_passwords {
+            try_join!(
+                password::delete_app_passwords_by_did(&did, self.db.as_ref()),
+                auth::revoke_refresh_tokens_by_did(&did, self.db.as_ref())
+            )?;
+        } else {
+            auth::revoke_password_refresh_tokens_by_did(&did, self.db.as_ref()).await?;
+        }
         Ok(())
     }
 
@@ -494,7 +614,9 @@ impl AccountManager {
         let UpdateEmailOpts { did, email } = opts;
         try_join!(
             account::update_email(&did, &email, db.as_ref()),
-            email_token::delete_all_email_tokens(&did, db.as_ref())
+            email_token::delete_all_email_tokens(&did, db.as_ref()),
+            // the user is explicitly vouching for this address again
+            email_undeliverable::clear_undeliverable(&email, db.as_ref())
         )?;
         Ok(())
     }
@@ -528,6 +650,189 @@ impl AccountManager {
         let db = self.db.clone();
         email_token::create_email_token(did, purpose, db.as_ref()).await
     }
+
+    // Email Deliverability
+    // ----------
+    /// Record a hard bounce or complaint so no further token emails are sent to `email`.
+    pub async fn mark_email_undeliverable(&self, email: &str, reason: &str) -> Result<()> {
+        let db = self.db.clone();
+        email_undeliverable::mark_undeliverable(email, reason, db.as_ref()).await
+    }
+
+    pub async fn is_email_undeliverable(&self, email: &str) -> Result<bool> {
+        let db = self.db.clone();
+        email_undeliverable::is_undeliverable(email, db.as_ref()).await
+    }
+
+    pub async fn assert_email_deliverable(&self, email: &str) -> Result<()> {
+        if self.is_email_undeliverable(email).await? {
+            bail!("Email address is undeliverable: {email}")
+        }
+        Ok(())
+    }
+
+    // Email Preferences
+    // ----------
+    pub async fn get_email_preference(&self, did: &str) -> Result<String> {
+        let db = self.db.clone();
+        email_preference::get_preference(did, db.as_ref()).await
+    }
+
+    pub async fn update_email_preference(&self, did: &str, preference: &str) -> Result<()> {
+        let db = self.db.clone();
+        email_preference::set_preference(did, preference, db.as_ref()).await
+    }
+
+    /// Whether the account has opted in to mail of this category. Security mail is always sent.
+    pub async fn wants_email(&self, did: &str, category: EmailCategory) -> Result<bool> {
+        match category {
+            EmailCategory::Security => Ok(true),
+            EmailCategory::Notification => {
+                Ok(self.get_email_preference(did).await? == email_preference::EMAIL_PREFERENCE_ALL)
+            }
+        }
+    }
+
+    // Locale
+    // ----------
+    pub async fn get_locale(&self, did: &str) -> Result<Option<String>> {
+        let db = self.db.clone();
+        account_locale::get_locale(did, db.as_ref()).await
+    }
+
+    pub async fn update_locale(&self, did: &str, locale: Option<&str>) -> Result<()> {
+        let db = self.db.clone();
+        account_locale::set_locale(did, locale, db.as_ref()).await
+    }
+
+    /// The locale to write to the account in: its own choice, else the request's.
+    pub async fn mail_locale(&self, did: &str, request: RequestLocale) -> Result<Option<String>> {
+        Ok(self.get_locale(did).await?.or(request.0))
+    }
+
+    // Account Export
+    // ----------
+    /// Start an export, or `None` when `did` already has one pending.
+    pub async fn create_account_export(
+        &self,
+        did: &str,
+        migration: bool,
+    ) -> Result<Option<AccountExport>> {
+        let db = self.db.clone();
+        account_export::create_export(did, migration, db.as_ref()).await
+    }
+
+    pub async fn has_migration_export_since(&self, did: &str, since: String) -> Result<bool> {
+        let db = self.db.clone();
+        account_export::has_migration_export_since(did, since, db.as_ref()).await
+    }
+
+    pub async fn get_account_export(
+        &self,
+        did: &str,
+        id: Option<String>,
+    ) -> Result<Option<AccountExport>> {
+        let db = self.db.clone();
+        account_export::get_export(did, id, db.as_ref()).await
+    }
+
+    /// Mark an export finished, as failed when `error` is set.
+    pub async fn complete_account_export(&self, id: &str, error: Option<String>) -> Result<()> {
+        let db = self.db.clone();
+        account_export::complete_export(id, error, db.as_ref()).await
+    }
+
+    // Login Attempts
+    // ----------
+    pub async fn record_login_attempt(&self, opts: LoginAttemptOpts) -> Result<()> {
+        let db = self.db.clone();
+        login_attempt::record_attempt(opts, db.as_ref()).await
+    }
+
+    pub async fn get_login_attempts(&self, did: &str) -> Result<Vec<LoginAttempt>> {
+        let db = self.db.clone();
+        login_attempt::get_attempts(did, db.as_ref()).await
+    }
+
+    // Two-Factor Authentication
+    // ----------
+    pub async fn get_totp(&self, did: &str) -> Result<Option<TotpSecret>> {
+        let db = self.db.clone();
+        totp::get_totp(did, db.as_ref()).await
+    }
+
+    pub async fn create_totp_secret(&self, did: &str) -> Result<String> {
+        let db = self.db.clone();
+        totp::create_totp_secret(did, db.as_ref()).await
+    }
+
+    pub async fn confirm_totp(&self, did: &str, code: &str) -> Result<Vec<String>> {
+        let db = self.db.clone();
+        totp::confirm_totp(did, code, db.as_ref()).await
+    }
+
+    /// See [`totp::verify_auth_factor`].
+    pub async fn verify_auth_factor(&self, did: &str, token: Option<String>) -> Result<()> {
+        let db = self.db.clone();
+        totp::verify_auth_factor(did, token, db.as_ref()).await
+    }
+
+    pub async fn delete_totp(&self, did: &str) -> Result<bool> {
+        let db = self.db.clone();
+        totp::delete_totp(did, db.as_ref()).await
+    }
+
+    // Personal Data
+    // ----------
+    pub async fn get_personal_data(&self, did: &str) -> Result<PersonalData> {
+        let db = self.db.clone();
+        personal_data::get_personal_data(did, db.as_ref()).await
+    }
+
+    /// Delete every row tied to `did`, by table. See [`personal_data::erase_personal_data`].
+    pub async fn erase_personal_data(&self, did: &str) -> Result<BTreeMap<&'static str, usize>> {
+        let db = self.db.clone();
+        personal_data::erase_personal_data(did, db.as_ref()).await
+    }
+
+    // Push Registrations
+    // ----------
+    pub async fn register_push(&self, opts: RegisterPushOpts) -> Result<()> {
+        let db = self.db.clone();
+        push_registration::upsert_registration(opts, db.as_ref()).await
+    }
+
+    pub async fn get_push_registrations(&self, did: &str) -> Result<Vec<PushRegistration>> {
+        let db = self.db.clone();
+        push_registration::get_registrations(did, db.as_ref()).await
+    }
+
+    // Moderation
+    // ----------
+    pub async fn create_report(&self, opts: CreateReportOpts) -> Result<ModerationReport> {
+        let db = self.db.clone();
+        moderation::create_report(opts, db.as_ref()).await
+    }
+
+    pub async fn count_reporters_since(&self, subject_did: &str, since: String) -> Result<i64> {
+        let db = self.db.clone();
+        moderation::count_reporters_since(subject_did, since, db.as_ref()).await
+    }
+
+    pub async fn has_audit_event_since(
+        &self,
+        subject_did: &str,
+        rule: String,
+        since: String,
+    ) -> Result<bool> {
+        let db = self.db.clone();
+        moderation::has_audit_event_since(subject_did, rule, since, db.as_ref()).await
+    }
+
+    pub async fn record_audit_event(&self, opts: AuditEventOpts) -> Result<i64> {
+        let db = self.db.clone();
+        moderation::record_audit_event(opts, db.as_ref()).await
+    }
 }
 
 pub mod helpers;
@@ -0,0 +1,90 @@
+// based on https://docs.aws.amazon.com/AmazonCloudFront/latest/DeveloperGuide/private-content-creating-signed-url-canned-policy.html
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::signature::{SignatureEncoding, Signer};
+use rsa::RsaPrivateKey;
+use sha1::Sha1;
+
+/// Parses a CloudFront key group private key, in either PKCS#1 or PKCS#8 PEM form.
+pub fn parse_private_key(pem: &str) -> Result<RsaPrivateKey> {
+    match RsaPrivateKey::from_pkcs8_pem(pem) {
+        Ok(key) => Ok(key),
+        Err(_) => Ok(RsaPrivateKey::from_pkcs1_pem(pem)?),
+    }
+}
+
+/// Signs `url` with a canned policy that expires at `expires_at` (unix seconds).
+pub fn sign_url(
+    url: &str,
+    key_pair_id: &str,
+    private_key: &RsaPrivateKey,
+    expires_at: i64,
+) -> String {
+    let policy = format!(
+        r#"{{"Statement":[{{"Resource":"{url}","Condition":{{"DateLessThan":{{"AWS:EpochTime":{expires_at}}}}}}}]}}"#
+    );
+    let signing_key = SigningKey::<Sha1>::new(private_key.clone());
+    let signature = signing_key.sign(policy.as_bytes()).to_vec();
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!(
+        "{url}{separator}Expires={expires_at}&Signature={0}&Key-Pair-Id={key_pair_id}",
+        url_safe_base64(&signature)
+    )
+}
+
+// CloudFront's own base64 variant
+fn url_safe_base64(bytes: &[u8]) -> String {
+    STANDARD
+        .encode(bytes)
+        .replace('+', "-")
+        .replace('=', "_")
+        .replace('/', "~")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::pkcs1v15::VerifyingKey;
+    use rsa::signature::Verifier;
+
+    #[test]
+    fn test_sign_url_with_canned_policy() {
+        let mut rng = rand::thread_rng();
+        let private_key = RsaPrivateKey::new(&mut rng, 1024).unwrap();
+        let url = "https://cdn.example.com/did:plc:abc/blocks/did:plc:abc/bafkrei";
+        let signed = sign_url(url, "K2JCJMDEHXQW5F", &private_key, 1700000000);
+
+        let (base, query) = signed.split_once('?').unwrap();
+        assert_eq!(base, url);
+        let params: Vec<(&str, &str)> = query
+            .split('&')
+            .map(|pair| pair.split_once('=').unwrap())
+            .collect();
+        assert_eq!(params[0], ("Expires", "1700000000"));
+        assert_eq!(params[2], ("Key-Pair-Id", "K2JCJMDEHXQW5F"));
+
+        let signature = STANDARD
+            .decode(
+                params[1]
+                    .1
+                    .replace('-', "+")
+                    .replace('_', "=")
+                    .replace('~', "/"),
+            )
+            .unwrap();
+        let policy = format!(
+            r#"{{"Statement":[{{"Resource":"{url}","Condition":{{"DateLessThan":{{"AWS:EpochTime":1700000000}}}}}}]}}"#
+        );
+        let verifying_key = VerifyingKey::<Sha1>::new(private_key.to_public_key());
+        assert!(verifying_key
+            .verify(
+                policy.as_bytes(),
+                &rsa::pkcs1v15::Signature::try_from(signature.as_slice()).unwrap()
+            )
+            .is_ok());
+    }
+}
@@ -1 +1,2 @@
+pub mod cloudfront;
 pub mod s3;
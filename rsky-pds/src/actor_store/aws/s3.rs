@@ -1,13 +1,17 @@
 use std::str::FromStr;
+use std::time::Duration;
 // based on https://github.com/bluesky-social/atproto/blob/main/packages/aws/src/s3.ts
 use anyhow::Result;
-use aws_config::SdkConfig;
+use aws_config::meta::region::RegionProviderChain;
+use aws_config::{BehaviorVersion, SdkConfig};
 use aws_sdk_s3 as s3;
+use aws_sdk_s3::config::Credentials;
 use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::presigning::PresigningConfig;
 use aws_sdk_s3::primitives::ByteStream;
 use aws_sdk_s3::types::{Delete, ObjectCannedAcl, ObjectIdentifier};
 use lexicon_cid::Cid;
-use rsky_common::env::env_str;
+use rsky_common::env::{env_bool, env_str};
 use rsky_common::get_random_str;
 
 struct MoveObject {
@@ -21,11 +25,43 @@ pub struct S3BlobStore {
     pub bucket: String,
 }
 
-// Intended to work with DigitalOcean Spaces Object Storage which is an
-// S3-compatible object storage service
+/// Loads the shared AWS config used for blob storage.
+///
+/// - `AWS_ENDPOINT`: custom endpoint for S3-compatible services (MinIO, R2, Backblaze, Spaces).
+///   Unset means AWS proper.
+/// - `AWS_REGION`: region to sign requests for, falling back to `us-east-1` (R2 expects `auto`).
+/// - `AWS_S3_ACCESS_KEY_ID` / `AWS_S3_SECRET_ACCESS_KEY`: static credentials. When unset the
+///   default AWS credential chain is used.
+pub async fn load_sdk_config() -> SdkConfig {
+    let mut loader = aws_config::defaults(BehaviorVersion::latest());
+    if let Some(endpoint) = env_str("AWS_ENDPOINT") {
+        loader = loader.endpoint_url(endpoint);
+    }
+    loader = loader.region(RegionProviderChain::default_provider().or_else("us-east-1"));
+    if let (Some(access_key_id), Some(secret_access_key)) = (
+        env_str("AWS_S3_ACCESS_KEY_ID"),
+        env_str("AWS_S3_SECRET_ACCESS_KEY"),
+    ) {
+        loader = loader.credentials_provider(Credentials::new(
+            access_key_id,
+            secret_access_key,
+            None,
+            None,
+            "rsky-pds",
+        ));
+    }
+    loader.load().await
+}
+
+// Intended to work with DigitalOcean Spaces Object Storage and other
+// S3-compatible object storage services. Set `AWS_S3_FORCE_PATH_STYLE=true`
+// for services without virtual-hosted-style buckets, e.g. MinIO.
 impl S3BlobStore {
     pub fn new(did: String, cfg: &SdkConfig) -> Self {
-        let client = aws_sdk_s3::Client::new(cfg);
+        let s3_config = s3::config::Builder::from(cfg)
+            .force_path_style(env_bool("AWS_S3_FORCE_PATH_STYLE").unwrap_or(false))
+            .build();
+        let client = aws_sdk_s3::Client::from_conf(s3_config);
         S3BlobStore {
             client,
             bucket: did,
@@ -52,6 +88,10 @@ impl S3BlobStore {
         format!("quarantine/{0}/{1}", self.bucket, cid)
     }
 
+    fn get_export_path(&self, id: &str) -> String {
+        format!("exports/{0}/{1}.tar", self.bucket, id)
+    }
+
     pub async fn put_temp(&self, bytes: Vec<u8>) -> Result<String> {
         let key = self.gen_key();
         let body = ByteStream::from(bytes);
@@ -125,6 +165,57 @@ impl S3BlobStore {
         }
     }
 
+    /// Path of a stored blob relative to the storage root, for serving through a CDN.
+    pub fn get_public_path(&self, cid: Cid) -> String {
+        format!("{0}/{1}", self.bucket, self.get_stored_path(cid))
+    }
+
+    /// Expiring `GetObject` URL for a stored blob, so clients can fetch it directly.
+    pub async fn get_presigned_url(
+        &self,
+        cid: Cid,
+        expires_in: Duration,
+        mime_type: Option<String>,
+    ) -> Result<String> {
+        let req = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(self.get_stored_path(cid))
+            .set_response_content_type(mime_type)
+            .presigned(PresigningConfig::expires_in(expires_in)?)
+            .await?;
+        Ok(req.uri().to_string())
+    }
+
+    /// Stores an account export archive. Unlike blobs it stays private and is only
+    /// reachable through a presigned URL.
+    pub async fn put_export(&self, id: &str, bytes: Vec<u8>) -> Result<()> {
+        let body = ByteStream::from(bytes);
+        self.client
+            .put_object()
+            .body(body)
+            .bucket(&self.bucket)
+            .key(self.get_export_path(id))
+            .content_type("application/x-tar")
+            .acl(ObjectCannedAcl::Private)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_export_presigned_url(&self, id: &str, expires_in: Duration) -> Result<String> {
+        let req = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(self.get_export_path(id))
+            .response_content_disposition(format!("attachment; filename=\"{id}.tar\""))
+            .presigned(PresigningConfig::expires_in(expires_in)?)
+            .await?;
+        Ok(req.uri().to_string())
+    }
+
     pub async fn get_bytes(&self, cid: Cid) -> Result<Vec<u8>> {
         let res = self.get_object(cid).await?;
         let bytes = res.collect().await.map(|data| data.into_bytes())?;
@@ -156,6 +247,10 @@ impl S3BlobStore {
         Ok(self.has_key(self.get_tmp_path(&key)).await)
     }
 
+    pub async fn delete_temp(&self, key: String) -> Result<()> {
+        self.delete_key(self.get_tmp_path(&key)).await
+    }
+
     async fn has_key(&self, key: String) -> bool {
         let res = self
             .client
@@ -196,12 +291,13 @@ impl S3BlobStore {
         self.client
             .copy_object()
             .bucket(&self.bucket)
-            .copy_source(format!(
-                "{0}/{1}/{2}",
-                env_str("AWS_ENDPOINT_BUCKET").unwrap(),
-                self.bucket,
-                keys.from
-            ))
+            .copy_source(match env_str("AWS_ENDPOINT_BUCKET") {
+                // Spaces addresses the source through the endpoint's own bucket
+                Some(endpoint_bucket) => {
+                    format!("{0}/{1}/{2}", endpoint_bucket, self.bucket, keys.from)
+                }
+                None => format!("{0}/{1}", self.bucket, keys.from),
+            })
             .key(keys.to)
             .acl(ObjectCannedAcl::PublicRead)
             .send()
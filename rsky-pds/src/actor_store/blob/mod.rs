@@ -1,4 +1,8 @@
+use crate::actor_store::aws::cloudfront;
 use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::repo::sqlite_repo::with_record_content;
+use crate::apis::ApiError;
+use crate::config::BlobRedirectConfig;
 use crate::db::DbConn;
 use crate::image;
 use crate::models::models;
@@ -21,8 +25,14 @@ use rsky_lexicon::com::atproto::admin::StatusAttr;
 use rsky_lexicon::com::atproto::repo::ListMissingBlobsRefRecordBlob;
 use rsky_repo::error::BlobError;
 use rsky_repo::types::{PreparedBlobRef, PreparedWrite};
+use rsky_repo::util::cbor_to_lex_record;
 use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+const BLOB_READ_CHUNK_SIZE: usize = 64 * 1024;
 
 pub struct BlobMetadata {
     pub temp_key: String,
@@ -89,7 +99,7 @@ impl BlobReader {
             .await?;
 
         match found {
-            None => bail!("Blob not found"),
+            None => bail!(ApiError::BlobNotFound),
             Some(found) => Ok(GetBlobMetadataOutput {
                 size: found.size,
                 mime_type: Some(found.mime_type),
@@ -117,6 +127,37 @@ impl BlobReader {
         })
     }
 
+    /// Expiring URL a client can be redirected to instead of proxying the blob's bytes.
+    pub async fn get_blob_url(&self, cid: Cid, redirect: &BlobRedirectConfig) -> Result<String> {
+        let metadata = self.get_blob_metadata(cid).await?;
+        match redirect {
+            BlobRedirectConfig::Presigned { expires_in } => {
+                self.blobstore
+                    .get_presigned_url(cid, Duration::from_secs(*expires_in), metadata.mime_type)
+                    .await
+            }
+            BlobRedirectConfig::CloudFront {
+                url,
+                key_pair_id,
+                private_key,
+                expires_in,
+            } => {
+                let url = format!(
+                    "{0}/{1}",
+                    url.trim_end_matches('/'),
+                    self.blobstore.get_public_path(cid)
+                );
+                let expires_at = chrono::Utc::now().timestamp() + *expires_in as i64;
+                Ok(cloudfront::sign_url(
+                    &url,
+                    key_pair_id,
+                    private_key,
+                    expires_at,
+                ))
+            }
+        }
+    }
+
     pub async fn get_records_for_blob(&self, cid: Cid) -> Result<Vec<String>> {
         use crate::schema::pds::record_blob::dsl as RecordBlobSchema;
 
@@ -137,18 +178,119 @@ impl BlobReader {
         Ok(res)
     }
 
+    /// Why a blob shouldn't be served: it or a record referencing it was taken down, or such a
+    /// record self-labels with one of `labels`. `None` if it can be served.
+    pub async fn get_blob_moderation(
+        &self,
+        cid: Cid,
+        labels: Vec<String>,
+    ) -> Result<Option<String>> {
+        use crate::schema::pds::blob::dsl as BlobSchema;
+        use crate::schema::pds::record::dsl as RecordSchema;
+        use crate::schema::pds::record_blob::dsl as RecordBlobSchema;
+        use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
+
+        let did = self.did.clone();
+        let (blob_takedown, records) = self
+            .db
+            .run(move |conn| {
+                let blob_takedown = BlobSchema::blob
+                    .filter(BlobSchema::did.eq(&did))
+                    .filter(BlobSchema::cid.eq(cid.to_string()))
+                    .select(BlobSchema::takedownRef)
+                    .first::<Option<String>>(conn)
+                    .optional()?
+                    .flatten();
+                let records = RecordBlobSchema::record_blob
+                    .inner_join(
+                        RecordSchema::record.on(RecordSchema::uri.eq(RecordBlobSchema::recordUri)),
+                    )
+                    .left_join(
+                        RepoBlockSchema::repo_block.on(RepoBlockSchema::cid.eq(RecordSchema::cid)),
+                    )
+                    .filter(RecordBlobSchema::did.eq(&did))
+                    .filter(RecordBlobSchema::blobCid.eq(cid.to_string()))
+                    .select((
+                        models::Record::as_select(),
+                        Option::<models::RepoBlock>::as_select(),
+                    ))
+                    .get_results::<(models::Record, Option<models::RepoBlock>)>(conn)?;
+                Ok::<_, Error>((blob_takedown, records))
+            })
+            .await?;
+
+        if blob_takedown.is_some() {
+            return Ok(Some("blob has been taken down".to_string()));
+        }
+        for (record, content) in with_record_content(records).await? {
+            if record.takedown_ref.is_some() {
+                return Ok(Some(format!("{} has been taken down", record.uri)));
+            }
+            if labels.is_empty() {
+                continue;
+            }
+            let value = serde_json::to_value(cbor_to_lex_record(content)?)?;
+            let self_labels = value["labels"]["values"].as_array().cloned();
+            for label in self_labels.unwrap_or_default() {
+                if let Some(val) = label["val"].as_str() {
+                    if labels.iter().any(|configured| configured == val) {
+                        return Ok(Some(format!("{} is labeled {val}", record.uri)));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Every blob stored for the account alongside the records referencing it.
+    pub async fn list_blobs_with_records(&self) -> Result<Vec<(models::Blob, Vec<String>)>> {
+        use crate::schema::pds::blob::dsl as BlobSchema;
+        use crate::schema::pds::record_blob::dsl as RecordBlobSchema;
+
+        let did = self.did.clone();
+        let (blobs, record_blobs) = self
+            .db
+            .run(move |conn| {
+                let blobs = BlobSchema::blob
+                    .filter(BlobSchema::did.eq(&did))
+                    .select(models::Blob::as_select())
+                    .order(BlobSchema::cid.asc())
+                    .get_results(conn)?;
+                let record_blobs = RecordBlobSchema::record_blob
+                    .filter(RecordBlobSchema::did.eq(&did))
+                    .select(models::RecordBlob::as_select())
+                    .get_results(conn)?;
+                Ok::<_, Error>((blobs, record_blobs))
+            })
+            .await?;
+        let mut records_by_blob: HashMap<String, Vec<String>> = HashMap::new();
+        for row in record_blobs {
+            records_by_blob
+                .entry(row.blob_cid)
+                .or_default()
+                .push(row.record_uri);
+        }
+        Ok(blobs
+            .into_iter()
+            .map(|blob| {
+                let records = records_by_blob.remove(&blob.cid).unwrap_or_default();
+                (blob, records)
+            })
+            .collect())
+    }
+
     pub async fn upload_blob_and_get_metadata(
         &self,
         user_suggested_mime: String,
         blob: Data<'_>,
+        content_length: Option<usize>,
+        upload_limit: usize,
     ) -> Result<BlobMetadata> {
-        let blob_stream = blob.open(100.mebibytes());
-        let bytes = blob_stream.into_bytes().await?;
-        let size = bytes.n.written;
-        let bytes = bytes.into_inner();
-        let (temp_key, sha256, img_info, sniffed_mime) = try_join!(
+        let blob_stream = blob.open((upload_limit + 1).bytes());
+        let (bytes, sha256) = read_blob(blob_stream, content_length, upload_limit).await?;
+        let size = bytes.len();
+        let (temp_key, img_info, sniffed_mime) = try_join!(
             self.blobstore.put_temp(bytes.clone()),
-            sha256_stream(bytes.clone()),
             image::maybe_get_info(bytes.clone()),
             image::mime_type_from_bytes(bytes.clone())
         )?;
@@ -403,6 +545,54 @@ impl BlobReader {
         Ok(())
     }
 
+    /// Makes the account's `record_blob` rows exactly `expected`, as (blob CID, record URI)
+    /// pairs, returning how many rows were added and removed.
+    pub async fn replace_record_blobs(
+        &self,
+        expected: HashSet<(String, String)>,
+    ) -> Result<(usize, usize)> {
+        use crate::schema::pds::record_blob::dsl as RecordBlobSchema;
+
+        let did = self.did.clone();
+        self.db
+            .run(move |conn| {
+                conn.transaction(|conn| {
+                    let current: HashSet<(String, String)> = RecordBlobSchema::record_blob
+                        .filter(RecordBlobSchema::did.eq(&did))
+                        .select((RecordBlobSchema::blobCid, RecordBlobSchema::recordUri))
+                        .load::<(String, String)>(conn)?
+                        .into_iter()
+                        .collect();
+                    let stale: Vec<&(String, String)> = current.difference(&expected).collect();
+                    for (cid, uri) in stale.iter() {
+                        delete(RecordBlobSchema::record_blob)
+                            .filter(RecordBlobSchema::did.eq(&did))
+                            .filter(RecordBlobSchema::blobCid.eq(cid))
+                            .filter(RecordBlobSchema::recordUri.eq(uri))
+                            .execute(conn)?;
+                    }
+                    let missing: Vec<models::RecordBlob> = expected
+                        .difference(&current)
+                        .map(|(cid, uri)| models::RecordBlob {
+                            blob_cid: cid.clone(),
+                            record_uri: uri.clone(),
+                            did: did.clone(),
+                        })
+                        .collect();
+                    // well under Postgres' bind parameter limit
+                    for chunk in missing.chunks(500) {
+                        insert_into(RecordBlobSchema::record_blob)
+                            .values(chunk)
+                            .on_conflict_do_nothing()
+                            .execute(conn)?;
+                    }
+                    Ok::<_, Error>((missing.len(), stale.len()))
+                })
+            })
+            .await
+            .map_err(Into::into)
+    }
+
     pub async fn blob_count(&self) -> Result<i64> {
         use crate::schema::pds::blob::dsl as BlobSchema;
 
@@ -499,12 +689,14 @@ impl BlobReader {
             cursor,
             limit,
         } = opts;
+        let did = self.did.clone();
 
         let res: Vec<String> = if let Some(since) = since {
             let mut builder = RecordBlobSchema::record_blob
                 .inner_join(
                     RecordSchema::record.on(RecordSchema::uri.eq(RecordBlobSchema::recordUri)),
                 )
+                .filter(RecordBlobSchema::did.eq(did))
                 .filter(RecordSchema::repoRev.gt(since))
                 .select(RecordBlobSchema::blobCid)
                 .distinct()
@@ -518,6 +710,7 @@ impl BlobReader {
             self.db.run(move |conn| builder.load(conn)).await?
         } else {
             let mut builder = RecordBlobSchema::record_blob
+                .filter(RecordBlobSchema::did.eq(did))
                 .select(RecordBlobSchema::blobCid)
                 .distinct()
                 .order(RecordBlobSchema::blobCid.asc())
@@ -621,30 +814,108 @@ pub async fn accepted_mime(mime: String, accepted: Vec<String>) -> bool {
 pub async fn verify_blob(blob: &PreparedBlobRef, found: &models::Blob) -> Result<()> {
     if let Some(max_size) = blob.constraints.max_size {
         if found.size as usize > max_size {
-            bail!(
-                "BlobTooLarge: This file is too large. It is {:?} but the maximum size is {:?}",
-                found.size,
-                max_size
-            )
+            bail!(ApiError::BlobTooLarge(format!(
+                "This file is too large. It is {:?} but the maximum size is {:?}",
+                found.size, max_size
+            )))
         }
     }
     if blob.mime_type != found.mime_type {
-        bail!("InvalidMimeType: Referenced MimeTy[e does not match stored blob. Expected: {:?}, Got: {:?}",found.mime_type, blob.mime_type)
+        bail!(ApiError::InvalidMimeType(format!(
+            "Referenced Mimetype does not match stored blob. Expected: {:?}, Got: {:?}",
+            found.mime_type, blob.mime_type
+        )))
     }
     if let Some(ref accept) = blob.constraints.accept {
         if !accepted_mime(blob.mime_type.clone(), accept.clone()).await {
-            bail!(
+            bail!(ApiError::InvalidMimeType(format!(
                 "Wrong type of file. It is {:?} but it must match {:?}.",
-                blob.mime_type,
-                accept
-            )
+                blob.mime_type, accept
+            )))
         }
     }
     Ok(())
 }
 
-pub async fn sha256_stream(to_hash: Vec<u8>) -> Result<Vec<u8>> {
-    let digest = Sha256::digest(&*to_hash);
-    let hash: &[u8] = digest.as_ref();
-    Ok(hash.to_vec())
+/// Reads an upload to the end, hashing as it arrives, and returns the bytes with their sha256.
+/// The reader should allow one byte past `upload_limit` so an oversized body is told apart from
+/// one that fits exactly; a declared `content_length` must match what was actually received.
+pub async fn read_blob<R: AsyncRead + Unpin>(
+    mut blob_stream: R,
+    content_length: Option<usize>,
+    upload_limit: usize,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let too_large = || {
+        ApiError::BlobTooLarge(format!(
+            "This file is too large. The maximum size is {upload_limit} bytes"
+        ))
+    };
+    if content_length.is_some_and(|content_length| content_length > upload_limit) {
+        bail!(too_large());
+    }
+
+    let mut hasher = Sha256::new();
+    let mut bytes = Vec::with_capacity(content_length.unwrap_or_default());
+    let mut chunk = vec![0u8; BLOB_READ_CHUNK_SIZE];
+    loop {
+        let read = blob_stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        if bytes.len() + read > upload_limit {
+            bail!(too_large());
+        }
+        hasher.update(&chunk[..read]);
+        bytes.extend_from_slice(&chunk[..read]);
+    }
+    if let Some(content_length) = content_length {
+        if bytes.len() != content_length {
+            bail!(ApiError::InvalidRequest(format!(
+                "Received {} bytes but Content-Length was {content_length}",
+                bytes.len()
+            )));
+        }
+    }
+    Ok((bytes, hasher.finalize().to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UPLOAD_LIMIT: usize = 8;
+
+    async fn read(body: &[u8], content_length: Option<usize>) -> Result<(Vec<u8>, Vec<u8>)> {
+        read_blob(
+            &body[..body.len().min(UPLOAD_LIMIT + 1)],
+            content_length,
+            UPLOAD_LIMIT,
+        )
+        .await
+    }
+
+    fn api_error(result: Result<(Vec<u8>, Vec<u8>)>) -> ApiError {
+        result.unwrap_err().downcast::<ApiError>().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_read_blob() {
+        let (bytes, sha256) = read(b"12345678", Some(8)).await.unwrap();
+        assert_eq!(bytes, b"12345678");
+        assert_eq!(sha256, Sha256::digest(b"12345678").to_vec());
+        assert!(read(b"1234", None).await.is_ok());
+
+        assert!(matches!(
+            api_error(read(b"123456789", None).await),
+            ApiError::BlobTooLarge(_)
+        ));
+        assert!(matches!(
+            api_error(read(b"1234", Some(9)).await),
+            ApiError::BlobTooLarge(_)
+        ));
+        assert!(matches!(
+            api_error(read(b"1234", Some(6)).await),
+            ApiError::InvalidRequest(_)
+        ));
+    }
 }
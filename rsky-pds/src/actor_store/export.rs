@@ -0,0 +1,115 @@
+//! Account takeout archive. A plain tar holding:
+//!
+//! - `repo.car`: full repo export, same as `com.atproto.sync.getRepo`
+//! - `blobs.json`: manifest of every stored blob and the records referencing it
+//! - `preferences.json`: private preferences, same as `app.bsky.actor.getPreferences`
+//!
+//! Blob bytes aren't included; they can be fetched by CID with `com.atproto.sync.getBlob`.
+
+use crate::actor_store::ActorStore;
+use crate::auth_verifier::AuthScope;
+use anyhow::Result;
+use rsky_lexicon::app::bsky::actor::GetPreferencesOutput;
+use rsky_repo::util::stream_to_buffer;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlobManifestEntry {
+    pub cid: String,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    pub size: i32,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    pub records: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlobManifest {
+    pub did: String,
+    pub blobs: Vec<BlobManifestEntry>,
+}
+
+pub async fn build_export_archive(actor_store: &ActorStore) -> Result<Vec<u8>> {
+    let car = {
+        let storage_guard = actor_store.storage.read().await;
+        storage_guard.get_car_stream(None).await?
+    };
+    let car = stream_to_buffer(car).await?;
+    let blobs = actor_store
+        .blob
+        .list_blobs_with_records()
+        .await?
+        .into_iter()
+        .map(|(blob, records)| BlobManifestEntry {
+            cid: blob.cid,
+            mime_type: blob.mime_type,
+            size: blob.size,
+            created_at: blob.created_at,
+            records,
+        })
+        .collect();
+    let manifest = BlobManifest {
+        did: actor_store.did.clone(),
+        blobs,
+    };
+    let preferences = GetPreferencesOutput {
+        preferences: actor_store
+            .pref
+            .get_preferences(None, AuthScope::Access)
+            .await?,
+    };
+    pack_archive(vec![
+        ("repo.car", car),
+        ("blobs.json", serde_json::to_vec_pretty(&manifest)?),
+        ("preferences.json", serde_json::to_vec_pretty(&preferences)?),
+    ])
+}
+
+pub fn pack_archive(entries: Vec<(&str, Vec<u8>)>) -> Result<Vec<u8>> {
+    let mtime = chrono::Utc::now().timestamp() as u64;
+    let mut archive = tar::Builder::new(Vec::new());
+    for (path, bytes) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        header.set_cksum();
+        archive.append_data(&mut header, path, bytes.as_slice())?;
+    }
+    Ok(archive.into_inner()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_pack_archive() {
+        let archive = pack_archive(vec![
+            ("repo.car", vec![1, 2, 3]),
+            ("blobs.json", b"{}".to_vec()),
+        ])
+        .unwrap();
+
+        let mut archive = tar::Archive::new(archive.as_slice());
+        let entries = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let mut entry = entry.unwrap();
+                let path = entry.path().unwrap().to_string_lossy().to_string();
+                let mut bytes = Vec::new();
+                entry.read_to_end(&mut bytes).unwrap();
+                (path, bytes)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            vec![
+                ("repo.car".to_string(), vec![1, 2, 3]),
+                ("blobs.json".to_string(), b"{}".to_vec()),
+            ]
+        );
+    }
+}
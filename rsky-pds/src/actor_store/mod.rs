@@ -6,31 +6,40 @@ use crate::actor_store::blob::BlobReader;
 use crate::actor_store::preference::PreferenceReader;
 use crate::actor_store::record::RecordReader;
 use crate::actor_store::repo::sql_repo::SqlRepoReader;
-use crate::actor_store::repo::types::SyncEvtData;
+use crate::actor_store::repo::types::{CommitOpCounts, SyncEvtData};
+use crate::config::{keys, ActorStoreConfig};
 use crate::db::DbConn;
-use anyhow::Result;
+use crate::repo::prepare::find_blob_refs;
+use crate::sequencer::events::is_too_big;
+use anyhow::{bail, Result};
+use aws_config::SdkConfig;
 use diesel::*;
-use futures::stream::{self, StreamExt};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use lazy_static::lazy_static;
 use lexicon_cid::Cid;
 use rsky_common;
+use rsky_common::tid::{Ticker, TID};
+use rsky_repo::mst::Leaf;
+use rsky_repo::parse::get_and_parse_record;
 use rsky_repo::repo::Repo;
 use rsky_repo::storage::readable_blockstore::ReadableBlockstore;
 use rsky_repo::storage::types::RepoStorage;
 use rsky_repo::types::{
-    write_to_op, CommitAction, CommitData, CommitDataWithOps, CommitOp, PreparedCreateOrUpdate,
-    PreparedWrite, RecordCreateOrUpdateOp, RecordWriteEnum, RecordWriteOp, WriteOpAction,
+    write_to_op, CommitAction, CommitData, CommitDataWithOps, CommitOp, Lex,
+    PreparedCreateOrUpdate, PreparedWrite, RecordCreateOrUpdateOp, RecordWriteEnum, RecordWriteOp,
+    WriteOpAction,
 };
-use rsky_repo::util::format_data_key;
+use rsky_repo::util::{format_data_key, parse_data_key};
 use rsky_syntax::aturi::AtUri;
-use secp256k1::{Keypair, Secp256k1, SecretKey};
-use std::env;
+use secp256k1::Keypair;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::str::FromStr;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedMutexGuard, RwLock};
 
 #[derive(Debug)]
-enum FormatCommitError {
+pub enum FormatCommitError {
     BadRecordSwap(String),
     RecordSwapMismatch(String),
     BadCommitSwap(String),
@@ -64,7 +73,11 @@ pub struct ActorStore {
 impl ActorStore {
     /// Concrete reader of an individual repo (hence S3BlobStore which takes `did` param)
     pub fn new(did: String, blobstore: S3BlobStore, db: DbConn) -> Self {
-        let db = Arc::new(db);
+        Self::with_shared_db(did, blobstore, Arc::new(db))
+    }
+
+    /// Like `new`, for callers working through several actors on one connection.
+    pub fn with_shared_db(did: String, blobstore: S3BlobStore, db: Arc<DbConn>) -> Self {
         ActorStore {
             storage: Arc::new(RwLock::new(SqlRepoReader::new(
                 did.clone(),
@@ -118,7 +131,10 @@ impl ActorStore {
             .into_iter()
             .map(PreparedWrite::Create)
             .collect::<Vec<PreparedWrite>>();
-        self.blob.process_write_blobs(writes).await?;
+        let indexing = indexing_lock(&self.did).lock_owned().await;
+        let res = self.blob.process_write_blobs(writes).await;
+        release_indexing_lock(&self.did, indexing);
+        res?;
         Ok(commit)
     }
 
@@ -140,15 +156,16 @@ impl ActorStore {
                 })
             })
             .collect::<Result<Vec<RecordCreateOrUpdateOp>>>()?;
-        let commit = Repo::format_init_commit(
-            self.storage.clone(),
-            self.did.clone(),
-            keypair,
-            Some(write_ops),
-        )
-        .await?;
+        let commit =
+            Repo::create_from_records(self.storage.clone(), self.did.clone(), keypair, write_ops)
+                .await?
+                .commit;
         let storage_guard = self.storage.read().await;
-        storage_guard.apply_commit(commit.clone(), None).await?;
+        let counts = CommitOpCounts {
+            creates: writes.len() as i32,
+            ..Default::default()
+        };
+        storage_guard.record_commit(&commit, counts).await?;
         let write_commit_ops = writes.iter().try_fold(
             Vec::with_capacity(writes.len()),
             |mut acc, w| -> Result<Vec<CommitOp>> {
@@ -166,7 +183,15 @@ impl ActorStore {
             .into_iter()
             .map(PreparedWrite::Create)
             .collect::<Vec<PreparedWrite>>();
-        self.blob.process_write_blobs(writes).await?;
+        // keeps a concurrent repairRecordBlobs from dropping the new record_blob rows
+        let indexing = indexing_lock(&self.did).lock_owned().await;
+        let res = async {
+            self.index_writes(writes.clone(), &commit.rev).await?;
+            self.blob.process_write_blobs(writes).await
+        }
+        .await;
+        release_indexing_lock(&self.did, indexing);
+        res?;
         Ok(CommitDataWithOps {
             commit_data: commit,
             ops: write_commit_ops,
@@ -178,6 +203,17 @@ impl ActorStore {
         &mut self,
         commit: CommitData,
         writes: Vec<PreparedWrite>,
+    ) -> Result<()> {
+        let indexing = indexing_lock(&self.did).lock_owned().await;
+        let res = self.process_import_repo_locked(commit, writes).await;
+        release_indexing_lock(&self.did, indexing);
+        res
+    }
+
+    async fn process_import_repo_locked(
+        &mut self,
+        commit: CommitData,
+        writes: Vec<PreparedWrite>,
     ) -> Result<()> {
         {
             let immutable_borrow = &self;
@@ -189,6 +225,9 @@ impl ActorStore {
         // persist the commit to repo storage
         let storage_guard = self.storage.read().await;
         storage_guard.apply_commit(commit.clone(), None).await?;
+        storage_guard
+            .record_commit(&commit, CommitOpCounts::from_writes(&writes))
+            .await?;
         // process blobs
         self.blob.process_write_blobs(writes).await?;
         Ok(())
@@ -205,24 +244,257 @@ impl ActorStore {
         // for which I'm sure we could safeguard on
         // but may not be necessary.
         // https://github.com/bluesky-social/atproto/pull/3585/files#diff-7627844a4a6b50190014e947d1331a96df3c64d4c5273fa0ce544f85c3c1265f
+        let quota = rate_limit::check_writes(&self.did, &writes)?;
+        // waits out deferred indexing of this repo's last write, so records index in order
+        let indexing = indexing_lock(&self.did).lock_owned().await;
         let commit = self.format_commit(writes.clone(), swap_commit_cid).await?;
-        {
-            let immutable_borrow = &self;
+        let deleted = match actor_store_config().deleted_record_retention {
+            Some(retain_for) => {
+                // before indexing drops the rows and the commit drops the blocks
+                let uris = writes
+                    .iter()
+                    .filter_map(|write| match write {
+                        PreparedWrite::Delete(write) => Some(write.uri.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                self.record.snapshot_deleted(uris, retain_for).await?
+            }
+            None => Vec::new(),
+        };
+        let defer_indexing = is_too_big(&commit)?;
+        if defer_indexing {
+            // marked before the commit lands, so a restart picks up indexing that didn't finish
+            mark_pending_index(&self.record.db, &self.did, &commit.commit_data.rev).await?;
+        } else {
             // & send to indexing
-            immutable_borrow
-                .index_writes(writes.clone(), &commit.commit_data.rev)
-                .await?;
+            index_writes(&self.record, writes.clone(), &commit.commit_data.rev).await?;
         }
         // persist the commit to repo storage
         let storage_guard = self.storage.read().await;
         storage_guard
             .apply_commit(commit.commit_data.clone(), None)
             .await?;
+        storage_guard
+            .record_commit(&commit.commit_data, CommitOpCounts::from_writes(&writes))
+            .await?;
+        quota.keep();
+        // only once the deletes have landed
+        self.record.retain_deleted(deleted).await?;
         // process blobs
-        self.blob.process_write_blobs(writes).await?;
+        self.blob.process_write_blobs(writes.clone()).await?;
+        if defer_indexing {
+            // tooBig commits can take a while to index, so the response doesn't wait on it
+            let record = RecordReader::new(self.did.clone(), self.record.db.clone());
+            let rev = commit.commit_data.rev.clone();
+            let did = self.did.clone();
+            tokio::spawn(async move {
+                match index_writes(&record, writes, &rev).await {
+                    Ok(()) => {
+                        if let Err(error) = clear_pending_index(&record.db, &did, Some(&rev)).await
+                        {
+                            tracing::error!(
+                                "@LOG: ERROR: failed to clear pending index of {did}: {error}"
+                            );
+                        }
+                    }
+                    // the marker stays, so the repo is reindexed on the next start
+                    Err(error) => tracing::error!(
+                        "@LOG: ERROR: deferred indexing of {did} at {rev} failed: {error}"
+                    ),
+                }
+                release_indexing_lock(&did, indexing);
+            });
+        } else {
+            release_indexing_lock(&self.did, indexing);
+        }
+        Ok(commit)
+    }
+
+    /// Re-signs the current repo head with `keypair` without changing its contents, so the
+    /// repo verifies against a rotated signing key.
+    pub async fn resign_repo(&mut self, keypair: Keypair) -> Result<CommitData> {
+        let current_root = self.storage.read().await.get_root_detailed().await?;
+        let repo = Repo::load(self.storage.clone(), Some(current_root.cid)).await?;
+        let rev = Ticker::new().next(Some(TID(current_root.rev.clone())));
+        let mut commit = repo.format_resign_commit(rev.0, keypair)?;
+        commit.since = Some(current_root.rev);
+        commit.prev = Some(current_root.cid);
+        let storage_guard = self.storage.read().await;
+        storage_guard.apply_commit(commit.clone(), None).await?;
+        storage_guard
+            .record_commit(&commit, CommitOpCounts::default())
+            .await?;
+        Ok(commit)
+    }
+
+    /// Rebuilds the repo's MST from its indexed records and their blocks, rather than from the
+    /// tree being repaired, commits it as a genesis-style commit with a new rev and reindexes
+    /// from the result. Meant for repos whose tree was damaged by past bugs; callers should
+    /// follow up with a `#sync` event.
+    pub async fn squash_repo(&mut self, keypair: Keypair) -> Result<CommitData> {
+        // keeps deferred indexing of an earlier write from landing on top of the reindex
+        let indexing = indexing_lock(&self.did).lock_owned().await;
+        let res = self.squash_repo_locked(keypair).await;
+        release_indexing_lock(&self.did, indexing);
+        res
+    }
+
+    async fn squash_repo_locked(&mut self, keypair: Keypair) -> Result<CommitData> {
+        let current_root = self.storage.read().await.get_root_detailed().await?;
+        let repo = Repo::load(self.storage.clone(), Some(current_root.cid)).await?;
+        let entries = self.indexed_entries().await?;
+        let rev = Ticker::new().next(Some(TID(current_root.rev)));
+        let commit = repo.format_squash_commit(rev.0, keypair, entries).await?;
+        {
+            let storage_guard = self.storage.read().await;
+            storage_guard.apply_commit(commit.clone(), None).await?;
+            storage_guard
+                .record_commit(&commit, CommitOpCounts::default())
+                .await?;
+        }
+
+        self.reindex_records(&commit.rev).await?;
         Ok(commit)
     }
 
+    /// Data keys and record CIDs of every indexed record, failing if any record's block is
+    /// missing since a tree can't be built over it.
+    async fn indexed_entries(&self) -> Result<Vec<(String, Cid)>> {
+        use crate::schema::pds::record::dsl as RecordSchema;
+
+        let did = self.did.clone();
+        let rows: Vec<(String, String, String)> = self
+            .record
+            .db
+            .run(move |conn| {
+                RecordSchema::record
+                    .filter(RecordSchema::did.eq(did))
+                    .select((
+                        RecordSchema::collection,
+                        RecordSchema::rkey,
+                        RecordSchema::cid,
+                    ))
+                    .get_results(conn)
+            })
+            .await?;
+        let mut entries = Vec::with_capacity(rows.len());
+        for chunk in rows.chunks(500) {
+            let cids = chunk
+                .iter()
+                .map(|(_, _, cid)| Cid::from_str(cid))
+                .collect::<Result<Vec<_>, _>>()?;
+            let found = self.storage.read().await.get_blocks(cids.clone()).await?;
+            if !found.missing.is_empty() {
+                bail!(
+                    "{} indexed records of {} have no block",
+                    found.missing.len(),
+                    self.did
+                );
+            }
+            for ((collection, rkey, _), cid) in chunk.iter().zip(cids) {
+                entries.push((format_data_key(collection.clone(), rkey.clone()), cid));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Reindexes records that a deferred indexing pass left behind, then clears the marker.
+    pub async fn reindex_pending(&mut self) -> Result<()> {
+        let indexing = indexing_lock(&self.did).lock_owned().await;
+        let res = self.reindex_pending_locked().await;
+        release_indexing_lock(&self.did, indexing);
+        res
+    }
+
+    async fn reindex_pending_locked(&mut self) -> Result<()> {
+        let current_root = self.storage.read().await.get_root_detailed().await?;
+        self.reindex_records(&current_root.rev).await?;
+        clear_pending_index(&self.record.db, &self.did, None).await
+    }
+
+    /// Rebuilds the record index from the records in the repo's current tree, indexing them at
+    /// `rev` and dropping rows for records the tree doesn't have.
+    async fn reindex_records(&mut self, rev: &str) -> Result<()> {
+        let current_root = self.storage.read().await.get_root_detailed().await?;
+        let repo = Repo::load(self.storage.clone(), Some(current_root.cid)).await?;
+        let leaves: Vec<Leaf> = repo.data.leaves().try_collect().await?;
+        let now = rsky_common::now();
+        let mut uris = HashSet::with_capacity(leaves.len());
+        for chunk in leaves.chunks(500) {
+            let cids = chunk.iter().map(|leaf| leaf.value).collect();
+            let found = self.storage.read().await.get_blocks(cids).await?;
+            for leaf in chunk {
+                let path = parse_data_key(&leaf.key)?;
+                let uri = AtUri::make(self.did.clone(), Some(path.collection), Some(path.rkey))?;
+                let parsed = get_and_parse_record(&found.blocks, leaf.value)?;
+                uris.insert(uri.to_string());
+                // as an update, so backlinks are rebuilt rather than added on top
+                self.record
+                    .index_record(
+                        uri,
+                        leaf.value,
+                        Some(parsed.record),
+                        Some(WriteOpAction::Update),
+                        rev.to_owned(),
+                        Some(now.clone()),
+                    )
+                    .await?;
+            }
+        }
+
+        // drop index rows for records the tree doesn't have
+        use crate::schema::pds::record::dsl as RecordSchema;
+        let did = self.did.clone();
+        let indexed: Vec<String> = self
+            .record
+            .db
+            .run(move |conn| {
+                RecordSchema::record
+                    .filter(RecordSchema::did.eq(did))
+                    .select(RecordSchema::uri)
+                    .get_results(conn)
+            })
+            .await?;
+        for uri in indexed {
+            if !uris.contains(&uri) {
+                let uri: AtUri = uri.try_into()?;
+                self.record.delete_record(&uri).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Recomputes the account's record to blob associations from the records in its repo,
+    /// fixing rows that drifted from what the records reference. Returns how many rows were
+    /// added and removed.
+    pub async fn repair_record_blobs(&mut self) -> Result<(usize, usize)> {
+        let indexing = indexing_lock(&self.did).lock_owned().await;
+        let res = self.repair_record_blobs_locked().await;
+        release_indexing_lock(&self.did, indexing);
+        res
+    }
+
+    async fn repair_record_blobs_locked(&mut self) -> Result<(usize, usize)> {
+        let current_root = self.storage.read().await.get_root_detailed().await?;
+        let repo = Repo::load(self.storage.clone(), Some(current_root.cid)).await?;
+        let leaves: Vec<Leaf> = repo.data.leaves().try_collect().await?;
+        let mut expected = HashSet::new();
+        for chunk in leaves.chunks(500) {
+            let cids = chunk.iter().map(|leaf| leaf.value).collect();
+            let found = self.storage.read().await.get_blocks(cids).await?;
+            for leaf in chunk {
+                let path = parse_data_key(&leaf.key)?;
+                let uri = AtUri::make(self.did.clone(), Some(path.collection), Some(path.rkey))?;
+                let parsed = get_and_parse_record(&found.blocks, leaf.value)?;
+                for found_ref in find_blob_refs(Lex::Map(parsed.record), None, None) {
+                    expected.insert((found_ref.r#ref.get_cid()?.to_string(), uri.to_string()));
+                }
+            }
+        }
+        self.blob.replace_record_blobs(expected).await
+    }
+
     pub async fn get_sync_event_data(&mut self) -> Result<SyncEvtData> {
         let storage_guard = self.storage.read().await;
         let current_root = storage_guard.get_root_detailed().await?;
@@ -338,12 +610,7 @@ impl ActorStore {
                 .into_iter()
                 .map(write_to_op)
                 .collect::<Result<Vec<RecordWriteOp>>>()?;
-            // @TODO: Use repo signing key global config
-            let secp = Secp256k1::new();
-            let repo_private_key = env::var("PDS_REPO_SIGNING_KEY_K256_PRIVATE_KEY_HEX").unwrap();
-            let repo_secret_key =
-                SecretKey::from_slice(&hex::decode(repo_private_key.as_bytes()).unwrap()).unwrap();
-            let repo_signing_key = Keypair::from_secret_key(&secp, &repo_secret_key);
+            let repo_signing_key = keys::repo_signing_keypair()?;
 
             let mut commit = repo
                 .format_commit(RecordWriteEnum::List(write_ops), repo_signing_key)
@@ -379,48 +646,7 @@ impl ActorStore {
     }
 
     pub async fn index_writes(&self, writes: Vec<PreparedWrite>, rev: &str) -> Result<()> {
-        let now: &str = &rsky_common::now();
-
-        let _ = stream::iter(writes)
-            .then(|write| async move {
-                Ok::<(), anyhow::Error>(match write {
-                    PreparedWrite::Create(write) => {
-                        let write_at_uri: AtUri = write.uri.try_into()?;
-                        self.record
-                            .index_record(
-                                write_at_uri.clone(),
-                                write.cid,
-                                Some(write.record),
-                                Some(write.action),
-                                rev.to_owned(),
-                                Some(now.to_string()),
-                            )
-                            .await?
-                    }
-                    PreparedWrite::Update(write) => {
-                        let write_at_uri: AtUri = write.uri.try_into()?;
-                        self.record
-                            .index_record(
-                                write_at_uri.clone(),
-                                write.cid,
-                                Some(write.record),
-                                Some(write.action),
-                                rev.to_owned(),
-                                Some(now.to_string()),
-                            )
-                            .await?
-                    }
-                    PreparedWrite::Delete(write) => {
-                        let write_at_uri: AtUri = write.uri.try_into()?;
-                        self.record.delete_record(&write_at_uri).await?
-                    }
-                })
-            })
-            .collect::<Vec<_>>()
-            .await
-            .into_iter()
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok(())
+        index_writes(&self.record, writes, rev).await
     }
 
     pub async fn destroy(&mut self) -> Result<()> {
@@ -483,6 +709,163 @@ impl ActorStore {
 
 pub mod aws;
 pub mod blob;
+pub mod export;
 pub mod preference;
+pub mod rate_limit;
 pub mod record;
 pub mod repo;
+
+lazy_static! {
+    static ref CONFIG: std::sync::RwLock<ActorStoreConfig> =
+        std::sync::RwLock::new(ActorStoreConfig::default());
+}
+
+pub fn set_actor_store_config(cfg: ActorStoreConfig) {
+    *CONFIG.write().unwrap() = cfg;
+}
+
+/// Repo storage settings from the `ServerConfig`, shared by every actor store.
+pub fn actor_store_config() -> ActorStoreConfig {
+    CONFIG.read().unwrap().clone()
+}
+
+lazy_static! {
+    /// Held while a repo's writes are being indexed, including past the response for deferred
+    /// indexing.
+    static ref INDEXING_LOCKS: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>> =
+        Mutex::new(HashMap::new());
+}
+
+fn indexing_lock(did: &str) -> Arc<tokio::sync::Mutex<()>> {
+    INDEXING_LOCKS
+        .lock()
+        .unwrap()
+        .entry(did.to_owned())
+        .or_default()
+        .clone()
+}
+
+fn release_indexing_lock(did: &str, guard: OwnedMutexGuard<()>) {
+    let mut locks = INDEXING_LOCKS.lock().unwrap();
+    drop(guard);
+    // only clones under `INDEXING_LOCKS`, so nobody else is waiting if this is the last one
+    if let Some(lock) = locks.get(did) {
+        if Arc::strong_count(lock) == 1 {
+            locks.remove(did);
+        }
+    }
+}
+
+async fn mark_pending_index(db: &DbConn, did: &str, rev: &str) -> Result<()> {
+    use crate::schema::pds::pending_index::dsl as PendingIndexSchema;
+
+    let (did, rev) = (did.to_owned(), rev.to_owned());
+    let now = rsky_common::now();
+    db.run(move |conn| {
+        insert_into(PendingIndexSchema::pending_index)
+            .values((
+                PendingIndexSchema::did.eq(&did),
+                PendingIndexSchema::rev.eq(&rev),
+                PendingIndexSchema::createdAt.eq(&now),
+            ))
+            // an earlier marker means an earlier pass failed, which only a reindex clears
+            .on_conflict(PendingIndexSchema::did)
+            .do_nothing()
+            .execute(conn)
+    })
+    .await?;
+    Ok(())
+}
+
+/// Clears the repo's marker, only if it was set at `rev` when one is given.
+async fn clear_pending_index(db: &DbConn, did: &str, rev: Option<&str>) -> Result<()> {
+    use crate::schema::pds::pending_index::dsl as PendingIndexSchema;
+
+    let (did, rev) = (did.to_owned(), rev.map(str::to_owned));
+    db.run(move |conn| {
+        let mut query = delete(PendingIndexSchema::pending_index)
+            .filter(PendingIndexSchema::did.eq(did))
+            .into_boxed();
+        if let Some(rev) = rev {
+            query = query.filter(PendingIndexSchema::rev.eq(rev));
+        }
+        query.execute(conn)
+    })
+    .await?;
+    Ok(())
+}
+
+/// Reindexes every repo whose deferred indexing didn't finish, e.g. because the process
+/// stopped first or the indexing failed. Run once at startup.
+pub async fn retry_pending_indexing(db: DbConn, s3_config: SdkConfig) {
+    use crate::schema::pds::pending_index::dsl as PendingIndexSchema;
+
+    let db = Arc::new(db);
+    let pending: Vec<String> = match db
+        .run(|conn| {
+            PendingIndexSchema::pending_index
+                .select(PendingIndexSchema::did)
+                .load(conn)
+        })
+        .await
+    {
+        Ok(pending) => pending,
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: failed to list pending indexing: {error}");
+            return;
+        }
+    };
+    for did in pending {
+        let blobstore = S3BlobStore::new(did.clone(), &s3_config);
+        let mut actor_store = ActorStore::with_shared_db(did.clone(), blobstore, db.clone());
+        match actor_store.reindex_pending().await {
+            Ok(()) => tracing::info!("@LOG: reindexed {did} after deferred indexing"),
+            Err(error) => tracing::error!("@LOG: ERROR: failed to reindex {did}: {error}"),
+        }
+    }
+}
+
+async fn index_writes(record: &RecordReader, writes: Vec<PreparedWrite>, rev: &str) -> Result<()> {
+    let now: &str = &rsky_common::now();
+
+    let _ = stream::iter(writes)
+        .then(|write| async move {
+            Ok::<(), anyhow::Error>(match write {
+                PreparedWrite::Create(write) => {
+                    let write_at_uri: AtUri = write.uri.try_into()?;
+                    record
+                        .index_record(
+                            write_at_uri.clone(),
+                            write.cid,
+                            Some(write.record),
+                            Some(write.action),
+                            rev.to_owned(),
+                            Some(now.to_string()),
+                        )
+                        .await?
+                }
+                PreparedWrite::Update(write) => {
+                    let write_at_uri: AtUri = write.uri.try_into()?;
+                    record
+                        .index_record(
+                            write_at_uri.clone(),
+                            write.cid,
+                            Some(write.record),
+                            Some(write.action),
+                            rev.to_owned(),
+                            Some(now.to_string()),
+                        )
+                        .await?
+                }
+                PreparedWrite::Delete(write) => {
+                    let write_at_uri: AtUri = write.uri.try_into()?;
+                    record.delete_record(&write_at_uri).await?
+                }
+            })
+        })
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(())
+}
@@ -0,0 +1,212 @@
+//! Per-collection write rate limits from `PDS_WRITE_RATE_LIMITS`, checked in
+//! [`ActorStore::process_writes`](super::ActorStore::process_writes) so every write path is
+//! covered. Only record creates count, and only once their commit lands. Windows are fixed and
+//! counted in memory, so each replica enforces its own share and restarts forgive.
+
+use crate::apis::ApiError;
+use crate::config::CollectionRateLimit;
+use anyhow::Result;
+use lazy_static::lazy_static;
+use rsky_repo::types::PreparedWrite;
+use rsky_syntax::aturi::AtUri;
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::SystemTime;
+
+/// Past this many tracked windows, expired ones are dropped on the next write.
+const MAX_TRACKED_WINDOWS: usize = 10_000;
+
+lazy_static! {
+    static ref LIMITER: RwLock<WriteRateLimiter> = RwLock::new(WriteRateLimiter::default());
+}
+
+pub fn set_write_rate_limits(limits: Vec<CollectionRateLimit>) {
+    *LIMITER.write().unwrap() = WriteRateLimiter::new(limits);
+}
+
+/// Counts `writes` against `did`'s limits, failing with `RateLimitExceeded` and counting
+/// nothing if any collection would go over. The count is given back when the returned
+/// [`Reservation`] is dropped without [`Reservation::keep`], so failed writes don't use quota.
+pub fn check_writes(did: &str, writes: &[PreparedWrite]) -> Result<Reservation> {
+    let limiter = LIMITER.read().unwrap();
+    if limiter.limits.is_empty() {
+        return Ok(Reservation::default());
+    }
+    let mut created: HashMap<String, u32> = HashMap::new();
+    for write in writes {
+        if let PreparedWrite::Create(write) = write {
+            let uri: AtUri = write.uri.clone().try_into()?;
+            *created.entry(uri.get_collection()).or_default() += 1;
+        }
+    }
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("timestamp in seconds since UNIX epoch")
+        .as_secs();
+    let counted = limiter.check(did, &created, now)?;
+    Ok(Reservation {
+        did: did.to_string(),
+        counted,
+    })
+}
+
+/// Quota taken by [`check_writes`] for writes that haven't landed yet.
+#[derive(Debug, Default)]
+#[must_use]
+pub struct Reservation {
+    did: String,
+    counted: Vec<Counted>,
+}
+
+impl Reservation {
+    /// The writes landed, so they keep their quota.
+    pub fn keep(mut self) {
+        self.counted.clear();
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if !self.counted.is_empty() {
+            LIMITER.read().unwrap().refund(&self.did, &self.counted);
+        }
+    }
+}
+
+/// Collection, start of the window the writes were counted in, and how many.
+type Counted = (String, u64, u32);
+
+#[derive(Debug, Default)]
+pub struct WriteRateLimiter {
+    limits: HashMap<String, CollectionRateLimit>,
+    /// Start of the current window and writes in it, by (did, collection).
+    windows: Mutex<HashMap<(String, String), (u64, u32)>>,
+}
+
+impl WriteRateLimiter {
+    pub fn new(limits: Vec<CollectionRateLimit>) -> Self {
+        WriteRateLimiter {
+            limits: limits
+                .into_iter()
+                .map(|limit| (limit.collection.clone(), limit))
+                .collect(),
+            windows: Mutex::default(),
+        }
+    }
+
+    /// `created` is new records by collection; `now` is in Unix seconds.
+    pub fn check(
+        &self,
+        did: &str,
+        created: &HashMap<String, u32>,
+        now: u64,
+    ) -> Result<Vec<Counted>, ApiError> {
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() > MAX_TRACKED_WINDOWS {
+            windows.retain(|(_, collection), (start, _)| {
+                self.limits
+                    .get(collection)
+                    .is_some_and(|limit| now < *start + limit.window)
+            });
+        }
+        let mut counted = Vec::new();
+        for (collection, count) in created {
+            let Some(limit) = self.limits.get(collection) else {
+                continue;
+            };
+            let key = (did.to_string(), collection.clone());
+            let (start, used) = match windows.get(&key) {
+                Some((start, used)) if now < start + limit.window => (*start, *used),
+                _ => (now, 0),
+            };
+            if used + count > limit.writes {
+                return Err(ApiError::RateLimitExceeded(format!(
+                    "at most {} {collection} records may be created every {}s; try again in {}s",
+                    limit.writes,
+                    limit.window,
+                    start + limit.window - now
+                )));
+            }
+            counted.push((key, (start, used + count)));
+        }
+        let counted = counted
+            .into_iter()
+            .map(|((did, collection), (start, used))| {
+                let count = created[&collection];
+                windows.insert((did, collection.clone()), (start, used));
+                (collection, start, count)
+            })
+            .collect();
+        Ok(counted)
+    }
+
+    /// Gives back writes counted by `check`, unless their window has since rolled over.
+    pub fn refund(&self, did: &str, counted: &[Counted]) {
+        let mut windows = self.windows.lock().unwrap();
+        for (collection, start, count) in counted {
+            let key = (did.to_string(), collection.clone());
+            if let Some((current, used)) = windows.get_mut(&key) {
+                if current == start {
+                    *used = used.saturating_sub(*count);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn created(collection: &str, count: u32) -> HashMap<String, u32> {
+        HashMap::from([(collection.to_string(), count)])
+    }
+
+    #[test]
+    fn test_check() {
+        let limiter = WriteRateLimiter::new(vec![CollectionRateLimit {
+            collection: "app.bsky.feed.post".to_string(),
+            writes: 3,
+            window: 60,
+        }]);
+        let did = "did:plc:abc";
+        let posts = created("app.bsky.feed.post", 2);
+        assert!(limiter.check(did, &posts, 1000).is_ok());
+        // a batch that would go over counts nothing
+        assert!(limiter.check(did, &posts, 1010).is_err());
+        assert!(limiter
+            .check(did, &created("app.bsky.feed.post", 1), 1020)
+            .is_ok());
+        assert!(limiter
+            .check(did, &created("app.bsky.feed.post", 1), 1030)
+            .is_err());
+        // other accounts and collections are unaffected, and the window resets
+        assert!(limiter.check("did:plc:xyz", &posts, 1030).is_ok());
+        assert!(limiter
+            .check(did, &created("app.bsky.feed.like", 10), 1030)
+            .is_ok());
+        assert!(limiter.check(did, &posts, 1060).is_ok());
+    }
+
+    #[test]
+    fn test_refund() {
+        let limiter = WriteRateLimiter::new(vec![CollectionRateLimit {
+            collection: "app.bsky.feed.post".to_string(),
+            writes: 3,
+            window: 60,
+        }]);
+        let did = "did:plc:abc";
+        let posts = created("app.bsky.feed.post", 3);
+        let counted = limiter.check(did, &posts, 1000).unwrap();
+        assert!(limiter.check(did, &posts, 1010).is_err());
+        // failed writes give their quota back
+        limiter.refund(did, &counted);
+        let counted = limiter.check(did, &posts, 1020).unwrap();
+        // but not into a window that has rolled over
+        assert!(limiter.check(did, &posts, 1060).is_ok());
+        limiter.refund(did, &counted);
+        assert!(limiter
+            .check(did, &created("app.bsky.feed.post", 1), 1070)
+            .is_err());
+    }
+}
@@ -1,11 +1,18 @@
+pub mod value_index;
+
+use crate::actor_store::record::value_index::{index_value, RecordValueQuery};
+use crate::actor_store::repo::sqlite_repo::with_record_content;
 use crate::db::DbConn;
-use crate::models::{models, Backlink, Record};
+use crate::models::{models, Backlink, DeletedRecord, Record};
 use anyhow::{bail, Result};
+use chrono::{Duration, Utc};
 use diesel::result::Error;
+use diesel::upsert::excluded;
 use diesel::*;
 use futures::stream::{self, StreamExt};
 use lexicon_cid::Cid;
 use rsky_common;
+use rsky_common::RFC3339_VARIANT;
 use rsky_lexicon::com::atproto::admin::StatusAttr;
 use rsky_repo::storage::Ipld;
 use rsky_repo::types::{Ids, Lex, RepoRecord, WriteOpAction};
@@ -79,6 +86,19 @@ pub fn get_backlinks(uri: &AtUri, record: &RepoRecord) -> Result<Vec<Backlink>>
     Ok(Vec::new())
 }
 
+pub fn deleted_record_cursor(record: &DeletedRecord) -> String {
+    format!("{}::{}", record.deleted_at, record.uri)
+}
+
+/// Permanently drops retained deleted records whose window has passed.
+pub fn purge_expired_deleted_records(now: &str, conn: &mut PgConnection) -> Result<usize> {
+    use crate::schema::pds::deleted_record::dsl as DeletedRecordSchema;
+
+    Ok(delete(DeletedRecordSchema::deleted_record)
+        .filter(DeletedRecordSchema::expiresAt.le(now))
+        .execute(conn)?)
+}
+
 pub struct RecordReader {
     pub did: String,
     pub db: Arc<DbConn>,
@@ -139,9 +159,12 @@ impl RecordReader {
             false
         };
         let mut builder = RecordSchema::record
-            .inner_join(RepoBlockSchema::repo_block.on(RepoBlockSchema::cid.eq(RecordSchema::cid)))
+            .left_join(RepoBlockSchema::repo_block.on(RepoBlockSchema::cid.eq(RecordSchema::cid)))
             .limit(limit)
-            .select((models::Record::as_select(), models::RepoBlock::as_select()))
+            .select((
+                models::Record::as_select(),
+                Option::<models::RepoBlock>::as_select(),
+            ))
             .filter(RecordSchema::did.eq(self.did.clone()))
             .filter(RecordSchema::collection.eq(collection))
             .into_boxed();
@@ -168,19 +191,29 @@ impl RecordReader {
                 builder = builder.filter(RecordSchema::rkey.lt(rkey_end));
             }
         }
-        let res: Vec<(models::Record, models::RepoBlock)> =
+        let res: Vec<(models::Record, Option<models::RepoBlock>)> =
             self.db.run(move |conn| builder.load(conn)).await?;
-        res.into_iter()
+        with_record_content(res)
+            .await?
+            .into_iter()
             .map(|row| {
                 Ok(RecordsForCollection {
                     uri: row.0.uri,
                     cid: row.0.cid,
-                    value: cbor_to_lex_record(row.1.content)?,
+                    value: cbor_to_lex_record(row.1)?,
                 })
             })
             .collect::<Result<Vec<RecordsForCollection>>>()
     }
 
+    /// Records in this repo matching an indexed value query, see [`RecordValueQuery`].
+    pub async fn query_records_by_value(
+        &self,
+        query: RecordValueQuery,
+    ) -> Result<Vec<RecordsForCollection>> {
+        query.did(self.did.clone()).execute(&self.db).await
+    }
+
     pub async fn get_record(
         &mut self,
         uri: &AtUri,
@@ -196,8 +229,11 @@ impl RecordReader {
             false
         };
         let mut builder = RecordSchema::record
-            .inner_join(RepoBlockSchema::repo_block.on(RepoBlockSchema::cid.eq(RecordSchema::cid)))
-            .select((models::Record::as_select(), models::RepoBlock::as_select()))
+            .left_join(RepoBlockSchema::repo_block.on(RepoBlockSchema::cid.eq(RecordSchema::cid)))
+            .select((
+                models::Record::as_select(),
+                Option::<models::RepoBlock>::as_select(),
+            ))
             .filter(RecordSchema::uri.eq(uri.to_string()))
             .into_boxed();
         if !include_soft_deleted {
@@ -206,15 +242,18 @@ impl RecordReader {
         if let Some(cid) = cid {
             builder = builder.filter(RecordSchema::cid.eq(cid));
         }
-        let record: Option<(models::Record, models::RepoBlock)> = self
+        let record: Option<(models::Record, Option<models::RepoBlock>)> = self
             .db
             .run(move |conn| builder.first(conn).optional())
             .await?;
+        let record = with_record_content(record.into_iter().collect())
+            .await?
+            .pop();
         if let Some(record) = record {
             Ok(Some(GetRecord {
                 uri: record.0.uri,
                 cid: record.0.cid,
-                value: cbor_to_lex_record(record.1.content)?,
+                value: cbor_to_lex_record(record.1)?,
                 indexed_at: record.0.indexed_at,
                 takedown_ref: record.0.takedown_ref,
             }))
@@ -383,6 +422,10 @@ impl RecordReader {
         let hostname = uri.get_hostname().to_string();
         let action = action.unwrap_or(WriteOpAction::Create);
         let indexed_at = timestamp.unwrap_or_else(|| rsky_common::now());
+        let value_json = match record {
+            Some(ref record) => index_value(&collection, record)?,
+            None => None,
+        };
         let row = Record {
             did: self.did.clone(),
             uri: uri.to_string(),
@@ -409,13 +452,14 @@ impl RecordReader {
             .db
             .run(move |conn| {
                 insert_into(RecordSchema::record)
-                    .values(row)
+                    .values((row, RecordSchema::valueJson.eq(&value_json)))
                     .on_conflict(RecordSchema::uri)
                     .do_update()
                     .set((
                         RecordSchema::cid.eq(cid.to_string()),
                         RecordSchema::repoRev.eq(&repo_rev),
                         RecordSchema::indexedAt.eq(&indexed_at),
+                        RecordSchema::valueJson.eq(&value_json),
                     ))
                     .execute(conn)?;
                 Ok::<_, Error>((record, uri))
@@ -458,6 +502,131 @@ impl RecordReader {
             .await
     }
 
+    /// Reads the records at `uris`, row and block, as `deleted_record` rows kept for
+    /// `retain_for` seconds. Has to run before the deleting commit is applied, while the blocks
+    /// are still in repo storage; [`RecordReader::retain_deleted`] stores them once it has.
+    pub async fn snapshot_deleted(
+        &self,
+        uris: Vec<String>,
+        retain_for: u64,
+    ) -> Result<Vec<DeletedRecord>> {
+        if uris.is_empty() {
+            return Ok(Vec::new());
+        }
+        use crate::schema::pds::record::dsl as RecordSchema;
+        use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
+
+        let did = self.did.clone();
+        let rows: Vec<(models::Record, Option<models::RepoBlock>)> = self
+            .db
+            .run(move |conn| {
+                RecordSchema::record
+                    .left_join(
+                        RepoBlockSchema::repo_block.on(RepoBlockSchema::cid
+                            .eq(RecordSchema::cid)
+                            .and(RepoBlockSchema::did.eq(RecordSchema::did))),
+                    )
+                    .filter(RecordSchema::did.eq(did))
+                    .filter(RecordSchema::uri.eq_any(uris))
+                    .select((
+                        models::Record::as_select(),
+                        Option::<models::RepoBlock>::as_select(),
+                    ))
+                    .load(conn)
+            })
+            .await?;
+        let deleted_at = rsky_common::now();
+        let expires_at = (Utc::now() + Duration::seconds(retain_for as i64))
+            .format(RFC3339_VARIANT)
+            .to_string();
+        let deleted: Vec<DeletedRecord> = with_record_content(rows)
+            .await?
+            .into_iter()
+            .map(|(record, content)| DeletedRecord {
+                uri: record.uri,
+                cid: record.cid,
+                did: record.did,
+                collection: record.collection,
+                rkey: record.rkey,
+                repo_rev: record.repo_rev,
+                indexed_at: record.indexed_at,
+                content,
+                deleted_at: deleted_at.clone(),
+                expires_at: expires_at.clone(),
+            })
+            .collect();
+        Ok(deleted)
+    }
+
+    /// Stores records read by [`RecordReader::snapshot_deleted`] for moderators to review.
+    pub async fn retain_deleted(&self, deleted: Vec<DeletedRecord>) -> Result<()> {
+        if deleted.is_empty() {
+            return Ok(());
+        }
+        use crate::schema::pds::deleted_record::dsl as DeletedRecordSchema;
+
+        self.db
+            .run(move |conn| {
+                insert_into(DeletedRecordSchema::deleted_record)
+                    .values(&deleted)
+                    .on_conflict((DeletedRecordSchema::uri, DeletedRecordSchema::cid))
+                    .do_update()
+                    .set((
+                        DeletedRecordSchema::deletedAt.eq(excluded(DeletedRecordSchema::deletedAt)),
+                        DeletedRecordSchema::expiresAt.eq(excluded(DeletedRecordSchema::expiresAt)),
+                    ))
+                    .execute(conn)
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Retained deleted records, most recently deleted first, optionally only those after
+    /// `cursor`, a `<deletedAt>::<uri>` pair as made by [`deleted_record_cursor`].
+    pub async fn list_deleted_records(
+        &self,
+        collection: Option<String>,
+        limit: i64,
+        cursor: Option<String>,
+    ) -> Result<Vec<DeletedRecord>> {
+        use crate::schema::pds::deleted_record::dsl as DeletedRecordSchema;
+
+        let did = self.did.clone();
+        let now = rsky_common::now();
+        let res = self
+            .db
+            .run(move |conn| {
+                let mut builder = DeletedRecordSchema::deleted_record
+                    .filter(DeletedRecordSchema::did.eq(did))
+                    .filter(DeletedRecordSchema::expiresAt.gt(now))
+                    .select(DeletedRecord::as_select())
+                    .order((
+                        DeletedRecordSchema::deletedAt.desc(),
+                        DeletedRecordSchema::uri.asc(),
+                    ))
+                    .limit(limit)
+                    .into_boxed();
+                if let Some(collection) = collection {
+                    builder = builder.filter(DeletedRecordSchema::collection.eq(collection));
+                }
+                if let Some((deleted_at, uri)) =
+                    cursor.as_deref().and_then(|cursor| cursor.split_once("::"))
+                {
+                    // records deleted by the same commit share a deletedAt
+                    builder = builder.filter(
+                        DeletedRecordSchema::deletedAt
+                            .lt(deleted_at.to_string())
+                            .or(DeletedRecordSchema::deletedAt
+                                .eq(deleted_at.to_string())
+                                .and(DeletedRecordSchema::uri.gt(uri.to_string()))),
+                    );
+                }
+                builder.load(conn)
+            })
+            .await?;
+        Ok(res)
+    }
+
     pub async fn remove_backlinks_by_uri(&self, uri: &AtUri) -> Result<()> {
         use crate::schema::pds::backlink::dsl as BacklinkSchema;
         let uri = uri.to_string();
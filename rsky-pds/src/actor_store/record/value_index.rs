@@ -0,0 +1,267 @@
+//! Optional JSONB index over record values, so internal features can ask for
+//! "records in collection X where field Y = Z" without hand-written SQL.
+//! Only collections listed in `PDS_RECORD_VALUE_INDEX_COLLECTIONS` are indexed. Records
+//! written before their collection was indexed are filled in by [`backfill`] at startup.
+
+use crate::actor_store::record::RecordsForCollection;
+use crate::actor_store::repo::sqlite_repo::with_record_content;
+use crate::db::DbConn;
+use crate::models::models;
+use anyhow::Result;
+use diesel::*;
+use lazy_static::lazy_static;
+use rsky_common::env::env_list;
+use rsky_repo::types::{Ids, RepoRecord};
+use rsky_repo::util::cbor_to_lex_record;
+use serde_json::{Map, Value as JsonValue};
+
+const BACKFILL_BATCH_SIZE: i64 = 500;
+
+lazy_static! {
+    pub static ref INDEXED_COLLECTIONS: Vec<String> = {
+        let collections = env_list("PDS_RECORD_VALUE_INDEX_COLLECTIONS");
+        if collections.is_empty() {
+            vec![
+                Ids::AppBskyFeedGenerator.as_str().to_string(),
+                Ids::AppBskyLabelerService.as_str().to_string(),
+            ]
+        } else {
+            collections
+        }
+    };
+}
+
+pub fn is_indexed_collection(collection: &str) -> bool {
+    INDEXED_COLLECTIONS.iter().any(|c| c == collection)
+}
+
+/// JSON form of a record stored in `record."valueJson"`, or `None` when the
+/// collection isn't indexed.
+pub fn index_value(collection: &str, record: &RepoRecord) -> Result<Option<JsonValue>> {
+    if !is_indexed_collection(collection) {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::to_value(record)?))
+}
+
+/// Query over indexed record values. Field paths use dots for nested objects,
+/// e.g. `subject.uri`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordValueQuery {
+    pub collection: String,
+    pub did: Option<String>,
+    pub rkey: Option<String>,
+    pub filter: Map<String, JsonValue>,
+    pub limit: i64,
+    pub cursor: Option<String>,
+    pub include_soft_deleted: bool,
+}
+
+impl RecordValueQuery {
+    pub fn new(collection: impl Into<String>) -> Self {
+        RecordValueQuery {
+            collection: collection.into(),
+            did: None,
+            rkey: None,
+            filter: Map::new(),
+            limit: 50,
+            cursor: None,
+            include_soft_deleted: false,
+        }
+    }
+
+    /// Restricts the query to a single repo.
+    pub fn did(mut self, did: impl Into<String>) -> Self {
+        self.did = Some(did.into());
+        self
+    }
+
+    /// Restricts the query to a single record key, e.g. `self` for singleton declarations.
+    pub fn rkey(mut self, rkey: impl Into<String>) -> Self {
+        self.rkey = Some(rkey.into());
+        self
+    }
+
+    /// Requires the value at `path` to equal `value`.
+    pub fn field_eq(mut self, path: &str, value: impl Into<JsonValue>) -> Self {
+        let mut value = value.into();
+        let segments: Vec<&str> = path.split('.').collect();
+        let (head, rest) = segments.split_first().unwrap_or((&path, &[]));
+        for segment in rest.iter().rev() {
+            let mut inner = Map::new();
+            inner.insert(segment.to_string(), value);
+            value = JsonValue::Object(inner);
+        }
+        merge(&mut self.filter, head, value);
+        self
+    }
+
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Continue after the given record uri, as returned from a previous page.
+    pub fn cursor(mut self, cursor: Option<String>) -> Self {
+        self.cursor = cursor;
+        self
+    }
+
+    pub fn include_soft_deleted(mut self, include_soft_deleted: bool) -> Self {
+        self.include_soft_deleted = include_soft_deleted;
+        self
+    }
+
+    pub async fn execute(self, db: &DbConn) -> Result<Vec<RecordsForCollection>> {
+        use crate::schema::pds::record::dsl as RecordSchema;
+        use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
+
+        let mut builder = RecordSchema::record
+            .left_join(
+                RepoBlockSchema::repo_block.on(RepoBlockSchema::cid
+                    .eq(RecordSchema::cid)
+                    .and(RepoBlockSchema::did.eq(RecordSchema::did))),
+            )
+            .select((
+                models::Record::as_select(),
+                Option::<models::RepoBlock>::as_select(),
+            ))
+            .filter(RecordSchema::collection.eq(self.collection))
+            .filter(RecordSchema::valueJson.contains(JsonValue::Object(self.filter)))
+            .order(RecordSchema::uri.asc())
+            .limit(self.limit)
+            .into_boxed();
+        if let Some(did) = self.did {
+            builder = builder.filter(RecordSchema::did.eq(did));
+        }
+        if let Some(rkey) = self.rkey {
+            builder = builder.filter(RecordSchema::rkey.eq(rkey));
+        }
+        if !self.include_soft_deleted {
+            builder = builder.filter(RecordSchema::takedownRef.is_null());
+        }
+        if let Some(cursor) = self.cursor {
+            builder = builder.filter(RecordSchema::uri.gt(cursor));
+        }
+        let res: Vec<(models::Record, Option<models::RepoBlock>)> =
+            db.run(move |conn| builder.load(conn)).await?;
+        with_record_content(res)
+            .await?
+            .into_iter()
+            .map(|row| {
+                Ok(RecordsForCollection {
+                    uri: row.0.uri,
+                    cid: row.0.cid,
+                    value: cbor_to_lex_record(row.1)?,
+                })
+            })
+            .collect::<Result<Vec<RecordsForCollection>>>()
+    }
+}
+
+/// Indexes the values of records in indexed collections that don't have one yet, a batch at a
+/// time in uri order.
+pub async fn backfill(db: DbConn) {
+    let mut cursor = String::new();
+    loop {
+        match backfill_batch(&db, cursor).await {
+            Ok(Some(last)) => cursor = last,
+            Ok(None) => break,
+            Err(error) => {
+                tracing::error!("@LOG: ERROR: failed to backfill record value index: {error}");
+                return;
+            }
+        }
+    }
+}
+
+/// Indexes the next batch of records after `cursor`, returning the last uri it looked at or
+/// `None` once there are none left.
+async fn backfill_batch(db: &DbConn, cursor: String) -> Result<Option<String>> {
+    use crate::schema::pds::record::dsl as RecordSchema;
+    use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
+
+    let rows: Vec<(models::Record, Option<models::RepoBlock>)> = db
+        .run(move |conn| {
+            RecordSchema::record
+                .left_join(
+                    RepoBlockSchema::repo_block.on(RepoBlockSchema::cid
+                        .eq(RecordSchema::cid)
+                        .and(RepoBlockSchema::did.eq(RecordSchema::did))),
+                )
+                .select((
+                    models::Record::as_select(),
+                    Option::<models::RepoBlock>::as_select(),
+                ))
+                .filter(RecordSchema::collection.eq_any(INDEXED_COLLECTIONS.clone()))
+                .filter(RecordSchema::valueJson.is_null())
+                .filter(RecordSchema::uri.gt(cursor))
+                .order(RecordSchema::uri.asc())
+                .limit(BACKFILL_BATCH_SIZE)
+                .load(conn)
+        })
+        .await?;
+    let Some(last) = rows.last().map(|(record, _)| record.uri.clone()) else {
+        return Ok(None);
+    };
+    let mut values = Vec::new();
+    for (record, content) in with_record_content(rows).await? {
+        // a record that doesn't decode is skipped rather than holding up the rest
+        let Ok(value) = cbor_to_lex_record(content) else {
+            continue;
+        };
+        if let Some(value_json) = index_value(&record.collection, &value)? {
+            values.push((record.uri, value_json));
+        }
+    }
+    db.run(move |conn| {
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            for (uri, value_json) in values {
+                update(RecordSchema::record)
+                    .filter(RecordSchema::uri.eq(uri))
+                    .set(RecordSchema::valueJson.eq(value_json))
+                    .execute(conn)?;
+            }
+            Ok(())
+        })
+    })
+    .await?;
+    Ok(Some(last))
+}
+
+fn merge(filter: &mut Map<String, JsonValue>, key: &str, value: JsonValue) {
+    match (filter.get_mut(key), value) {
+        (Some(JsonValue::Object(existing)), JsonValue::Object(value)) => {
+            for (key, value) in value {
+                merge(existing, &key, value);
+            }
+        }
+        (_, value) => {
+            filter.insert(key.to_string(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_field_eq_builds_nested_filter() {
+        let query = RecordValueQuery::new("app.bsky.feed.like")
+            .field_eq("subject.uri", "at://did:plc:abc/app.bsky.feed.post/1")
+            .field_eq("subject.cid", "bafyrei")
+            .field_eq("$type", "app.bsky.feed.like");
+        assert_eq!(
+            JsonValue::Object(query.filter),
+            json!({
+                "$type": "app.bsky.feed.like",
+                "subject": {
+                    "uri": "at://did:plc:abc/app.bsky.feed.post/1",
+                    "cid": "bafyrei"
+                }
+            })
+        );
+    }
+}
@@ -1,2 +1,3 @@
 pub mod sql_repo;
+pub mod sqlite_repo;
 pub mod types;
@@ -1,19 +1,23 @@
+use crate::actor_store::actor_store_config;
+use crate::actor_store::repo::sqlite_repo::SqliteRepoStore;
+use crate::actor_store::repo::types::CommitOpCounts;
 use crate::db::DbConn;
 use crate::models;
 use crate::models::RepoBlock;
 use anyhow::Result;
-use diesel::dsl::sql;
+use diesel::dsl::{exists, sql};
 use diesel::prelude::*;
 use diesel::sql_types::{Bool, Text};
 use diesel::*;
-use futures::{stream, StreamExt, TryStreamExt};
+use futures::{stream, Stream, StreamExt, TryStreamExt};
 use lexicon_cid::Cid;
 use rsky_common;
 use rsky_repo::block_map::{BlockMap, BlocksAndMissing};
-use rsky_repo::car::blocks_to_car_file;
+use rsky_repo::car::write_car_stream;
 use rsky_repo::cid_set::CidSet;
 use rsky_repo::storage::readable_blockstore::ReadableBlockstore;
 use rsky_repo::storage::types::RepoStorage;
+use rsky_repo::storage::verify_blocks;
 use rsky_repo::storage::CidAndRev;
 use rsky_repo::storage::RepoRootError::RepoRootNotFoundError;
 use rsky_repo::types::CommitData;
@@ -31,6 +35,12 @@ pub struct SqlRepoReader {
     pub rev: Option<String>,
     pub now: String,
     pub did: String,
+    /// Re-hash blocks loaded from the database against their CIDs, see
+    /// [`crate::config::ActorStoreConfig::verify_blocks_on_read`].
+    pub verify_on_read: bool,
+    /// Set when `PDS_REPO_STORAGE_BACKEND=sqlite`: blocks, commit history and the root live in
+    /// the actor's own SQLite file, with the root mirrored to Postgres afterwards.
+    pub sqlite: Option<SqliteRepoStore>,
 }
 
 impl ReadableBlockstore for SqlRepoReader {
@@ -52,19 +62,26 @@ impl ReadableBlockstore for SqlRepoReader {
                 return Ok(Some(cached_result.clone()));
             }
 
-            let found: Option<Vec<u8>> = db
-                .run(move |conn| {
-                    RepoBlockSchema::repo_block
-                        .filter(RepoBlockSchema::cid.eq(cid.to_string()))
-                        .filter(RepoBlockSchema::did.eq(did))
-                        .select(RepoBlockSchema::content)
-                        .first(conn)
-                        .optional()
-                })
-                .await?;
+            let found: Option<Vec<u8>> = match &self.sqlite {
+                Some(sqlite) => sqlite.get_block(cid.to_string()).await?,
+                None => {
+                    db.run(move |conn| {
+                        RepoBlockSchema::repo_block
+                            .filter(RepoBlockSchema::cid.eq(cid.to_string()))
+                            .filter(RepoBlockSchema::did.eq(did))
+                            .select(RepoBlockSchema::content)
+                            .first(conn)
+                            .optional()
+                    })
+                    .await?
+                }
+            };
             match found {
                 None => Ok(None),
                 Some(result) => {
+                    if self.verify_on_read {
+                        verify_blocks(&[(cid, result.as_slice())])?;
+                    }
                     {
                         let mut cache_guard = self.cache.write().await;
                         cache_guard.set(cid, result.clone());
@@ -116,18 +133,28 @@ impl ReadableBlockstore for SqlRepoReader {
                     let blocks = Arc::clone(&blocks);
                     let missing = Arc::clone(&missing_set);
                     let batch = batch.to_vec(); // Convert to owned Vec
+                    let verify_on_read = self.verify_on_read;
+                    let sqlite = self.sqlite.clone();
 
                     async move {
                         // Database query
-                        let rows: Vec<(String, Vec<u8>)> = this_db
-                            .run(move |conn| {
-                                RepoBlockSchema::repo_block
-                                    .filter(RepoBlockSchema::cid.eq_any(batch))
-                                    .filter(RepoBlockSchema::did.eq(this_did))
-                                    .select((RepoBlockSchema::cid, RepoBlockSchema::content))
-                                    .load(conn)
-                            })
-                            .await?;
+                        let rows: Vec<(String, Vec<u8>)> = match sqlite {
+                            Some(sqlite) => sqlite.get_blocks(batch).await?,
+                            None => {
+                                this_db
+                                    .run(move |conn| {
+                                        RepoBlockSchema::repo_block
+                                            .filter(RepoBlockSchema::cid.eq_any(batch))
+                                            .filter(RepoBlockSchema::did.eq(this_did))
+                                            .select((
+                                                RepoBlockSchema::cid,
+                                                RepoBlockSchema::content,
+                                            ))
+                                            .load(conn)
+                                    })
+                                    .await?
+                            }
+                        };
 
                         // Process rows with locked access
                         let mut blocks = blocks.lock().await;
@@ -135,6 +162,9 @@ impl ReadableBlockstore for SqlRepoReader {
 
                         for row in rows {
                             let cid = Cid::from_str(&row.0)?; // Proper error handling
+                            if verify_on_read {
+                                verify_blocks(&[(cid, row.1.as_slice())])?;
+                            }
                             blocks.set(cid, row.1);
                             missing.delete(cid);
                         }
@@ -190,6 +220,16 @@ impl RepoStorage for SqlRepoReader {
         Box::pin(async move {
             use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
 
+            if let Some(sqlite) = &self.sqlite {
+                let mut blocks = BlockMap::new();
+                blocks.set(cid, bytes);
+                sqlite
+                    .write_blocks(self.to_repo_blocks(&blocks, &rev), vec![])
+                    .await?;
+                let mut cache_guard = self.cache.write().await;
+                cache_guard.set(cid, bytes_cloned);
+                return Ok(());
+            }
             db.run(move |conn| {
                 insert_into(RepoBlockSchema::repo_block)
                     .values((
@@ -215,50 +255,29 @@ impl RepoStorage for SqlRepoReader {
         to_put: BlockMap,
         rev: String,
     ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + Sync + 'a>> {
-        let did: String = self.did.clone();
         let db: Arc<DbConn> = self.db.clone();
 
         Box::pin(async move {
-            use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
-
-            let blocks: Vec<RepoBlock> = to_put
-                .map
-                .iter()
-                .map(|(cid, bytes)| RepoBlock {
-                    cid: cid.to_string(),
-                    did: did.clone(),
-                    repo_rev: rev.clone(),
-                    size: bytes.0.len() as i32,
-                    content: bytes.0.clone(),
-                })
-                .collect();
-
-            let chunks: Vec<Vec<RepoBlock>> =
-                blocks.chunks(50).map(|chunk| chunk.to_vec()).collect();
-
-            let _: Vec<_> = stream::iter(chunks)
-                .then(|batch| {
-                    let db = db.clone();
-                    async move {
-                        db.run(move |conn| {
-                            insert_into(RepoBlockSchema::repo_block)
-                                .values(batch)
-                                .on_conflict_do_nothing()
-                                .execute(conn)
-                                .map(|_| ())
+            let blocks = self.to_repo_blocks(&to_put, &rev);
+            match &self.sqlite {
+                Some(sqlite) => sqlite.write_blocks(blocks, vec![]).await?,
+                None => {
+                    db.run(move |conn| {
+                        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                            insert_blocks(conn, &blocks)
                         })
-                        .await
-                        .map_err(anyhow::Error::from)
-                    }
-                })
-                .collect::<Vec<_>>()
-                .await
-                .into_iter()
-                .collect::<Result<Vec<()>>>()?;
-
+                    })
+                    .await?
+                }
+            }
+            {
+                let mut cache_guard = self.cache.write().await;
+                cache_guard.add_map(to_put)?;
+            }
             Ok(())
         })
     }
+
     fn update_root<'a>(
         &'a self,
         cid: Cid,
@@ -270,53 +289,155 @@ impl RepoStorage for SqlRepoReader {
         let now: String = self.now.clone();
 
         Box::pin(async move {
-            use crate::schema::pds::repo_root::dsl as RepoRootSchema;
-
             let is_create = is_create.unwrap_or(false);
-            if is_create {
-                db.run(move |conn| {
-                    insert_into(RepoRootSchema::repo_root)
-                        .values((
-                            RepoRootSchema::did.eq(did),
-                            RepoRootSchema::cid.eq(cid.to_string()),
-                            RepoRootSchema::rev.eq(rev),
-                            RepoRootSchema::indexedAt.eq(now),
-                        ))
-                        .execute(conn)
-                })
-                .await?;
-            } else {
-                db.run(move |conn| {
-                    update(RepoRootSchema::repo_root)
-                        .filter(RepoRootSchema::did.eq(did))
-                        .set((
-                            RepoRootSchema::cid.eq(cid.to_string()),
-                            RepoRootSchema::rev.eq(rev),
-                            RepoRootSchema::indexedAt.eq(now),
-                        ))
-                        .execute(conn)
-                })
-                .await?;
+            if let Some(sqlite) = &self.sqlite {
+                sqlite
+                    .write_root(models::RepoRoot {
+                        did: did.clone(),
+                        cid: cid.to_string(),
+                        rev: rev.clone(),
+                        indexed_at: now.clone(),
+                    })
+                    .await?;
             }
+            db.run(move |conn| write_root(conn, did, cid, rev, now, is_create))
+                .await?;
             Ok(())
         })
     }
 
+    /// Writes the new root, new blocks and block removals in a single transaction so a failure
+    /// partway through never leaves the root pointing at blocks that weren't stored.
     fn apply_commit<'a>(
         &'a self,
         commit: CommitData,
         is_create: Option<bool>,
     ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + Sync + 'a>> {
+        let did: String = self.did.clone();
+        let db: Arc<DbConn> = self.db.clone();
+        let now: String = self.now.clone();
+
         Box::pin(async move {
-            self.update_root(commit.cid, commit.rev.clone(), is_create)
+            use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
+
+            let is_create = is_create.unwrap_or(false);
+            let blocks = self.to_repo_blocks(&commit.new_blocks, &commit.rev);
+            let removed = commit.removed_cids.to_list();
+            let removed_strings: Vec<String> = removed.iter().map(|c| c.to_string()).collect();
+            let (root, rev) = (commit.cid, commit.rev.clone());
+            if let Some(sqlite) = &self.sqlite {
+                // the file is authoritative; the Postgres root is a mirror for cross-account
+                // queries and is only moved once the commit is durable in the file
+                let new_root = models::RepoRoot {
+                    did: did.clone(),
+                    cid: root.to_string(),
+                    rev: rev.clone(),
+                    indexed_at: now.clone(),
+                };
+                sqlite
+                    .write_commit(Some(new_root), blocks, removed_strings)
+                    .await?;
+                db.run(move |conn| write_root(conn, did, root, rev, now, is_create))
+                    .await?;
+            } else {
+                db.run(move |conn| {
+                    conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                        write_root(conn, did.clone(), root, rev, now, is_create)?;
+                        insert_blocks(conn, &blocks)?;
+                        insert_commit_blocks(conn, &blocks)?;
+                        if !removed_strings.is_empty() {
+                            delete(RepoBlockSchema::repo_block)
+                                .filter(RepoBlockSchema::did.eq(&did))
+                                .filter(RepoBlockSchema::cid.eq_any(removed_strings))
+                                .execute(conn)?;
+                        }
+                        Ok(())
+                    })
+                })
                 .await?;
-            self.put_many(commit.new_blocks, commit.rev).await?;
-            self.delete_many(commit.removed_cids.to_list()).await?;
+            }
+            {
+                let mut cache_guard = self.cache.write().await;
+                for cid in removed {
+                    cache_guard.delete(cid)?;
+                }
+                cache_guard.add_map(commit.new_blocks)?;
+            }
             Ok(())
         })
     }
 }
 
+// Rows per insert statement, well under Postgres' bind parameter limit
+const BLOCK_INSERT_CHUNK_SIZE: usize = 500;
+
+/// Insert blocks in chunks on the given connection. Callers run this inside a transaction so
+/// the batch lands all at once or not at all.
+fn insert_blocks(conn: &mut PgConnection, blocks: &[RepoBlock]) -> QueryResult<()> {
+    use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
+
+    for chunk in blocks.chunks(BLOCK_INSERT_CHUNK_SIZE) {
+        insert_into(RepoBlockSchema::repo_block)
+            .values(chunk)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Record which blocks a commit added, keyed by its rev, for answering `getRepo?since`.
+fn insert_commit_blocks(conn: &mut PgConnection, blocks: &[RepoBlock]) -> QueryResult<()> {
+    use crate::schema::pds::repo_commit_block::dsl as RepoCommitBlockSchema;
+
+    let rows: Vec<models::RepoCommitBlock> = blocks
+        .iter()
+        .map(|block| models::RepoCommitBlock {
+            did: block.did.clone(),
+            rev: block.repo_rev.clone(),
+            cid: block.cid.clone(),
+        })
+        .collect();
+    for chunk in rows.chunks(BLOCK_INSERT_CHUNK_SIZE) {
+        insert_into(RepoCommitBlockSchema::repo_commit_block)
+            .values(chunk)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+fn write_root(
+    conn: &mut PgConnection,
+    did: String,
+    cid: Cid,
+    rev: String,
+    now: String,
+    is_create: bool,
+) -> QueryResult<()> {
+    use crate::schema::pds::repo_root::dsl as RepoRootSchema;
+
+    if is_create {
+        insert_into(RepoRootSchema::repo_root)
+            .values((
+                RepoRootSchema::did.eq(did),
+                RepoRootSchema::cid.eq(cid.to_string()),
+                RepoRootSchema::rev.eq(rev),
+                RepoRootSchema::indexedAt.eq(now),
+            ))
+            .execute(conn)?;
+    } else {
+        update(RepoRootSchema::repo_root)
+            .filter(RepoRootSchema::did.eq(did))
+            .set((
+                RepoRootSchema::cid.eq(cid.to_string()),
+                RepoRootSchema::rev.eq(rev),
+                RepoRootSchema::indexedAt.eq(now),
+            ))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
 // Basically handles getting ipld blocks from db
 impl SqlRepoReader {
     pub fn new(did: String, now: Option<String>, db: Arc<DbConn>) -> Self {
@@ -327,37 +448,114 @@ impl SqlRepoReader {
             rev: None,
             db,
             now,
+            verify_on_read: actor_store_config().verify_blocks_on_read,
+            sqlite: SqliteRepoStore::for_did(&did),
             did,
         }
     }
 
-    pub async fn get_car_stream(&self, since: Option<String>) -> Result<Vec<u8>> {
-        match self.get_root().await {
-            None => Err(anyhow::Error::new(RepoRootNotFoundError)),
-            Some(root) => {
-                let mut car = BlockMap::new();
-                let mut cursor: Option<CidAndRev> = None;
-                let mut write_rows = |rows: Vec<RepoBlock>| -> Result<()> {
-                    for row in rows {
-                        car.set(Cid::from_str(&row.cid)?, row.content);
+    fn to_repo_blocks(&self, blocks: &BlockMap, rev: &str) -> Vec<RepoBlock> {
+        blocks
+            .map
+            .iter()
+            .map(|(cid, bytes)| RepoBlock {
+                cid: cid.to_string(),
+                did: self.did.clone(),
+                repo_rev: rev.to_owned(),
+                size: bytes.0.len() as i32,
+                content: bytes.0.clone(),
+            })
+            .collect()
+    }
+
+    /// Streams the repo as a CAR, or only the blocks added after rev `since`. Blocks are written
+    /// out page by page as they come back from the database, so large repos are never held in
+    /// memory whole.
+    pub async fn get_car_stream(
+        &self,
+        since: Option<String>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>> {
+        let root = self
+            .get_root()
+            .await
+            .ok_or_else(|| anyhow::Error::new(RepoRootNotFoundError))?;
+        let reader = self.clone();
+        Ok(Box::pin(write_car_stream(
+            Some(&root),
+            move |mut car| async move {
+                if let Some(since) = &since {
+                    if let Some(blocks) = reader.get_blocks_since(since.clone()).await? {
+                        for entry in blocks.entries()? {
+                            car.write(entry.cid, entry.bytes).await?;
+                        }
+                        return Ok(car);
                     }
-                    Ok(())
-                };
+                }
+                let mut cursor: Option<CidAndRev> = None;
                 loop {
-                    let res = self.get_block_range(&since, &cursor).await?;
-                    write_rows(res.clone())?;
-                    if let Some(last_row) = res.last() {
-                        cursor = Some(CidAndRev {
-                            cid: Cid::from_str(&last_row.cid)?,
-                            rev: last_row.repo_rev.clone(),
-                        });
-                    } else {
-                        break;
+                    let rows = reader.get_block_range(&since, &cursor).await?;
+                    match rows.last() {
+                        Some(last_row) => {
+                            cursor = Some(CidAndRev {
+                                cid: Cid::from_str(&last_row.cid)?,
+                                rev: last_row.repo_rev.clone(),
+                            });
+                        }
+                        None => break,
+                    }
+                    for row in rows {
+                        car.write(Cid::from_str(&row.cid)?, row.content).await?;
                     }
                 }
-                blocks_to_car_file(Some(&root), car).await
-            }
+                Ok(car)
+            },
+        )))
+    }
+
+    /// The blocks added by every commit after `since` that are still in the repo, looked up in
+    /// `repo_commit_block`. None when that doesn't cover all of those commits, i.e. `since` is
+    /// older than its oldest rev for the repo (from before it was kept, or already pruned), or
+    /// the repo's blocks live in SQLite.
+    pub async fn get_blocks_since(&self, since: String) -> Result<Option<BlockMap>> {
+        if self.sqlite.is_some() {
+            return Ok(None);
+        }
+        let did: String = self.did.clone();
+        let db: Arc<DbConn> = self.db.clone();
+        use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
+        use crate::schema::pds::repo_commit_block::dsl as RepoCommitBlockSchema;
+
+        let rows = db
+            .run(move |conn| {
+                let covered = select(exists(
+                    RepoCommitBlockSchema::repo_commit_block
+                        .filter(RepoCommitBlockSchema::did.eq(&did))
+                        .filter(RepoCommitBlockSchema::rev.le(&since)),
+                ))
+                .get_result::<bool>(conn)?;
+                if !covered {
+                    return Ok::<_, diesel::result::Error>(None);
+                }
+                let added = RepoCommitBlockSchema::repo_commit_block
+                    .filter(RepoCommitBlockSchema::did.eq(&did))
+                    .filter(RepoCommitBlockSchema::rev.gt(&since))
+                    .select(RepoCommitBlockSchema::cid);
+                RepoBlockSchema::repo_block
+                    .filter(RepoBlockSchema::did.eq(&did))
+                    .filter(RepoBlockSchema::cid.eq_any(added))
+                    .select((RepoBlockSchema::cid, RepoBlockSchema::content))
+                    .load::<(String, Vec<u8>)>(conn)
+                    .map(Some)
+            })
+            .await?;
+        let Some(rows) = rows else {
+            return Ok(None);
+        };
+        let mut blocks = BlockMap::new();
+        for (cid, content) in rows {
+            blocks.set(Cid::from_str(&cid)?, content);
         }
+        Ok(Some(blocks))
     }
 
     pub async fn get_block_range(
@@ -371,6 +569,9 @@ impl SqlRepoReader {
         let cursor = cursor.clone();
         use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
 
+        if let Some(sqlite) = &self.sqlite {
+            return sqlite.get_block_range(since, cursor).await;
+        }
         Ok(db
             .run(move |conn| {
                 let mut builder = RepoBlockSchema::repo_block
@@ -403,6 +604,9 @@ impl SqlRepoReader {
     }
 
     pub async fn count_blocks(&self) -> Result<i64> {
+        if let Some(sqlite) = &self.sqlite {
+            return sqlite.count_blocks().await;
+        }
         let did: String = self.did.clone();
         let db: Arc<DbConn> = self.db.clone();
         use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
@@ -427,16 +631,20 @@ impl SqlRepoReader {
         let db: Arc<DbConn> = self.db.clone();
         use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
 
-        let res: Vec<(String, Vec<u8>)> = db
-            .run(move |conn| {
-                RepoBlockSchema::repo_block
-                    .filter(RepoBlockSchema::did.eq(did))
-                    .filter(RepoBlockSchema::repoRev.eq(rev))
-                    .select((RepoBlockSchema::cid, RepoBlockSchema::content))
-                    .limit(15)
-                    .get_results::<(String, Vec<u8>)>(conn)
-            })
-            .await?;
+        let res: Vec<(String, Vec<u8>)> = match &self.sqlite {
+            Some(sqlite) => sqlite.get_blocks_for_rev(rev, 15).await?,
+            None => {
+                db.run(move |conn| {
+                    RepoBlockSchema::repo_block
+                        .filter(RepoBlockSchema::did.eq(did))
+                        .filter(RepoBlockSchema::repoRev.eq(rev))
+                        .select((RepoBlockSchema::cid, RepoBlockSchema::content))
+                        .limit(15)
+                        .get_results::<(String, Vec<u8>)>(conn)
+                })
+                .await?
+            }
+        };
         for row in res {
             let mut cache_guard = self.cache.write().await;
             cache_guard.set(Cid::from_str(&row.0)?, row.1)
@@ -444,6 +652,88 @@ impl SqlRepoReader {
         Ok(())
     }
 
+    /// Append a commit to the repo's commit history, keeping only the most recent
+    /// [`crate::config::ActorStoreConfig::commit_history_limit`] entries.
+    pub async fn record_commit(&self, commit: &CommitData, counts: CommitOpCounts) -> Result<()> {
+        let db: Arc<DbConn> = self.db.clone();
+        use crate::schema::pds::repo_commit::dsl as RepoCommitSchema;
+        use crate::schema::pds::repo_commit_block::dsl as RepoCommitBlockSchema;
+
+        let retain = actor_store_config().commit_history_limit as i64;
+        let row = models::RepoCommit {
+            did: self.did.clone(),
+            rev: commit.rev.clone(),
+            cid: commit.cid.to_string(),
+            since: commit.since.clone(),
+            creates: counts.creates,
+            updates: counts.updates,
+            deletes: counts.deletes,
+            committed_at: self.now.clone(),
+        };
+        if let Some(sqlite) = &self.sqlite {
+            return sqlite.record_commit(row, retain).await;
+        }
+        db.run(move |conn| {
+            conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                insert_into(RepoCommitSchema::repo_commit)
+                    .values(&row)
+                    .on_conflict((RepoCommitSchema::did, RepoCommitSchema::rev))
+                    .do_nothing()
+                    .execute(conn)?;
+                let cutoff: Option<String> = RepoCommitSchema::repo_commit
+                    .filter(RepoCommitSchema::did.eq(&row.did))
+                    .select(RepoCommitSchema::rev)
+                    .order(RepoCommitSchema::rev.desc())
+                    .offset(retain)
+                    .first(conn)
+                    .optional()?;
+                if let Some(cutoff) = cutoff {
+                    delete(RepoCommitSchema::repo_commit)
+                        .filter(RepoCommitSchema::did.eq(&row.did))
+                        .filter(RepoCommitSchema::rev.le(&cutoff))
+                        .execute(conn)?;
+                    delete(RepoCommitBlockSchema::repo_commit_block)
+                        .filter(RepoCommitBlockSchema::did.eq(&row.did))
+                        .filter(RepoCommitBlockSchema::rev.le(&cutoff))
+                        .execute(conn)?;
+                }
+                Ok(())
+            })
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Retained commits, newest first, optionally starting before a given rev.
+    pub async fn list_commits(
+        &self,
+        limit: i64,
+        before: Option<String>,
+    ) -> Result<Vec<models::RepoCommit>> {
+        let did: String = self.did.clone();
+        let db: Arc<DbConn> = self.db.clone();
+        use crate::schema::pds::repo_commit::dsl as RepoCommitSchema;
+
+        if let Some(sqlite) = &self.sqlite {
+            return sqlite.list_commits(limit, before).await;
+        }
+        let res = db
+            .run(move |conn| {
+                let mut builder = RepoCommitSchema::repo_commit
+                    .filter(RepoCommitSchema::did.eq(did))
+                    .select(models::RepoCommit::as_select())
+                    .order(RepoCommitSchema::rev.desc())
+                    .limit(limit)
+                    .into_boxed();
+                if let Some(before) = before {
+                    builder = builder.filter(RepoCommitSchema::rev.lt(before));
+                }
+                builder.load(conn)
+            })
+            .await?;
+        Ok(res)
+    }
+
     pub async fn delete_many(&self, cids: Vec<Cid>) -> Result<()> {
         if cids.is_empty() {
             return Ok(());
@@ -453,6 +743,9 @@ impl SqlRepoReader {
         use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
 
         let cid_strings: Vec<String> = cids.into_iter().map(|c| c.to_string()).collect();
+        if let Some(sqlite) = &self.sqlite {
+            return sqlite.write_blocks(vec![], cid_strings).await;
+        }
         db.run(move |conn| {
             delete(RepoBlockSchema::repo_block)
                 .filter(RepoBlockSchema::did.eq(did))
@@ -464,6 +757,12 @@ impl SqlRepoReader {
     }
 
     pub async fn get_root_detailed(&self) -> Result<CidAndRev> {
+        if let Some(sqlite) = &self.sqlite {
+            // files written before the root moved into them fall through to the mirror
+            if let Some(root) = sqlite.get_root().await? {
+                return Ok(root);
+            }
+        }
         let did: String = self.did.clone();
         let db: Arc<DbConn> = self.db.clone();
         use crate::schema::pds::repo_root::dsl as RepoRootSchema;
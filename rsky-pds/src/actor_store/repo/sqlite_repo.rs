@@ -0,0 +1,504 @@
+//! Repo blocks and commit history kept in one SQLite file per actor, the way the reference PDS
+//! lays out its repos, instead of the shared Postgres tables. Picked with
+//! `PDS_REPO_STORAGE_BACKEND=sqlite`; files live under `PDS_REPO_STORAGE_DIRECTORY` (default
+//! `./repos`) at `<first two hex chars of sha256(did)>/<did>/store.sqlite`.
+//!
+//! Only the repo moves: its blocks, its commit history and the root pointer, so a commit's new
+//! root and blocks land in one SQLite transaction. Everything else in the actor store, records,
+//! backlinks, blobs and preferences, stays in Postgres alongside the accounts, so Postgres is
+//! still required. It also keeps a mirror of the root, written after the SQLite commit, because
+//! `listRepos`, the admin routes and the viewer query those across every account. The mirror can
+//! trail the file after a crash between the two writes; reads of the repo's own root always come
+//! from the file. Switching backends doesn't move existing repos, so pick one before creating
+//! accounts.
+use crate::models;
+use anyhow::Result;
+use lazy_static::lazy_static;
+use lexicon_cid::Cid;
+use rsky_common::env::env_str;
+use rsky_repo::storage::CidAndRev;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS repo_block (
+    cid TEXT PRIMARY KEY,
+    repoRev TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    content BLOB NOT NULL
+);
+CREATE INDEX IF NOT EXISTS repo_block_repo_rev_idx ON repo_block (repoRev, cid);
+CREATE TABLE IF NOT EXISTS repo_commit (
+    rev TEXT PRIMARY KEY,
+    cid TEXT NOT NULL,
+    since TEXT,
+    creates INTEGER NOT NULL,
+    updates INTEGER NOT NULL,
+    deletes INTEGER NOT NULL,
+    committedAt TEXT NOT NULL
+);
+"#;
+
+// Stays under SQLite's default bind parameter limit
+const SELECT_CHUNK_SIZE: usize = 500;
+
+// Open connections kept around; past this, idle ones are closed
+const MAX_OPEN_CONNECTIONS: usize = 1_000;
+
+lazy_static! {
+    static ref DIRECTORY: Option<PathBuf> = match env_str("PDS_REPO_STORAGE_BACKEND").as_deref() {
+        Some("sqlite") => Some(PathBuf::from(
+            env_str("PDS_REPO_STORAGE_DIRECTORY").unwrap_or("./repos".to_string())
+        )),
+        _ => None,
+    };
+    static ref CONNECTIONS: Mutex<HashMap<PathBuf, Arc<Mutex<Connection>>>> =
+        Mutex::new(HashMap::new());
+}
+
+#[derive(Clone, Debug)]
+pub struct SqliteRepoStore {
+    pub did: String,
+    pub path: PathBuf,
+}
+
+impl SqliteRepoStore {
+    /// The actor's repo file, if the SQLite backend is configured.
+    pub fn for_did(did: &str) -> Option<Self> {
+        DIRECTORY
+            .as_ref()
+            .map(|directory| Self::in_directory(directory.clone(), did))
+    }
+
+    pub fn in_directory(directory: PathBuf, did: &str) -> Self {
+        let hash = hex::encode(Sha256::digest(did.as_bytes()));
+        SqliteRepoStore {
+            did: did.to_string(),
+            path: directory.join(&hash[..2]).join(did).join("store.sqlite"),
+        }
+    }
+
+    fn open(&self) -> Result<Connection> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(&self.path)?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(conn)
+    }
+
+    /// The actor's cached connection, opening it on first use.
+    fn connection(&self) -> Result<Arc<Mutex<Connection>>> {
+        let mut connections = CONNECTIONS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(conn) = connections.get(&self.path) {
+            return Ok(conn.clone());
+        }
+        if connections.len() >= MAX_OPEN_CONNECTIONS {
+            connections.retain(|_, conn| Arc::strong_count(conn) > 1);
+        }
+        let conn = Arc::new(Mutex::new(self.open()?));
+        connections.insert(self.path.clone(), conn.clone());
+        Ok(conn)
+    }
+
+    /// Runs `f` on the actor's connection off the async runtime.
+    async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let conn = self.connection()?;
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().unwrap_or_else(|e| e.into_inner());
+            f(&mut conn)
+        })
+        .await?
+    }
+
+    pub async fn get_block(&self, cid: String) -> Result<Option<Vec<u8>>> {
+        self.run(move |conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT content FROM repo_block WHERE cid = ?1",
+                    params![cid],
+                    |row| row.get(0),
+                )
+                .optional()?)
+        })
+        .await
+    }
+
+    pub async fn get_blocks(&self, cids: Vec<String>) -> Result<Vec<(String, Vec<u8>)>> {
+        self.run(move |conn| {
+            let mut found = Vec::with_capacity(cids.len());
+            for chunk in cids.chunks(SELECT_CHUNK_SIZE) {
+                let placeholders = vec!["?"; chunk.len()].join(", ");
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT cid, content FROM repo_block WHERE cid IN ({placeholders})"
+                ))?;
+                let rows = stmt.query_map(params_from_iter(chunk), |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })?;
+                for row in rows {
+                    found.push(row?);
+                }
+            }
+            Ok(found)
+        })
+        .await
+    }
+
+    /// Stores `blocks` and drops `removed` in one transaction.
+    pub async fn write_blocks(
+        &self,
+        blocks: Vec<models::RepoBlock>,
+        removed: Vec<String>,
+    ) -> Result<()> {
+        self.write_commit(None, blocks, removed).await
+    }
+
+    /// Points the repo at `root`.
+    pub async fn write_root(&self, root: models::RepoRoot) -> Result<()> {
+        self.write_commit(Some(root), vec![], vec![]).await
+    }
+
+    /// Moves the root, stores `blocks` and drops `removed` in one transaction, so the root never
+    /// points at blocks that weren't stored.
+    pub async fn write_commit(
+        &self,
+        root: Option<models::RepoRoot>,
+        blocks: Vec<models::RepoBlock>,
+        removed: Vec<String>,
+    ) -> Result<()> {
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            if let Some(root) = root {
+                tx.execute(
+                    "INSERT OR REPLACE INTO repo_root (id, cid, rev, indexedAt) VALUES (0, ?1, ?2, ?3)",
+                    params![root.cid, root.rev, root.indexed_at],
+                )?;
+            }
+            {
+                let mut insert = tx.prepare(
+                    "INSERT OR IGNORE INTO repo_block (cid, repoRev, size, content) VALUES (?1, ?2, ?3, ?4)",
+                )?;
+                for block in blocks {
+                    insert.execute(params![block.cid, block.repo_rev, block.size, block.content])?;
+                }
+                let mut delete = tx.prepare("DELETE FROM repo_block WHERE cid = ?1")?;
+                for cid in removed {
+                    delete.execute(params![cid])?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn get_root(&self) -> Result<Option<CidAndRev>> {
+        let row: Option<(String, String)> = self
+            .run(|conn| {
+                Ok(conn
+                    .query_row("SELECT cid, rev FROM repo_root", [], |row| {
+                        Ok((row.get(0)?, row.get(1)?))
+                    })
+                    .optional()?)
+            })
+            .await?;
+        match row {
+            Some((cid, rev)) => Ok(Some(CidAndRev {
+                cid: Cid::from_str(&cid)?,
+                rev,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Same paging as the Postgres `get_block_range`: newest rev first, 500 at a time.
+    pub async fn get_block_range(
+        &self,
+        since: Option<String>,
+        cursor: Option<CidAndRev>,
+    ) -> Result<Vec<models::RepoBlock>> {
+        let did = self.did.clone();
+        self.run(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT cid, repoRev, size, content FROM repo_block
+                 WHERE (?1 IS NULL OR (repoRev, cid) < (?1, ?2))
+                   AND (?3 IS NULL OR repoRev > ?3)
+                 ORDER BY repoRev DESC, cid DESC
+                 LIMIT 500",
+            )?;
+            let (cursor_rev, cursor_cid) = match cursor {
+                Some(cursor) => (Some(cursor.rev), Some(cursor.cid.to_string())),
+                None => (None, None),
+            };
+            let rows = stmt.query_map(params![cursor_rev, cursor_cid, since], |row| {
+                Ok(models::RepoBlock {
+                    cid: row.get(0)?,
+                    did: did.clone(),
+                    repo_rev: row.get(1)?,
+                    size: row.get(2)?,
+                    content: row.get(3)?,
+                })
+            })?;
+            Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+        })
+        .await
+    }
+
+    pub async fn count_blocks(&self) -> Result<i64> {
+        self.run(
+            |conn| Ok(conn.query_row("SELECT COUNT(*) FROM repo_block", [], |row| row.get(0))?),
+        )
+        .await
+    }
+
+    pub async fn get_blocks_for_rev(
+        &self,
+        rev: String,
+        limit: i64,
+    ) -> Result<Vec<(String, Vec<u8>)>> {
+        self.run(move |conn| {
+            let mut stmt =
+                conn.prepare("SELECT cid, content FROM repo_block WHERE repoRev = ?1 LIMIT ?2")?;
+            let rows = stmt.query_map(params![rev, limit], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+        })
+        .await
+    }
+
+    /// Appends `commit`, keeping only the newest `retain` entries.
+    pub async fn record_commit(&self, commit: models::RepoCommit, retain: i64) -> Result<()> {
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT OR IGNORE INTO repo_commit
+                 (rev, cid, since, creates, updates, deletes, committedAt)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    commit.rev,
+                    commit.cid,
+                    commit.since,
+                    commit.creates,
+                    commit.updates,
+                    commit.deletes,
+                    commit.committed_at
+                ],
+            )?;
+            tx.execute(
+                "DELETE FROM repo_commit WHERE rev <= (
+                    SELECT rev FROM repo_commit ORDER BY rev DESC LIMIT 1 OFFSET ?1
+                 )",
+                params![retain],
+            )?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn list_commits(
+        &self,
+        limit: i64,
+        before: Option<String>,
+    ) -> Result<Vec<models::RepoCommit>> {
+        let did = self.did.clone();
+        self.run(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT rev, cid, since, creates, updates, deletes, committedAt FROM repo_commit
+                 WHERE ?1 IS NULL OR rev < ?1
+                 ORDER BY rev DESC
+                 LIMIT ?2",
+            )?;
+            let rows = stmt.query_map(params![before, limit], |row| {
+                Ok(models::RepoCommit {
+                    did: did.clone(),
+                    rev: row.get(0)?,
+                    cid: row.get(1)?,
+                    since: row.get(2)?,
+                    creates: row.get(3)?,
+                    updates: row.get(4)?,
+                    deletes: row.get(5)?,
+                    committed_at: row.get(6)?,
+                })
+            })?;
+            Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+        })
+        .await
+    }
+
+    /// How many blocks and commits the store holds.
+    pub async fn counts(&self) -> Result<(i64, i64)> {
+        self.run(|conn| {
+            let blocks = conn.query_row("SELECT COUNT(*) FROM repo_block", [], |row| row.get(0))?;
+            let commits =
+                conn.query_row("SELECT COUNT(*) FROM repo_commit", [], |row| row.get(0))?;
+            Ok((blocks, commits))
+        })
+        .await
+    }
+
+    /// Removes the actor's store, returning how many blocks and commits it held.
+    pub async fn destroy(&self) -> Result<(usize, usize)> {
+        if !self.path.exists() {
+            return Ok((0, 0));
+        }
+        let (blocks, commits) = self.counts().await?;
+        CONNECTIONS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.path);
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::remove_dir_all(dir).await?;
+        }
+        Ok((blocks as usize, commits as usize))
+    }
+}
+
+/// Fills in record content for rows whose block wasn't in Postgres, which is every row when
+/// the SQLite backend is on. Rows with no block anywhere are dropped, as the inner join they
+/// replace would have done; the rest keep their order.
+pub async fn with_record_content(
+    rows: Vec<(models::Record, Option<models::RepoBlock>)>,
+) -> Result<Vec<(models::Record, Vec<u8>)>> {
+    let mut found: HashMap<(String, String), Vec<u8>> = HashMap::new();
+    if DIRECTORY.is_some() {
+        // queries may span repos, so look each one up in its own store
+        let mut missing: HashMap<String, Vec<String>> = HashMap::new();
+        for (record, _) in rows.iter().filter(|(_, block)| block.is_none()) {
+            missing
+                .entry(record.did.clone())
+                .or_default()
+                .push(record.cid.clone());
+        }
+        for (did, cids) in missing {
+            if let Some(store) = SqliteRepoStore::for_did(&did) {
+                for (cid, content) in store.get_blocks(cids).await? {
+                    found.insert((did.clone(), cid), content);
+                }
+            }
+        }
+    }
+    Ok(rows
+        .into_iter()
+        .filter_map(|(record, block)| match block {
+            Some(block) => Some((record, block.content)),
+            None => found
+                .remove(&(record.did.clone(), record.cid.clone()))
+                .map(|content| (record, content)),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(cid: &str, rev: &str) -> models::RepoBlock {
+        models::RepoBlock {
+            cid: cid.to_string(),
+            did: "did:example:alice".to_string(),
+            repo_rev: rev.to_string(),
+            size: 1,
+            content: cid.as_bytes().to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocks_and_commits_round_trip() {
+        let directory =
+            std::env::temp_dir().join(format!("rsky-pds-sqlite-{}", std::process::id()));
+        let store = SqliteRepoStore::in_directory(directory.clone(), "did:example:alice");
+
+        store
+            .write_blocks(
+                vec![block("a", "1"), block("b", "1"), block("c", "2")],
+                vec![],
+            )
+            .await
+            .unwrap();
+        store
+            .write_blocks(vec![], vec!["b".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(store.count_blocks().await.unwrap(), 2);
+        assert_eq!(
+            store.get_block("a".to_string()).await.unwrap(),
+            Some(b"a".to_vec())
+        );
+        assert_eq!(store.get_block("b".to_string()).await.unwrap(), None);
+        let range = store
+            .get_block_range(Some("1".to_string()), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            range.iter().map(|b| b.cid.as_str()).collect::<Vec<_>>(),
+            ["c"]
+        );
+
+        for rev in ["1", "2", "3"] {
+            let commit = models::RepoCommit {
+                did: store.did.clone(),
+                rev: rev.to_string(),
+                cid: format!("cid{rev}"),
+                since: None,
+                creates: 1,
+                updates: 0,
+                deletes: 0,
+                committed_at: rev.to_string(),
+            };
+            store.record_commit(commit, 2).await.unwrap();
+        }
+        let commits = store.list_commits(10, None).await.unwrap();
+        assert_eq!(
+            commits.iter().map(|c| c.rev.as_str()).collect::<Vec<_>>(),
+            ["3", "2"]
+        );
+
+        assert_eq!(store.destroy().await.unwrap(), (2, 2));
+        assert!(!store.path.exists());
+        let _ = std::fs::remove_dir_all(directory);
+    }
+
+    #[tokio::test]
+    async fn test_commit_moves_root_with_blocks() {
+        let directory =
+            std::env::temp_dir().join(format!("rsky-pds-sqlite-root-{}", std::process::id()));
+        let store = SqliteRepoStore::in_directory(directory.clone(), "did:example:bob");
+        let cid = "bafyreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm";
+        assert!(store.get_root().await.unwrap().is_none());
+
+        let root = models::RepoRoot {
+            did: store.did.clone(),
+            cid: cid.to_string(),
+            rev: "2".to_string(),
+            indexed_at: "now".to_string(),
+        };
+        store
+            .write_commit(Some(root), vec![block("c", "2")], vec![])
+            .await
+            .unwrap();
+        let found = store.get_root().await.unwrap().unwrap();
+        assert_eq!(
+            (found.cid.to_string().as_str(), found.rev.as_str()),
+            (cid, "2")
+        );
+        assert_eq!(store.count_blocks().await.unwrap(), 1);
+        assert!(Arc::ptr_eq(
+            &store.connection().unwrap(),
+            &store.connection().unwrap()
+        ));
+
+        store.destroy().await.unwrap();
+        let _ = std::fs::remove_dir_all(directory);
+    }
+}
@@ -1,7 +1,31 @@
 use lexicon_cid::Cid;
 use rsky_repo::block_map::BlockMap;
+use rsky_repo::types::PreparedWrite;
 pub struct SyncEvtData {
     pub cid: Cid,
     pub rev: String,
     pub blocks: BlockMap,
 }
+
+/// Number of record ops of each kind in a commit, kept in the commit history.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CommitOpCounts {
+    pub creates: i32,
+    pub updates: i32,
+    pub deletes: i32,
+}
+
+impl CommitOpCounts {
+    pub fn from_writes(writes: &[PreparedWrite]) -> Self {
+        writes
+            .iter()
+            .fold(CommitOpCounts::default(), |mut counts, write| {
+                match write {
+                    PreparedWrite::Create(_) => counts.creates += 1,
+                    PreparedWrite::Update(_) => counts.updates += 1,
+                    PreparedWrite::Delete(_) => counts.deletes += 1,
+                }
+                counts
+            })
+    }
+}
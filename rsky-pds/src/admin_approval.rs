@@ -0,0 +1,280 @@
+//! Guard rails for destructive admin actions: account deletion and takedowns. The caller has to
+//! repeat the subject in `confirm` and say why in `justification`, which is logged with who they
+//! are. With `PDS_ADMIN_DUAL_APPROVAL_SECS` set, the first confirmation only records the action
+//! in `pds.admin_approval`; it is carried out once a different moderator confirms it within that
+//! many seconds.
+//!
+//! Moderators are told apart by their client certificate's common name when admin mTLS is on and
+//! by the labeler's DID for mod service calls. The name given in `moderator` is only logged, since
+//! anyone holding the admin password can claim it, so dual approval won't count a confirmation
+//! without one of the other two and the server won't start with it unless one is configured.
+
+use crate::apis::ApiError;
+use crate::auth_verifier::AccessOutput;
+use crate::config::ServerConfig;
+use crate::db::DbConn;
+use crate::models::AdminApproval;
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use diesel::*;
+use rocket::mtls::Certificate;
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+use rsky_common::RFC3339_VARIANT;
+use std::convert::Infallible;
+
+pub const ACTION_DELETE_ACCOUNT: &str = "deleteAccount";
+pub const ACTION_TAKEDOWN: &str = "takedown";
+
+/// The `confirm`, `justification` and `moderator` query parameters of an admin request.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AdminConfirmation {
+    pub confirm: Option<String>,
+    pub justification: Option<String>,
+    pub moderator: Option<String>,
+    /// Common name of the admin client certificate, when admin mTLS is on.
+    pub cert_name: Option<String>,
+    /// `PDS_ADMIN_DUAL_APPROVAL_SECS`.
+    pub dual_approval_window: Option<u64>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminConfirmation {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let param = |name: &str| {
+            req.query_value::<String>(name)
+                .and_then(|value| value.ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let cert_name = match req.guard::<Certificate<'_>>().await {
+            Outcome::Success(cert) => cert.subject().common_name().map(str::to_string),
+            _ => None,
+        };
+        Outcome::Success(AdminConfirmation {
+            confirm: param("confirm"),
+            justification: param("justification"),
+            moderator: param("moderator"),
+            cert_name,
+            dual_approval_window: req
+                .rocket()
+                .state::<ServerConfig>()
+                .and_then(|cfg| cfg.admin.dual_approval_window),
+        })
+    }
+}
+
+impl AdminConfirmation {
+    /// Fails unless `confirm` repeats `subject` and a justification was given.
+    pub fn check(&self, subject: &str) -> Result<&str, ApiError> {
+        if self.confirm.as_deref() != Some(subject) {
+            return Err(ApiError::InvalidRequest(format!(
+                "confirm must repeat the subject, {subject}"
+            )));
+        }
+        match &self.justification {
+            Some(justification) => Ok(justification),
+            None => Err(ApiError::InvalidRequest(
+                "justification is required".to_string(),
+            )),
+        }
+    }
+
+    /// Who is acting, for the log.
+    pub fn moderator(&self, access: &AccessOutput) -> String {
+        self.verified_moderator(access)
+            .or_else(|| self.moderator.clone())
+            .unwrap_or_else(|| "admin".to_string())
+    }
+
+    /// Who is acting, when the server vouches for it rather than the caller, for telling the
+    /// two approvers apart.
+    pub fn verified_moderator(&self, access: &AccessOutput) -> Option<String> {
+        access
+            .credentials
+            .as_ref()
+            .and_then(|credentials| credentials.iss.clone())
+            .or_else(|| self.cert_name.clone())
+    }
+}
+
+enum Approval {
+    /// A different moderator had asked for the same action.
+    Approved(AdminApproval),
+    /// Recorded, waiting on a second moderator until the given time.
+    Pending(String),
+    /// The moderator that asked for it is confirming it again.
+    SameModerator,
+}
+
+/// Checks the confirmation of `action` on `subject`. Ok means go ahead; under dual approval the
+/// first confirmation fails with `ApprovalPending` instead.
+pub async fn approve(
+    action: &str,
+    subject: &str,
+    confirmation: &AdminConfirmation,
+    access: &AccessOutput,
+    db: &DbConn,
+) -> Result<(), ApiError> {
+    let justification = confirmation.check(subject)?;
+    let moderator = confirmation.moderator(access);
+    tracing::warn!("@LOG: admin {action} of {subject} confirmed by {moderator}: {justification}");
+    let Some(window) = confirmation.dual_approval_window else {
+        return Ok(());
+    };
+    let Some(moderator) = confirmation.verified_moderator(access) else {
+        return Err(ApiError::InvalidRequest(
+            "dual approval needs a moderator identified by an admin client certificate or the mod service".to_string(),
+        ));
+    };
+
+    let request = AdminApproval {
+        action: action.to_string(),
+        subject: subject.to_string(),
+        requested_by: moderator.clone(),
+        justification: justification.to_string(),
+        created_at: rsky_common::now(),
+        expires_at: (Utc::now() + Duration::seconds(window as i64))
+            .format(RFC3339_VARIANT)
+            .to_string(),
+    };
+    match record_approval(request, db).await {
+        Ok(Approval::Approved(first)) => {
+            tracing::warn!(
+                "@LOG: admin {action} of {subject} requested by {} ({}) approved by {moderator}",
+                first.requested_by,
+                first.justification
+            );
+            Ok(())
+        }
+        Ok(Approval::Pending(expires_at)) => Err(ApiError::BadRequest(
+            "ApprovalPending".to_string(),
+            format!(
+                "{action} of {subject} needs a second moderator to confirm it before {expires_at}"
+            ),
+        )),
+        Ok(Approval::SameModerator) => Err(ApiError::BadRequest(
+            "ApprovalPending".to_string(),
+            format!(
+                "{action} of {subject} must be confirmed by a moderator other than {moderator}"
+            ),
+        )),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: failed to record admin approval: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
+
+/// Completes a pending approval of the same action by another moderator, or records `request`
+/// as pending when there is none.
+async fn record_approval(request: AdminApproval, db: &DbConn) -> Result<Approval> {
+    use crate::schema::pds::admin_approval::dsl as AdminApprovalSchema;
+
+    let now = rsky_common::now();
+    let approval = db
+        .run(move |conn| {
+            conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                delete(AdminApprovalSchema::admin_approval)
+                    .filter(AdminApprovalSchema::expiresAt.lt(&now))
+                    .execute(conn)?;
+                let pending = AdminApprovalSchema::admin_approval
+                    .find((&request.action, &request.subject))
+                    .select(AdminApproval::as_select())
+                    .for_update()
+                    .first(conn)
+                    .optional()?;
+                match pending {
+                    None => {
+                        let expires_at = request.expires_at.clone();
+                        insert_into(AdminApprovalSchema::admin_approval)
+                            .values(&request)
+                            .on_conflict_do_nothing()
+                            .execute(conn)?;
+                        Ok(Approval::Pending(expires_at))
+                    }
+                    Some(pending) if pending.requested_by == request.requested_by => {
+                        Ok(Approval::SameModerator)
+                    }
+                    Some(pending) => {
+                        delete(
+                            AdminApprovalSchema::admin_approval
+                                .find((&pending.action, &pending.subject)),
+                        )
+                        .execute(conn)?;
+                        Ok(Approval::Approved(pending))
+                    }
+                }
+            })
+        })
+        .await?;
+    Ok(approval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth_verifier::Credentials;
+
+    fn confirmation(confirm: Option<&str>, justification: Option<&str>) -> AdminConfirmation {
+        AdminConfirmation {
+            confirm: confirm.map(str::to_string),
+            justification: justification.map(str::to_string),
+            moderator: Some("alice".to_string()),
+            cert_name: None,
+            dual_approval_window: None,
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let did = "did:plc:abc";
+        assert_eq!(
+            confirmation(Some(did), Some("spam")).check(did).ok(),
+            Some("spam")
+        );
+        assert!(confirmation(None, Some("spam")).check(did).is_err());
+        assert!(confirmation(Some("did:plc:xyz"), Some("spam"))
+            .check(did)
+            .is_err());
+        assert!(confirmation(Some(did), None).check(did).is_err());
+    }
+
+    #[test]
+    fn test_moderator() {
+        let admin = AccessOutput {
+            credentials: Some(Credentials {
+                r#type: "admin_token".to_string(),
+                did: None,
+                scope: None,
+                audience: None,
+                token_id: None,
+                aud: None,
+                iss: None,
+                is_privileged: None,
+            }),
+            artifacts: None,
+        };
+        let mut confirmation = confirmation(None, None);
+        assert_eq!(confirmation.moderator(&admin), "alice");
+        assert_eq!(confirmation.verified_moderator(&admin), None);
+        assert_eq!(AdminConfirmation::default().moderator(&admin), "admin");
+
+        confirmation.cert_name = Some("bob".to_string());
+        assert_eq!(confirmation.moderator(&admin), "bob");
+        assert_eq!(
+            confirmation.verified_moderator(&admin).as_deref(),
+            Some("bob")
+        );
+
+        let mut mod_service = admin.clone();
+        mod_service.credentials.as_mut().unwrap().iss = Some("did:plc:labeler".to_string());
+        assert_eq!(confirmation.moderator(&mod_service), "did:plc:labeler");
+        assert_eq!(
+            confirmation.verified_moderator(&mod_service).as_deref(),
+            Some("did:plc:labeler")
+        );
+    }
+}
@@ -42,7 +42,7 @@ pub async fn get_preferences(
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
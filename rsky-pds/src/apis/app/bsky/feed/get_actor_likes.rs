@@ -87,7 +87,7 @@ pub async fn get_actor_likes(
             Ok(response) => Ok(response),
             Err(error) => {
                 tracing::error!("{error}");
-                Err(ApiError::RuntimeError)
+                Err(error.into())
             }
         },
     }
@@ -1,6 +1,8 @@
+use crate::actor_store::record::value_index::RecordValueQuery;
 use crate::apis::ApiError;
 use crate::auth_verifier::{AccessOutput, AccessStandard};
 use crate::config::ServerConfig;
+use crate::db::DbConn;
 use crate::pipethrough::{pipethrough, OverrideOpts, ProxyRequest};
 use crate::read_after_write::util::ReadAfterWriteResponse;
 use crate::xrpc_server::types::{HandlerPipeThrough, InvalidRequestError};
@@ -16,6 +18,7 @@ use rocket::request::{FromRequest, Outcome, Request};
 use rocket::State;
 use rsky_lexicon::app::bsky::feed::AuthorFeed;
 use rsky_repo::types::Ids;
+use rsky_syntax::aturi::AtUri;
 use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, PartialEq, Serialize)]
@@ -65,86 +68,60 @@ impl<'r> FromRequest<'r> for GetFeedPipeThrough {
                 }
                 match req.query_value::<String>("feed") {
                     Some(Ok(feed)) => {
-                        let app_view_agent = req.guard::<&State<SharedATPAgent>>().await.unwrap();
-                        let cfg = req.guard::<&State<ServerConfig>>().await.unwrap();
-                        match (&cfg.bsky_app_view, &app_view_agent.app_view_agent) {
-                            (Some(_), Some(app_view_agent_unwrapped)) => {
-                                let lock = app_view_agent_unwrapped.read().await;
-                                let AppBskyFeedGetFeedGeneratorOutput { data, .. } = match lock
-                                    .service
-                                    .app
-                                    .bsky
-                                    .feed
-                                    .get_feed_generator(AppBskyFeedGetFeedGeneratorParams {
-                                        data: AppBskyFeedGetFeedGeneratorData { feed },
-                                        extra_data: AtriumIpld::Null,
-                                    })
-                                    .await
-                                {
-                                    Ok(res) => res,
-                                    Err(error) => {
-                                        req.local_cache(|| {
-                                            Some(ApiError::InvalidRequest(error.to_string()))
-                                        });
-                                        return Outcome::Error((
-                                            Status::BadRequest,
-                                            anyhow!(error.to_string()),
-                                        ));
-                                    }
-                                };
-                                let headers = req.headers().clone().into_iter().fold(
-                                    BTreeMap::new(),
-                                    |mut acc: BTreeMap<String, String>, cur| {
-                                        let _ = acc.insert(
-                                            cur.name().to_string(),
-                                            cur.value().to_string(),
-                                        );
-                                        acc
-                                    },
-                                );
-                                let proxy_req = ProxyRequest {
-                                    headers,
-                                    query: match req.uri().query() {
-                                        None => None,
-                                        Some(query) => Some(query.to_string()),
-                                    },
-                                    path: req.uri().path().to_string(),
-                                    method: req.method(),
-                                    id_resolver: req
-                                        .guard::<&State<SharedIdResolver>>()
-                                        .await
-                                        .unwrap(),
-                                    cfg: req.guard::<&State<ServerConfig>>().await.unwrap(),
-                                };
-                                match pipethrough(
-                                    &proxy_req,
-                                    requester,
-                                    OverrideOpts {
-                                        aud: Some(data.view.did.to_string()),
-                                        lxm: Some(
-                                            Ids::AppBskyFeedGetFeedSkeleton.as_str().to_string(),
-                                        ),
-                                    },
-                                )
-                                .await
-                                {
-                                    Ok(res) => Outcome::Success(Self {
-                                        encoding: res.encoding,
-                                        buffer: res.buffer,
-                                        headers: res.headers,
-                                    }),
-                                    Err(error) => {
-                                        req.local_cache(|| {
-                                            Some(ApiError::InvalidRequest(error.to_string()))
-                                        });
-                                        Outcome::Error((Status::BadRequest, error))
-                                    }
-                                }
+                        let aud = match feed_generator_did(req, feed).await {
+                            Ok(Some(aud)) => aud,
+                            Ok(None) => {
+                                return Outcome::Error((
+                                    Status::InternalServerError,
+                                    anyhow!("internal error"),
+                                ))
+                            }
+                            Err(error) => {
+                                req.local_cache(|| {
+                                    Some(ApiError::InvalidRequest(error.to_string()))
+                                });
+                                return Outcome::Error((Status::BadRequest, error));
+                            }
+                        };
+                        let headers = req.headers().clone().into_iter().fold(
+                            BTreeMap::new(),
+                            |mut acc: BTreeMap<String, String>, cur| {
+                                let _ = acc.insert(cur.name().to_string(), cur.value().to_string());
+                                acc
+                            },
+                        );
+                        let proxy_req = ProxyRequest {
+                            headers,
+                            query: match req.uri().query() {
+                                None => None,
+                                Some(query) => Some(query.to_string()),
+                            },
+                            path: req.uri().path().to_string(),
+                            method: req.method(),
+                            id_resolver: req.guard::<&State<SharedIdResolver>>().await.unwrap(),
+                            cfg: req.guard::<&State<ServerConfig>>().await.unwrap(),
+                        };
+                        match pipethrough(
+                            &proxy_req,
+                            requester,
+                            OverrideOpts {
+                                aud: Some(aud),
+                                lxm: Some(Ids::AppBskyFeedGetFeedSkeleton.as_str().to_string()),
+                            },
+                        )
+                        .await
+                        {
+                            Ok(res) => Outcome::Success(Self {
+                                encoding: res.encoding,
+                                buffer: res.buffer,
+                                headers: res.headers,
+                            }),
+                            Err(error) => {
+                                req.local_cache(|| {
+                                    Some(ApiError::InvalidRequest(error.to_string()))
+                                });
+                                Outcome::Error((Status::BadRequest, error))
                             }
-                            _ => Outcome::Error((
-                                Status::InternalServerError,
-                                anyhow!("internal error"),
-                            )),
                         }
                     }
                     _ => {
@@ -167,6 +144,57 @@ impl<'r> FromRequest<'r> for GetFeedPipeThrough {
     }
 }
 
+/// The service DID a feed generator is served from, or `None` when it isn't declared here and
+/// there's no app view to ask.
+async fn feed_generator_did(req: &Request<'_>, feed: String) -> Result<Option<String>> {
+    if let Outcome::Success(db) = req.guard::<DbConn>().await {
+        if let Some(did) = local_feed_generator_did(&feed, &db).await? {
+            return Ok(Some(did));
+        }
+    }
+    let app_view_agent = req.guard::<&State<SharedATPAgent>>().await.unwrap();
+    let cfg = req.guard::<&State<ServerConfig>>().await.unwrap();
+    let (Some(_), Some(app_view_agent)) = (&cfg.bsky_app_view, &app_view_agent.app_view_agent)
+    else {
+        return Ok(None);
+    };
+    let lock = app_view_agent.read().await;
+    let AppBskyFeedGetFeedGeneratorOutput { data, .. } = lock
+        .service
+        .app
+        .bsky
+        .feed
+        .get_feed_generator(AppBskyFeedGetFeedGeneratorParams {
+            data: AppBskyFeedGetFeedGeneratorData { feed },
+            extra_data: AtriumIpld::Null,
+        })
+        .await
+        .map_err(|error| anyhow!(error.to_string()))?;
+    Ok(Some(data.view.did.to_string()))
+}
+
+/// The service DID from a feed generator declaration by an account on this PDS, looked up in
+/// the record value index.
+async fn local_feed_generator_did(feed: &str, db: &DbConn) -> Result<Option<String>> {
+    let uri = AtUri::new(feed.to_string(), None)?;
+    if uri.get_collection() != Ids::AppBskyFeedGenerator.as_str() {
+        return Ok(None);
+    }
+    let records = RecordValueQuery::new(uri.get_collection())
+        .did(uri.get_hostname().as_str())
+        .rkey(uri.get_rkey())
+        .limit(1)
+        .execute(db)
+        .await?;
+    match records.into_iter().next() {
+        None => Ok(None),
+        Some(record) => Ok(serde_json::to_value(record.value)?
+            .get("did")
+            .and_then(|did| did.as_str())
+            .map(|did| did.to_string())),
+    }
+}
+
 /// Get a hydrated feed from an actor's selected feed generator. Implemented by App View.
 #[tracing::instrument(skip_all)]
 #[allow(unused_variables)]
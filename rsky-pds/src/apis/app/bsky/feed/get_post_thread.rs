@@ -5,6 +5,7 @@ use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandard;
 use crate::config::ServerConfig;
 use crate::db::DbConn;
+use crate::http::{self, Destination};
 use crate::models::{ErrorCode, ErrorMessageResponse};
 use crate::read_after_write::types::{LocalRecords, RecordDescript};
 use crate::read_after_write::util::{
@@ -13,17 +14,10 @@ use crate::read_after_write::util::{
 };
 use crate::read_after_write::viewer::LocalViewer;
 use crate::xrpc_server::types::{HandlerPipeThrough, InvalidRequestError, XRPCError};
-use crate::{SharedLocalViewer, APP_USER_AGENT};
-use anyhow::{anyhow, Result};
-use atrium_api::app::bsky::feed::get_post_thread::{
-    Parameters as AppBskyFeedGetPostThreadParams, ParametersData as AppBskyFeedGetPostThreadData,
-};
-use atrium_api::client::AtpServiceClient;
-use atrium_api::types::LimitedU16;
-use atrium_xrpc_client::reqwest::ReqwestClientBuilder;
+use crate::SharedLocalViewer;
+use anyhow::Result;
 use aws_config::SdkConfig;
 use futures::stream::{self, StreamExt};
-use ipld_core::ipld::Ipld as AtriumIpld;
 use reqwest::header::HeaderMap;
 use rocket::State;
 use rsky_lexicon::app::bsky::feed::Post;
@@ -414,55 +408,33 @@ pub async fn read_after_write_not_found(
                                                 let headers = cfg
                                                     .appview_auth_headers(&requester, &nsid)
                                                     .await?;
-                                                let client = ReqwestClientBuilder::new(
-                                                    bsky_app_view.url.clone(),
-                                                )
-                                                .client(
-                                                    reqwest::ClientBuilder::new()
-                                                        .user_agent(APP_USER_AGENT)
-                                                        .timeout(std::time::Duration::from_millis(
-                                                            1000,
-                                                        ))
-                                                        .default_headers(headers)
-                                                        .build()
-                                                        .unwrap(),
-                                                )
-                                                .build();
-                                                let agent = AtpServiceClient::new(client);
-                                                match agent
-                                                    .service
-                                                    .app
-                                                    .bsky
-                                                    .feed
-                                                    .get_post_thread(
-                                                        AppBskyFeedGetPostThreadParams {
-                                                            data: AppBskyFeedGetPostThreadData {
-                                                                depth: Some(
-                                                                    LimitedU16::try_from(0)
-                                                                        .map_err(|e| anyhow!(e))?,
-                                                                ),
-                                                                parent_height: Some(
-                                                                    LimitedU16::try_from(
-                                                                        parentHeight,
-                                                                    )
-                                                                    .map_err(|e| anyhow!(e))?,
-                                                                ),
-                                                                uri: highest_parent,
-                                                            },
-                                                            extra_data: AtriumIpld::Null,
-                                                        },
-                                                    )
-                                                    .await
-                                                {
+                                                let url =
+                                                    format!("{}/xrpc/{nsid}", bsky_app_view.url);
+                                                let parents_res =
+                                                    http::get(Destination::AppView, url)
+                                                        .headers(headers)
+                                                        .query(&[
+                                                            ("depth", "0".to_string()),
+                                                            (
+                                                                "parentHeight",
+                                                                parentHeight.to_string(),
+                                                            ),
+                                                            ("uri", highest_parent),
+                                                        ])
+                                                        .send()
+                                                        .await
+                                                        .and_then(|res| res.error_for_status());
+                                                let parents = match parents_res {
+                                                    Ok(res) => {
+                                                        res.json::<GetPostThreadOutput>().await
+                                                    }
+                                                    Err(error) => Err(error),
+                                                };
+                                                match parents {
                                                     Err(_) => (),
-                                                    Ok(parents_res) => {
-                                                        let val = serde_json::to_value(
-                                                            parents_res.data.thread,
-                                                        )
-                                                        .expect(
-                                                            "Couldn't serialize atrium to value.",
-                                                        );
-                                                        thread.parent = Some(Box::new(serde_json::from_value::<ThreadViewPostEnum>(val).expect("Couldn't deserialize from atrium to value to ThreadViewPostEnum.")));
+                                                    Ok(parents) => {
+                                                        thread.parent =
+                                                            Some(Box::new(parents.thread));
                                                     }
                                                 }
                                             }
@@ -1,104 +1,87 @@
+use crate::account_manager::helpers::push_registration::RegisterPushOpts;
+use crate::account_manager::AccountManager;
 use crate::apis::app::bsky::util::get_did_doc;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandardSignupQueued;
 use crate::config::ServerConfig;
-use crate::{context, SharedIdResolver, APP_USER_AGENT};
+use crate::http::{self, Destination};
+use crate::{context, SharedIdResolver};
 use anyhow::{anyhow, bail, Result};
-use atrium_api::app::bsky::notification::register_push::{
-    Input as AppBskyNotificationRegisterPushInput, InputData as AppBskyNotificationRegisterPushData,
-};
-use atrium_api::client::AtpServiceClient;
+use atrium_api::app::bsky::notification::register_push::InputData as AppBskyNotificationRegisterPushData;
 use atrium_api::types::string::Did;
-use atrium_xrpc_client::reqwest::ReqwestClientBuilder;
-use ipld_core::ipld::Ipld as AtriumIpld;
+use reqwest::header::HeaderMap;
 use rocket::serde::json::Json;
 use rocket::State;
 use rsky_common::get_notif_endpoint;
 use rsky_lexicon::app::bsky::notification::RegisterPushInput;
 use rsky_repo::types::Ids;
 
+const PUSH_PLATFORMS: [&str; 3] = ["ios", "android", "web"];
+
+/// Forward the registration to the notification service named by `serviceDid`, then remember it
+/// locally so the account's devices are known to this PDS.
 pub async fn inner_register_push(
     body: Json<RegisterPushInput>,
     auth: AccessStandardSignupQueued,
     cfg: &State<ServerConfig>,
-    app_view_url: String,
     id_resolver: &State<SharedIdResolver>,
-) -> Result<()> {
+    account_manager: AccountManager,
+) -> Result<(), ApiError> {
     let RegisterPushInput {
         service_did,
         token,
         platform,
         app_id,
     } = body.into_inner();
-    let did: String = match auth.access.credentials {
-        None => "".to_string(),
-        Some(credentials) => credentials.did.unwrap_or("".to_string()),
-    };
-    let nsid = Ids::AppBskyFeedGetFeedGenerator.as_str().to_string();
+    if !PUSH_PLATFORMS.contains(&platform.as_str()) {
+        return Err(ApiError::InvalidRequest("invalid platform".to_string()));
+    }
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let nsid = Ids::AppBskyNotificationRegisterPush.as_str().to_string();
     let auth_headers = context::service_auth_headers(&did, &service_did, &nsid).await?;
 
-    let client = ReqwestClientBuilder::new(app_view_url)
-        .client(
-            reqwest::ClientBuilder::new()
-                .user_agent(APP_USER_AGENT)
-                .timeout(std::time::Duration::from_millis(1000))
-                .default_headers(auth_headers.clone())
-                .build()
-                .unwrap(),
-        )
-        .build();
-    let agent = AtpServiceClient::new(client);
+    // the configured appview is the usual target and saves resolving its did doc
+    let endpoint = match &cfg.bsky_app_view {
+        Some(bsky_app_view) if bsky_app_view.did == service_did => bsky_app_view.url.clone(),
+        _ => get_endpoint(id_resolver, service_did.clone()).await?,
+    };
+    let data = AppBskyNotificationRegisterPushData {
+        app_id: app_id.clone(),
+        platform: platform.clone(),
+        service_did: Did::new(service_did.clone())
+            .map_err(|error| ApiError::InvalidRequest(error.to_string()))?,
+        token: token.clone(),
+    };
+    send_register_push(endpoint, auth_headers, data).await?;
 
-    if let Some(ref bsky_app_view) = cfg.bsky_app_view {
-        if bsky_app_view.did == service_did {
-            agent
-                .service
-                .app
-                .bsky
-                .notification
-                .register_push(AppBskyNotificationRegisterPushInput {
-                    data: AppBskyNotificationRegisterPushData {
-                        app_id,
-                        platform,
-                        service_did: Did::new(service_did).map_err(|e| anyhow!(e))?,
-                        token,
-                    },
-                    extra_data: AtriumIpld::Null,
-                })
-                .await?;
-            return Ok(());
-        }
-    }
-    let notif_endpoint = get_endpoint(id_resolver, service_did.clone()).await?;
-    let client = ReqwestClientBuilder::new(notif_endpoint)
-        .client(
-            reqwest::ClientBuilder::new()
-                .user_agent(APP_USER_AGENT)
-                .timeout(std::time::Duration::from_millis(1000))
-                .default_headers(auth_headers)
-                .build()
-                .unwrap(),
-        )
-        .build();
-    let agent = AtpServiceClient::new(client);
-    agent
-        .service
-        .app
-        .bsky
-        .notification
-        .register_push(AppBskyNotificationRegisterPushInput {
-            data: AppBskyNotificationRegisterPushData {
-                app_id,
-                platform,
-                service_did: Did::new(service_did).map_err(|e| anyhow!(e))?,
-                token,
-            },
-            extra_data: AtriumIpld::Null,
+    account_manager
+        .register_push(RegisterPushOpts {
+            did,
+            service_did,
+            token,
+            platform,
+            app_id,
         })
         .await?;
     Ok(())
 }
 
+async fn send_register_push(
+    endpoint: String,
+    auth_headers: HeaderMap,
+    data: AppBskyNotificationRegisterPushData,
+) -> Result<()> {
+    let nsid = Ids::AppBskyNotificationRegisterPush.as_str();
+    http::post(Destination::AppView, format!("{endpoint}/xrpc/{nsid}"))
+        .headers(auth_headers)
+        .json(&data)
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(|error| anyhow!("failed to register push with notification service: {error}"))?;
+    Ok(())
+}
+
 #[tracing::instrument(skip_all)]
 #[rocket::post(
     "/xrpc/app.bsky.notification.registerPush",
@@ -110,20 +93,13 @@ pub async fn register_push(
     auth: AccessStandardSignupQueued,
     cfg: &State<ServerConfig>,
     id_resolver: &State<SharedIdResolver>,
+    account_manager: AccountManager,
 ) -> Result<(), ApiError> {
-    if !["ios", "android", "web"].contains(&body.platform.as_str()) {
-        return Err(ApiError::InvalidRequest("invalid platform".to_string()));
-    }
-    match &cfg.bsky_app_view {
-        None => return Err(ApiError::RuntimeError),
-        Some(bsky_app_view) => {
-            match inner_register_push(body, auth, cfg, bsky_app_view.url.clone(), id_resolver).await
-            {
-                Ok(_) => Ok(()),
-                Err(_) => {
-                    return Err(ApiError::RuntimeError);
-                }
-            }
+    match inner_register_push(body, auth, cfg, id_resolver, account_manager).await {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error)
         }
     }
 }
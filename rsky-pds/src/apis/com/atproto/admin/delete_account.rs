@@ -1,11 +1,12 @@
-use crate::account_manager::helpers::account::AccountStatus;
+use crate::account_manager::helpers::account::AccountStatusTransition;
 use crate::account_manager::AccountManager;
 use crate::actor_store::aws::s3::S3BlobStore;
 use crate::actor_store::ActorStore;
+use crate::admin_approval::{self, AdminConfirmation, ACTION_DELETE_ACCOUNT};
 use crate::apis::ApiError;
 use crate::auth_verifier::AdminToken;
 use crate::db::DbConn;
-use crate::{sequencer, SharedSequencer};
+use crate::SharedSequencer;
 use anyhow::Result;
 use aws_config::SdkConfig;
 use rocket::serde::json::Json;
@@ -16,24 +17,32 @@ async fn inner_delete_account(
     body: Json<DeleteAccountInput>,
     sequencer: &State<SharedSequencer>,
     s3_config: &State<SdkConfig>,
+    confirmation: AdminConfirmation,
+    auth: AdminToken,
     db: DbConn,
     account_manager: AccountManager,
 ) -> Result<()> {
     let DeleteAccountInput { did } = body.into_inner();
+    admin_approval::approve(
+        ACTION_DELETE_ACCOUNT,
+        &did,
+        &confirmation,
+        &auth.access,
+        &db,
+    )
+    .await?;
 
     let mut actor_store =
         ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
     actor_store.destroy().await?;
-    account_manager.delete_account(&did).await?;
-    let mut lock = sequencer.sequencer.write().await;
-    let account_seq = lock
-        .sequence_account_evt(did.clone(), AccountStatus::Deleted)
+    account_manager
+        .update_account_status(&did, vec![AccountStatusTransition::Delete], sequencer)
         .await?;
-
-    sequencer::delete_all_for_user(&did, Some(vec![account_seq])).await?;
     Ok(())
 }
 
+/// Needs `confirm` set to the account's DID and a `justification`, and under dual approval a
+/// second moderator's confirmation too.
 #[tracing::instrument(skip_all)]
 #[rocket::post(
     "/xrpc/com.atproto.admin.deleteAccount",
@@ -44,15 +53,26 @@ pub async fn delete_account(
     body: Json<DeleteAccountInput>,
     sequencer: &State<SharedSequencer>,
     s3_config: &State<SdkConfig>,
-    _auth: AdminToken,
+    confirmation: AdminConfirmation,
+    auth: AdminToken,
     db: DbConn,
     account_manager: AccountManager,
 ) -> Result<(), ApiError> {
-    match inner_delete_account(body, sequencer, s3_config, db, account_manager).await {
+    match inner_delete_account(
+        body,
+        sequencer,
+        s3_config,
+        confirmation,
+        auth,
+        db,
+        account_manager,
+    )
+    .await
+    {
         Ok(_) => Ok(()),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
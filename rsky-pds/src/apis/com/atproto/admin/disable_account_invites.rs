@@ -24,7 +24,7 @@ pub async fn disable_account_invites(
         Ok(_) => Ok(()),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -37,7 +37,7 @@ pub async fn disable_invite_codes(
         Ok(_) => Ok(()),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -1,8 +1,10 @@
 use crate::account_manager::helpers::account::AvailabilityFlags;
+use crate::account_manager::helpers::moderation::{AuditEventOpts, AUDIT_ACTION_EMAIL};
 use crate::account_manager::AccountManager;
 use crate::apis::ApiError;
 use crate::auth_verifier::Moderator;
 use crate::mailer::moderation::{HtmlMailOpts, ModerationMailer};
+use crate::mailer::EmailCategory;
 use anyhow::{bail, Result};
 use rocket::serde::json::Json;
 use rsky_lexicon::com::atproto::admin::{SendMailInput, SendMailOutput};
@@ -15,7 +17,8 @@ async fn inner_send_email(
         content,
         recipient_did,
         subject,
-        ..
+        sender_did,
+        comment,
     } = body.into_inner();
     let subject = subject.unwrap_or("Message via your PDS".to_string());
 
@@ -34,12 +37,30 @@ async fn inner_send_email(
         Some(account) => match account.email {
             None => bail!("account does not have an email address"),
             Some(email) => {
+                if !account_manager
+                    .wants_email(&recipient_did, EmailCategory::Notification)
+                    .await?
+                {
+                    return Ok(SendMailOutput { sent: false });
+                }
                 ModerationMailer::send_html(HtmlMailOpts {
                     to: email,
-                    subject,
+                    subject: subject.clone(),
                     html: content,
                 })
                 .await?;
+                account_manager
+                    .record_audit_event(AuditEventOpts {
+                        subject_did: recipient_did.clone(),
+                        action: AUDIT_ACTION_EMAIL,
+                        rule: subject,
+                        report_count: 0,
+                        expires_at: None,
+                        created_by: Some(sender_did),
+                        comment,
+                    })
+                    .await?;
+                tracing::info!("@LOG: moderation email sent to {recipient_did}");
 
                 Ok(SendMailOutput { sent: true })
             }
@@ -58,7 +79,7 @@ pub async fn send_email(
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
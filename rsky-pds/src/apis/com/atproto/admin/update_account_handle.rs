@@ -1,9 +1,9 @@
 use crate::account_manager::helpers::account::AvailabilityFlags;
 use crate::account_manager::AccountManager;
-use crate::apis::com::atproto::server::get_keys_from_private_key_str;
 use crate::apis::ApiError;
 use crate::auth_verifier::AdminToken;
-use crate::config::ServerConfig;
+use crate::config::{keys, ServerConfig};
+use crate::handle::policy::HandlePolicy;
 use crate::handle::{normalize_and_validate_handle, HandleValidationContext, HandleValidationOpts};
 use crate::{plc, SharedIdResolver, SharedSequencer};
 use anyhow::{bail, Result};
@@ -11,13 +11,13 @@ use rocket::serde::json::Json;
 use rocket::State;
 use rsky_common::env::env_str;
 use rsky_lexicon::com::atproto::admin::UpdateAccountHandleInput;
-use std::env;
 
 async fn inner_update_account_handle(
     body: Json<UpdateAccountHandleInput>,
     sequencer: &State<SharedSequencer>,
     server_config: &State<ServerConfig>,
     id_resolver: &State<SharedIdResolver>,
+    handle_policy: &State<HandlePolicy>,
     account_manager: AccountManager,
 ) -> Result<()> {
     let UpdateAccountHandleInput { did, handle } = body.into_inner();
@@ -29,6 +29,7 @@ async fn inner_update_account_handle(
     let validation_ctx = HandleValidationContext {
         server_config,
         id_resolver,
+        handle_policy,
     };
     let handle = normalize_and_validate_handle(opts, validation_ctx).await?;
 
@@ -50,15 +51,14 @@ async fn inner_update_account_handle(
         None => {
             let plc_url = env_str("PDS_DID_PLC_URL").unwrap_or("https://plc.directory".to_owned());
             let plc_client = plc::Client::new(plc_url);
-            let private_key = env::var("PDS_PLC_ROTATION_KEY_K256_PRIVATE_KEY_HEX").unwrap();
-            let (signing_key, _) = get_keys_from_private_key_str(private_key)?;
+            let signing_key = keys::plc_rotation_key()?;
             plc_client
                 .update_handle(&did, &signing_key, &handle)
                 .await?;
             account_manager.update_handle(&did, &handle).await?;
         }
     }
-    let mut lock = sequencer.sequencer.write().await;
+    let lock = sequencer.sequencer.read().await;
     lock.sequence_identity_evt(did.clone(), Some(handle.clone()))
         .await?;
     Ok(())
@@ -75,16 +75,24 @@ pub async fn update_account_handle(
     sequencer: &State<SharedSequencer>,
     server_config: &State<ServerConfig>,
     id_resolver: &State<SharedIdResolver>,
+    handle_policy: &State<HandlePolicy>,
     _auth: AdminToken,
     account_manager: AccountManager,
 ) -> Result<(), ApiError> {
-    match inner_update_account_handle(body, sequencer, server_config, id_resolver, account_manager)
-        .await
+    match inner_update_account_handle(
+        body,
+        sequencer,
+        server_config,
+        id_resolver,
+        handle_policy,
+        account_manager,
+    )
+    .await
     {
         Ok(_) => Ok(()),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
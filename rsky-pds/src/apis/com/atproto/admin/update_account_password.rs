@@ -1,8 +1,10 @@
 use crate::account_manager::{AccountManager, UpdateAccountPasswordOpts};
 use crate::apis::ApiError;
 use crate::auth_verifier::AdminToken;
+use crate::config::ServerConfig;
 use anyhow::Result;
 use rocket::serde::json::Json;
+use rocket::State;
 use rsky_lexicon::com::atproto::admin::UpdateAccountPasswordInput;
 
 #[tracing::instrument(skip_all)]
@@ -14,17 +16,22 @@ use rsky_lexicon::com::atproto::admin::UpdateAccountPasswordInput;
 pub async fn update_account_password(
     body: Json<UpdateAccountPasswordInput>,
     _auth: AdminToken,
+    cfg: &State<ServerConfig>,
     account_manager: AccountManager,
 ) -> Result<(), ApiError> {
     let UpdateAccountPasswordInput { did, password } = body.into_inner();
     match account_manager
-        .update_account_password(UpdateAccountPasswordOpts { did, password })
+        .update_account_password(UpdateAccountPasswordOpts {
+            did,
+            password,
+            revoke_app_passwords: !cfg.service.app_passwords_survive_password_reset,
+        })
         .await
     {
         Ok(_) => Ok(()),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
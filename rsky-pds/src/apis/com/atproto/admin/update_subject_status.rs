@@ -1,6 +1,8 @@
+use crate::account_manager::helpers::account::AccountStatusTransition;
 use crate::account_manager::AccountManager;
 use crate::actor_store::aws::s3::S3BlobStore;
 use crate::actor_store::ActorStore;
+use crate::admin_approval::{self, AdminConfirmation, ACTION_TAKEDOWN};
 use crate::apis::ApiError;
 use crate::auth_verifier::Moderator;
 use crate::db::DbConn;
@@ -18,6 +20,8 @@ async fn inner_update_subject_status(
     body: Json<SubjectStatus>,
     sequencer: &State<SharedSequencer>,
     s3_config: &State<SdkConfig>,
+    confirmation: AdminConfirmation,
+    auth: Moderator,
     db: DbConn,
     account_manager: AccountManager,
 ) -> Result<UpdateSubjectStatusOutput> {
@@ -27,12 +31,21 @@ async fn inner_update_subject_status(
         deactivated,
     } = body.into_inner();
 
+    // lifting a takedown needs no confirmation
+    if takedown.as_ref().is_some_and(|takedown| takedown.applied) {
+        let target = match &subject {
+            Subject::RepoRef(subject) => &subject.did,
+            Subject::StrongRef(subject) => &subject.uri,
+            Subject::RepoBlobRef(subject) => &subject.cid,
+        };
+        admin_approval::approve(ACTION_TAKEDOWN, target, &confirmation, &auth.access, &db).await?;
+    }
+
+    let mut transitions = Vec::new();
     if let Some(takedown) = &takedown {
         match &subject {
-            Subject::RepoRef(subject) => {
-                account_manager
-                    .takedown_account(&subject.did, takedown.clone())
-                    .await?;
+            Subject::RepoRef(_) => {
+                transitions.push(AccountStatusTransition::Takedown(takedown.clone()));
             }
             Subject::StrongRef(subject) => {
                 let subject_at_uri: AtUri = subject.uri.clone().try_into()?;
@@ -61,27 +74,23 @@ async fn inner_update_subject_status(
     }
 
     if let Some(deactivated) = deactivated {
-        if let Subject::RepoRef(subject) = &subject {
-            if deactivated.applied {
-                account_manager
-                    .deactivate_account(&subject.did, None)
-                    .await?;
-            } else {
-                account_manager.activate_account(&subject.did).await?;
-            }
-        }
+        transitions.push(match deactivated.applied {
+            true => AccountStatusTransition::Deactivate { delete_after: None },
+            false => AccountStatusTransition::Activate,
+        });
     }
 
     if let Subject::RepoRef(subject) = &subject {
-        let status = account_manager.get_account_status(&subject.did).await?;
-        let mut lock = sequencer.sequencer.write().await;
-        lock.sequence_account_evt(subject.did.clone(), status)
+        account_manager
+            .update_account_status(&subject.did, transitions, sequencer)
             .await?;
     }
 
     Ok(UpdateSubjectStatusOutput { subject, takedown })
 }
 
+/// Applying a takedown needs `confirm` set to the subject's DID, record URI or blob CID and a
+/// `justification`, and under dual approval a second moderator's confirmation too.
 #[tracing::instrument(skip_all)]
 #[rocket::post(
     "/xrpc/com.atproto.admin.updateSubjectStatus",
@@ -92,15 +101,26 @@ pub async fn update_subject_status(
     body: Json<SubjectStatus>,
     sequencer: &State<SharedSequencer>,
     s3_config: &State<SdkConfig>,
+    confirmation: AdminConfirmation,
     db: DbConn,
-    _auth: Moderator,
+    auth: Moderator,
     account_manager: AccountManager,
 ) -> Result<Json<UpdateSubjectStatusOutput>, ApiError> {
-    match inner_update_subject_status(body, sequencer, s3_config, db, account_manager).await {
+    match inner_update_subject_status(
+        body,
+        sequencer,
+        s3_config,
+        confirmation,
+        auth,
+        db,
+        account_manager,
+    )
+    .await
+    {
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
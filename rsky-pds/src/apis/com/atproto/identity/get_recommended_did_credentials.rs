@@ -2,113 +2,115 @@ use crate::account_manager::helpers::account::AvailabilityFlags;
 use crate::account_manager::AccountManager;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandard;
-use crate::config::ServerConfig;
+use crate::config::{keys, ServerConfig};
 use rocket::serde::json::Json;
 use rocket::State;
-use rsky_crypto::utils::encode_did_key;
 use rsky_lexicon::com::atproto::identity::GetRecommendedDidCredentialsResponse;
-use secp256k1::{Keypair, Secp256k1, SecretKey};
+use rsky_syntax::handle::INVALID_HANDLE;
 use serde_json::json;
-use std::env;
 
-#[tracing::instrument(skip_all)]
-#[rocket::get("/xrpc/com.atproto.identity.getRecommendedDidCredentials")]
-pub async fn get_recommended_did_credentials(
+/// DID credentials an account should hold to be served by this PDS. Used by clients migrating an
+/// account in (e.g. goat) to build the PLC operation that moves the DID over, so accounts that
+/// are still deactivated mid-migration are included.
+async fn inner_get_recommended_did_credentials(
     auth: AccessStandard,
     cfg: &State<ServerConfig>,
     account_manager: AccountManager,
-) -> Result<Json<GetRecommendedDidCredentialsResponse>, ApiError> {
+) -> Result<GetRecommendedDidCredentialsResponse, ApiError> {
     let requester = auth.access.credentials.unwrap().did.unwrap();
     let availability_flags = AvailabilityFlags {
         include_taken_down: Some(true),
         include_deactivated: Some(true),
     };
-    let account = account_manager
+    let account = match account_manager
         .get_account(&requester, Some(availability_flags))
         .await?
-        .expect("Account not found despite valid access");
-
-    let mut also_known_as = Vec::new();
-    match account.handle {
-        None => {}
-        Some(res) => {
-            also_known_as.push("at://".to_string() + res.as_str());
-        }
-    }
+    {
+        Some(account) => account,
+        None => return Err(ApiError::AccountNotFound),
+    };
+    let keys = keys::service_keys().map_err(|error| {
+        tracing::error!("@LOG: ERROR: service keys unavailable {error}");
+        ApiError::RuntimeError
+    })?;
 
-    let signing_key = get_public_signing_key()?;
-    let verification_methods = json!({
-        "atproto": signing_key
-    });
+    Ok(recommended_credentials(
+        account.handle,
+        keys.repo_signing_did_key(),
+        keys.plc_rotation_did_key(),
+        &cfg.service.public_url,
+    ))
+}
 
-    let rotation_key = get_public_rotation_key()?;
-    let rotation_keys = vec![rotation_key];
+pub fn recommended_credentials(
+    handle: Option<String>,
+    signing_did_key: String,
+    rotation_did_key: String,
+    public_url: &str,
+) -> GetRecommendedDidCredentialsResponse {
+    // an account that arrived without a resolvable handle shouldn't publish the placeholder
+    let also_known_as = handle
+        .filter(|handle| handle != INVALID_HANDLE)
+        .map(|handle| vec![format!("at://{handle}")])
+        .unwrap_or_default();
 
-    let services = json!({
-        "atproto_pds": {
-            "type": "AtprotoPersonalDataServer",
-            "endpoint": cfg.service.public_url
-        }
-    });
-    let response = GetRecommendedDidCredentialsResponse {
+    GetRecommendedDidCredentialsResponse {
         also_known_as,
-        verification_methods,
-        rotation_keys,
-        services,
-    };
-    Ok(Json(response))
+        verification_methods: json!({
+            "atproto": signing_did_key
+        }),
+        rotation_keys: vec![rotation_did_key],
+        services: json!({
+            "atproto_pds": {
+                "type": "AtprotoPersonalDataServer",
+                "endpoint": public_url
+            }
+        }),
+    }
 }
 
-fn get_public_rotation_key() -> Result<String, ApiError> {
-    let secp = Secp256k1::new();
-    let private_rotation_key = match env::var("PDS_PLC_ROTATION_KEY_K256_PRIVATE_KEY_HEX") {
-        Ok(res) => res,
-        Err(error) => {
-            tracing::error!("Error geting rotation private key\n{error}");
-            return Err(ApiError::RuntimeError);
-        }
-    };
-    match hex::decode(private_rotation_key.as_bytes()) {
-        Ok(bytes) => match SecretKey::from_slice(&bytes) {
-            Ok(secret_key) => {
-                let rotation_keypair = Keypair::from_secret_key(&secp, &secret_key);
-                Ok(encode_did_key(&rotation_keypair.public_key()))
-            }
-            Err(error) => {
-                tracing::error!("Error geting rotation secret key from bytes\n{error}");
-                Err(ApiError::RuntimeError)
-            }
-        },
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/com.atproto.identity.getRecommendedDidCredentials")]
+pub async fn get_recommended_did_credentials(
+    auth: AccessStandard,
+    cfg: &State<ServerConfig>,
+    account_manager: AccountManager,
+) -> Result<Json<GetRecommendedDidCredentialsResponse>, ApiError> {
+    match inner_get_recommended_did_credentials(auth, cfg, account_manager).await {
+        Ok(res) => Ok(Json(res)),
         Err(error) => {
-            tracing::error!("Unable to hex decode rotation key\n{error}");
-            Err(ApiError::RuntimeError)
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error)
         }
     }
 }
 
-fn get_public_signing_key() -> Result<String, ApiError> {
-    let secp = Secp256k1::new();
-    let private_signing_key = match env::var("PDS_REPO_SIGNING_KEY_K256_PRIVATE_KEY_HEX") {
-        Ok(res) => res,
-        Err(error) => {
-            tracing::error!("Error geting signing private key\n{error}");
-            return Err(ApiError::RuntimeError);
-        }
-    };
-    match hex::decode(private_signing_key.as_bytes()) {
-        Ok(bytes) => match SecretKey::from_slice(&bytes) {
-            Ok(secret_key) => {
-                let signing_keypair = Keypair::from_secret_key(&secp, &secret_key);
-                Ok(encode_did_key(&signing_keypair.public_key()))
-            }
-            Err(error) => {
-                tracing::error!("Error geting signing secret key from bytes\n{error}");
-                Err(ApiError::RuntimeError)
-            }
-        },
-        Err(error) => {
-            tracing::error!("Unable to hex decode signing key\n{error}");
-            Err(ApiError::RuntimeError)
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recommended_credentials() {
+        let creds = recommended_credentials(
+            Some("alice.test".to_string()),
+            "did:key:signing".to_string(),
+            "did:key:rotation".to_string(),
+            "https://pds.test",
+        );
+        assert_eq!(creds.also_known_as, vec!["at://alice.test".to_string()]);
+        assert_eq!(creds.rotation_keys, vec!["did:key:rotation".to_string()]);
+        assert_eq!(creds.verification_methods["atproto"], "did:key:signing");
+        assert_eq!(
+            creds.services["atproto_pds"]["endpoint"],
+            "https://pds.test"
+        );
+
+        let creds = recommended_credentials(
+            Some(INVALID_HANDLE.to_string()),
+            "did:key:signing".to_string(),
+            "did:key:rotation".to_string(),
+            "https://pds.test",
+        );
+        assert!(creds.also_known_as.is_empty());
     }
 }
@@ -2,6 +2,7 @@ use crate::account_manager::helpers::account::{ActorAccount, AvailabilityFlags};
 use crate::account_manager::AccountManager;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessFull;
+use crate::locale::RequestLocale;
 use crate::mailer::{send_plc_operation, TokenParam};
 use crate::models::models::EmailTokenPurpose;
 
@@ -44,7 +45,24 @@ async fn get_account(
         },
         Err(error) => {
             tracing::error!("Error getting account\n{error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
+        }
+    }
+}
+
+#[tracing::instrument(skip_all)]
+async fn assert_email_deliverable(
+    account: &ActorAccount,
+    account_manager: &AccountManager,
+) -> Result<(), ApiError> {
+    let Some(email) = &account.email else {
+        return Ok(());
+    };
+    match account_manager.assert_email_deliverable(email).await {
+        Ok(()) => Ok(()),
+        Err(error) => {
+            tracing::error!("Not sending plc operation token\n{error}");
+            Err(error.into())
         }
     }
 }
@@ -61,28 +79,34 @@ async fn create_email_token(
         Ok(res) => Ok(res),
         Err(error) => {
             tracing::error!("Failed to create plc operation token\n{error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
 
 #[tracing::instrument(skip_all)]
-async fn do_plc_operation(account: &ActorAccount, token: String) -> Result<(), ApiError> {
+async fn do_plc_operation(
+    account: &ActorAccount,
+    token: String,
+    locale: Option<&str>,
+) -> Result<(), ApiError> {
     match &account.email {
         None => {
             tracing::error!("Failed to find email for account");
             Err(ApiError::RuntimeError)
         }
-        Some(email) => match send_plc_operation(email.clone(), TokenParam { token }).await {
-            Ok(_) => {
-                tracing::debug!("Successfully sent PLC Operation Email");
-                Ok(())
+        Some(email) => {
+            match send_plc_operation(email.clone(), TokenParam { token }, locale).await {
+                Ok(_) => {
+                    tracing::debug!("Successfully sent PLC Operation Email");
+                    Ok(())
+                }
+                Err(error) => {
+                    tracing::error!("Failed to send PLC Operation Token Email\n{error}");
+                    Err(error.into())
+                }
             }
-            Err(error) => {
-                tracing::error!("Failed to send PLC Operation Token Email\n{error}");
-                Err(ApiError::RuntimeError)
-            }
-        },
+        }
     }
 }
 
@@ -90,12 +114,15 @@ async fn do_plc_operation(account: &ActorAccount, token: String) -> Result<(), A
 #[tracing::instrument(skip_all)]
 pub async fn request_plc_operation_signature(
     auth: AccessFull,
+    locale: RequestLocale,
     account_manager: AccountManager,
 ) -> Result<(), ApiError> {
     let requester = get_requester_did(&auth).await?;
     let account = get_account(requester.as_str(), &account_manager).await?;
+    assert_email_deliverable(&account, &account_manager).await?;
     let token = create_email_token(requester.as_str(), &account_manager).await?;
-    do_plc_operation(&account, token).await?;
+    let locale = account_manager.mail_locale(&requester, locale).await?;
+    do_plc_operation(&account, token, locale.as_deref()).await?;
 
     Ok(())
 }
@@ -1,7 +1,8 @@
 use crate::account_manager::helpers::account::ActorAccount;
 use crate::account_manager::AccountManager;
 use crate::apis::ApiError;
-use crate::{SharedIdResolver, APP_USER_AGENT};
+use crate::http::{self, Destination};
+use crate::SharedIdResolver;
 use anyhow::{bail, Result};
 use rocket::serde::json::Json;
 use rocket::State;
@@ -12,19 +13,16 @@ async fn try_resolve_from_app_view(handle: &String) -> Result<Option<String>> {
     match env_str("PDS_BSKY_APP_VIEW_URL") {
         None => Ok(None),
         Some(bsky_app_view_url) => {
-            let client = reqwest::Client::builder()
-                .user_agent(APP_USER_AGENT)
-                .build()?;
             let params = Some(vec![("handle", handle)]);
-            let res = client
-                .get(format!(
-                    "{bsky_app_view_url}/xrpc/com.atproto.identity.resolveHandle"
-                ))
-                .header("Connection", "Keep-Alive")
-                .header("Keep-Alive", "timeout=5, max=1000")
-                .query(&params)
-                .send()
-                .await;
+            let res = http::get(
+                Destination::Proxy,
+                format!("{bsky_app_view_url}/xrpc/com.atproto.identity.resolveHandle"),
+            )
+            .header("Connection", "Keep-Alive")
+            .header("Keep-Alive", "timeout=5, max=1000")
+            .query(&params)
+            .send()
+            .await;
             match res {
                 Err(_) => Ok(None),
                 Ok(res) => match res.json::<ResolveHandleOutput>().await {
@@ -86,7 +84,7 @@ pub async fn resolve_handle(
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -1,7 +1,7 @@
 use crate::account_manager::AccountManager;
-use crate::apis::com::atproto::server::get_keys_from_private_key_str;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessFull;
+use crate::config::keys;
 use crate::models::models::EmailTokenPurpose;
 use crate::plc;
 use crate::plc::operations::create_update_op;
@@ -47,12 +47,11 @@ pub async fn sign_plc_operation(
         },
         Err(error) => {
             tracing::error!("Error getting last PLC operation\n{error}");
-            return Err(ApiError::RuntimeError);
+            return Err(error.into());
         }
     };
 
-    let private_key = env_str("PDS_PLC_ROTATION_KEY_K256_PRIVATE_KEY_HEX").unwrap();
-    let (secret_rotation_key, _) = get_keys_from_private_key_str(private_key)?;
+    let secret_rotation_key = keys::plc_rotation_key()?;
 
     //If request doesn't contain field, check last op for field. In the case of CreateOpV1,
     // we don't set it (which is aligned with BSky Implementation
@@ -115,7 +114,7 @@ pub async fn sign_plc_operation(
         Ok(res) => res,
         Err(error) => {
             tracing::error!("Error creating signed operation\n{error}");
-            return Err(ApiError::RuntimeError);
+            return Err(error.into());
         }
     };
 
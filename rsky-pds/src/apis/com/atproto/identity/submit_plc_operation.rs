@@ -2,15 +2,13 @@ use crate::account_manager::helpers::account::AvailabilityFlags;
 use crate::account_manager::AccountManager;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandard;
-use crate::config::ServerConfig;
+use crate::config::{keys, ServerConfig};
 use crate::plc::types::{OpOrTombstone, Operation};
 use crate::{plc, SharedIdResolver, SharedSequencer};
 use rocket::serde::json::Json;
 use rocket::State;
-use rsky_crypto::utils::encode_did_key;
 use rsky_lexicon::com::atproto::identity::SubmitPlcOperationRequest;
-use secp256k1::{Keypair, Secp256k1, SecretKey};
-use std::env;
+use rsky_syntax::handle::INVALID_HANDLE;
 
 #[tracing::instrument(skip_all)]
 fn get_requester_did(auth: &AccessStandard) -> Result<String, ApiError> {
@@ -31,27 +29,10 @@ fn get_requester_did(auth: &AccessStandard) -> Result<String, ApiError> {
 
 #[tracing::instrument(skip_all)]
 fn get_public_rotation_key() -> Result<String, ApiError> {
-    let secp = Secp256k1::new();
-    let private_rotation_key = match env::var("PDS_PLC_ROTATION_KEY_K256_PRIVATE_KEY_HEX") {
-        Ok(res) => res,
+    match keys::service_keys() {
+        Ok(keys) => Ok(keys.plc_rotation_did_key()),
         Err(error) => {
             tracing::error!("Error geting rotation private key\n{error}");
-            return Err(ApiError::RuntimeError);
-        }
-    };
-    match hex::decode(private_rotation_key.as_bytes()) {
-        Ok(bytes) => match SecretKey::from_slice(&bytes) {
-            Ok(secret_key) => {
-                let rotation_keypair = Keypair::from_secret_key(&secp, &secret_key);
-                Ok(encode_did_key(&rotation_keypair.public_key()))
-            }
-            Err(error) => {
-                tracing::error!("Error geting rotation secret key from bytes\n{error}");
-                Err(ApiError::RuntimeError)
-            }
-        },
-        Err(error) => {
-            tracing::error!("Unable to hex decode rotation key\n{error}");
             Err(ApiError::RuntimeError)
         }
     }
@@ -59,27 +40,10 @@ fn get_public_rotation_key() -> Result<String, ApiError> {
 
 #[tracing::instrument(skip_all)]
 fn get_public_signing_key() -> Result<String, ApiError> {
-    let secp = Secp256k1::new();
-    let private_signing_key = match env::var("PDS_REPO_SIGNING_KEY_K256_PRIVATE_KEY_HEX") {
-        Ok(res) => res,
+    match keys::service_keys() {
+        Ok(keys) => Ok(keys.repo_signing_did_key()),
         Err(error) => {
             tracing::error!("Error geting signing private key\n{error}");
-            return Err(ApiError::RuntimeError);
-        }
-    };
-    match hex::decode(private_signing_key.as_bytes()) {
-        Ok(bytes) => match SecretKey::from_slice(&bytes) {
-            Ok(secret_key) => {
-                let signing_keypair = Keypair::from_secret_key(&secp, &secret_key);
-                Ok(encode_did_key(&signing_keypair.public_key()))
-            }
-            Err(error) => {
-                tracing::error!("Error geting signing secret key from bytes\n{error}");
-                Err(ApiError::RuntimeError)
-            }
-        },
-        Err(error) => {
-            tracing::error!("Unable to hex decode signing key\n{error}");
             Err(ApiError::RuntimeError)
         }
     }
@@ -92,7 +56,7 @@ async fn validate_plc_request(
     public_endpoint: &str,
     account_manager: &AccountManager,
 ) -> Result<(), ApiError> {
-    let public_rotation_key = get_public_signing_key()?;
+    let public_rotation_key = get_public_rotation_key()?;
     if !op.rotation_keys.contains(&public_rotation_key) {
         return Err(ApiError::InvalidRequest(
             "Rotation keys do not include server's rotation key".to_string(),
@@ -151,10 +115,11 @@ async fn validate_plc_request(
         },
         Err(error) => {
             tracing::error!("Error looking up account\n{error}");
-            return Err(ApiError::RuntimeError);
+            return Err(error.into());
         }
     };
-    if let Some(handle) = account.handle {
+    // accounts that arrived without a resolvable handle aren't recommended one to publish
+    if let Some(handle) = account.handle.filter(|handle| handle != INVALID_HANDLE) {
         let op_handle = match op.also_known_as.first() {
             None => {
                 return Err(ApiError::InvalidRequest(
@@ -187,7 +152,7 @@ async fn do_plc_operation(plc_url: &str, did: &str, op: Operation) -> Result<(),
         }
         Err(error) => {
             tracing::error!("Failed to update did:plc\n{error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -238,7 +203,7 @@ pub async fn submit_plc_operation(
     do_plc_operation(server_config.identity.plc_url.as_str(), did.as_str(), op).await?;
 
     //Update Sequencer
-    let mut seq_lock = sequencer.sequencer.write().await;
+    let seq_lock = sequencer.sequencer.read().await;
     seq_lock.sequence_identity_evt(did.clone(), None).await?;
 
     //Refresh DID after PLC update
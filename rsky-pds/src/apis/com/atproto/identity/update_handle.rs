@@ -1,9 +1,9 @@
 use crate::account_manager::helpers::account::AvailabilityFlags;
 use crate::account_manager::AccountManager;
-use crate::apis::com::atproto::server::get_keys_from_private_key_str;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandardCheckTakedown;
-use crate::config::ServerConfig;
+use crate::config::{keys, ServerConfig};
+use crate::handle::policy::HandlePolicy;
 use crate::handle::{normalize_and_validate_handle, HandleValidationContext, HandleValidationOpts};
 use crate::{plc, SharedIdResolver, SharedSequencer};
 use anyhow::{bail, Result};
@@ -11,7 +11,6 @@ use rocket::serde::json::Json;
 use rocket::State;
 use rsky_common::env::env_str;
 use rsky_lexicon::com::atproto::identity::UpdateHandleInput;
-use std::env;
 
 #[tracing::instrument(skip_all)]
 async fn inner_update_handle(
@@ -19,6 +18,7 @@ async fn inner_update_handle(
     sequencer: &State<SharedSequencer>,
     server_config: &State<ServerConfig>,
     id_resolver: &State<SharedIdResolver>,
+    handle_policy: &State<HandlePolicy>,
     auth: AccessStandardCheckTakedown,
     account_manager: AccountManager,
 ) -> Result<()> {
@@ -33,6 +33,7 @@ async fn inner_update_handle(
     let validation_ctx = HandleValidationContext {
         server_config,
         id_resolver,
+        handle_policy,
     };
     let handle = normalize_and_validate_handle(opts, validation_ctx).await?;
 
@@ -47,20 +48,20 @@ async fn inner_update_handle(
         .await?;
 
     match account {
-        Some(account) if account.did != requester => bail!("Handle already taken: {handle}"),
+        Some(account) if account.did != requester => bail!(ApiError::HandleNotAvailable),
         Some(_) => (),
         None => {
             let plc_url = env_str("PDS_DID_PLC_URL").unwrap_or("https://plc.directory".to_owned());
             let plc_client = plc::Client::new(plc_url);
-            let private_key = env::var("PDS_PLC_ROTATION_KEY_K256_PRIVATE_KEY_HEX").unwrap();
-            let (signing_key, _) = get_keys_from_private_key_str(private_key)?;
+            let signing_key = keys::plc_rotation_key()?;
             plc_client
                 .update_handle(&requester, &signing_key, &handle)
                 .await?;
             account_manager.update_handle(&requester, &handle).await?;
+            id_resolver.id_resolver.write().await.purge_handle(&handle);
         }
     }
-    let mut lock = sequencer.sequencer.write().await;
+    let lock = sequencer.sequencer.read().await;
     match lock
         .sequence_identity_evt(requester.clone(), Some(handle.clone()))
         .await
@@ -89,6 +90,7 @@ pub async fn update_handle(
     sequencer: &State<SharedSequencer>,
     server_config: &State<ServerConfig>,
     id_resolver: &State<SharedIdResolver>,
+    handle_policy: &State<HandlePolicy>,
     auth: AccessStandardCheckTakedown,
     account_manager: AccountManager,
 ) -> Result<(), ApiError> {
@@ -97,6 +99,7 @@ pub async fn update_handle(
         sequencer,
         server_config,
         id_resolver,
+        handle_policy,
         auth,
         account_manager,
     )
@@ -105,7 +108,7 @@ pub async fn update_handle(
         Ok(_) => Ok(()),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
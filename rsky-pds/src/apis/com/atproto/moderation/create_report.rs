@@ -0,0 +1,122 @@
+use crate::account_manager::helpers::moderation::CreateReportOpts;
+use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
+use crate::auth_verifier::AccessStandard;
+use crate::config::{ServerConfig, ServiceConfig};
+use crate::http::{self, Destination};
+use crate::models::ModerationReport;
+use crate::{context, moderation, SharedSequencer};
+use anyhow::Result;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::atproto::admin::Subject;
+use rsky_lexicon::com::atproto::moderation::{CreateReportInput, CreateReportOutput};
+use rsky_repo::types::Ids;
+use rsky_syntax::aturi::AtUri;
+
+async fn get_subject_did(subject: &Subject, account_manager: &AccountManager) -> Result<String> {
+    match subject {
+        Subject::RepoRef(subject) => Ok(subject.did.clone()),
+        Subject::RepoBlobRef(subject) => Ok(subject.did.clone()),
+        Subject::StrongRef(subject) => {
+            let uri: AtUri = subject.uri.clone().try_into()?;
+            let hostname = uri.get_hostname();
+            match account_manager.get_did_for_actor(hostname, None).await? {
+                Some(did) => Ok(did),
+                None => Ok(hostname.to_string()),
+            }
+        }
+    }
+}
+
+async fn forward_report(
+    did: &str,
+    report_service: &ServiceConfig,
+    body: &CreateReportInput,
+) -> Result<CreateReportOutput> {
+    let nsid = Ids::ComAtprotoModerationCreateReport.as_str();
+    let auth_headers = context::service_auth_headers(did, &report_service.did, nsid).await?;
+    let res = http::post(
+        Destination::ModService,
+        format!("{}/xrpc/{nsid}", report_service.url),
+    )
+    .headers(auth_headers)
+    .json(body)
+    .send()
+    .await?
+    .error_for_status()?;
+    Ok(res.json().await?)
+}
+
+fn to_output(report: ModerationReport, subject: Subject) -> CreateReportOutput {
+    CreateReportOutput {
+        id: report.id,
+        reason_type: report.reason_type,
+        reason: report.reason,
+        subject,
+        reported_by: report.reported_by,
+        created_at: report.created_at,
+    }
+}
+
+/// Record the report so the deployment's auto-action rules can count it, then hand it to the
+/// report service when one is configured.
+async fn inner_create_report(
+    body: Json<CreateReportInput>,
+    auth: AccessStandard,
+    cfg: &State<ServerConfig>,
+    sequencer: &State<SharedSequencer>,
+    account_manager: AccountManager,
+) -> Result<CreateReportOutput> {
+    let body = body.into_inner();
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let subject_did = get_subject_did(&body.subject, &account_manager).await?;
+    let subject_uri = match &body.subject {
+        Subject::StrongRef(subject) => Some(subject.uri.clone()),
+        _ => None,
+    };
+
+    let report = account_manager
+        .create_report(CreateReportOpts {
+            subject_did: subject_did.clone(),
+            subject_uri,
+            reason_type: body.reason_type.clone(),
+            reason: body.reason.clone(),
+            reported_by: did.clone(),
+        })
+        .await?;
+    moderation::apply_auto_actions(
+        &subject_did,
+        &cfg.moderation.auto_actions,
+        &account_manager,
+        sequencer,
+    )
+    .await?;
+
+    match &cfg.report_service {
+        Some(report_service) => forward_report(&did, report_service, &body).await,
+        None => Ok(to_output(report, body.subject)),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/com.atproto.moderation.createReport",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn create_report(
+    body: Json<CreateReportInput>,
+    auth: AccessStandard,
+    cfg: &State<ServerConfig>,
+    sequencer: &State<SharedSequencer>,
+    account_manager: AccountManager,
+) -> Result<Json<CreateReportOutput>, ApiError> {
+    match inner_create_report(body, auth, cfg, sequencer, account_manager).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
@@ -110,7 +110,7 @@ async fn inner_apply_writes(
             .process_writes(writes.clone(), swap_commit_cid)
             .await?;
 
-        let mut lock = sequencer.sequencer.write().await;
+        let lock = sequencer.sequencer.read().await;
         lock.sequence_commit(did.clone(), commit.clone()).await?;
         account_manager
             .update_repo_root(
@@ -140,7 +140,7 @@ pub async fn apply_writes(
         Ok(()) => Ok(()),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
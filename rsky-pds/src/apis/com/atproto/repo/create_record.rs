@@ -6,6 +6,7 @@ use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandardIncludeChecks;
 use crate::db::DbConn;
 use crate::repo::prepare::{prepare_create, prepare_delete, PrepareCreateOpts, PrepareDeleteOpts};
+use crate::spam::{self, SpamSubject};
 use crate::SharedSequencer;
 use anyhow::{bail, Result};
 use aws_config::SdkConfig;
@@ -13,10 +14,12 @@ use lexicon_cid::Cid;
 use rocket::serde::json::Json;
 use rocket::State;
 use rsky_lexicon::com::atproto::repo::{CreateRecordInput, CreateRecordOutput};
-use rsky_repo::types::{PreparedDelete, PreparedWrite};
+use rsky_repo::types::{PreparedDelete, PreparedWrite, RepoRecord};
 use rsky_syntax::aturi::AtUri;
 use std::str::FromStr;
 
+const MAX_RKEY_ATTEMPTS: usize = 3;
+
 async fn inner_create_record(
     body: Json<CreateRecordInput>,
     auth: AccessStandardIncludeChecks,
@@ -54,18 +57,51 @@ async fn inner_create_record(
             Some(swap_commit) => Some(Cid::from_str(&swap_commit)?),
             None => None,
         };
-        let write = prepare_create(PrepareCreateOpts {
-            did: did.clone(),
-            collection: collection.clone(),
-            record: serde_json::from_value(record)?,
-            rkey,
-            validate,
-            swap_cid: None,
-        })
-        .await?;
-
         let mut actor_store =
             ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+        let record_json = record.clone();
+        let record: RepoRecord = serde_json::from_value(record)?;
+        let mut attempts = 0;
+        let write = loop {
+            let write = prepare_create(PrepareCreateOpts {
+                did: did.clone(),
+                collection: collection.clone(),
+                record: record.clone(),
+                rkey: rkey.clone(),
+                validate,
+                swap_cid: None,
+            })
+            .await?;
+            let write_at_uri: AtUri = write.uri.clone().try_into()?;
+            let existing = actor_store
+                .record
+                .get_record(&write_at_uri, None, Some(true))
+                .await?;
+            if existing.is_none() {
+                break write;
+            }
+            if rkey.is_some() {
+                bail!(ApiError::BadRequest(
+                    "RecordAlreadyExists".to_string(),
+                    format!("Record already exists at {}", write.uri),
+                ));
+            }
+            // generated TIDs come from one monotonic clock, so this only happens when another
+            // writer (e.g. an imported repo) already used the TID; drawing again moves past it
+            attempts += 1;
+            tracing::warn!("rkey collision creating {}", write.uri);
+            if attempts == MAX_RKEY_ATTEMPTS {
+                bail!("Failed to generate a free record key after {MAX_RKEY_ATTEMPTS} attempts");
+            }
+        };
+
+        let spam_subject = SpamSubject::Record {
+            did: did.clone(),
+            uri: write.uri.clone(),
+            record: record_json,
+        };
+        let spam_verdict = spam::check(&spam_subject).await?;
+
         let backlink_conflicts: Vec<AtUri> = match validate {
             Some(true) => {
                 let write_at_uri: AtUri = write.uri.clone().try_into()?;
@@ -96,11 +132,14 @@ async fn inner_create_record(
             .process_writes(writes.clone(), swap_commit_cid)
             .await?;
 
-        let mut lock = sequencer.sequencer.write().await;
+        let lock = sequencer.sequencer.read().await;
         lock.sequence_commit(did.clone(), commit.clone()).await?;
         account_manager
             .update_repo_root(did, commit.commit_data.cid, commit.commit_data.rev)
             .await?;
+        if let Err(error) = spam::flag(&spam_subject, spam_verdict, &account_manager).await {
+            tracing::error!("@LOG: ERROR: failed to flag {}: {error}", write.uri);
+        }
 
         Ok(CreateRecordOutput {
             uri: write.uri.clone(),
@@ -130,7 +169,7 @@ pub async fn create_record(
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -81,7 +81,7 @@ async fn inner_delete_record(
                 }
             };
 
-            let mut lock = sequencer.sequencer.write().await;
+            let lock = sequencer.sequencer.read().await;
             lock.sequence_commit(did.clone(), commit.clone()).await?;
             account_manager
                 .update_repo_root(did, commit.commit_data.cid, commit.commit_data.rev)
@@ -110,7 +110,7 @@ pub async fn delete_record(
         Ok(()) => Ok(()),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -1,12 +1,15 @@
 use crate::actor_store::aws::s3::S3BlobStore;
 use crate::actor_store::ActorStore;
+use crate::apis::com::atproto::server::{fetch_did_web_doc, safe_resolve_did_doc};
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessFullImport;
+use crate::config::keys;
 use crate::db::DbConn;
 use crate::repo::prepare::{
     prepare_create, prepare_delete, prepare_update, PrepareCreateOpts, PrepareDeleteOpts,
     PrepareUpdateOpts,
 };
+use crate::SharedIdResolver;
 use aws_config::SdkConfig;
 use futures::{stream, StreamExt};
 use lexicon_cid::Cid;
@@ -15,6 +18,9 @@ use rocket::data::{FromData, Outcome, ToByteUnit};
 use rocket::http::Status;
 use rocket::{Data, Request, State};
 use rsky_common::env::env_int;
+use rsky_common::get_verification_material;
+use rsky_identity::did::atproto_data::get_did_key_from_multibase;
+use rsky_identity::types::DidDocument;
 use rsky_repo::block_map::BlockMap;
 use rsky_repo::car::{read_stream_car_with_root, CarWithRoot};
 use rsky_repo::parse::get_and_parse_record;
@@ -51,7 +57,7 @@ impl<'r> FromData<'r> for ImportRepoInput {
                     }
 
                     let import_datastream = data.open(content_length.get().bytes());
-                    match read_stream_car_with_root(import_datastream).await {
+                    match read_stream_car_with_root(import_datastream, None).await {
                         Ok(car_with_root) => Outcome::Success(ImportRepoInput { car_with_root }),
                         Err(error) => {
                             let error = ApiError::InvalidRequest(error.to_string());
@@ -72,15 +78,55 @@ impl<'r> FromData<'r> for ImportRepoInput {
     }
 }
 
+/// The key the imported commit must be signed with: the one the account's DID document names,
+/// for did:plc and did:web alike. Once the document already points at this PDS, the commit
+/// was signed by the PDS being left and there's nothing to check it against.
+async fn import_signing_key(
+    did: &String,
+    id_resolver: &State<SharedIdResolver>,
+) -> Result<Option<String>, ApiError> {
+    // a did:web host is the caller's to point anywhere, so it's fetched from public addresses only
+    let did_doc = if did.starts_with("did:web:") {
+        fetch_did_web_doc(did).await?
+    } else {
+        safe_resolve_did_doc(id_resolver, did, Some(true)).await?
+    };
+    let Some(did_doc) = did_doc else {
+        return Err(ApiError::InvalidRequest(format!(
+            "Could not resolve DID document for {did}"
+        )));
+    };
+    signing_key_to_check(&did_doc, &keys::service_keys()?.repo_signing_did_key())
+}
+
+/// The atproto key of `did_doc`, or `None` when that's `own_key`.
+fn signing_key_to_check(did_doc: &DidDocument, own_key: &str) -> Result<Option<String>, ApiError> {
+    let signing_key = match get_verification_material(&did_doc, "atproto") {
+        Some(key) => get_did_key_from_multibase(key)?,
+        None => None,
+    };
+    let Some(signing_key) = signing_key else {
+        return Err(ApiError::InvalidRequest(
+            "DID document has no atproto signing key".to_string(),
+        ));
+    };
+    if signing_key == own_key {
+        return Ok(None);
+    }
+    Ok(Some(signing_key))
+}
+
 #[tracing::instrument(skip_all)]
 #[rocket::post("/xrpc/com.atproto.repo.importRepo", data = "<import_repo_input>")]
 pub async fn import_repo(
     auth: AccessFullImport,
     import_repo_input: ImportRepoInput,
     s3_config: &State<SdkConfig>,
+    id_resolver: &State<SharedIdResolver>,
     db: DbConn,
 ) -> Result<(), ApiError> {
     let requester = auth.access.credentials.unwrap().did.unwrap();
+    let signing_key = import_signing_key(&requester, id_resolver).await?;
     let mut actor_store = ActorStore::new(
         requester.clone(),
         S3BlobStore::new(requester.clone(), s3_config),
@@ -108,8 +154,8 @@ pub async fn import_repo(
         curr_repo,
         &mut imported_blocks,
         imported_root,
-        None,
-        None,
+        Some(&requester),
+        signing_key.as_ref(),
         Some(opts),
     )
     .await
@@ -117,7 +163,7 @@ pub async fn import_repo(
         Ok(res) => res,
         Err(error) => {
             tracing::error!("{:?}", error);
-            return Err(ApiError::RuntimeError);
+            return Err(error.into());
         }
     };
 
@@ -131,7 +177,7 @@ pub async fn import_repo(
         Ok(_res) => {}
         Err(error) => {
             tracing::error!("Error importing repo\n{error}");
-            return Err(ApiError::RuntimeError);
+            return Err(error.into());
         }
     }
 
@@ -196,7 +242,54 @@ async fn prepare_import_repo_writes(
         Ok(res) => Ok(res),
         Err(error) => {
             tracing::error!("Error preparing import repo writes\n{error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsky_crypto::utils::encode_did_key;
+    use rsky_identity::types::VerificationMethod;
+    use secp256k1::{Secp256k1, SecretKey};
+
+    fn did_key(secret: u8) -> String {
+        let secret_key = SecretKey::from_slice(&[secret; 32]).unwrap();
+        encode_did_key(&secret_key.public_key(&Secp256k1::new()))
+    }
+
+    fn did_doc(signing_key: Option<&str>) -> DidDocument {
+        DidDocument {
+            context: None,
+            id: "did:web:alice.example.com".to_string(),
+            also_known_as: None,
+            verification_method: signing_key.map(|key| {
+                vec![VerificationMethod {
+                    id: "#atproto".to_string(),
+                    r#type: "Multikey".to_string(),
+                    controller: "did:web:alice.example.com".to_string(),
+                    public_key_multibase: key.strip_prefix("did:key:").map(str::to_string),
+                }]
+            }),
+            service: None,
+        }
+    }
+
+    #[test]
+    fn test_signing_key_to_check() {
+        let ours = did_key(1);
+        let theirs = did_key(2);
+        // still on the old PDS, its key has to have signed the import
+        assert_eq!(
+            signing_key_to_check(&did_doc(Some(&theirs)), &ours).unwrap(),
+            Some(theirs)
+        );
+        // already pointing here, the old PDS signed with a key the document no longer names
+        assert_eq!(
+            signing_key_to_check(&did_doc(Some(&ours)), &ours).unwrap(),
+            None
+        );
+        assert!(signing_key_to_check(&did_doc(None), &ours).is_err());
+    }
+}
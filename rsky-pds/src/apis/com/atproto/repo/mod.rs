@@ -1,5 +1,6 @@
 use crate::account_manager::helpers::account::{ActorAccount, AvailabilityFlags};
 use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
 use anyhow::{bail, Result};
 
 pub async fn assert_repo_availability(
@@ -17,16 +18,22 @@ pub async fn assert_repo_availability(
         )
         .await?;
     match account {
-        None => bail!("RepoNotFound: Could not find repo for DID: {did}"),
+        None => bail!(ApiError::RepoNotFound(format!(
+            "Could not find repo for DID: {did}"
+        ))),
         Some(account) => {
             if is_admin_of_self {
                 return Ok(account);
             }
             if account.takedown_ref.is_some() {
-                bail!("RepoTakendown: Repo has been takendown: {did}");
+                bail!(ApiError::RepoTakendown(format!(
+                    "Repo has been takendown: {did}"
+                )));
             }
             if account.deactivated_at.is_some() {
-                bail!("RepoDeactivated: Repo has been deactivated: {did}");
+                bail!(ApiError::RepoDeactivated(format!(
+                    "Repo has been deactivated: {did}"
+                )));
             }
             Ok(account)
         }
@@ -1,7 +1,7 @@
 use crate::account_manager::helpers::account::AvailabilityFlags;
 use crate::account_manager::AccountManager;
 use crate::actor_store::aws::s3::S3BlobStore;
-use crate::actor_store::ActorStore;
+use crate::actor_store::{ActorStore, FormatCommitError};
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandardIncludeChecks;
 use crate::db::DbConn;
@@ -97,7 +97,24 @@ async fn inner_put_record(
             };
 
             match current {
-                Some(current) if current.cid == write.cid().unwrap().to_string() => (None, write),
+                // re-saving an unchanged record would sequence an empty commit, so answer with
+                // what's already there, as long as the swaps still hold
+                Some(current) if current.cid == write.cid().unwrap().to_string() => {
+                    if let Some(swap_record_cid) = swap_record_cid {
+                        if swap_record_cid.to_string() != current.cid {
+                            bail!(FormatCommitError::RecordSwapMismatch(current.cid));
+                        }
+                    }
+                    if let Some(swap_commit_cid) = swap_commit_cid {
+                        match actor_store.get_repo_root().await {
+                            Some(root) if root == swap_commit_cid => (),
+                            Some(root) => bail!(FormatCommitError::BadCommitSwap(root.to_string())),
+                            None => bail!(FormatCommitError::MissingRepoRoot(did.clone())),
+                        }
+                    }
+                    tracing::debug!("@LOG: putRecord of unchanged {uri}, skipping commit");
+                    (None, write)
+                }
                 _ => {
                     let commit = actor_store
                         .process_writes(vec![write.clone()], swap_commit_cid)
@@ -108,7 +125,7 @@ async fn inner_put_record(
         };
 
         if let Some(commit) = commit {
-            let mut lock = sequencer.sequencer.write().await;
+            let lock = sequencer.sequencer.read().await;
             lock.sequence_commit(did.clone(), commit.clone()).await?;
             account_manager
                 .update_repo_root(did, commit.commit_data.cid, commit.commit_data.rev)
@@ -138,7 +155,7 @@ pub async fn put_record(
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -2,9 +2,11 @@ use crate::actor_store::aws::s3::S3BlobStore;
 use crate::actor_store::ActorStore;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandardIncludeChecks;
+use crate::config::ServerConfig;
 use crate::db::DbConn;
 use anyhow::Result;
 use aws_config::SdkConfig;
+use reqwest::header;
 use rocket::data::Data;
 use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome};
@@ -37,11 +39,37 @@ impl<'r> FromRequest<'r> for ContentType {
     }
 }
 
+/// Declared size of the request body, when the client sent one
+#[derive(Clone, Copy)]
+pub struct ContentLength(pub Option<usize>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ContentLength {
+    type Error = ApiError;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.headers().get_one(header::CONTENT_LENGTH.as_ref()) {
+            None => Outcome::Success(ContentLength(None)),
+            Some(content_length) => match content_length.parse::<usize>() {
+                Ok(content_length) => Outcome::Success(ContentLength(Some(content_length))),
+                Err(_) => {
+                    let error =
+                        ApiError::InvalidRequest("Invalid Content-Length header".to_string());
+                    req.local_cache(|| Some(error.clone()));
+                    Outcome::Error((Status::BadRequest, error))
+                }
+            },
+        }
+    }
+}
+
 async fn inner_upload_blob(
     auth: AccessStandardIncludeChecks,
     blob: Data<'_>,
     content_type: ContentType,
+    content_length: ContentLength,
     s3_config: &State<SdkConfig>,
+    cfg: &State<ServerConfig>,
     db: DbConn,
 ) -> Result<BlobOutput> {
     let requester = auth.access.credentials.unwrap().did.unwrap();
@@ -54,7 +82,12 @@ async fn inner_upload_blob(
 
     let metadata = actor_store
         .blob
-        .upload_blob_and_get_metadata(content_type.name, blob)
+        .upload_blob_and_get_metadata(
+            content_type.name,
+            blob,
+            content_length.0,
+            cfg.service.blob_upload_limit,
+        )
         .await?;
     let blobref = actor_store.blob.track_untethered_blob(metadata).await?;
 
@@ -96,14 +129,16 @@ pub async fn upload_blob(
     auth: AccessStandardIncludeChecks,
     blob: Data<'_>,
     content_type: ContentType,
+    content_length: ContentLength,
     s3_config: &State<SdkConfig>,
+    cfg: &State<ServerConfig>,
     db: DbConn,
 ) -> Result<Json<BlobOutput>, ApiError> {
-    match inner_upload_blob(auth, blob, content_type, s3_config, db).await {
+    match inner_upload_blob(auth, blob, content_type, content_length, s3_config, cfg, db).await {
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("{error:?}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -1,4 +1,4 @@
-use crate::account_manager::helpers::account::AvailabilityFlags;
+use crate::account_manager::helpers::account::{AccountStatusTransition, AvailabilityFlags};
 use crate::account_manager::AccountManager;
 use crate::actor_store::aws::s3::S3BlobStore;
 use crate::actor_store::ActorStore;
@@ -33,7 +33,13 @@ async fn inner_activate_account(
         .await?;
 
     if let Some(account) = account {
-        account_manager.activate_account(&requester).await?;
+        account_manager
+            .update_account_status(
+                &requester,
+                vec![AccountStatusTransition::Activate],
+                sequencer,
+            )
+            .await?;
 
         let mut actor_store = ActorStore::new(
             requester.clone(),
@@ -43,10 +49,7 @@ async fn inner_activate_account(
         let sync_data = actor_store.get_sync_event_data().await?;
 
         // @NOTE: we're over-emitting for now for backwards compatibility, can reduce this in the future
-        let status = account_manager.get_account_status(&requester).await?;
-        let mut lock = sequencer.sequencer.write().await;
-        lock.sequence_account_evt(requester.clone(), status).await?;
-
+        let lock = sequencer.sequencer.read().await;
         let handle = account.handle.unwrap_or(INVALID_HANDLE.to_string());
         lock.sequence_identity_evt(requester.clone(), Some(handle))
             .await?;
@@ -54,7 +57,7 @@ async fn inner_activate_account(
         Ok(())
     } else {
         tracing::error!("User not found");
-        Err(ApiError::RuntimeError)
+        Err(ApiError::AccountNotFound)
     }
 }
 
@@ -69,6 +72,9 @@ pub async fn activate_account(
 ) -> Result<(), ApiError> {
     match inner_activate_account(auth, sequencer, s3_config, db, account_manager).await {
         Ok(_) => Ok(()),
-        Err(error) => Err(error),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error)
+        }
     }
 }
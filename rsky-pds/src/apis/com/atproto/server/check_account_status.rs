@@ -69,7 +69,7 @@ pub async fn check_account_status(
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("Internal Error: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -1,16 +1,20 @@
-use crate::account_manager::helpers::account::AccountStatus;
 use crate::account_manager::{AccountManager, CreateAccountOpts};
 use crate::actor_store::aws::s3::S3BlobStore;
 use crate::actor_store::ActorStore;
-use crate::apis::com::atproto::server::safe_resolve_did_doc;
+use crate::apis::com::atproto::server::{
+    assert_valid_did_documents_for_service, safe_resolve_did_doc,
+};
 use crate::apis::ApiError;
 use crate::auth_verifier::UserDidAuthOptional;
-use crate::config::ServerConfig;
+use crate::config::{keys, ServerConfig};
 use crate::db::DbConn;
+use crate::handle::policy::HandlePolicy;
 use crate::handle::{normalize_and_validate_handle, HandleValidationContext, HandleValidationOpts};
 use crate::plc::operations::{create_op, CreateAtprotoOpInput};
 use crate::plc::types::{OpOrTombstone, Operation};
+use crate::repo::prepare::{prepare_create, PrepareCreateOpts};
 use crate::sequencer::events::sync_evt_data_from_commit;
+use crate::spam::{self, SpamSubject};
 use crate::SharedSequencer;
 use crate::{plc, SharedIdResolver};
 use aws_config::SdkConfig;
@@ -20,7 +24,9 @@ use rocket::State;
 use rsky_common::env::env_str;
 use rsky_crypto::utils::encode_did_key;
 use rsky_lexicon::com::atproto::server::{CreateAccountInput, CreateAccountOutput};
-use secp256k1::{Keypair, Secp256k1, SecretKey};
+use rsky_repo::types::PreparedCreateOrUpdate;
+use secp256k1::Keypair;
+use serde_json::json;
 use std::env;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -33,6 +39,7 @@ pub struct TransformedCreateAccountInput {
     pub signing_key: Keypair,
     pub plc_op: Option<Operation>,
     pub deactivated: bool,
+    pub display_name: Option<String>,
 }
 
 //TODO: Potential for taking advantage of async better
@@ -49,6 +56,7 @@ pub async fn server_create_account(
     s3_config: &State<SdkConfig>,
     cfg: &State<ServerConfig>,
     id_resolver: &State<SharedIdResolver>,
+    handle_policy: &State<HandlePolicy>,
     account_manager: AccountManager,
     db: DbConn,
 ) -> Result<Json<CreateAccountOutput>, ApiError> {
@@ -67,24 +75,40 @@ pub async fn server_create_account(
         deactivated,
         plc_op,
         signing_key,
+        display_name,
     } = validate_inputs_for_local_pds(
         cfg,
         id_resolver,
+        handle_policy,
         body.into_inner(),
         requester,
         &account_manager,
     )
     .await?;
 
+    let spam_subject = SpamSubject::Account {
+        did: did.clone(),
+        handle: handle.clone(),
+        email: email.clone(),
+    };
+    let spam_verdict = spam::check(&spam_subject).await?;
+
+    // Migrating accounts bring their own profile along with the rest of their repo
+    let genesis_writes = if cfg.service.bootstrap_profile && !deactivated {
+        vec![prepare_bootstrap_profile(&did, display_name).await?]
+    } else {
+        Vec::new()
+    };
+
     // Create new actor repo TODO: Proper rollback
     let mut actor_store =
         ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
-    let commit = match actor_store.create_repo(signing_key, Vec::new()).await {
+    let commit = match actor_store.create_repo(signing_key, genesis_writes).await {
         Ok(commit) => commit,
         Err(error) => {
             tracing::error!("Failed to create repo\n{:?}", error);
             actor_store.destroy().await?;
-            return Err(ApiError::RuntimeError);
+            return Err(error.into());
         }
     };
 
@@ -115,7 +139,7 @@ pub async fn server_create_account(
         Err(error) => {
             tracing::error!("Error resolving DID Doc\n{error}");
             actor_store.destroy().await?;
-            return Err(ApiError::RuntimeError);
+            return Err(error.into());
         }
     };
 
@@ -140,26 +164,31 @@ pub async fn server_create_account(
         Err(error) => {
             tracing::error!("Error creating account\n{error}");
             actor_store.destroy().await?;
-            return Err(ApiError::RuntimeError);
+            return Err(error.into());
         }
     }
+    if let Err(error) = spam::flag(&spam_subject, spam_verdict, &account_manager).await {
+        tracing::error!("Failed to flag account\n{error}");
+    }
 
     if !deactivated {
-        let mut lock = sequencer.sequencer.write().await;
-        match lock
-            .sequence_identity_evt(did.clone(), Some(handle.clone()))
+        let identity_res = sequencer
+            .sequencer
+            .write()
             .await
-        {
+            .sequence_identity_evt(did.clone(), Some(handle.clone()))
+            .await;
+        match identity_res {
             Ok(_) => {
                 tracing::debug!("Sequenece identity event succeeded");
             }
             Err(error) => {
                 tracing::error!("Sequence Identity Event failed\n{error}");
-                return Err(ApiError::RuntimeError);
+                return Err(error.into());
             }
         }
-        match lock
-            .sequence_account_evt(did.clone(), AccountStatus::Active)
+        match account_manager
+            .sequence_account_status(&did, sequencer)
             .await
         {
             Ok(_) => {
@@ -167,16 +196,17 @@ pub async fn server_create_account(
             }
             Err(error) => {
                 tracing::error!("Sequence Account Event failed\n{error}");
-                return Err(ApiError::RuntimeError);
+                return Err(error.into());
             }
         }
+        let lock = sequencer.sequencer.read().await;
         match lock.sequence_commit(did.clone(), commit.clone()).await {
             Ok(_) => {
                 tracing::debug!("Sequence commit succeeded");
             }
             Err(error) => {
                 tracing::error!("Sequence Commit failed\n{error}");
-                return Err(ApiError::RuntimeError);
+                return Err(error.into());
             }
         }
         match lock
@@ -191,7 +221,7 @@ pub async fn server_create_account(
             }
             Err(error) => {
                 tracing::error!("Sequence sync event data from commit failed\n{error}");
-                return Err(ApiError::RuntimeError);
+                return Err(error.into());
             }
         }
     }
@@ -204,7 +234,7 @@ pub async fn server_create_account(
         }
         Err(error) => {
             tracing::error!("Update Repo Root failed\n{error}");
-            return Err(ApiError::RuntimeError);
+            return Err(error.into());
         }
     }
 
@@ -233,6 +263,7 @@ pub async fn server_create_account(
 pub async fn validate_inputs_for_local_pds(
     cfg: &State<ServerConfig>,
     id_resolver: &State<SharedIdResolver>,
+    handle_policy: &State<HandlePolicy>,
     input: CreateAccountInput,
     requester: Option<String>,
     account_manager: &AccountManager,
@@ -281,6 +312,7 @@ pub async fn validate_inputs_for_local_pds(
     let validation_ctx = HandleValidationContext {
         server_config: cfg,
         id_resolver,
+        handle_policy,
     };
     let handle = normalize_and_validate_handle(opts, validation_ctx).await?;
     if !super::validate_handle(&handle) {
@@ -303,14 +335,24 @@ pub async fn validate_inputs_for_local_pds(
     };
 
     // Get Signing Key
-    let secp = Secp256k1::new();
-    let private_key = env::var("PDS_REPO_SIGNING_KEY_K256_PRIVATE_KEY_HEX").unwrap();
-    let secret_key = SecretKey::from_slice(&hex::decode(private_key.as_bytes()).unwrap()).unwrap();
-    let signing_key = Keypair::from_secret_key(&secp, &secret_key);
+    let signing_key = keys::repo_signing_keypair()?;
 
     match input.did {
+        // an externally managed did:web already naming this PDS and its signing key is proof
+        // enough of control, and its account starts out active
+        Some(input_did) if input_did.starts_with("did:web:") && requester.is_none() => {
+            if let Err(error) = assert_valid_did_documents_for_service(input_did.clone()).await {
+                return Err(ApiError::InvalidRequest(format!(
+                    "DID document for {input_did} is not set up for this PDS: {error}"
+                )));
+            }
+            did = input_did;
+            plc_op = None;
+            deactivated = false;
+        }
         Some(input_did) => {
-            if input_did == requester.unwrap_or("n/a".to_string()) {
+            // migrating in takes service auth from the account's current PDS, issued by the did
+            if requester.as_deref() != Some(input_did.as_str()) {
                 return Err(ApiError::AuthRequiredError(format!(
                     "Missing auth to create account with did: {input_did}"
                 )));
@@ -336,6 +378,36 @@ pub async fn validate_inputs_for_local_pds(
         signing_key,
         plc_op,
         deactivated,
+        display_name: input.display_name,
+    })
+}
+
+/// Initial `app.bsky.actor.profile` record written in the genesis commit, so appviews can render
+/// the account before the user ever edits their profile.
+pub async fn prepare_bootstrap_profile(
+    did: &str,
+    display_name: Option<String>,
+) -> Result<PreparedCreateOrUpdate, ApiError> {
+    let mut profile = json!({
+        "$type": "app.bsky.actor.profile",
+        "createdAt": rsky_common::now()
+    });
+    if let Some(display_name) = display_name.filter(|name| !name.trim().is_empty()) {
+        profile["displayName"] = json!(display_name);
+    }
+    let record = serde_json::from_value(profile).map_err(|_| ApiError::RuntimeError)?;
+    prepare_create(PrepareCreateOpts {
+        did: did.to_owned(),
+        collection: "app.bsky.actor.profile".to_string(),
+        rkey: Some("self".to_string()),
+        swap_cid: None,
+        record,
+        validate: Some(true),
+    })
+    .await
+    .map_err(|error| {
+        tracing::error!("Invalid bootstrap profile\n{error}");
+        ApiError::InvalidRequest("Invalid displayName".to_string())
     })
 }
 
@@ -352,12 +424,8 @@ async fn format_did_and_plc_op(
     }
 
     //Add PDS rotation key
-    let secp = Secp256k1::new();
-    let private_rotation_key = env::var("PDS_PLC_ROTATION_KEY_K256_PRIVATE_KEY_HEX").unwrap();
-    let private_secret_key =
-        SecretKey::from_slice(&hex::decode(private_rotation_key.as_bytes()).unwrap()).unwrap();
-    let rotation_keypair = Keypair::from_secret_key(&secp, &private_secret_key);
-    rotation_keys.push(encode_did_key(&rotation_keypair.public_key()));
+    let service_keys = keys::service_keys()?;
+    rotation_keys.push(service_keys.plc_rotation_did_key());
 
     //Build PLC Create Operation
 
@@ -370,11 +438,11 @@ async fn format_did_and_plc_op(
         ),
         rotation_keys,
     };
-    let response = match create_op(create_op_input, rotation_keypair.secret_key()).await {
+    let response = match create_op(create_op_input, service_keys.plc_rotation).await {
         Ok(res) => res,
         Err(error) => {
             tracing::error!("{error}");
-            return Err(ApiError::RuntimeError);
+            return Err(error.into());
         }
     };
 
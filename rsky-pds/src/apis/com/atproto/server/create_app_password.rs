@@ -4,6 +4,8 @@ use crate::auth_verifier::AccessFull;
 use rocket::serde::json::Json;
 use rsky_lexicon::com::atproto::server::{CreateAppPasswordInput, CreateAppPasswordOutput};
 
+/// `privileged` app passwords also reach DMs and the other methods in
+/// [`PRIVILEGED_METHODS`](crate::pipethrough::PRIVILEGED_METHODS); none reach account management.
 #[tracing::instrument(skip_all)]
 #[rocket::post(
     "/xrpc/com.atproto.server.createAppPassword",
@@ -15,15 +17,19 @@ pub async fn create_app_password(
     auth: AccessFull,
     account_manager: AccountManager,
 ) -> Result<Json<CreateAppPasswordOutput>, ApiError> {
-    let CreateAppPasswordInput { name } = body.into_inner();
+    let CreateAppPasswordInput { name, privileged } = body.into_inner();
     match account_manager
-        .create_app_password(auth.access.credentials.unwrap().did.unwrap(), name)
+        .create_app_password(
+            auth.access.credentials.unwrap().did.unwrap(),
+            name,
+            privileged.unwrap_or(false),
+        )
         .await
     {
         Ok(app_password) => Ok(Json(app_password)),
         Err(error) => {
             tracing::error!("Internal Error: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -40,7 +40,7 @@ pub async fn create_invite_codes(
         })),
         Err(error) => {
             tracing::error!("Internal Error: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -1,19 +1,67 @@
 use crate::account_manager::helpers::account::AvailabilityFlags;
+use crate::account_manager::helpers::login_attempt::{
+    LoginAttemptOpts, LOGIN_METHOD_APP_PASSWORD, LOGIN_METHOD_PASSWORD,
+};
 use crate::account_manager::AccountManager;
+use crate::apis::com::atproto::server::assert_valid_session_name;
 use crate::apis::ApiError;
+use crate::client_ip::client_ip;
+use rocket::request::{FromRequest, Outcome, Request};
 use rocket::serde::json::Json;
 use rsky_lexicon::com::atproto::server::{CreateSessionInput, CreateSessionOutput};
 use rsky_syntax::handle::INVALID_HANDLE;
 
+/// Where a request came from, as recorded against login attempts.
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientInfo {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(ClientInfo {
+            ip: client_ip(req).map(|ip| ip.to_string()),
+            user_agent: req.headers().get_one("User-Agent").map(str::to_owned),
+        })
+    }
+}
+
+pub async fn record_login_attempt(
+    account_manager: &AccountManager,
+    client: &ClientInfo,
+    did: &str,
+    success: bool,
+    method: &'static str,
+) {
+    let opts = LoginAttemptOpts {
+        did: did.to_owned(),
+        success,
+        method,
+        ip: client.ip.clone(),
+        user_agent: client.user_agent.clone(),
+    };
+    // the audit trail is best effort and never blocks a login
+    if let Err(error) = account_manager.record_login_attempt(opts).await {
+        tracing::error!("@LOG: ERROR: failed to record login attempt for {did}: {error}");
+    }
+}
+
 #[tracing::instrument(skip_all)]
 async fn inner_create_session(
     body: Json<CreateSessionInput>,
+    client: ClientInfo,
     account_manager: AccountManager,
 ) -> Result<CreateSessionOutput, ApiError> {
     let CreateSessionInput {
         password,
         identifier,
+        session_name,
+        auth_factor_token,
     } = body.into_inner();
+    assert_valid_session_name(&session_name)?;
     let identifier = identifier.to_lowercase();
 
     let user = match identifier.contains("@") {
@@ -41,7 +89,7 @@ async fn inner_create_session(
         }
     };
     if let Ok(Some(user)) = user {
-        let mut app_password_name: Option<String> = None;
+        let mut app_password = None;
 
         let valid_account_pass = match account_manager
             .verify_account_password(&user.did, &password)
@@ -50,7 +98,7 @@ async fn inner_create_session(
             Ok(res) => res,
             Err(e) => {
                 tracing::error!("{e:?}");
-                return Err(ApiError::RuntimeError);
+                return Err(e.into());
             }
         };
         if !valid_account_pass {
@@ -59,23 +107,55 @@ async fn inner_create_session(
                 .await
             {
                 Ok(res) => {
-                    app_password_name = res;
+                    app_password = res;
                 }
                 Err(e) => {
                     tracing::error!("{e:?}");
-                    return Err(ApiError::RuntimeError);
+                    return Err(e.into());
                 }
             }
-            if app_password_name.is_none() {
+            if app_password.is_none() {
+                record_login_attempt(
+                    &account_manager,
+                    &client,
+                    &user.did,
+                    false,
+                    LOGIN_METHOD_PASSWORD,
+                )
+                .await;
                 return Err(ApiError::InvalidLogin);
             }
         }
         if user.takedown_ref.is_some() {
             return Err(ApiError::AccountTakendown);
         }
+        // app passwords are a second credential already, so only the account password needs one
+        if app_password.is_none() {
+            if let Err(error) = account_manager
+                .verify_auth_factor(&user.did, auth_factor_token)
+                .await
+            {
+                let error = ApiError::from(error);
+                if !matches!(error, ApiError::AuthFactorRequired) {
+                    record_login_attempt(
+                        &account_manager,
+                        &client,
+                        &user.did,
+                        false,
+                        LOGIN_METHOD_PASSWORD,
+                    )
+                    .await;
+                }
+                return Err(error);
+            }
+        }
+        let method = match app_password {
+            Some(_) => LOGIN_METHOD_APP_PASSWORD,
+            None => LOGIN_METHOD_PASSWORD,
+        };
         let (access_jwt, refresh_jwt);
         match account_manager
-            .create_session(user.did.clone(), app_password_name)
+            .create_session(user.did.clone(), app_password, session_name)
             .await
         {
             Ok(res) => {
@@ -83,9 +163,10 @@ async fn inner_create_session(
             }
             Err(e) => {
                 tracing::error!("{e:?}");
-                return Err(ApiError::RuntimeError);
+                return Err(e.into());
             }
         }
+        record_login_attempt(&account_manager, &client, &user.did, true, method).await;
         Ok(CreateSessionOutput {
             did: user.did,
             did_doc: None,
@@ -107,10 +188,11 @@ async fn inner_create_session(
 )]
 pub async fn create_session(
     body: Json<CreateSessionInput>,
+    client: ClientInfo,
     account_manager: AccountManager,
 ) -> Result<Json<CreateSessionOutput>, ApiError> {
     // @TODO: Add rate limiting
-    match inner_create_session(body, account_manager).await {
+    match inner_create_session(body, client, account_manager).await {
         Ok(res) => Ok(Json(res)),
         Err(error) => Err(error),
     }
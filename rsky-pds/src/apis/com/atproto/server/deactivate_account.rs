@@ -1,8 +1,11 @@
+use crate::account_manager::helpers::account::AccountStatusTransition;
 use crate::account_manager::AccountManager;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessFull;
+use crate::SharedSequencer;
 use anyhow::Result;
 use rocket::serde::json::Json;
+use rocket::State;
 use rsky_lexicon::com::atproto::server::DeactivateAccountInput;
 
 #[tracing::instrument(skip_all)]
@@ -13,16 +16,24 @@ use rsky_lexicon::com::atproto::server::DeactivateAccountInput;
 )]
 pub async fn deactivate_account(
     body: Json<DeactivateAccountInput>,
+    sequencer: &State<SharedSequencer>,
     auth: AccessFull,
     account_manager: AccountManager,
 ) -> Result<(), ApiError> {
     let did = auth.access.credentials.unwrap().did.unwrap();
     let DeactivateAccountInput { delete_after } = body.into_inner();
-    match account_manager.deactivate_account(&did, delete_after).await {
-        Ok(()) => Ok(()),
+    match account_manager
+        .update_account_status(
+            &did,
+            vec![AccountStatusTransition::Deactivate { delete_after }],
+            sequencer,
+        )
+        .await
+    {
+        Ok(_) => Ok(()),
         Err(error) => {
             tracing::error!("Internal Error: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -1,18 +1,48 @@
-use crate::account_manager::helpers::account::{AccountStatus, AvailabilityFlags};
+use crate::account_manager::helpers::account::{
+    AccountStatusTransition, ActorAccount, AvailabilityFlags,
+};
 use crate::account_manager::AccountManager;
 use crate::actor_store::aws::s3::S3BlobStore;
 use crate::actor_store::ActorStore;
 use crate::apis::ApiError;
 use crate::auth_verifier::AdminToken;
+use crate::config::ServerConfig;
 use crate::db::DbConn;
 use crate::models::models::EmailTokenPurpose;
-use crate::sequencer;
 use crate::SharedSequencer;
 use aws_config::SdkConfig;
 use rocket::serde::json::Json;
 use rocket::State;
+use rsky_common::time::from_micros_to_str;
 use rsky_lexicon::com::atproto::server::DeleteAccountInput;
 
+/// Accounts that took a migration export are kept for the migration retention, so the user can
+/// still fetch their data or come back if the move goes wrong.
+async fn assert_not_migrating(
+    account: &ActorAccount,
+    account_manager: &AccountManager,
+    cfg: &ServerConfig,
+) -> Result<(), ApiError> {
+    let since = from_micros_to_str(
+        chrono::Utc::now().timestamp_micros()
+            - cfg.account_export.migration_retention as i64 * 1_000_000,
+    );
+    if !account_manager
+        .has_migration_export_since(&account.did, since)
+        .await?
+    {
+        return Ok(());
+    }
+    match &account.delete_after {
+        Some(delete_after) => Err(ApiError::InvalidRequest(format!(
+            "Account is migrating and can't be deleted before {delete_after}"
+        ))),
+        None => Err(ApiError::InvalidRequest(
+            "Account is migrating and can't be deleted yet".to_string(),
+        )),
+    }
+}
+
 #[tracing::instrument(skip_all)]
 async fn inner_delete_account(
     body: Json<DeleteAccountInput>,
@@ -20,6 +50,7 @@ async fn inner_delete_account(
     s3_config: &State<SdkConfig>,
     db: DbConn,
     account_manager: AccountManager,
+    cfg: &State<ServerConfig>,
 ) -> Result<(), ApiError> {
     let DeleteAccountInput {
         did,
@@ -35,7 +66,8 @@ async fn inner_delete_account(
             }),
         )
         .await?;
-    if account.is_some() {
+    if let Some(account) = account {
+        assert_not_migrating(&account, &account_manager, cfg).await?;
         let valid_pass = account_manager
             .verify_account_password(&did, &password)
             .await?;
@@ -49,16 +81,13 @@ async fn inner_delete_account(
         let mut actor_store =
             ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
         actor_store.destroy().await?;
-        account_manager.delete_account(&did).await?;
-        let mut lock = sequencer.sequencer.write().await;
-        let account_seq = lock
-            .sequence_account_evt(did.clone(), AccountStatus::Deleted)
+        account_manager
+            .update_account_status(&did, vec![AccountStatusTransition::Delete], sequencer)
             .await?;
-        sequencer::delete_all_for_user(&did, Some(vec![account_seq])).await?;
         Ok(())
     } else {
         tracing::error!("account not found");
-        Err(ApiError::RuntimeError)
+        Err(ApiError::AccountNotFound)
     }
 }
 
@@ -75,8 +104,9 @@ pub async fn delete_account(
     db: DbConn,
     _auth: AdminToken,
     account_manager: AccountManager,
+    cfg: &State<ServerConfig>,
 ) -> Result<(), ApiError> {
-    match inner_delete_account(body, sequencer, s3_config, db, account_manager).await {
+    match inner_delete_account(body, sequencer, s3_config, db, account_manager, cfg).await {
         Ok(_) => Ok(()),
         Err(error) => Err(error),
     }
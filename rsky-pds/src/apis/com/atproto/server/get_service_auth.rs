@@ -1,61 +1,89 @@
 use crate::account_manager::helpers::auth::{create_service_jwt, ServiceJwtParams};
 use crate::apis::ApiError;
-use crate::auth_verifier::AccessFull;
+use crate::auth_verifier::AccessStandard;
+use crate::config::keys;
 use crate::pipethrough::{PRIVILEGED_METHODS, PROTECTED_METHODS};
-use anyhow::{bail, Result};
-use chrono::offset::Utc as UtcOffset;
-use chrono::DateTime;
 use rocket::serde::json::Json;
-use rsky_common::time::{from_micros_to_utc, HOUR, MINUTE};
+use rsky_common::time::{HOUR, MINUTE};
 use rsky_lexicon::com::atproto::server::GetServiceAuthOutput;
-use secp256k1::SecretKey;
-use std::env;
 use std::time::SystemTime;
 
+/// Checks a requested `exp` (Unix seconds) against `now`: tokens bound to a method may live up
+/// to an hour, method-less ones, which are good for any method, up to a minute.
+pub fn check_expiration(exp: u64, lxm: Option<&str>, now: u64) -> Result<(), ApiError> {
+    let bad_expiration = |message: &str| {
+        Err(ApiError::BadRequest(
+            "BadExpiration".to_string(),
+            message.to_string(),
+        ))
+    };
+    // HOUR and MINUTE are in milliseconds
+    let diff = exp as i64 - now as i64;
+    if diff < 0 {
+        bad_expiration("expiration is in past")
+    } else if diff > (HOUR / 1000) as i64 {
+        bad_expiration("cannot request a token with an expiration more than an hour in the future")
+    } else if lxm.is_none() && diff > (MINUTE / 1000) as i64 {
+        bad_expiration(
+            "cannot request a method-less token with an expiration more than a minute in the future",
+        )
+    } else {
+        Ok(())
+    }
+}
+
 pub async fn inner_get_service_auth(
     aud: String,
     exp: Option<u64>,
     lxm: Option<String>,
-    auth: AccessFull,
-) -> Result<String> {
+    auth: AccessStandard,
+) -> Result<String, ApiError> {
     let credentials = auth.access.credentials.unwrap();
-    let did = credentials.clone().did.unwrap();
-    // We just use the repo signing key
-    let private_key = env::var("PDS_REPO_SIGNING_KEY_K256_PRIVATE_KEY_HEX").unwrap();
-    let keypair = SecretKey::from_slice(&hex::decode(private_key.as_bytes()).unwrap()).unwrap();
-    let exp = match exp {
-        None => None,
-        Some(exp) => Some(exp * 1000),
-    };
+    let did = credentials.did.unwrap();
+    if !aud.starts_with("did:") {
+        return Err(ApiError::InvalidRequest(format!(
+            "aud must be a DID: {aud}"
+        )));
+    }
     if let Some(exp) = exp {
-        let system_time = SystemTime::now();
-        let now: DateTime<UtcOffset> = system_time.into();
-        let diff = from_micros_to_utc(exp as i64) - now;
-        if diff.num_milliseconds() < 0 {
-            bail!("BadExpiration: expiration is in past");
-        } else if diff.num_milliseconds() > HOUR as i64 {
-            bail!("BadExpiration: cannot request a token with an expiration more than an hour in the future");
-        } else if lxm.is_none() && diff.num_milliseconds() > MINUTE as i64 {
-            bail!("BadExpiration: cannot request a method-less token with an expiration more than a minute in the future");
-        }
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("timestamp in seconds since UNIX epoch")
+            .as_secs();
+        check_expiration(exp, lxm.as_deref(), now)?;
     }
     if let Some(ref lxm) = lxm {
         if PROTECTED_METHODS.contains(lxm.as_str()) {
-            bail!("cannot request a service auth token for the following protected method: {lxm}");
+            return Err(ApiError::InvalidRequest(format!(
+                "cannot request a service auth token for the following protected method: {lxm}"
+            )));
         }
-        if credentials.is_privileged.unwrap_or(false) && PRIVILEGED_METHODS.contains(lxm.as_str()) {
-            bail!("insufficient access to request a service auth token for the following method: {lxm}");
+        if !credentials.is_privileged.unwrap_or(false) && PRIVILEGED_METHODS.contains(lxm.as_str())
+        {
+            return Err(ApiError::InvalidRequest(format!(
+                "insufficient access to request a service auth token for the following method: {lxm}"
+            )));
         }
     }
+    // Every account's repo is signed with the service's repo signing key, which is the
+    // #atproto verification method in their DID documents, so that's the key services expect.
+    let keypair = keys::repo_signing_key().map_err(|error| {
+        tracing::error!("@LOG: ERROR: failed to load repo signing key: {error}");
+        ApiError::RuntimeError
+    })?;
     create_service_jwt(ServiceJwtParams {
         iss: did,
         aud,
-        exp: None,
+        exp,
         lxm,
         jti: None,
         keypair,
     })
     .await
+    .map_err(|error| {
+        tracing::error!("@LOG: ERROR: failed to sign service auth token: {error}");
+        ApiError::RuntimeError
+    })
 }
 
 /// Get a signed token on behalf of the requesting DID for the requested service.
@@ -69,13 +97,23 @@ pub async fn get_service_auth(
     exp: Option<u64>,
     // Lexicon (XRPC) method to bind the requested token to
     lxm: Option<String>,
-    auth: AccessFull,
+    auth: AccessStandard,
 ) -> Result<Json<GetServiceAuthOutput>, ApiError> {
-    match inner_get_service_auth(aud, exp, lxm, auth).await {
-        Ok(token) => Ok(Json(GetServiceAuthOutput { token })),
-        Err(error) => {
-            tracing::error!("Internal Error: {error}");
-            Err(ApiError::RuntimeError)
-        }
+    let token = inner_get_service_auth(aud, exp, lxm, auth).await?;
+    Ok(Json(GetServiceAuthOutput { token }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_expiration() {
+        let now = 1_700_000_000;
+        assert!(check_expiration(now + 60, None, now).is_ok());
+        assert!(check_expiration(now + 61, None, now).is_err());
+        assert!(check_expiration(now + 3600, Some("app.bsky.feed.getFeed"), now).is_ok());
+        assert!(check_expiration(now + 3601, Some("app.bsky.feed.getFeed"), now).is_err());
+        assert!(check_expiration(now - 1, Some("app.bsky.feed.getFeed"), now).is_err());
     }
 }
@@ -1,13 +1,26 @@
+use crate::apis::ApiError;
+use crate::config::keys;
+use crate::http::{self, Destination};
 use crate::{plc, SharedIdResolver};
 use anyhow::{bail, Result};
 use rand::{distributions::Alphanumeric, Rng};
 use rocket::form::validate::Contains;
 use rocket::State;
 use rsky_common::env::{env_int, env_str};
-use rsky_crypto::utils::encode_did_key;
+use rsky_common::{get_service_endpoint, get_verification_material, GetServiceEndpointOpts};
+use rsky_identity::common::decode_uri_component;
+use rsky_identity::did::atproto_data::get_did_key_from_multibase;
+use rsky_identity::did::web_resolver::DOC_PATH;
 use rsky_identity::types::DidDocument;
 use secp256k1::{PublicKey, Secp256k1, SecretKey};
 use std::env;
+use std::net::IpAddr;
+use url::Url;
+
+const MAX_DID_DOC_SIZE: usize = 64 * 1024;
+
+/// Longest session name, in characters, accepted by createSession and refreshSession.
+pub const MAX_SESSION_NAME_LENGTH: usize = 64;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AssertionContents {
@@ -74,6 +87,17 @@ pub fn validate_handle(handle: &str) -> bool {
     // Need to check suffix here and need to make sure handle doesn't include "." after trumming it
 }
 
+pub fn assert_valid_session_name(session_name: &Option<String>) -> Result<(), ApiError> {
+    match session_name {
+        Some(session_name) if session_name.chars().count() > MAX_SESSION_NAME_LENGTH => {
+            Err(ApiError::InvalidRequest(format!(
+                "Session name must be at most {MAX_SESSION_NAME_LENGTH} characters"
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
 pub fn get_keys_from_private_key_str(private_key: String) -> Result<(SecretKey, PublicKey)> {
     let secp = Secp256k1::new();
     let decoded_key = hex::decode(private_key.as_bytes()).map_err(|error| {
@@ -114,21 +138,87 @@ pub async fn assert_valid_did_documents_for_service(did: String) -> Result<()> {
             rotation_keys: Some(resolved.rotation_keys),
         })
         .await?;
+    } else if did.starts_with("did:web:") {
+        // fetched fresh rather than cached, so a document that was just published counts
+        let Some(doc) = fetch_did_web_doc(&did).await? else {
+            bail!("DID document not found for {did}")
+        };
+        let signing_key = match get_verification_material(&doc, "atproto") {
+            Some(key) => get_did_key_from_multibase(key)?,
+            None => None,
+        };
+        let pds_endpoint = get_service_endpoint(
+            doc,
+            GetServiceEndpointOpts {
+                id: "#atproto_pds".to_string(),
+                r#type: Some("AtprotoPersonalDataServer".to_string()),
+            },
+        );
+        // did:web has no rotation keys; whoever hosts the document controls it
+        assert_valid_doc_contents(AssertionContents {
+            pds_endpoint,
+            signing_key,
+            rotation_keys: None,
+        })
+        .await?;
     } else {
-        bail!("Not yet supporting did:web")
+        bail!("Unsupported DID method: {did}")
     }
     Ok(())
 }
 
+/// Where a did:web publishes its document. Only domains are allowed, since the document is
+/// fetched on behalf of whoever named the DID; their addresses are checked when it's fetched.
+pub fn did_web_url(did: &str) -> Result<Url> {
+    let Some(host) = did.strip_prefix("did:web:") else {
+        bail!("Not a did:web: {did}")
+    };
+    if host.contains(':') {
+        bail!("did:web with a path is not supported: {did}")
+    }
+    let url = Url::parse(&format!(
+        "https://{}{DOC_PATH}",
+        decode_uri_component(host)?
+    ))?;
+    match url.host_str() {
+        Some(host) if host.parse::<IpAddr>().is_err() && host.contains('.') => Ok(url),
+        _ => bail!("did:web must be on a public domain: {did}"),
+    }
+}
+
+/// Fetches a did:web document fresh, only from public addresses and without following
+/// redirects, see [`http`].
+pub async fn fetch_did_web_doc(did: &str) -> Result<Option<DidDocument>> {
+    let mut res = http::get(Destination::DidWeb, did_web_url(did)?)
+        .header("Accept", "application/json")
+        .send()
+        .await?;
+    if res.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    res = res.error_for_status()?;
+    let mut body = Vec::new();
+    while let Some(chunk) = res.chunk().await? {
+        body.extend_from_slice(&chunk);
+        if body.len() > MAX_DID_DOC_SIZE {
+            bail!("DID document for {did} is too large")
+        }
+    }
+    let doc: DidDocument = serde_json::from_slice(&body)?;
+    if doc.id != did {
+        bail!("DID document id does not match {did}")
+    }
+    Ok(Some(doc))
+}
+
 pub async fn assert_valid_doc_contents(contents: AssertionContents) -> Result<()> {
     let AssertionContents {
         signing_key,
         pds_endpoint,
         rotation_keys,
     } = contents;
-    let private_key = env::var("PDS_PLC_ROTATION_KEY_K256_PRIVATE_KEY_HEX").unwrap();
-    let (_, plc_rotation_key) = get_keys_from_private_key_str(private_key)?;
-    let plc_rotation_key = encode_did_key(&plc_rotation_key);
+    let service_keys = keys::service_keys()?;
+    let plc_rotation_key = service_keys.plc_rotation_did_key();
 
     if let Some(rotation_keys) = rotation_keys {
         if !rotation_keys.contains(plc_rotation_key) {
@@ -148,12 +238,7 @@ pub async fn assert_valid_doc_contents(contents: AssertionContents) -> Result<()
         bail!("DID document atproto_pds service endpoint does not match PDS public url")
     }
 
-    let repo_signing_key = env::var("PDS_REPO_SIGNING_KEY_K256_PRIVATE_KEY_HEX").unwrap();
-    let repo_signing_keypair =
-        SecretKey::from_slice(&hex::decode(repo_signing_key.as_bytes()).unwrap()).unwrap();
-    let secp = Secp256k1::new();
-    let repo_public_key = repo_signing_keypair.public_key(&secp);
-    if signing_key.is_none() || signing_key.unwrap() != encode_did_key(&repo_public_key) {
+    if signing_key.is_none() || signing_key.unwrap() != service_keys.repo_signing_did_key() {
         bail!("DID document verification method does not match expected signing key")
     }
     Ok(())
@@ -193,3 +278,53 @@ pub mod reserve_signing_key;
 pub mod reset_password;
 pub mod revoke_app_password;
 pub mod update_email;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assert_valid_session_name() {
+        assert!(assert_valid_session_name(&None).is_ok());
+        assert!(assert_valid_session_name(&Some("phone".to_string())).is_ok());
+        assert!(assert_valid_session_name(&Some("é".repeat(MAX_SESSION_NAME_LENGTH))).is_ok());
+        assert!(matches!(
+            assert_valid_session_name(&Some("a".repeat(MAX_SESSION_NAME_LENGTH + 1))),
+            Err(ApiError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_did_web_url() {
+        assert_eq!(
+            did_web_url("did:web:alice.example.com").unwrap().as_str(),
+            "https://alice.example.com/.well-known/did.json"
+        );
+        assert_eq!(
+            did_web_url("did:web:alice.example.com%3A8443")
+                .unwrap()
+                .as_str(),
+            "https://alice.example.com:8443/.well-known/did.json"
+        );
+        for did in [
+            "did:plc:abc",
+            "did:web:localhost",
+            "did:web:localhost%3A2583",
+            "did:web:127.0.0.1",
+            "did:web:169.254.169.254",
+            "did:web:%5B%3A%3A1%5D",
+            "did:web:example.com:users:alice",
+        ] {
+            assert!(did_web_url(did).is_err(), "{did}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_did_web_account_on_localhost_is_refused() {
+        assert!(
+            assert_valid_did_documents_for_service("did:web:localhost".to_string())
+                .await
+                .is_err()
+        );
+    }
+}
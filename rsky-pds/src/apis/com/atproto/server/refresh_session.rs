@@ -1,13 +1,15 @@
 use crate::account_manager::helpers::account::AvailabilityFlags;
 use crate::account_manager::AccountManager;
+use crate::apis::com::atproto::server::assert_valid_session_name;
 use crate::apis::ApiError;
 use crate::auth_verifier::{Credentials, Refresh};
 use anyhow::Result;
 use rocket::serde::json::Json;
-use rsky_lexicon::com::atproto::server::RefreshSessionOutput;
+use rsky_lexicon::com::atproto::server::{RefreshSessionInput, RefreshSessionOutput};
 use rsky_syntax::handle::INVALID_HANDLE;
 
 async fn inner_refresh_session(
+    body: Option<Json<RefreshSessionInput>>,
     auth: Refresh,
     account_manager: AccountManager,
 ) -> Result<RefreshSessionOutput, ApiError> {
@@ -28,7 +30,11 @@ async fn inner_refresh_session(
         if user.takedown_ref.is_some() {
             return Err(ApiError::AccountTakendown);
         }
-        let rotated = account_manager.rotate_refresh_token(&token_id).await?;
+        let session_name = body.and_then(|body| body.into_inner().session_name);
+        assert_valid_session_name(&session_name)?;
+        let rotated = account_manager
+            .rotate_refresh_token(&token_id, session_name)
+            .await?;
         if let Some(rotated) = rotated {
             Ok(RefreshSessionOutput {
                 handle: user.handle.unwrap_or(INVALID_HANDLE.to_string()),
@@ -46,12 +52,13 @@ async fn inner_refresh_session(
 }
 
 #[tracing::instrument(skip_all)]
-#[rocket::post("/xrpc/com.atproto.server.refreshSession")]
+#[rocket::post("/xrpc/com.atproto.server.refreshSession", data = "<body>")]
 pub async fn refresh_session(
+    body: Option<Json<RefreshSessionInput>>,
     auth: Refresh,
     account_manager: AccountManager,
 ) -> Result<Json<RefreshSessionOutput>, ApiError> {
-    match inner_refresh_session(auth, account_manager).await {
+    match inner_refresh_session(body, auth, account_manager).await {
         Ok(res) => Ok(Json(res)),
         Err(error) => Err(error),
     }
@@ -2,6 +2,7 @@ use crate::account_manager::helpers::account::AvailabilityFlags;
 use crate::account_manager::AccountManager;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandardIncludeChecks;
+use crate::locale::RequestLocale;
 use crate::mailer;
 use crate::mailer::TokenParam;
 use crate::models::models::EmailTokenPurpose;
@@ -9,6 +10,7 @@ use anyhow::{bail, Result};
 
 async fn inner_request_email_confirmation(
     auth: AccessStandardIncludeChecks,
+    locale: RequestLocale,
     account_manager: AccountManager,
 ) -> Result<()> {
     let did = auth.access.credentials.unwrap().did.unwrap();
@@ -23,10 +25,12 @@ async fn inner_request_email_confirmation(
         .await?;
     if let Some(account) = account {
         if let Some(email) = account.email {
+            account_manager.assert_email_deliverable(&email).await?;
             let token = account_manager
                 .create_email_token(&did, EmailTokenPurpose::ConfirmEmail)
                 .await?;
-            mailer::send_confirm_email(email, TokenParam { token }).await?;
+            let locale = account_manager.mail_locale(&did, locale).await?;
+            mailer::send_confirm_email(email, TokenParam { token }, locale.as_deref()).await?;
             Ok(())
         } else {
             bail!("Account does not have an email address")
@@ -40,13 +44,14 @@ async fn inner_request_email_confirmation(
 #[rocket::post("/xrpc/com.atproto.server.requestEmailConfirmation")]
 pub async fn request_email_confirmation(
     auth: AccessStandardIncludeChecks,
+    locale: RequestLocale,
     account_manager: AccountManager,
 ) -> Result<(), ApiError> {
-    match inner_request_email_confirmation(auth, account_manager).await {
+    match inner_request_email_confirmation(auth, locale, account_manager).await {
         Ok(_) => Ok(()),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -2,6 +2,7 @@ use crate::account_manager::helpers::account::AvailabilityFlags;
 use crate::account_manager::AccountManager;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandardIncludeChecks;
+use crate::locale::RequestLocale;
 use crate::mailer;
 use crate::mailer::TokenParam;
 use crate::models::models::EmailTokenPurpose;
@@ -11,6 +12,7 @@ use rsky_lexicon::com::atproto::server::RequestEmailUpdateOutput;
 
 async fn inner_request_email_update(
     auth: AccessStandardIncludeChecks,
+    locale: RequestLocale,
     account_manager: AccountManager,
 ) -> Result<RequestEmailUpdateOutput> {
     let did = auth.access.credentials.unwrap().did.unwrap();
@@ -27,10 +29,12 @@ async fn inner_request_email_update(
         if let Some(email) = account.email {
             let token_required = account.email_confirmed_at.is_some();
             if token_required {
+                account_manager.assert_email_deliverable(&email).await?;
                 let token = account_manager
                     .create_email_token(&did, EmailTokenPurpose::UpdateEmail)
                     .await?;
-                mailer::send_update_email(email, TokenParam { token }).await?;
+                let locale = account_manager.mail_locale(&did, locale).await?;
+                mailer::send_update_email(email, TokenParam { token }, locale.as_deref()).await?;
             }
 
             Ok(RequestEmailUpdateOutput { token_required })
@@ -46,13 +50,14 @@ async fn inner_request_email_update(
 #[rocket::post("/xrpc/com.atproto.server.requestEmailUpdate")]
 pub async fn request_email_update(
     auth: AccessStandardIncludeChecks,
+    locale: RequestLocale,
     account_manager: AccountManager,
 ) -> Result<Json<RequestEmailUpdateOutput>, ApiError> {
-    match inner_request_email_update(auth, account_manager).await {
+    match inner_request_email_update(auth, locale, account_manager).await {
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -2,6 +2,7 @@ use crate::account_manager::helpers::account::AvailabilityFlags;
 use crate::account_manager::AccountManager;
 use crate::apis::ApiError;
 use crate::auth_verifier::AccessStandardIncludeChecks;
+use crate::locale::RequestLocale;
 use crate::mailer;
 use crate::mailer::IdentifierAndTokenParams;
 use crate::models::models::EmailTokenPurpose;
@@ -11,6 +12,7 @@ use rsky_lexicon::com::atproto::server::RequestPasswordResetInput;
 
 async fn inner_request_password_reset(
     body: Json<RequestPasswordResetInput>,
+    locale: RequestLocale,
     account_manager: AccountManager,
 ) -> Result<()> {
     let RequestPasswordResetInput { email } = body.into_inner();
@@ -28,15 +30,22 @@ async fn inner_request_password_reset(
 
     if let Some(account) = account {
         if let Some(email) = account.email {
+            // answers like a sent email, so the response doesn't reveal whose address bounced
+            if account_manager.is_email_undeliverable(&email).await? {
+                tracing::info!("@LOG: skipped password reset to undeliverable {email}");
+                return Ok(());
+            }
             let token = account_manager
                 .create_email_token(&account.did, EmailTokenPurpose::ResetPassword)
                 .await?;
+            let locale = account_manager.mail_locale(&account.did, locale).await?;
             mailer::send_reset_password(
                 email.clone(),
                 IdentifierAndTokenParams {
                     identifier: account.handle.unwrap_or(email),
                     token,
                 },
+                locale.as_deref(),
             )
             .await?;
             Ok(())
@@ -57,13 +66,14 @@ async fn inner_request_password_reset(
 pub async fn request_password_reset(
     body: Json<RequestPasswordResetInput>,
     _auth: AccessStandardIncludeChecks,
+    locale: RequestLocale,
     account_manager: AccountManager,
 ) -> Result<(), ApiError> {
-    match inner_request_password_reset(body, account_manager).await {
+    match inner_request_password_reset(body, locale, account_manager).await {
         Ok(_) => Ok(()),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -1,6 +1,8 @@
 use crate::account_manager::{AccountManager, ResetPasswordOpts};
 use crate::apis::ApiError;
+use crate::config::ServerConfig;
 use rocket::serde::json::Json;
+use rocket::State;
 use rsky_lexicon::com::atproto::server::ResetPasswordInput;
 
 #[tracing::instrument(skip_all)]
@@ -11,17 +13,22 @@ use rsky_lexicon::com::atproto::server::ResetPasswordInput;
 )]
 pub async fn reset_password(
     body: Json<ResetPasswordInput>,
+    cfg: &State<ServerConfig>,
     account_manager: AccountManager,
 ) -> Result<(), ApiError> {
     let ResetPasswordInput { token, password } = body.into_inner();
     match account_manager
-        .reset_password(ResetPasswordOpts { token, password })
+        .reset_password(ResetPasswordOpts {
+            token,
+            password,
+            revoke_app_passwords: !cfg.service.app_passwords_survive_password_reset,
+        })
         .await
     {
         Ok(_) => Ok(()),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
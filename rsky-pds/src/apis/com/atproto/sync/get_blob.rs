@@ -5,13 +5,15 @@ use crate::apis::com::atproto::repo::assert_repo_availability;
 use crate::apis::ApiError;
 use crate::auth_verifier;
 use crate::auth_verifier::OptionalAccessOrAdminToken;
+use crate::config::ServerConfig;
 use crate::db::DbConn;
-use anyhow::Result;
+use anyhow::{bail, Result};
 use aws_config::SdkConfig;
 use aws_sdk_s3::operation::get_object::GetObjectError;
 use aws_sdk_s3::primitives::AggregatedBytes;
 use lexicon_cid::Cid;
-use rocket::http::Header;
+use rocket::http::{Header, Status};
+use rocket::response::Redirect;
 use rocket::{Responder, State};
 use std::str::FromStr;
 
@@ -19,14 +21,26 @@ use std::str::FromStr;
 #[response(status = 200)]
 pub struct BlobResponder(Vec<u8>, Header<'static>, Header<'static>, Header<'static>);
 
+#[derive(Responder)]
+pub enum GetBlobResponder {
+    Blob(BlobResponder),
+    Redirect(Redirect),
+}
+
+enum GetBlobOutput {
+    Bytes(Vec<u8>, Option<String>),
+    Url(String),
+}
+
 async fn inner_get_blob(
     did: String,
     cid: String,
     s3_config: &State<SdkConfig>,
+    cfg: &State<ServerConfig>,
     auth: OptionalAccessOrAdminToken,
     db: DbConn,
     account_manager: AccountManager,
-) -> Result<(Vec<u8>, Option<String>)> {
+) -> Result<GetBlobOutput> {
     let is_user_or_admin = if let Some(access) = auth.access {
         auth_verifier::is_user_or_admin(access, &did)
     } else {
@@ -37,36 +51,52 @@ async fn inner_get_blob(
     let cid = Cid::from_str(&cid)?;
     let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
 
+    if let Some(status) = cfg.blob_moderation.status {
+        let labels = cfg.blob_moderation.labels.clone();
+        if let Some(reason) = actor_store.blob.get_blob_moderation(cid, labels).await? {
+            bail!(ApiError::BlobUnavailable(
+                Status::new(status),
+                format!("Blob is unavailable: {reason}")
+            ));
+        }
+    }
+
+    if let Some(redirect) = &cfg.blob_redirect {
+        let url = actor_store.blob.get_blob_url(cid, redirect).await?;
+        return Ok(GetBlobOutput::Url(url));
+    }
     let found = actor_store.blob.get_blob(cid).await?;
     let buf: AggregatedBytes = found.stream.collect().await?;
-    Ok((buf.to_vec(), found.mime_type))
+    Ok(GetBlobOutput::Bytes(buf.to_vec(), found.mime_type))
 }
 
 /// Get a blob associated with a given account. Returns the full blob as originally uploaded.
-/// Does not require auth; implemented by PDS.
+/// Does not require auth; implemented by PDS. When `PDS_BLOB_REDIRECT` is configured, redirects
+/// to an expiring signed URL on the blobstore or CDN instead of proxying the bytes. When
+/// `PDS_BLOB_MODERATED_STATUS` is configured, moderated media is answered with that status and a
+/// `BlobUnavailable` error instead.
 #[tracing::instrument(skip_all)]
 #[rocket::get("/xrpc/com.atproto.sync.getBlob?<did>&<cid>")]
 pub async fn get_blob(
     did: String,
     cid: String,
     s3_config: &State<SdkConfig>,
+    cfg: &State<ServerConfig>,
     auth: OptionalAccessOrAdminToken,
     db: DbConn,
     account_manager: AccountManager,
-) -> Result<BlobResponder, ApiError> {
-    match inner_get_blob(did, cid, s3_config, auth, db, account_manager).await {
-        Ok(res) => {
-            let (bytes, mime_type) = res;
-            Ok(BlobResponder(
-                bytes.clone(),
-                Header::new("content-length", bytes.len().to_string()),
-                Header::new(
-                    "content-type",
-                    mime_type.unwrap_or("application/octet-stream".to_string()),
-                ),
-                Header::new("content-security-policy", "default-src 'none'; sandbox"),
-            ))
-        }
+) -> Result<GetBlobResponder, ApiError> {
+    match inner_get_blob(did, cid, s3_config, cfg, auth, db, account_manager).await {
+        Ok(GetBlobOutput::Url(url)) => Ok(GetBlobResponder::Redirect(Redirect::temporary(url))),
+        Ok(GetBlobOutput::Bytes(bytes, mime_type)) => Ok(GetBlobResponder::Blob(BlobResponder(
+            bytes.clone(),
+            Header::new("content-length", bytes.len().to_string()),
+            Header::new(
+                "content-type",
+                mime_type.unwrap_or("application/octet-stream".to_string()),
+            ),
+            Header::new("content-security-policy", "default-src 'none'; sandbox"),
+        ))),
         Err(error) => {
             match error.downcast_ref() {
                 Some(GetObjectError::NoSuchKey(_)) => {
@@ -75,7 +105,7 @@ pub async fn get_blob(
                 }
                 _ => {
                     tracing::error!("Error: {}", error);
-                    Err(ApiError::RuntimeError)
+                    Err(error.into())
                 }
             }
             // @TODO: Need to update error handling to return 404 if we have it but it's in tmp
@@ -12,6 +12,7 @@ use lexicon_cid::Cid;
 use rocket::{Responder, State};
 use rsky_repo::car::blocks_to_car_file;
 use rsky_repo::storage::readable_blockstore::ReadableBlockstore;
+use rsky_repo::storage::types::RepoStorage;
 use std::str::FromStr;
 
 #[derive(Responder)]
@@ -33,13 +34,18 @@ async fn inner_get_blocks(
     };
     let _ = assert_repo_availability(&did, is_user_or_admin, &account_manager).await?;
 
-    let cids: Vec<Cid> = cids
-        .into_iter()
-        .map(|c| Cid::from_str(&c).map_err(anyhow::Error::new))
-        .collect::<Result<Vec<Cid>>>()?;
+    let cids: Vec<Cid> = match cids.iter().map(|c| Cid::from_str(c)).collect() {
+        Ok(cids) => cids,
+        Err(error) => bail!(ApiError::InvalidRequest(format!("Invalid cid: {error}"))),
+    };
 
     let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
     let storage_guard = actor_store.storage.read().await;
+    if storage_guard.get_root().await.is_none() {
+        bail!(ApiError::RepoNotFound(format!(
+            "Could not find repo for DID: {did}"
+        )));
+    }
     let got = storage_guard.get_blocks(cids).await?;
 
     if !got.missing.is_empty() {
@@ -48,7 +54,10 @@ async fn inner_get_blocks(
             .into_iter()
             .map(|c| c.to_string())
             .collect::<Vec<String>>();
-        bail!("Could not find cids: `{missing_str:?}`");
+        bail!(ApiError::BadRequest(
+            "BlockNotFound".to_string(),
+            format!("Could not find cids: `{missing_str:?}`")
+        ));
     }
 
     let car = blocks_to_car_file(None, got.blocks).await?;
@@ -71,7 +80,7 @@ pub async fn get_blocks(
         Ok(res) => Ok(BlockResponder(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
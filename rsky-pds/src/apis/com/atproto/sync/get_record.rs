@@ -37,19 +37,26 @@ async fn inner_get_record(
     let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
     let storage_guard = actor_store.storage.read().await;
     let commit: Option<Cid> = match commit {
-        Some(commit) => Some(Cid::from_str(&commit)?),
+        Some(commit) => match Cid::from_str(&commit) {
+            Ok(commit) => Some(commit),
+            Err(error) => bail!(ApiError::InvalidRequest(format!("Invalid commit: {error}"))),
+        },
         None => storage_guard.get_root().await,
     };
 
     match commit {
-        None => bail!("Could not find repo for DID: {did}"),
+        None => bail!(ApiError::RepoNotFound(format!(
+            "Could not find repo for DID: {did}"
+        ))),
         Some(commit) => {
-            rsky_repo::sync::provider::get_records(
+            // the proof walks the MST down to the record's key: the blocks on that path show
+            // the record is there (its block included) or that it isn't
+            Ok(rsky_repo::sync::provider::get_records(
                 actor_store.storage.clone(),
                 commit,
                 vec![RecordPath { collection, rkey }],
             )
-            .await
+            .await?)
         }
     }
 }
@@ -83,7 +90,7 @@ pub async fn get_record(
         Ok(res) => Ok(BlockResponder(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -8,23 +8,41 @@ use crate::auth_verifier::OptionalAccessOrAdminToken;
 use crate::db::DbConn;
 use anyhow::{bail, Result};
 use aws_config::SdkConfig;
+use futures::future::ready;
+use futures::{Stream, StreamExt};
+use rocket::response::stream::ByteStream;
 use rocket::{Responder, State};
+use std::pin::Pin;
+
+pub type CarStream = Pin<Box<dyn Stream<Item = Vec<u8>> + Send>>;
 
 #[derive(Responder)]
 #[response(status = 200, content_type = "application/vnd.ipld.car")]
-pub struct BlockResponder(Vec<u8>);
+pub struct BlockResponder(ByteStream<CarStream>);
 
 async fn get_car_stream(
     s3_config: &State<SdkConfig>,
     did: String,
     since: Option<String>,
     db: DbConn,
-) -> Result<Vec<u8>> {
+) -> Result<CarStream> {
     let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
     let storage_guard = actor_store.storage.read().await;
     match storage_guard.get_car_stream(since).await {
         Err(_) => bail!("Could not find repo for DID: {did}"),
-        Ok(carstream) => Ok(carstream),
+        Ok(carstream) => {
+            // the status is already sent by the time a block fails to load, so all that's
+            // left is to cut the CAR short
+            let carstream = carstream
+                .take_while(move |chunk| {
+                    if let Err(error) = chunk {
+                        tracing::error!("@LOG: ERROR: getRepo for {did} failed: {error}");
+                    }
+                    ready(chunk.is_ok())
+                })
+                .filter_map(|chunk| ready(chunk.ok()));
+            Ok(Box::pin(carstream))
+        }
     }
 }
 
@@ -35,7 +53,7 @@ async fn inner_get_repo(
     auth: OptionalAccessOrAdminToken,
     db: DbConn,
     account_manager: AccountManager,
-) -> Result<Vec<u8>> {
+) -> Result<CarStream> {
     let is_user_or_admin = if let Some(access) = auth.access {
         auth_verifier::is_user_or_admin(access, &did)
     } else {
@@ -58,10 +76,10 @@ pub async fn get_repo(
     account_manager: AccountManager,
 ) -> Result<BlockResponder, ApiError> {
     match inner_get_repo(did, since, s3_config, auth, db, account_manager).await {
-        Ok(res) => Ok(BlockResponder(res)),
+        Ok(res) => Ok(BlockResponder(ByteStream(res))),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
@@ -30,22 +30,23 @@ async fn inner_list_blobs(
     };
     let _ = assert_repo_availability(&did, is_user_or_admin, &account_manager).await?;
 
+    let limit = limit.unwrap_or(500).clamp(1, 1000);
     let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
     let blob_cids = actor_store
         .blob
         .list_blobs(ListBlobsOpts {
             since,
             cursor,
-            limit: limit.unwrap_or(500),
+            limit,
         })
         .await?;
 
-    let last_blob: Option<String> = match blob_cids.last() {
-        None => None,
-        Some(last) => Some(last.clone()),
-    };
     Ok(ListBlobsOutput {
-        cursor: last_blob,
+        // a short page means there's nothing after it
+        cursor: match blob_cids.last() {
+            Some(last) if blob_cids.len() == limit as usize => Some(last.clone()),
+            _ => None,
+        },
         cids: blob_cids,
     })
 }
@@ -79,7 +80,7 @@ pub async fn list_blobs(
         Ok(res) => Ok(Json(res)),
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
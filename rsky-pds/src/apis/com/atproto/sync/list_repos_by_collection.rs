@@ -0,0 +1,71 @@
+use crate::apis::ApiError;
+use crate::db::DbConn;
+use anyhow::{bail, Result};
+use diesel::prelude::*;
+use rocket::serde::json::Json;
+use rsky_lexicon::com::atproto::sync::{ListReposByCollectionOutput, RefRepoByCollection};
+use rsky_syntax::nsid::ensure_valid_nsid;
+
+async fn inner_list_repos_by_collection(
+    collection: String,
+    limit: Option<i64>,
+    cursor: Option<String>,
+    db: &DbConn,
+) -> Result<ListReposByCollectionOutput> {
+    if ensure_valid_nsid(&collection).is_err() {
+        bail!(ApiError::InvalidRequest(format!(
+            "Invalid collection NSID: {collection}"
+        )));
+    }
+    let limit = limit.unwrap_or(500).clamp(1, 2000);
+
+    use crate::schema::pds::actor::dsl as ActorSchema;
+    use crate::schema::pds::record::dsl as RecordSchema;
+
+    // backed by record_collection_did_idx, paging by did
+    let mut builder = RecordSchema::record
+        .inner_join(ActorSchema::actor.on(ActorSchema::did.eq(RecordSchema::did)))
+        .filter(RecordSchema::collection.eq(collection))
+        .filter(ActorSchema::takedownRef.is_null())
+        .filter(ActorSchema::deactivatedAt.is_null())
+        .select(RecordSchema::did)
+        .distinct()
+        .order(RecordSchema::did.asc())
+        .limit(limit)
+        .into_boxed();
+    if let Some(cursor) = cursor {
+        builder = builder.filter(RecordSchema::did.gt(cursor));
+    }
+    let dids: Vec<String> = db.run(move |conn| builder.load(conn)).await?;
+
+    Ok(ListReposByCollectionOutput {
+        // a short page means there's nothing after it
+        cursor: match dids.last() {
+            Some(last) if dids.len() as i64 == limit => Some(last.clone()),
+            _ => None,
+        },
+        repos: dids
+            .into_iter()
+            .map(|did| RefRepoByCollection { did })
+            .collect(),
+    })
+}
+
+/// Enumerates all the DIDs which have records with the given collection NSID. Does not require
+/// auth; taken down and deactivated repos are left out.
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/com.atproto.sync.listReposByCollection?<collection>&<limit>&<cursor>")]
+pub async fn list_repos_by_collection(
+    collection: String,
+    limit: Option<i64>,
+    cursor: Option<String>,
+    db: DbConn,
+) -> Result<Json<ListReposByCollectionOutput>, ApiError> {
+    match inner_list_repos_by_collection(collection, limit, cursor, &db).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
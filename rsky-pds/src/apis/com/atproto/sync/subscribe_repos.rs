@@ -6,8 +6,13 @@ use crate::sequencer::events::{
 };
 use crate::sequencer::outbox::{Outbox, OutboxOpts};
 use crate::sequencer::Sequencer;
+use crate::shutdown::ShutdownState;
+use crate::xrpc_server::stream::deflate::{
+    DeflateOffer, FrameEncoder, PerMessageDeflate, WithExtensions,
+};
 use crate::xrpc_server::stream::frames::{ErrorFrame, Frame, MessageFrame, MessageFrameOpts};
 use crate::xrpc_server::stream::types::ErrorFrameBody;
+use anyhow::Result;
 use chrono::offset::Utc as UtcOffset;
 use chrono::{DateTime, Duration};
 use futures::{pin_mut, StreamExt};
@@ -21,7 +26,7 @@ use rsky_lexicon::com::atproto::sync::{
 };
 use serde_json::json;
 use std::time::SystemTime;
-use tokio::time::{interval, Duration as TokioDuration};
+use tokio::time::{interval, timeout, Duration as TokioDuration};
 use ws::Message;
 
 fn get_backfill_limit(ms: u64) -> String {
@@ -31,6 +36,135 @@ fn get_backfill_limit(ms: u64) -> String {
     format!("{}", dt.format(RFC3339_VARIANT))
 }
 
+fn seq_evt_to_frame(evt: SeqEvt) -> Result<Vec<u8>> {
+    match evt {
+        SeqEvt::TypedCommitEvt(commit) => {
+            let TypedCommitEvt {
+                r#type,
+                seq,
+                time,
+                evt,
+            } = commit;
+            let CommitEvt {
+                rebase,
+                too_big,
+                repo,
+                commit,
+                prev,
+                rev,
+                since,
+                blocks,
+                ops,
+                blobs,
+                prev_data: _,
+            } = evt;
+            let subscribe_commit_evt = SubscribeReposCommit {
+                seq,
+                time: from_str_to_utc(&time),
+                rebase,
+                too_big,
+                repo,
+                commit,
+                prev,
+                rev,
+                since,
+                blocks,
+                ops: ops
+                    .into_iter()
+                    .map(|op| SubscribeReposCommitOperation {
+                        path: op.path,
+                        cid: op.cid,
+                        action: op.action.to_string(),
+                    })
+                    .collect::<Vec<SubscribeReposCommitOperation>>(),
+                blobs: blobs
+                    .into_iter()
+                    .map(|blob| blob.to_string())
+                    .collect::<Vec<String>>(),
+            };
+            MessageFrame::new(
+                subscribe_commit_evt,
+                Some(MessageFrameOpts {
+                    r#type: Some(format!("#{0}", r#type)),
+                }),
+            )
+            .to_bytes()
+        }
+        SeqEvt::TypedIdentityEvt(identity) => {
+            let TypedIdentityEvt {
+                r#type,
+                seq,
+                time,
+                evt,
+            } = identity;
+            let IdentityEvt { did, handle } = evt;
+            let subscribe_identity_evt = SubscribeReposIdentity {
+                did,
+                seq,
+                handle,
+                time: from_str_to_utc(&time),
+            };
+            MessageFrame::new(
+                subscribe_identity_evt,
+                Some(MessageFrameOpts {
+                    r#type: Some(format!("#{0}", r#type)),
+                }),
+            )
+            .to_bytes()
+        }
+        SeqEvt::TypedAccountEvt(account) => {
+            let TypedAccountEvt {
+                r#type,
+                seq,
+                time,
+                evt,
+            } = account;
+            let AccountEvt {
+                did,
+                active,
+                status,
+            } = evt;
+            let subscribe_account_evt = SubscribeReposAccount {
+                did,
+                seq,
+                status,
+                active,
+                time: from_str_to_utc(&time),
+            };
+            MessageFrame::new(
+                subscribe_account_evt,
+                Some(MessageFrameOpts {
+                    r#type: Some(format!("#{0}", r#type)),
+                }),
+            )
+            .to_bytes()
+        }
+        SeqEvt::TypedSyncEvt(sync) => {
+            let TypedSyncEvt {
+                r#type,
+                seq,
+                time,
+                evt,
+            } = sync;
+            let SyncEvt { did, blocks, rev } = evt;
+            let subscribe_sync_evt = SubscribeReposSync {
+                seq,
+                did,
+                blocks,
+                rev,
+                time: from_str_to_utc(&time),
+            };
+            MessageFrame::new(
+                subscribe_sync_evt,
+                Some(MessageFrameOpts {
+                    r#type: Some(format!("#{0}", r#type)),
+                }),
+            )
+            .to_bytes()
+        }
+    }
+}
+
 /// Repository event stream, aka Firehose endpoint. Outputs repo commits with diff data,
 /// and identity update events, for all repositories on the current server. See the atproto
 /// specifications for details around stream sequencing, repo versioning, CAR diff format, and more.
@@ -40,10 +174,16 @@ fn get_backfill_limit(ms: u64) -> String {
 pub async fn subscribe_repos<'a>(
     cursor: Option<i64>,
     cfg: &'a State<ServerConfig>,
+    shutdown_state: &'a State<ShutdownState>,
     mut shutdown: Shutdown,
+    deflate_offer: Option<DeflateOffer>,
     ws: ws::WebSocket,
-) -> ws::Stream!['a] {
-    ws::Stream! { ws =>
+) -> WithExtensions<ws::Stream!['a]> {
+    let deflate =
+        PerMessageDeflate::negotiate(deflate_offer, cfg.subscription.compression.as_ref());
+    let extensions = deflate.as_ref().map(PerMessageDeflate::response_header);
+    let mut encoder = FrameEncoder::new(deflate);
+    let inner = ws::Stream! { ws =>
         let sequencer_lock = Sequencer::new(
             Crawlers::new(cfg.service.hostname.clone(), cfg.crawlers.clone()),
             None,
@@ -52,6 +192,8 @@ pub async fn subscribe_repos<'a>(
             sequencer_lock.clone(),
             Some(OutboxOpts {
                 max_buffer_size: cfg.subscription.repo_backfill_limit_ms as usize,
+                backfill_page_size: cfg.subscription.backfill_page_size,
+                backfill_max_events_per_sec: cfg.subscription.backfill_max_events_per_sec,
             })
         );
 
@@ -88,7 +230,7 @@ pub async fn subscribe_repos<'a>(
                         error: "FutureCursor".to_string(),
                         message: Some("Cursor in the future.".to_string()),
                     });
-                    yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
+                    yield encoder.binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
                 },
                 false => match next {
                     Some(next) if next.sequenced_at < backfill_time => {
@@ -96,7 +238,7 @@ pub async fn subscribe_repos<'a>(
                             error: "OutdatedCursor".to_string(),
                             message: Some("Requested cursor exceeded limit. Possibly missing events.".to_string()),
                         });
-                        yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
+                        yield encoder.binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
                         match sequencer_lock.earliest_after_time(backfill_time).await {
                             Ok(Some(start_evt)) if start_evt.seq.is_some() => outbox_cursor = Some(start_evt.seq.unwrap() - 1),
                             Ok(None) => outbox_cursor = None,
@@ -105,7 +247,7 @@ pub async fn subscribe_repos<'a>(
                                     error: "EarliestAfterTimeError".to_string(),
                                     message: Some("Failed to fetch earliest event after backfill time.".to_string()),
                                 });
-                                yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
+                                yield encoder.binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
                                 return;
                             }
                         }
@@ -132,7 +274,7 @@ pub async fn subscribe_repos<'a>(
                                 error: "EventStreamError".to_string(),
                                 message: Some(err.to_string()),
                             });
-                            yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
+                            yield encoder.binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
                             return;
                         },
                         None => {
@@ -140,120 +282,20 @@ pub async fn subscribe_repos<'a>(
                                 error: "EventStreamError".to_string(),
                                 message: Some("Failed to fetch event from stream.".to_string()),
                             });
-                            yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
+                            yield encoder.binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
                             return;
                         }
                     };
 
-                    match evt {
-                        SeqEvt::TypedCommitEvt(commit) => {
-                            let TypedCommitEvt { r#type, seq, time, evt } = commit;
-                            let CommitEvt { rebase, too_big, repo, commit, prev, rev, since, blocks, ops, blobs, prev_data} = evt;
-                            let subscribe_commit_evt = SubscribeReposCommit {
-                                seq,
-                                time: from_str_to_utc(&time),
-                                rebase,
-                                too_big,
-                                repo,
-                                commit,
-                                prev,
-                                rev,
-                                since,
-                                blocks,
-                                ops: ops.into_iter().map(|op| SubscribeReposCommitOperation {
-                                    path: op.path,
-                                    cid: match op.cid {
-                                        None => None,
-                                        Some(cid) => Some(cid)
-                                    },
-                                    action: op.action.to_string()
-                                }).collect::<Vec<SubscribeReposCommitOperation>>(),
-                                blobs: blobs.into_iter().map(|blob| blob.to_string()).collect::<Vec<String>>(),
-                            };
-                            let message_frame = MessageFrame::new(subscribe_commit_evt, Some(MessageFrameOpts { r#type: Some(format!("#{0}",r#type)) }));
-                            let binary = match message_frame.to_bytes() {
-                                Ok(binary) => binary,
-                                Err(_) => {
-                                    let error_frame = ErrorFrame::new(ErrorFrameBody {
-                                        error: "SerializationError".to_string(),
-                                        message: Some("Failed to serialize event to message frame.".to_string()),
-                                    });
-                                    yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
-                                    return;
-                                }
-                            };
-                            yield Message::Binary(binary);
-                        },
-                        SeqEvt::TypedIdentityEvt(identity) => {
-                            let TypedIdentityEvt { r#type, seq, time, evt } = identity;
-                            let IdentityEvt { did, handle } = evt;
-                            let subscribe_identity_evt = SubscribeReposIdentity {
-                                did,
-                                seq,
-                                handle,
-                                time: from_str_to_utc(&time),
-                            };
-                            let message_frame = MessageFrame::new(subscribe_identity_evt, Some(MessageFrameOpts { r#type: Some(format!("#{0}",r#type)) }));
-                            let binary = match message_frame.to_bytes() {
-                                Ok(binary) => binary,
-                                Err(_) => {
-                                    let error_frame = ErrorFrame::new(ErrorFrameBody {
-                                        error: "SerializationError".to_string(),
-                                        message: Some("Failed to serialize event to message frame.".to_string()),
-                                    });
-                                    yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
-                                    return;
-                                }
-                            };
-                            yield Message::Binary(binary);
-                        },
-                        SeqEvt::TypedAccountEvt(account) => {
-                            let TypedAccountEvt { r#type, seq, time, evt } = account;
-                            let AccountEvt { did, active, status } = evt;
-                            let subscribe_account_evt = SubscribeReposAccount {
-                                did,
-                                seq,
-                                status,
-                                active,
-                                time: from_str_to_utc(&time),
-                            };
-                            let message_frame = MessageFrame::new(subscribe_account_evt, Some(MessageFrameOpts { r#type: Some(format!("#{0}",r#type)) }));
-                            let binary = match message_frame.to_bytes() {
-                                Ok(binary) => binary,
-                                Err(_) => {
-                                    let error_frame = ErrorFrame::new(ErrorFrameBody {
-                                        error: "SerializationError".to_string(),
-                                        message: Some("Failed to serialize event to message frame.".to_string()),
-                                    });
-                                    yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
-                                    return;
-                                }
-                            };
-                            yield Message::Binary(binary);
-                        },
-                        SeqEvt::TypedSyncEvt(sync) => {
-                            let TypedSyncEvt { r#type, seq, time, evt } = sync;
-                            let SyncEvt { did, blocks, rev } = evt;
-                            let subscribe_sync_evt = SubscribeReposSync {
-                                seq,
-                                did,
-                                blocks,
-                                rev,
-                                time: from_str_to_utc(&time),
-                            };
-                            let message_frame = MessageFrame::new(subscribe_sync_evt, Some(MessageFrameOpts { r#type: Some(format!("#{0}",r#type)) }));
-                            let binary = match message_frame.to_bytes() {
-                                Ok(binary) => binary,
-                                Err(_) => {
-                                    let error_frame = ErrorFrame::new(ErrorFrameBody {
-                                        error: "SerializationError".to_string(),
-                                        message: Some("Failed to serialize event to message frame.".to_string()),
-                                    });
-                                    yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
-                                    return;
-                                }
-                            };
-                            yield Message::Binary(binary);
+                    match seq_evt_to_frame(evt) {
+                        Ok(binary) => yield encoder.binary(binary),
+                        Err(_) => {
+                            let error_frame = ErrorFrame::new(ErrorFrameBody {
+                                error: "SerializationError".to_string(),
+                                message: Some("Failed to serialize event to message frame.".to_string()),
+                            });
+                            yield encoder.binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
+                            return;
                         }
                     }
                 }
@@ -300,8 +342,24 @@ pub async fn subscribe_repos<'a>(
                     // Send a Ping message to the client
                     yield ws::Message::Ping(vec![]);
                 },
-                _ = &mut shutdown => break
+                _ = &mut shutdown => {
+                    // let in-flight writes commit and the sequencer emit them before hanging up
+                    shutdown_state.wait_for_writes(TokioDuration::from_secs(cfg.shutdown.grace)).await;
+                    let flush_window = TokioDuration::from_millis(cfg.shutdown.flush_window_ms);
+                    while let Ok(Some(Ok(evt))) = timeout(flush_window, event_stream.next()).await {
+                        match seq_evt_to_frame(evt) {
+                            Ok(binary) => yield encoder.binary(binary),
+                            Err(_) => break,
+                        }
+                    }
+                    yield Message::Close(Some(ws::frame::CloseFrame {
+                        code: ws::frame::CloseCode::Away,
+                        reason: "Server shutting down".into(),
+                    }));
+                    break;
+                }
             }
         }
-    }
+    };
+    WithExtensions { inner, extensions }
 }
@@ -0,0 +1,182 @@
+use crate::account_manager::helpers::account::AvailabilityFlags;
+use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
+use crate::config::ServerConfig;
+use crate::handle::errors::ErrorKind;
+use crate::handle::policy::HandlePolicy;
+use crate::handle::{normalize_and_validate_handle, HandleValidationContext, HandleValidationOpts};
+use crate::SharedIdResolver;
+use anyhow::{bail, Result};
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::atproto::temp::{
+    CheckHandleAvailabilityOutput, CheckHandleAvailabilityResult, ResultAvailable,
+    ResultUnavailable, Suggestion,
+};
+
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Numbered variants of a service domain handle, e.g. `alice2.example.com` for
+/// `alice.example.com`, with the name cut short where the number wouldn't fit in `max_len`.
+pub fn suggest_handles(handle: &str, domain: &str, max_len: usize) -> Vec<String> {
+    let name = handle.strip_suffix(domain).unwrap_or(handle);
+    (1..=20)
+        .map(|n| {
+            let n = n.to_string();
+            let keep = name.len().min(max_len.saturating_sub(n.len()));
+            format!("{}{n}{domain}", &name[..keep])
+        })
+        .collect()
+}
+
+fn is_reserved(handle: &str, handle_policy: &HandlePolicy) -> bool {
+    let name = handle.split('.').next().unwrap_or_default();
+    handle_policy.is_reserved(name)
+}
+
+/// The first [`MAX_SUGGESTIONS`] candidates that aren't in `taken`, in order.
+fn available_suggestions(candidates: Vec<String>, taken: &[String]) -> Vec<Suggestion> {
+    candidates
+        .into_iter()
+        .filter(|candidate| !taken.contains(candidate))
+        .take(MAX_SUGGESTIONS)
+        .map(|handle| Suggestion {
+            handle,
+            method: "numbered".to_string(),
+        })
+        .collect()
+}
+
+async fn is_available(
+    handle: &str,
+    handle_policy: &HandlePolicy,
+    account_manager: &AccountManager,
+) -> Result<bool> {
+    if is_reserved(handle, handle_policy) {
+        return Ok(false);
+    }
+    let account = account_manager
+        .get_account(
+            handle,
+            Some(AvailabilityFlags {
+                include_deactivated: Some(true),
+                include_taken_down: Some(true),
+            }),
+        )
+        .await?;
+    Ok(account.is_none())
+}
+
+async fn inner_check_handle_availability(
+    handle: String,
+    cfg: &State<ServerConfig>,
+    id_resolver: &State<SharedIdResolver>,
+    handle_policy: &State<HandlePolicy>,
+    account_manager: AccountManager,
+) -> Result<CheckHandleAvailabilityOutput> {
+    let opts = HandleValidationOpts {
+        handle: handle.clone(),
+        did: None,
+        allow_reserved: None,
+    };
+    let validation_ctx = HandleValidationContext {
+        server_config: cfg,
+        id_resolver,
+        handle_policy,
+    };
+    let handle = match normalize_and_validate_handle(opts, validation_ctx).await {
+        Ok(handle) => handle,
+        // reserved, but otherwise valid
+        Err(error) if matches!(error.kind, ErrorKind::HandleNotAvailable) => handle.to_lowercase(),
+        Err(error) => bail!(ApiError::from(error)),
+    };
+
+    if is_available(&handle, handle_policy, &account_manager).await? {
+        return Ok(CheckHandleAvailabilityOutput {
+            handle,
+            result: CheckHandleAvailabilityResult::Available(ResultAvailable {}),
+        });
+    }
+    let mut suggestions = Vec::new();
+    // only service domain handles get this far without a DID to check them against
+    if let Some(domain) = cfg
+        .identity
+        .service_handle_domains
+        .iter()
+        .find(|domain| handle.ends_with(domain.as_str()))
+    {
+        let candidates: Vec<String> = suggest_handles(&handle, domain, handle_policy.max_length())
+            .into_iter()
+            .filter(|candidate| !is_reserved(candidate, handle_policy))
+            .collect();
+        let taken = account_manager
+            .get_taken_handles(candidates.clone())
+            .await?;
+        suggestions = available_suggestions(candidates, &taken);
+    }
+    Ok(CheckHandleAvailabilityOutput {
+        handle,
+        result: CheckHandleAvailabilityResult::Unavailable(ResultUnavailable { suggestions }),
+    })
+}
+
+/// Checks whether the provided handle is available, without creating anything. If the handle
+/// is taken or reserved, available suggestions will be returned. Does not require auth.
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/com.atproto.temp.checkHandleAvailability?<handle>")]
+pub async fn check_handle_availability(
+    handle: String,
+    cfg: &State<ServerConfig>,
+    id_resolver: &State<SharedIdResolver>,
+    handle_policy: &State<HandlePolicy>,
+    account_manager: AccountManager,
+) -> Result<Json<CheckHandleAvailabilityOutput>, ApiError> {
+    match inner_check_handle_availability(handle, cfg, id_resolver, handle_policy, account_manager)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest_handles() {
+        let suggestions = suggest_handles("alice.example.com", ".example.com", 18);
+        assert_eq!(suggestions[0], "alice1.example.com");
+        assert_eq!(suggestions[19], "alice20.example.com");
+        // names at the length limit give up characters to the number
+        let suggestions = suggest_handles("abcdefghijklmnopqr.example.com", ".example.com", 18);
+        assert_eq!(suggestions[0], "abcdefghijklmnopq1.example.com");
+        assert_eq!(suggestions[9], "abcdefghijklmnop10.example.com");
+    }
+
+    #[test]
+    fn test_available_suggestions() {
+        let candidates = suggest_handles("alice.example.com", ".example.com", 18);
+        let taken = vec![
+            "alice1.example.com".to_string(),
+            "alice3.example.com".to_string(),
+        ];
+        let suggestions: Vec<String> = available_suggestions(candidates, &taken)
+            .into_iter()
+            .map(|suggestion| suggestion.handle)
+            .collect();
+        assert_eq!(
+            suggestions,
+            vec![
+                "alice2.example.com",
+                "alice4.example.com",
+                "alice5.example.com",
+                "alice6.example.com",
+                "alice7.example.com",
+            ]
+        );
+    }
+}
@@ -0,0 +1,14 @@
+use crate::auth_verifier::AccessStandard;
+use rocket::serde::json::Json;
+use rsky_lexicon::com::atproto::temp::CheckSignupQueueOutput;
+
+/// This PDS has no signup queue, so every account with a session is already activated.
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/com.atproto.temp.checkSignupQueue")]
+pub async fn check_signup_queue(_auth: AccessStandard) -> Json<CheckSignupQueueOutput> {
+    Json(CheckSignupQueueOutput {
+        activated: true,
+        place_in_queue: None,
+        estimated_time_ms: None,
+    })
+}
@@ -0,0 +1,97 @@
+use crate::apis::ApiError;
+use crate::config::ServerConfig;
+use crate::http::{self, Destination};
+use anyhow::Result;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::atproto::label::Label;
+use rsky_lexicon::com::atproto::temp::FetchLabelsOutput;
+use rsky_repo::types::Ids;
+
+const DEFAULT_LIMIT: u16 = 50;
+const MAX_LIMIT: u16 = 250;
+// queryLabels can't filter by time, so bound how far we page looking for newer labels
+const MAX_PAGES: usize = 20;
+
+#[derive(Deserialize)]
+struct QueryLabelsOutput {
+    cursor: Option<String>,
+    labels: Vec<Label>,
+}
+
+/// Labels created after `since`, in milliseconds since the epoch.
+fn created_since(labels: Vec<Label>, since: Option<i64>) -> Vec<Label> {
+    match since {
+        None => labels,
+        Some(since) => labels
+            .into_iter()
+            .filter(|label| label.cts.timestamp_millis() > since)
+            .collect(),
+    }
+}
+
+async fn inner_fetch_labels(
+    since: Option<i64>,
+    limit: Option<u16>,
+    cfg: &State<ServerConfig>,
+) -> Result<FetchLabelsOutput> {
+    // the PDS doesn't keep labels of its own
+    let Some(mod_service) = &cfg.mod_service else {
+        return Ok(FetchLabelsOutput { labels: vec![] });
+    };
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT) as usize;
+    let url = format!(
+        "{}/xrpc/{}",
+        mod_service.url,
+        Ids::ComAtprotoLabelQueryLabels.as_str()
+    );
+    // page through until `limit` labels past `since` are found, rather than filtering a single
+    // page and dropping whatever matched beyond it
+    let mut labels = Vec::new();
+    let mut cursor: Option<String> = None;
+    for _ in 0..MAX_PAGES {
+        let mut params = vec![
+            ("uriPatterns", "*".to_string()),
+            ("limit", MAX_LIMIT.to_string()),
+        ];
+        if let Some(cursor) = cursor.take() {
+            params.push(("cursor", cursor));
+        }
+        let res: QueryLabelsOutput = http::get(Destination::ModService, &url)
+            .query(&params)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let exhausted = res.labels.is_empty();
+        labels.extend(created_since(res.labels, since));
+        if labels.len() >= limit || exhausted {
+            break;
+        }
+        match res.cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    labels.truncate(limit);
+    Ok(FetchLabelsOutput { labels })
+}
+
+/// Deprecated shim kept for older labeler tooling, answered from the configured mod service's
+/// `queryLabels`.
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/com.atproto.temp.fetchLabels?<since>&<limit>")]
+pub async fn fetch_labels(
+    since: Option<i64>,
+    limit: Option<u16>,
+    cfg: &State<ServerConfig>,
+) -> Result<Json<FetchLabelsOutput>, ApiError> {
+    match inner_fetch_labels(since, limit, cfg).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod check_handle_availability;
+pub mod check_signup_queue;
+pub mod fetch_labels;
+pub mod request_phone_verification;
@@ -0,0 +1,27 @@
+use crate::apis::ApiError;
+use crate::auth_verifier::AccessStandard;
+use crate::config::ServerConfig;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::atproto::temp::RequestPhoneVerificationInput;
+
+/// Nothing is ever sent. With `PDS_ACCEPT_PHONE_VERIFICATION` set the request succeeds, so
+/// signup flows that insist on calling this can carry on; otherwise it's refused.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/com.atproto.temp.requestPhoneVerification",
+    format = "json",
+    data = "<_body>"
+)]
+pub async fn request_phone_verification(
+    _body: Json<RequestPhoneVerificationInput>,
+    _auth: AccessStandard,
+    cfg: &State<ServerConfig>,
+) -> Result<(), ApiError> {
+    match cfg.phone_verification.accept {
+        true => Ok(()),
+        false => Err(ApiError::MethodNotImplemented(
+            "Phone verification is not supported by this PDS".to_string(),
+        )),
+    }
+}
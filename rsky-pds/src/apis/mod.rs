@@ -1,26 +1,33 @@
+use crate::actor_store::FormatCommitError;
 use crate::auth_verifier::AccessStandard;
 use crate::handle;
 use crate::handle::errors::ErrorKind;
-use crate::pipethrough::{pipethrough_procedure, pipethrough_procedure_post, ProxyRequest};
+use crate::locale::{self, RequestLocale};
+use crate::pipethrough::{
+    check_proxied_method, pipethrough_procedure, pipethrough_procedure_post, ProxyRequest,
+};
+use crate::request_id::RequestId;
 use anyhow::{Error, Result};
 use rocket::http::{ContentType, Header, Status};
 use rocket::request::FromParam;
 use rocket::serde::json::Json;
 use rocket::{response, Data, Request, Responder};
+use rsky_common_web::{XrpcError, XrpcErrorBody, XrpcErrorKind};
+use rsky_repo::error::{BlobError, RepoError};
+use std::fmt;
 
 #[derive(Responder)]
 #[response(status = 200)]
 pub struct ProxyResponder(Vec<u8>, Header<'static>, Header<'static>);
 
-#[allow(dead_code)]
 pub struct Nsid(String);
 
 impl<'a> FromParam<'a> for Nsid {
     type Error = &'a str;
 
     fn from_param(param: &'a str) -> Result<Self, Self::Error> {
-        // This is how we make sure we allowlist lexicons and what gets proxied
-        if param.starts_with("app.bsky.") || param.starts_with("chat.bsky") {
+        // What actually gets proxied is decided by `check_proxied_method`
+        if is_nsid(param) {
             Ok(Nsid(param.to_string()))
         } else {
             Err(param)
@@ -28,6 +35,22 @@ impl<'a> FromParam<'a> for Nsid {
     }
 }
 
+/// At least three dot-separated segments of letters, digits and hyphens, the last one (the
+/// method name) starting with a letter.
+fn is_nsid(param: &str) -> bool {
+    let segments: Vec<&str> = param.split('.').collect();
+    segments.len() >= 3
+        && segments.iter().all(|segment| {
+            !segment.is_empty()
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && segments
+            .last()
+            .is_some_and(|name| name.starts_with(|c: char| c.is_ascii_alphabetic()))
+}
+
 // Lower ranks have higher presidence
 #[tracing::instrument(skip_all)]
 #[allow(unused_variables)]
@@ -38,6 +61,7 @@ pub async fn bsky_api_get_forwarder(
     auth: AccessStandard,
     req: ProxyRequest<'_>,
 ) -> Result<ProxyResponder, ApiError> {
+    check_proxied_method(&req, &auth.access, &nsid.0)?;
     let requester: Option<String> = match auth.access.credentials {
         None => None,
         Some(credentials) => credentials.did,
@@ -50,25 +74,26 @@ pub async fn bsky_api_get_forwarder(
                 Some(val) => Header::new("content-length", val.to_string()),
             };
             let content_type = match headers.get("content-type") {
-                None => Header::new("content-type", "octet-stream".to_string()),
+                None => Header::new("content-type", "application/octet-stream".to_string()),
                 Some(val) => Header::new("Content-Type", val.to_string()),
             };
             Ok(ProxyResponder(res.buffer, content_length, content_type))
         }
         Err(error) => {
             tracing::error!("@LOG: ERROR: {error}");
-            Err(ApiError::RuntimeError)
+            Err(error.into())
         }
     }
 }
 
-#[rocket::post("/xrpc/<_nsid>", data = "<body>", rank = 2)]
+#[rocket::post("/xrpc/<nsid>", data = "<body>", rank = 2)]
 pub async fn bsky_api_post_forwarder(
     body: Data<'_>,
-    _nsid: Nsid,
+    nsid: Nsid,
     auth: AccessStandard,
     req: ProxyRequest<'_>,
 ) -> Result<ProxyResponder, ApiError> {
+    check_proxied_method(&req, &auth.access, &nsid.0)?;
     let requester: Option<String> = match auth.access.credentials {
         None => None,
         Some(credentials) => credentials.did,
@@ -87,10 +112,15 @@ pub async fn bsky_api_post_forwarder(
     Ok(ProxyResponder(res.buffer, content_length, content_type))
 }
 
+/// Errors returned by xrpc routes. `error()` is the lexicon error name sent to clients, and
+/// `kind()` the protocol-wide error it's sent as.
 #[derive(Clone, Debug)]
 pub enum ApiError {
     RuntimeError,
     InvalidLogin,
+    /// The password was right, but the account also needs an authenticator app or recovery
+    /// code. Sent under the lexicon's `AuthFactorTokenRequired`, which clients prompt on.
+    AuthFactorRequired,
     AccountTakendown,
     InvalidRequest(String),
     ExpiredToken,
@@ -108,347 +138,237 @@ pub enum ApiError {
     WellKnownNotFound,
     AccountNotFound,
     BlobNotFound,
+    /// Moderated media withheld under `PDS_BLOB_MODERATED_STATUS`.
+    BlobUnavailable(Status, String),
+    BlobTooLarge(String),
+    InvalidMimeType(String),
+    InvalidSwap(String),
+    RepoNotFound(String),
+    RepoTakendown(String),
+    RepoDeactivated(String),
     BadRequest(String, String),
     AuthRequiredError(String),
+    ServiceUnavailable(String),
+    MethodNotImplemented(String),
+    RateLimitExceeded(String),
 }
 
 #[derive(Serialize)]
 pub struct ErrorBody {
-    error: String,
-    message: String,
+    #[serde(flatten)]
+    body: XrpcErrorBody,
+    #[serde(rename = "requestId", skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
 }
 
-impl<'r, 'o: 'r> ::rocket::response::Responder<'r, 'o> for ApiError {
-    fn respond_to(self, __req: &'r Request<'_>) -> response::Result<'o> {
+impl ApiError {
+    /// Errors named by a method's lexicon, like `InvalidLogin`, are sent with this kind's status
+    /// under their own name.
+    pub fn kind(&self) -> XrpcErrorKind {
         match self {
-            ApiError::RuntimeError => {
-                let body = Json(ErrorBody {
-                    error: "InternalServerError".to_string(),
-                    message: "Something went wrong".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 500u16 });
-                Ok(res)
-            }
-            ApiError::InvalidLogin => {
-                let body = Json(ErrorBody {
-                    error: "InvalidLogin".to_string(),
-                    message: "Invalid identifier or password".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(::rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::AccountTakendown => {
-                let body = Json(ErrorBody {
-                    error: "AccountTakendown".to_string(),
-                    message: "Account has been taken down".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(::rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::InvalidRequest(message) => {
-                let body = Json(ErrorBody {
-                    error: "InvalidRequest".to_string(),
-                    message,
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::ExpiredToken => {
-                let body = Json(ErrorBody {
-                    error: "ExpiredToken".to_string(),
-                    message: "Token is expired".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::InvalidToken => {
-                let body = Json(ErrorBody {
-                    error: "InvalidToken".to_string(),
-                    message: "Token is invalid".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::InvalidHandle => {
-                let body = Json(ErrorBody {
-                    error: "InvalidHandle".to_string(),
-                    message: "Handle is invalid".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::InvalidEmail => {
-                let body = Json(ErrorBody {
-                    error: "InvalidEmail".to_string(),
-                    message: "Invalid email".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::InvalidPassword => {
-                let body = Json(ErrorBody {
-                    error: "InvalidPassword".to_string(),
-                    message: "Invalid Password".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::InvalidInviteCode => {
-                let body = Json(ErrorBody {
-                    error: "InvalidInviteCode".to_string(),
-                    message: "Invalid invite code".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::HandleNotAvailable => {
-                let body = Json(ErrorBody {
-                    error: "HandleNotAvailable".to_string(),
-                    message: "Handle not available".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::EmailNotAvailable => {
-                let body = Json(ErrorBody {
-                    error: "EmailNotAvailable".to_string(),
-                    message: "Email not available".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::UnsupportedDomain => {
-                let body = Json(ErrorBody {
-                    error: "UnsupportedDomain".to_string(),
-                    message: "Unsupported domain".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::UnresolvableDid => {
-                let body = Json(ErrorBody {
-                    error: "UnresolvableDid".to_string(),
-                    message: "Unresolved Did".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::IncompatibleDidDoc => {
-                let body = Json(ErrorBody {
-                    error: "IncompatibleDidDoc".to_string(),
-                    message: "IncompatibleDidDoc".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::AccountNotFound => {
-                let body = Json(ErrorBody {
-                    error: "AccountNotFound".to_string(),
-                    message: "Account could not be found".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::BlobNotFound => {
-                let body = Json(ErrorBody {
-                    error: "BlobNotFound".to_string(),
-                    message: "Blob could not be found".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::WellKnownNotFound => {
-                let body = Json(ErrorBody {
-                    error: "WellKnownNotFound".to_string(),
-                    message: "User not found".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(::rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 404u16 });
-                Ok(res)
-            }
-            ApiError::BadRequest(error, message) => {
-                let body = Json(ErrorBody { error, message });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 400u16 });
-                Ok(res)
-            }
-            ApiError::AuthRequiredError(message) => {
-                let body = Json(ErrorBody {
-                    error: "AuthRequiredError".to_string(),
-                    message,
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(::rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 401u16 });
-                Ok(res)
-            }
-            ApiError::RecordNotFound => {
-                let body = Json(ErrorBody {
-                    error: "RecordNotFound".to_string(),
-                    message: "Record could not be found".to_string(),
-                });
-                let mut res =
-                    <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
-                res.set_header(ContentType(rocket::http::MediaType::const_new(
-                    "application",
-                    "json",
-                    &[],
-                )));
-                res.set_status(Status { code: 404u16 });
-                Ok(res)
+            ApiError::RuntimeError => XrpcErrorKind::InternalServerError,
+            ApiError::AuthRequiredError(_) | ApiError::AuthFactorRequired => {
+                XrpcErrorKind::AuthenticationRequired
             }
+            ApiError::ServiceUnavailable(_) => XrpcErrorKind::NotEnoughResources,
+            ApiError::MethodNotImplemented(_) => XrpcErrorKind::MethodNotImplemented,
+            ApiError::RateLimitExceeded(_) => XrpcErrorKind::RateLimitExceeded,
+            ApiError::BlobUnavailable(status, _) => XrpcErrorKind::from_status(status.code),
+            _ => XrpcErrorKind::InvalidRequest,
         }
     }
+
+    pub fn error(&self) -> &str {
+        match self {
+            ApiError::RuntimeError
+            | ApiError::InvalidRequest(_)
+            | ApiError::AuthRequiredError(_)
+            | ApiError::ServiceUnavailable(_)
+            | ApiError::MethodNotImplemented(_)
+            | ApiError::RateLimitExceeded(_) => self.kind().name(),
+            ApiError::InvalidLogin => "InvalidLogin",
+            ApiError::AuthFactorRequired => "AuthFactorTokenRequired",
+            ApiError::AccountTakendown => "AccountTakendown",
+            ApiError::ExpiredToken => "ExpiredToken",
+            ApiError::InvalidToken => "InvalidToken",
+            ApiError::RecordNotFound => "RecordNotFound",
+            ApiError::InvalidHandle => "InvalidHandle",
+            ApiError::InvalidEmail => "InvalidEmail",
+            ApiError::InvalidPassword => "InvalidPassword",
+            ApiError::InvalidInviteCode => "InvalidInviteCode",
+            ApiError::HandleNotAvailable => "HandleNotAvailable",
+            ApiError::EmailNotAvailable => "EmailNotAvailable",
+            ApiError::UnsupportedDomain => "UnsupportedDomain",
+            ApiError::UnresolvableDid => "UnresolvableDid",
+            ApiError::IncompatibleDidDoc => "IncompatibleDidDoc",
+            ApiError::WellKnownNotFound => "WellKnownNotFound",
+            ApiError::AccountNotFound => "AccountNotFound",
+            ApiError::BlobNotFound => "BlobNotFound",
+            ApiError::BlobUnavailable(..) => "BlobUnavailable",
+            ApiError::BlobTooLarge(_) => "BlobTooLarge",
+            ApiError::InvalidMimeType(_) => "InvalidMimeType",
+            ApiError::InvalidSwap(_) => "InvalidSwap",
+            ApiError::RepoNotFound(_) => "RepoNotFound",
+            ApiError::RepoTakendown(_) => "RepoTakendown",
+            ApiError::RepoDeactivated(_) => "RepoDeactivated",
+            ApiError::BadRequest(error, _) => error,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::RuntimeError => "Something went wrong",
+            ApiError::InvalidLogin => "Invalid identifier or password",
+            ApiError::AuthFactorRequired => "A code from your authenticator app is required",
+            ApiError::AccountTakendown => "Account has been taken down",
+            ApiError::ExpiredToken => "Token is expired",
+            ApiError::InvalidToken => "Token is invalid",
+            ApiError::RecordNotFound => "Record could not be found",
+            ApiError::InvalidHandle => "Handle is invalid",
+            ApiError::InvalidEmail => "Invalid email",
+            ApiError::InvalidPassword => "Invalid Password",
+            ApiError::InvalidInviteCode => "Invalid invite code",
+            ApiError::HandleNotAvailable => "Handle not available",
+            ApiError::EmailNotAvailable => "Email not available",
+            ApiError::UnsupportedDomain => "Unsupported domain",
+            ApiError::UnresolvableDid => "Unresolved Did",
+            ApiError::IncompatibleDidDoc => "IncompatibleDidDoc",
+            ApiError::WellKnownNotFound => "User not found",
+            ApiError::AccountNotFound => "Account could not be found",
+            ApiError::BlobNotFound => "Blob could not be found",
+            ApiError::InvalidRequest(message)
+            | ApiError::BlobUnavailable(_, message)
+            | ApiError::BlobTooLarge(message)
+            | ApiError::InvalidMimeType(message)
+            | ApiError::InvalidSwap(message)
+            | ApiError::RepoNotFound(message)
+            | ApiError::RepoTakendown(message)
+            | ApiError::RepoDeactivated(message)
+            | ApiError::BadRequest(_, message)
+            | ApiError::AuthRequiredError(message)
+            | ApiError::ServiceUnavailable(message)
+            | ApiError::MethodNotImplemented(message)
+            | ApiError::RateLimitExceeded(message) => message,
+        }
+    }
+
+    /// `message()` in `locale`. Only the fixed messages are looked up, under `error.<error()>`;
+    /// ones with details filled in stay as they are.
+    pub fn localized_message(&self, locale: Option<&str>) -> String {
+        match self {
+            ApiError::InvalidRequest(message)
+            | ApiError::BlobUnavailable(_, message)
+            | ApiError::BlobTooLarge(message)
+            | ApiError::InvalidMimeType(message)
+            | ApiError::InvalidSwap(message)
+            | ApiError::RepoNotFound(message)
+            | ApiError::RepoTakendown(message)
+            | ApiError::RepoDeactivated(message)
+            | ApiError::BadRequest(_, message)
+            | ApiError::AuthRequiredError(message)
+            | ApiError::ServiceUnavailable(message)
+            | ApiError::MethodNotImplemented(message)
+            | ApiError::RateLimitExceeded(message) => message.clone(),
+            _ => locale::translate(locale, &format!("error.{}", self.error()), self.message()),
+        }
+    }
+
+    /// `kind()`'s status, besides the not-found errors and withheld blobs, which keep theirs.
+    pub fn status(&self) -> Status {
+        match self {
+            ApiError::WellKnownNotFound | ApiError::RecordNotFound => Status::NotFound,
+            ApiError::BlobUnavailable(status, _) => *status,
+            _ => Status::new(self.kind().status()),
+        }
+    }
+
+    pub fn to_xrpc_error(&self, locale: Option<&str>) -> XrpcError {
+        XrpcError::custom(self.error(), self.localized_message(locale))
+            .with_status(self.status().code)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.error(), self.message())
+    }
 }
 
+impl std::error::Error for ApiError {}
+
+impl<'r, 'o: 'r> ::rocket::response::Responder<'r, 'o> for ApiError {
+    fn respond_to(self, __req: &'r Request<'_>) -> response::Result<'o> {
+        let error = self.to_xrpc_error(RequestLocale::of(__req).0.as_deref());
+        let status = Status::new(error.status);
+        let body = Json(ErrorBody {
+            body: error.body,
+            request_id: RequestId::of(__req).map(str::to_string),
+        });
+        let mut res = <Json<ErrorBody> as ::rocket::response::Responder>::respond_to(body, __req)?;
+        res.set_header(ContentType(rocket::http::MediaType::const_new(
+            "application",
+            "json",
+            &[],
+        )));
+        res.set_status(status);
+        Ok(res)
+    }
+}
+
+/// Recovers a typed error from anything bailed with one further down the stack,
+/// falling back to `InternalServerError`.
 impl From<Error> for ApiError {
-    fn from(_value: Error) -> Self {
+    fn from(value: Error) -> Self {
+        if let Some(error) = value.downcast_ref::<ApiError>() {
+            return error.clone();
+        }
+        if let Some(error) = value.downcast_ref::<FormatCommitError>() {
+            return match error {
+                FormatCommitError::MissingRepoRoot(_) => ApiError::RuntimeError,
+                _ => ApiError::InvalidSwap(error.to_string()),
+            };
+        }
+        let value = match value.downcast::<RepoError>() {
+            Ok(error) => return error.into(),
+            Err(value) => value,
+        };
+        if let Some(BlobError::BlobNotFoundError) = value.downcast_ref::<BlobError>() {
+            return ApiError::BlobNotFound;
+        }
+        if let Ok(error) = value.downcast::<handle::errors::Error>() {
+            return error.into();
+        }
         ApiError::RuntimeError
     }
 }
 
+impl From<RepoError> for ApiError {
+    fn from(value: RepoError) -> Self {
+        match value {
+            RepoError::BadCommitSwapError(_) | RepoError::BadRecordSwapError(_) => {
+                ApiError::InvalidSwap(value.to_string())
+            }
+            RepoError::InvalidRecordError | RepoError::InvalidKey(_) => {
+                ApiError::InvalidRequest(value.to_string())
+            }
+            RepoError::Other(error) => error.into(),
+            _ => ApiError::RuntimeError,
+        }
+    }
+}
+
 impl From<handle::errors::Error> for ApiError {
     fn from(value: handle::errors::Error) -> Self {
         match value.kind {
             ErrorKind::InvalidHandle => ApiError::InvalidHandle,
             ErrorKind::HandleNotAvailable => ApiError::HandleNotAvailable,
             ErrorKind::UnsupportedDomain => ApiError::UnsupportedDomain,
+            ErrorKind::HandleUnresolvable => {
+                ApiError::BadRequest("HandleUnresolvable".to_string(), value.message)
+            }
+            ErrorKind::HandleDidMismatch => {
+                ApiError::BadRequest("HandleDidMismatch".to_string(), value.message)
+            }
             ErrorKind::InternalError => ApiError::RuntimeError,
         }
     }
@@ -456,3 +376,76 @@ impl From<handle::errors::Error> for ApiError {
 
 pub mod app;
 pub mod com;
+pub mod xyz;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_is_nsid() {
+        assert!(is_nsid("app.bsky.feed.getTimeline"));
+        assert!(is_nsid("tools.ozone.moderation.queryStatuses"));
+        assert!(is_nsid("com.example-labs.fooBar"));
+        assert!(!is_nsid("app.bsky"));
+        assert!(!is_nsid("app..bsky.getTimeline"));
+        assert!(!is_nsid("app.bsky.feed.1getTimeline"));
+        assert!(!is_nsid("app.bsky.feed/getTimeline"));
+    }
+
+    #[test]
+    fn test_from_anyhow_keeps_typed_errors() {
+        let error: ApiError = anyhow!(ApiError::RepoTakendown("gone".to_string()))
+            .context("while reading repo")
+            .into();
+        assert_eq!(error.error(), "RepoTakendown");
+        assert_eq!(error.message(), "gone");
+
+        let error: ApiError =
+            anyhow::Error::new(FormatCommitError::BadCommitSwap("bafyrei".to_string())).into();
+        assert_eq!(error.error(), "InvalidSwap");
+        assert_eq!(error.status(), Status::BadRequest);
+
+        let error: ApiError = anyhow!(ApiError::BlobUnavailable(
+            Status::UnavailableForLegalReasons,
+            "Blob is unavailable: blob has been taken down".to_string()
+        ))
+        .into();
+        assert_eq!(error.error(), "BlobUnavailable");
+        assert_eq!(error.status().code, 451);
+
+        let error: ApiError = anyhow::Error::new(handle::errors::Error::new(
+            ErrorKind::HandleDidMismatch,
+            "Handle resolves to did:plc:other, not did:plc:me",
+        ))
+        .into();
+        assert_eq!(error.error(), "HandleDidMismatch");
+        assert_eq!(error.status(), Status::BadRequest);
+
+        let error: ApiError = anyhow!("database is down").into();
+        assert_eq!(error.error(), "InternalServerError");
+        assert_eq!(error.status(), Status::InternalServerError);
+    }
+
+    #[test]
+    fn test_statuses_follow_xrpc_error_kinds() {
+        let error = ApiError::AuthRequiredError("Missing token".to_string()).to_xrpc_error(None);
+        assert_eq!(
+            (error.status, error.error()),
+            (401, "AuthenticationRequired")
+        );
+        let error = ApiError::AuthFactorRequired.to_xrpc_error(None);
+        assert_eq!(
+            (error.status, error.error()),
+            (401, "AuthFactorTokenRequired")
+        );
+        let error = ApiError::InvalidLogin.to_xrpc_error(None);
+        assert_eq!((error.status, error.error()), (400, "InvalidLogin"));
+        let error = ApiError::RateLimitExceeded("Slow down".to_string()).to_xrpc_error(None);
+        assert_eq!((error.status, error.error()), (429, "RateLimitExceeded"));
+        assert_eq!(error.message(), Some("Slow down"));
+        let error = ApiError::RecordNotFound.to_xrpc_error(None);
+        assert_eq!((error.status, error.error()), (404, "RecordNotFound"));
+    }
+}
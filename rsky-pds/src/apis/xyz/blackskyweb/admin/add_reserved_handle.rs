@@ -0,0 +1,45 @@
+use crate::apis::ApiError;
+use crate::auth_verifier::AdminToken;
+use crate::db::DbConn;
+use crate::handle::policy::HandlePolicy;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::xyz::blackskyweb::admin::{AddReservedHandleInput, ReservedHandle};
+
+/// Reserves a name on the service domains, e.g. `support` for `support.example.com`. Accounts
+/// already holding it keep it; it's refused to everyone else from the next request on this
+/// replica, and on the others once they refresh.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/xyz.blackskyweb.admin.addReservedHandle",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn add_reserved_handle(
+    body: Json<AddReservedHandleInput>,
+    _auth: AdminToken,
+    handle_policy: &State<HandlePolicy>,
+    db: DbConn,
+) -> Result<Json<ReservedHandle>, ApiError> {
+    let AddReservedHandleInput { name, reason } = body.into_inner();
+    let name = name.trim().to_lowercase();
+    if name.is_empty()
+        || name.starts_with('-')
+        || name.ends_with('-')
+        || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(ApiError::InvalidRequest(
+            "name must be a single handle segment, e.g. `support`".to_string(),
+        ));
+    }
+    match handle_policy.reserve(name, reason, &db).await {
+        Ok(reserved) => {
+            tracing::warn!("@LOG: reserved handle name {}", reserved.name);
+            Ok(Json(reserved))
+        }
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
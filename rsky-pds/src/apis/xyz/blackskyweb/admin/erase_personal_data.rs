@@ -0,0 +1,91 @@
+use crate::account_manager::AccountManager;
+use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::ActorStore;
+use crate::apis::ApiError;
+use crate::auth_verifier::AdminToken;
+use crate::db::DbConn;
+use crate::SharedSequencer;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::xyz::blackskyweb::admin::ErasePersonalDataInput;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Outcome of an erasure, kept by the operator as a record that the request was carried out.
+#[derive(Debug, Serialize)]
+pub struct ErasureReport {
+    pub did: String,
+    #[serde(rename = "erasedAt")]
+    pub erased_at: String,
+    /// Rows removed, by table.
+    pub deleted: BTreeMap<&'static str, usize>,
+    #[serde(rename = "blobsDeleted")]
+    pub blobs_deleted: usize,
+    /// Sections of getPersonalData still holding data once the erasure finished.
+    pub remaining: Vec<&'static str>,
+    /// Whether nothing beyond the account's deletion event remains.
+    pub verified: bool,
+}
+
+async fn inner_erase_personal_data(
+    body: Json<ErasePersonalDataInput>,
+    sequencer: &State<SharedSequencer>,
+    s3_config: &State<SdkConfig>,
+    db: DbConn,
+    account_manager: AccountManager,
+) -> Result<ErasureReport> {
+    let ErasePersonalDataInput { did } = body.into_inner();
+
+    let blobs_deleted = account_manager.get_personal_data(&did).await?.blobs.len();
+    let mut actor_store =
+        ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+    actor_store.destroy().await?;
+    let deleted = account_manager.erase_personal_data(&did).await?;
+    // emits the deletion event and purges the account's other events from the sequencer
+    account_manager
+        .sequence_account_status(&did, sequencer)
+        .await?;
+
+    let remaining = account_manager
+        .get_personal_data(&did)
+        .await?
+        .remaining_after_erasure();
+    if !remaining.is_empty() {
+        tracing::error!("@LOG: ERROR: erasure of {did} left data behind in {remaining:?}");
+    }
+    Ok(ErasureReport {
+        did,
+        erased_at: rsky_common::now(),
+        deleted,
+        blobs_deleted,
+        verified: remaining.is_empty(),
+        remaining,
+    })
+}
+
+/// Erase everything stored about an account: its repo, blobs, account rows, tokens and logs.
+/// Answers with a report verifying that nothing was left behind.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/xyz.blackskyweb.admin.erasePersonalData",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn erase_personal_data(
+    body: Json<ErasePersonalDataInput>,
+    sequencer: &State<SharedSequencer>,
+    s3_config: &State<SdkConfig>,
+    _auth: AdminToken,
+    db: DbConn,
+    account_manager: AccountManager,
+) -> Result<Json<ErasureReport>, ApiError> {
+    match inner_erase_personal_data(body, sequencer, s3_config, db, account_manager).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::record::deleted_record_cursor;
+use crate::actor_store::ActorStore;
+use crate::apis::ApiError;
+use crate::auth_verifier::Moderator;
+use crate::db::DbConn;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::xyz::blackskyweb::admin::{DeletedRecordView, GetDeletedRecordsOutput};
+use rsky_repo::util::cbor_to_lex_record;
+
+async fn inner_get_deleted_records(
+    did: String,
+    collection: Option<String>,
+    limit: Option<i64>,
+    cursor: Option<String>,
+    s3_config: &State<SdkConfig>,
+    db: DbConn,
+) -> Result<GetDeletedRecordsOutput> {
+    let limit = limit.unwrap_or(50).clamp(1, 100);
+    let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+    let deleted = actor_store
+        .record
+        .list_deleted_records(collection, limit, cursor)
+        .await?;
+
+    let cursor = match deleted.last() {
+        Some(last) if deleted.len() as i64 == limit => Some(deleted_record_cursor(last)),
+        _ => None,
+    };
+    Ok(GetDeletedRecordsOutput {
+        cursor,
+        records: deleted
+            .into_iter()
+            .map(|row| {
+                Ok(DeletedRecordView {
+                    value: serde_json::to_value(cbor_to_lex_record(row.content)?)?,
+                    uri: row.uri,
+                    cid: row.cid,
+                    repo_rev: row.repo_rev,
+                    indexed_at: row.indexed_at,
+                    deleted_at: row.deleted_at,
+                    expires_at: row.expires_at,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?,
+    })
+}
+
+/// List records an account deleted that are still retained for review under
+/// `PDS_DELETED_RECORD_RETENTION_SECS`, most recently deleted first.
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/xyz.blackskyweb.admin.getDeletedRecords?<did>&<collection>&<limit>&<cursor>")]
+pub async fn get_deleted_records(
+    did: String,
+    collection: Option<String>,
+    limit: Option<i64>,
+    cursor: Option<String>,
+    s3_config: &State<SdkConfig>,
+    _auth: Moderator,
+    db: DbConn,
+) -> Result<Json<GetDeletedRecordsOutput>, ApiError> {
+    match inner_get_deleted_records(did, collection, limit, cursor, s3_config, db).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
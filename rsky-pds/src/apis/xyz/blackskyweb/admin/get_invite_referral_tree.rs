@@ -0,0 +1,182 @@
+use crate::account_manager::helpers::account::AvailabilityFlags;
+use crate::account_manager::helpers::invite::Referral;
+use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
+use crate::auth_verifier::Moderator;
+use anyhow::{bail, Result};
+use futures::try_join;
+use rocket::serde::json::Json;
+use rsky_lexicon::xyz::blackskyweb::admin::{GetInviteReferralTreeOutput, InviteReferral};
+use std::collections::{BTreeMap, HashSet};
+
+const DEFAULT_DEPTH: usize = 3;
+const MAX_DEPTH: usize = 10;
+/// Bounds the walk behind the counts, which goes past the requested depth.
+const MAX_WALK_DEPTH: usize = 32;
+const MAX_REFERRALS: usize = 10_000;
+
+/// Builds the subtree under `node` from the walked referrals, keyed by inviter. Counts cover
+/// everything walked; `invited` is only filled in for `depth` more levels.
+pub fn build_referral_tree(
+    mut node: InviteReferral,
+    referrals: &BTreeMap<String, Vec<Referral>>,
+    depth: usize,
+) -> InviteReferral {
+    let invited: Vec<InviteReferral> = referrals
+        .get(&node.did)
+        .into_iter()
+        .flatten()
+        .map(|referral| {
+            let child = InviteReferral {
+                did: referral.did.clone(),
+                handle: referral.handle.clone(),
+                invite_code: Some(referral.code.clone()),
+                taken_down: referral.taken_down,
+                invited_count: 0,
+                descendant_count: 0,
+                taken_down_descendant_count: 0,
+                invited: Vec::new(),
+            };
+            build_referral_tree(child, referrals, depth.saturating_sub(1))
+        })
+        .collect();
+    node.invited_count = invited.len() as i64;
+    node.descendant_count = invited.iter().map(|c| 1 + c.descendant_count).sum();
+    node.taken_down_descendant_count = invited
+        .iter()
+        .map(|c| c.taken_down as i64 + c.taken_down_descendant_count)
+        .sum();
+    if depth > 0 {
+        node.invited = invited;
+    }
+    node
+}
+
+async fn inner_get_invite_referral_tree(
+    did: String,
+    depth: Option<usize>,
+    account_manager: AccountManager,
+) -> Result<GetInviteReferralTreeOutput> {
+    let (account, invited_by) = try_join!(
+        account_manager.get_account(
+            &did,
+            Some(AvailabilityFlags {
+                include_deactivated: Some(true),
+                include_taken_down: Some(true)
+            })
+        ),
+        account_manager.get_invited_by_for_accounts(vec![did.clone()])
+    )?;
+    let Some(account) = account else {
+        bail!(ApiError::AccountNotFound);
+    };
+    let invited_by = invited_by.get(&did);
+
+    let mut referrals: BTreeMap<String, Vec<Referral>> = BTreeMap::new();
+    let mut seen: HashSet<String> = HashSet::from([did.clone()]);
+    let mut frontier = vec![did.clone()];
+    let mut levels = 0;
+    while !frontier.is_empty() && levels < MAX_WALK_DEPTH && seen.len() < MAX_REFERRALS {
+        let mut next = Vec::new();
+        for referral in account_manager.get_referrals(frontier).await? {
+            if seen.insert(referral.did.clone()) {
+                next.push(referral.did.clone());
+                referrals
+                    .entry(referral.inviter.clone())
+                    .or_default()
+                    .push(referral);
+            }
+        }
+        frontier = next;
+        levels += 1;
+    }
+
+    let root = InviteReferral {
+        did: account.did,
+        handle: account.handle,
+        invite_code: invited_by.map(|code| code.code.clone()),
+        taken_down: account.takedown_ref.is_some(),
+        invited_count: 0,
+        descendant_count: 0,
+        taken_down_descendant_count: 0,
+        invited: Vec::new(),
+    };
+    Ok(GetInviteReferralTreeOutput {
+        invited_by: invited_by.map(|code| code.for_account.clone()),
+        tree: build_referral_tree(
+            root,
+            &referrals,
+            depth.unwrap_or(DEFAULT_DEPTH).min(MAX_DEPTH),
+        ),
+        truncated: !frontier.is_empty(),
+    })
+}
+
+/// The accounts that signed up with `did`'s invite codes, the accounts they invited in turn,
+/// and so on, with per-account counts. For tracing invite-gated signups back to their source
+/// when investigating abuse.
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/xyz.blackskyweb.admin.getInviteReferralTree?<did>&<depth>")]
+pub async fn get_invite_referral_tree(
+    did: String,
+    depth: Option<usize>,
+    _auth: Moderator,
+    account_manager: AccountManager,
+) -> Result<Json<GetInviteReferralTreeOutput>, ApiError> {
+    match inner_get_invite_referral_tree(did, depth, account_manager).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn referral(inviter: &str, did: &str, taken_down: bool) -> Referral {
+        Referral {
+            inviter: inviter.to_string(),
+            code: format!("code-{did}"),
+            did: did.to_string(),
+            handle: None,
+            taken_down,
+        }
+    }
+
+    #[test]
+    fn test_build_referral_tree() {
+        let mut referrals: BTreeMap<String, Vec<Referral>> = BTreeMap::new();
+        for r in [
+            referral("did:root", "did:a", false),
+            referral("did:root", "did:b", true),
+            referral("did:a", "did:c", true),
+            referral("did:c", "did:d", false),
+        ] {
+            referrals.entry(r.inviter.clone()).or_default().push(r);
+        }
+        let root = InviteReferral {
+            did: "did:root".to_string(),
+            handle: None,
+            invite_code: None,
+            taken_down: false,
+            invited_count: 0,
+            descendant_count: 0,
+            taken_down_descendant_count: 0,
+            invited: Vec::new(),
+        };
+        let tree = build_referral_tree(root, &referrals, 2);
+        assert_eq!(tree.invited_count, 2);
+        assert_eq!(tree.descendant_count, 4);
+        assert_eq!(tree.taken_down_descendant_count, 2);
+        let a = &tree.invited[0];
+        assert_eq!(a.invite_code.as_deref(), Some("code-did:a"));
+        assert_eq!(a.descendant_count, 2);
+        // past the requested depth, counts stay but children are left out
+        let c = &a.invited[0];
+        assert_eq!(c.invited_count, 1);
+        assert!(c.invited.is_empty());
+    }
+}
@@ -0,0 +1,14 @@
+use crate::auth_verifier::AdminToken;
+use crate::maintenance::MaintenanceState;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::xyz::blackskyweb::admin::MaintenanceMode;
+
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/xyz.blackskyweb.admin.getMaintenanceMode")]
+pub async fn get_maintenance_mode(
+    _auth: AdminToken,
+    maintenance: &State<MaintenanceState>,
+) -> Json<MaintenanceMode> {
+    Json(maintenance.mode())
+}
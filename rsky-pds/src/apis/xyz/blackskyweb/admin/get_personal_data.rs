@@ -0,0 +1,22 @@
+use crate::account_manager::helpers::personal_data::PersonalData;
+use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
+use crate::auth_verifier::AdminToken;
+use rocket::serde::json::Json;
+
+/// Everything stored about an account, for answering a data subject access request.
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/xyz.blackskyweb.admin.getPersonalData?<did>")]
+pub async fn get_personal_data(
+    did: String,
+    _auth: AdminToken,
+    account_manager: AccountManager,
+) -> Result<Json<PersonalData>, ApiError> {
+    match account_manager.get_personal_data(&did).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::ActorStore;
+use crate::apis::ApiError;
+use crate::auth_verifier::Moderator;
+use crate::db::DbConn;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::xyz::blackskyweb::admin::{GetRepoCommitHistoryOutput, RepoCommitView};
+
+async fn inner_get_repo_commit_history(
+    did: String,
+    limit: Option<i64>,
+    cursor: Option<String>,
+    s3_config: &State<SdkConfig>,
+    db: DbConn,
+) -> Result<GetRepoCommitHistoryOutput> {
+    let limit = limit.unwrap_or(50).clamp(1, 1000);
+    let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+    let storage_guard = actor_store.storage.read().await;
+    let commits = storage_guard.list_commits(limit, cursor).await?;
+
+    let cursor = match commits.last() {
+        Some(last) if commits.len() as i64 == limit => Some(last.rev.clone()),
+        _ => None,
+    };
+    Ok(GetRepoCommitHistoryOutput {
+        cursor,
+        commits: commits
+            .into_iter()
+            .map(|row| RepoCommitView {
+                rev: row.rev,
+                cid: row.cid,
+                since: row.since,
+                creates: row.creates,
+                updates: row.updates,
+                deletes: row.deletes,
+                committed_at: row.committed_at,
+            })
+            .collect(),
+    })
+}
+
+/// List the most recent commits retained for a repo, newest first. Any returned `rev` can be
+/// passed as `since` to `com.atproto.sync.getRepo` to fetch the diff after that commit.
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/xyz.blackskyweb.admin.getRepoCommitHistory?<did>&<limit>&<cursor>")]
+pub async fn get_repo_commit_history(
+    did: String,
+    limit: Option<i64>,
+    cursor: Option<String>,
+    s3_config: &State<SdkConfig>,
+    _auth: Moderator,
+    db: DbConn,
+) -> Result<Json<GetRepoCommitHistoryOutput>, ApiError> {
+    match inner_get_repo_commit_history(did, limit, cursor, s3_config, db).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use crate::auth_verifier::AdminToken;
+use crate::handle::policy::HandlePolicy;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::xyz::blackskyweb::admin::GetReservedHandlesOutput;
+
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/xyz.blackskyweb.admin.getReservedHandles")]
+pub async fn get_reserved_handles(
+    _auth: AdminToken,
+    handle_policy: &State<HandlePolicy>,
+) -> Json<GetReservedHandlesOutput> {
+    Json(GetReservedHandlesOutput {
+        handles: handle_policy.list(),
+    })
+}
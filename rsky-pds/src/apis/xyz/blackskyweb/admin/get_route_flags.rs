@@ -0,0 +1,16 @@
+use crate::auth_verifier::AdminToken;
+use crate::route_flags::RouteFlags;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::xyz::blackskyweb::admin::GetRouteFlagsOutput;
+
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/xyz.blackskyweb.admin.getRouteFlags")]
+pub async fn get_route_flags(
+    _auth: AdminToken,
+    route_flags: &State<RouteFlags>,
+) -> Json<GetRouteFlagsOutput> {
+    Json(GetRouteFlagsOutput {
+        flags: route_flags.list(),
+    })
+}
@@ -0,0 +1,17 @@
+pub mod add_reserved_handle;
+pub mod erase_personal_data;
+pub mod get_deleted_records;
+pub mod get_invite_referral_tree;
+pub mod get_maintenance_mode;
+pub mod get_personal_data;
+pub mod get_repo_commit_history;
+pub mod get_reserved_handles;
+pub mod get_route_flags;
+pub mod purge_identity_cache;
+pub mod remove_reserved_handle;
+pub mod repair_record_blobs;
+pub mod reset_totp;
+pub mod rotate_account_keys;
+pub mod squash_repo;
+pub mod update_maintenance_mode;
+pub mod update_route_flag;
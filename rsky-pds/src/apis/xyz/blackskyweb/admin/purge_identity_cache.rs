@@ -0,0 +1,51 @@
+use crate::apis::ApiError;
+use crate::auth_verifier::AdminToken;
+use crate::SharedIdResolver;
+use anyhow::Result;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::xyz::blackskyweb::admin::PurgeIdentityCacheInput;
+
+async fn inner_purge_identity_cache(
+    body: Json<PurgeIdentityCacheInput>,
+    id_resolver: &State<SharedIdResolver>,
+) -> Result<()> {
+    let PurgeIdentityCacheInput { did, handle } = body.into_inner();
+    let mut lock = id_resolver.id_resolver.write().await;
+    if did.is_none() && handle.is_none() {
+        lock.purge_all()?;
+        tracing::info!("@LOG: purged identity cache");
+        return Ok(());
+    }
+    if let Some(did) = did {
+        lock.purge_did(&did)?;
+        tracing::info!("@LOG: purged cached DID document for {did}");
+    }
+    if let Some(handle) = handle {
+        lock.purge_handle(&handle);
+        tracing::info!("@LOG: purged cached resolution of {handle}");
+    }
+    Ok(())
+}
+
+/// Drops cached DID documents and handle resolutions, e.g. after an identity update the PDS
+/// didn't see, so the next lookup goes to the network.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/xyz.blackskyweb.admin.purgeIdentityCache",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn purge_identity_cache(
+    body: Json<PurgeIdentityCacheInput>,
+    _auth: AdminToken,
+    id_resolver: &State<SharedIdResolver>,
+) -> Result<(), ApiError> {
+    match inner_purge_identity_cache(body, id_resolver).await {
+        Ok(()) => Ok(()),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
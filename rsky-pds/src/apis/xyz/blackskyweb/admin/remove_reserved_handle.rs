@@ -0,0 +1,40 @@
+use crate::apis::ApiError;
+use crate::auth_verifier::AdminToken;
+use crate::db::DbConn;
+use crate::handle::policy::HandlePolicy;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::xyz::blackskyweb::admin::RemoveReservedHandleInput;
+
+/// Releases a name reserved through `addReservedHandle`. Built-in and configured names stay
+/// reserved.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/xyz.blackskyweb.admin.removeReservedHandle",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn remove_reserved_handle(
+    body: Json<RemoveReservedHandleInput>,
+    _auth: AdminToken,
+    handle_policy: &State<HandlePolicy>,
+    db: DbConn,
+) -> Result<(), ApiError> {
+    let name = body.into_inner().name.trim().to_lowercase();
+    if handle_policy.is_reserved_by_default(&name) {
+        return Err(ApiError::InvalidRequest(format!(
+            "{name} is reserved by default and can't be released at runtime"
+        )));
+    }
+    match handle_policy.release(name.clone(), &db).await {
+        Ok(true) => {
+            tracing::warn!("@LOG: released handle name {name}");
+            Ok(())
+        }
+        Ok(false) => Err(ApiError::InvalidRequest(format!("{name} isn't reserved"))),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
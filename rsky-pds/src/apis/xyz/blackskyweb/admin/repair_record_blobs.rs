@@ -0,0 +1,121 @@
+use crate::account_manager::helpers::account::AvailabilityFlags;
+use crate::account_manager::AccountManager;
+use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::ActorStore;
+use crate::apis::ApiError;
+use crate::auth_verifier::AdminToken;
+use crate::db::DbConn;
+use anyhow::{bail, Result};
+use aws_config::SdkConfig;
+use diesel::prelude::*;
+use rocket::serde::json::Json;
+use rocket::{Orbit, Rocket, State};
+use rsky_lexicon::xyz::blackskyweb::admin::{
+    RecordBlobRepair, RepairRecordBlobsInput, RepairRecordBlobsOutput,
+};
+
+async fn repair_repo(
+    did: String,
+    s3_config: &State<SdkConfig>,
+    db: DbConn,
+) -> Result<RecordBlobRepair> {
+    let mut actor_store =
+        ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+    let (added, removed) = actor_store.repair_record_blobs().await?;
+    if added > 0 || removed > 0 {
+        tracing::info!("@LOG: repaired record blobs for {did}: {added} added, {removed} removed");
+    }
+    Ok(RecordBlobRepair {
+        did,
+        added: added as i64,
+        removed: removed as i64,
+    })
+}
+
+async fn inner_repair_record_blobs(
+    body: Json<RepairRecordBlobsInput>,
+    s3_config: &State<SdkConfig>,
+    rocket: &Rocket<Orbit>,
+    account_manager: AccountManager,
+    db: DbConn,
+) -> Result<RepairRecordBlobsOutput> {
+    let RepairRecordBlobsInput { did, cursor, limit } = body.into_inner();
+    if let Some(did) = did {
+        let account = account_manager
+            .get_account(
+                &did,
+                Some(AvailabilityFlags {
+                    include_deactivated: Some(true),
+                    include_taken_down: Some(true),
+                }),
+            )
+            .await?;
+        if account.is_none() {
+            bail!(ApiError::AccountNotFound);
+        }
+        return Ok(RepairRecordBlobsOutput {
+            cursor: None,
+            repos: vec![repair_repo(did, s3_config, db).await?],
+        });
+    }
+
+    use crate::schema::pds::actor::dsl as ActorSchema;
+
+    let limit = limit.unwrap_or(50).clamp(1, 500);
+    let mut builder = ActorSchema::actor
+        .select(ActorSchema::did)
+        .order(ActorSchema::did.asc())
+        .limit(limit)
+        .into_boxed();
+    if let Some(cursor) = cursor {
+        builder = builder.filter(ActorSchema::did.gt(cursor));
+    }
+    let dids: Vec<String> = db.run(move |conn| builder.load(conn)).await?;
+
+    // a short page means there's nothing after it
+    let cursor = match dids.last() {
+        Some(last) if dids.len() as i64 == limit => Some(last.clone()),
+        _ => None,
+    };
+    let mut repos = Vec::with_capacity(dids.len());
+    for did in dids {
+        // each actor store takes its own connection
+        let Some(db) = DbConn::get_one(rocket).await else {
+            bail!(ApiError::ServiceUnavailable(
+                "No database connection available".to_string()
+            ));
+        };
+        match repair_repo(did.clone(), s3_config, db).await {
+            Ok(repair) => repos.push(repair),
+            // e.g. accounts whose repo was never created, which shouldn't stop the batch
+            Err(error) => tracing::warn!("@LOG: couldn't repair record blobs for {did}: {error}"),
+        }
+    }
+    Ok(RepairRecordBlobsOutput { cursor, repos })
+}
+
+/// Recompute which blobs each record references from the records themselves, for one account
+/// or, without a `did`, a batch of accounts paged by `cursor`. Fixes drift in the record to
+/// blob associations that shows up as false positives in `listMissingBlobs` and blob GC.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/xyz.blackskyweb.admin.repairRecordBlobs",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn repair_record_blobs(
+    body: Json<RepairRecordBlobsInput>,
+    s3_config: &State<SdkConfig>,
+    rocket: &Rocket<Orbit>,
+    _auth: AdminToken,
+    account_manager: AccountManager,
+    db: DbConn,
+) -> Result<Json<RepairRecordBlobsOutput>, ApiError> {
+    match inner_repair_record_blobs(body, s3_config, rocket, account_manager, db).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
+use crate::auth_verifier::AdminToken;
+use rocket::serde::json::Json;
+use rsky_lexicon::xyz::blackskyweb::admin::ResetTotpInput;
+
+/// Turns off two-factor authentication for an account locked out of it, so it can sign in with
+/// its password alone and enroll again.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/xyz.blackskyweb.admin.resetTotp",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn reset_totp(
+    body: Json<ResetTotpInput>,
+    _auth: AdminToken,
+    account_manager: AccountManager,
+) -> Result<(), ApiError> {
+    let ResetTotpInput { did } = body.into_inner();
+    match account_manager.delete_totp(&did).await {
+        Ok(true) => {
+            tracing::warn!("@LOG: reset two-factor authentication for {did}");
+            Ok(())
+        }
+        Ok(false) => Err(ApiError::InvalidRequest(format!(
+            "{did} doesn't have two-factor authentication"
+        ))),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use crate::account_manager::helpers::account::AvailabilityFlags;
+use crate::account_manager::AccountManager;
+use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::ActorStore;
+use crate::apis::ApiError;
+use crate::auth_verifier::AdminToken;
+use crate::config::keys;
+use crate::db::DbConn;
+use crate::plc::operations::{create_atproto_update_op, normalize_op, CreateAtprotoUpdateOpOpts};
+use crate::plc::types::{CompatibleOp, CompatibleOpOrTombstone, OpOrTombstone};
+use crate::{plc, SharedSequencer};
+use anyhow::{bail, Result};
+use aws_config::SdkConfig;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_common::env::env_str;
+use rsky_lexicon::xyz::blackskyweb::admin::{RotateAccountKeysInput, RotateAccountKeysOutput};
+
+/// Swaps the retired server rotation key for the current one, keeping its priority.
+pub fn rotate_server_key(
+    rotation_keys: &[String],
+    previous: Option<&str>,
+    current: &str,
+) -> Vec<String> {
+    let mut rotated: Vec<String> = Vec::with_capacity(rotation_keys.len());
+    for key in rotation_keys {
+        let key = match previous {
+            Some(previous) if key == previous => current,
+            _ => key.as_str(),
+        };
+        if !rotated.iter().any(|k| k == key) {
+            rotated.push(key.to_string());
+        }
+    }
+    rotated
+}
+
+async fn inner_rotate_account_keys(
+    body: Json<RotateAccountKeysInput>,
+    sequencer: &State<SharedSequencer>,
+    s3_config: &State<SdkConfig>,
+    account_manager: AccountManager,
+    db: DbConn,
+) -> Result<RotateAccountKeysOutput> {
+    let RotateAccountKeysInput { did } = body.into_inner();
+    let account = account_manager
+        .get_account(
+            &did,
+            Some(AvailabilityFlags {
+                include_deactivated: Some(true),
+                include_taken_down: Some(true),
+            }),
+        )
+        .await?;
+    if account.is_none() {
+        bail!(ApiError::AccountNotFound);
+    }
+    if !did.starts_with("did:plc:") {
+        // did:web documents are published by their owner, not through us
+        bail!(ApiError::InvalidRequest(format!(
+            "Keys can only be rotated for did:plc identities: {did}"
+        )));
+    }
+    let service_keys = keys::service_keys()?;
+    let signing_key = service_keys.repo_signing_did_key();
+    let rotation_key = service_keys.plc_rotation_did_key();
+    let previous_rotation_key = service_keys.previous_plc_rotation_did_key();
+
+    let plc_url = env_str("PDS_DID_PLC_URL").unwrap_or("https://plc.directory".to_owned());
+    let plc_client = plc::Client::new(plc_url);
+    let last_op: CompatibleOp = match plc_client.ensure_last_op(&did).await? {
+        CompatibleOpOrTombstone::CreateOpV1(last_op) => CompatibleOp::CreateOpV1(last_op),
+        CompatibleOpOrTombstone::Operation(last_op) => CompatibleOp::Operation(last_op),
+        CompatibleOpOrTombstone::Tombstone(_) => bail!(ApiError::InvalidRequest(format!(
+            "{did} is tombstoned and has no keys to rotate"
+        ))),
+    };
+    let current = normalize_op(last_op.clone());
+    // the op has to be signed by a key the document already trusts
+    let signer = match (service_keys.previous_plc_rotation, &previous_rotation_key) {
+        (Some(previous), Some(previous_did_key))
+            if current.rotation_keys.contains(previous_did_key) =>
+        {
+            previous
+        }
+        _ if current.rotation_keys.contains(&rotation_key) => service_keys.plc_rotation,
+        _ => bail!(ApiError::InvalidRequest(format!(
+            "No server rotation key is authorized to update {did}"
+        ))),
+    };
+    let rotation_keys = rotate_server_key(
+        &current.rotation_keys,
+        previous_rotation_key.as_deref(),
+        &rotation_key,
+    );
+    if current.verification_methods.get("atproto") != Some(&signing_key)
+        || current.rotation_keys != rotation_keys
+    {
+        let op = create_atproto_update_op(
+            last_op,
+            &signer,
+            CreateAtprotoUpdateOpOpts {
+                signing_key: Some(signing_key.clone()),
+                handle: None,
+                pds: None,
+                rotation_keys: Some(rotation_keys.clone()),
+            },
+        )
+        .await?;
+        plc_client
+            .send_operation(&did, &OpOrTombstone::Operation(op))
+            .await?;
+    }
+
+    let mut actor_store =
+        ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+    let commit = actor_store
+        .resign_repo(keys::repo_signing_keypair()?)
+        .await?;
+    let sync_data = actor_store.get_sync_event_data().await?;
+    let lock = sequencer.sequencer.read().await;
+    lock.sequence_identity_evt(did.clone(), None).await?;
+    lock.sequence_sync_evt(did.clone(), sync_data).await?;
+
+    Ok(RotateAccountKeysOutput {
+        did,
+        signing_key,
+        rotation_keys,
+        rev: commit.rev,
+    })
+}
+
+/// Point an account's DID document at the server's current repo signing and PLC rotation
+/// keys, then re-sign its repo head with the new signing key. Run for every hosted account
+/// after deploying new keys, with the old rotation key kept as
+/// `PDS_PLC_ROTATION_KEY_K256_PREVIOUS` until all accounts are done.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/xyz.blackskyweb.admin.rotateAccountKeys",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn rotate_account_keys(
+    body: Json<RotateAccountKeysInput>,
+    sequencer: &State<SharedSequencer>,
+    s3_config: &State<SdkConfig>,
+    _auth: AdminToken,
+    account_manager: AccountManager,
+    db: DbConn,
+) -> Result<Json<RotateAccountKeysOutput>, ApiError> {
+    match inner_rotate_account_keys(body, sequencer, s3_config, account_manager, db).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_server_key() {
+        let keys = vec!["did:key:recovery".to_string(), "did:key:old".to_string()];
+        assert_eq!(
+            rotate_server_key(&keys, Some("did:key:old"), "did:key:new"),
+            vec!["did:key:recovery", "did:key:new"]
+        );
+        // already rotated, or nothing to retire
+        let rotated = vec!["did:key:new".to_string(), "did:key:old".to_string()];
+        assert_eq!(
+            rotate_server_key(&rotated, Some("did:key:old"), "did:key:new"),
+            vec!["did:key:new"]
+        );
+        assert_eq!(rotate_server_key(&keys, None, "did:key:new"), keys);
+    }
+}
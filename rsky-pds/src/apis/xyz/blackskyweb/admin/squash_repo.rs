@@ -0,0 +1,78 @@
+use crate::account_manager::helpers::account::AvailabilityFlags;
+use crate::account_manager::AccountManager;
+use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::ActorStore;
+use crate::apis::ApiError;
+use crate::auth_verifier::AdminToken;
+use crate::config::keys;
+use crate::db::DbConn;
+use crate::SharedSequencer;
+use anyhow::{bail, Result};
+use aws_config::SdkConfig;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::xyz::blackskyweb::admin::{SquashRepoInput, SquashRepoOutput};
+
+async fn inner_squash_repo(
+    body: Json<SquashRepoInput>,
+    sequencer: &State<SharedSequencer>,
+    s3_config: &State<SdkConfig>,
+    account_manager: AccountManager,
+    db: DbConn,
+) -> Result<SquashRepoOutput> {
+    let SquashRepoInput { did } = body.into_inner();
+    let account = account_manager
+        .get_account(
+            &did,
+            Some(AvailabilityFlags {
+                include_deactivated: Some(true),
+                include_taken_down: Some(true),
+            }),
+        )
+        .await?;
+    if account.is_none() {
+        bail!(ApiError::AccountNotFound);
+    }
+
+    let mut actor_store =
+        ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config), db);
+    let commit = actor_store
+        .squash_repo(keys::repo_signing_keypair()?)
+        .await?;
+    // the new commit doesn't follow from the last one, so consumers have to resync the repo
+    let sync_data = actor_store.get_sync_event_data().await?;
+    let lock = sequencer.sequencer.read().await;
+    lock.sequence_sync_evt(did.clone(), sync_data).await?;
+
+    Ok(SquashRepoOutput {
+        did,
+        cid: commit.cid.to_string(),
+        rev: commit.rev,
+    })
+}
+
+/// Rebuild an account's MST from its indexed records and their blocks, committing the rebuilt
+/// tree under a new rev and emitting a `#sync` event. For recovering repos whose tree was left
+/// inconsistent by past bugs.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/xyz.blackskyweb.admin.squashRepo",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn squash_repo(
+    body: Json<SquashRepoInput>,
+    sequencer: &State<SharedSequencer>,
+    s3_config: &State<SdkConfig>,
+    _auth: AdminToken,
+    account_manager: AccountManager,
+    db: DbConn,
+) -> Result<Json<SquashRepoOutput>, ApiError> {
+    match inner_squash_repo(body, sequencer, s3_config, account_manager, db).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use crate::auth_verifier::AdminToken;
+use crate::maintenance::MaintenanceState;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::xyz::blackskyweb::admin::MaintenanceMode;
+
+/// Turns read-only maintenance mode on or off. Takes effect for the next request.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/xyz.blackskyweb.admin.updateMaintenanceMode",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn update_maintenance_mode(
+    body: Json<MaintenanceMode>,
+    _auth: AdminToken,
+    maintenance: &State<MaintenanceState>,
+) -> Json<MaintenanceMode> {
+    let MaintenanceMode { read_only, reason } = body.into_inner();
+    maintenance.set(read_only, reason);
+    tracing::warn!("@LOG: read-only maintenance mode set to {read_only}");
+    Json(maintenance.mode())
+}
@@ -0,0 +1,38 @@
+use crate::apis::ApiError;
+use crate::auth_verifier::AdminToken;
+use crate::db::DbConn;
+use crate::route_flags::RouteFlags;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::xyz::blackskyweb::admin::RouteFlag;
+
+/// Disables or re-enables an xrpc method. Takes effect on this replica for the next request,
+/// and on the others once they refresh.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/xyz.blackskyweb.admin.updateRouteFlag",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn update_route_flag(
+    body: Json<RouteFlag>,
+    _auth: AdminToken,
+    route_flags: &State<RouteFlags>,
+    db: DbConn,
+) -> Result<Json<RouteFlag>, ApiError> {
+    let flag = body.into_inner();
+    if flag.nsid.is_empty() {
+        return Err(ApiError::InvalidRequest("nsid is required".to_string()));
+    }
+    let (nsid, disabled) = (flag.nsid.clone(), flag.disabled);
+    match route_flags.set(flag.clone(), &db).await {
+        Ok(()) => {
+            tracing::warn!("@LOG: route {nsid} set to disabled={disabled}");
+            Ok(Json(flag))
+        }
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(ApiError::RuntimeError)
+        }
+    }
+}
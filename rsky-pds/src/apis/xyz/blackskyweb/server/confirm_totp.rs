@@ -0,0 +1,31 @@
+use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
+use crate::auth_verifier::AccessFull;
+use rocket::serde::json::Json;
+use rsky_lexicon::xyz::blackskyweb::server::{ConfirmTotpInput, ConfirmTotpOutput};
+
+/// Turns on two-factor authentication once `code` shows the authenticator app has the secret
+/// from `createTotpSecret`. The recovery codes returned aren't shown again.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/xyz.blackskyweb.server.confirmTotp",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn confirm_totp(
+    body: Json<ConfirmTotpInput>,
+    auth: AccessFull,
+    account_manager: AccountManager,
+) -> Result<Json<ConfirmTotpOutput>, ApiError> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    match account_manager.confirm_totp(&did, &body.code).await {
+        Ok(recovery_codes) => {
+            tracing::info!("@LOG: enabled two-factor authentication for {did}");
+            Ok(Json(ConfirmTotpOutput { recovery_codes }))
+        }
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use crate::account_manager::helpers::totp::provisioning_uri;
+use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
+use crate::auth_verifier::AccessFull;
+use crate::config::ServerConfig;
+use anyhow::Result;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::xyz::blackskyweb::server::CreateTotpSecretOutput;
+
+async fn inner_create_totp_secret(
+    auth: AccessFull,
+    cfg: &State<ServerConfig>,
+    account_manager: AccountManager,
+) -> Result<CreateTotpSecretOutput> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let account = account_manager.get_account(&did, None).await?;
+    let label = account
+        .and_then(|account| account.handle)
+        .unwrap_or(did.clone());
+    let secret = account_manager.create_totp_secret(&did).await?;
+    Ok(CreateTotpSecretOutput {
+        uri: provisioning_uri(&secret, &label, &cfg.service.hostname),
+        secret,
+    })
+}
+
+/// Starts two-factor enrollment with a new authenticator app secret, replacing one that
+/// wasn't confirmed. Requires auth, and not with an app password.
+#[tracing::instrument(skip_all)]
+#[rocket::post("/xrpc/xyz.blackskyweb.server.createTotpSecret")]
+pub async fn create_totp_secret(
+    auth: AccessFull,
+    cfg: &State<ServerConfig>,
+    account_manager: AccountManager,
+) -> Result<Json<CreateTotpSecretOutput>, ApiError> {
+    match inner_create_totp_secret(auth, cfg, account_manager).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
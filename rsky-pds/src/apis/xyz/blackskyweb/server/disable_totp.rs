@@ -0,0 +1,43 @@
+use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
+use crate::auth_verifier::AccessFull;
+use anyhow::Result;
+use rocket::serde::json::Json;
+use rsky_lexicon::xyz::blackskyweb::server::DisableTotpInput;
+
+async fn inner_disable_totp(
+    body: Json<DisableTotpInput>,
+    auth: AccessFull,
+    account_manager: AccountManager,
+) -> Result<()> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let DisableTotpInput { code } = body.into_inner();
+    // a stolen session alone isn't enough to turn it off
+    account_manager.verify_auth_factor(&did, Some(code)).await?;
+    if account_manager.delete_totp(&did).await? {
+        tracing::info!("@LOG: disabled two-factor authentication for {did}");
+    }
+    Ok(())
+}
+
+/// Turns off two-factor authentication, given a current or recovery code. Requires auth, and
+/// not with an app password.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/xyz.blackskyweb.server.disableTotp",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn disable_totp(
+    body: Json<DisableTotpInput>,
+    auth: AccessFull,
+    account_manager: AccountManager,
+) -> Result<(), ApiError> {
+    match inner_disable_totp(body, auth, account_manager).await {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
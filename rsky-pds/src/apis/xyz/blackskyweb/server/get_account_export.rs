@@ -0,0 +1,76 @@
+use crate::account_manager::helpers::account_export::EXPORT_COMPLETE;
+use crate::account_manager::AccountManager;
+use crate::actor_store::aws::s3::S3BlobStore;
+use crate::apis::ApiError;
+use crate::auth_verifier::AccessFull;
+use crate::config::ServerConfig;
+use crate::models::AccountExport;
+use anyhow::{bail, Result};
+use aws_config::SdkConfig;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::xyz::blackskyweb::server::AccountExportView;
+use std::time::Duration;
+
+pub async fn to_export_view(
+    export: AccountExport,
+    s3_config: &SdkConfig,
+    cfg: &ServerConfig,
+) -> Result<AccountExportView> {
+    let download_url = if export.status == EXPORT_COMPLETE {
+        let blobstore = S3BlobStore::new(export.did.clone(), s3_config);
+        Some(
+            blobstore
+                .get_export_presigned_url(
+                    &export.id,
+                    Duration::from_secs(cfg.account_export.url_expires_in),
+                )
+                .await?,
+        )
+    } else {
+        None
+    };
+    Ok(AccountExportView {
+        id: export.id,
+        status: export.status,
+        created_at: export.created_at,
+        completed_at: export.completed_at,
+        download_url,
+        error: export.error,
+        migration: export.migration,
+    })
+}
+
+async fn inner_get_account_export(
+    id: Option<String>,
+    auth: AccessFull,
+    account_manager: AccountManager,
+    s3_config: &State<SdkConfig>,
+    cfg: &State<ServerConfig>,
+) -> Result<AccountExportView> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    match account_manager.get_account_export(&did, id).await? {
+        None => bail!(ApiError::InvalidRequest("Export not found".to_string())),
+        Some(export) => to_export_view(export, s3_config, cfg).await,
+    }
+}
+
+/// Status of a takeout archive, the latest one if no id is given. Once complete, includes a
+/// short-lived download link.
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/xyz.blackskyweb.server.getAccountExport?<id>")]
+pub async fn get_account_export(
+    id: Option<String>,
+    auth: AccessFull,
+    account_manager: AccountManager,
+    s3_config: &State<SdkConfig>,
+    cfg: &State<ServerConfig>,
+) -> Result<Json<AccountExportView>, ApiError> {
+    match inner_get_account_export(id, auth, account_manager, s3_config, cfg).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
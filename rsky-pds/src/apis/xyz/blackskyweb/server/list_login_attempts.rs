@@ -0,0 +1,42 @@
+use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
+use crate::auth_verifier::AccessFull;
+use anyhow::Result;
+use rocket::serde::json::Json;
+use rsky_lexicon::xyz::blackskyweb::server::{ListLoginAttemptsOutput, LoginAttemptView};
+
+async fn inner_list_login_attempts(
+    auth: AccessFull,
+    account_manager: AccountManager,
+) -> Result<ListLoginAttemptsOutput> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let attempts = account_manager
+        .get_login_attempts(&did)
+        .await?
+        .into_iter()
+        .map(|attempt| LoginAttemptView {
+            success: attempt.success,
+            method: attempt.method,
+            ip: attempt.ip,
+            user_agent: attempt.user_agent,
+            created_at: attempt.created_at,
+        })
+        .collect();
+    Ok(ListLoginAttemptsOutput { attempts })
+}
+
+/// Recent sign-in attempts against the account, successful or not, newest first.
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/xyz.blackskyweb.server.listLoginAttempts")]
+pub async fn list_login_attempts(
+    auth: AccessFull,
+    account_manager: AccountManager,
+) -> Result<Json<ListLoginAttemptsOutput>, ApiError> {
+    match inner_list_login_attempts(auth, account_manager).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
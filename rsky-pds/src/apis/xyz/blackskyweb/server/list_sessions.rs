@@ -0,0 +1,42 @@
+use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
+use crate::auth_verifier::AccessFull;
+use anyhow::Result;
+use rocket::serde::json::Json;
+use rsky_lexicon::xyz::blackskyweb::server::{ListSessionsOutput, SessionView};
+
+async fn inner_list_sessions(
+    auth: AccessFull,
+    account_manager: AccountManager,
+) -> Result<ListSessionsOutput> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let sessions = account_manager
+        .list_sessions(&did)
+        .await?
+        .into_iter()
+        .map(|token| SessionView {
+            id: token.id,
+            session_name: token.session_name,
+            app_password_name: token.app_password_name,
+            expires_at: token.expires_at,
+        })
+        .collect();
+    Ok(ListSessionsOutput { sessions })
+}
+
+/// Sessions signed in to the account, with the names clients gave them at createSession or
+/// refreshSession.
+#[tracing::instrument(skip_all)]
+#[rocket::get("/xrpc/xyz.blackskyweb.server.listSessions")]
+pub async fn list_sessions(
+    auth: AccessFull,
+    account_manager: AccountManager,
+) -> Result<Json<ListSessionsOutput>, ApiError> {
+    match inner_list_sessions(auth, account_manager).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod confirm_totp;
+pub mod create_totp_secret;
+pub mod disable_totp;
+pub mod get_account_export;
+pub mod list_login_attempts;
+pub mod list_sessions;
+pub mod request_account_export;
+pub mod update_email_preference;
+pub mod update_locale;
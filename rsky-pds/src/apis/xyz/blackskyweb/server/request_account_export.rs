@@ -0,0 +1,121 @@
+use crate::account_manager::helpers::account::AccountStatusTransition;
+use crate::account_manager::AccountManager;
+use crate::actor_store::aws::s3::S3BlobStore;
+use crate::actor_store::export::build_export_archive;
+use crate::actor_store::ActorStore;
+use crate::apis::xyz::blackskyweb::server::get_account_export::to_export_view;
+use crate::apis::ApiError;
+use crate::auth_verifier::AccessFull;
+use crate::config::ServerConfig;
+use crate::db::DbConn;
+use crate::SharedSequencer;
+use anyhow::{bail, Result};
+use aws_config::SdkConfig;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_common::time::from_micros_to_str;
+use rsky_lexicon::xyz::blackskyweb::server::{AccountExportView, RequestAccountExportInput};
+
+async fn run_export(
+    id: String,
+    did: String,
+    s3_config: SdkConfig,
+    account_manager: AccountManager,
+    db: DbConn,
+) {
+    let res: Result<()> = async {
+        let actor_store =
+            ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), &s3_config), db);
+        let archive = build_export_archive(&actor_store).await?;
+        actor_store.blob.blobstore.put_export(&id, archive).await
+    }
+    .await;
+    let error = match res {
+        Ok(_) => None,
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: account export {id} for {did} failed: {error}");
+            Some("Export failed".to_string())
+        }
+    };
+    if let Err(error) = account_manager.complete_account_export(&id, error).await {
+        tracing::error!("@LOG: ERROR: could not record account export {id}: {error}");
+    }
+}
+
+async fn inner_request_account_export(
+    body: Option<Json<RequestAccountExportInput>>,
+    auth: AccessFull,
+    sequencer: &State<SharedSequencer>,
+    account_manager: AccountManager,
+    db: DbConn,
+    s3_config: &State<SdkConfig>,
+    cfg: &State<ServerConfig>,
+) -> Result<AccountExportView> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let migration = body.and_then(|body| body.migration).unwrap_or(false);
+    let export = match account_manager
+        .create_account_export(&did, migration)
+        .await?
+    {
+        Some(export) => export,
+        None => bail!(ApiError::InvalidRequest(
+            "An export is already in progress".to_string()
+        )),
+    };
+    if migration {
+        // deactivating freezes the repo before the archive is built, and tells the network the
+        // account is leaving; deleteAccount holds off until the retention is over
+        let delete_after = from_micros_to_str(
+            chrono::Utc::now().timestamp_micros()
+                + cfg.account_export.migration_retention as i64 * 1_000_000,
+        );
+        if let Err(error) = account_manager
+            .update_account_status(
+                &did,
+                vec![AccountStatusTransition::Deactivate {
+                    delete_after: Some(delete_after),
+                }],
+                sequencer,
+            )
+            .await
+        {
+            account_manager
+                .complete_account_export(&export.id, Some("Export failed".to_string()))
+                .await?;
+            return Err(error);
+        }
+    }
+    tokio::spawn(run_export(
+        export.id.clone(),
+        did,
+        s3_config.inner().clone(),
+        account_manager,
+        db,
+    ));
+    to_export_view(export, s3_config, cfg).await
+}
+
+/// Start building a takeout archive of the account's repo, blob manifest and preferences.
+/// Poll getAccountExport for a download link. With `migration`, the account is deactivated
+/// first so the archive is final, for moving to another PDS.
+#[tracing::instrument(skip_all)]
+#[rocket::post("/xrpc/xyz.blackskyweb.server.requestAccountExport", data = "<body>")]
+pub async fn request_account_export(
+    body: Option<Json<RequestAccountExportInput>>,
+    auth: AccessFull,
+    sequencer: &State<SharedSequencer>,
+    account_manager: AccountManager,
+    db: DbConn,
+    s3_config: &State<SdkConfig>,
+    cfg: &State<ServerConfig>,
+) -> Result<Json<AccountExportView>, ApiError> {
+    match inner_request_account_export(body, auth, sequencer, account_manager, db, s3_config, cfg)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error.into())
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::account_manager::helpers::email_preference::assert_valid_preference;
+use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
+use crate::auth_verifier::AccessFull;
+use rocket::serde::json::Json;
+use rsky_lexicon::xyz::blackskyweb::server::UpdateEmailPreferenceInput;
+
+async fn inner_update_email_preference(
+    body: Json<UpdateEmailPreferenceInput>,
+    auth: AccessFull,
+    account_manager: AccountManager,
+) -> Result<(), ApiError> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let UpdateEmailPreferenceInput { preference } = body.into_inner();
+    assert_valid_preference(&preference)
+        .map_err(|error| ApiError::InvalidRequest(error.to_string()))?;
+    account_manager
+        .update_email_preference(&did, &preference)
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/xyz.blackskyweb.server.updateEmailPreference",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn update_email_preference(
+    body: Json<UpdateEmailPreferenceInput>,
+    auth: AccessFull,
+    account_manager: AccountManager,
+) -> Result<(), ApiError> {
+    match inner_update_email_preference(body, auth, account_manager).await {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error)
+        }
+    }
+}
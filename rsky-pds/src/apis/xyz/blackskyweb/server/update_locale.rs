@@ -0,0 +1,45 @@
+use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
+use crate::auth_verifier::AccessStandard;
+use crate::locale;
+use rocket::serde::json::Json;
+use rsky_lexicon::xyz::blackskyweb::server::UpdateLocaleInput;
+
+async fn inner_update_locale(
+    body: Json<UpdateLocaleInput>,
+    auth: AccessStandard,
+    account_manager: AccountManager,
+) -> Result<(), ApiError> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let UpdateLocaleInput { locale } = body.into_inner();
+    if let Some(tag) = &locale {
+        if locale::normalize(tag).is_none() {
+            return Err(ApiError::InvalidRequest(format!("Invalid locale: {tag}")));
+        }
+    }
+    account_manager
+        .update_locale(&did, locale.as_deref())
+        .await?;
+    Ok(())
+}
+
+/// Sets the language mail to the account is written in.
+#[tracing::instrument(skip_all)]
+#[rocket::post(
+    "/xrpc/xyz.blackskyweb.server.updateLocale",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn update_locale(
+    body: Json<UpdateLocaleInput>,
+    auth: AccessStandard,
+    account_manager: AccountManager,
+) -> Result<(), ApiError> {
+    match inner_update_locale(body, auth, account_manager).await {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(error)
+        }
+    }
+}
@@ -2,6 +2,8 @@ use crate::account_manager::helpers::account::{ActorAccount, AvailabilityFlags};
 use crate::account_manager::helpers::auth::CustomClaimObj;
 use crate::account_manager::AccountManager;
 use crate::apis::ApiError;
+use crate::config::ServerConfig;
+use crate::oauth;
 use crate::xrpc_server::auth::{verify_jwt as verify_service_jwt_server, ServiceJwtPayload};
 use crate::SharedIdResolver;
 use anyhow::{bail, Result};
@@ -9,6 +11,7 @@ use base64::{engine::general_purpose::STANDARD as base64pad, Engine as _};
 use jwt_simple::claims::Audiences;
 use jwt_simple::prelude::*;
 use rocket::http::Status;
+use rocket::mtls::Certificate;
 use rocket::request::{FromRequest, Outcome, Request};
 use rocket::State;
 use rsky_common::env::env_str;
@@ -52,6 +55,12 @@ impl AuthScope {
             _ => bail!("Invalid AuthScope: `{scope:?}` is not a valid auth scope"),
         }
     }
+
+    /// Whether sessions with this scope may call the privileged methods, i.e. full password
+    /// sessions and privileged app passwords.
+    pub fn is_privileged(&self) -> bool {
+        matches!(self, AuthScope::Access | AuthScope::AppPassPrivileged)
+    }
 }
 
 pub enum RoleStatus {
@@ -78,6 +87,25 @@ pub struct AccessOutput {
     pub artifacts: Option<String>,
 }
 
+impl AccessOutput {
+    /// The output for a validated access token.
+    pub fn access(did: String, scope: AuthScope, audience: Option<String>, token: String) -> Self {
+        AccessOutput {
+            credentials: Some(Credentials {
+                r#type: "access".to_string(),
+                did: Some(did),
+                is_privileged: Some(scope.is_privileged()),
+                scope: Some(scope),
+                audience,
+                token_id: None,
+                aud: None,
+                iss: None,
+            }),
+            artifacts: Some(token),
+        }
+    }
+}
+
 pub struct ValidatedBearer {
     pub did: String,
     pub scope: AuthScope,
@@ -643,6 +671,10 @@ impl<'r> FromRequest<'r> for AdminToken {
                     let error = AuthError::AuthRequired("BadAuth".to_string());
                     req.local_cache(|| Some(ApiError::InvalidRequest(error.to_string())));
                     Outcome::Error((Status::BadRequest, error))
+                } else if !has_admin_client_cert(req).await {
+                    let error = AuthError::AuthRequired("ClientCertRequired".to_string());
+                    req.local_cache(|| Some(ApiError::AuthRequiredError(error.to_string())));
+                    Outcome::Error((Status::Unauthorized, error))
                 } else {
                     Outcome::Success(AdminToken {
                         access: AccessOutput {
@@ -665,6 +697,16 @@ impl<'r> FromRequest<'r> for AdminToken {
     }
 }
 
+/// With `PDS_ADMIN_MTLS_CA` set, the admin token alone isn't enough: the connection must also
+/// have presented a client certificate chaining to one of those CAs.
+async fn has_admin_client_cert(req: &Request<'_>) -> bool {
+    let required = req
+        .rocket()
+        .state::<ServerConfig>()
+        .is_some_and(|cfg| cfg.admin.mtls_ca.is_some());
+    !required || req.guard::<Certificate<'_>>().await.is_success()
+}
+
 #[derive(Clone)]
 pub struct OptionalAccessOrAdminToken {
     pub access: Option<AccessOutput>,
@@ -675,7 +717,7 @@ impl<'r> FromRequest<'r> for OptionalAccessOrAdminToken {
     type Error = AuthError;
 
     async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        if is_bearer_token(req) {
+        if is_bearer_token(req) || is_dpop_token(req) {
             match AccessFull::from_request(req).await {
                 Outcome::Success(output) => Outcome::Success(OptionalAccessOrAdminToken {
                     access: Some(output.access),
@@ -703,37 +745,6 @@ impl<'r> FromRequest<'r> for OptionalAccessOrAdminToken {
     }
 }
 
-pub async fn validate_bearer_access_token<'r>(
-    request: &'r Request<'_>,
-    scopes: Vec<AuthScope>,
-) -> Result<AccessOutput> {
-    let mut options = VerificationOptions::default();
-    options.allowed_audiences = Some(HashSet::from_strings(&[
-        env::var("PDS_SERVICE_DID").unwrap()
-    ]));
-    let ValidatedBearer {
-        did,
-        scope,
-        token,
-        audience,
-        ..
-    } = validate_bearer_token(request, scopes, Some(options)).await?;
-    let is_privileged = vec![AuthScope::Access, AuthScope::AppPassPrivileged].contains(&scope);
-    Ok(AccessOutput {
-        credentials: Some(Credentials {
-            r#type: "access".to_string(),
-            did: Some(did),
-            scope: Some(scope),
-            audience,
-            token_id: None,
-            aud: None,
-            iss: None,
-            is_privileged: Some(is_privileged),
-        }),
-        artifacts: Some(token),
-    })
-}
-
 pub async fn validate_bearer_token<'r>(
     request: &'r Request<'_>,
     scopes: Vec<AuthScope>,
@@ -781,7 +792,25 @@ pub async fn validate_bearer_token<'r>(
     }
 }
 
-// @TODO: Implement DPop/OAuth
+/// Validates a DPoP-bound OAuth access token when the request carries one, otherwise a bearer
+/// token from a legacy session.
+pub async fn validate_access_bearer<'r>(
+    request: &'r Request<'_>,
+    scopes: Vec<AuthScope>,
+    verify_options: Option<VerificationOptions>,
+) -> Result<ValidatedBearer> {
+    match dpop_token_from_req(request)? {
+        None => validate_bearer_token(request, scopes, verify_options).await,
+        Some(token) => {
+            let validated = oauth::validate_dpop_access(request, token).await?;
+            if scopes.len() > 0 && !scopes.contains(&validated.scope) {
+                bail!("Bad token scope")
+            }
+            Ok(validated)
+        }
+    }
+}
+
 pub async fn validate_access_token<'r>(
     request: &'r Request<'_>,
     scopes: Vec<AuthScope>,
@@ -797,7 +826,7 @@ pub async fn validate_access_token<'r>(
         token,
         audience,
         ..
-    } = validate_bearer_token(request, scopes, Some(options)).await?;
+    } = validate_access_bearer(request, scopes, Some(options)).await?;
     let ValidateAccessTokenOpts {
         check_takedown,
         check_deactivated,
@@ -854,19 +883,7 @@ pub async fn validate_access_token<'r>(
             )));
         }
     }
-    Ok(AccessOutput {
-        credentials: Some(Credentials {
-            r#type: "access".to_string(),
-            did: Some(did),
-            scope: Some(scope),
-            audience,
-            token_id: None,
-            aud: None,
-            iss: None,
-            is_privileged: None,
-        }),
-        artifacts: Some(token),
-    })
+    Ok(AccessOutput::access(did, scope, audience, token))
 }
 
 pub async fn verify_service_jwt<'r>(
@@ -879,10 +896,14 @@ pub async fn verify_service_jwt<'r>(
             Some(opts_iss) if opts_iss.contains(&iss) => bail!("UntrustedIss: Untrusted issuer"),
             _ => (),
         }
-        let parts = iss.split("#").collect::<Vec<&str>>();
-        if let (Some(did), Some(service_id)) = (parts.get(0), parts.get(1)) {
-            let (did, service_id) = (did.to_string(), *service_id);
-            let key_id = if service_id == "atproto_labeler" {
+        // users sign as their bare did, services as `<did>#<service id>`
+        let (did, service_id) = match iss.split_once('#') {
+            Some((did, service_id)) => (did, Some(service_id)),
+            None => (iss.as_str(), None),
+        };
+        if did.starts_with("did:") {
+            let did = did.to_string();
+            let key_id = if service_id == Some("atproto_labeler") {
                 "atproto_label"
             } else {
                 "atproto"
@@ -932,6 +953,7 @@ pub fn is_user_or_admin(auth: AccessOutput, did: &String) -> bool {
 
 const BEARER: &str = "Bearer ";
 const BASIC: &str = "Basic ";
+const DPOP: &str = "DPoP ";
 
 pub fn is_bearer_token(request: &Request) -> bool {
     match request.headers().get_one("Authorization") {
@@ -940,6 +962,13 @@ pub fn is_bearer_token(request: &Request) -> bool {
     }
 }
 
+pub fn is_dpop_token(request: &Request) -> bool {
+    match request.headers().get_one("Authorization") {
+        None => false,
+        Some(auth_header) => auth_header.starts_with(DPOP),
+    }
+}
+
 pub fn is_basic_token(request: &Request) -> bool {
     match request.headers().get_one("Authorization") {
         None => false,
@@ -958,6 +987,13 @@ pub fn bearer_token_from_req(request: &Request) -> Result<Option<String>> {
     }
 }
 
+pub fn dpop_token_from_req(request: &Request) -> Result<Option<String>> {
+    match request.headers().get_one("authorization") {
+        Some(header) if header.starts_with(DPOP) => Ok(Some(header[DPOP.len()..].to_string())),
+        _ => Ok(None),
+    }
+}
+
 pub async fn verify_jwt(
     jwt: String,
     jwt_key: Keypair,
@@ -0,0 +1,92 @@
+//! Reads back the firehose archive written by the sequencer archiver.
+//!
+//! ```text
+//! rsky-pds-replay <from> [<to>] [--restore]
+//! ```
+//!
+//! `<from>` and `<to>` are RFC 3339 times; every hour from the one containing `<from>` up to
+//! and including the one containing `<to>` (default: `<from>`'s) is read. Events are printed
+//! as JSON lines, or with `--restore` written back into `repo_seq` under their original seq,
+//! skipping any still there, so subscribeRepos can serve them again.
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use diesel::*;
+use rsky_pds::actor_store::aws::s3::load_sdk_config;
+use rsky_pds::config::env_to_cfg;
+use rsky_pds::db::establish_connection_for_sequencer;
+use rsky_pds::sequencer::archive::{ArchivedEvt, SeqArchive};
+use serde_json::json;
+
+fn parse_hour(arg: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(arg)?
+        .with_timezone(&Utc)
+        .duration_trunc(Duration::hours(1))?)
+}
+
+fn restore(evts: &[ArchivedEvt]) -> Result<usize> {
+    use rsky_pds::schema::pds::repo_seq::dsl as RepoSeqSchema;
+
+    let conn = &mut establish_connection_for_sequencer()?;
+    let mut restored = 0;
+    for evt in evts {
+        restored += insert_into(RepoSeqSchema::repo_seq)
+            .values((
+                RepoSeqSchema::seq.eq(evt.seq),
+                RepoSeqSchema::did.eq(&evt.did),
+                RepoSeqSchema::eventType.eq(&evt.event_type),
+                RepoSeqSchema::event.eq(&evt.event),
+                RepoSeqSchema::invalidated.eq(evt.invalidated),
+                RepoSeqSchema::sequencedAt.eq(&evt.sequenced_at),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+    }
+    Ok(restored)
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    dotenvy::dotenv().ok();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let do_restore = args.iter().any(|arg| arg == "--restore");
+    let times: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    let (from, to) = match times.as_slice() {
+        [from] => (parse_hour(from)?, parse_hour(from)?),
+        [from, to] => (parse_hour(from)?, parse_hour(to)?),
+        _ => bail!("usage: rsky-pds-replay <from> [<to>] [--restore]"),
+    };
+    let Some(cfg) = env_to_cfg().seq_archive else {
+        bail!("PDS_SEQ_ARCHIVE_BUCKET is not set");
+    };
+    let archive = SeqArchive::new(&cfg, &load_sdk_config().await);
+
+    let mut hour = from;
+    while hour <= to {
+        let evts = archive.read_hour(hour).await?;
+        if do_restore {
+            let restored = restore(&evts)?;
+            eprintln!(
+                "{}: restored {restored} of {} events",
+                archive.hour_key(hour),
+                evts.len()
+            );
+        } else {
+            for evt in evts {
+                println!(
+                    "{}",
+                    json!({
+                        "seq": evt.seq,
+                        "did": evt.did,
+                        "eventType": evt.event_type,
+                        "invalidated": evt.invalidated,
+                        "sequencedAt": evt.sequenced_at,
+                        "size": evt.event.len(),
+                    })
+                );
+            }
+        }
+        hour = hour + Duration::hours(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,381 @@
+//! The address a request really came from, for login audit logs and anything else keyed on
+//! the client. Behind a load balancer the TCP peer is the balancer, so:
+//!
+//! - `X-Forwarded-For` is read only when the peer is one of `PDS_TRUSTED_PROXIES`, walking it
+//!   from the right past every trusted hop, so a client can't claim an address by sending the
+//!   header itself.
+//! - With `PDS_PROXY_PROTOCOL_PORT` set, balancers speaking the PROXY protocol (v1 or v2)
+//!   connect there instead. Only peers in `PDS_TRUSTED_PROXIES` are accepted, since the header
+//!   names any address its sender likes. Each connection's header is stripped and the rest
+//!   relayed to rocket, remembering which client each relayed connection belongs to. TLS
+//!   passes through untouched.
+//!
+//! IPv4-mapped IPv6 addresses are compared and reported as plain IPv4.
+
+use anyhow::{bail, Result};
+use rocket::Request;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const PROXY_V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+/// Longest possible v1 header, CRLF included.
+const PROXY_V1_MAX_LEN: usize = 107;
+const PROXY_HEADER_MAX_LEN: usize = 536;
+
+/// A block of addresses, like `10.0.0.0/8` or `fd00::/8`. A bare address is a block of one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, canonical(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                prefix_eq(&net.octets(), &ip.octets(), self.prefix)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_eq(&net.octets(), &ip.octets(), self.prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s.trim(), None),
+        };
+        let addr = canonical(addr.parse::<IpAddr>()?);
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse::<u8>()?,
+            None => max,
+        };
+        if prefix > max {
+            bail!("prefix /{prefix} is too long for {addr}");
+        }
+        Ok(Cidr { addr, prefix })
+    }
+}
+
+fn prefix_eq(a: &[u8], b: &[u8], prefix: u8) -> bool {
+    let bytes = (prefix / 8) as usize;
+    let bits = prefix % 8;
+    if a[..bytes] != b[..bytes] {
+        return false;
+    }
+    bits == 0 || (a[bytes] ^ b[bytes]) >> (8 - bits) == 0
+}
+
+/// Unwraps IPv4-mapped IPv6 addresses, so `::ffff:10.0.0.1` matches `10.0.0.0/8`.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => ip,
+        },
+        ip => ip,
+    }
+}
+
+/// The client behind `peer`: the rightmost `X-Forwarded-For` entry that isn't a trusted proxy,
+/// or `peer` itself if it isn't trusted. If every hop is trusted, the leftmost one is the
+/// client.
+pub fn resolve_client_ip(peer: IpAddr, forwarded_for: Option<&str>, trusted: &[Cidr]) -> IpAddr {
+    let peer = canonical(peer);
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|cidr| cidr.contains(ip));
+    if !is_trusted(peer) {
+        return peer;
+    }
+    let Some(forwarded_for) = forwarded_for else {
+        return peer;
+    };
+    let mut client = peer;
+    for hop in forwarded_for.rsplit(',') {
+        let Some(ip) = parse_hop(hop.trim()) else {
+            // can't tell who sent anything further left
+            break;
+        };
+        client = canonical(ip);
+        if !is_trusted(client) {
+            break;
+        }
+    }
+    client
+}
+
+/// An `X-Forwarded-For` entry, which some proxies write with a port or in brackets.
+fn parse_hop(hop: &str) -> Option<IpAddr> {
+    if let Ok(ip) = hop.parse::<IpAddr>() {
+        return Some(ip);
+    }
+    if let Ok(addr) = hop.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+    hop.strip_prefix('[')
+        .and_then(|hop| hop.strip_suffix(']'))
+        .and_then(|hop| hop.parse().ok())
+}
+
+/// Real clients of connections relayed from the PROXY protocol listener, by the address the
+/// relay connected to rocket from.
+#[derive(Clone, Default)]
+pub struct ProxiedConnections(Arc<Mutex<HashMap<SocketAddr, SocketAddr>>>);
+
+impl ProxiedConnections {
+    fn get(&self, relay: SocketAddr) -> Option<SocketAddr> {
+        self.0.lock().unwrap().get(&relay).copied()
+    }
+}
+
+/// Managed by rocket; without it, the client is just the TCP peer.
+#[derive(Clone, Default)]
+pub struct ClientIpResolver {
+    pub trusted_proxies: Vec<Cidr>,
+    pub proxied: ProxiedConnections,
+}
+
+/// The client address of `req`, as worked out by the managed [`ClientIpResolver`].
+pub fn client_ip(req: &Request<'_>) -> Option<IpAddr> {
+    let remote = req.remote()?;
+    let Some(resolver) = req.rocket().state::<ClientIpResolver>() else {
+        return Some(canonical(remote.ip()));
+    };
+    // the relay's own address, for connections from the PROXY protocol listener
+    let peer = resolver.proxied.get(remote).unwrap_or(remote);
+    let forwarded_for = req.headers().get_one("X-Forwarded-For");
+    Some(resolve_client_ip(
+        peer.ip(),
+        forwarded_for,
+        &resolver.trusted_proxies,
+    ))
+}
+
+#[derive(Debug, PartialEq)]
+enum ProxyHeader {
+    /// Need more bytes.
+    Incomplete,
+    /// `len` bytes of header, and the client, unless the sender didn't say (`LOCAL`/`UNKNOWN`).
+    Parsed {
+        source: Option<SocketAddr>,
+        len: usize,
+    },
+}
+
+fn parse_proxy_header(buf: &[u8]) -> Result<ProxyHeader> {
+    let signature_len = buf.len().min(PROXY_V2_SIGNATURE.len());
+    if buf[..signature_len] == PROXY_V2_SIGNATURE[..signature_len] {
+        return parse_proxy_v2(buf);
+    }
+    let prefix_len = buf.len().min(6);
+    if buf[..prefix_len] != b"PROXY "[..prefix_len] {
+        bail!("not a PROXY protocol header");
+    }
+    let Some(end) = buf.windows(2).position(|window| window == b"\r\n") else {
+        if buf.len() >= PROXY_V1_MAX_LEN {
+            bail!("PROXY v1 header too long");
+        }
+        return Ok(ProxyHeader::Incomplete);
+    };
+    let line = std::str::from_utf8(&buf[..end])?;
+    let parts: Vec<&str> = line.split(' ').collect();
+    let source = match parts.as_slice() {
+        ["PROXY", "TCP4" | "TCP6", src, _dst, sport, _dport] => {
+            Some(SocketAddr::new(src.parse()?, sport.parse()?))
+        }
+        ["PROXY", "UNKNOWN", ..] => None,
+        _ => bail!("malformed PROXY v1 header: {line}"),
+    };
+    Ok(ProxyHeader::Parsed {
+        source,
+        len: end + 2,
+    })
+}
+
+fn parse_proxy_v2(buf: &[u8]) -> Result<ProxyHeader> {
+    if buf.len() < 16 {
+        return Ok(ProxyHeader::Incomplete);
+    }
+    let version_command = buf[12];
+    if version_command >> 4 != 2 {
+        bail!("unsupported PROXY protocol version");
+    }
+    let len = 16 + u16::from_be_bytes([buf[14], buf[15]]) as usize;
+    if len > PROXY_HEADER_MAX_LEN {
+        bail!("PROXY v2 header too long");
+    }
+    if buf.len() < len {
+        return Ok(ProxyHeader::Incomplete);
+    }
+    let addresses = &buf[16..len];
+    let source = match (version_command & 0x0f, buf[13] >> 4) {
+        // LOCAL: health checks from the balancer itself
+        (0, _) => None,
+        (1, 1) if addresses.len() >= 12 => {
+            let ip = Ipv4Addr::new(addresses[0], addresses[1], addresses[2], addresses[3]);
+            let port = u16::from_be_bytes([addresses[8], addresses[9]]);
+            Some(SocketAddr::new(IpAddr::V4(ip), port))
+        }
+        (1, 2) if addresses.len() >= 36 => {
+            let octets: [u8; 16] = addresses[..16].try_into()?;
+            let port = u16::from_be_bytes([addresses[32], addresses[33]]);
+            Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(octets)), port))
+        }
+        (1, _) => None,
+        _ => bail!("unsupported PROXY v2 command"),
+    };
+    Ok(ProxyHeader::Parsed { source, len })
+}
+
+/// Accepts PROXY protocol connections from `trusted` peers on `port` and relays each to rocket
+/// at `upstream`.
+pub async fn run_proxy_protocol_listener(
+    port: u16,
+    upstream: SocketAddr,
+    trusted: Vec<Cidr>,
+    proxied: ProxiedConnections,
+) {
+    let listener = match TcpListener::bind(("::", port)).await {
+        Ok(listener) => listener,
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: failed to listen for PROXY protocol on {port}: {error}");
+            return;
+        }
+    };
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(error) => {
+                tracing::warn!("@LOG: failed to accept PROXY protocol connection: {error}");
+                continue;
+            }
+        };
+        if !trusted.iter().any(|cidr| cidr.contains(peer.ip())) {
+            tracing::warn!("@LOG: refused PROXY protocol connection from untrusted {peer}");
+            continue;
+        }
+        let proxied = proxied.clone();
+        tokio::spawn(async move {
+            if let Err(error) = relay(stream, upstream, proxied).await {
+                tracing::warn!("@LOG: dropped PROXY protocol connection from {peer}: {error}");
+            }
+        });
+    }
+}
+
+async fn relay(
+    mut client: TcpStream,
+    upstream: SocketAddr,
+    proxied: ProxiedConnections,
+) -> Result<()> {
+    let mut buf = Vec::with_capacity(PROXY_HEADER_MAX_LEN);
+    let (source, len) = loop {
+        let mut chunk = [0u8; PROXY_HEADER_MAX_LEN];
+        let read = client
+            .read(&mut chunk[..PROXY_HEADER_MAX_LEN - buf.len()])
+            .await?;
+        if read == 0 {
+            bail!("connection closed before the PROXY header");
+        }
+        buf.extend_from_slice(&chunk[..read]);
+        if let ProxyHeader::Parsed { source, len } = parse_proxy_header(&buf)? {
+            break (source, len);
+        }
+        if buf.len() >= PROXY_HEADER_MAX_LEN {
+            bail!("PROXY header too long");
+        }
+    };
+    let mut server = TcpStream::connect(upstream).await?;
+    let relay_addr = server.local_addr()?;
+    if let Some(source) = source {
+        proxied.0.lock().unwrap().insert(relay_addr, source);
+    }
+    let result = async {
+        server.write_all(&buf[len..]).await?;
+        tokio::io::copy_bidirectional(&mut client, &mut server).await?;
+        Ok(())
+    }
+    .await;
+    proxied.0.lock().unwrap().remove(&relay_addr);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_client_ip() {
+        let trusted: Vec<Cidr> = ["10.0.0.0/8", "fd00::/8"]
+            .iter()
+            .map(|cidr| cidr.parse().unwrap())
+            .collect();
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+
+        // untrusted peers can't pick their address
+        assert_eq!(
+            resolve_client_ip(ip("203.0.113.9"), Some("198.51.100.1"), &trusted),
+            ip("203.0.113.9")
+        );
+        // skips trusted hops from the right, spoofed entries to the left are ignored
+        assert_eq!(
+            resolve_client_ip(
+                ip("10.0.0.2"),
+                Some("1.2.3.4, 2001:db8::7, 10.0.0.5"),
+                &trusted
+            ),
+            ip("2001:db8::7")
+        );
+        assert_eq!(
+            resolve_client_ip(ip("::ffff:10.0.0.2"), Some("[2001:db8::8]:4431"), &trusted),
+            ip("2001:db8::8")
+        );
+        assert_eq!(
+            resolve_client_ip(ip("fd00::1"), Some("198.51.100.2:8080"), &trusted),
+            ip("198.51.100.2")
+        );
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn test_parse_proxy_header() {
+        let v1 = b"PROXY TCP6 2001:db8::1 2001:db8::2 51000 443\r\nGET /";
+        assert_eq!(
+            parse_proxy_header(v1).unwrap(),
+            ProxyHeader::Parsed {
+                source: Some("[2001:db8::1]:51000".parse().unwrap()),
+                len: v1.len() - 5,
+            }
+        );
+        assert_eq!(
+            parse_proxy_header(b"PROXY TCP4 1.2").unwrap(),
+            ProxyHeader::Incomplete
+        );
+        assert!(parse_proxy_header(b"GET / HTTP/1.1\r\n").is_err());
+
+        let mut v2 = PROXY_V2_SIGNATURE.to_vec();
+        v2.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        v2.extend_from_slice(&[192, 0, 2, 7, 10, 0, 0, 1, 0x1f, 0x90, 0x01, 0xbb]);
+        assert_eq!(
+            parse_proxy_header(&v2).unwrap(),
+            ProxyHeader::Parsed {
+                source: Some("192.0.2.7:8080".parse().unwrap()),
+                len: 28,
+            }
+        );
+        assert_eq!(
+            parse_proxy_header(&v2[..20]).unwrap(),
+            ProxyHeader::Incomplete
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Repo signing and PLC rotation keys. Each key is read from the first of these that is set,
+//! so every environment can keep its keys wherever suits it:
+//!
+//! - `<PREFIX>_PRIVATE_KEY_HEX`: hex encoded secp256k1 secret key
+//! - `<PREFIX>_PRIVATE_KEY_FILE`: path to a file holding the hex encoded key
+//! - `<PREFIX>_KMS_CIPHERTEXT`: base64 AWS KMS ciphertext of the raw 32 key bytes
+//!
+//! `PDS_PLC_ROTATION_KEY_K256_PREVIOUS` takes the same suffixes and holds the rotation key
+//! being retired, which is still needed to sign the PLC operations that replace it.
+
+use anyhow::{bail, Context, Result};
+use aws_config::SdkConfig;
+use aws_sdk_kms::primitives::Blob;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use lazy_static::lazy_static;
+use rsky_common::env::env_str;
+use rsky_crypto::utils::encode_did_key;
+use secp256k1::{Keypair, Secp256k1, SecretKey};
+use std::sync::RwLock;
+
+pub const REPO_SIGNING_KEY: &str = "PDS_REPO_SIGNING_KEY_K256";
+pub const PLC_ROTATION_KEY: &str = "PDS_PLC_ROTATION_KEY_K256";
+pub const PREVIOUS_PLC_ROTATION_KEY: &str = "PDS_PLC_ROTATION_KEY_K256_PREVIOUS";
+
+lazy_static! {
+    static ref SERVICE_KEYS: RwLock<Option<ServiceKeys>> = RwLock::new(None);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeySource {
+    Hex(String),
+    File(String),
+    Kms(String),
+}
+
+impl KeySource {
+    pub fn from_env(prefix: &str) -> Option<Self> {
+        if let Some(hex) = env_str(&format!("{prefix}_PRIVATE_KEY_HEX")) {
+            Some(Self::Hex(hex))
+        } else if let Some(path) = env_str(&format!("{prefix}_PRIVATE_KEY_FILE")) {
+            Some(Self::File(path))
+        } else {
+            env_str(&format!("{prefix}_KMS_CIPHERTEXT")).map(Self::Kms)
+        }
+    }
+
+    pub async fn load(&self, sdk_config: Option<&SdkConfig>) -> Result<SecretKey> {
+        let bytes = match self {
+            Self::Hex(hex) => hex::decode(hex.trim()).context("key is not valid hex")?,
+            Self::File(path) => {
+                let contents = tokio::fs::read_to_string(path)
+                    .await
+                    .with_context(|| format!("could not read key file {path}"))?;
+                hex::decode(contents.trim())
+                    .with_context(|| format!("key file {path} is not valid hex"))?
+            }
+            Self::Kms(ciphertext) => {
+                let Some(sdk_config) = sdk_config else {
+                    bail!("KMS keys can only be loaded at startup")
+                };
+                let ciphertext = STANDARD
+                    .decode(ciphertext.trim())
+                    .context("KMS ciphertext is not valid base64")?;
+                let output = aws_sdk_kms::Client::new(sdk_config)
+                    .decrypt()
+                    .ciphertext_blob(Blob::new(ciphertext))
+                    .send()
+                    .await
+                    .context("KMS decrypt failed")?;
+                match output.plaintext() {
+                    Some(plaintext) => plaintext.as_ref().to_vec(),
+                    None => bail!("KMS decrypt returned no plaintext"),
+                }
+            }
+        };
+        SecretKey::from_slice(&bytes).context("key is not a valid secp256k1 secret key")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ServiceKeys {
+    pub repo_signing: SecretKey,
+    pub plc_rotation: SecretKey,
+    pub previous_plc_rotation: Option<SecretKey>,
+}
+
+impl ServiceKeys {
+    /// Loads every configured key, failing on anything missing or malformed.
+    pub async fn load(sdk_config: Option<&SdkConfig>) -> Result<Self> {
+        let load = |prefix: &'static str| async move {
+            match KeySource::from_env(prefix) {
+                Some(source) => source
+                    .load(sdk_config)
+                    .await
+                    .with_context(|| format!("invalid {prefix}"))
+                    .map(Some),
+                None => Ok(None),
+            }
+        };
+        let Some(repo_signing) = load(REPO_SIGNING_KEY).await? else {
+            bail!("{REPO_SIGNING_KEY} is not configured")
+        };
+        let Some(plc_rotation) = load(PLC_ROTATION_KEY).await? else {
+            bail!("{PLC_ROTATION_KEY} is not configured")
+        };
+        let keys = Self {
+            repo_signing,
+            plc_rotation,
+            previous_plc_rotation: load(PREVIOUS_PLC_ROTATION_KEY).await?,
+        };
+        keys.validate()?;
+        Ok(keys)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.previous_plc_rotation == Some(self.plc_rotation) {
+            bail!("{PREVIOUS_PLC_ROTATION_KEY} must differ from {PLC_ROTATION_KEY}")
+        }
+        if self.repo_signing == self.plc_rotation {
+            tracing::warn!(
+                "@LOG: {REPO_SIGNING_KEY} and {PLC_ROTATION_KEY} are the same key; \
+                 keep them separate so a leaked signing key can't take over identities"
+            );
+        }
+        Ok(())
+    }
+
+    pub fn repo_signing_did_key(&self) -> String {
+        encode_did_key(&self.repo_signing.public_key(&Secp256k1::new()))
+    }
+
+    pub fn plc_rotation_did_key(&self) -> String {
+        encode_did_key(&self.plc_rotation.public_key(&Secp256k1::new()))
+    }
+
+    pub fn previous_plc_rotation_did_key(&self) -> Option<String> {
+        self.previous_plc_rotation
+            .map(|key| encode_did_key(&key.public_key(&Secp256k1::new())))
+    }
+}
+
+/// Installs the keys loaded at startup for the rest of the process.
+pub fn set_service_keys(keys: ServiceKeys) {
+    *SERVICE_KEYS.write().unwrap() = Some(keys);
+}
+
+/// The keys loaded at startup, or the hex env keys when running outside the server.
+pub fn service_keys() -> Result<ServiceKeys> {
+    if let Some(keys) = SERVICE_KEYS.read().unwrap().as_ref() {
+        return Ok(keys.clone());
+    }
+    let load = |prefix: &str| match KeySource::from_env(prefix) {
+        Some(KeySource::Hex(hex)) => Ok(SecretKey::from_slice(&hex::decode(hex.trim())?)?),
+        _ => bail!("{prefix}_PRIVATE_KEY_HEX is not configured"),
+    };
+    Ok(ServiceKeys {
+        repo_signing: load(REPO_SIGNING_KEY)?,
+        plc_rotation: load(PLC_ROTATION_KEY)?,
+        previous_plc_rotation: load(PREVIOUS_PLC_ROTATION_KEY).ok(),
+    })
+}
+
+pub fn repo_signing_key() -> Result<SecretKey> {
+    Ok(service_keys()?.repo_signing)
+}
+
+pub fn repo_signing_keypair() -> Result<Keypair> {
+    Ok(Keypair::from_secret_key(
+        &Secp256k1::new(),
+        &repo_signing_key()?,
+    ))
+}
+
+pub fn plc_rotation_key() -> Result<SecretKey> {
+    Ok(service_keys()?.plc_rotation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_HEX: &str = "9f6a8b3c0e8d3a1b2c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f7081920a";
+
+    #[tokio::test]
+    async fn test_load_key_sources() {
+        let from_hex = KeySource::Hex(KEY_HEX.to_string())
+            .load(None)
+            .await
+            .unwrap();
+
+        let path = std::env::temp_dir().join("rsky-pds-test-signing-key");
+        std::fs::write(&path, format!("{KEY_HEX}\n")).unwrap();
+        let from_file = KeySource::File(path.to_string_lossy().to_string())
+            .load(None)
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(from_hex, from_file);
+
+        assert!(KeySource::Hex("not hex".to_string())
+            .load(None)
+            .await
+            .is_err());
+        assert!(KeySource::Kms("AQID".to_string()).load(None).await.is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_reused_rotation_key() {
+        let key = SecretKey::from_slice(&hex::decode(KEY_HEX).unwrap()).unwrap();
+        let keys = ServiceKeys {
+            repo_signing: key,
+            plc_rotation: key,
+            previous_plc_rotation: Some(key),
+        };
+        assert!(keys.validate().is_err());
+        assert!(ServiceKeys {
+            previous_plc_rotation: None,
+            ..keys
+        }
+        .validate()
+        .is_ok());
+    }
+}
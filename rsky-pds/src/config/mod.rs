@@ -1,8 +1,15 @@
+pub mod keys;
+
+use crate::actor_store::aws::cloudfront;
+use crate::client_ip::Cidr;
 use crate::context;
 use anyhow::{bail, Result};
 use reqwest::header::HeaderMap;
+use rsa::RsaPrivateKey;
 use rsky_common::env::{env_bool, env_int, env_list, env_str};
-use rsky_common::time::{DAY, HOUR, SECOND};
+use rsky_common::time::{DAY, HOUR, MINUTE, SECOND};
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ServerConfig {
@@ -13,7 +20,30 @@ pub struct ServerConfig {
     pub subscription: SubscriptionConfig,
     pub invites: InvitesConfig,
     pub identity: IdentityConfig,
+    pub handle_policy: HandlePolicyConfig,
     pub crawlers: Vec<String>,
+    pub blob_redirect: Option<BlobRedirectConfig>,
+    pub blob_moderation: BlobModerationConfig,
+    pub shutdown: ShutdownConfig,
+    pub account_export: AccountExportConfig,
+    pub moderation: ModerationConfig,
+    pub maintenance: MaintenanceConfig,
+    pub admin: AdminConfig,
+    pub route_flags: RouteFlagsConfig,
+    pub webhooks: WebhookConfig,
+    /// None unless `PDS_SPAM_SCORING_URL` is set.
+    pub spam_scoring: Option<SpamScoringConfig>,
+    pub client_ip: ClientIpConfig,
+    pub phone_verification: PhoneVerificationConfig,
+    pub database: DatabaseConfig,
+    pub actor_store: ActorStoreConfig,
+    pub seq_archive: Option<SeqArchiveConfig>,
+    /// None unless `PDS_SEQUENCER_COALESCE_MS` is set above 0.
+    pub seq_coalesce: Option<SeqCoalesceConfig>,
+    /// Caps on how fast one account may create records in a collection.
+    pub write_rate_limits: Vec<CollectionRateLimit>,
+    /// None unless `PDS_RATE_LIMITS_ENABLED=true`.
+    pub rate_limits: Option<RateLimitConfig>,
 }
 
 /// BksyAppViewConfig, ModServiceConfig, ReportServiceConfig, etc.
@@ -28,6 +58,361 @@ pub struct ServiceConfig {
 pub struct SubscriptionConfig {
     pub max_buffer: u64,
     pub repo_backfill_limit_ms: u64,
+    /// Events read from the database at a time while replaying history to a subscriber.
+    pub backfill_page_size: i64,
+    /// Most historical events sent to one subscriber a second, so a relay catching up on deep
+    /// history doesn't starve live traffic of the database. Live events aren't held back.
+    pub backfill_max_events_per_sec: Option<u32>,
+    /// permessage-deflate for subscribeRepos, offered when `PDS_FIREHOSE_COMPRESSION=deflate`.
+    pub compression: Option<FirehoseCompressionConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FirehoseCompressionConfig {
+    /// zlib compression level, 0-9.
+    pub level: u32,
+    /// Keep each subscriber's 32KiB window between frames. Compresses better, but costs
+    /// roughly 300KiB of compressor state per connection for as long as it stays open.
+    pub context_takeover: bool,
+    /// Frames smaller than this many bytes are sent uncompressed.
+    pub min_frame_size: usize,
+    /// Most subscribers compressed at once; the rest are streamed uncompressed.
+    pub max_connections: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShutdownConfig {
+    /// Seconds to wait for in-flight writes once shutdown is triggered.
+    pub grace: u64,
+    /// Milliseconds given to the sequencer to emit drained commits before subscribers close.
+    pub flush_window_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabaseConfig {
+    /// Apply pending migrations on boot.
+    pub auto_migrate: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeqArchiveConfig {
+    /// Bucket finished hours of sequenced events are uploaded to.
+    pub bucket: String,
+    /// Key prefix the hourly files and checkpoint go under.
+    pub prefix: String,
+    /// Seconds between checks for a finished hour to upload.
+    pub interval: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeqCoalesceConfig {
+    /// Milliseconds the writer waits after an event for others to insert alongside it.
+    pub window: u64,
+    /// Most events inserted by one statement.
+    pub max_batch: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaintenanceConfig {
+    /// Start in read-only maintenance mode.
+    pub read_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlobModerationConfig {
+    /// Status (410 or 451) getBlob answers with instead of the bytes of moderated media. Unset
+    /// leaves taken down blobs 404ing and labeled ones served as usual.
+    pub status: Option<u16>,
+    /// Self-label values that withhold the blobs of records carrying them, as takedowns do.
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdminConfig {
+    /// PEM file of the CAs admin clients' certificates must chain to. When set, admin-token
+    /// routes also require a client certificate, so rocket's own TLS has to be configured.
+    pub mtls_ca: Option<String>,
+    /// Seconds a second moderator has to confirm an account deletion or takedown before it is
+    /// carried out. Unset, one moderator's confirmation is enough. Needs admin mTLS or a mod
+    /// service to tell moderators apart.
+    pub dual_approval_window: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteFlagsConfig {
+    /// NSIDs of xrpc methods disabled unless an admin turns them back on.
+    pub disabled: Vec<String>,
+    /// Seconds between reloads of flags other replicas may have changed.
+    pub refresh_interval: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandlePolicyConfig {
+    /// Names held back on the service domains on top of the built-in list.
+    pub reserved: Vec<String>,
+    /// Terms no handle may contain, on any domain.
+    pub banned: Vec<String>,
+    /// TLDs refused on top of the ones the handle syntax disallows, e.g. `zip`.
+    pub disallowed_tlds: Vec<String>,
+    /// Bounds on the name before a service domain, e.g. `alice` in `alice.example.com`.
+    pub min_length: usize,
+    pub max_length: usize,
+    /// Seconds between reloads of names other replicas may have reserved.
+    pub refresh_interval: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitConfig {
+    pub rules: Vec<RateLimitRule>,
+    /// Shares counts between replicas; without it each replica counts on its own.
+    pub redis_url: Option<String>,
+}
+
+/// What a [`RateLimitRule`] counts requests by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimitKey {
+    Ip,
+    /// The authenticated account, or the IP for requests without a session.
+    Did,
+}
+
+/// At most `points` requests to `nsid` (every xrpc method when None) per IP or account every
+/// `window` seconds. Written as `<ip|did>:<nsid|*>=<points>/<window>`, e.g.
+/// `ip:com.atproto.server.createSession=30/300`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitRule {
+    pub key: RateLimitKey,
+    pub nsid: Option<String>,
+    pub points: u32,
+    pub window: u64,
+}
+
+impl RateLimitRule {
+    pub fn applies_to(&self, nsid: &str) -> bool {
+        self.nsid
+            .as_deref()
+            .is_none_or(|rule_nsid| rule_nsid == nsid)
+    }
+}
+
+impl FromStr for RateLimitRule {
+    type Err = anyhow::Error;
+
+    fn from_str(rule: &str) -> Result<Self> {
+        let Some((scope, rate)) = rule.trim().split_once('=') else {
+            bail!("rate limit `{rule}` must start with <ip|did>:<nsid|*>=");
+        };
+        let key = match scope.split_once(':') {
+            Some(("ip", nsid)) => (RateLimitKey::Ip, nsid),
+            Some(("did", nsid)) => (RateLimitKey::Did, nsid),
+            _ => bail!("rate limit `{rule}` must count by ip or did"),
+        };
+        let Some((points, window)) = rate.split_once('/') else {
+            bail!("rate limit `{rule}` must end with <points>/<window>");
+        };
+        let window: u64 = window.parse()?;
+        if window == 0 {
+            bail!("rate limit `{rule}` needs a window of 1s or more");
+        }
+        Ok(RateLimitRule {
+            key: key.0,
+            nsid: match key.1 {
+                "*" => None,
+                nsid => Some(nsid.to_string()),
+            },
+            points: points.parse()?,
+            window,
+        })
+    }
+}
+
+impl fmt::Display for RateLimitRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key = match self.key {
+            RateLimitKey::Ip => "ip",
+            RateLimitKey::Did => "did",
+        };
+        let nsid = self.nsid.as_deref().unwrap_or("*");
+        write!(f, "{key}:{nsid}={}/{}", self.points, self.window)
+    }
+}
+
+/// Used when rate limiting is on but `PDS_RATE_LIMITS` is unset; the reference PDS's budgets.
+pub const DEFAULT_RATE_LIMITS: [&str; 5] = [
+    "ip:*=3000/300",
+    "ip:com.atproto.server.createSession=30/300",
+    "ip:com.atproto.server.createSession=300/86400",
+    "ip:com.atproto.server.createAccount=100/300",
+    "did:com.atproto.repo.uploadBlob=1000/86400",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookConfig {
+    /// Endpoints every account lifecycle event is POSTed to.
+    pub urls: Vec<String>,
+    /// Key requests are signed with. Unset sends them unsigned.
+    pub secret: Option<String>,
+    /// Redeliveries attempted, with exponential backoff, before an event is dropped.
+    pub max_retries: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpamScoringConfig {
+    /// Scoring service new accounts and records are POSTed to.
+    pub url: String,
+    /// Milliseconds to wait for a score before falling back on `fail_open`.
+    pub timeout: u64,
+    /// Let writes through when no score comes back in time, rather than rejecting them.
+    pub fail_open: bool,
+    /// Scores at or above this are rejected.
+    pub block_threshold: f64,
+    /// Scores at or above this, but under `block_threshold`, go through and get the account
+    /// flagged in the moderation audit log.
+    pub flag_threshold: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientIpConfig {
+    /// Load balancers whose `X-Forwarded-For` is believed, as CIDRs or bare addresses.
+    pub trusted_proxies: Vec<Cidr>,
+    /// Port taking PROXY protocol connections from the trusted proxies, relayed to rocket with
+    /// the client's address kept.
+    pub proxy_protocol_port: Option<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhoneVerificationConfig {
+    /// Answer `com.atproto.temp.requestPhoneVerification` with success without sending
+    /// anything, for clients that call it during signup. Otherwise it's rejected, since this
+    /// PDS doesn't verify phone numbers.
+    pub accept: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountExportConfig {
+    /// Seconds a takeout download link stays valid.
+    pub url_expires_in: u64,
+    /// Seconds an account that took a migration export is kept before it may be deleted.
+    pub migration_retention: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModerationConfig {
+    /// Rules checked, in order, each time an account is reported.
+    pub auto_actions: Vec<AutoActionRule>,
+    /// Seconds between sweeps that lift expired automatic takedowns.
+    pub sweep_interval: u64,
+}
+
+/// Repo storage settings, shared by every actor store through
+/// [`crate::actor_store::set_actor_store_config`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActorStoreConfig {
+    /// Seconds that records deleted by their owner stay in `deleted_record` for moderators to
+    /// review, from `PDS_DELETED_RECORD_RETENTION_SECS`. None deletes them outright.
+    pub deleted_record_retention: Option<u64>,
+    /// Commits kept per repo in the commit history, from `PDS_REPO_COMMIT_HISTORY_LIMIT`.
+    pub commit_history_limit: u64,
+    /// Re-hash blocks loaded from the database against their CIDs, from
+    /// `PDS_VERIFY_BLOCKS_ON_READ`.
+    pub verify_blocks_on_read: bool,
+}
+
+impl Default for ActorStoreConfig {
+    fn default() -> Self {
+        ActorStoreConfig {
+            deleted_record_retention: None,
+            commit_history_limit: 100,
+            verify_blocks_on_read: false,
+        }
+    }
+}
+
+/// `reports` or more distinct reporters flagging an account within `window` seconds triggers
+/// `action`. Written as `<reports>/<window>:flag` or `<reports>/<window>:takedown=<seconds>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoActionRule {
+    pub reports: i64,
+    pub window: u64,
+    pub action: AutoAction,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AutoAction {
+    /// Record the account in the moderation audit log for a moderator to review.
+    Flag,
+    /// Take the account down for `duration` seconds.
+    Takedown { duration: u64 },
+}
+
+impl FromStr for AutoActionRule {
+    type Err = anyhow::Error;
+
+    fn from_str(rule: &str) -> Result<Self> {
+        let Some((threshold, action)) = rule.trim().split_once(':') else {
+            bail!("moderation rule `{rule}` is missing an action");
+        };
+        let Some((reports, window)) = threshold.split_once('/') else {
+            bail!("moderation rule `{rule}` must start with <reports>/<window>");
+        };
+        let action = match action.split_once('=') {
+            None if action == "flag" => AutoAction::Flag,
+            Some(("takedown", duration)) => AutoAction::Takedown {
+                duration: duration.parse()?,
+            },
+            _ => bail!("unknown action in moderation rule `{rule}`"),
+        };
+        let reports: i64 = reports.parse()?;
+        if reports < 1 {
+            bail!("moderation rule `{rule}` must need at least one report");
+        }
+        Ok(AutoActionRule {
+            reports,
+            window: window.parse()?,
+            action,
+        })
+    }
+}
+
+impl fmt::Display for AutoActionRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}:", self.reports, self.window)?;
+        match self.action {
+            AutoAction::Flag => write!(f, "flag"),
+            AutoAction::Takedown { duration } => write!(f, "takedown={duration}"),
+        }
+    }
+}
+
+/// At most `writes` new records in `collection` per account every `window` seconds. Written as
+/// `<collection>=<writes>/<window>`, e.g. `app.bsky.feed.post=100/3600`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionRateLimit {
+    pub collection: String,
+    pub writes: u32,
+    pub window: u64,
+}
+
+impl FromStr for CollectionRateLimit {
+    type Err = anyhow::Error;
+
+    fn from_str(limit: &str) -> Result<Self> {
+        let Some((collection, rate)) = limit.trim().split_once('=') else {
+            bail!("write rate limit `{limit}` must start with <collection>=");
+        };
+        let Some((writes, window)) = rate.split_once('/') else {
+            bail!("write rate limit `{limit}` must end with <writes>/<window>");
+        };
+        let window: u64 = window.parse()?;
+        if collection.split('.').count() < 3 || window == 0 {
+            bail!("write rate limit `{limit}` needs a collection NSID and a window of 1s or more");
+        }
+        Ok(CollectionRateLimit {
+            collection: collection.to_string(),
+            writes: writes.parse()?,
+            window,
+        })
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -36,6 +421,11 @@ pub struct IdentityConfig {
     pub resolver_timeout: u64,
     pub cache_state_ttl: u64,
     pub cache_max_ttl: u64,
+    /// How long a DID that doesn't exist is remembered as missing, in ms.
+    pub cache_negative_ttl: u64,
+    pub handle_cache_ttl: u64,
+    /// How long a handle that doesn't resolve is remembered as such, in ms.
+    pub handle_cache_negative_ttl: u64,
     pub recovery_did_key: Option<String>,
     pub service_handle_domains: Vec<String>,
     pub handle_backup_name_servers: Option<Vec<String>>,
@@ -49,6 +439,20 @@ pub struct InvitesConfig {
     pub epoch: Option<usize>,
 }
 
+/// How `com.atproto.sync.getBlob` hands out blobs when not proxying bytes itself.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlobRedirectConfig {
+    /// Presigned `GetObject` URL from the blobstore (S3, R2, MinIO).
+    Presigned { expires_in: u64 },
+    /// CloudFront URL signed with a canned policy.
+    CloudFront {
+        url: String,
+        key_pair_id: String,
+        private_key: RsaPrivateKey,
+        expires_in: u64,
+    },
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct CoreConfig {
     pub port: usize,
@@ -62,6 +466,10 @@ pub struct CoreConfig {
     pub blob_upload_limit: usize,
     pub contact_email_address: Option<String>,
     pub dev_mode: bool,
+    /// Write an `app.bsky.actor.profile` record into the genesis commit of new accounts.
+    pub bootstrap_profile: bool,
+    /// Keep app passwords, and the sessions minted from them, when the account password is reset.
+    pub app_passwords_survive_password_reset: bool,
 }
 
 pub fn env_to_cfg() -> ServerConfig {
@@ -85,6 +493,9 @@ pub fn env_to_cfg() -> ServerConfig {
         blob_upload_limit: env_int("PDS_BLOB_UPLOAD_LIMIT").unwrap_or_else(|| 5 * 1024 * 1024), // 5mb
         contact_email_address: env_str("PDS_CONTACT_EMAIL_ADDRESS"),
         dev_mode: env_bool("PDS_DEV_MODE").unwrap_or(false),
+        bootstrap_profile: env_bool("PDS_BOOTSTRAP_PROFILE").unwrap_or(false),
+        app_passwords_survive_password_reset: env_bool("PDS_APP_PASSWORDS_SURVIVE_PASSWORD_RESET")
+            .unwrap_or(false),
     };
     let service_handle_domains: Vec<String>;
     if env_list("PDS_SERVICE_HANDLE_DOMAINS").len() > 0 {
@@ -100,6 +511,12 @@ pub fn env_to_cfg() -> ServerConfig {
             as u64,
         cache_state_ttl: env_int("PDS_DID_CACHE_STALE_TTL").unwrap_or_else(|| HOUR as usize) as u64,
         cache_max_ttl: env_int("PDS_DID_CACHE_MAX_TTL").unwrap_or_else(|| DAY as usize) as u64,
+        cache_negative_ttl: env_int("PDS_DID_CACHE_NEGATIVE_TTL")
+            .unwrap_or_else(|| 5 * MINUTE as usize) as u64,
+        handle_cache_ttl: env_int("PDS_HANDLE_CACHE_TTL").unwrap_or_else(|| 10 * MINUTE as usize)
+            as u64,
+        handle_cache_negative_ttl: env_int("PDS_HANDLE_CACHE_NEGATIVE_TTL")
+            .unwrap_or_else(|| MINUTE as usize) as u64,
         recovery_did_key: env_str("PDS_RECOVERY_DID_KEY"),
         service_handle_domains,
         handle_backup_name_servers: Some(env_list("PDS_HANDLE_BACKUP_NAMESERVERS")),
@@ -142,7 +559,168 @@ pub fn env_to_cfg() -> ServerConfig {
         max_buffer: env_int("PDS_MAX_SUBSCRIPTION_BUFFER").unwrap_or(500) as u64,
         repo_backfill_limit_ms: env_int("PDS_REPO_BACKFILL_LIMIT_MS").unwrap_or(DAY as usize)
             as u64,
+        backfill_page_size: env_int("PDS_FIREHOSE_BACKFILL_PAGE_SIZE")
+            .unwrap_or(500)
+            .max(1) as i64,
+        backfill_max_events_per_sec: env_int("PDS_FIREHOSE_BACKFILL_MAX_EVENTS_PER_SEC")
+            .filter(|rate| *rate > 0)
+            .map(|rate| rate as u32),
+        compression: match env_str("PDS_FIREHOSE_COMPRESSION").as_deref() {
+            None | Some("") | Some("none") => None,
+            Some("deflate") => Some(FirehoseCompressionConfig {
+                level: env_int("PDS_FIREHOSE_COMPRESSION_LEVEL")
+                    .unwrap_or(6)
+                    .min(9) as u32,
+                context_takeover: env_bool("PDS_FIREHOSE_COMPRESSION_CONTEXT_TAKEOVER")
+                    .unwrap_or(true),
+                min_frame_size: env_int("PDS_FIREHOSE_COMPRESSION_MIN_BYTES").unwrap_or(256),
+                max_connections: env_int("PDS_FIREHOSE_COMPRESSION_MAX_CONNECTIONS"),
+            }),
+            Some(other) => panic!("unknown PDS_FIREHOSE_COMPRESSION mode: {other}"),
+        },
+    };
+    let shutdown_cfg = ShutdownConfig {
+        grace: env_int("PDS_SHUTDOWN_GRACE_SECS").unwrap_or(15) as u64,
+        flush_window_ms: env_int("PDS_SHUTDOWN_FLUSH_MS").unwrap_or(2 * SECOND as usize) as u64,
+    };
+    let database_cfg = DatabaseConfig {
+        auto_migrate: env_bool("PDS_DB_AUTO_MIGRATE").unwrap_or(true),
+    };
+    let seq_archive_cfg = env_str("PDS_SEQ_ARCHIVE_BUCKET")
+        .filter(|bucket| !bucket.is_empty())
+        .map(|bucket| SeqArchiveConfig {
+            bucket,
+            prefix: env_str("PDS_SEQ_ARCHIVE_PREFIX").unwrap_or("firehose".to_string()),
+            interval: env_int("PDS_SEQ_ARCHIVE_INTERVAL_SECS").unwrap_or(300) as u64,
+        });
+    let seq_coalesce_cfg = env_int("PDS_SEQUENCER_COALESCE_MS")
+        .filter(|window| *window > 0)
+        .map(|window| SeqCoalesceConfig {
+            window: window as u64,
+            max_batch: env_int("PDS_SEQUENCER_COALESCE_MAX_BATCH")
+                .unwrap_or(200)
+                .max(1),
+        });
+    let maintenance_cfg = MaintenanceConfig {
+        read_only: env_bool("PDS_READ_ONLY").unwrap_or(false),
+    };
+    let admin_cfg = AdminConfig {
+        mtls_ca: env_str("PDS_ADMIN_MTLS_CA").filter(|path| !path.is_empty()),
+        dual_approval_window: env_int("PDS_ADMIN_DUAL_APPROVAL_SECS")
+            .filter(|secs| *secs > 0)
+            .map(|secs| secs as u64),
+    };
+    if admin_cfg.dual_approval_window.is_some()
+        && admin_cfg.mtls_ca.is_none()
+        && mod_service_cfg.is_none()
+    {
+        // otherwise the second approver is whoever the caller claims to be
+        panic!("PDS_ADMIN_DUAL_APPROVAL_SECS needs PDS_ADMIN_MTLS_CA or PDS_MOD_SERVICE_URL");
+    }
+    let rate_limits_cfg = match env_bool("PDS_RATE_LIMITS_ENABLED").unwrap_or(false) {
+        false => None,
+        true => {
+            let mut rules = env_list("PDS_RATE_LIMITS");
+            rules.retain(|rule| !rule.trim().is_empty());
+            if rules.is_empty() {
+                rules = DEFAULT_RATE_LIMITS.map(str::to_string).to_vec();
+            }
+            Some(RateLimitConfig {
+                rules: rules
+                    .iter()
+                    .map(|rule| rule.parse().expect("invalid PDS_RATE_LIMITS entry"))
+                    .collect(),
+                redis_url: env_str("PDS_RATE_LIMITS_REDIS_URL"),
+            })
+        }
+    };
+    let terms = |name: &str| -> Vec<String> {
+        env_list(name)
+            .into_iter()
+            .map(|term| term.trim().to_lowercase())
+            .filter(|term| !term.is_empty())
+            .collect()
+    };
+    let handle_policy_cfg = HandlePolicyConfig {
+        reserved: terms("PDS_HANDLE_RESERVED_NAMES"),
+        banned: terms("PDS_HANDLE_BANNED_TERMS"),
+        disallowed_tlds: terms("PDS_HANDLE_DISALLOWED_TLDS")
+            .into_iter()
+            .map(|tld| tld.trim_start_matches('.').to_string())
+            .collect(),
+        min_length: env_int("PDS_HANDLE_MIN_LENGTH").unwrap_or(3),
+        max_length: env_int("PDS_HANDLE_MAX_LENGTH").unwrap_or(18),
+        refresh_interval: env_int("PDS_RESERVED_HANDLES_REFRESH_SECS").unwrap_or(60) as u64,
+    };
+    let route_flags_cfg = RouteFlagsConfig {
+        disabled: env_list("PDS_DISABLED_ROUTES")
+            .into_iter()
+            .map(|nsid| nsid.trim().to_string())
+            .filter(|nsid| !nsid.is_empty())
+            .collect(),
+        refresh_interval: env_int("PDS_ROUTE_FLAGS_REFRESH_SECS").unwrap_or(30) as u64,
+    };
+    let webhook_cfg = WebhookConfig {
+        urls: env_list("PDS_WEBHOOK_URLS")
+            .into_iter()
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .collect(),
+        secret: env_str("PDS_WEBHOOK_SECRET").filter(|secret| !secret.is_empty()),
+        max_retries: env_int("PDS_WEBHOOK_MAX_RETRIES").unwrap_or(5) as u32,
+    };
+    let spam_scoring_cfg = env_str("PDS_SPAM_SCORING_URL")
+        .filter(|url| !url.is_empty())
+        .map(|url| SpamScoringConfig {
+            url,
+            timeout: env_int("PDS_SPAM_SCORING_TIMEOUT_MS").unwrap_or(1000) as u64,
+            fail_open: env_bool("PDS_SPAM_SCORING_FAIL_OPEN").unwrap_or(true),
+            block_threshold: env_str("PDS_SPAM_BLOCK_THRESHOLD")
+                .map(|score| score.parse().expect("invalid PDS_SPAM_BLOCK_THRESHOLD"))
+                .unwrap_or(0.9),
+            flag_threshold: env_str("PDS_SPAM_FLAG_THRESHOLD")
+                .map(|score| score.parse().expect("invalid PDS_SPAM_FLAG_THRESHOLD"))
+                .unwrap_or(0.5),
+        });
+    let client_ip_cfg = ClientIpConfig {
+        trusted_proxies: env_list("PDS_TRUSTED_PROXIES")
+            .iter()
+            .filter(|cidr| !cidr.trim().is_empty())
+            .map(|cidr| cidr.parse().expect("invalid PDS_TRUSTED_PROXIES entry"))
+            .collect(),
+        proxy_protocol_port: env_int("PDS_PROXY_PROTOCOL_PORT").map(|port| port as u16),
+    };
+    let phone_verification_cfg = PhoneVerificationConfig {
+        accept: env_bool("PDS_ACCEPT_PHONE_VERIFICATION").unwrap_or(false),
+    };
+    let account_export_cfg = AccountExportConfig {
+        url_expires_in: env_int("PDS_ACCOUNT_EXPORT_URL_EXPIRES_IN").unwrap_or(3600) as u64,
+        migration_retention: env_int("PDS_ACCOUNT_EXPORT_MIGRATION_RETENTION").unwrap_or(7 * 86400)
+            as u64,
+    };
+    let moderation_cfg = ModerationConfig {
+        auto_actions: env_list("PDS_MODERATION_AUTO_ACTIONS")
+            .iter()
+            .filter(|rule| !rule.trim().is_empty())
+            .map(|rule| {
+                rule.parse()
+                    .expect("invalid PDS_MODERATION_AUTO_ACTIONS rule")
+            })
+            .collect(),
+        sweep_interval: env_int("PDS_MODERATION_SWEEP_INTERVAL_SECS").unwrap_or(60) as u64,
     };
+    let actor_store_cfg = ActorStoreConfig {
+        deleted_record_retention: env_int("PDS_DELETED_RECORD_RETENTION_SECS")
+            .filter(|secs| *secs > 0)
+            .map(|secs| secs as u64),
+        commit_history_limit: env_int("PDS_REPO_COMMIT_HISTORY_LIMIT").unwrap_or(100) as u64,
+        verify_blocks_on_read: env_bool("PDS_VERIFY_BLOCKS_ON_READ").unwrap_or(false),
+    };
+    let write_rate_limits = env_list("PDS_WRITE_RATE_LIMITS")
+        .iter()
+        .filter(|limit| !limit.trim().is_empty())
+        .map(|limit| limit.parse().expect("invalid PDS_WRITE_RATE_LIMITS entry"))
+        .collect();
     // default to being required if left undefined
     let invites_cfg = match env_bool("PDS_INVITE_REQUIRED").unwrap_or(true) {
         false => InvitesConfig {
@@ -157,6 +735,39 @@ pub fn env_to_cfg() -> ServerConfig {
         },
     };
     let crawlers_cfg = env_list("PDS_CRAWLERS");
+    let blob_moderation_cfg = BlobModerationConfig {
+        status: match env_int("PDS_BLOB_MODERATED_STATUS") {
+            None => None,
+            Some(status @ (410 | 451)) => Some(status as u16),
+            Some(other) => panic!("PDS_BLOB_MODERATED_STATUS must be 410 or 451, not {other}"),
+        },
+        labels: env_list("PDS_BLOB_MODERATED_LABELS")
+            .into_iter()
+            .map(|label| label.trim().to_string())
+            .filter(|label| !label.is_empty())
+            .collect(),
+    };
+    let blob_redirect_expires_in = env_int("PDS_BLOB_REDIRECT_EXPIRES_IN").unwrap_or(300) as u64;
+    let blob_redirect_cfg = match env_str("PDS_BLOB_REDIRECT").as_deref() {
+        None | Some("") | Some("none") => None,
+        Some("presigned") => Some(BlobRedirectConfig::Presigned {
+            expires_in: blob_redirect_expires_in,
+        }),
+        Some("cloudfront") => Some(BlobRedirectConfig::CloudFront {
+            url: env_str("PDS_BLOB_CLOUDFRONT_URL")
+                .expect("if blob redirect is cloudfront, must configure its url as well."),
+            key_pair_id: env_str("PDS_BLOB_CLOUDFRONT_KEY_PAIR_ID")
+                .expect("if blob redirect is cloudfront, must configure its key pair id as well."),
+            private_key: cloudfront::parse_private_key(
+                &env_str("PDS_BLOB_CLOUDFRONT_PRIVATE_KEY").expect(
+                    "if blob redirect is cloudfront, must configure its private key as well.",
+                ),
+            )
+            .expect("invalid cloudfront private key"),
+            expires_in: blob_redirect_expires_in,
+        }),
+        Some(other) => panic!("unknown PDS_BLOB_REDIRECT mode: {other}"),
+    };
 
     ServerConfig {
         service: service_cfg,
@@ -167,6 +778,25 @@ pub fn env_to_cfg() -> ServerConfig {
         invites: invites_cfg,
         crawlers: crawlers_cfg,
         identity: identity_cfg,
+        handle_policy: handle_policy_cfg,
+        blob_redirect: blob_redirect_cfg,
+        blob_moderation: blob_moderation_cfg,
+        shutdown: shutdown_cfg,
+        account_export: account_export_cfg,
+        moderation: moderation_cfg,
+        maintenance: maintenance_cfg,
+        admin: admin_cfg,
+        route_flags: route_flags_cfg,
+        webhooks: webhook_cfg,
+        spam_scoring: spam_scoring_cfg,
+        client_ip: client_ip_cfg,
+        phone_verification: phone_verification_cfg,
+        database: database_cfg,
+        actor_store: actor_store_cfg,
+        seq_archive: seq_archive_cfg,
+        seq_coalesce: seq_coalesce_cfg,
+        write_rate_limits,
+        rate_limits: rate_limits_cfg,
     }
 }
 
@@ -180,3 +810,87 @@ impl ServerConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_auto_action_rule() {
+        let rule: AutoActionRule = "5/3600:takedown=86400".parse().unwrap();
+        assert_eq!(
+            rule,
+            AutoActionRule {
+                reports: 5,
+                window: 3600,
+                action: AutoAction::Takedown { duration: 86400 },
+            }
+        );
+        assert_eq!(rule.to_string(), "5/3600:takedown=86400");
+
+        let rule: AutoActionRule = " 3/600:flag".parse().unwrap();
+        assert_eq!(rule.action, AutoAction::Flag);
+        assert_eq!(rule.to_string(), "3/600:flag");
+
+        assert!("3/600".parse::<AutoActionRule>().is_err());
+        assert!("3:flag".parse::<AutoActionRule>().is_err());
+        assert!("0/600:flag".parse::<AutoActionRule>().is_err());
+        assert!("3/600:takedown".parse::<AutoActionRule>().is_err());
+        assert!("3/600:suspend=60".parse::<AutoActionRule>().is_err());
+    }
+
+    #[test]
+    fn test_parse_rate_limit_rule() {
+        let rule: RateLimitRule = "ip:com.atproto.server.createSession=30/300"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            rule,
+            RateLimitRule {
+                key: RateLimitKey::Ip,
+                nsid: Some("com.atproto.server.createSession".to_string()),
+                points: 30,
+                window: 300,
+            }
+        );
+        assert!(rule.applies_to("com.atproto.server.createSession"));
+        assert!(!rule.applies_to("com.atproto.server.createAccount"));
+
+        let rule: RateLimitRule = "did:*=100/60".parse().unwrap();
+        assert_eq!(rule.key, RateLimitKey::Did);
+        assert!(rule.applies_to("app.bsky.feed.getTimeline"));
+        assert_eq!(rule.to_string(), "did:*=100/60");
+
+        for rule in DEFAULT_RATE_LIMITS {
+            assert_eq!(rule.parse::<RateLimitRule>().unwrap().to_string(), rule);
+        }
+        assert!("*=100/60".parse::<RateLimitRule>().is_err());
+        assert!("user:*=100/60".parse::<RateLimitRule>().is_err());
+        assert!("ip:*=100".parse::<RateLimitRule>().is_err());
+        assert!("ip:*=100/0".parse::<RateLimitRule>().is_err());
+    }
+
+    #[test]
+    fn test_parse_collection_rate_limit() {
+        let limit: CollectionRateLimit = " app.bsky.graph.follow=1000/86400".parse().unwrap();
+        assert_eq!(
+            limit,
+            CollectionRateLimit {
+                collection: "app.bsky.graph.follow".to_string(),
+                writes: 1000,
+                window: 86400,
+            }
+        );
+        assert!("app.bsky.feed.post".parse::<CollectionRateLimit>().is_err());
+        assert!("app.bsky.feed.post=100"
+            .parse::<CollectionRateLimit>()
+            .is_err());
+        assert!("post=100/3600".parse::<CollectionRateLimit>().is_err());
+        assert!("app.bsky.feed.post=100/0"
+            .parse::<CollectionRateLimit>()
+            .is_err());
+        assert!("app.bsky.feed.post=-1/3600"
+            .parse::<CollectionRateLimit>()
+            .is_err());
+    }
+}
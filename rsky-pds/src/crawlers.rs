@@ -1,7 +1,9 @@
-use crate::APP_USER_AGENT;
+use crate::http::{self, Destination};
 use anyhow::Result;
 use futures::stream::{self, StreamExt};
 use rsky_common::time::MINUTE;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::SystemTime;
 
 const NOTIFY_THRESHOLD: i32 = 20 * MINUTE; // 20 minutes;
@@ -10,7 +12,8 @@ const NOTIFY_THRESHOLD: i32 = 20 * MINUTE; // 20 minutes;
 pub struct Crawlers {
     pub hostname: String,
     pub crawlers: Vec<String>,
-    pub last_notified: usize,
+    /// Shared by clones, so the sequencer's copies don't each notify on their own schedule.
+    pub last_notified: Arc<AtomicUsize>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -23,32 +26,31 @@ impl Crawlers {
         Crawlers {
             hostname,
             crawlers,
-            last_notified: 0,
+            last_notified: Arc::new(AtomicUsize::new(0)),
         }
     }
 
-    pub async fn notify_of_update(&mut self) -> Result<()> {
+    pub async fn notify_of_update(&self) -> Result<()> {
         let now = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .expect("timestamp in micros since UNIX epoch")
             .as_micros() as usize;
-        if now - self.last_notified < NOTIFY_THRESHOLD as usize {
+        if now - self.last_notified.load(Ordering::Relaxed) < NOTIFY_THRESHOLD as usize {
             return Ok(());
         }
         let _ = stream::iter(self.crawlers.clone())
             .then(|service: String| async move {
-                let client = reqwest::Client::builder()
-                    .user_agent(APP_USER_AGENT)
-                    .build()?;
                 let record = CrawlerRequest {
                     hostname: service.clone(),
                 };
                 Ok::<reqwest::Response, anyhow::Error>(
-                    client
-                        .post(format!("{}/xrpc/com.atproto.sync.requestCrawl", service))
-                        .json(&record)
-                        .send()
-                        .await?,
+                    http::post(
+                        Destination::Crawler,
+                        format!("{}/xrpc/com.atproto.sync.requestCrawl", service),
+                    )
+                    .json(&record)
+                    .send()
+                    .await?,
                 )
             })
             .collect::<Vec<_>>()
@@ -56,7 +58,7 @@ impl Crawlers {
             .into_iter()
             .collect::<Result<Vec<_>, _>>()?;
 
-        self.last_notified = now;
+        self.last_notified.store(now, Ordering::Relaxed);
         Ok(())
     }
 }
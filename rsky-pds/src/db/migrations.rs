@@ -0,0 +1,97 @@
+//! Schema migrations, run on boot so a PDS never serves against a schema it wasn't built for.
+//!
+//! Replicas booting together serialize on a postgres advisory lock: the first one applies
+//! what's pending, the rest find nothing left to do once they get the lock. A replica whose
+//! build is older than the schema refuses to start rather than write with stale assumptions.
+
+use crate::config::ServerConfig;
+use crate::db::DbConn;
+use anyhow::{anyhow, bail, Result};
+use diesel::migration::MigrationSource;
+use diesel::pg::{Pg, PgConnection};
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use rocket::{fairing, Build, Rocket};
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+
+/// Schema version read once at startup, after migrating, and reported by `_health`.
+pub struct SchemaVersion(pub Option<String>);
+
+/// Advisory lock key held while migrating ("rskymigr").
+const MIGRATION_LOCK: i64 = 0x7273_6b79_6d69_6772;
+
+/// Latest migration this build knows about.
+pub fn latest_known_version() -> Option<String> {
+    MigrationSource::<Pg>::migrations(&MIGRATIONS)
+        .ok()?
+        .iter()
+        .map(|migration| migration.name().version().to_string())
+        .max()
+}
+
+/// Latest migration applied to the database.
+pub fn schema_version(conn: &mut PgConnection) -> Result<Option<String>> {
+    let applied = conn
+        .applied_migrations()
+        .map_err(|error| anyhow!("failed to read applied migrations: {error}"))?;
+    Ok(applied.iter().max().map(|version| version.to_string()))
+}
+
+/// Applies pending migrations under the advisory lock, returning the versions applied.
+pub fn run_pending(conn: &mut PgConnection) -> Result<Vec<String>> {
+    diesel::sql_query("SELECT pg_advisory_lock($1)")
+        .bind::<BigInt, _>(MIGRATION_LOCK)
+        .execute(conn)?;
+    let result = migrate_locked(conn);
+    // session-level lock, so release it even if migrating failed
+    let unlocked = diesel::sql_query("SELECT pg_advisory_unlock($1)")
+        .bind::<BigInt, _>(MIGRATION_LOCK)
+        .execute(conn);
+    let applied = result?;
+    unlocked?;
+    Ok(applied)
+}
+
+fn migrate_locked(conn: &mut PgConnection) -> Result<Vec<String>> {
+    if let (Some(current), Some(known)) = (schema_version(conn)?, latest_known_version()) {
+        if current > known {
+            bail!("database schema is at {current}, newer than this build's {known}");
+        }
+    }
+    let applied = conn
+        .run_pending_migrations(MIGRATIONS)
+        .map_err(|error| anyhow!("failed to run migrations: {error}"))?;
+    Ok(applied.iter().map(|version| version.to_string()).collect())
+}
+
+/// Ignite hook for `AdHoc::try_on_ignite`; attach after `DbConn::fairing()`. Migrating is
+/// skipped when `PDS_DB_AUTO_MIGRATE=false`, for deployments that migrate out of band; either
+/// way the resulting [`SchemaVersion`] is managed for `_health`.
+pub async fn migrate_on_ignite(rocket: Rocket<Build>) -> fairing::Result {
+    let auto_migrate = rocket
+        .state::<ServerConfig>()
+        .map_or(true, |cfg| cfg.database.auto_migrate);
+    let Some(conn) = DbConn::get_one(&rocket).await else {
+        tracing::error!("@LOG: no database connection to run migrations with");
+        return Err(rocket);
+    };
+    let res = conn
+        .run(move |conn| {
+            if auto_migrate {
+                for version in run_pending(conn)? {
+                    tracing::info!("@LOG: applied migration {version}");
+                }
+            }
+            schema_version(conn)
+        })
+        .await;
+    match res {
+        Ok(version) => Ok(rocket.manage(SchemaVersion(version))),
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: {error}");
+            Err(rocket)
+        }
+    }
+}
@@ -1,3 +1,5 @@
+pub mod migrations;
+
 use anyhow::Result;
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
@@ -8,6 +8,12 @@ pub enum ErrorKind {
     HandleNotAvailable,
     #[error("Unsupported domain")]
     UnsupportedDomain,
+    /// Neither DNS nor the well-known endpoint gave a DID for the handle.
+    #[error("Handle unresolvable")]
+    HandleUnresolvable,
+    /// The handle resolves, but to another DID.
+    #[error("Handle DID mismatch")]
+    HandleDidMismatch,
     #[error("Internal error")]
     InternalError,
 }
@@ -2,13 +2,14 @@ use crate::config::ServerConfig;
 use crate::handle::errors::{Error, ErrorKind, Result};
 use crate::SharedIdResolver;
 use explicit_slurs::has_explicit_slur;
-use reserved::RESERVED_SUBDOMAINS;
+use policy::HandlePolicy;
 use rocket::State;
 use rsky_syntax::handle::{is_valid_tld, normalize_and_ensure_valid_handle};
 
 pub struct HandleValidationContext<'a> {
     pub server_config: &'a State<ServerConfig>,
     pub id_resolver: &'a State<SharedIdResolver>,
+    pub handle_policy: &'a State<HandlePolicy>,
 }
 
 pub struct HandleValidationOpts {
@@ -39,46 +40,63 @@ pub async fn normalize_and_validate_handle(
             "Inappropriate language in handle",
         ));
     }
-
     let service_domains = &ctx.server_config.identity.service_handle_domains;
+    ctx.handle_policy.check_handle(&handle, service_domains)?;
+
     if is_service_domain(&handle, service_domains) {
         // Verify constraints on a service domain
         ensure_handle_service_constraints(
             &handle,
             service_domains,
+            ctx.handle_policy,
             opts.allow_reserved.unwrap_or(false),
         )?;
     } else {
-        if opts.did.is_none() {
+        let Some(did) = opts.did else {
             return Err(Error::new(
                 ErrorKind::UnsupportedDomain,
                 "Not a supported handle domain",
             ));
-        }
+        };
 
         // Verify resolution of a non-service domain
-        let mut lock = ctx.id_resolver.id_resolver.write().await;
-        match lock.handle.resolve(&handle).await.unwrap() {
-            Some(resolved_did) => {
-                if resolved_did != opts.did.unwrap() {
-                    return Err(Error::new(
-                        ErrorKind::InvalidHandle,
-                        "External handle did not resolve to DID",
-                    ));
-                }
-            }
-            None => {
-                return Err(Error::new(
-                    ErrorKind::InvalidHandle,
-                    "Id Resolver did not resolve to DID",
-                ));
-            }
-        }
+        verify_external_handle(&handle, &did, ctx.id_resolver).await?;
     }
 
     Ok(handle)
 }
 
+/// Checks a handle outside the service domains points at `did`, through either its
+/// `_atproto` TXT record or its `/.well-known/atproto-did`. Both are looked up fresh, as a
+/// cached resolution may predate the user setting them up.
+async fn verify_external_handle(
+    handle: &String,
+    did: &str,
+    id_resolver: &State<SharedIdResolver>,
+) -> Result<()> {
+    let resolver = id_resolver.id_resolver.read().await.handle.clone();
+    let dns = resolver.resolve_dns(handle).await.ok().flatten();
+    if dns.as_deref() == Some(did) {
+        return Ok(());
+    }
+    let http = resolver.resolve_http(handle).await.ok().flatten();
+    if http.as_deref() == Some(did) {
+        return Ok(());
+    }
+    match (dns, http) {
+        (Some(other), _) | (None, Some(other)) => Err(Error::new(
+            ErrorKind::HandleDidMismatch,
+            &format!("Handle resolves to {other}, not {did}"),
+        )),
+        (None, None) => Err(Error::new(
+            ErrorKind::HandleUnresolvable,
+            &format!(
+                "No DID found in a _atproto.{handle} TXT record or at https://{handle}/.well-known/atproto-did"
+            ),
+        )),
+    }
+}
+
 fn base_normalize_and_validate(handle: &str) -> Result<String> {
     match normalize_and_ensure_valid_handle(handle) {
         Ok(normalized) => Ok(normalized),
@@ -95,6 +113,7 @@ fn is_service_domain(handle: &str, available_user_domains: &[String]) -> bool {
 fn ensure_handle_service_constraints(
     handle: &str,
     available_user_domains: &[String],
+    policy: &HandlePolicy,
     allow_reserved: bool,
 ) -> Result<()> {
     let supported_domain = available_user_domains
@@ -111,21 +130,10 @@ fn ensure_handle_service_constraints(
         ));
     }
 
-    if front.len() < 3 {
-        return Err(Error::new(ErrorKind::InvalidHandle, "Handle too short"));
-    }
-
-    if front.len() > 18 {
-        return Err(Error::new(ErrorKind::InvalidHandle, "Handle too long"));
-    }
-
-    if !allow_reserved && RESERVED_SUBDOMAINS.contains(front.as_str()) {
-        return Err(Error::new(ErrorKind::HandleNotAvailable, "Reserved handle"));
-    }
-
-    Ok(())
+    policy.check_name(&front, allow_reserved)
 }
 
 pub mod errors;
 pub mod explicit_slurs;
+pub mod policy;
 pub mod reserved;
@@ -0,0 +1,241 @@
+//! Operator rules for handles, on top of the syntax checks, the built-in [`reserved`](super::reserved)
+//! names and the slur filter. `PDS_HANDLE_BANNED_TERMS` and `PDS_HANDLE_DISALLOWED_TLDS` apply
+//! to every handle; `PDS_HANDLE_RESERVED_NAMES` and `PDS_HANDLE_MIN_LENGTH`/`_MAX_LENGTH` to the
+//! name before a service domain.
+//!
+//! Names reserved through `xyz.blackskyweb.admin.addReservedHandle` are stored in
+//! `pds.reserved_handle` and reach other replicas on their next refresh. Only those can be
+//! released again at runtime.
+
+use crate::config::HandlePolicyConfig;
+use crate::db::DbConn;
+use crate::handle::errors::{Error, ErrorKind};
+use crate::handle::reserved::is_reserved_handle;
+use crate::models::models;
+use anyhow::Result;
+use diesel::*;
+use rsky_lexicon::xyz::blackskyweb::admin::ReservedHandle;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Shared with the refresh job, so clones see the same reserved names.
+#[derive(Clone)]
+pub struct HandlePolicy {
+    config: Arc<HandlePolicyConfig>,
+    defaults: Arc<HashSet<String>>,
+    added: Arc<RwLock<BTreeMap<String, ReservedHandle>>>,
+}
+
+impl HandlePolicy {
+    pub fn new(config: HandlePolicyConfig) -> Self {
+        HandlePolicy {
+            defaults: Arc::new(config.reserved.iter().cloned().collect()),
+            config: Arc::new(config),
+            added: Arc::default(),
+        }
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.config.max_length
+    }
+
+    /// Whether `name` is held back before a service domain, by default or by an admin.
+    pub fn is_reserved(&self, name: &str) -> bool {
+        is_reserved_handle(name)
+            || self.defaults.contains(name)
+            || self.added.read().unwrap().contains_key(name)
+    }
+
+    /// Whether `name` can only be released by changing the config or the built-in list.
+    pub fn is_reserved_by_default(&self, name: &str) -> bool {
+        is_reserved_handle(name) || self.defaults.contains(name)
+    }
+
+    /// Checks a normalized handle against the disallowed TLDs, and its name labels against the
+    /// banned terms one label at a time. The name labels are those before a service domain, or
+    /// before the TLD for other domains, so the operator's own domain never trips a term.
+    pub fn check_handle(&self, handle: &str, service_domains: &[String]) -> Result<(), Error> {
+        let tld = handle.rsplit('.').next().unwrap_or_default();
+        if self.config.disallowed_tlds.iter().any(|t| t == tld) {
+            return Err(Error::new(
+                ErrorKind::InvalidHandle,
+                "Handle TLD is invalid or disallowed",
+            ));
+        }
+        let name = service_domains
+            .iter()
+            .find_map(|domain| handle.strip_suffix(domain.as_str()))
+            .unwrap_or_else(|| handle.strip_suffix(tld).unwrap_or(handle));
+        let banned = name.split('.').any(|label| {
+            // same separators the slur check looks through
+            let cleaned = label.replace(['-', '_'], "");
+            self.config
+                .banned
+                .iter()
+                .any(|term| label.contains(term.as_str()) || cleaned.contains(term.as_str()))
+        });
+        if banned {
+            return Err(Error::new(
+                ErrorKind::InvalidHandle,
+                "Inappropriate language in handle",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the name before a service domain, e.g. `alice` in `alice.example.com`.
+    pub fn check_name(&self, name: &str, allow_reserved: bool) -> Result<(), Error> {
+        if name.len() < self.config.min_length {
+            return Err(Error::new(ErrorKind::InvalidHandle, "Handle too short"));
+        }
+        if name.len() > self.config.max_length {
+            return Err(Error::new(ErrorKind::InvalidHandle, "Handle too long"));
+        }
+        if !allow_reserved && self.is_reserved(name) {
+            return Err(Error::new(ErrorKind::HandleNotAvailable, "Reserved handle"));
+        }
+        Ok(())
+    }
+
+    /// Names reserved by an admin.
+    pub fn list(&self) -> Vec<ReservedHandle> {
+        self.added.read().unwrap().values().cloned().collect()
+    }
+
+    /// Stores `name` and applies it on this replica right away.
+    pub async fn reserve(
+        &self,
+        name: String,
+        reason: Option<String>,
+        db: &DbConn,
+    ) -> Result<ReservedHandle> {
+        use crate::schema::pds::reserved_handle::dsl as ReservedHandleSchema;
+
+        let row = models::ReservedHandle {
+            name,
+            reason,
+            created_at: rsky_common::now(),
+        };
+        let row = db
+            .run(move |conn| {
+                insert_into(ReservedHandleSchema::reserved_handle)
+                    .values(&row)
+                    .on_conflict(ReservedHandleSchema::name)
+                    .do_update()
+                    .set(ReservedHandleSchema::reason.eq(&row.reason))
+                    .returning(models::ReservedHandle::as_returning())
+                    .get_result(conn)
+            })
+            .await?;
+        let reserved = to_lexicon(row);
+        self.added
+            .write()
+            .unwrap()
+            .insert(reserved.name.clone(), reserved.clone());
+        Ok(reserved)
+    }
+
+    /// Releases a name reserved by an admin, returning whether there was one.
+    pub async fn release(&self, name: String, db: &DbConn) -> Result<bool> {
+        use crate::schema::pds::reserved_handle::dsl as ReservedHandleSchema;
+
+        let key = name.clone();
+        let deleted = db
+            .run(move |conn| {
+                delete(ReservedHandleSchema::reserved_handle)
+                    .filter(ReservedHandleSchema::name.eq(&key))
+                    .execute(conn)
+            })
+            .await?;
+        self.added.write().unwrap().remove(&name);
+        Ok(deleted > 0)
+    }
+
+    /// Reloads the stored names.
+    pub async fn refresh(&self, db: &DbConn) -> Result<()> {
+        use crate::schema::pds::reserved_handle::dsl as ReservedHandleSchema;
+
+        let rows = db
+            .run(|conn| {
+                ReservedHandleSchema::reserved_handle
+                    .select(models::ReservedHandle::as_select())
+                    .load(conn)
+            })
+            .await?;
+        let added = rows
+            .into_iter()
+            .map(|row| (row.name.clone(), to_lexicon(row)))
+            .collect();
+        *self.added.write().unwrap() = added;
+        Ok(())
+    }
+}
+
+fn to_lexicon(row: models::ReservedHandle) -> ReservedHandle {
+    ReservedHandle {
+        name: row.name,
+        reason: row.reason,
+        created_at: row.created_at,
+    }
+}
+
+/// Runs [`HandlePolicy::refresh`] every `interval` seconds until aborted.
+pub async fn run_refresher(interval: u64, policy: HandlePolicy, db: DbConn) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval.max(1)));
+    loop {
+        ticker.tick().await;
+        if let Err(error) = policy.refresh(&db).await {
+            tracing::error!("@LOG: ERROR: failed to refresh reserved handles: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_policy() {
+        let policy = HandlePolicy::new(HandlePolicyConfig {
+            reserved: vec!["staff".to_string()],
+            banned: vec!["scam".to_string()],
+            disallowed_tlds: vec!["zip".to_string()],
+            min_length: 4,
+            max_length: 10,
+            refresh_interval: 60,
+        });
+        let domains = vec![".scamwatch.example".to_string()];
+        assert!(policy.check_handle("alice.example.com", &domains).is_ok());
+        assert!(policy.check_handle("alice.example.zip", &domains).is_err());
+        assert!(policy
+            .check_handle("free-sc-am.example.com", &domains)
+            .is_err());
+        assert!(policy.check_handle("scam.example.com", &domains).is_err());
+        // only within a label, and never in the service domain
+        assert!(policy.check_handle("sc.am.example.com", &domains).is_ok());
+        assert!(policy
+            .check_handle("alice.scamwatch.example", &domains)
+            .is_ok());
+
+        assert!(policy.check_name("alice", false).is_ok());
+        assert!(policy.check_name("bob", false).is_err());
+        assert!(policy.check_name("alicealicealice", false).is_err());
+        // built-in and configured reservations, unless allowed
+        assert!(policy.check_name("admin", false).is_err());
+        assert!(policy.check_name("staff", false).is_err());
+        assert!(policy.check_name("staff", true).is_ok());
+
+        policy.added.write().unwrap().insert(
+            "events".to_string(),
+            ReservedHandle {
+                name: "events".to_string(),
+                reason: None,
+                created_at: rsky_common::now(),
+            },
+        );
+        assert!(policy.is_reserved("events"));
+        assert!(!policy.is_reserved_by_default("events"));
+        assert!(policy.is_reserved_by_default("staff"));
+    }
+}
@@ -0,0 +1,270 @@
+//! The one HTTP client all outbound requests go through, so they share pooled connections and
+//! a DNS cache instead of each call paying for a fresh handshake and lookup. Timeouts are set
+//! per request, by [`Destination`].
+//!
+//! Requests to URLs an outside party picked, like OAuth client metadata, go through a second
+//! client that doesn't follow redirects and only connects to public addresses, so they can't
+//! be pointed at loopback, the local network or a cloud metadata service.
+
+use crate::APP_USER_AGENT;
+use lazy_static::lazy_static;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use reqwest::{Client, IntoUrl, Method, RequestBuilder};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+const POOL_MAX_IDLE_PER_HOST: usize = 32;
+const DNS_TTL: Duration = Duration::from_secs(60);
+// Hosts whose answers are cached; expired ones go first once it's full, then the rest
+const MAX_DNS_ENTRIES: usize = 4_096;
+
+lazy_static! {
+    static ref CLIENT: Client = Client::builder()
+        .user_agent(APP_USER_AGENT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .pool_max_idle_per_host(POOL_MAX_IDLE_PER_HOST)
+        .tcp_keepalive(Duration::from_secs(60))
+        .http2_keep_alive_while_idle(true)
+        .http2_keep_alive_timeout(Duration::from_secs(5))
+        .dns_resolver(Arc::new(CachingResolver::default()))
+        .build()
+        .expect("failed to build HTTP client");
+    static ref PUBLIC_CLIENT: Client = Client::builder()
+        .user_agent(APP_USER_AGENT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .redirect(Policy::none())
+        .dns_resolver(Arc::new(PublicResolver::default()))
+        .build()
+        .expect("failed to build HTTP client");
+}
+
+/// Who a request goes to, which decides how long it may take.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Destination {
+    Plc,
+    /// Services requests are proxied to, like the appview.
+    Proxy,
+    /// Calls the PDS makes to the appview itself partway through answering a request.
+    AppView,
+    /// Relays and other crawlers.
+    Crawler,
+    Mailer,
+    ModService,
+    Webhook,
+    /// OAuth client metadata documents and key sets. Public addresses only.
+    OAuthClient,
+    /// did:web documents named by whoever is creating or importing an account. Public
+    /// addresses only.
+    DidWeb,
+}
+
+impl Destination {
+    pub fn timeout(&self) -> Duration {
+        match self {
+            Destination::Plc => Duration::from_secs(10),
+            // covers reading the whole body, so leave room for large responses
+            Destination::Proxy => Duration::from_secs(60),
+            Destination::AppView => Duration::from_secs(1),
+            Destination::Crawler => Duration::from_secs(10),
+            Destination::Mailer => Duration::from_secs(15),
+            Destination::ModService => Duration::from_secs(5),
+            Destination::Webhook => Duration::from_secs(10),
+            Destination::OAuthClient => Duration::from_secs(5),
+            Destination::DidWeb => Duration::from_secs(3),
+        }
+    }
+}
+
+/// The shared client, for callers that need to set their own timeout.
+pub fn client() -> &'static Client {
+    &CLIENT
+}
+
+pub fn request<U: IntoUrl>(destination: Destination, method: Method, url: U) -> RequestBuilder {
+    let client: &Client = match destination {
+        Destination::OAuthClient | Destination::DidWeb => &PUBLIC_CLIENT,
+        _ => &CLIENT,
+    };
+    client.request(method, url).timeout(destination.timeout())
+}
+
+pub fn get<U: IntoUrl>(destination: Destination, url: U) -> RequestBuilder {
+    request(destination, Method::GET, url)
+}
+
+pub fn post<U: IntoUrl>(destination: Destination, url: U) -> RequestBuilder {
+    request(destination, Method::POST, url)
+}
+
+/// Caches the system resolver's answers for `DNS_TTL`, for up to `MAX_DNS_ENTRIES` hosts.
+#[derive(Default)]
+struct CachingResolver {
+    cache: Arc<Mutex<HashMap<String, (Instant, Vec<SocketAddr>)>>>,
+}
+
+impl Resolve for CachingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let cache = self.cache.clone();
+        Box::pin(async move {
+            let host = name.as_str().to_owned();
+            let cached = cache
+                .lock()
+                .unwrap()
+                .get(&host)
+                .filter(|(resolved_at, _)| resolved_at.elapsed() < DNS_TTL)
+                .map(|(_, addrs)| addrs.clone());
+            if let Some(addrs) = cached {
+                let addrs: Addrs = Box::new(addrs.into_iter());
+                return Ok(addrs);
+            }
+            // reqwest fills in the port
+            let resolved: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            {
+                let mut cache = cache.lock().unwrap();
+                if cache.len() >= MAX_DNS_ENTRIES {
+                    cache.retain(|_, (resolved_at, _)| resolved_at.elapsed() < DNS_TTL);
+                }
+                if cache.len() >= MAX_DNS_ENTRIES {
+                    cache.clear();
+                }
+                cache.insert(host, (Instant::now(), resolved.clone()));
+            }
+            let addrs: Addrs = Box::new(resolved.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Whether `ip` is on the public internet. IP literals in URLs skip the resolver, so callers
+/// fetching URLs from outside check those themselves.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_unspecified()
+                || v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_multicast()
+                // this network, shared address space and the reserved 240.0.0.0/4
+                || a == 0
+                || (a == 100 && (b & 0xc0) == 64)
+                || a >= 240)
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let segments = v6.segments();
+            // NAT64 reaches whatever IPv4 address it embeds
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [a, b] = segments[6].to_be_bytes();
+                let [c, d] = segments[7].to_be_bytes();
+                return is_public_ip(IpAddr::V4([a, b, c, d].into()));
+            }
+            !(v6.is_unspecified()
+                || v6.is_loopback()
+                || v6.is_multicast()
+                // unique local fc00::/7, link local fe80::/10 and documentation 2001:db8::/32
+                || (segments[0] & 0xfe00) == 0xfc00
+                || (segments[0] & 0xffc0) == 0xfe80
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8))
+        }
+    }
+}
+
+/// Resolves like [`CachingResolver`], but fails for names with any non-public address.
+#[derive(Default)]
+struct PublicResolver(CachingResolver);
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolving = self.0.resolve(name);
+        Box::pin(async move {
+            let resolved: Vec<SocketAddr> = resolving.await?.collect();
+            if let Some(addr) = resolved.iter().find(|addr| !is_public_ip(addr.ip())) {
+                return Err(format!("{} is not a public address", addr.ip()).into());
+            }
+            let addrs: Addrs = Box::new(resolved.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn test_caching_resolver() {
+        let resolver = CachingResolver::default();
+        let addrs: Vec<SocketAddr> = resolver
+            .resolve(Name::from_str("localhost").unwrap())
+            .await
+            .unwrap()
+            .collect();
+        assert!(!addrs.is_empty());
+        assert!(resolver.cache.lock().unwrap().contains_key("localhost"));
+    }
+
+    #[tokio::test]
+    async fn test_caching_resolver_is_bounded() {
+        let resolver = CachingResolver::default();
+        {
+            let mut cache = resolver.cache.lock().unwrap();
+            for i in 0..MAX_DNS_ENTRIES {
+                cache.insert(format!("host{i}.example"), (Instant::now(), vec![]));
+            }
+        }
+        resolver
+            .resolve(Name::from_str("localhost").unwrap())
+            .await
+            .unwrap();
+        let cache = resolver.cache.lock().unwrap();
+        assert!(cache.len() <= MAX_DNS_ENTRIES);
+        assert!(cache.contains_key("localhost"));
+    }
+
+    #[tokio::test]
+    async fn test_public_resolver() {
+        let resolver = PublicResolver::default();
+        assert!(resolver
+            .resolve(Name::from_str("localhost").unwrap())
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        let public = |ip: &str| is_public_ip(ip.parse().unwrap());
+        assert!(public("93.184.216.34"));
+        assert!(public("2606:2800:220:1:248:1893:25c8:1946"));
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::ffff:127.0.0.1",
+            "fd00::1",
+            "fe80::1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!public(ip), "{ip}");
+        }
+    }
+}
@@ -4,36 +4,67 @@ extern crate core;
 extern crate mailchecker;
 extern crate serde;
 use crate::read_after_write::viewer::{LocalViewer, LocalViewerCreator, LocalViewerCreatorParams};
+use crate::sequencer::archive::{self, SeqArchive};
+use crate::sequencer::coalesce;
 use crate::sequencer::Sequencer;
 use atrium_xrpc_client::reqwest::ReqwestClient;
 use event_emitter_rs::EventEmitter;
 use lazy_static::lazy_static;
 pub mod account_manager;
 pub mod actor_store;
+pub mod admin_approval;
 pub mod apis;
 pub mod auth_verifier;
+pub mod client_ip;
 pub mod config;
 pub mod context;
 pub mod crawlers;
 pub mod db;
 pub mod handle;
+pub mod http;
 pub mod image;
 pub mod lexicon;
+pub mod locale;
 pub mod mailer;
+pub mod maintenance;
 pub mod models;
+pub mod moderation;
+pub mod oauth;
 pub mod pipethrough;
 pub mod plc;
+pub mod rate_limit;
 pub mod read_after_write;
 pub mod repo;
+pub mod request_id;
+pub mod route_flags;
 pub mod schema;
+pub mod self_check;
 pub mod sequencer;
+pub mod shutdown;
+pub mod spam;
+#[cfg(any(test, feature = "test_helpers"))]
+pub mod test_helpers;
+pub mod webhooks;
 pub mod well_known;
 pub mod xrpc_server;
 use crate::account_manager::{AccountManager, SharedAccountManager};
-use crate::config::env_to_cfg;
+use crate::actor_store::aws::s3::load_sdk_config;
+use crate::actor_store::rate_limit::set_write_rate_limits;
+use crate::actor_store::record::value_index;
+use crate::actor_store::set_actor_store_config;
+use crate::client_ip::{ClientIpResolver, ProxiedConnections};
+use crate::config::keys::{self, ServiceKeys};
+use crate::config::{env_to_cfg, ServerConfig};
 use crate::crawlers::Crawlers;
+use crate::db::migrations::{self, SchemaVersion};
 use crate::db::DbConn;
+use crate::handle::policy::{self, HandlePolicy};
+use crate::maintenance::{MaintenanceState, ReadOnlyMode};
 use crate::models::{ErrorCode, ErrorMessageResponse, ServerVersion};
+use crate::rate_limit::RateLimiter;
+use crate::request_id::{traced, RequestIds};
+use crate::route_flags::{DisabledRoutes, RouteFlags};
+use crate::shutdown::{GracefulShutdown, ShutdownState};
 use diesel::prelude::*;
 use rocket::{catch, catchers, get, options, routes, Build, Rocket};
 
@@ -68,13 +99,14 @@ lazy_static! {
 }
 
 extern crate rocket;
-use crate::apis::{app, bsky_api_get_forwarder, bsky_api_post_forwarder, com, ApiError};
+use crate::apis::{app, bsky_api_get_forwarder, bsky_api_post_forwarder, com, xyz, ApiError};
 use atrium_api::client::AtpServiceClient;
 use atrium_xrpc_client::reqwest::ReqwestClientBuilder;
+use aws_config::SdkConfig;
 use diesel::sql_types::Int4;
 use dotenvy::dotenv;
 use rocket::data::{Limits, ToByteUnit};
-use rocket::fairing::{Fairing, Info, Kind};
+use rocket::fairing::{AdHoc, Fairing, Info, Kind};
 use rocket::figment::{
     util::map,
     value::{Map, Value},
@@ -84,11 +116,13 @@ use rocket::http::Status;
 use rocket::response::status;
 use rocket::serde::json::Json;
 use rocket::shield::{NoSniff, Shield};
-use rocket::{Request, Response};
-use rsky_common::env::env_list;
-use rsky_identity::types::{DidCache, IdentityResolverOpts};
+use rocket::{Request, Response, State};
+use rsky_identity::types::{DidCache, HandleCache, IdentityResolverOpts};
 use rsky_identity::IdResolver;
 use std::env;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
 pub struct CORS;
@@ -124,7 +158,19 @@ async fn robots() -> &'static str {
 #[get("/xrpc/_health")]
 async fn health(
     connection: DbConn,
+    shutdown: &State<ShutdownState>,
+    schema_version: &State<SchemaVersion>,
 ) -> Result<Json<ServerVersion>, status::Custom<Json<ErrorMessageResponse>>> {
+    // lets load balancers stop routing here while writes drain
+    if shutdown.is_draining() {
+        return Err(status::Custom(
+            Status::ServiceUnavailable,
+            Json(ErrorMessageResponse {
+                code: Some(ErrorCode::ServiceUnavailable),
+                message: Some("Server is shutting down".to_string()),
+            }),
+        ));
+    }
     let result = connection
         .run(move |conn| {
             diesel::select(diesel::dsl::sql::<Int4>("1")) // SELECT 1;
@@ -137,6 +183,7 @@ async fn health(
             let env_version = env::var("VERSION").unwrap_or("0.3.0-beta.3".into());
             let version = ServerVersion {
                 version: env_version,
+                schema_version: schema_version.0.clone(),
             };
             Ok(Json(version))
         }
@@ -186,6 +233,11 @@ impl Fairing for CORS {
             "POST, GET, PATCH, OPTIONS, DELETE",
         ));
         response.set_header(Header::new("Access-Control-Allow-Headers", "*"));
+        // browser-based OAuth clients have to read the nonce to retry with it
+        response.set_header(Header::new(
+            "Access-Control-Expose-Headers",
+            "DPoP-Nonce, WWW-Authenticate",
+        ));
         response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
     }
 }
@@ -209,10 +261,24 @@ pub async fn build_rocket(cfg: Option<RocketConfig>) -> Rocket<Build> {
         "timeout" => 30.into(),
     };
 
-    let figment = rocket::Config::figment()
-        .merge(("databases", map!["pg_db" => db]))
-        .merge(("limits", Limits::default().limit("file", 100.mebibytes())));
     let cfg = env_to_cfg();
+    // rocket's grace period covers draining writes and flushing the firehose
+    let shutdown_grace = cfg.shutdown.grace + cfg.shutdown.flush_window_ms.div_ceil(1000);
+
+    let mut figment = rocket::Config::figment()
+        .merge(("databases", map!["pg_db" => db]))
+        .merge(("limits", Limits::default().limit("file", 100.mebibytes())))
+        .merge(("shutdown", map!["grace" => shutdown_grace]))
+        // client addresses come from `client_ip`, which only believes trusted proxies
+        .merge(("ip_header", false));
+    if let Some(ca_certs) = &cfg.admin.mtls_ca {
+        // optional during the handshake, so only admin routes turn away clients without one
+        let mutual: Map<_, Value> = map! {
+            "ca_certs" => ca_certs.clone().into(),
+            "mandatory" => false.into(),
+        };
+        figment = figment.merge(("tls.mutual", mutual));
+    }
 
     let sequencer = SharedSequencer {
         sequencer: RwLock::new(Sequencer::new(
@@ -221,21 +287,99 @@ pub async fn build_rocket(cfg: Option<RocketConfig>) -> Rocket<Build> {
         )),
     };
     let mut background_sequencer = sequencer.sequencer.write().await.clone();
-    tokio::spawn(async move { background_sequencer.start().await });
+    let shutdown_state = ShutdownState::default();
+    let maintenance_state = MaintenanceState::new(cfg.maintenance.read_only);
+    let route_flags = RouteFlags::new(cfg.route_flags.disabled.clone());
+    let handle_policy = HandlePolicy::new(cfg.handle_policy.clone());
+    shutdown_state.register_background_job(
+        "sequencer",
+        tokio::spawn(async move { background_sequencer.start().await }).abort_handle(),
+    );
+    if let Some(seq_coalesce) = &cfg.seq_coalesce {
+        shutdown_state.register_background_job(
+            "seq_writer",
+            tokio::spawn(coalesce::set_seq_writer(
+                seq_coalesce.clone(),
+                sequencer.sequencer.read().await.crawlers.clone(),
+            ))
+            .abort_handle(),
+        );
+    }
+    // runs even without rules so takedowns from since-removed rules still expire
+    shutdown_state.register_background_job(
+        "moderation_sweeper",
+        tokio::spawn(moderation::run_takedown_sweeper(
+            cfg.moderation.sweep_interval,
+            sequencer.sequencer.read().await.clone(),
+        ))
+        .abort_handle(),
+    );
 
-    let aws_sdk_config = aws_config::from_env()
-        .endpoint_url(env::var("AWS_ENDPOINT").unwrap_or("localhost".to_owned()))
-        .load()
-        .await;
+    let client_ip_resolver = ClientIpResolver {
+        trusted_proxies: cfg.client_ip.trusted_proxies.clone(),
+        proxied: ProxiedConnections::default(),
+    };
+    if let Some(port) = cfg.client_ip.proxy_protocol_port {
+        let rocket_cfg: rocket::Config = figment.extract().expect("invalid rocket config");
+        let upstream = match rocket_cfg.address.is_unspecified() {
+            true => std::net::SocketAddr::new(Ipv4Addr::LOCALHOST.into(), rocket_cfg.port),
+            false => std::net::SocketAddr::new(rocket_cfg.address, rocket_cfg.port),
+        };
+        shutdown_state.register_background_job(
+            "proxy_protocol_listener",
+            tokio::spawn(client_ip::run_proxy_protocol_listener(
+                port,
+                upstream,
+                client_ip_resolver.trusted_proxies.clone(),
+                client_ip_resolver.proxied.clone(),
+            ))
+            .abort_handle(),
+        );
+    }
+
+    let aws_sdk_config = load_sdk_config().await;
+    if let Some(seq_archive) = &cfg.seq_archive {
+        shutdown_state.register_background_job(
+            "seq_archiver",
+            tokio::spawn(archive::run_archiver(
+                seq_archive.interval,
+                SeqArchive::new(seq_archive, &aws_sdk_config),
+            ))
+            .abort_handle(),
+        );
+    }
+    let service_keys = ServiceKeys::load(Some(&aws_sdk_config))
+        .await
+        .expect("Invalid repo signing or PLC rotation key");
+    keys::set_service_keys(service_keys);
+    webhooks::set_webhooks(cfg.webhooks.clone());
+    if let Some(spam_scoring) = &cfg.spam_scoring {
+        spam::set_spam_hook(
+            spam_scoring.clone(),
+            Arc::new(spam::HttpSpamScorer::new(spam_scoring.url.clone())),
+        );
+    }
+    set_write_rate_limits(cfg.write_rate_limits.clone());
+    set_actor_store_config(cfg.actor_store.clone());
 
     let id_resolver = SharedIdResolver {
         id_resolver: RwLock::new(IdResolver::new(IdentityResolverOpts {
-            timeout: None,
-            plc_url: Some(
-                env::var("PDS_DID_PLC_URL").unwrap_or("https://plc.directory".to_owned()),
+            timeout: Some(Duration::from_millis(cfg.identity.resolver_timeout)),
+            plc_url: Some(cfg.identity.plc_url.clone()),
+            did_cache: Some(
+                DidCache::new(
+                    Some(Duration::from_millis(cfg.identity.cache_state_ttl)),
+                    Some(Duration::from_millis(cfg.identity.cache_max_ttl)),
+                )
+                .with_negative_ttl(Duration::from_millis(cfg.identity.cache_negative_ttl)),
             ),
-            did_cache: Some(DidCache::new(None, None)),
-            backup_nameservers: Some(env_list("PDS_HANDLE_BACKUP_NAMESERVERS")),
+            handle_cache: Some(HandleCache::new(
+                Some(Duration::from_millis(cfg.identity.handle_cache_ttl)),
+                Some(Duration::from_millis(
+                    cfg.identity.handle_cache_negative_ttl,
+                )),
+            )),
+            backup_nameservers: cfg.identity.handle_backup_name_servers.clone(),
         })),
     };
 
@@ -281,23 +425,46 @@ pub async fn build_rocket(cfg: Option<RocketConfig>) -> Rocket<Build> {
     };
 
     let shield = Shield::default().enable(NoSniff::Enable);
+    let rate_limiter = match &cfg.rate_limits {
+        Some(rate_limits) => {
+            RateLimiter::new(rate_limits).expect("invalid PDS_RATE_LIMITS_REDIS_URL")
+        }
+        None => RateLimiter::default(),
+    };
 
     rocket::custom(figment)
         .mount(
             "/",
-            routes![
+            traced(routes![
                 index,
                 robots,
                 health,
                 com::atproto::admin::delete_account::delete_account,
                 com::atproto::admin::disable_account_invites::disable_account_invites,
+                xyz::blackskyweb::admin::add_reserved_handle::add_reserved_handle,
                 com::atproto::admin::disable_invite_codes::disable_invite_codes,
                 com::atproto::admin::enable_account_invites::enable_account_invites,
+                xyz::blackskyweb::admin::erase_personal_data::erase_personal_data,
                 com::atproto::admin::get_account_info::get_account_info,
+                xyz::blackskyweb::admin::get_deleted_records::get_deleted_records,
                 com::atproto::admin::get_invite_codes::get_invite_codes,
+                xyz::blackskyweb::admin::get_invite_referral_tree::get_invite_referral_tree,
+                xyz::blackskyweb::admin::get_maintenance_mode::get_maintenance_mode,
+                xyz::blackskyweb::admin::get_personal_data::get_personal_data,
+                xyz::blackskyweb::admin::get_repo_commit_history::get_repo_commit_history,
+                xyz::blackskyweb::admin::get_reserved_handles::get_reserved_handles,
+                xyz::blackskyweb::admin::get_route_flags::get_route_flags,
+                xyz::blackskyweb::admin::purge_identity_cache::purge_identity_cache,
+                xyz::blackskyweb::admin::remove_reserved_handle::remove_reserved_handle,
+                xyz::blackskyweb::admin::repair_record_blobs::repair_record_blobs,
+                xyz::blackskyweb::admin::reset_totp::reset_totp,
+                xyz::blackskyweb::admin::rotate_account_keys::rotate_account_keys,
+                xyz::blackskyweb::admin::squash_repo::squash_repo,
                 com::atproto::admin::get_subject_status::get_subject_status,
                 com::atproto::admin::send_email::send_email,
                 com::atproto::admin::update_account_password::update_account_password,
+                xyz::blackskyweb::admin::update_maintenance_mode::update_maintenance_mode,
+                xyz::blackskyweb::admin::update_route_flag::update_route_flag,
                 com::atproto::admin::update_account_email::update_account_email,
                 com::atproto::admin::update_account_handle::update_account_handle,
                 com::atproto::admin::update_subject_status::update_subject_status,
@@ -307,6 +474,7 @@ pub async fn build_rocket(cfg: Option<RocketConfig>) -> Rocket<Build> {
                 com::atproto::identity::get_recommended_did_credentials::get_recommended_did_credentials,
                 com::atproto::identity::request_plc_operation_signature::request_plc_operation_signature,
                 com::atproto::identity::submit_plc_operation::submit_plc_operation,
+                com::atproto::moderation::create_report::create_report,
                 com::atproto::repo::apply_writes::apply_writes,
                 com::atproto::repo::create_record::create_record,
                 com::atproto::repo::delete_record::delete_record,
@@ -318,29 +486,38 @@ pub async fn build_rocket(cfg: Option<RocketConfig>) -> Rocket<Build> {
                 com::atproto::repo::put_record::put_record,
                 com::atproto::repo::upload_blob::upload_blob,
                 com::atproto::server::confirm_email::confirm_email,
+                xyz::blackskyweb::server::confirm_totp::confirm_totp,
                 com::atproto::server::create_account::server_create_account,
                 com::atproto::server::create_app_password::create_app_password,
                 com::atproto::server::create_invite_code::create_invite_code,
                 com::atproto::server::create_invite_codes::create_invite_codes,
                 com::atproto::server::create_session::create_session,
+                xyz::blackskyweb::server::create_totp_secret::create_totp_secret,
                 com::atproto::server::deactivate_account::deactivate_account,
                 com::atproto::server::delete_account::delete_account,
                 com::atproto::server::delete_session::delete_session,
                 com::atproto::server::describe_server::describe_server,
+                xyz::blackskyweb::server::disable_totp::disable_totp,
                 com::atproto::server::check_account_status::check_account_status,
                 com::atproto::server::activate_account::activate_account,
                 com::atproto::server::get_service_auth::get_service_auth,
+                xyz::blackskyweb::server::get_account_export::get_account_export,
                 com::atproto::server::get_account_invite_codes::get_account_invite_codes,
                 com::atproto::server::get_session::get_session,
                 com::atproto::server::list_app_passwords::list_app_passwords,
+                xyz::blackskyweb::server::list_login_attempts::list_login_attempts,
+                xyz::blackskyweb::server::list_sessions::list_sessions,
                 com::atproto::server::refresh_session::refresh_session,
                 com::atproto::server::request_account_delete::request_account_delete,
+                xyz::blackskyweb::server::request_account_export::request_account_export,
                 com::atproto::server::request_email_confirmation::request_email_confirmation,
                 com::atproto::server::request_email_update::request_email_update,
                 com::atproto::server::request_password_reset::request_password_reset,
                 com::atproto::server::reset_password::reset_password,
                 com::atproto::server::revoke_app_password::revoke_app_password,
                 com::atproto::server::update_email::update_email,
+                xyz::blackskyweb::server::update_email_preference::update_email_preference,
+                xyz::blackskyweb::server::update_locale::update_locale,
                 com::atproto::server::reserve_signing_key::reserve_signing_key,
                 com::atproto::sync::get_blob::get_blob,
                 com::atproto::sync::get_blocks::get_blocks,
@@ -350,7 +527,12 @@ pub async fn build_rocket(cfg: Option<RocketConfig>) -> Rocket<Build> {
                 com::atproto::sync::get_repo_status::get_repo_status,
                 com::atproto::sync::list_blobs::list_blobs,
                 com::atproto::sync::list_repos::list_repos,
+                com::atproto::sync::list_repos_by_collection::list_repos_by_collection,
                 com::atproto::sync::subscribe_repos::subscribe_repos,
+                com::atproto::temp::check_handle_availability::check_handle_availability,
+                com::atproto::temp::check_signup_queue::check_signup_queue,
+                com::atproto::temp::fetch_labels::fetch_labels,
+                com::atproto::temp::request_phone_verification::request_phone_verification,
                 app::bsky::actor::get_preferences::get_preferences,
                 app::bsky::actor::get_profile::get_profile,
                 app::bsky::actor::get_profiles::get_profiles,
@@ -364,13 +546,109 @@ pub async fn build_rocket(cfg: Option<RocketConfig>) -> Rocket<Build> {
                 bsky_api_get_forwarder,
                 bsky_api_post_forwarder,
                 well_known::well_known,
+                well_known::oauth_authorization_server,
+                well_known::oauth_protected_resource,
+                oauth::routes::par,
+                oauth::routes::authorize,
+                oauth::routes::authorize_post,
+                oauth::routes::token,
+                oauth::routes::revoke,
+                mailer::webhook::mailgun_webhook,
+                mailer::webhook::ses_webhook,
                 all_options
-            ],
+            ]),
         )
         .register("/", catchers![default_catcher])
         .attach(CORS)
+        .attach(RequestIds)
         .attach(DbConn::fairing())
+        .attach(AdHoc::try_on_ignite(
+            "Run database migrations",
+            migrations::migrate_on_ignite,
+        ))
+        .attach(AdHoc::on_liftoff("Refresh reserved handles", |rocket| {
+            Box::pin(async move {
+                let (Some(db), Some(cfg), Some(handle_policy), Some(shutdown_state)) = (
+                    DbConn::get_one(rocket).await,
+                    rocket.state::<ServerConfig>(),
+                    rocket.state::<HandlePolicy>(),
+                    rocket.state::<ShutdownState>(),
+                ) else {
+                    tracing::error!("@LOG: ERROR: can't refresh reserved handles");
+                    return;
+                };
+                shutdown_state.register_background_job(
+                    "reserved_handles_refresher",
+                    tokio::spawn(policy::run_refresher(
+                        cfg.handle_policy.refresh_interval,
+                        handle_policy.clone(),
+                        db,
+                    ))
+                    .abort_handle(),
+                );
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Refresh route flags", |rocket| {
+            Box::pin(async move {
+                let (Some(db), Some(cfg), Some(route_flags), Some(shutdown_state)) = (
+                    DbConn::get_one(rocket).await,
+                    rocket.state::<ServerConfig>(),
+                    rocket.state::<RouteFlags>(),
+                    rocket.state::<ShutdownState>(),
+                ) else {
+                    tracing::error!("@LOG: ERROR: can't refresh route flags");
+                    return;
+                };
+                shutdown_state.register_background_job(
+                    "route_flags_refresher",
+                    tokio::spawn(route_flags::run_refresher(
+                        cfg.route_flags.refresh_interval,
+                        route_flags.clone(),
+                        db,
+                    ))
+                    .abort_handle(),
+                );
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Retry deferred indexing", |rocket| {
+            Box::pin(async move {
+                let (Some(db), Some(s3_config), Some(shutdown_state)) = (
+                    DbConn::get_one(rocket).await,
+                    rocket.state::<SdkConfig>(),
+                    rocket.state::<ShutdownState>(),
+                ) else {
+                    tracing::error!("@LOG: ERROR: can't retry deferred indexing");
+                    return;
+                };
+                shutdown_state.register_background_job(
+                    "pending_indexing",
+                    tokio::spawn(actor_store::retry_pending_indexing(db, s3_config.clone()))
+                        .abort_handle(),
+                );
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Backfill record value index", |rocket| {
+            Box::pin(async move {
+                let (Some(db), Some(shutdown_state)) = (
+                    DbConn::get_one(rocket).await,
+                    rocket.state::<ShutdownState>(),
+                ) else {
+                    tracing::error!("@LOG: ERROR: can't backfill record value index");
+                    return;
+                };
+                shutdown_state.register_background_job(
+                    "record_value_index_backfill",
+                    tokio::spawn(value_index::backfill(db)).abort_handle(),
+                );
+            })
+        }))
         .attach(shield)
+        .attach(ReadOnlyMode)
+        .attach(DisabledRoutes)
+        .attach(rate_limiter)
+        .attach(GracefulShutdown {
+            cfg: cfg.shutdown.clone(),
+        })
         .manage(sequencer)
         .manage(aws_sdk_config)
         .manage(id_resolver)
@@ -378,4 +656,9 @@ pub async fn build_rocket(cfg: Option<RocketConfig>) -> Rocket<Build> {
         .manage(local_viewer)
         .manage(app_view_agent)
         .manage(account_manager)
+        .manage(shutdown_state)
+        .manage(maintenance_state)
+        .manage(route_flags)
+        .manage(handle_policy)
+        .manage(client_ip_resolver)
 }
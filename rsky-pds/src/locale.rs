@@ -0,0 +1,237 @@
+//! Translations of user-facing strings: email subjects and Mailgun template names, and the
+//! fixed messages of xrpc errors. English is built in; other languages come from catalogs in
+//! `PDS_LOCALE_DIR`, one flat JSON object of key to text per locale, e.g. `de.json` or
+//! `pt-BR.json`. Keys missing from a catalog fall back to English.
+//!
+//! Mail goes out in the account's chosen locale if it has one, else in the best match for the
+//! request's `Accept-Language`; errors always use the latter. Without either, the PDS uses
+//! `PDS_DEFAULT_LOCALE` (English unless set).
+
+use anyhow::Result;
+use lazy_static::lazy_static;
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+use rsky_common::env::env_str;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fs;
+use std::path::Path;
+
+/// The language of the built-in strings.
+pub const BUILT_IN_LOCALE: &str = "en";
+
+lazy_static! {
+    static ref CATALOGS: Catalogs = match env_str("PDS_LOCALE_DIR") {
+        Some(dir) if !dir.is_empty() => Catalogs::load(Path::new(&dir)),
+        _ => Catalogs::default(),
+    };
+    static ref DEFAULT_LOCALE: String = env_str("PDS_DEFAULT_LOCALE")
+        .and_then(|locale| normalize(&locale))
+        .unwrap_or_else(|| BUILT_IN_LOCALE.to_string());
+}
+
+/// Lowercases `tag` and swaps `_` for `-`, so `pt_BR` and `pt-br` both become `pt-br`. None when
+/// it isn't shaped like a language tag.
+pub fn normalize(tag: &str) -> Option<String> {
+    let tag = tag.trim().replace('_', "-").to_lowercase();
+    let mut subtags = tag.split('-');
+    let language = subtags.next()?;
+    let valid = (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        });
+    valid.then_some(tag)
+}
+
+fn primary_language(tag: &str) -> &str {
+    tag.split('-').next().unwrap_or(tag)
+}
+
+/// Translations by normalized locale.
+#[derive(Debug, Default)]
+pub struct Catalogs {
+    by_locale: HashMap<String, HashMap<String, String>>,
+}
+
+impl Catalogs {
+    /// Reads every `<locale>.json` in `dir`, skipping (and logging) the ones that don't parse.
+    pub fn load(dir: &Path) -> Self {
+        let mut catalogs = Catalogs::default();
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(error) => {
+                tracing::error!("@LOG: ERROR: failed to read locale dir {dir:?}: {error}");
+                return catalogs;
+            }
+        };
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            if path.extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let Some(locale) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(normalize)
+            else {
+                tracing::warn!("@LOG: skipping locale file {path:?}: not named after a locale");
+                continue;
+            };
+            match read_catalog(&path) {
+                Ok(catalog) => {
+                    catalogs.by_locale.insert(locale, catalog);
+                }
+                Err(error) => {
+                    tracing::error!("@LOG: ERROR: failed to load locale file {path:?}: {error}")
+                }
+            }
+        }
+        catalogs
+    }
+
+    pub fn insert(&mut self, locale: &str, catalog: HashMap<String, String>) {
+        if let Some(locale) = normalize(locale) {
+            self.by_locale.insert(locale, catalog);
+        }
+    }
+
+    fn supports(&self, locale: &str) -> bool {
+        self.by_locale.contains_key(locale) || primary_language(locale) == BUILT_IN_LOCALE
+    }
+
+    /// The text for `key` in `locale`, or in its primary language when there's no catalog for
+    /// the region, e.g. `de` for `de-at`.
+    pub fn lookup(&self, locale: &str, key: &str) -> Option<&str> {
+        [locale, primary_language(locale)]
+            .into_iter()
+            .find_map(|locale| self.by_locale.get(locale)?.get(key))
+            .map(String::as_str)
+    }
+
+    /// The best locale we have for an `Accept-Language` header, honouring its q-values.
+    pub fn negotiate(&self, accept_language: &str) -> Option<String> {
+        let mut ranges: Vec<(f32, String)> = accept_language
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = normalize(parts.next()?)?;
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                (quality > 0.0).then_some((quality, tag))
+            })
+            .collect();
+        // stable, so equally weighted ranges keep the client's order
+        ranges.sort_by(|a, b| b.0.total_cmp(&a.0));
+        ranges.into_iter().find_map(|(_, tag)| {
+            if self.supports(&tag) {
+                Some(tag)
+            } else {
+                let language = primary_language(&tag);
+                self.supports(language).then(|| language.to_string())
+            }
+        })
+    }
+
+    /// `key` in `locale` (or the default locale), falling back to the built-in `english`.
+    pub fn translate(&self, locale: Option<&str>, key: &str, english: &str) -> String {
+        let locale = locale.unwrap_or(DEFAULT_LOCALE.as_str());
+        self.lookup(locale, key).unwrap_or(english).to_string()
+    }
+}
+
+fn read_catalog(path: &Path) -> Result<HashMap<String, String>> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+/// [`Catalogs::translate`] against the catalogs from `PDS_LOCALE_DIR`.
+pub fn translate(locale: Option<&str>, key: &str, english: &str) -> String {
+    CATALOGS.translate(locale, key, english)
+}
+
+/// [`Catalogs::negotiate`] against the catalogs from `PDS_LOCALE_DIR`.
+pub fn negotiate(accept_language: &str) -> Option<String> {
+    CATALOGS.negotiate(accept_language)
+}
+
+/// The best locale for the request's `Accept-Language`, if it named one we have.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RequestLocale(pub Option<String>);
+
+impl RequestLocale {
+    pub fn of(req: &Request<'_>) -> Self {
+        RequestLocale(req.headers().get_one("Accept-Language").and_then(negotiate))
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestLocale {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(RequestLocale::of(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalogs() -> Catalogs {
+        let mut catalogs = Catalogs::default();
+        catalogs.insert(
+            "de",
+            HashMap::from([("greeting".to_string(), "Hallo".to_string())]),
+        );
+        catalogs.insert(
+            "pt_BR",
+            HashMap::from([("greeting".to_string(), "Olá".to_string())]),
+        );
+        catalogs
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("pt_BR"), Some("pt-br".to_string()));
+        assert_eq!(normalize(" EN "), Some("en".to_string()));
+        assert_eq!(normalize("zh-Hant-TW"), Some("zh-hant-tw".to_string()));
+        assert_eq!(normalize("*"), None);
+        assert_eq!(normalize("../de"), None);
+        assert_eq!(normalize("english"), None);
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let catalogs = catalogs();
+        assert_eq!(
+            catalogs.negotiate("de-AT, en;q=0.5"),
+            Some("de".to_string())
+        );
+        assert_eq!(
+            catalogs.negotiate("fr, pt-BR;q=0.9"),
+            Some("pt-br".to_string())
+        );
+        assert_eq!(
+            catalogs.negotiate("de;q=0.2, en-GB;q=0.8"),
+            Some("en-gb".to_string())
+        );
+        assert_eq!(catalogs.negotiate("de;q=0, fr"), None);
+        assert_eq!(catalogs.negotiate("*"), None);
+    }
+
+    #[test]
+    fn test_translate() {
+        let catalogs = catalogs();
+        assert_eq!(
+            catalogs.translate(Some("de-at"), "greeting", "Hello"),
+            "Hallo"
+        );
+        assert_eq!(
+            catalogs.translate(Some("pt-br"), "greeting", "Hello"),
+            "Olá"
+        );
+        assert_eq!(catalogs.translate(Some("pt"), "greeting", "Hello"), "Hello");
+        assert_eq!(catalogs.translate(Some("de"), "farewell", "Bye"), "Bye");
+        assert_eq!(catalogs.translate(Some("en"), "greeting", "Hello"), "Hello");
+    }
+}
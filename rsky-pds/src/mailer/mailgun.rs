@@ -0,0 +1,75 @@
+use crate::http::{self, Destination};
+use anyhow::Result;
+use mailgun_rs::{EmailAddress, Message};
+use rsky_common::env::{env_bool, env_str};
+use std::collections::HashMap;
+use std::env;
+
+const MAILGUN_API_URL: &str = "https://api.mailgun.net/v3";
+
+/// Sends a message through the Mailgun messages API, like `mailgun_rs::Mailgun::async_send`
+/// but with DKIM options the crate doesn't expose:
+/// - `PDS_EMAIL_DKIM`: have Mailgun sign with the sending domain's key (default true).
+/// - `PDS_EMAIL_DKIM_SECONDARY`: `domain/selector` for a second signature, e.g. the PDS
+///   hostname when sending through a shared Mailgun domain.
+pub async fn send(message: Message, sender: &EmailAddress) -> Result<()> {
+    let api_key = env::var("PDS_MAILGUN_API_KEY")?;
+    let domain = env::var("PDS_MAILGUN_DOMAIN")?;
+
+    let mut params = message_params(message);
+    params.insert("from".to_string(), sender.to_string());
+    let dkim = env_bool("PDS_EMAIL_DKIM").unwrap_or(true);
+    params.insert("o:dkim".to_string(), yes_no(dkim).to_string());
+    if let Some(secondary) = env_str("PDS_EMAIL_DKIM_SECONDARY") {
+        params.insert("o:secondary-dkim".to_string(), secondary);
+    }
+
+    http::post(
+        Destination::Mailer,
+        format!("{MAILGUN_API_URL}/{domain}/messages"),
+    )
+    .basic_auth("api", Some(api_key))
+    .form(&params)
+    .send()
+    .await?
+    .error_for_status()?;
+    Ok(())
+}
+
+fn message_params(message: Message) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    let recipients = [("to", message.to), ("cc", message.cc), ("bcc", message.bcc)];
+    for (field, addresses) in recipients {
+        if !addresses.is_empty() {
+            let joined = addresses
+                .iter()
+                .map(EmailAddress::to_string)
+                .collect::<Vec<String>>()
+                .join(",");
+            params.insert(field.to_string(), joined);
+        }
+    }
+    params.insert("subject".to_string(), message.subject);
+    params.insert("text".to_string(), message.text);
+    params.insert("html".to_string(), message.html);
+    if !message.template.is_empty() {
+        params.insert("template".to_string(), message.template);
+        let variables = match message.template_json {
+            Some(template_json) => serde_json::to_string(&template_json),
+            None => serde_json::to_string(&message.template_vars),
+        };
+        params.insert(
+            "h:X-Mailgun-Variables".to_string(),
+            variables.unwrap_or_default(),
+        );
+    }
+    params
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
@@ -1,12 +1,24 @@
+pub mod mailgun;
 pub mod moderation;
+pub mod webhook;
 
 extern crate mailgun_rs;
 
+use crate::locale;
 use anyhow::Result;
-use mailgun_rs::{EmailAddress, Mailgun, MailgunRegion, Message};
+use mailgun_rs::{EmailAddress, Message};
 use std::collections::HashMap;
 use std::env;
 
+/// What an email is for, checked against the recipient's email preference before sending.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmailCategory {
+    /// Tokens for password resets, email changes, account deletion and identity operations.
+    Security,
+    /// Everything else, e.g. messages from moderators.
+    Notification,
+}
+
 pub struct MailOpts {
     pub to: String,
     pub subject: String,
@@ -42,77 +54,120 @@ pub async fn send_template(opts: MailOpts) -> Result<()> {
         ..Default::default()
     };
 
-    let client = Mailgun {
-        api_key: env::var("PDS_MAILGUN_API_KEY").unwrap(),
-        domain: env::var("PDS_MAILGUN_DOMAIN").unwrap(),
-        message,
-    };
     let sender = EmailAddress::name_address(
         &env::var("PDS_EMAIL_FROM_NAME").unwrap(),
         &env::var("PDS_EMAIL_FROM_ADDRESS").unwrap(),
     );
 
-    client.async_send(MailgunRegion::US, &sender).await?;
-    Ok(())
+    mailgun::send(message, &sender).await
+}
+
+/// Sends the mail `key` in `locale`: its subject and Mailgun template come from the locale
+/// catalogs' `email.<key>.subject` and `email.<key>.template`, defaulting to the English ones.
+/// The locale is also passed on as the `locale` variable, for templates that branch on it.
+async fn send_localized(
+    to: String,
+    locale: Option<&str>,
+    key: &str,
+    subject: &str,
+    template: &str,
+    mut template_vars: HashMap<String, String>,
+) -> Result<()> {
+    if let Some(locale) = locale {
+        template_vars.insert("locale".to_string(), locale.to_string());
+    }
+    send_template(MailOpts {
+        to,
+        subject: locale::translate(locale, &format!("email.{key}.subject"), subject),
+        template: locale::translate(locale, &format!("email.{key}.template"), template),
+        template_vars,
+    })
+    .await
 }
 
-pub async fn send_reset_password(to: String, params: IdentifierAndTokenParams) -> Result<()> {
+pub async fn send_reset_password(
+    to: String,
+    params: IdentifierAndTokenParams,
+    locale: Option<&str>,
+) -> Result<()> {
     let mut template_vars = HashMap::new();
     template_vars.insert("identifier".to_string(), params.identifier);
     template_vars.insert("token".to_string(), params.token);
-    send_template(MailOpts {
+    send_localized(
         to,
-        subject: "Password Reset Requested".to_string(),
-        template: "reset password".to_string(),
+        locale,
+        "reset_password",
+        "Password Reset Requested",
+        "reset password",
         template_vars,
-    })
+    )
     .await
 }
 
-pub async fn send_account_delete(to: String, params: TokenParam) -> Result<()> {
+pub async fn send_account_delete(
+    to: String,
+    params: TokenParam,
+    locale: Option<&str>,
+) -> Result<()> {
     let mut template_vars = HashMap::new();
     template_vars.insert("token".to_string(), params.token);
-    send_template(MailOpts {
+    send_localized(
         to,
-        subject: "Account Deletion Requested".to_string(),
-        template: "delete account".to_string(),
+        locale,
+        "delete_account",
+        "Account Deletion Requested",
+        "delete account",
         template_vars,
-    })
+    )
     .await
 }
 
-pub async fn send_confirm_email(to: String, params: TokenParam) -> Result<()> {
+pub async fn send_confirm_email(
+    to: String,
+    params: TokenParam,
+    locale: Option<&str>,
+) -> Result<()> {
     let mut template_vars = HashMap::new();
     template_vars.insert("token".to_string(), params.token);
-    send_template(MailOpts {
+    send_localized(
         to,
-        subject: "Email Confirmation".to_string(),
-        template: "confirm email".to_string(),
+        locale,
+        "confirm_email",
+        "Email Confirmation",
+        "confirm email",
         template_vars,
-    })
+    )
     .await
 }
 
-pub async fn send_update_email(to: String, params: TokenParam) -> Result<()> {
+pub async fn send_update_email(to: String, params: TokenParam, locale: Option<&str>) -> Result<()> {
     let mut template_vars = HashMap::new();
     template_vars.insert("token".to_string(), params.token);
-    send_template(MailOpts {
+    send_localized(
         to,
-        subject: "Email Update Requested".to_string(),
-        template: "email update".to_string(),
+        locale,
+        "update_email",
+        "Email Update Requested",
+        "email update",
         template_vars,
-    })
+    )
     .await
 }
 
-pub async fn send_plc_operation(to: String, params: TokenParam) -> Result<()> {
+pub async fn send_plc_operation(
+    to: String,
+    params: TokenParam,
+    locale: Option<&str>,
+) -> Result<()> {
     let mut template_vars = HashMap::new();
     template_vars.insert("token".to_string(), params.token);
-    send_template(MailOpts {
+    send_localized(
         to,
-        subject: "PLC Update Operation Requested".to_string(),
-        template: "plc operation".to_string(),
+        locale,
+        "plc_operation",
+        "PLC Update Operation Requested",
+        "plc operation",
         template_vars,
-    })
+    )
     .await
 }
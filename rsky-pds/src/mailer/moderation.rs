@@ -1,5 +1,6 @@
+use crate::mailer::mailgun;
 use anyhow::Result;
-use mailgun_rs::{EmailAddress, Mailgun, MailgunRegion, Message};
+use mailgun_rs::{EmailAddress, Message};
 use std::env;
 
 pub struct HtmlMailOpts {
@@ -22,17 +23,11 @@ impl ModerationMailer {
             ..Default::default()
         };
 
-        let client = Mailgun {
-            api_key: env::var("PDS_MAILGUN_API_KEY").unwrap(),
-            domain: env::var("PDS_MAILGUN_DOMAIN").unwrap(),
-            message,
-        };
         let sender = EmailAddress::name_address(
             &env::var("PDS_MODERATION_EMAIL_FROM_NAME").unwrap(),
             &env::var("PDS_MODERATION_EMAIL_FROM_ADDRESS").unwrap(),
         );
 
-        client.async_send(MailgunRegion::US, &sender).await?;
-        Ok(())
+        mailgun::send(message, &sender).await
     }
 }
@@ -0,0 +1,454 @@
+//! Bounce and complaint webhooks. Hard bounces and spam complaints mark the recipient
+//! undeliverable so token emails stop going to dead addresses.
+//!
+//! Mailgun events are HMAC signed, and each signature is accepted once and only while fresh.
+//! SNS messages are signed with a certificate AWS serves from `sns.<region>.amazonaws.com`.
+
+use crate::account_manager::AccountManager;
+use crate::http::{self, Destination};
+use anyhow::{anyhow, bail, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use rocket::data::{Data, ToByteUnit};
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rsa::pkcs1v15::{Signature, VerifyingKey};
+use rsa::pkcs8::DecodePublicKey;
+use rsa::signature::Verifier;
+use rsa::RsaPublicKey;
+use rsky_common::env::env_str;
+use sha1::Sha1;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use subtle::ConstantTimeEq;
+use url::Url;
+use x509_cert::der::{DecodePem, Encode};
+use x509_cert::Certificate;
+
+/// How far a Mailgun timestamp may be from our clock.
+const MAILGUN_MAX_AGE_SECS: i64 = 300;
+
+lazy_static! {
+    /// Mailgun tokens seen within `MAILGUN_MAX_AGE_SECS`, with their timestamps.
+    static ref MAILGUN_TOKENS: Mutex<HashMap<String, i64>> = Mutex::new(HashMap::new());
+    /// SNS signing keys, by certificate URL.
+    static ref SNS_KEYS: Mutex<HashMap<String, RsaPublicKey>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MailgunSignature {
+    pub timestamp: String,
+    pub token: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MailgunEventData {
+    pub event: String,
+    pub severity: Option<String>,
+    pub recipient: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MailgunWebhook {
+    pub signature: MailgunSignature,
+    #[serde(rename = "event-data")]
+    pub event_data: MailgunEventData,
+}
+
+/// SNS envelope around an SES notification.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SnsMessage {
+    pub r#type: String,
+    pub message: String,
+    pub message_id: String,
+    pub subject: Option<String>,
+    pub timestamp: String,
+    pub topic_arn: String,
+    pub token: Option<String>,
+    #[serde(rename = "SubscribeURL")]
+    pub subscribe_url: Option<String>,
+    pub signature_version: String,
+    pub signature: String,
+    #[serde(rename = "SigningCertURL")]
+    pub signing_cert_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SesRecipient {
+    #[serde(rename = "emailAddress")]
+    pub email_address: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SesBounce {
+    #[serde(rename = "bounceType")]
+    pub bounce_type: String,
+    #[serde(rename = "bouncedRecipients")]
+    pub bounced_recipients: Vec<SesRecipient>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SesComplaint {
+    #[serde(rename = "complainedRecipients")]
+    pub complained_recipients: Vec<SesRecipient>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SesNotification {
+    #[serde(rename = "notificationType")]
+    pub notification_type: String,
+    pub bounce: Option<SesBounce>,
+    pub complaint: Option<SesComplaint>,
+}
+
+pub fn verify_mailgun_signature(signing_key: &str, signature: &MailgunSignature) -> bool {
+    let Ok(expected) = hex::decode(&signature.signature) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(signing_key.as_bytes()) else {
+        return false;
+    };
+    mac.update(signature.timestamp.as_bytes());
+    mac.update(signature.token.as_bytes());
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// Accepts each signature's token once, and only within `MAILGUN_MAX_AGE_SECS` of `now`.
+pub fn check_mailgun_freshness(signature: &MailgunSignature, now: i64) -> bool {
+    let Ok(timestamp) = signature.timestamp.parse::<i64>() else {
+        return false;
+    };
+    if (now - timestamp).abs() > MAILGUN_MAX_AGE_SECS {
+        return false;
+    }
+    let mut seen = MAILGUN_TOKENS.lock().unwrap();
+    seen.retain(|_, at| (now - *at).abs() <= MAILGUN_MAX_AGE_SECS);
+    seen.insert(signature.token.clone(), timestamp).is_none()
+}
+
+/// Recipient and reason, if the event means the address should not be mailed again.
+pub fn mailgun_undeliverable(event: &MailgunEventData) -> Option<(String, String)> {
+    match (event.event.as_str(), event.severity.as_deref()) {
+        ("failed", Some("permanent")) => Some((
+            event.recipient.clone(),
+            format!("bounce: {}", event.reason.as_deref().unwrap_or("unknown")),
+        )),
+        ("complained", _) => Some((event.recipient.clone(), "complaint".to_string())),
+        _ => None,
+    }
+}
+
+/// Recipients and reasons for the addresses that should not be mailed again.
+pub fn ses_undeliverable(notification: &SesNotification) -> Vec<(String, String)> {
+    match (
+        notification.notification_type.as_str(),
+        &notification.bounce,
+        &notification.complaint,
+    ) {
+        ("Bounce", Some(bounce), _) if bounce.bounce_type == "Permanent" => bounce
+            .bounced_recipients
+            .iter()
+            .map(|r| (r.email_address.clone(), "bounce: permanent".to_string()))
+            .collect(),
+        ("Complaint", _, Some(complaint)) => complaint
+            .complained_recipients
+            .iter()
+            .map(|r| (r.email_address.clone(), "complaint".to_string()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// What SNS signs: the message's fields for its type, each name and value on a line of its own.
+fn sns_string_to_sign(sns: &SnsMessage) -> Result<String> {
+    let mut fields = vec![
+        ("Message", Some(&sns.message)),
+        ("MessageId", Some(&sns.message_id)),
+    ];
+    match sns.r#type.as_str() {
+        "Notification" => {
+            fields.push(("Subject", sns.subject.as_ref()));
+            fields.push(("Timestamp", Some(&sns.timestamp)));
+        }
+        "SubscriptionConfirmation" | "UnsubscribeConfirmation" => {
+            fields.push(("SubscribeURL", sns.subscribe_url.as_ref()));
+            fields.push(("Timestamp", Some(&sns.timestamp)));
+            fields.push(("Token", sns.token.as_ref()));
+        }
+        other => bail!("Unexpected SNS message type: {other}"),
+    }
+    fields.push(("TopicArn", Some(&sns.topic_arn)));
+    fields.push(("Type", Some(&sns.r#type)));
+    Ok(fields
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| format!("{name}\n{value}\n")))
+        .collect())
+}
+
+/// Certificates are only fetched over https from SNS itself.
+fn check_sns_cert_url(cert_url: &str) -> Result<Url> {
+    let url = Url::parse(cert_url)?;
+    let from_sns = url.host_str().is_some_and(|host| {
+        let region = host
+            .strip_prefix("sns.")
+            .and_then(|host| host.strip_suffix(".amazonaws.com"));
+        region.is_some_and(|region| {
+            !region.is_empty()
+                && region
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        })
+    });
+    if url.scheme() != "https" || !from_sns || !url.path().ends_with(".pem") {
+        bail!("Unexpected SNS SigningCertURL: {cert_url}");
+    }
+    Ok(url)
+}
+
+async fn sns_signing_key(cert_url: &str) -> Result<RsaPublicKey> {
+    if let Some(key) = SNS_KEYS.lock().unwrap().get(cert_url) {
+        return Ok(key.clone());
+    }
+    let url = check_sns_cert_url(cert_url)?;
+    let pem = http::get(Destination::Mailer, url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let cert = Certificate::from_pem(pem.as_bytes()).map_err(|error| anyhow!("{error}"))?;
+    let spki = cert
+        .tbs_certificate
+        .subject_public_key_info
+        .to_der()
+        .map_err(|error| anyhow!("{error}"))?;
+    let key = RsaPublicKey::from_public_key_der(&spki)?;
+    SNS_KEYS
+        .lock()
+        .unwrap()
+        .insert(cert_url.to_string(), key.clone());
+    Ok(key)
+}
+
+/// Checks the signature of an SNS message against its signing certificate.
+async fn verify_sns_signature(sns: &SnsMessage) -> Result<()> {
+    let string_to_sign = sns_string_to_sign(sns)?;
+    let signature = Signature::try_from(STANDARD.decode(&sns.signature)?.as_slice())?;
+    let key = sns_signing_key(&sns.signing_cert_url).await?;
+    match sns.signature_version.as_str() {
+        "1" => VerifyingKey::<Sha1>::new(key).verify(string_to_sign.as_bytes(), &signature)?,
+        "2" => VerifyingKey::<Sha256>::new(key).verify(string_to_sign.as_bytes(), &signature)?,
+        other => bail!("Unsupported SNS SignatureVersion: {other}"),
+    }
+    Ok(())
+}
+
+async fn confirm_sns_subscription(subscribe_url: &str) -> Result<()> {
+    let url = Url::parse(subscribe_url)?;
+    match url.host_str() {
+        Some(host) if url.scheme() == "https" && host.ends_with(".amazonaws.com") => {
+            http::get(Destination::Mailer, url)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        }
+        _ => bail!("Unexpected SNS SubscribeURL: {subscribe_url}"),
+    }
+}
+
+async fn mark_all(
+    account_manager: &AccountManager,
+    undeliverable: Vec<(String, String)>,
+) -> Status {
+    for (email, reason) in undeliverable {
+        tracing::info!("@LOG: marking {email} undeliverable ({reason})");
+        if let Err(error) = account_manager
+            .mark_email_undeliverable(&email, &reason)
+            .await
+        {
+            tracing::error!("@LOG: ERROR: {error}");
+            return Status::InternalServerError;
+        }
+    }
+    Status::Ok
+}
+
+/// Mailgun `failed`/`complained` webhook, signed with `PDS_MAILGUN_WEBHOOK_SIGNING_KEY`.
+#[tracing::instrument(skip_all)]
+#[rocket::post("/webhooks/email/mailgun", format = "json", data = "<body>")]
+pub async fn mailgun_webhook(
+    body: Json<MailgunWebhook>,
+    account_manager: AccountManager,
+) -> Status {
+    let Some(signing_key) = env_str("PDS_MAILGUN_WEBHOOK_SIGNING_KEY") else {
+        return Status::NotFound;
+    };
+    let body = body.into_inner();
+    if !verify_mailgun_signature(&signing_key, &body.signature) {
+        return Status::Unauthorized;
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("timestamp in seconds since UNIX epoch")
+        .as_secs() as i64;
+    if !check_mailgun_freshness(&body.signature, now) {
+        tracing::warn!("@LOG: rejected stale or replayed Mailgun webhook");
+        return Status::Unauthorized;
+    }
+    let undeliverable = mailgun_undeliverable(&body.event_data)
+        .into_iter()
+        .collect();
+    mark_all(&account_manager, undeliverable).await
+}
+
+/// SES bounce/complaint notifications delivered by SNS. The subscription URL must carry
+/// `?token=` matching `PDS_SES_WEBHOOK_TOKEN`, every message must carry a valid SNS signature,
+/// and with `PDS_SES_WEBHOOK_TOPIC_ARN` set, come from that topic. SNS posts with a
+/// `text/plain` content type.
+#[tracing::instrument(skip_all)]
+#[rocket::post("/webhooks/email/ses?<token>", data = "<body>")]
+pub async fn ses_webhook(token: String, body: Data<'_>, account_manager: AccountManager) -> Status {
+    let Some(expected) = env_str("PDS_SES_WEBHOOK_TOKEN") else {
+        return Status::NotFound;
+    };
+    if !bool::from(token.as_bytes().ct_eq(expected.as_bytes())) {
+        return Status::Unauthorized;
+    }
+    // notifications carry the original mail's headers, so allow more than the default limit
+    let Ok(body) = body.open(256.kibibytes()).into_string().await else {
+        return Status::BadRequest;
+    };
+    let Ok(sns) = serde_json::from_str::<SnsMessage>(&body) else {
+        return Status::BadRequest;
+    };
+    if let Some(topic_arn) = env_str("PDS_SES_WEBHOOK_TOPIC_ARN") {
+        if sns.topic_arn != topic_arn {
+            return Status::Unauthorized;
+        }
+    }
+    if let Err(error) = verify_sns_signature(&sns).await {
+        tracing::warn!("@LOG: rejected SNS message: {error}");
+        return Status::Unauthorized;
+    }
+    match sns.r#type.as_str() {
+        "SubscriptionConfirmation" => {
+            let Some(subscribe_url) = sns.subscribe_url else {
+                return Status::BadRequest;
+            };
+            match confirm_sns_subscription(&subscribe_url).await {
+                Ok(()) => Status::Ok,
+                Err(error) => {
+                    tracing::error!("@LOG: ERROR: {error}");
+                    Status::BadRequest
+                }
+            }
+        }
+        "Notification" => match serde_json::from_str::<SesNotification>(&sns.message) {
+            Ok(notification) => mark_all(&account_manager, ses_undeliverable(&notification)).await,
+            Err(_) => Status::BadRequest,
+        },
+        _ => Status::Ok,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_mailgun_signature() {
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"key-123").unwrap();
+        mac.update(b"1700000000");
+        mac.update(b"abcdef");
+        let mut signature = MailgunSignature {
+            timestamp: "1700000000".to_string(),
+            token: "abcdef".to_string(),
+            signature: hex::encode(mac.finalize().into_bytes()),
+        };
+        assert!(verify_mailgun_signature("key-123", &signature));
+        assert!(!verify_mailgun_signature("key-456", &signature));
+        signature.token = "abcdeg".to_string();
+        assert!(!verify_mailgun_signature("key-123", &signature));
+    }
+
+    #[test]
+    fn test_check_mailgun_freshness() {
+        let signature = |timestamp: i64, token: &str| MailgunSignature {
+            timestamp: timestamp.to_string(),
+            token: token.to_string(),
+            signature: String::new(),
+        };
+        let now = 1_700_000_000;
+        assert!(check_mailgun_freshness(&signature(now - 10, "fresh"), now));
+        // the same token again is a replay
+        assert!(!check_mailgun_freshness(&signature(now - 10, "fresh"), now));
+        assert!(!check_mailgun_freshness(
+            &signature(now - MAILGUN_MAX_AGE_SECS - 1, "stale"),
+            now
+        ));
+    }
+
+    #[test]
+    fn test_sns_string_to_sign() {
+        let sns: SnsMessage = serde_json::from_str(
+            r#"{
+                "Type": "Notification",
+                "MessageId": "22b80b92-fdea-4c2c-8f9d-bdfb0c7bf324",
+                "TopicArn": "arn:aws:sns:us-west-2:123456789012:MyTopic",
+                "Message": "Hello",
+                "Timestamp": "2012-05-02T00:54:06.655Z",
+                "SignatureVersion": "1",
+                "Signature": "",
+                "SigningCertURL": "https://sns.us-west-2.amazonaws.com/SimpleNotificationService-f3ecfb7224c7233fe7bb5f59f96de52f.pem"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            sns_string_to_sign(&sns).unwrap(),
+            "Message\nHello\nMessageId\n22b80b92-fdea-4c2c-8f9d-bdfb0c7bf324\n\
+             Timestamp\n2012-05-02T00:54:06.655Z\n\
+             TopicArn\narn:aws:sns:us-west-2:123456789012:MyTopic\nType\nNotification\n"
+        );
+        assert!(check_sns_cert_url(&sns.signing_cert_url).is_ok());
+        assert!(check_sns_cert_url("https://sns.us-west-2.amazonaws.com.evil.test/a.pem").is_err());
+        assert!(check_sns_cert_url("http://sns.us-west-2.amazonaws.com/a.pem").is_err());
+        assert!(check_sns_cert_url("https://evil.test/sns.us-west-2.amazonaws.com.pem").is_err());
+    }
+
+    #[test]
+    fn test_ses_undeliverable() {
+        let message = r#"{
+            "notificationType": "Bounce",
+            "bounce": {
+                "bounceType": "Permanent",
+                "bouncedRecipients": [{"emailAddress": "gone@example.com"}]
+            }
+        }"#;
+        let notification: SesNotification = serde_json::from_str(message).unwrap();
+        assert_eq!(
+            ses_undeliverable(&notification),
+            vec![(
+                "gone@example.com".to_string(),
+                "bounce: permanent".to_string()
+            )]
+        );
+
+        let message = r#"{
+            "notificationType": "Bounce",
+            "bounce": {
+                "bounceType": "Transient",
+                "bouncedRecipients": [{"emailAddress": "full@example.com"}]
+            }
+        }"#;
+        let notification: SesNotification = serde_json::from_str(message).unwrap();
+        assert!(ses_undeliverable(&notification).is_empty());
+    }
+}
@@ -1,8 +1,14 @@
-use rsky_pds::build_rocket;
+use rsky_pds::{build_rocket, self_check};
 
 #[rocket::main]
 async fn main() {
     let subscriber = tracing_subscriber::FmtSubscriber::new();
     tracing::subscriber::set_global_default(subscriber).unwrap();
+    if std::env::args().skip(1).any(|arg| arg == "--check") {
+        dotenvy::dotenv().ok();
+        let report = self_check::run().await;
+        print!("{report}");
+        std::process::exit(if report.passed() { 0 } else { 1 });
+    }
     let _ = build_rocket(None).await.launch().await;
 }
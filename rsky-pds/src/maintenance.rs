@@ -0,0 +1,126 @@
+//! Read-only maintenance mode. While it's on, every xrpc procedure is turned away with
+//! `ServiceUnavailable` before reaching its route, but queries and the firehose keep being
+//! served, so operators can run migrations without taking the PDS down. Toggled at runtime
+//! through `xyz.blackskyweb.admin.updateMaintenanceMode`, or at startup with `PDS_READ_ONLY`.
+
+use crate::apis::ApiError;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::Method;
+use rocket::{Data, Request};
+use rsky_lexicon::xyz::blackskyweb::admin::MaintenanceMode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+/// Procedures still served in read-only mode: turning it back off, and keeping sessions alive
+/// so clients can go on reading.
+const ALLOWED_WRITES: [&str; 3] = [
+    "/xrpc/xyz.blackskyweb.admin.updateMaintenanceMode",
+    "/xrpc/com.atproto.server.createSession",
+    "/xrpc/com.atproto.server.refreshSession",
+];
+
+// no route is mounted here, so rewritten requests fall through to the default catcher
+const UNAVAILABLE_PATH: &str = "/_read-only";
+
+#[derive(Default)]
+pub struct MaintenanceState {
+    read_only: AtomicBool,
+    reason: RwLock<Option<String>>,
+}
+
+impl MaintenanceState {
+    pub fn new(read_only: bool) -> Self {
+        MaintenanceState {
+            read_only: AtomicBool::new(read_only),
+            reason: RwLock::new(None),
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::SeqCst)
+    }
+
+    pub fn set(&self, read_only: bool, reason: Option<String>) {
+        // reason first, so a request that sees the flag never reads a stale reason
+        *self.reason.write().unwrap() = if read_only { reason } else { None };
+        self.read_only.store(read_only, Ordering::SeqCst);
+    }
+
+    pub fn mode(&self) -> MaintenanceMode {
+        MaintenanceMode {
+            read_only: self.is_read_only(),
+            reason: self.reason.read().unwrap().clone(),
+        }
+    }
+
+    fn unavailable(&self) -> ApiError {
+        let message = match self.reason.read().unwrap().as_deref() {
+            Some(reason) => format!("PDS is read-only for maintenance: {reason}"),
+            None => "PDS is read-only for maintenance".to_string(),
+        };
+        ApiError::ServiceUnavailable(message)
+    }
+}
+
+fn is_blocked_write(request: &Request<'_>) -> bool {
+    let path = request.uri().path();
+    request.method() == Method::Post
+        && path.starts_with("/xrpc/")
+        && !ALLOWED_WRITES.contains(&path.as_str())
+}
+
+/// Attach before `GracefulShutdown`, so rejected requests aren't counted as in-flight writes.
+pub struct ReadOnlyMode;
+
+#[rocket::async_trait]
+impl Fairing for ReadOnlyMode {
+    fn info(&self) -> Info {
+        Info {
+            name: "Reject writes while in read-only maintenance mode",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        let Some(state) = request.rocket().state::<MaintenanceState>() else {
+            return;
+        };
+        if state.is_read_only() && is_blocked_write(request) {
+            let error = state.unavailable();
+            request.local_cache(|| Some(error));
+            request.set_uri(Origin::parse(UNAVAILABLE_PATH).unwrap());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_clears_reason() {
+        let state = MaintenanceState::new(false);
+        state.set(true, Some("migrating".to_string()));
+        assert_eq!(
+            state.mode(),
+            MaintenanceMode {
+                read_only: true,
+                reason: Some("migrating".to_string()),
+            }
+        );
+        assert_eq!(
+            state.unavailable().message(),
+            "PDS is read-only for maintenance: migrating"
+        );
+
+        state.set(false, Some("ignored".to_string()));
+        assert_eq!(
+            state.mode(),
+            MaintenanceMode {
+                read_only: false,
+                reason: None,
+            }
+        );
+    }
+}
@@ -1,20 +1,31 @@
 pub mod models;
 pub use self::models::Account;
+pub use self::models::AccountExport;
 pub use self::models::AccountPref;
 pub use self::models::Actor;
+pub use self::models::AdminApproval;
 pub use self::models::AppPassword;
 pub use self::models::Backlink;
 pub use self::models::Blob;
+pub use self::models::DeletedRecord;
 pub use self::models::DidDoc;
 pub use self::models::EmailToken;
 pub use self::models::InviteCode;
 pub use self::models::InviteCodeUse;
+pub use self::models::LoginAttempt;
+pub use self::models::ModerationAudit;
+pub use self::models::ModerationReport;
+pub use self::models::OAuthRequest;
+pub use self::models::OAuthToken;
+pub use self::models::PushRegistration;
 pub use self::models::Record;
 pub use self::models::RecordBlob;
 pub use self::models::RefreshToken;
 pub use self::models::RepoBlock;
+pub use self::models::RepoCommit;
 pub use self::models::RepoRoot;
 pub use self::models::RepoSeq;
+pub use self::models::TotpSecret;
 pub mod error_code;
 pub use self::error_code::ErrorCode;
 pub mod error_message_response;
@@ -37,6 +37,35 @@ pub struct Account {
     pub email_confirmed_at: Option<String>,
 }
 
+#[derive(
+    Queryable,
+    Identifiable,
+    Insertable,
+    Selectable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(table_name = crate::schema::pds::account_export)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AccountExport {
+    pub id: String,
+    pub did: String,
+    pub status: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = completedAt)]
+    #[serde(rename = "completedAt")]
+    pub completed_at: Option<String>,
+    pub error: Option<String>,
+    /// Final export taken with the account frozen, for moving to another PDS.
+    pub migration: bool,
+}
+
 #[derive(
     Queryable,
     Identifiable,
@@ -82,6 +111,36 @@ pub struct Actor {
     pub delete_after: Option<String>,
 }
 
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(action, subject))]
+#[diesel(table_name = crate::schema::pds::admin_approval)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AdminApproval {
+    pub action: String,
+    pub subject: String,
+    #[diesel(column_name = requestedBy)]
+    #[serde(rename = "requestedBy")]
+    pub requested_by: String,
+    pub justification: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = expiresAt)]
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
 #[derive(
     Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
 )]
@@ -95,6 +154,7 @@ pub struct AppPassword {
     #[diesel(column_name = createdAt)]
     #[serde(rename = "createdAt")]
     pub created_at: String,
+    pub privileged: bool,
 }
 
 #[derive(
@@ -147,6 +207,42 @@ pub struct Blob {
     pub takedown_ref: Option<String>,
 }
 
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(uri, cid))]
+#[diesel(table_name = crate::schema::pds::deleted_record)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DeletedRecord {
+    pub uri: String,
+    pub cid: String,
+    pub did: String,
+    pub collection: String,
+    pub rkey: String,
+    #[diesel(column_name = repoRev)]
+    #[serde(rename = "repoRev")]
+    pub repo_rev: Option<String>,
+    #[diesel(column_name = indexedAt)]
+    #[serde(rename = "indexedAt")]
+    pub indexed_at: String,
+    pub content: Vec<u8>,
+    #[diesel(column_name = deletedAt)]
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: String,
+    #[diesel(column_name = expiresAt)]
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
 #[derive(
     Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
 )]
@@ -282,6 +378,177 @@ pub struct InviteCodeUse {
     pub used_at: String,
 }
 
+#[derive(
+    Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::login_attempt)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LoginAttempt {
+    pub id: i64,
+    pub did: String,
+    pub success: bool,
+    pub method: String,
+    pub ip: Option<String>,
+    #[diesel(column_name = userAgent)]
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+#[derive(
+    Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::moderation_audit)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ModerationAudit {
+    pub id: i64,
+    #[diesel(column_name = subjectDid)]
+    #[serde(rename = "subjectDid")]
+    pub subject_did: String,
+    pub action: String,
+    pub rule: String,
+    #[diesel(column_name = reportCount)]
+    #[serde(rename = "reportCount")]
+    pub report_count: i64,
+    #[diesel(column_name = expiresAt)]
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<String>,
+    #[diesel(column_name = resolvedAt)]
+    #[serde(rename = "resolvedAt")]
+    pub resolved_at: Option<String>,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = createdBy)]
+    #[serde(rename = "createdBy")]
+    pub created_by: Option<String>,
+    pub comment: Option<String>,
+}
+
+#[derive(
+    Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::moderation_report)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ModerationReport {
+    pub id: i64,
+    #[diesel(column_name = subjectDid)]
+    #[serde(rename = "subjectDid")]
+    pub subject_did: String,
+    #[diesel(column_name = subjectUri)]
+    #[serde(rename = "subjectUri")]
+    pub subject_uri: Option<String>,
+    #[diesel(column_name = reasonType)]
+    #[serde(rename = "reasonType")]
+    pub reason_type: String,
+    pub reason: Option<String>,
+    #[diesel(column_name = reportedBy)]
+    #[serde(rename = "reportedBy")]
+    pub reported_by: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+#[derive(
+    Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::oauth_request)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OAuthRequest {
+    pub id: String,
+    #[diesel(column_name = clientId)]
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[diesel(column_name = clientAuth)]
+    #[serde(rename = "clientAuth")]
+    pub client_auth: String,
+    pub parameters: String,
+    #[diesel(column_name = dpopJkt)]
+    #[serde(rename = "dpopJkt")]
+    pub dpop_jkt: Option<String>,
+    pub did: Option<String>,
+    pub code: Option<String>,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = expiresAt)]
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+#[derive(
+    Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::oauth_token)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OAuthToken {
+    pub id: String,
+    pub did: String,
+    #[diesel(column_name = clientId)]
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[diesel(column_name = clientAuth)]
+    #[serde(rename = "clientAuth")]
+    pub client_auth: String,
+    pub scope: String,
+    #[diesel(column_name = dpopJkt)]
+    #[serde(rename = "dpopJkt")]
+    pub dpop_jkt: String,
+    #[diesel(column_name = refreshToken)]
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = updatedAt)]
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+    #[diesel(column_name = expiresAt)]
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Insertable,
+    Selectable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(did, token))]
+#[diesel(table_name = crate::schema::pds::push_registration)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PushRegistration {
+    pub did: String,
+    #[diesel(column_name = serviceDid)]
+    #[serde(rename = "serviceDid")]
+    pub service_did: String,
+    pub token: String,
+    pub platform: String,
+    #[diesel(column_name = appId)]
+    #[serde(rename = "appId")]
+    pub app_id: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = updatedAt)]
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
+
 #[derive(
     Queryable,
     Identifiable,
@@ -319,6 +586,7 @@ pub struct Record {
     Queryable,
     Identifiable,
     Selectable,
+    Insertable,
     Clone,
     Debug,
     PartialEq,
@@ -357,6 +625,9 @@ pub struct RefreshToken {
     #[diesel(column_name = appPasswordName)]
     #[serde(rename = "appPasswordName")]
     pub app_password_name: Option<String>,
+    #[diesel(column_name = sessionName)]
+    #[serde(rename = "sessionName")]
+    pub session_name: Option<String>,
 }
 
 #[derive(
@@ -386,6 +657,55 @@ pub struct RepoBlock {
     pub content: Vec<u8>,
 }
 
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(did, rev))]
+#[diesel(table_name = crate::schema::pds::repo_commit)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RepoCommit {
+    pub did: String,
+    pub rev: String,
+    pub cid: String,
+    pub since: Option<String>,
+    pub creates: i32,
+    pub updates: i32,
+    pub deletes: i32,
+    #[diesel(column_name = committedAt)]
+    #[serde(rename = "committedAt")]
+    pub committed_at: String,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(did, rev, cid))]
+#[diesel(table_name = crate::schema::pds::repo_commit_block)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RepoCommitBlock {
+    pub did: String,
+    pub rev: String,
+    pub cid: String,
+}
+
 #[derive(
     Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
 )]
@@ -444,3 +764,91 @@ impl RepoSeq {
         }
     }
 }
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(nsid))]
+#[diesel(table_name = crate::schema::pds::route_flag)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RouteFlag {
+    pub nsid: String,
+    pub disabled: bool,
+    pub reason: Option<String>,
+    #[diesel(column_name = updatedAt)]
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(name))]
+#[diesel(table_name = crate::schema::pds::reserved_handle)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ReservedHandle {
+    pub name: String,
+    pub reason: Option<String>,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(did))]
+#[diesel(table_name = crate::schema::pds::totp_secret)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TotpSecret {
+    pub did: String,
+    /// The base32 secret shown to the user at enrollment, encrypted with the server's key, see
+    /// `totp::encrypt_secret`.
+    pub secret: String,
+    /// Time step of the last code accepted, so a code can't be replayed.
+    #[diesel(column_name = lastUsedStep)]
+    #[serde(rename = "lastUsedStep")]
+    pub last_used_step: Option<i64>,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    /// None until a first code confirms enrollment; codes aren't asked for before then.
+    #[diesel(column_name = confirmedAt)]
+    #[serde(rename = "confirmedAt")]
+    pub confirmed_at: Option<String>,
+    /// Wrong codes in a row since the last accepted one.
+    #[diesel(column_name = failedAttempts)]
+    #[serde(rename = "failedAttempts")]
+    pub failed_attempts: i32,
+    /// Codes are refused until then, after too many wrong ones.
+    #[diesel(column_name = lockedUntil)]
+    #[serde(rename = "lockedUntil")]
+    pub locked_until: Option<String>,
+}
@@ -0,0 +1,145 @@
+//! Report-threshold auto-actions. Every report against a local account is checked against the
+//! deployment's [`AutoActionRule`]s, and a rule whose threshold is met either flags the account
+//! for review or takes it down for a while. Every action lands in the moderation audit log.
+//! Automatic takedowns are lifted by [`run_takedown_sweeper`] once they expire, which also purges
+//! deleted records whose retention for review is over.
+
+use crate::account_manager::helpers::account::AccountStatusTransition;
+use crate::account_manager::helpers::moderation::{
+    auto_takedown_ref, expired_takedowns, lift_takedown, AuditEventOpts, AUDIT_ACTION_FLAG,
+    AUDIT_ACTION_TAKEDOWN,
+};
+use crate::account_manager::AccountManager;
+use crate::actor_store::record::purge_expired_deleted_records;
+use crate::config::{AutoAction, AutoActionRule};
+use crate::db::establish_connection_for_sequencer;
+use crate::sequencer::Sequencer;
+use crate::webhooks::{self, WebhookEvent};
+use crate::SharedSequencer;
+use anyhow::Result;
+use rsky_common::time::from_micros_to_str;
+use rsky_lexicon::com::atproto::admin::StatusAttr;
+use std::time::Duration;
+
+fn seconds_from_now(seconds: i64) -> String {
+    from_micros_to_str(chrono::Utc::now().timestamp_micros() + seconds * 1_000_000)
+}
+
+/// Applies every rule `subject_did` now meets. A rule acts at most once per window, rather than
+/// again on each report past its threshold. Accounts hosted elsewhere are left to their PDS.
+pub async fn apply_auto_actions(
+    subject_did: &str,
+    rules: &[AutoActionRule],
+    account_manager: &AccountManager,
+    sequencer: &SharedSequencer,
+) -> Result<()> {
+    let Some(status) = account_manager
+        .get_account_admin_status(subject_did)
+        .await?
+    else {
+        return Ok(());
+    };
+    let mut taken_down = status.takedown.applied;
+    for rule in rules {
+        let since = seconds_from_now(-(rule.window as i64));
+        let reporters = account_manager
+            .count_reporters_since(subject_did, since.clone())
+            .await?;
+        if reporters < rule.reports
+            || account_manager
+                .has_audit_event_since(subject_did, rule.to_string(), since)
+                .await?
+        {
+            continue;
+        }
+        match rule.action {
+            AutoAction::Flag => {
+                account_manager
+                    .record_audit_event(AuditEventOpts {
+                        subject_did: subject_did.to_owned(),
+                        action: AUDIT_ACTION_FLAG,
+                        rule: rule.to_string(),
+                        report_count: reporters,
+                        expires_at: None,
+                        created_by: None,
+                        comment: None,
+                    })
+                    .await?;
+                tracing::info!("@LOG: flagged {subject_did} for review under rule {rule}");
+            }
+            // never replace an existing takedown, which the sweeper could then lift
+            AutoAction::Takedown { .. } if taken_down => continue,
+            AutoAction::Takedown { duration } => {
+                let audit_id = account_manager
+                    .record_audit_event(AuditEventOpts {
+                        subject_did: subject_did.to_owned(),
+                        action: AUDIT_ACTION_TAKEDOWN,
+                        rule: rule.to_string(),
+                        report_count: reporters,
+                        expires_at: Some(seconds_from_now(duration as i64)),
+                        created_by: None,
+                        comment: None,
+                    })
+                    .await?;
+                account_manager
+                    .update_account_status(
+                        subject_did,
+                        vec![AccountStatusTransition::Takedown(StatusAttr {
+                            applied: true,
+                            r#ref: Some(auto_takedown_ref(audit_id)),
+                        })],
+                        sequencer,
+                    )
+                    .await?;
+                taken_down = true;
+                tracing::info!("@LOG: took down {subject_did} for {duration}s under rule {rule}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Lifts expired automatic takedowns and sequences an `#account` event for each account that
+/// comes back.
+pub async fn sweep_expired_takedowns(sequencer: &mut Sequencer) -> Result<()> {
+    let conn = &mut establish_connection_for_sequencer()?;
+    for audit in expired_takedowns(&rsky_common::now(), conn)? {
+        if let Some(status) = lift_takedown(&audit, conn)? {
+            sequencer
+                .sequence_account_evt(audit.subject_did.clone(), status)
+                .await?;
+            webhooks::notify(WebhookEvent::AccountTakedown {
+                did: audit.subject_did.clone(),
+                applied: false,
+                r#ref: None,
+            });
+            tracing::info!("@LOG: lifted automatic takedown of {}", audit.subject_did);
+        }
+    }
+    Ok(())
+}
+
+/// Permanently purges deleted records kept for review once their retention window is over.
+pub fn purge_deleted_records() -> Result<()> {
+    let conn = &mut establish_connection_for_sequencer()?;
+    let purged = purge_expired_deleted_records(&rsky_common::now(), conn)?;
+    if purged > 0 {
+        tracing::info!("@LOG: purged {purged} retained deleted records");
+    }
+    Ok(())
+}
+
+/// Runs [`sweep_expired_takedowns`] and [`purge_deleted_records`] every `interval` seconds
+/// until aborted.
+pub async fn run_takedown_sweeper(interval: u64, mut sequencer: Sequencer) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval.max(1)));
+    loop {
+        ticker.tick().await;
+        if let Err(error) = sweep_expired_takedowns(&mut sequencer).await {
+            tracing::error!("@LOG: ERROR: failed to lift expired takedowns: {error}");
+        }
+        if let Err(error) = purge_deleted_records() {
+            tracing::error!("@LOG: ERROR: failed to purge retained deleted records: {error}");
+        }
+    }
+}
@@ -0,0 +1,373 @@
+//! Client metadata. atproto clients don't register: a `client_id` is the https URL of a JSON
+//! document describing the client, fetched whenever it starts a login or spends a refresh
+//! token. Loopback ids (`http://localhost`, with `redirect_uri` and `scope` in the query) get
+//! metadata made up from the id itself, for development.
+
+use crate::http::{self, Destination};
+use crate::oauth::dpop::{self, Jwk, Jws};
+use crate::oauth::{OAuthError, CLIENT_AUTH_NONE, CLIENT_AUTH_PRIVATE_KEY_JWT, SCOPE_ATPROTO};
+use anyhow::Result;
+use std::net::IpAddr;
+use url::Url;
+
+pub const CLIENT_ASSERTION_TYPE_JWT_BEARER: &str =
+    "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+/// Largest metadata document (or `jwks_uri` response) we'll read.
+const MAX_DOCUMENT_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Jwks {
+    pub keys: Vec<Jwk>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientMetadata {
+    pub client_id: String,
+    #[serde(default)]
+    pub client_name: Option<String>,
+    #[serde(default)]
+    pub client_uri: Option<String>,
+    pub redirect_uris: Vec<String>,
+    #[serde(default = "default_grant_types")]
+    pub grant_types: Vec<String>,
+    #[serde(default = "default_response_types")]
+    pub response_types: Vec<String>,
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default = "default_auth_method")]
+    pub token_endpoint_auth_method: String,
+    #[serde(default)]
+    pub dpop_bound_access_tokens: bool,
+    #[serde(default)]
+    pub jwks: Option<Jwks>,
+    #[serde(default)]
+    pub jwks_uri: Option<String>,
+}
+
+fn default_grant_types() -> Vec<String> {
+    vec!["authorization_code".to_string()]
+}
+
+fn default_response_types() -> Vec<String> {
+    vec!["code".to_string()]
+}
+
+fn default_auth_method() -> String {
+    "client_secret_basic".to_string()
+}
+
+fn invalid_metadata(message: &str) -> OAuthError {
+    OAuthError::InvalidClientMetadata(message.to_string())
+}
+
+fn is_loopback_client_id(url: &Url) -> bool {
+    url.scheme() == "http" && url.host_str() == Some("localhost")
+}
+
+fn is_loopback_redirect(url: &Url) -> bool {
+    url.scheme() == "http" && matches!(url.host_str(), Some("127.0.0.1" | "[::1]"))
+}
+
+/// Fetches and checks the metadata of `client_id`.
+pub async fn resolve(client_id: &str) -> Result<ClientMetadata, OAuthError> {
+    let url = Url::parse(client_id).map_err(|_| invalid_metadata("client_id must be a URL"))?;
+    if is_loopback_client_id(&url) {
+        let metadata = loopback_metadata(client_id, &url)?;
+        metadata.validate(client_id)?;
+        return Ok(metadata);
+    }
+    check_fetchable(&url)?;
+    if url.path() == "/" {
+        return Err(invalid_metadata("client_id must have a path"));
+    }
+    let metadata: ClientMetadata = serde_json::from_slice(&fetch(url).await?)
+        .map_err(|error| invalid_metadata(&format!("invalid client metadata: {error}")))?;
+    metadata.validate(client_id)?;
+    Ok(metadata)
+}
+
+/// Only https URLs on domains are fetched. The names are resolved by a client that refuses
+/// non-public addresses and doesn't follow redirects, see [`http`].
+fn check_fetchable(url: &Url) -> Result<(), OAuthError> {
+    if url.scheme() != "https" || url.fragment().is_some() || !url.username().is_empty() {
+        return Err(invalid_metadata(
+            "client_id must be an https URL without a fragment or credentials",
+        ));
+    }
+    match url.host_str() {
+        Some(host) if host.parse::<IpAddr>().is_err() && host.contains('.') => Ok(()),
+        _ => Err(invalid_metadata("client_id must be on a public domain")),
+    }
+}
+
+async fn fetch(url: Url) -> Result<Vec<u8>, OAuthError> {
+    let unreachable = |error: reqwest::Error| {
+        invalid_metadata(&format!("unable to fetch client metadata: {error}"))
+    };
+    let mut res = http::get(Destination::OAuthClient, url)
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(unreachable)?;
+    let mut body = Vec::new();
+    while let Some(chunk) = res.chunk().await.map_err(unreachable)? {
+        body.extend_from_slice(&chunk);
+        if body.len() > MAX_DOCUMENT_SIZE {
+            return Err(invalid_metadata("client metadata is too large"));
+        }
+    }
+    Ok(body)
+}
+
+fn loopback_metadata(client_id: &str, url: &Url) -> Result<ClientMetadata, OAuthError> {
+    if url.port().is_some() || !matches!(url.path(), "" | "/") {
+        return Err(invalid_metadata(
+            "loopback client_id must be http://localhost without a port or path",
+        ));
+    }
+    let mut redirect_uris = Vec::new();
+    let mut scope = None;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "redirect_uri" => redirect_uris.push(value.into_owned()),
+            "scope" => scope = Some(value.into_owned()),
+            _ => (),
+        }
+    }
+    if redirect_uris.is_empty() {
+        redirect_uris = vec!["http://127.0.0.1/".to_string(), "http://[::1]/".to_string()];
+    }
+    let metadata = ClientMetadata {
+        client_id: client_id.to_string(),
+        client_name: Some("Loopback client".to_string()),
+        client_uri: None,
+        redirect_uris,
+        grant_types: vec![
+            "authorization_code".to_string(),
+            "refresh_token".to_string(),
+        ],
+        response_types: default_response_types(),
+        scope: Some(scope.unwrap_or(SCOPE_ATPROTO.to_string())),
+        token_endpoint_auth_method: CLIENT_AUTH_NONE.to_string(),
+        dpop_bound_access_tokens: true,
+        jwks: None,
+        jwks_uri: None,
+    };
+    if !metadata
+        .redirect_uris
+        .iter()
+        .all(|uri| Url::parse(uri).is_ok_and(|uri| is_loopback_redirect(&uri)))
+    {
+        return Err(invalid_metadata(
+            "loopback clients may only redirect to 127.0.0.1 or [::1]",
+        ));
+    }
+    Ok(metadata)
+}
+
+impl ClientMetadata {
+    pub fn validate(&self, client_id: &str) -> Result<(), OAuthError> {
+        if self.client_id != client_id {
+            return Err(invalid_metadata(
+                "client_id does not match the metadata URL",
+            ));
+        }
+        if !self.dpop_bound_access_tokens {
+            return Err(invalid_metadata("dpop_bound_access_tokens must be true"));
+        }
+        if !self.grant_types.iter().any(|g| g == "authorization_code") {
+            return Err(invalid_metadata(
+                "grant_types must include authorization_code",
+            ));
+        }
+        if !self.response_types.iter().any(|r| r == "code") {
+            return Err(invalid_metadata("response_types must include code"));
+        }
+        if !self.scopes().contains(&SCOPE_ATPROTO) {
+            return Err(invalid_metadata("scope must include atproto"));
+        }
+        if self.redirect_uris.is_empty() {
+            return Err(invalid_metadata("redirect_uris must not be empty"));
+        }
+        for uri in &self.redirect_uris {
+            let uri = Url::parse(uri).map_err(|_| invalid_metadata("invalid redirect_uri"))?;
+            // https for web apps, loopback or a private-use scheme for native ones
+            let allowed = uri.scheme() == "https"
+                || is_loopback_redirect(&uri)
+                || (uri.scheme().contains('.') && uri.fragment().is_none());
+            if !allowed {
+                return Err(invalid_metadata(&format!(
+                    "redirect_uri {uri} is not allowed"
+                )));
+            }
+        }
+        match self.token_endpoint_auth_method.as_str() {
+            CLIENT_AUTH_NONE => Ok(()),
+            CLIENT_AUTH_PRIVATE_KEY_JWT if self.jwks.is_some() || self.jwks_uri.is_some() => Ok(()),
+            CLIENT_AUTH_PRIVATE_KEY_JWT => Err(invalid_metadata(
+                "private_key_jwt clients must publish jwks or a jwks_uri",
+            )),
+            other => Err(invalid_metadata(&format!(
+                "unsupported token_endpoint_auth_method {other}"
+            ))),
+        }
+    }
+
+    pub fn scopes(&self) -> Vec<&str> {
+        self.scope
+            .as_deref()
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect()
+    }
+
+    /// Whether the client may ask for every scope in `requested`.
+    pub fn allows_scope(&self, requested: &str) -> bool {
+        let allowed = self.scopes();
+        requested
+            .split_whitespace()
+            .all(|scope| allowed.contains(&scope))
+    }
+
+    /// Redirect URIs match exactly, except that loopback ones may use any port (RFC 8252).
+    pub fn allows_redirect_uri(&self, redirect_uri: &str) -> bool {
+        let Ok(requested) = Url::parse(redirect_uri) else {
+            return false;
+        };
+        self.redirect_uris.iter().any(|registered| {
+            if registered == redirect_uri {
+                return true;
+            }
+            let Ok(mut registered) = Url::parse(registered) else {
+                return false;
+            };
+            if !is_loopback_redirect(&registered) || !is_loopback_redirect(&requested) {
+                return false;
+            }
+            let _ = registered.set_port(requested.port());
+            registered == requested
+        })
+    }
+
+    async fn keys(&self) -> Result<Vec<Jwk>, OAuthError> {
+        if let Some(jwks) = &self.jwks {
+            return Ok(jwks.keys.clone());
+        }
+        let Some(jwks_uri) = &self.jwks_uri else {
+            return Ok(vec![]);
+        };
+        let url = Url::parse(jwks_uri).map_err(|_| invalid_metadata("invalid jwks_uri"))?;
+        check_fetchable(&url)?;
+        let jwks: Jwks = serde_json::from_slice(&fetch(url).await?)
+            .map_err(|error| invalid_metadata(&format!("invalid jwks: {error}")))?;
+        Ok(jwks.keys)
+    }
+
+    /// Authenticates a token request from this client, returning the method it used.
+    /// Confidential clients sign a JWT assertion, with `aud` the issuer, using one of their keys.
+    pub async fn authenticate(
+        &self,
+        assertion_type: Option<&str>,
+        assertion: Option<&str>,
+        issuer: &str,
+    ) -> Result<&'static str, OAuthError> {
+        let invalid = |message: &str| OAuthError::InvalidClient(message.to_string());
+        if self.token_endpoint_auth_method == CLIENT_AUTH_NONE {
+            return Ok(CLIENT_AUTH_NONE);
+        }
+        let (Some(CLIENT_ASSERTION_TYPE_JWT_BEARER), Some(assertion)) = (assertion_type, assertion)
+        else {
+            return Err(invalid("client assertion required"));
+        };
+        let jws = Jws::decode(assertion).map_err(|_| invalid("malformed client assertion"))?;
+        let kid = jws.header["kid"].as_str();
+        let keys = self.keys().await?;
+        let verified = keys
+            .iter()
+            .filter(|key| kid.is_none() || key.kid.as_deref() == kid)
+            .any(|key| jws.verify(key).is_ok());
+        if !verified {
+            return Err(invalid("client assertion signature is invalid"));
+        }
+        let client_id = Some(self.client_id.as_str());
+        if jws.claim("iss") != client_id || jws.claim("sub") != client_id {
+            return Err(invalid(
+                "client assertion iss and sub must be the client_id",
+            ));
+        }
+        let audience_ok = match &jws.payload["aud"] {
+            serde_json::Value::String(aud) => aud == issuer,
+            serde_json::Value::Array(auds) => auds.iter().any(|aud| aud == issuer),
+            _ => false,
+        };
+        if !audience_ok {
+            return Err(invalid("client assertion aud must be the issuer"));
+        }
+        let now = dpop::now();
+        match (jws.payload["iat"].as_u64(), jws.payload["exp"].as_u64()) {
+            (Some(iat), exp) if iat <= now + 10 && exp.is_none_or(|exp| exp > now) => (),
+            _ => return Err(invalid("client assertion is expired or not yet valid")),
+        }
+        let Some(jti) = jws.claim("jti") else {
+            return Err(invalid("client assertion is missing its jti"));
+        };
+        let until = jws.payload["exp"].as_u64().unwrap_or(now + dpop::MAX_AGE);
+        dpop::check_replay(&format!("client:{}:{jti}", self.client_id), until)
+            .map_err(|_| invalid("client assertion replayed"))?;
+        Ok(CLIENT_AUTH_PRIVATE_KEY_JWT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loopback_metadata() {
+        let client_id = "http://localhost?redirect_uri=http%3A%2F%2F127.0.0.1%2Fcallback&scope=atproto%20transition%3Ageneric";
+        let metadata = loopback_metadata(client_id, &Url::parse(client_id).unwrap()).unwrap();
+        metadata.validate(client_id).unwrap();
+        assert!(metadata.allows_scope("atproto transition:generic"));
+        assert!(!metadata.allows_scope("atproto transition:chat.bsky"));
+        assert!(metadata.allows_redirect_uri("http://127.0.0.1:8080/callback"));
+        assert!(!metadata.allows_redirect_uri("http://127.0.0.1:8080/other"));
+        assert!(!metadata.allows_redirect_uri("https://example.com/callback"));
+
+        let with_port = "http://localhost:3000";
+        assert!(loopback_metadata(with_port, &Url::parse(with_port).unwrap()).is_err());
+        let elsewhere = "http://localhost?redirect_uri=https%3A%2F%2Fexample.com";
+        assert!(loopback_metadata(elsewhere, &Url::parse(elsewhere).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_validate_metadata() {
+        let client_id = "https://app.example.com/client-metadata.json";
+        let mut metadata: ClientMetadata = serde_json::from_value(serde_json::json!({
+            "client_id": client_id,
+            "redirect_uris": ["https://app.example.com/callback", "com.example.app:/callback"],
+            "grant_types": ["authorization_code", "refresh_token"],
+            "scope": "atproto transition:generic",
+            "token_endpoint_auth_method": "none",
+            "dpop_bound_access_tokens": true,
+        }))
+        .unwrap();
+        metadata.validate(client_id).unwrap();
+        assert!(metadata.validate("https://other.example.com/").is_err());
+
+        metadata
+            .redirect_uris
+            .push("http://app.example.com/callback".to_string());
+        assert!(metadata.validate(client_id).is_err());
+        metadata.redirect_uris.pop();
+
+        metadata.token_endpoint_auth_method = CLIENT_AUTH_PRIVATE_KEY_JWT.to_string();
+        assert!(metadata.validate(client_id).is_err());
+
+        assert!(check_fetchable(&Url::parse("https://10.0.0.1/client.json").unwrap()).is_err());
+        assert!(
+            check_fetchable(&Url::parse("http://app.example.com/client.json").unwrap()).is_err()
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! DPoP (RFC 9449) proofs. Clients sign a short-lived JWT with a key of their own for every
+//! token and resource request; tokens are bound to that key's thumbprint, so one that leaks is
+//! useless without the key. Only ES256 keys are accepted, which is what atproto requires.
+//!
+//! Nonces are an HMAC of the current minute, so they need no storage. Replicas only accept each
+//! other's when they share `PDS_DPOP_SECRET`.
+
+use crate::oauth::OAuthError;
+use anyhow::{bail, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use rsky_common::env::env_str;
+use rsky_crypto::p256::operations::verify_sig;
+use rsky_crypto::types::VerifyOptions;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds a proof stays acceptable after its `iat`.
+pub const MAX_AGE: u64 = 60;
+/// Seconds a proof's `iat` may be ahead of our clock.
+const MAX_SKEW: u64 = 10;
+/// Seconds each nonce is current for; the one before and after are accepted too.
+const NONCE_ROTATION: u64 = 60;
+
+lazy_static! {
+    static ref NONCE_SECRET: Vec<u8> = match env_str("PDS_DPOP_SECRET") {
+        Some(secret) if !secret.is_empty() => secret.into_bytes(),
+        _ => rand::random::<[u8; 32]>().to_vec(),
+    };
+    /// `jti`s of recent proofs and client assertions, until they'd be rejected as too old anyway.
+    static ref SEEN_JTIS: Mutex<HashMap<String, u64>> = Mutex::new(HashMap::new());
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("timestamp in seconds since UNIX epoch")
+        .as_secs()
+}
+
+pub fn b64url(bytes: impl AsRef<[u8]>) -> String {
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// A public key, as found in DPoP proof headers and client `jwks`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    #[serde(default)]
+    pub crv: String,
+    #[serde(default)]
+    pub x: String,
+    #[serde(default)]
+    pub y: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+}
+
+impl Jwk {
+    fn sec1_bytes(&self) -> Result<Vec<u8>> {
+        if self.kty != "EC" || self.crv != "P-256" {
+            bail!("unsupported key type {} {}", self.kty, self.crv);
+        }
+        let mut bytes = vec![0x04];
+        bytes.extend(URL_SAFE_NO_PAD.decode(&self.x)?);
+        bytes.extend(URL_SAFE_NO_PAD.decode(&self.y)?);
+        Ok(bytes)
+    }
+
+    /// RFC 7638 thumbprint, which tokens are bound to.
+    pub fn thumbprint(&self) -> String {
+        let canonical = serde_json::json!({
+            "crv": self.crv,
+            "kty": self.kty,
+            "x": self.x,
+            "y": self.y,
+        });
+        // only the required members, in lexicographic order
+        b64url(Sha256::digest(canonical.to_string()))
+    }
+}
+
+/// A compact JWS, decoded but not yet verified.
+pub struct Jws {
+    pub header: Value,
+    pub payload: Value,
+    signing_input: String,
+    signature: Vec<u8>,
+}
+
+impl Jws {
+    pub fn decode(token: &str) -> Result<Self> {
+        let parts: Vec<&str> = token.split('.').collect();
+        let [header, payload, signature] = parts.as_slice() else {
+            bail!("malformed JWT");
+        };
+        Ok(Jws {
+            header: serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header)?)?,
+            payload: serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload)?)?,
+            signing_input: format!("{header}.{payload}"),
+            signature: URL_SAFE_NO_PAD.decode(signature)?,
+        })
+    }
+
+    pub fn verify(&self, key: &Jwk) -> Result<()> {
+        if self.header["alg"] != "ES256" {
+            bail!("unsupported alg {}", self.header["alg"]);
+        }
+        let opts = VerifyOptions {
+            allow_malleable_sig: Some(true),
+        };
+        let valid = verify_sig(
+            &key.sec1_bytes()?,
+            self.signing_input.as_bytes(),
+            &self.signature,
+            Some(opts),
+        )?;
+        if !valid {
+            bail!("invalid signature");
+        }
+        Ok(())
+    }
+
+    pub fn claim(&self, name: &str) -> Option<&str> {
+        self.payload[name].as_str()
+    }
+}
+
+fn nonce_at(counter: u64) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(&NONCE_SECRET).expect("HMAC accepts any key length");
+    mac.update(&counter.to_be_bytes());
+    b64url(mac.finalize().into_bytes())
+}
+
+pub fn current_nonce() -> String {
+    nonce_at(now() / NONCE_ROTATION)
+}
+
+fn is_valid_nonce(nonce: &str) -> bool {
+    let counter = now() / NONCE_ROTATION;
+    [counter.saturating_sub(1), counter, counter + 1]
+        .into_iter()
+        .any(|counter| nonce_at(counter) == nonce)
+}
+
+/// Remembers `jti` until `until`, failing if it was seen before.
+pub fn check_replay(jti: &str, until: u64) -> Result<()> {
+    let now = now();
+    let mut seen = SEEN_JTIS.lock().unwrap();
+    seen.retain(|_, expires| *expires > now);
+    if seen.insert(jti.to_owned(), until).is_some() {
+        bail!("jti has been used before");
+    }
+    Ok(())
+}
+
+/// Verifies the DPoP `proof` for a `htm` request to `htu`, and, at the resource server, that it
+/// was made for `access_token`. Returns the thumbprint of the key it was signed with.
+pub fn verify_proof(
+    proof: &str,
+    htm: &str,
+    htu: &str,
+    access_token: Option<&str>,
+    require_nonce: bool,
+) -> Result<String, OAuthError> {
+    let invalid = |message: &str| OAuthError::InvalidDpopProof(message.to_string());
+    let jws = Jws::decode(proof).map_err(|_| invalid("Malformed DPoP proof"))?;
+    if jws.header["typ"] != "dpop+jwt" {
+        return Err(invalid("DPoP proof must have typ dpop+jwt"));
+    }
+    let jwk: Jwk = serde_json::from_value(jws.header["jwk"].clone())
+        .map_err(|_| invalid("DPoP proof is missing its jwk"))?;
+    jws.verify(&jwk)
+        .map_err(|error| invalid(&format!("DPoP proof signature: {error}")))?;
+
+    if jws.claim("htm") != Some(htm) {
+        return Err(invalid("DPoP htm mismatch"));
+    }
+    // the query and fragment aren't part of the comparison
+    let claimed_htu = jws
+        .claim("htu")
+        .and_then(|htu| htu.split(['?', '#']).next());
+    if claimed_htu != Some(htu) {
+        return Err(invalid("DPoP htu mismatch"));
+    }
+    let now = now();
+    match jws.payload["iat"].as_u64() {
+        Some(iat) if iat + MAX_AGE >= now && iat <= now + MAX_SKEW => (),
+        _ => return Err(invalid("DPoP proof is expired or not yet valid")),
+    }
+    match jws.claim("nonce") {
+        Some(nonce) if !is_valid_nonce(nonce) => return Err(OAuthError::UseDpopNonce),
+        None if require_nonce => return Err(OAuthError::UseDpopNonce),
+        _ => (),
+    }
+    if let Some(access_token) = access_token {
+        if jws.claim("ath") != Some(b64url(Sha256::digest(access_token)).as_str()) {
+            return Err(invalid("DPoP ath mismatch"));
+        }
+    }
+    let Some(jti) = jws.claim("jti") else {
+        return Err(invalid("DPoP proof is missing its jti"));
+    };
+    check_replay(&format!("dpop:{jti}"), now + MAX_AGE + MAX_SKEW)
+        .map_err(|_| invalid("DPoP proof replayed"))?;
+    Ok(jwk.thumbprint())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::{Signature, SigningKey};
+    use serde_json::json;
+
+    pub(crate) fn public_jwk(key: &SigningKey) -> Jwk {
+        let point = key.verifying_key().to_encoded_point(false);
+        Jwk {
+            kty: "EC".to_string(),
+            crv: "P-256".to_string(),
+            x: b64url(point.x().unwrap()),
+            y: b64url(point.y().unwrap()),
+            kid: None,
+        }
+    }
+
+    pub(crate) fn sign(key: &SigningKey, header: Value, payload: Value) -> String {
+        let input = format!(
+            "{}.{}",
+            b64url(header.to_string()),
+            b64url(payload.to_string())
+        );
+        let signature: Signature = key.sign(input.as_bytes());
+        format!("{input}.{}", b64url(signature.to_bytes()))
+    }
+
+    fn proof(key: &SigningKey, jti: &str, nonce: Option<String>) -> String {
+        sign(
+            key,
+            json!({ "typ": "dpop+jwt", "alg": "ES256", "jwk": public_jwk(key) }),
+            json!({
+                "jti": jti,
+                "htm": "POST",
+                "htu": "https://pds.test/oauth/token",
+                "iat": now(),
+                "nonce": nonce,
+            }),
+        )
+    }
+
+    #[test]
+    fn test_verify_proof() {
+        let key = SigningKey::random(&mut rand::thread_rng());
+        let htu = "https://pds.test/oauth/token";
+
+        let without_nonce = proof(&key, "a", None);
+        assert_eq!(
+            verify_proof(&without_nonce, "POST", htu, None, true),
+            Err(OAuthError::UseDpopNonce)
+        );
+
+        let valid = proof(&key, "b", Some(current_nonce()));
+        assert_eq!(
+            verify_proof(&valid, "POST", htu, None, true),
+            Ok(public_jwk(&key).thumbprint())
+        );
+        assert!(
+            verify_proof(&valid, "POST", htu, None, true).is_err(),
+            "replay"
+        );
+
+        let other = proof(&key, "c", Some(current_nonce()));
+        assert!(verify_proof(&other, "GET", htu, None, true).is_err());
+
+        let wrong_key = SigningKey::random(&mut rand::thread_rng());
+        let forged = format!(
+            "{}.{}",
+            other.rsplit_once('.').unwrap().0,
+            proof(&wrong_key, "d", None).rsplit_once('.').unwrap().1
+        );
+        assert!(verify_proof(&forged, "POST", htu, None, true).is_err());
+    }
+
+    #[test]
+    fn test_thumbprint() {
+        // RFC 7638 doesn't have an EC example; this one is from RFC 9449 section 6.1
+        let jwk = Jwk {
+            kty: "EC".to_string(),
+            crv: "P-256".to_string(),
+            x: "l8tFrhx-34tV3hRICRDY9zCkDlpBhF42UQUfWVAWBFs".to_string(),
+            y: "9VE4jf_Ok_o64zbTTlcuNJajHmt6v9TDVrU0CdvGRDA".to_string(),
+            kid: None,
+        };
+        assert_eq!(
+            jwk.thumbprint(),
+            "0ZcOCORZNYy-DWpqq30jZyJGHTN0d2HglBV3uiguA4I"
+        );
+    }
+}
@@ -0,0 +1,311 @@
+//! atproto OAuth. The PDS is both the authorization server third-party clients log in through
+//! and the resource server their tokens are spent at, so users never hand an app their password.
+//!
+//! A login runs: the client pushes its authorization request to `/oauth/par`, sends the user to
+//! `/oauth/authorize` with the `request_uri` it got back, where they sign in and approve, and
+//! then trades the code it is redirected with at `/oauth/token`, proving PKCE and a DPoP key.
+//! Access tokens are JWTs signed with the PDS's own key and bound to that DPoP key; the session
+//! behind them (and its refresh token) is a row in `oauth_token`, deleted on revocation,
+//! takedown or password reset, after which its access tokens stop working too.
+
+pub mod client;
+pub mod dpop;
+pub mod routes;
+pub mod store;
+
+use crate::auth_verifier::{AuthScope, JwtPayload, ValidatedBearer};
+use crate::config::ServerConfig;
+use crate::db::DbConn;
+use crate::models::OAuthToken;
+use anyhow::Result;
+use jwt_simple::claims::Audiences;
+use jwt_simple::prelude::*;
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::{self, Responder};
+use rocket::serde::json::Json;
+use rocket::Request;
+use secp256k1::{Keypair, Secp256k1, SecretKey};
+use serde_json::{json, Value};
+use std::env;
+use thiserror::Error;
+
+/// How long an access token lasts; clients refresh after that.
+pub const ACCESS_TOKEN_LIFETIME: u64 = 60 * 60;
+/// How long a pushed request can wait for the user to approve it.
+pub const REQUEST_LIFETIME: i64 = 5 * 60;
+/// How long the code from an approved request can wait to be exchanged.
+pub const CODE_LIFETIME: i64 = 60;
+/// How long a session of a public client (one without keys of its own) may be refreshed for.
+pub const PUBLIC_SESSION_LIFETIME: i64 = 14 * 24 * 60 * 60;
+/// How long a session of a confidential client may be refreshed for.
+pub const CONFIDENTIAL_SESSION_LIFETIME: i64 = 180 * 24 * 60 * 60;
+
+pub const SCOPE_ATPROTO: &str = "atproto";
+pub const SCOPE_TRANSITION_GENERIC: &str = "transition:generic";
+pub const SCOPE_TRANSITION_CHAT: &str = "transition:chat.bsky";
+pub const SCOPES_SUPPORTED: [&str; 3] = [
+    SCOPE_ATPROTO,
+    SCOPE_TRANSITION_GENERIC,
+    SCOPE_TRANSITION_CHAT,
+];
+
+/// Clients with no keys of their own.
+pub const CLIENT_AUTH_NONE: &str = "none";
+/// Clients authenticating with a JWT signed by a key from their metadata.
+pub const CLIENT_AUTH_PRIVATE_KEY_JWT: &str = "private_key_jwt";
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum OAuthError {
+    #[error("invalid_request: {0}")]
+    InvalidRequest(String),
+    #[error("invalid_client: {0}")]
+    InvalidClient(String),
+    #[error("invalid_client_metadata: {0}")]
+    InvalidClientMetadata(String),
+    #[error("invalid_grant: {0}")]
+    InvalidGrant(String),
+    #[error("invalid_scope: {0}")]
+    InvalidScope(String),
+    #[error("unsupported_grant_type: {0}")]
+    UnsupportedGrantType(String),
+    #[error("invalid_dpop_proof: {0}")]
+    InvalidDpopProof(String),
+    /// The proof carried no nonce, or a stale one; retry with the one in `DPoP-Nonce`.
+    #[error("use_dpop_nonce: Authorization server requires nonce in DPoP proof")]
+    UseDpopNonce,
+    #[error("invalid_token: {0}")]
+    InvalidToken(String),
+    #[error("access_denied: {0}")]
+    AccessDenied(String),
+    #[error("server_error: {0}")]
+    ServerError(String),
+}
+
+impl OAuthError {
+    pub fn error(&self) -> &'static str {
+        match self {
+            OAuthError::InvalidRequest(_) => "invalid_request",
+            OAuthError::InvalidClient(_) => "invalid_client",
+            OAuthError::InvalidClientMetadata(_) => "invalid_client_metadata",
+            OAuthError::InvalidGrant(_) => "invalid_grant",
+            OAuthError::InvalidScope(_) => "invalid_scope",
+            OAuthError::UnsupportedGrantType(_) => "unsupported_grant_type",
+            OAuthError::InvalidDpopProof(_) => "invalid_dpop_proof",
+            OAuthError::UseDpopNonce => "use_dpop_nonce",
+            OAuthError::InvalidToken(_) => "invalid_token",
+            OAuthError::AccessDenied(_) => "access_denied",
+            OAuthError::ServerError(_) => "server_error",
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        match self {
+            OAuthError::UseDpopNonce => "Authorization server requires nonce in DPoP proof",
+            OAuthError::InvalidRequest(message)
+            | OAuthError::InvalidClient(message)
+            | OAuthError::InvalidClientMetadata(message)
+            | OAuthError::InvalidGrant(message)
+            | OAuthError::InvalidScope(message)
+            | OAuthError::UnsupportedGrantType(message)
+            | OAuthError::InvalidDpopProof(message)
+            | OAuthError::InvalidToken(message)
+            | OAuthError::AccessDenied(message)
+            | OAuthError::ServerError(message) => message,
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        match self {
+            OAuthError::InvalidClient(_)
+            | OAuthError::InvalidDpopProof(_)
+            | OAuthError::InvalidToken(_) => Status::Unauthorized,
+            OAuthError::AccessDenied(_) => Status::Forbidden,
+            OAuthError::ServerError(_) => Status::InternalServerError,
+            _ => Status::BadRequest,
+        }
+    }
+}
+
+impl From<anyhow::Error> for OAuthError {
+    fn from(error: anyhow::Error) -> Self {
+        if let Some(error) = error.downcast_ref::<OAuthError>() {
+            return error.clone();
+        }
+        tracing::error!("@LOG: ERROR: oauth: {error}");
+        OAuthError::ServerError("Internal server error".to_string())
+    }
+}
+
+/// Every OAuth response carries a fresh DPoP nonce and must not be cached.
+fn oauth_response<'r, 'o: 'r>(
+    req: &'r Request<'_>,
+    status: Status,
+    body: Value,
+) -> response::Result<'o> {
+    let mut res = Json(body).respond_to(req)?;
+    res.set_header(ContentType::JSON);
+    res.set_header(Header::new("Cache-Control", "no-store"));
+    res.set_header(Header::new("DPoP-Nonce", dpop::current_nonce()));
+    res.set_status(status);
+    Ok(res)
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for OAuthError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        let body = json!({
+            "error": self.error(),
+            "error_description": self.description(),
+        });
+        oauth_response(req, self.status(), body)
+    }
+}
+
+/// A successful OAuth JSON response.
+pub struct OAuthJson(pub Status, pub Value);
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for OAuthJson {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        oauth_response(req, self.0, self.1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Confirmation {
+    /// Thumbprint of the DPoP key the token is bound to.
+    pub jkt: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessTokenClaims {
+    pub scope: String,
+    pub client_id: String,
+    pub cnf: Confirmation,
+}
+
+/// The legacy session scope an OAuth scope grants, if any. Tokens never get the full access
+/// scope, so account management (deletion, password and email changes) stays out of reach.
+pub fn auth_scope(scope: &str) -> Option<AuthScope> {
+    let scopes: Vec<&str> = scope.split_whitespace().collect();
+    if !scopes.contains(&SCOPE_ATPROTO) || !scopes.contains(&SCOPE_TRANSITION_GENERIC) {
+        return None;
+    }
+    match scopes.contains(&SCOPE_TRANSITION_CHAT) {
+        true => Some(AuthScope::AppPassPrivileged),
+        false => Some(AuthScope::AppPass),
+    }
+}
+
+fn jwt_key() -> Result<ES256kKeyPair> {
+    let secp = Secp256k1::new();
+    let private_key = env::var("PDS_JWT_KEY_K256_PRIVATE_KEY_HEX")?;
+    let secret_key = SecretKey::from_slice(&hex::decode(private_key.as_bytes())?)?;
+    let jwt_key = Keypair::from_secret_key(&secp, &secret_key);
+    Ok(ES256kKeyPair::from_bytes(
+        jwt_key.secret_bytes().as_slice(),
+    )?)
+}
+
+pub fn create_access_token(token: &OAuthToken, cfg: &ServerConfig) -> Result<String> {
+    let claims = Claims::with_custom_claims(
+        AccessTokenClaims {
+            scope: token.scope.clone(),
+            client_id: token.client_id.clone(),
+            cnf: Confirmation {
+                jkt: token.dpop_jkt.clone(),
+            },
+        },
+        Duration::from_secs(ACCESS_TOKEN_LIFETIME),
+    )
+    .with_issuer(&cfg.service.public_url)
+    .with_audience(&cfg.service.did)
+    .with_subject(&token.did)
+    .with_jwt_id(&token.id);
+    Ok(jwt_key()?.sign(claims)?)
+}
+
+pub fn verify_access_token(
+    jwt: &str,
+    cfg: &ServerConfig,
+) -> Result<JWTClaims<AccessTokenClaims>, OAuthError> {
+    let mut options = VerificationOptions::default();
+    options.allowed_issuers = Some(HashSet::from_strings(&[&cfg.service.public_url]));
+    options.allowed_audiences = Some(HashSet::from_strings(&[&cfg.service.did]));
+    jwt_key()?
+        .public_key()
+        .verify_token::<AccessTokenClaims>(jwt, Some(options))
+        .map_err(|error| OAuthError::InvalidToken(error.to_string()))
+}
+
+/// Checks a DPoP-bound access token, the proof sent along with it, and that its session hasn't
+/// been revoked.
+pub async fn validate_dpop_access(
+    request: &Request<'_>,
+    token: String,
+) -> Result<ValidatedBearer, OAuthError> {
+    let Some(cfg) = request.rocket().state::<ServerConfig>() else {
+        return Err(OAuthError::ServerError("missing server config".to_string()));
+    };
+    let claims = verify_access_token(&token, cfg)?;
+    let Some(proof) = request.headers().get_one("DPoP") else {
+        return Err(OAuthError::InvalidDpopProof(
+            "DPoP proof required".to_string(),
+        ));
+    };
+    let htu = format!("{}{}", cfg.service.public_url, request.uri().path());
+    let jkt = dpop::verify_proof(proof, request.method().as_str(), &htu, Some(&token), false)?;
+    if jkt != claims.custom.cnf.jkt {
+        return Err(OAuthError::InvalidDpopProof(
+            "DPoP key does not match the token".to_string(),
+        ));
+    }
+    let (Some(did), Some(id)) = (claims.subject.clone(), claims.jwt_id.clone()) else {
+        return Err(OAuthError::InvalidToken("Malformed token".to_string()));
+    };
+    let Some(scope) = auth_scope(&claims.custom.scope) else {
+        return Err(OAuthError::InvalidToken(
+            "Token scope grants no access".to_string(),
+        ));
+    };
+    let db = match request.guard::<DbConn>().await {
+        rocket::outcome::Outcome::Success(db) => db,
+        _ => return Err(OAuthError::ServerError("database unavailable".to_string())),
+    };
+    if store::get_token(&id, &db).await?.is_none() {
+        return Err(OAuthError::InvalidToken(
+            "Token has been revoked".to_string(),
+        ));
+    }
+    Ok(ValidatedBearer {
+        did: did.clone(),
+        scope: scope.clone(),
+        token,
+        payload: JwtPayload {
+            scope,
+            sub: Some(did),
+            aud: Some(Audiences::AsString(cfg.service.did.clone())),
+            exp: claims.expires_at,
+            iat: claims.issued_at,
+            jti: Some(id),
+        },
+        audience: Some(cfg.service.did.clone()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auth_scope() {
+        assert_eq!(auth_scope("atproto"), None);
+        assert_eq!(
+            auth_scope("atproto transition:generic"),
+            Some(AuthScope::AppPass)
+        );
+        assert_eq!(
+            auth_scope("transition:chat.bsky atproto transition:generic"),
+            Some(AuthScope::AppPassPrivileged)
+        );
+        assert_eq!(auth_scope("transition:generic"), None);
+    }
+}
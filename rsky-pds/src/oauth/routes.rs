@@ -0,0 +1,669 @@
+use crate::account_manager::helpers::account::AvailabilityFlags;
+use crate::account_manager::helpers::login_attempt::LOGIN_METHOD_OAUTH;
+use crate::account_manager::AccountManager;
+use crate::apis::com::atproto::server::create_session::{record_login_attempt, ClientInfo};
+use crate::apis::ApiError;
+use crate::config::ServerConfig;
+use crate::db::DbConn;
+use crate::models::OAuthRequest;
+use crate::oauth::client::{self, ClientMetadata};
+use crate::oauth::dpop::{self, b64url};
+use crate::oauth::store::{self, AuthorizationParameters, CreateRequestOpts, CreateTokenOpts};
+use crate::oauth::{
+    create_access_token, verify_access_token, OAuthError, OAuthJson, ACCESS_TOKEN_LIFETIME,
+    CLIENT_AUTH_NONE, CONFIDENTIAL_SESSION_LIFETIME, PUBLIC_SESSION_LIFETIME, REQUEST_LIFETIME,
+    SCOPES_SUPPORTED, SCOPE_ATPROTO,
+};
+use rocket::form::{Form, FromForm};
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::content::RawHtml;
+use rocket::response::Redirect;
+use rocket::{Responder, State};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+const REQUEST_URI_PREFIX: &str = "urn:ietf:params:oauth:request_uri:";
+
+/// The `DPoP` proof header, if the client sent one.
+pub struct DpopHeader(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for DpopHeader {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(DpopHeader(req.headers().get_one("DPoP").map(str::to_owned)))
+    }
+}
+
+impl DpopHeader {
+    /// Verifies the proof for a POST to `path`, returning its key thumbprint.
+    fn verify(&self, cfg: &ServerConfig, path: &str) -> Result<String, OAuthError> {
+        let Some(proof) = &self.0 else {
+            return Err(OAuthError::InvalidDpopProof(
+                "DPoP proof required".to_string(),
+            ));
+        };
+        let htu = format!("{}{path}", cfg.service.public_url);
+        dpop::verify_proof(proof, "POST", &htu, None, true)
+    }
+}
+
+fn random_token(prefix: &str) -> String {
+    format!("{prefix}{}", b64url(rand::random::<[u8; 32]>()))
+}
+
+fn required(value: Option<String>, name: &str) -> Result<String, OAuthError> {
+    value
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| OAuthError::InvalidRequest(format!("{name} is required")))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+pub fn authorization_server_metadata(cfg: &ServerConfig) -> Value {
+    let issuer = &cfg.service.public_url;
+    json!({
+        "issuer": issuer,
+        "scopes_supported": SCOPES_SUPPORTED,
+        "subject_types_supported": ["public"],
+        "response_types_supported": ["code"],
+        "response_modes_supported": ["query", "fragment"],
+        "grant_types_supported": ["authorization_code", "refresh_token"],
+        "code_challenge_methods_supported": ["S256"],
+        "ui_locales_supported": ["en-US"],
+        "display_values_supported": ["page"],
+        "authorization_response_iss_parameter_supported": true,
+        "require_pushed_authorization_requests": true,
+        "pushed_authorization_request_endpoint": format!("{issuer}/oauth/par"),
+        "authorization_endpoint": format!("{issuer}/oauth/authorize"),
+        "token_endpoint": format!("{issuer}/oauth/token"),
+        "revocation_endpoint": format!("{issuer}/oauth/revoke"),
+        "token_endpoint_auth_methods_supported": ["none", "private_key_jwt"],
+        "token_endpoint_auth_signing_alg_values_supported": ["ES256"],
+        "dpop_signing_alg_values_supported": ["ES256"],
+        "client_id_metadata_document_supported": true,
+        "protected_resources": [issuer],
+    })
+}
+
+pub fn protected_resource_metadata(cfg: &ServerConfig) -> Value {
+    let resource = &cfg.service.public_url;
+    json!({
+        "resource": resource,
+        "authorization_servers": [resource],
+        "scopes_supported": SCOPES_SUPPORTED,
+        "bearer_methods_supported": ["header"],
+        "resource_documentation": "https://atproto.com",
+    })
+}
+
+#[derive(FromForm)]
+pub struct ParForm {
+    client_id: Option<String>,
+    response_type: Option<String>,
+    redirect_uri: Option<String>,
+    scope: Option<String>,
+    state: Option<String>,
+    code_challenge: Option<String>,
+    code_challenge_method: Option<String>,
+    response_mode: Option<String>,
+    login_hint: Option<String>,
+    client_assertion_type: Option<String>,
+    client_assertion: Option<String>,
+}
+
+#[tracing::instrument(skip_all)]
+async fn inner_par(
+    body: ParForm,
+    dpop: DpopHeader,
+    cfg: &ServerConfig,
+    db: DbConn,
+) -> Result<Value, OAuthError> {
+    let jkt = dpop.verify(cfg, "/oauth/par")?;
+    let client_id = required(body.client_id, "client_id")?;
+    let client = client::resolve(&client_id).await?;
+    let client_auth = client
+        .authenticate(
+            body.client_assertion_type.as_deref(),
+            body.client_assertion.as_deref(),
+            &cfg.service.public_url,
+        )
+        .await?;
+
+    if required(body.response_type, "response_type")? != "code" {
+        return Err(OAuthError::InvalidRequest(
+            "response_type must be code".to_string(),
+        ));
+    }
+    if required(body.code_challenge_method, "code_challenge_method")? != "S256" {
+        return Err(OAuthError::InvalidRequest(
+            "code_challenge_method must be S256".to_string(),
+        ));
+    }
+    let code_challenge = required(body.code_challenge, "code_challenge")?;
+    let redirect_uri = required(body.redirect_uri, "redirect_uri")?;
+    if !client.allows_redirect_uri(&redirect_uri) {
+        return Err(OAuthError::InvalidRequest(
+            "redirect_uri is not registered for this client".to_string(),
+        ));
+    }
+    let scope = body.scope.unwrap_or(SCOPE_ATPROTO.to_string());
+    if !scope.split_whitespace().any(|scope| scope == SCOPE_ATPROTO) {
+        return Err(OAuthError::InvalidScope(
+            "scope must include atproto".to_string(),
+        ));
+    }
+    if !client.allows_scope(&scope) {
+        return Err(OAuthError::InvalidScope(
+            "scope is not allowed for this client".to_string(),
+        ));
+    }
+    if !matches!(
+        body.response_mode.as_deref(),
+        None | Some("query" | "fragment")
+    ) {
+        return Err(OAuthError::InvalidRequest(
+            "response_mode must be query or fragment".to_string(),
+        ));
+    }
+
+    let request_uri = random_token(&format!("{REQUEST_URI_PREFIX}req-"));
+    store::create_request(
+        CreateRequestOpts {
+            id: request_uri.clone(),
+            client_id,
+            client_auth,
+            parameters: AuthorizationParameters {
+                redirect_uri,
+                scope,
+                state: body.state,
+                code_challenge,
+                response_mode: body.response_mode,
+                login_hint: body.login_hint,
+            },
+            dpop_jkt: Some(jkt),
+            expires_at: store::expires_in(REQUEST_LIFETIME),
+        },
+        &db,
+    )
+    .await?;
+    Ok(json!({
+        "request_uri": request_uri,
+        "expires_in": REQUEST_LIFETIME,
+    }))
+}
+
+#[rocket::post("/oauth/par", data = "<body>")]
+pub async fn par(
+    body: Form<ParForm>,
+    dpop: DpopHeader,
+    cfg: &State<ServerConfig>,
+    db: DbConn,
+) -> Result<OAuthJson, OAuthError> {
+    let res = inner_par(body.into_inner(), dpop, cfg, db).await?;
+    Ok(OAuthJson(Status::Created, res))
+}
+
+#[derive(Responder)]
+pub enum AuthorizeResponder {
+    Page(RawHtml<String>),
+    Redirect(Redirect),
+}
+
+fn parameters(request: &OAuthRequest) -> Result<AuthorizationParameters, OAuthError> {
+    Ok(serde_json::from_str(&request.parameters).map_err(anyhow::Error::new)?)
+}
+
+/// The login and consent page for a pending request.
+fn authorize_page(
+    request: &OAuthRequest,
+    parameters: &AuthorizationParameters,
+    client: &ClientMetadata,
+    identifier: Option<&str>,
+    error: Option<&str>,
+) -> RawHtml<String> {
+    let client_name = client.client_name.as_deref().unwrap_or(&client.client_id);
+    let client_host = Url::parse(&client.client_id)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_owned))
+        .unwrap_or_default();
+    let identifier = identifier
+        .or(parameters.login_hint.as_deref())
+        .unwrap_or_default();
+    let error = match error {
+        Some(error) => format!(r#"<p class="error">{}</p>"#, escape_html(error)),
+        None => String::new(),
+    };
+    RawHtml(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Sign in to {client_name}</title>
+<style>
+body {{ font-family: system-ui, sans-serif; max-width: 24rem; margin: 4rem auto; padding: 0 1rem; }}
+input {{ display: block; width: 100%; box-sizing: border-box; margin: 0.25rem 0 1rem; padding: 0.5rem; }}
+button {{ padding: 0.5rem 1rem; margin-right: 0.5rem; }}
+.error {{ color: #b00020; }}
+code {{ word-break: break-all; }}
+</style>
+</head>
+<body>
+<h1>Sign in</h1>
+<p><strong>{client_name}</strong> (<code>{client_host}</code>) is asking to access your account with the scope <code>{scope}</code>.</p>
+{error}
+<form method="post" action="/oauth/authorize">
+<input type="hidden" name="request_uri" value="{request_uri}">
+<label>Handle or email<input name="identifier" value="{identifier}" autocomplete="username" required></label>
+<label>Password<input name="password" type="password" autocomplete="current-password"></label>
+<label>Two-factor code, if enabled<input name="auth_factor_token" autocomplete="one-time-code"></label>
+<button type="submit" name="decision" value="allow">Allow</button>
+<button type="submit" name="decision" value="deny" formnovalidate>Deny</button>
+</form>
+</body>
+</html>
+"#,
+        client_name = escape_html(client_name),
+        client_host = escape_html(&client_host),
+        scope = escape_html(&parameters.scope),
+        request_uri = escape_html(&request.id),
+        identifier = escape_html(identifier),
+    ))
+}
+
+/// Where to send the user back to the client, with `params` in the query or fragment.
+fn client_redirect_url(
+    parameters: &AuthorizationParameters,
+    issuer: &str,
+    params: Vec<(&str, &str)>,
+) -> Result<String, OAuthError> {
+    let mut url = Url::parse(&parameters.redirect_uri).map_err(anyhow::Error::new)?;
+    let mut params = params;
+    if let Some(state) = &parameters.state {
+        params.push(("state", state));
+    }
+    params.push(("iss", issuer));
+    match parameters.response_mode.as_deref() {
+        Some("fragment") => {
+            let fragment = url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(params)
+                .finish();
+            url.set_fragment(Some(&fragment));
+        }
+        _ => {
+            url.query_pairs_mut().extend_pairs(params);
+        }
+    }
+    Ok(url.to_string())
+}
+
+async fn pending_request(
+    request_uri: &str,
+    client_id: Option<&str>,
+    db: &DbConn,
+) -> Result<(OAuthRequest, AuthorizationParameters, ClientMetadata), OAuthError> {
+    let Some(request) = store::get_pending_request(request_uri, db).await? else {
+        return Err(OAuthError::InvalidRequest(
+            "Authorization request expired or unknown".to_string(),
+        ));
+    };
+    if client_id.is_some_and(|client_id| client_id != request.client_id) {
+        return Err(OAuthError::InvalidRequest("client_id mismatch".to_string()));
+    }
+    let parameters = parameters(&request)?;
+    let client = client::resolve(&request.client_id).await?;
+    Ok((request, parameters, client))
+}
+
+#[rocket::get("/oauth/authorize?<client_id>&<request_uri>")]
+pub async fn authorize(
+    client_id: String,
+    request_uri: String,
+    db: DbConn,
+) -> Result<RawHtml<String>, OAuthError> {
+    let (request, parameters, client) =
+        pending_request(&request_uri, Some(&client_id), &db).await?;
+    Ok(authorize_page(&request, &parameters, &client, None, None))
+}
+
+#[derive(FromForm)]
+pub struct AuthorizeForm {
+    request_uri: String,
+    identifier: String,
+    password: Option<String>,
+    auth_factor_token: Option<String>,
+    decision: String,
+}
+
+#[rocket::post("/oauth/authorize", data = "<body>")]
+pub async fn authorize_post(
+    body: Form<AuthorizeForm>,
+    client_info: ClientInfo,
+    cfg: &State<ServerConfig>,
+    account_manager: AccountManager,
+    db: DbConn,
+) -> Result<AuthorizeResponder, OAuthError> {
+    let AuthorizeForm {
+        request_uri,
+        identifier,
+        password,
+        auth_factor_token,
+        decision,
+    } = body.into_inner();
+    let (request, parameters, client) = pending_request(&request_uri, None, &db).await?;
+    let issuer = &cfg.service.public_url;
+    if decision != "allow" {
+        store::delete_request(&request.id, &db).await?;
+        let url = client_redirect_url(
+            &parameters,
+            issuer,
+            vec![
+                ("error", "access_denied"),
+                ("error_description", "The user denied the request"),
+            ],
+        )?;
+        return Ok(AuthorizeResponder::Redirect(Redirect::to(url)));
+    }
+
+    let identifier = identifier.trim().to_lowercase();
+    let flags = Some(AvailabilityFlags {
+        include_deactivated: Some(true),
+        include_taken_down: Some(true),
+    });
+    let user = match identifier.contains('@') {
+        true => {
+            account_manager
+                .get_account_by_email(&identifier, flags)
+                .await?
+        }
+        false => account_manager.get_account(&identifier, flags).await?,
+    };
+    let retry = |error: &str| {
+        AuthorizeResponder::Page(authorize_page(
+            &request,
+            &parameters,
+            &client,
+            Some(identifier.as_str()),
+            Some(error),
+        ))
+    };
+    let Some(user) = user else {
+        return Ok(retry("Invalid identifier or password"));
+    };
+    // app passwords are for clients that can't do OAuth, so only the account password works here
+    let password = password.unwrap_or_default();
+    if !account_manager
+        .verify_account_password(&user.did, &password)
+        .await?
+    {
+        record_login_attempt(
+            &account_manager,
+            &client_info,
+            &user.did,
+            false,
+            LOGIN_METHOD_OAUTH,
+        )
+        .await;
+        return Ok(retry("Invalid identifier or password"));
+    }
+    if user.takedown_ref.is_some() {
+        return Ok(retry("This account has been taken down"));
+    }
+    let auth_factor_token = auth_factor_token.filter(|token| !token.trim().is_empty());
+    if let Err(error) = account_manager
+        .verify_auth_factor(&user.did, auth_factor_token)
+        .await
+    {
+        match error.downcast_ref::<ApiError>() {
+            Some(ApiError::AuthFactorRequired) => {
+                return Ok(retry("Enter the code from your authenticator app"));
+            }
+            Some(ApiError::InvalidToken) => {
+                record_login_attempt(
+                    &account_manager,
+                    &client_info,
+                    &user.did,
+                    false,
+                    LOGIN_METHOD_OAUTH,
+                )
+                .await;
+                return Ok(retry("Invalid two-factor code"));
+            }
+            _ => return Err(error.into()),
+        }
+    }
+
+    let code = random_token("cod-");
+    if !store::authorize_request(&request.id, &user.did, &code, &db).await? {
+        return Err(OAuthError::InvalidRequest(
+            "Authorization request expired".to_string(),
+        ));
+    }
+    record_login_attempt(
+        &account_manager,
+        &client_info,
+        &user.did,
+        true,
+        LOGIN_METHOD_OAUTH,
+    )
+    .await;
+    let url = client_redirect_url(&parameters, issuer, vec![("code", &code)])?;
+    Ok(AuthorizeResponder::Redirect(Redirect::to(url)))
+}
+
+#[derive(FromForm)]
+pub struct TokenForm {
+    grant_type: Option<String>,
+    client_id: Option<String>,
+    code: Option<String>,
+    redirect_uri: Option<String>,
+    code_verifier: Option<String>,
+    refresh_token: Option<String>,
+    client_assertion_type: Option<String>,
+    client_assertion: Option<String>,
+}
+
+async fn exchange_code(
+    body: TokenForm,
+    client: &ClientMetadata,
+    client_auth: &str,
+    jkt: String,
+    db: &DbConn,
+) -> Result<crate::models::OAuthToken, OAuthError> {
+    let invalid = |message: &str| OAuthError::InvalidGrant(message.to_string());
+    let code = required(body.code, "code")?;
+    let Some(request) = store::take_request_by_code(&code, db).await? else {
+        return Err(invalid("Invalid or expired code"));
+    };
+    if request.client_id != client.client_id || request.client_auth != client_auth {
+        return Err(invalid("Code was issued to another client"));
+    }
+    let parameters = parameters(&request)?;
+    if body.redirect_uri.as_deref() != Some(parameters.redirect_uri.as_str()) {
+        return Err(invalid("redirect_uri mismatch"));
+    }
+    let code_verifier = required(body.code_verifier, "code_verifier")?;
+    if b64url(Sha256::digest(code_verifier)) != parameters.code_challenge {
+        return Err(invalid("Invalid code_verifier"));
+    }
+    if request
+        .dpop_jkt
+        .is_some_and(|request_jkt| request_jkt != jkt)
+    {
+        return Err(invalid("DPoP key mismatch"));
+    }
+    let Some(did) = request.did else {
+        return Err(invalid("Request was not approved"));
+    };
+    let lifetime = match client_auth {
+        CLIENT_AUTH_NONE => PUBLIC_SESSION_LIFETIME,
+        _ => CONFIDENTIAL_SESSION_LIFETIME,
+    };
+    let token = store::create_token(
+        CreateTokenOpts {
+            id: random_token("tok-"),
+            did,
+            client_id: request.client_id,
+            client_auth: client_auth.to_string(),
+            scope: parameters.scope,
+            dpop_jkt: jkt,
+            refresh_token: random_token("ref-"),
+            expires_at: store::expires_in(lifetime),
+        },
+        db,
+    )
+    .await?;
+    Ok(token)
+}
+
+async fn refresh(
+    body: TokenForm,
+    client: &ClientMetadata,
+    client_auth: &str,
+    jkt: String,
+    db: &DbConn,
+) -> Result<crate::models::OAuthToken, OAuthError> {
+    let invalid = |message: &str| OAuthError::InvalidGrant(message.to_string());
+    if !client
+        .grant_types
+        .iter()
+        .any(|grant| grant == "refresh_token")
+    {
+        return Err(OAuthError::UnsupportedGrantType(
+            "Client may not use refresh tokens".to_string(),
+        ));
+    }
+    let refresh_token = required(body.refresh_token, "refresh_token")?;
+    let Some(token) =
+        store::rotate_refresh_token(&refresh_token, &random_token("ref-"), db).await?
+    else {
+        return Err(invalid("Invalid refresh token"));
+    };
+    let mismatch = if token.client_id != client.client_id || token.client_auth != client_auth {
+        Some("Refresh token was issued to another client")
+    } else if token.dpop_jkt != jkt {
+        Some("DPoP key mismatch")
+    } else {
+        None
+    };
+    if let Some(mismatch) = mismatch {
+        // someone other than the session's client holds its refresh token, so end the session
+        store::revoke_token(&token.id, db).await?;
+        return Err(invalid(mismatch));
+    }
+    Ok(token)
+}
+
+#[tracing::instrument(skip_all)]
+async fn inner_token(
+    body: TokenForm,
+    dpop: DpopHeader,
+    cfg: &ServerConfig,
+    db: DbConn,
+) -> Result<Value, OAuthError> {
+    let jkt = dpop.verify(cfg, "/oauth/token")?;
+    let client = client::resolve(&required(body.client_id.clone(), "client_id")?).await?;
+    let client_auth = client
+        .authenticate(
+            body.client_assertion_type.as_deref(),
+            body.client_assertion.as_deref(),
+            &cfg.service.public_url,
+        )
+        .await?;
+    let token = match body.grant_type.as_deref() {
+        Some("authorization_code") => exchange_code(body, &client, client_auth, jkt, &db).await?,
+        Some("refresh_token") => refresh(body, &client, client_auth, jkt, &db).await?,
+        other => {
+            return Err(OAuthError::UnsupportedGrantType(format!(
+                "Unsupported grant_type {}",
+                other.unwrap_or_default()
+            )))
+        }
+    };
+    let access_token = create_access_token(&token, cfg)?;
+    Ok(json!({
+        "access_token": access_token,
+        "token_type": "DPoP",
+        "expires_in": ACCESS_TOKEN_LIFETIME,
+        "refresh_token": token.refresh_token,
+        "scope": token.scope,
+        "sub": token.did,
+    }))
+}
+
+#[rocket::post("/oauth/token", data = "<body>")]
+pub async fn token(
+    body: Form<TokenForm>,
+    dpop: DpopHeader,
+    cfg: &State<ServerConfig>,
+    db: DbConn,
+) -> Result<OAuthJson, OAuthError> {
+    let res = inner_token(body.into_inner(), dpop, cfg, db).await?;
+    Ok(OAuthJson(Status::Ok, res))
+}
+
+#[derive(FromForm)]
+pub struct RevokeForm {
+    token: String,
+}
+
+/// Ends the session behind an access or refresh token. Unknown tokens aren't an error
+/// (RFC 7009).
+#[rocket::post("/oauth/revoke", data = "<body>")]
+pub async fn revoke(
+    body: Form<RevokeForm>,
+    cfg: &State<ServerConfig>,
+    db: DbConn,
+) -> Result<OAuthJson, OAuthError> {
+    let token = match verify_access_token(&body.token, cfg) {
+        Ok(claims) => claims.jwt_id.unwrap_or_default(),
+        Err(_) => body.token.clone(),
+    };
+    store::revoke_token(&token, &db).await?;
+    Ok(OAuthJson(Status::Ok, json!({})))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_redirect_url() {
+        let mut parameters = AuthorizationParameters {
+            redirect_uri: "https://app.example.com/callback?from=pds".to_string(),
+            scope: "atproto".to_string(),
+            state: Some("a b".to_string()),
+            code_challenge: String::new(),
+            response_mode: None,
+            login_hint: None,
+        };
+        assert_eq!(
+            client_redirect_url(&parameters, "https://pds.test", vec![("code", "c")]).unwrap(),
+            "https://app.example.com/callback?from=pds&code=c&state=a+b&iss=https%3A%2F%2Fpds.test"
+        );
+
+        parameters.response_mode = Some("fragment".to_string());
+        assert_eq!(
+            client_redirect_url(&parameters, "https://pds.test", vec![("code", "c")]).unwrap(),
+            "https://app.example.com/callback?from=pds#code=c&state=a+b&iss=https%3A%2F%2Fpds.test"
+        );
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use crate::db::DbConn;
+use crate::models::{OAuthRequest, OAuthToken};
+use crate::oauth::CODE_LIFETIME;
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use diesel::*;
+use rsky_common::RFC3339_VARIANT;
+
+/// What the client pushed to the PAR endpoint, kept until the code is exchanged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthorizationParameters {
+    pub redirect_uri: String,
+    pub scope: String,
+    pub state: Option<String>,
+    pub code_challenge: String,
+    /// `query` (the default) or `fragment`.
+    pub response_mode: Option<String>,
+    pub login_hint: Option<String>,
+}
+
+pub fn expires_in(seconds: i64) -> String {
+    (Utc::now() + Duration::seconds(seconds))
+        .format(RFC3339_VARIANT)
+        .to_string()
+}
+
+pub struct CreateRequestOpts {
+    pub id: String,
+    pub client_id: String,
+    pub client_auth: &'static str,
+    pub parameters: AuthorizationParameters,
+    pub dpop_jkt: Option<String>,
+    pub expires_at: String,
+}
+
+/// Stores a pushed request, clearing out abandoned ones while at it.
+pub async fn create_request(opts: CreateRequestOpts, db: &DbConn) -> Result<()> {
+    use crate::schema::pds::oauth_request::dsl as OAuthRequestSchema;
+
+    let parameters = serde_json::to_string(&opts.parameters)?;
+    let now = rsky_common::now();
+    db.run(move |conn| {
+        delete(OAuthRequestSchema::oauth_request)
+            .filter(OAuthRequestSchema::expiresAt.lt(&now))
+            .execute(conn)?;
+        insert_into(OAuthRequestSchema::oauth_request)
+            .values((
+                OAuthRequestSchema::id.eq(opts.id),
+                OAuthRequestSchema::clientId.eq(opts.client_id),
+                OAuthRequestSchema::clientAuth.eq(opts.client_auth),
+                OAuthRequestSchema::parameters.eq(parameters),
+                OAuthRequestSchema::dpopJkt.eq(opts.dpop_jkt),
+                OAuthRequestSchema::createdAt.eq(&now),
+                OAuthRequestSchema::expiresAt.eq(opts.expires_at),
+            ))
+            .execute(conn)
+    })
+    .await?;
+    Ok(())
+}
+
+/// A request still waiting for the user to approve it.
+pub async fn get_pending_request(id: &str, db: &DbConn) -> Result<Option<OAuthRequest>> {
+    use crate::schema::pds::oauth_request::dsl as OAuthRequestSchema;
+
+    let id = id.to_owned();
+    let now = rsky_common::now();
+    let res = db
+        .run(move |conn| {
+            OAuthRequestSchema::oauth_request
+                .find(id)
+                .filter(OAuthRequestSchema::did.is_null())
+                .filter(OAuthRequestSchema::expiresAt.gt(now))
+                .select(OAuthRequest::as_select())
+                .first(conn)
+                .optional()
+        })
+        .await?;
+    Ok(res)
+}
+
+/// Marks a pending request approved by `did`, issuing `code` for it. False when the request
+/// expired or was already approved.
+pub async fn authorize_request(id: &str, did: &str, code: &str, db: &DbConn) -> Result<bool> {
+    use crate::schema::pds::oauth_request::dsl as OAuthRequestSchema;
+
+    let (id, did, code) = (id.to_owned(), did.to_owned(), code.to_owned());
+    let now = rsky_common::now();
+    let updated = db
+        .run(move |conn| {
+            update(OAuthRequestSchema::oauth_request)
+                .filter(OAuthRequestSchema::id.eq(id))
+                .filter(OAuthRequestSchema::did.is_null())
+                .filter(OAuthRequestSchema::expiresAt.gt(now))
+                .set((
+                    OAuthRequestSchema::did.eq(did),
+                    OAuthRequestSchema::code.eq(code),
+                    OAuthRequestSchema::expiresAt.eq(expires_in(CODE_LIFETIME)),
+                ))
+                .execute(conn)
+        })
+        .await?;
+    Ok(updated > 0)
+}
+
+pub async fn delete_request(id: &str, db: &DbConn) -> Result<()> {
+    use crate::schema::pds::oauth_request::dsl as OAuthRequestSchema;
+
+    let id = id.to_owned();
+    db.run(move |conn| {
+        delete(OAuthRequestSchema::oauth_request)
+            .filter(OAuthRequestSchema::id.eq(id))
+            .execute(conn)
+    })
+    .await?;
+    Ok(())
+}
+
+/// Removes and returns the approved request `code` was issued for, so a code works only once.
+pub async fn take_request_by_code(code: &str, db: &DbConn) -> Result<Option<OAuthRequest>> {
+    use crate::schema::pds::oauth_request::dsl as OAuthRequestSchema;
+
+    let code = code.to_owned();
+    let now = rsky_common::now();
+    let res = db
+        .run(move |conn| {
+            delete(OAuthRequestSchema::oauth_request)
+                .filter(OAuthRequestSchema::code.eq(code))
+                .returning(OAuthRequest::as_returning())
+                .get_result(conn)
+                .optional()
+        })
+        .await?;
+    Ok(res.filter(|request| request.expires_at > now))
+}
+
+pub struct CreateTokenOpts {
+    pub id: String,
+    pub did: String,
+    pub client_id: String,
+    pub client_auth: String,
+    pub scope: String,
+    pub dpop_jkt: String,
+    pub refresh_token: String,
+    pub expires_at: String,
+}
+
+pub async fn create_token(opts: CreateTokenOpts, db: &DbConn) -> Result<OAuthToken> {
+    use crate::schema::pds::oauth_token::dsl as OAuthTokenSchema;
+
+    let now = rsky_common::now();
+    let res = db
+        .run(move |conn| {
+            insert_into(OAuthTokenSchema::oauth_token)
+                .values((
+                    OAuthTokenSchema::id.eq(opts.id),
+                    OAuthTokenSchema::did.eq(opts.did),
+                    OAuthTokenSchema::clientId.eq(opts.client_id),
+                    OAuthTokenSchema::clientAuth.eq(opts.client_auth),
+                    OAuthTokenSchema::scope.eq(opts.scope),
+                    OAuthTokenSchema::dpopJkt.eq(opts.dpop_jkt),
+                    OAuthTokenSchema::refreshToken.eq(opts.refresh_token),
+                    OAuthTokenSchema::createdAt.eq(&now),
+                    OAuthTokenSchema::updatedAt.eq(&now),
+                    OAuthTokenSchema::expiresAt.eq(opts.expires_at),
+                ))
+                .returning(OAuthToken::as_returning())
+                .get_result(conn)
+        })
+        .await?;
+    Ok(res)
+}
+
+/// A session that hasn't expired or been revoked.
+pub async fn get_token(id: &str, db: &DbConn) -> Result<Option<OAuthToken>> {
+    use crate::schema::pds::oauth_token::dsl as OAuthTokenSchema;
+
+    let id = id.to_owned();
+    let now = rsky_common::now();
+    let res = db
+        .run(move |conn| {
+            OAuthTokenSchema::oauth_token
+                .find(id)
+                .filter(OAuthTokenSchema::expiresAt.gt(now))
+                .select(OAuthToken::as_select())
+                .first(conn)
+                .optional()
+        })
+        .await?;
+    Ok(res)
+}
+
+/// Swaps the session's refresh token `old` for `new`. None when `old` isn't current, including
+/// when it was already rotated by a concurrent request.
+pub async fn rotate_refresh_token(old: &str, new: &str, db: &DbConn) -> Result<Option<OAuthToken>> {
+    use crate::schema::pds::oauth_token::dsl as OAuthTokenSchema;
+
+    let (old, new) = (old.to_owned(), new.to_owned());
+    let now = rsky_common::now();
+    let res = db
+        .run(move |conn| {
+            update(OAuthTokenSchema::oauth_token)
+                .filter(OAuthTokenSchema::refreshToken.eq(old))
+                .filter(OAuthTokenSchema::expiresAt.gt(&now))
+                .set((
+                    OAuthTokenSchema::refreshToken.eq(new),
+                    OAuthTokenSchema::updatedAt.eq(&now),
+                ))
+                .returning(OAuthToken::as_returning())
+                .get_result(conn)
+                .optional()
+        })
+        .await?;
+    Ok(res)
+}
+
+/// Ends the session `token` is the id or refresh token of.
+pub async fn revoke_token(token: &str, db: &DbConn) -> Result<bool> {
+    use crate::schema::pds::oauth_token::dsl as OAuthTokenSchema;
+
+    let token = token.to_owned();
+    let deleted = db
+        .run(move |conn| {
+            delete(OAuthTokenSchema::oauth_token)
+                .filter(
+                    OAuthTokenSchema::id
+                        .eq(&token)
+                        .or(OAuthTokenSchema::refreshToken.eq(&token)),
+                )
+                .execute(conn)
+        })
+        .await?;
+    Ok(deleted > 0)
+}
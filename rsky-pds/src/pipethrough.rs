@@ -1,12 +1,14 @@
 use crate::apis::ApiError;
 use crate::auth_verifier::{AccessOutput, AccessStandard};
 use crate::config::{ServerConfig, ServiceConfig};
+use crate::http::{self, Destination};
+use crate::request_id::REQUEST_ID_HEADER;
 use crate::xrpc_server::types::{HandlerPipeThrough, InvalidRequestError, XRPCError};
-use crate::{context, SharedIdResolver, APP_USER_AGENT};
+use crate::{context, SharedIdResolver};
 use anyhow::{bail, Result};
 use lazy_static::lazy_static;
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
-use reqwest::{Client, RequestBuilder, Response};
+use reqwest::{RequestBuilder, Response};
 use rocket::data::ToByteUnit;
 use rocket::http::{Method, Status};
 use rocket::request::{FromRequest, Outcome, Request};
@@ -17,7 +19,6 @@ use serde::de::DeserializeOwned;
 use serde_json::Value as JsonValue;
 use std::collections::{BTreeMap, HashSet};
 use std::str::FromStr;
-use std::time::Duration;
 use url::Url;
 
 pub struct OverrideOpts {
@@ -228,11 +229,12 @@ pub async fn pipethrough_procedure_post<'r>(
 // Request setup/formatting
 // -------------------
 
-const REQ_HEADERS_TO_FORWARD: [&str; 4] = [
+const REQ_HEADERS_TO_FORWARD: [&str; 5] = [
     "accept-language",
     "content-type",
     "atproto-accept-labelers",
     "x-bsky-topics",
+    REQUEST_ID_HEADER,
 ];
 
 #[tracing::instrument(skip_all)]
@@ -312,33 +314,13 @@ pub fn format_req_init(
     body: Option<Vec<u8>>,
 ) -> Result<RequestBuilder> {
     match req.method {
-        Method::Get => {
-            let client = Client::builder()
-                .user_agent(APP_USER_AGENT)
-                .http2_keep_alive_while_idle(true)
-                .http2_keep_alive_timeout(Duration::from_secs(5))
-                .default_headers(headers)
-                .build()?;
-            Ok(client.get(url))
-        }
+        Method::Get => Ok(http::get(Destination::Proxy, url).headers(headers)),
         Method::Head => {
-            let client = Client::builder()
-                .user_agent(APP_USER_AGENT)
-                .http2_keep_alive_while_idle(true)
-                .http2_keep_alive_timeout(Duration::from_secs(5))
-                .default_headers(headers)
-                .build()?;
-            Ok(client.head(url))
-        }
-        Method::Post => {
-            let client = Client::builder()
-                .user_agent(APP_USER_AGENT)
-                .http2_keep_alive_while_idle(true)
-                .http2_keep_alive_timeout(Duration::from_secs(5))
-                .default_headers(headers)
-                .build()?;
-            Ok(client.post(url).body(body.unwrap()))
+            Ok(http::request(Destination::Proxy, reqwest::Method::HEAD, url).headers(headers))
         }
+        Method::Post => Ok(http::post(Destination::Proxy, url)
+            .headers(headers)
+            .body(body.unwrap())),
         _ => bail!(InvalidRequestError::MethodNotFound),
     }
 }
@@ -350,33 +332,13 @@ pub fn format_req_init_with_value(
     body: Option<JsonValue>,
 ) -> Result<RequestBuilder> {
     match req.method {
-        Method::Get => {
-            let client = Client::builder()
-                .user_agent(APP_USER_AGENT)
-                .http2_keep_alive_while_idle(true)
-                .http2_keep_alive_timeout(Duration::from_secs(5))
-                .default_headers(headers)
-                .build()?;
-            Ok(client.get(url))
-        }
+        Method::Get => Ok(http::get(Destination::Proxy, url).headers(headers)),
         Method::Head => {
-            let client = Client::builder()
-                .user_agent(APP_USER_AGENT)
-                .http2_keep_alive_while_idle(true)
-                .http2_keep_alive_timeout(Duration::from_secs(5))
-                .default_headers(headers)
-                .build()?;
-            Ok(client.head(url))
-        }
-        Method::Post => {
-            let client = Client::builder()
-                .user_agent(APP_USER_AGENT)
-                .http2_keep_alive_while_idle(true)
-                .http2_keep_alive_timeout(Duration::from_secs(5))
-                .default_headers(headers)
-                .build()?;
-            Ok(client.post(url).json(&body.unwrap()))
+            Ok(http::request(Destination::Proxy, reqwest::Method::HEAD, url).headers(headers))
         }
+        Method::Post => Ok(http::post(Destination::Proxy, url)
+            .headers(headers)
+            .json(&body.unwrap())),
         _ => bail!(InvalidRequestError::MethodNotFound),
     }
 }
@@ -546,24 +508,64 @@ lazy_static! {
 
 }
 
+/// Keeps sessions of app passwords that aren't privileged away from DMs and the other
+/// [`PRIVILEGED_METHODS`] when proxying.
+pub fn check_privileged_method(access: &AccessOutput, nsid: &str) -> Result<(), ApiError> {
+    let is_privileged = access
+        .credentials
+        .as_ref()
+        .and_then(|credentials| credentials.is_privileged)
+        .unwrap_or(false);
+    if !is_privileged && PRIVILEGED_METHODS.contains(nsid) {
+        return Err(ApiError::BadRequest(
+            "InvalidToken".to_string(),
+            "Bad token method".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Namespaces forwarded to their [`default_service`] without an `atproto-proxy` header. Other
+/// methods this PDS doesn't serve itself are only proxied to the service the header names.
+pub const DEFAULT_PROXY_NAMESPACES: [&str; 3] = ["app.bsky.", "chat.bsky.", "tools.ozone."];
+
+pub fn is_proxied_by_default(nsid: &str) -> bool {
+    nsid == Ids::ComAtprotoModerationCreateReport.as_str()
+        || DEFAULT_PROXY_NAMESPACES
+            .iter()
+            .any(|namespace| nsid.starts_with(namespace))
+}
+
+/// Whether a method without a route of its own may be forwarded on the requester's behalf:
+/// never the [`PROTECTED_METHODS`], the [`PRIVILEGED_METHODS`] only for privileged sessions,
+/// and outside [`DEFAULT_PROXY_NAMESPACES`] only when `atproto-proxy` says where to.
+pub fn check_proxied_method(
+    req: &ProxyRequest,
+    access: &AccessOutput,
+    nsid: &str,
+) -> Result<(), ApiError> {
+    if PROTECTED_METHODS.contains(nsid) {
+        return Err(ApiError::BadRequest(
+            "InvalidToken".to_string(),
+            "Bad token method".to_string(),
+        ));
+    }
+    check_privileged_method(access, nsid)?;
+    if !is_proxied_by_default(nsid) && !req.headers.contains_key("atproto-proxy") {
+        return Err(ApiError::MethodNotImplemented(format!(
+            "{nsid} is not implemented by this PDS; set atproto-proxy to forward it"
+        )));
+    }
+    Ok(())
+}
+
 pub async fn default_service<'r>(req: &'r ProxyRequest<'_>, nsid: &str) -> Option<ServiceConfig> {
     let cfg = req.cfg;
+    if nsid.starts_with("tools.ozone.") {
+        // the whole namespace, so methods newer than our lexicons still reach the mod service
+        return cfg.mod_service.clone();
+    }
     match Ids::from_str(nsid) {
-        Ok(Ids::ToolsOzoneTeamAddMember) => cfg.mod_service.clone(),
-        Ok(Ids::ToolsOzoneTeamDeleteMember) => cfg.mod_service.clone(),
-        Ok(Ids::ToolsOzoneTeamUpdateMember) => cfg.mod_service.clone(),
-        Ok(Ids::ToolsOzoneTeamListMembers) => cfg.mod_service.clone(),
-        Ok(Ids::ToolsOzoneCommunicationCreateTemplate) => cfg.mod_service.clone(),
-        Ok(Ids::ToolsOzoneCommunicationDeleteTemplate) => cfg.mod_service.clone(),
-        Ok(Ids::ToolsOzoneCommunicationUpdateTemplate) => cfg.mod_service.clone(),
-        Ok(Ids::ToolsOzoneCommunicationListTemplates) => cfg.mod_service.clone(),
-        Ok(Ids::ToolsOzoneModerationEmitEvent) => cfg.mod_service.clone(),
-        Ok(Ids::ToolsOzoneModerationGetEvent) => cfg.mod_service.clone(),
-        Ok(Ids::ToolsOzoneModerationGetRecord) => cfg.mod_service.clone(),
-        Ok(Ids::ToolsOzoneModerationGetRepo) => cfg.mod_service.clone(),
-        Ok(Ids::ToolsOzoneModerationQueryEvents) => cfg.mod_service.clone(),
-        Ok(Ids::ToolsOzoneModerationQueryStatuses) => cfg.mod_service.clone(),
-        Ok(Ids::ToolsOzoneModerationSearchRepos) => cfg.mod_service.clone(),
         Ok(Ids::ComAtprotoModerationCreateReport) => cfg.report_service.clone(),
         _ => cfg.bsky_app_view.clone(),
     }
@@ -601,3 +603,40 @@ pub fn is_safe_url(url: Url) -> bool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth_verifier::AuthScope;
+
+    #[test]
+    fn test_is_proxied_by_default() {
+        assert!(is_proxied_by_default("app.bsky.feed.getTimeline"));
+        assert!(is_proxied_by_default("chat.bsky.convo.listConvos"));
+        assert!(is_proxied_by_default("tools.ozone.moderation.queryEvents"));
+        assert!(is_proxied_by_default("com.atproto.moderation.createReport"));
+        assert!(!is_proxied_by_default("com.atproto.server.getSession"));
+        assert!(!is_proxied_by_default("com.example.feed.getThings"));
+    }
+
+    #[test]
+    fn test_check_privileged_method() {
+        let access = |scope| {
+            AccessOutput::access(
+                "did:plc:khvyd3oiw46vif5gm7hijslk".to_string(),
+                scope,
+                None,
+                "token".to_string(),
+            )
+        };
+        let nsid = "chat.bsky.convo.listConvos";
+        // full password sessions and privileged app passwords can reach DMs
+        assert!(check_privileged_method(&access(AuthScope::Access), nsid).is_ok());
+        assert!(check_privileged_method(&access(AuthScope::AppPassPrivileged), nsid).is_ok());
+        assert!(check_privileged_method(&access(AuthScope::AppPass), nsid).is_err());
+        assert!(
+            check_privileged_method(&access(AuthScope::AppPass), "app.bsky.feed.getTimeline")
+                .is_ok()
+        );
+    }
+}
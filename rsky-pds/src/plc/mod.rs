@@ -1,6 +1,6 @@
+use crate::http::{self, Destination};
 use crate::plc::operations::update_handle_op;
 use crate::plc::types::{CompatibleOp, OpOrTombstone};
-use crate::APP_USER_AGENT;
 use anyhow::{bail, Result};
 use rsky_common::encode_uri_component;
 use secp256k1::SecretKey;
@@ -26,11 +26,7 @@ impl Client {
         url: String,
         params: Option<Vec<(&str, String)>>,
     ) -> Result<T> {
-        let client = reqwest::Client::builder()
-            .user_agent(APP_USER_AGENT)
-            .build()?;
-        let mut builder = client
-            .get(url)
+        let mut builder = http::get(Destination::Plc, url)
             .header("Connection", "Keep-Alive")
             .header("Keep-Alive", "timeout=5, max=1000");
         if let Some(params) = params {
@@ -41,11 +37,7 @@ impl Client {
     }
 
     pub async fn send_operation(&self, did: &String, op: &OpOrTombstone) -> Result<()> {
-        let client = reqwest::Client::builder()
-            .user_agent(APP_USER_AGENT)
-            .build()?;
-        let response = client
-            .post(self.post_op_url(did))
+        let response = http::post(Destination::Plc, self.post_op_url(did))
             .json(op)
             .header("Connection", "Keep-Alive")
             .header("Keep-Alive", "timeout=5, max=1000")
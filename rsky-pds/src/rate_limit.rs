@@ -0,0 +1,323 @@
+//! Request rate limits for xrpc methods, on with `PDS_RATE_LIMITS_ENABLED=true`. Each
+//! [`RateLimitRule`] gives a budget per IP or per account, for one method or all of them, over a
+//! fixed window; `PDS_RATE_LIMITS` replaces the [`DEFAULT_RATE_LIMITS`](crate::config::DEFAULT_RATE_LIMITS).
+//! Requests over any budget answer `RateLimitExceeded` before reaching their route, and every
+//! limited response carries `RateLimit-*` headers for the tightest budget that applied.
+//!
+//! Counts live in memory unless `PDS_RATE_LIMITS_REDIS_URL` is set, in which case replicas
+//! share them. If Redis can't be reached requests are let through rather than turned away.
+
+use crate::apis::ApiError;
+use crate::auth_verifier::validate_bearer_token;
+use crate::client_ip::client_ip;
+use crate::config::{RateLimitConfig, RateLimitKey, RateLimitRule};
+use anyhow::Result;
+use redis::aio::ConnectionManager;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::Header;
+use rocket::{Data, Request, Response};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tokio::sync::OnceCell;
+
+// no route is mounted here, so rewritten requests fall through to the default catcher
+const RATE_LIMITED_PATH: &str = "/_rate_limited";
+
+/// Past this many tracked windows, expired ones are dropped on the next request.
+const MAX_TRACKED_WINDOWS: usize = 100_000;
+
+/// Where a budget stands after counting a request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Unix seconds when the window resets.
+    pub reset: u64,
+    pub window: u64,
+    pub exceeded: bool,
+}
+
+impl RateLimitStatus {
+    fn new(rule: &RateLimitRule, count: u32, window_start: u64) -> Self {
+        RateLimitStatus {
+            limit: rule.points,
+            remaining: rule.points.saturating_sub(count),
+            reset: window_start + rule.window,
+            window: rule.window,
+            exceeded: count > rule.points,
+        }
+    }
+
+    /// The standard `RateLimit-*` headers.
+    pub fn headers(&self) -> [Header<'static>; 4] {
+        [
+            Header::new("RateLimit-Limit", self.limit.to_string()),
+            Header::new("RateLimit-Remaining", self.remaining.to_string()),
+            Header::new("RateLimit-Reset", self.reset.to_string()),
+            Header::new(
+                "RateLimit-Policy",
+                format!("{};w={}", self.limit, self.window),
+            ),
+        ]
+    }
+}
+
+enum Counter {
+    Memory(Mutex<HashMap<String, (u64, u32)>>),
+    Redis {
+        client: redis::Client,
+        conn: OnceCell<ConnectionManager>,
+    },
+}
+
+impl Counter {
+    /// Counts a request against `key` in the window starting at `window_start`, returning the
+    /// count so far.
+    async fn hit(&self, key: &str, window: u64, window_start: u64) -> Result<u32> {
+        match self {
+            Counter::Memory(windows) => {
+                let mut windows = windows.lock().unwrap();
+                if windows.len() > MAX_TRACKED_WINDOWS {
+                    windows.retain(|_, (start, _)| *start >= window_start);
+                }
+                let entry = windows.entry(key.to_string()).or_insert((window_start, 0));
+                if entry.0 != window_start {
+                    *entry = (window_start, 0);
+                }
+                entry.1 += 1;
+                Ok(entry.1)
+            }
+            Counter::Redis { client, conn } => {
+                let mut conn = conn
+                    .get_or_try_init(|| ConnectionManager::new(client.clone()))
+                    .await?
+                    .clone();
+                // the key names its window, so it's never reset, only left to expire
+                let key = format!("rsky-pds:rl:{key}:{window_start}");
+                let (count,): (u32,) = redis::pipe()
+                    .atomic()
+                    .incr(&key, 1)
+                    .expire(&key, window as i64)
+                    .ignore()
+                    .query_async(&mut conn)
+                    .await?;
+                Ok(count)
+            }
+        }
+    }
+}
+
+/// Attach before `GracefulShutdown`, so rejected requests aren't counted as in-flight writes.
+/// The default has no rules and lets everything through.
+pub struct RateLimiter {
+    rules: Vec<RateLimitRule>,
+    counter: Counter,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        RateLimiter {
+            rules: Vec::new(),
+            counter: Counter::Memory(Mutex::default()),
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn new(cfg: &RateLimitConfig) -> Result<Self> {
+        let counter = match &cfg.redis_url {
+            Some(url) => Counter::Redis {
+                client: redis::Client::open(url.as_str())?,
+                conn: OnceCell::new(),
+            },
+            None => Counter::Memory(Mutex::default()),
+        };
+        Ok(RateLimiter {
+            rules: cfg.rules.clone(),
+            counter,
+        })
+    }
+
+    /// Counts a request to `nsid` against every rule that applies, returning the tightest
+    /// budget, which is exceeded if any of them is.
+    pub async fn check(
+        &self,
+        nsid: &str,
+        ip: Option<String>,
+        did: Option<String>,
+        now: u64,
+    ) -> Option<RateLimitStatus> {
+        let mut tightest: Option<RateLimitStatus> = None;
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !rule.applies_to(nsid) {
+                continue;
+            }
+            let subject = match rule.key {
+                RateLimitKey::Did => did.clone().or_else(|| ip.clone()),
+                RateLimitKey::Ip => ip.clone(),
+            };
+            let Some(subject) = subject else {
+                continue;
+            };
+            // rules may share a method and key, so the rule's position keeps their counts apart
+            let key = format!(
+                "{index}:{nsid}:{subject}",
+                nsid = rule.nsid.as_deref().unwrap_or("*")
+            );
+            let window_start = now - now % rule.window;
+            let count = match self.counter.hit(&key, rule.window, window_start).await {
+                Ok(count) => count,
+                Err(error) => {
+                    tracing::error!("@LOG: ERROR: failed to count rate limit {rule}: {error}");
+                    continue;
+                }
+            };
+            let status = RateLimitStatus::new(rule, count, window_start);
+            tightest = Some(match tightest {
+                None => status,
+                Some(current) => {
+                    let exceeded = current.exceeded || status.exceeded;
+                    let mut tighter = match status.remaining < current.remaining {
+                        true => status,
+                        false => current,
+                    };
+                    tighter.exceeded = exceeded;
+                    tighter
+                }
+            });
+        }
+        tightest
+    }
+
+    fn needs_did(&self, nsid: &str) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| rule.key == RateLimitKey::Did && rule.applies_to(nsid))
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for RateLimiter {
+    fn info(&self) -> Info {
+        Info {
+            name: "Rate limit xrpc methods",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        if self.rules.is_empty() {
+            return;
+        }
+        let Some(nsid) = request.uri().path().as_str().strip_prefix("/xrpc/") else {
+            return;
+        };
+        let nsid = nsid.to_string();
+        let ip = client_ip(request).map(|ip| ip.to_string());
+        let did = match self.needs_did(&nsid) {
+            true => validate_bearer_token(request, vec![], None)
+                .await
+                .ok()
+                .map(|bearer| bearer.did),
+            false => None,
+        };
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .expect("timestamp in seconds since UNIX epoch")
+            .as_secs();
+        let Some(status) = self.check(&nsid, ip, did, now).await else {
+            return;
+        };
+        request.local_cache(|| Some(status));
+        if status.exceeded {
+            request.local_cache(|| {
+                Some(ApiError::RateLimitExceeded(
+                    "Rate Limit Exceeded".to_string(),
+                ))
+            });
+            request.set_uri(Origin::parse(RATE_LIMITED_PATH).unwrap());
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let status: &Option<RateLimitStatus> = request.local_cache(|| None);
+        if let Some(status) = status {
+            for header in status.headers() {
+                response.set_header(header);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(rules: &[&str]) -> RateLimiter {
+        RateLimiter::new(&RateLimitConfig {
+            rules: rules.iter().map(|rule| rule.parse().unwrap()).collect(),
+            redis_url: None,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_check() {
+        let limiter = limiter(&[
+            "ip:*=100/60",
+            "ip:com.atproto.server.createSession=2/300",
+            "did:com.atproto.repo.uploadBlob=1/60",
+        ]);
+        let ip = Some("203.0.113.7".to_string());
+        let create_session = "com.atproto.server.createSession";
+
+        let status = limiter
+            .check(create_session, ip.clone(), None, 600)
+            .await
+            .unwrap();
+        assert_eq!((status.limit, status.remaining), (2, 1));
+        assert_eq!(status.reset, 900);
+        assert!(!status.exceeded);
+        limiter.check(create_session, ip.clone(), None, 610).await;
+        let status = limiter
+            .check(create_session, ip.clone(), None, 620)
+            .await
+            .unwrap();
+        assert!(status.exceeded);
+        // a new window starts over
+        let status = limiter
+            .check(create_session, ip.clone(), None, 900)
+            .await
+            .unwrap();
+        assert!(!status.exceeded);
+
+        // the global budget still applies to other methods
+        let status = limiter
+            .check("app.bsky.feed.getTimeline", ip.clone(), None, 620)
+            .await
+            .unwrap();
+        assert_eq!((status.limit, status.remaining), (100, 95));
+
+        // per-account budgets follow the account, not the address
+        let upload = "com.atproto.repo.uploadBlob";
+        let alice = Some("did:plc:alice".to_string());
+        assert!(
+            !limiter
+                .check(upload, ip.clone(), alice.clone(), 0)
+                .await
+                .unwrap()
+                .exceeded
+        );
+        assert!(
+            limiter
+                .check(upload, ip.clone(), alice, 1)
+                .await
+                .unwrap()
+                .exceeded
+        );
+        let bob = Some("did:plc:bob".to_string());
+        assert!(!limiter.check(upload, ip, bob, 2).await.unwrap().exceeded);
+    }
+}
@@ -58,9 +58,16 @@ impl<'r, T: Serialize> Responder<'r, 'static> for ReadAfterWriteResponse<T> {
             }
             ReadAfterWriteResponse::HandlerResponse(handler_response) => {
                 let mut builder = Response::build();
-                let encoding = handler_response.encoding.clone();
-                let headers = handler_response.headers.clone();
-                let bytes = serde_json::to_vec(&handler_response).unwrap();
+                let HandlerResponse {
+                    encoding,
+                    body,
+                    headers,
+                } = handler_response;
+                // only the munged view goes out; encoding and headers become response headers
+                let bytes = serde_json::to_vec(&body).map_err(|error| {
+                    tracing::error!("@LOG: ERROR: failed to serialize munged response: {error}");
+                    Status::InternalServerError
+                })?;
                 builder.sized_body(bytes.len(), Cursor::new(bytes));
                 builder
                     .status(Status::Ok)
@@ -100,7 +107,8 @@ pub fn get_local_lag(local: &LocalRecords) -> Result<Option<usize>> {
             let system_time = SystemTime::now();
             let now: DateTime<UtcOffset> = system_time.into();
             let duration = now - from_str_to_utc(&oldest);
-            Ok(Some(duration.num_milliseconds() as usize))
+            // a record indexed "in the future" by a skewed clock isn't lagging
+            Ok(Some(duration.num_milliseconds().max(0) as usize))
         }
     }
 }
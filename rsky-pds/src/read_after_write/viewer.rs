@@ -1,29 +1,21 @@
 use crate::account_manager::helpers::auth::ServiceJwtParams;
 use crate::account_manager::AccountManager;
+use crate::actor_store::repo::sqlite_repo::with_record_content;
 use crate::actor_store::ActorStore;
+use crate::config::keys;
+use crate::http::{self, Destination};
 use crate::models::models;
 use crate::read_after_write::types::{LocalRecords, RecordDescript};
 use crate::read_after_write::util;
 use crate::xrpc_server::auth::create_service_auth_headers;
-use crate::APP_USER_AGENT;
 use anyhow::{bail, Result};
-use atrium_api::app::bsky::feed::get_feed_generator::{
-    Output as AppBskyFeedGetFeedGeneratorOutput, Parameters as AppBskyFeedGetFeedGeneratorParams,
-    ParametersData as AppBskyFeedGetFeedGeneratorData,
-};
-use atrium_api::app::bsky::feed::get_posts::{
-    Output as AppBskyFeedGetPostsOutput, Parameters as AppBskyFeedGetPostsParams,
-    ParametersData as AppBskyFeedGetPostsData,
-};
-use atrium_api::app::bsky::graph::get_list::{
-    Output as AppBskyGraphGetListOutput, Parameters as AppBskyGraphGetListParams,
-    ParametersData as AppBskyGraphGetListData,
-};
+use atrium_api::app::bsky::feed::get_feed_generator::Output as AppBskyFeedGetFeedGeneratorOutput;
+use atrium_api::app::bsky::feed::get_posts::Output as AppBskyFeedGetPostsOutput;
+use atrium_api::app::bsky::graph::get_list::Output as AppBskyGraphGetListOutput;
 use atrium_api::client::AtpServiceClient;
 use atrium_xrpc_client::reqwest::{ReqwestClient, ReqwestClientBuilder};
 use diesel::*;
 use futures::stream::{self, StreamExt};
-use ipld_core::ipld::Ipld as AtriumIpld;
 use lexicon_cid::Cid;
 use reqwest::header::HeaderMap;
 use rsky_common::beginning_of_time;
@@ -44,8 +36,7 @@ use rsky_lexicon::app::bsky::graph::ListView;
 use rsky_repo::types::Ids;
 use rsky_syntax::aturi::AtUri;
 use rsky_syntax::handle::INVALID_HANDLE;
-use secp256k1::SecretKey;
-use std::env;
+use serde::de::DeserializeOwned;
 use std::str::FromStr;
 
 pub type Agent = AtpServiceClient<ReqwestClient>;
@@ -103,13 +94,7 @@ impl LocalViewer {
                         None => None,
                         Some(ref bsky_app_view_url) => {
                             let client = ReqwestClientBuilder::new(bsky_app_view_url.clone())
-                                .client(
-                                    reqwest::ClientBuilder::new()
-                                        .user_agent(APP_USER_AGENT)
-                                        .timeout(std::time::Duration::from_millis(1000))
-                                        .build()
-                                        .unwrap(),
-                                )
+                                .client(http::client().clone())
                                 .build();
                             Some(AtpServiceClient::new(client))
                         }
@@ -144,9 +129,7 @@ impl LocalViewer {
         match &self.appview_did {
             None => bail!("Could not find bsky appview did"),
             Some(appview_did) => {
-                let private_key = env::var("PDS_REPO_SIGNING_KEY_K256_PRIVATE_KEY_HEX").unwrap();
-                let keypair =
-                    SecretKey::from_slice(&hex::decode(private_key.as_bytes()).unwrap()).unwrap();
+                let keypair = keys::repo_signing_key()?;
                 create_service_auth_headers(ServiceJwtParams {
                     iss: did.to_owned(),
                     aud: appview_did.clone(),
@@ -187,17 +170,15 @@ impl LocalViewer {
                     .optional()
             })
             .await?;
+        let profile_res = with_record_content(profile_res.into_iter().collect())
+            .await?
+            .pop();
         let account_res = self.account_manager.get_account(&self.did, None).await?;
         match account_res {
             None => Ok(None),
             Some(account_res) => {
                 let record: Option<Profile> = match profile_res {
-                    Some(profile_res) => match profile_res.1 {
-                        Some(profile_res) => {
-                            serde_ipld_dagcbor::from_slice(profile_res.content.as_slice())?
-                        }
-                        None => None,
-                    },
+                    Some(profile_res) => serde_ipld_dagcbor::from_slice(profile_res.1.as_slice())?,
                     None => None,
                 };
                 Ok(Some(ProfileViewBasic {
@@ -395,18 +376,12 @@ impl LocalViewer {
             (Some(_), Some(_)) => {
                 let collection = AtUri::new(embed.record.uri.clone(), None)?.get_collection();
                 if collection == Ids::AppBskyFeedPost.as_str() {
-                    let appview_agent = self.get_authenticated_agent_for_nsid(&collection).await?;
-                    let res: AppBskyFeedGetPostsOutput = appview_agent
-                        .service
-                        .app
-                        .bsky
-                        .feed
-                        .get_posts(AppBskyFeedGetPostsParams {
-                            data: AppBskyFeedGetPostsData {
-                                uris: vec![embed.record.uri],
-                            },
-                            extra_data: AtriumIpld::Null,
-                        })
+                    let res: AppBskyFeedGetPostsOutput = self
+                        .appview_query(
+                            &collection,
+                            Ids::AppBskyFeedGetPosts.as_str(),
+                            &[("uris", embed.record.uri)],
+                        )
                         .await?;
                     match res.posts.first() {
                         None => Ok(None),
@@ -431,37 +406,23 @@ impl LocalViewer {
                         }
                     }
                 } else if collection == Ids::AppBskyFeedGenerator.as_str() {
-                    let appview_agent = self.get_authenticated_agent_for_nsid(&collection).await?;
-                    let res: AppBskyFeedGetFeedGeneratorOutput = appview_agent
-                        .service
-                        .app
-                        .bsky
-                        .feed
-                        .get_feed_generator(AppBskyFeedGetFeedGeneratorParams {
-                            data: AppBskyFeedGetFeedGeneratorData {
-                                feed: embed.record.uri,
-                            },
-                            extra_data: AtriumIpld::Null,
-                        })
+                    let res: AppBskyFeedGetFeedGeneratorOutput = self
+                        .appview_query(
+                            &collection,
+                            Ids::AppBskyFeedGetFeedGenerator.as_str(),
+                            &[("feed", embed.record.uri)],
+                        )
                         .await?;
                     let generator_view: GeneratorView =
                         serde_json::from_value(serde_json::to_value(&res.view)?)?;
                     Ok(Some(record::ViewUnion::GeneratorView(generator_view)))
                 } else if collection == Ids::AppBskyGraphList.as_str() {
-                    let appview_agent = self.get_authenticated_agent_for_nsid(&collection).await?;
-                    let res: AppBskyGraphGetListOutput = appview_agent
-                        .service
-                        .app
-                        .bsky
-                        .graph
-                        .get_list(AppBskyGraphGetListParams {
-                            data: AppBskyGraphGetListData {
-                                cursor: None,
-                                limit: None,
-                                list: embed.record.uri,
-                            },
-                            extra_data: AtriumIpld::Null,
-                        })
+                    let res: AppBskyGraphGetListOutput = self
+                        .appview_query(
+                            &collection,
+                            Ids::AppBskyGraphGetList.as_str(),
+                            &[("list", embed.record.uri)],
+                        )
                         .await?;
                     let list_view: ListView =
                         serde_json::from_value(serde_json::to_value(&res.list)?)?;
@@ -630,26 +591,25 @@ impl LocalViewer {
         }
     }
 
-    async fn get_authenticated_agent_for_nsid(
+    /// Runs the appview query `method` with service auth for `lxm`, on the shared client.
+    async fn appview_query<O: DeserializeOwned>(
         &self,
-        nsid: &String,
-    ) -> Result<AtpServiceClient<ReqwestClient>> {
-        let auth_headers = self.service_auth_headers(&self.did, nsid).await?;
+        lxm: &String,
+        method: &str,
+        params: &[(&str, String)],
+    ) -> Result<O> {
+        let auth_headers = self.service_auth_headers(&self.did, lxm).await?;
         let base = match self.appview_agent_str {
             None => bail!("no appview url configured"),
             Some(ref appview_agent_str) => appview_agent_str,
         };
-        let client = ReqwestClientBuilder::new(base)
-            .client(
-                reqwest::ClientBuilder::new()
-                    .user_agent(APP_USER_AGENT)
-                    .timeout(std::time::Duration::from_millis(1000))
-                    .default_headers(auth_headers)
-                    .build()
-                    .unwrap(),
-            )
-            .build();
-        Ok(AtpServiceClient::new(client))
+        let res = http::get(Destination::AppView, format!("{base}/xrpc/{method}"))
+            .headers(auth_headers)
+            .query(params)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(res.json().await?)
     }
 }
 
@@ -660,15 +620,20 @@ pub async fn get_records_since_rev(actor_store: &ActorStore, rev: String) -> Res
     let did = actor_store.did.clone();
     let did_1 = did.clone();
     let rev_1 = rev.clone();
-    let res: Vec<(models::Record, models::RepoBlock)> = actor_store
+    let res: Vec<(models::Record, Option<models::RepoBlock>)> = actor_store
         .record
         .db
         .run(move |conn| {
             RecordSchema::record
-                .inner_join(
-                    RepoBlockSchema::repo_block.on(RepoBlockSchema::cid.eq(RecordSchema::cid)),
+                .left_join(
+                    RepoBlockSchema::repo_block.on(RepoBlockSchema::cid
+                        .eq(RecordSchema::cid)
+                        .and(RepoBlockSchema::did.eq(RecordSchema::did))),
                 )
-                .select((models::Record::as_select(), models::RepoBlock::as_select()))
+                .select((
+                    models::Record::as_select(),
+                    Option::<models::RepoBlock>::as_select(),
+                ))
                 .filter(RecordSchema::did.eq(did_1))
                 .filter(RecordSchema::repoRev.gt(rev_1))
                 .limit(10)
@@ -703,7 +668,7 @@ pub async fn get_records_since_rev(actor_store: &ActorStore, rev: String) -> Res
     }
 
     // res.reduce() in javascript
-    res.into_iter().try_fold(
+    with_record_content(res).await?.into_iter().try_fold(
         LocalRecords {
             count: 0,
             profile: None,
@@ -714,19 +679,19 @@ pub async fn get_records_since_rev(actor_store: &ActorStore, rev: String) -> Res
             if uri.get_collection() == Ids::AppBskyActorProfile.as_str()
                 && uri.get_rkey() == "self".to_string()
             {
-                let profile: Profile = serde_ipld_dagcbor::from_slice(cur.1.content.as_slice())?;
+                let profile: Profile = serde_ipld_dagcbor::from_slice(cur.1.as_slice())?;
                 let descript = RecordDescript {
                     uri,
-                    cid: Cid::from_str(&cur.1.cid)?,
+                    cid: Cid::from_str(&cur.0.cid)?,
                     indexed_at: cur.0.indexed_at,
                     record: profile,
                 };
                 acc.profile = Some(descript);
             } else if uri.get_collection() == Ids::AppBskyFeedPost.as_str() {
-                let post: Post = serde_ipld_dagcbor::from_slice(cur.1.content.as_slice())?;
+                let post: Post = serde_ipld_dagcbor::from_slice(cur.1.as_slice())?;
                 let descript = RecordDescript {
                     uri,
-                    cid: Cid::from_str(&cur.1.cid)?,
+                    cid: Cid::from_str(&cur.0.cid)?,
                     indexed_at: cur.0.indexed_at,
                     record: post,
                 };
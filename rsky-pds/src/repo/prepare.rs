@@ -1,9 +1,11 @@
+use crate::apis::ApiError;
 use crate::lexicon::LEXICONS;
 use anyhow::bail;
 use lazy_static::lazy_static;
 use lexicon_cid::Cid;
 use rsky_common::ipld::cid_for_cbor;
-use rsky_common::tid::Ticker;
+use rsky_common::tid::TID;
+use rsky_lexicon::app::bsky::embed::Embeds;
 use rsky_lexicon::blob_refs::{BlobRef, JsonBlobRef};
 use rsky_repo::storage::Ipld;
 use rsky_repo::types::{
@@ -12,6 +14,7 @@ use rsky_repo::types::{
 };
 use rsky_repo::util::{cbor_to_lex, lex_to_ipld};
 use rsky_syntax::aturi::AtUri;
+use rsky_syntax::record_key::ensure_valid_record_key;
 use serde_json::{json, Value as JsonValue};
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -139,11 +142,32 @@ pub fn find_blob_refs(val: Lex, path: Option<Vec<String>>, layer: Option<u8>) ->
 
 pub fn assert_valid_record(record: &RepoRecord) -> anyhow::Result<()> {
     match record.get("$type") {
+        Some(Lex::Ipld(Ipld::String(record_type)))
+            if record_type == Ids::AppBskyFeedPost.as_str() =>
+        {
+            assert_valid_embed(record)
+        }
         Some(Lex::Ipld(Ipld::String(_))) => Ok(()),
         _ => bail!("No $type provided"),
     }
 }
 
+/// Refuses a post whose embed doesn't parse or breaks its lexicon's limits, e.g. too many
+/// images or an oversized video.
+fn assert_valid_embed(record: &RepoRecord) -> anyhow::Result<()> {
+    let Some(embed) = record.get("embed") else {
+        return Ok(());
+    };
+    let embed: Embeds = match serde_json::to_value(embed).and_then(serde_json::from_value) {
+        Ok(embed) => embed,
+        Err(error) => bail!(ApiError::InvalidRequest(format!("Invalid embed: {error}"))),
+    };
+    if let Err(error) = embed.validate() {
+        bail!(ApiError::InvalidRequest(format!("Invalid embed: {error}")));
+    }
+    Ok(())
+}
+
 pub fn set_collection_name(
     collection: &String,
     mut record: RepoRecord,
@@ -188,8 +212,15 @@ pub async fn prepare_create(opts: PrepareCreateOpts) -> anyhow::Result<PreparedC
     }
 
     // assert_no_explicit_slurs(rkey, record).await?;
-    let next_rkey = Ticker::new().next(None);
-    let rkey = rkey.unwrap_or(next_rkey.to_string());
+    let rkey = match rkey {
+        Some(rkey) => {
+            if let Err(error) = ensure_valid_record_key(&rkey) {
+                bail!(ApiError::InvalidRequest(error.to_string()));
+            }
+            rkey
+        }
+        None => TID::next().to_string(),
+    };
     let uri = AtUri::make(did, Some(collection), Some(rkey))?;
     Ok(PreparedCreateOrUpdate {
         action: WriteOpAction::Create,
@@ -265,3 +296,83 @@ lazy_static! {
         })
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(embed: JsonValue) -> RepoRecord {
+        serde_json::from_value(json!({
+            "$type": "app.bsky.feed.post",
+            "text": "hello",
+            "createdAt": "2025-01-01T00:00:00.000Z",
+            "embed": embed,
+        }))
+        .unwrap()
+    }
+
+    fn image(mime_type: &str, size: i64) -> JsonValue {
+        json!({
+            "alt": "",
+            "image": {
+                "$type": "blob",
+                "ref": { "$link": "bafkreibme22gw2h7y2h7tg2fhqotaqjucnbc24deqo72b6mkl2egezxhvy" },
+                "mimeType": mime_type,
+                "size": size,
+            },
+        })
+    }
+
+    fn images(images: Vec<JsonValue>) -> JsonValue {
+        json!({ "$type": "app.bsky.embed.images", "images": images })
+    }
+
+    fn assert_invalid_request(result: anyhow::Result<()>) {
+        let error = result.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ApiError>(),
+            Some(ApiError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn accepts_embed_within_limits() {
+        let record = post(images(vec![image("image/jpeg", 1_000)]));
+        assert_valid_record(&record).unwrap();
+    }
+
+    #[test]
+    fn rejects_embed_over_limits() {
+        assert_invalid_request(assert_valid_record(&post(images(vec![
+            image(
+                "image/png",
+                1_000
+            );
+            5
+        ]))));
+        assert_invalid_request(assert_valid_record(&post(images(vec![image(
+            "image/png",
+            2_000_000,
+        )]))));
+        assert_invalid_request(assert_valid_record(&post(images(vec![image(
+            "video/mp4",
+            1_000,
+        )]))));
+    }
+
+    #[test]
+    fn rejects_malformed_embed() {
+        let record = post(json!({ "$type": "app.bsky.embed.external", "external": "nope" }));
+        assert_invalid_request(assert_valid_record(&record));
+    }
+
+    #[test]
+    fn ignores_embeds_outside_posts() {
+        let mut record = post(json!({ "$type": "app.bsky.embed.external", "external": "nope" }));
+        record.insert(
+            "$type".to_string(),
+            Lex::Ipld(Ipld::String("com.example.note".to_string())),
+        );
+        assert_valid_record(&record).unwrap();
+    }
+}
@@ -0,0 +1,151 @@
+//! Per-request IDs for correlating client bug reports with server logs. Each request keeps the
+//! `x-request-id` it came in with, or is given a fresh one, which is then:
+//!
+//! - echoed back in the `x-request-id` response header and in xrpc error bodies,
+//! - forwarded to upstream services on proxied requests,
+//! - recorded on a `request` span around each route handler, so everything it logs carries it,
+//! - logged alongside every failed response.
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::request::{FromRequest, Outcome};
+use rocket::route::{self, Handler};
+use rocket::{Data, Request, Response, Route};
+use std::convert::Infallible;
+use tracing::Instrument;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    fn generate() -> Self {
+        RequestId(hex::encode(rand::random::<[u8; 16]>()))
+    }
+
+    /// Accepts an inbound ID if it's short, printable ASCII; anything else is replaced rather
+    /// than copied into logs and upstream requests.
+    fn parse(value: &str) -> Option<Self> {
+        let valid = !value.is_empty()
+            && value.len() <= MAX_REQUEST_ID_LEN
+            && value.bytes().all(|byte| byte.is_ascii_graphic());
+        valid.then(|| RequestId(value.to_string()))
+    }
+
+    /// The ID the `RequestIds` fairing gave `req`, if it's attached.
+    pub fn of<'r>(req: &'r Request<'_>) -> Option<&'r str> {
+        req.local_cache(|| None::<RequestId>)
+            .as_ref()
+            .map(|id| id.0.as_str())
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestId {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match RequestId::of(req) {
+            Some(id) => Outcome::Success(RequestId(id.to_string())),
+            None => Outcome::Success(RequestId::generate()),
+        }
+    }
+}
+
+/// Attach before any fairing that rewrites or rejects requests, so those get an ID too.
+pub struct RequestIds;
+
+#[rocket::async_trait]
+impl Fairing for RequestIds {
+    fn info(&self) -> Info {
+        Info {
+            name: "Assign and echo request IDs",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        let inbound = request.headers().get_one(REQUEST_ID_HEADER);
+        let id = match inbound.and_then(RequestId::parse) {
+            Some(id) => id,
+            None => {
+                let id = RequestId::generate();
+                // so guards and proxied requests further down see the same ID
+                request.replace_header(Header::new(REQUEST_ID_HEADER, id.0.clone()));
+                id
+            }
+        };
+        request.local_cache(|| Some(id));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let Some(id) = RequestId::of(request) else {
+            return;
+        };
+        response.set_raw_header(REQUEST_ID_HEADER, id);
+        let status = response.status();
+        if status.code >= 500 {
+            tracing::error!(
+                request_id = id,
+                "@LOG: {} {} failed with {status}",
+                request.method(),
+                request.uri().path()
+            );
+        } else if status.code >= 400 {
+            tracing::warn!(
+                request_id = id,
+                "@LOG: {} {} failed with {status}",
+                request.method(),
+                request.uri().path()
+            );
+        }
+    }
+}
+
+/// Runs a route's handler inside a `request` span carrying the request ID.
+#[derive(Clone)]
+struct Traced(Box<dyn Handler>);
+
+#[rocket::async_trait]
+impl Handler for Traced {
+    async fn handle<'r>(&self, req: &'r Request<'_>, data: Data<'r>) -> route::Outcome<'r> {
+        let span = tracing::info_span!(
+            "request",
+            request_id = RequestId::of(req).unwrap_or_default(),
+            method = %req.method(),
+            path = %req.uri().path(),
+        );
+        self.0.handle(req, data).instrument(span).await
+    }
+}
+
+/// Wraps each route's handler so its logs are recorded under the request's ID.
+pub fn traced(routes: Vec<Route>) -> Vec<Route> {
+    routes
+        .into_iter()
+        .map(|mut route| {
+            route.handler = Box::new(Traced(route.handler));
+            route
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_id() {
+        assert_eq!(
+            RequestId::parse("client-4f2a"),
+            Some(RequestId("client-4f2a".to_string()))
+        );
+        assert_eq!(RequestId::parse(""), None);
+        assert_eq!(RequestId::parse("has space"), None);
+        assert_eq!(RequestId::parse("line\nbreak"), None);
+        assert_eq!(RequestId::parse(&"a".repeat(MAX_REQUEST_ID_LEN + 1)), None);
+        assert_eq!(RequestId::generate().0.len(), 32);
+    }
+}
@@ -0,0 +1,204 @@
+//! Per-route feature flags, so operators can switch off an xrpc method under abuse, e.g. open
+//! registration or `importRepo`, without redeploying. Disabled methods answer
+//! `MethodNotImplemented` before reaching their route.
+//!
+//! `PDS_DISABLED_ROUTES` sets the defaults. Flags set through
+//! `xyz.blackskyweb.admin.updateRouteFlag` are stored in `pds.route_flag`, win over the defaults,
+//! and reach other replicas on their next refresh.
+
+use crate::apis::ApiError;
+use crate::db::DbConn;
+use crate::models::models;
+use anyhow::Result;
+use diesel::*;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::{Data, Request};
+use rsky_lexicon::xyz::blackskyweb::admin::RouteFlag;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Never disabled, so a flag can always be turned back off.
+const ALWAYS_ENABLED: &str = "xyz.blackskyweb.admin.updateRouteFlag";
+
+// no route is mounted here, so rewritten requests fall through to the default catcher
+const DISABLED_PATH: &str = "/_disabled";
+
+/// Shared with the refresh job, so clones see the same flags.
+#[derive(Clone, Default)]
+pub struct RouteFlags {
+    defaults: Arc<HashSet<String>>,
+    overrides: Arc<RwLock<BTreeMap<String, RouteFlag>>>,
+}
+
+impl RouteFlags {
+    pub fn new(disabled: Vec<String>) -> Self {
+        RouteFlags {
+            defaults: Arc::new(disabled.into_iter().collect()),
+            overrides: Arc::default(),
+        }
+    }
+
+    /// Why `nsid` is disabled, if it is.
+    pub fn disabled(&self, nsid: &str) -> Option<String> {
+        if nsid == ALWAYS_ENABLED {
+            return None;
+        }
+        match self.overrides.read().unwrap().get(nsid) {
+            Some(flag) if flag.disabled => Some(match &flag.reason {
+                Some(reason) => format!("{nsid} is disabled on this PDS: {reason}"),
+                None => format!("{nsid} is disabled on this PDS"),
+            }),
+            Some(_) => None,
+            None if self.defaults.contains(nsid) => Some(format!("{nsid} is disabled on this PDS")),
+            None => None,
+        }
+    }
+
+    /// Every method with a flag, whether from the defaults or set by an admin.
+    pub fn list(&self) -> Vec<RouteFlag> {
+        let overrides = self.overrides.read().unwrap();
+        let mut flags = overrides.clone();
+        for nsid in self.defaults.iter() {
+            flags.entry(nsid.clone()).or_insert_with(|| RouteFlag {
+                nsid: nsid.clone(),
+                disabled: true,
+                reason: None,
+            });
+        }
+        flags.into_values().collect()
+    }
+
+    /// Stores `flag` and applies it on this replica right away.
+    pub async fn set(&self, flag: RouteFlag, db: &DbConn) -> Result<()> {
+        use crate::schema::pds::route_flag::dsl as RouteFlagSchema;
+
+        let row = models::RouteFlag {
+            nsid: flag.nsid.clone(),
+            disabled: flag.disabled,
+            reason: flag.reason.clone(),
+            updated_at: rsky_common::now(),
+        };
+        db.run(move |conn| {
+            insert_into(RouteFlagSchema::route_flag)
+                .values(&row)
+                .on_conflict(RouteFlagSchema::nsid)
+                .do_update()
+                .set((
+                    RouteFlagSchema::disabled.eq(row.disabled),
+                    RouteFlagSchema::reason.eq(&row.reason),
+                    RouteFlagSchema::updatedAt.eq(&row.updated_at),
+                ))
+                .execute(conn)
+        })
+        .await?;
+        self.overrides
+            .write()
+            .unwrap()
+            .insert(flag.nsid.clone(), flag);
+        Ok(())
+    }
+
+    /// Reloads the stored flags.
+    pub async fn refresh(&self, db: &DbConn) -> Result<()> {
+        use crate::schema::pds::route_flag::dsl as RouteFlagSchema;
+
+        let rows = db
+            .run(|conn| {
+                RouteFlagSchema::route_flag
+                    .select(models::RouteFlag::as_select())
+                    .load(conn)
+            })
+            .await?;
+        let overrides = rows
+            .into_iter()
+            .map(|row| {
+                let flag = RouteFlag {
+                    nsid: row.nsid.clone(),
+                    disabled: row.disabled,
+                    reason: row.reason,
+                };
+                (row.nsid, flag)
+            })
+            .collect();
+        *self.overrides.write().unwrap() = overrides;
+        Ok(())
+    }
+}
+
+/// Runs [`RouteFlags::refresh`] every `interval` seconds until aborted.
+pub async fn run_refresher(interval: u64, flags: RouteFlags, db: DbConn) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval.max(1)));
+    loop {
+        ticker.tick().await;
+        if let Err(error) = flags.refresh(&db).await {
+            tracing::error!("@LOG: ERROR: failed to refresh route flags: {error}");
+        }
+    }
+}
+
+/// Attach before `GracefulShutdown`, so rejected requests aren't counted as in-flight writes.
+pub struct DisabledRoutes;
+
+#[rocket::async_trait]
+impl Fairing for DisabledRoutes {
+    fn info(&self) -> Info {
+        Info {
+            name: "Reject xrpc methods disabled by route flags",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        let Some(flags) = request.rocket().state::<RouteFlags>() else {
+            return;
+        };
+        let Some(nsid) = request.uri().path().as_str().strip_prefix("/xrpc/") else {
+            return;
+        };
+        if let Some(message) = flags.disabled(nsid) {
+            request.local_cache(|| Some(ApiError::MethodNotImplemented(message)));
+            request.set_uri(Origin::parse(DISABLED_PATH).unwrap());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_win_over_defaults() {
+        let flags = RouteFlags::new(vec!["com.atproto.repo.importRepo".to_string()]);
+        assert!(flags.disabled("com.atproto.repo.importRepo").is_some());
+        assert_eq!(flags.disabled("com.atproto.server.createAccount"), None);
+
+        flags.overrides.write().unwrap().extend([
+            (
+                "com.atproto.repo.importRepo".to_string(),
+                RouteFlag {
+                    nsid: "com.atproto.repo.importRepo".to_string(),
+                    disabled: false,
+                    reason: None,
+                },
+            ),
+            (
+                "com.atproto.server.createAccount".to_string(),
+                RouteFlag {
+                    nsid: "com.atproto.server.createAccount".to_string(),
+                    disabled: true,
+                    reason: Some("spam wave".to_string()),
+                },
+            ),
+        ]);
+        assert_eq!(flags.disabled("com.atproto.repo.importRepo"), None);
+        assert_eq!(
+            flags
+                .disabled("com.atproto.server.createAccount")
+                .as_deref(),
+            Some("com.atproto.server.createAccount is disabled on this PDS: spam wave")
+        );
+        assert_eq!(flags.list().len(), 2);
+    }
+}
@@ -13,6 +13,26 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.account_export (id) {
+            id -> Varchar,
+            did -> Varchar,
+            status -> Varchar,
+            createdAt -> Varchar,
+            completedAt -> Nullable<Varchar>,
+            error -> Nullable<Varchar>,
+            migration -> Bool,
+        }
+    }
+
+    diesel::table! {
+        pds.account_locale (did) {
+            did -> Varchar,
+            locale -> Varchar,
+            updatedAt -> Varchar,
+        }
+    }
+
     diesel::table! {
         pds.account_pref (id) {
             id -> Int4,
@@ -33,12 +53,24 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.admin_approval (action, subject) {
+            action -> Varchar,
+            subject -> Varchar,
+            requestedBy -> Varchar,
+            justification -> Varchar,
+            createdAt -> Varchar,
+            expiresAt -> Varchar,
+        }
+    }
+
     diesel::table! {
         pds.app_password (did, name) {
             did -> Varchar,
             name -> Varchar,
             password -> Varchar,
             createdAt -> Varchar,
+            privileged -> Bool,
         }
     }
 
@@ -64,6 +96,21 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.deleted_record (uri, cid) {
+            uri -> Varchar,
+            cid -> Varchar,
+            did -> Varchar,
+            collection -> Varchar,
+            rkey -> Varchar,
+            repoRev -> Nullable<Varchar>,
+            indexedAt -> Varchar,
+            content -> Bytea,
+            deletedAt -> Varchar,
+            expiresAt -> Varchar,
+        }
+    }
+
     diesel::table! {
         pds.did_doc (did) {
             did -> Varchar,
@@ -72,6 +119,14 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.email_preference (did) {
+            did -> Varchar,
+            preference -> Varchar,
+            updatedAt -> Varchar,
+        }
+    }
+
     diesel::table! {
         pds.email_token (purpose, did) {
             purpose -> Varchar,
@@ -81,6 +136,14 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.email_undeliverable (email) {
+            email -> Varchar,
+            reason -> Varchar,
+            createdAt -> Varchar,
+        }
+    }
+
     diesel::table! {
         pds.invite_code (code) {
             code -> Varchar,
@@ -100,6 +163,94 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.login_attempt (id) {
+            id -> Int8,
+            did -> Varchar,
+            success -> Bool,
+            method -> Varchar,
+            ip -> Nullable<Varchar>,
+            userAgent -> Nullable<Varchar>,
+            createdAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.moderation_audit (id) {
+            id -> Int8,
+            subjectDid -> Varchar,
+            action -> Varchar,
+            rule -> Varchar,
+            reportCount -> Int8,
+            expiresAt -> Nullable<Varchar>,
+            resolvedAt -> Nullable<Varchar>,
+            createdAt -> Varchar,
+            createdBy -> Nullable<Varchar>,
+            comment -> Nullable<Varchar>,
+        }
+    }
+
+    diesel::table! {
+        pds.moderation_report (id) {
+            id -> Int8,
+            subjectDid -> Varchar,
+            subjectUri -> Nullable<Varchar>,
+            reasonType -> Varchar,
+            reason -> Nullable<Varchar>,
+            reportedBy -> Varchar,
+            createdAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.oauth_request (id) {
+            id -> Varchar,
+            clientId -> Varchar,
+            clientAuth -> Varchar,
+            parameters -> Text,
+            dpopJkt -> Nullable<Varchar>,
+            did -> Nullable<Varchar>,
+            code -> Nullable<Varchar>,
+            createdAt -> Varchar,
+            expiresAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.oauth_token (id) {
+            id -> Varchar,
+            did -> Varchar,
+            clientId -> Varchar,
+            clientAuth -> Varchar,
+            scope -> Varchar,
+            dpopJkt -> Varchar,
+            refreshToken -> Varchar,
+            createdAt -> Varchar,
+            updatedAt -> Varchar,
+            expiresAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.pending_index (did) {
+            did -> Varchar,
+            rev -> Varchar,
+            createdAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.push_registration (did, token) {
+            did -> Varchar,
+            serviceDid -> Varchar,
+            token -> Varchar,
+            platform -> Varchar,
+            appId -> Varchar,
+            createdAt -> Varchar,
+            updatedAt -> Varchar,
+        }
+    }
+
     diesel::table! {
         pds.record (uri) {
             uri -> Varchar,
@@ -110,6 +261,7 @@ pub mod pds {
             repoRev -> Nullable<Varchar>,
             indexedAt -> Varchar,
             takedownRef -> Nullable<Varchar>,
+            valueJson -> Nullable<Jsonb>,
         }
     }
 
@@ -128,6 +280,7 @@ pub mod pds {
             expiresAt -> Varchar,
             nextId -> Nullable<Varchar>,
             appPasswordName -> Nullable<Varchar>,
+            sessionName -> Nullable<Varchar>,
         }
     }
 
@@ -141,6 +294,27 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.repo_commit (did, rev) {
+            did -> Varchar,
+            rev -> Varchar,
+            cid -> Varchar,
+            since -> Nullable<Varchar>,
+            creates -> Int4,
+            updates -> Int4,
+            deletes -> Int4,
+            committedAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.repo_commit_block (did, rev, cid) {
+            did -> Varchar,
+            rev -> Varchar,
+            cid -> Varchar,
+        }
+    }
+
     diesel::table! {
         pds.repo_root (did) {
             did -> Varchar,
@@ -161,22 +335,78 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.reserved_handle (name) {
+            name -> Varchar,
+            reason -> Nullable<Varchar>,
+            createdAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.route_flag (nsid) {
+            nsid -> Varchar,
+            disabled -> Bool,
+            reason -> Nullable<Varchar>,
+            updatedAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.totp_recovery_code (did, codeHash) {
+            did -> Varchar,
+            codeHash -> Varchar,
+            usedAt -> Nullable<Varchar>,
+        }
+    }
+
+    diesel::table! {
+        pds.totp_secret (did) {
+            did -> Varchar,
+            secret -> Varchar,
+            lastUsedStep -> Nullable<Int8>,
+            createdAt -> Varchar,
+            confirmedAt -> Nullable<Varchar>,
+            failedAttempts -> Int4,
+            lockedUntil -> Nullable<Varchar>,
+        }
+    }
+
     diesel::allow_tables_to_appear_in_same_query!(
         account,
+        account_export,
+        account_locale,
         account_pref,
         actor,
+        admin_approval,
         app_password,
         backlink,
         blob,
+        deleted_record,
         did_doc,
+        email_preference,
         email_token,
+        email_undeliverable,
         invite_code,
         invite_code_use,
+        login_attempt,
+        moderation_audit,
+        moderation_report,
+        oauth_request,
+        oauth_token,
+        pending_index,
+        push_registration,
         record,
         record_blob,
         refresh_token,
         repo_block,
+        repo_commit,
+        repo_commit_block,
         repo_root,
         repo_seq,
+        reserved_handle,
+        route_flag,
+        totp_recovery_code,
+        totp_secret,
     );
 }
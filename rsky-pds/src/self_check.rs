@@ -0,0 +1,167 @@
+//! `rsky-pds --check`: validates configuration, the database and its migrations, blob storage,
+//! the service keys and reachability of the PLC directory, prints a report and exits non-zero
+//! if anything failed, so it can gate a container's readiness before the server starts.
+
+use crate::actor_store::aws::s3::{load_sdk_config, S3BlobStore};
+use crate::config::keys::ServiceKeys;
+use crate::config::{env_to_cfg, ServerConfig};
+use crate::db::establish_connection_for_sequencer;
+use crate::db::migrations::{latest_known_version, schema_version, MIGRATIONS};
+use crate::http::{self, Destination};
+use anyhow::{anyhow, bail, Result};
+use aws_config::SdkConfig;
+use diesel_migrations::MigrationHarness;
+use std::fmt::{Display, Formatter};
+use std::panic;
+
+#[derive(Debug)]
+pub struct CheckResult {
+    pub name: &'static str,
+    /// What was found on success, or why it failed.
+    pub outcome: Result<String, String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub results: Vec<CheckResult>,
+}
+
+impl Report {
+    fn record(&mut self, name: &'static str, outcome: Result<String>) {
+        self.results.push(CheckResult {
+            name,
+            outcome: outcome.map_err(|error| format!("{error:#}")),
+        });
+    }
+
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|result| result.outcome.is_ok())
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for result in &self.results {
+            match &result.outcome {
+                Ok(detail) => writeln!(f, "ok    {}: {detail}", result.name)?,
+                Err(error) => writeln!(f, "FAIL  {}: {error}", result.name)?,
+            }
+        }
+        match self.passed() {
+            true => writeln!(f, "all checks passed"),
+            false => writeln!(f, "some checks failed"),
+        }
+    }
+}
+
+/// Runs every check. Later checks that need the config are skipped if it didn't load.
+pub async fn run() -> Report {
+    let mut report = Report::default();
+    let cfg = check_config();
+    report.record(
+        "config",
+        cfg.as_ref()
+            .map(|cfg| format!("{} ({})", cfg.service.hostname, cfg.service.did))
+            .map_err(|error| anyhow!("{error}")),
+    );
+    let Ok(cfg) = cfg else {
+        return report;
+    };
+    let auto_migrate = cfg.database.auto_migrate;
+    report.record(
+        "database",
+        tokio::task::spawn_blocking(move || check_database(auto_migrate))
+            .await
+            .unwrap_or_else(|error| Err(error.into())),
+    );
+    let sdk_config = load_sdk_config().await;
+    report.record("blobstore", check_blobstore(&cfg, &sdk_config).await);
+    report.record("keys", check_keys(&sdk_config).await);
+    report.record("plc", check_plc(&cfg).await);
+    report
+}
+
+/// Config loading panics on bad values; turn those into a failure with the panic's message.
+fn check_config() -> Result<ServerConfig, String> {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let cfg = panic::catch_unwind(env_to_cfg);
+    panic::set_hook(hook);
+    cfg.map_err(|payload| {
+        payload
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| {
+                payload
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+            })
+            .unwrap_or_else(|| "invalid configuration".to_string())
+    })
+}
+
+fn check_database(auto_migrate: bool) -> Result<String> {
+    let conn = &mut establish_connection_for_sequencer()?;
+    let current = schema_version(conn)?.unwrap_or_else(|| "empty".to_string());
+    let known = latest_known_version().unwrap_or_default();
+    if current != "empty" && current > known {
+        bail!("schema is at {current}, newer than this build's {known}");
+    }
+    let pending = conn
+        .pending_migrations(MIGRATIONS)
+        .map_err(|error| anyhow!("failed to read pending migrations: {error}"))?
+        .len();
+    match (pending, auto_migrate) {
+        (0, _) => Ok(format!("schema at {current}")),
+        (pending, true) => Ok(format!(
+            "schema at {current}, {pending} pending migrations will run on boot"
+        )),
+        (pending, false) => bail!(
+            "schema at {current} with {pending} pending migrations and PDS_DB_AUTO_MIGRATE=false"
+        ),
+    }
+}
+
+/// Writes, reads back and deletes a temp blob under the service DID.
+async fn check_blobstore(cfg: &ServerConfig, sdk_config: &SdkConfig) -> Result<String> {
+    let blobstore = S3BlobStore::new(cfg.service.did.clone(), sdk_config);
+    let key = blobstore.put_temp(b"rsky-pds self-check".to_vec()).await?;
+    let readable = blobstore.has_temp(key.clone()).await?;
+    blobstore.delete_temp(key).await?;
+    if !readable {
+        bail!("wrote a temp blob but could not read it back");
+    }
+    Ok("write, read and delete".to_string())
+}
+
+async fn check_keys(sdk_config: &SdkConfig) -> Result<String> {
+    ServiceKeys::load(Some(sdk_config)).await?;
+    Ok("repo signing and PLC rotation keys loaded".to_string())
+}
+
+async fn check_plc(cfg: &ServerConfig) -> Result<String> {
+    let url = format!("{}/_health", cfg.identity.plc_url.trim_end_matches('/'));
+    let res = http::get(Destination::Plc, &url).send().await?;
+    if !res.status().is_success() {
+        bail!("{url} returned {}", res.status());
+    }
+    Ok(cfg.identity.plc_url.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let mut report = Report::default();
+        report.record("config", Ok("pds.example.com".to_string()));
+        assert!(report.passed());
+        report.record("plc", Err(anyhow!("timed out")));
+        assert!(!report.passed());
+        assert_eq!(
+            report.to_string(),
+            "ok    config: pds.example.com\nFAIL  plc: timed out\nsome checks failed\n"
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! Long-term firehose archive. With `PDS_SEQ_ARCHIVE_BUCKET` set, every finished hour of
+//! `repo_seq` is uploaded as `<prefix>/YYYY/MM/DD/HH.cbor.gz`: a gzipped run of CBOR-encoded
+//! [`ArchivedEvt`]s in seq order. Progress is kept in `<prefix>/checkpoint.json` next to the
+//! archives, so a restarted (or different) PDS instance picks up where the last one stopped and
+//! every event lands in exactly one file, even when its clock put it in an earlier hour.
+//!
+//! `rsky-pds-replay` reads the archives back, to inspect them or to restore events into
+//! `repo_seq`.
+
+use crate::config::SeqArchiveConfig;
+use crate::db::establish_connection_for_sequencer;
+use crate::models;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use aws_sdk_s3 as s3;
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::operation::get_object::GetObjectError;
+use aws_sdk_s3::primitives::ByteStream;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use diesel::*;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rsky_common::env::env_bool;
+use rsky_common::RFC3339_VARIANT;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+// Rows loaded per query while building an hour's file
+const PAGE_SIZE: i64 = 10_000;
+
+/// One `repo_seq` row as stored in the archive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedEvt {
+    pub seq: i64,
+    pub did: String,
+    #[serde(rename = "eventType")]
+    pub event_type: String,
+    #[serde(with = "serde_bytes")]
+    pub event: Vec<u8>,
+    pub invalidated: i16,
+    #[serde(rename = "sequencedAt")]
+    pub sequenced_at: String,
+}
+
+impl TryFrom<models::RepoSeq> for ArchivedEvt {
+    type Error = anyhow::Error;
+
+    fn try_from(row: models::RepoSeq) -> Result<Self> {
+        Ok(ArchivedEvt {
+            seq: row
+                .seq
+                .ok_or_else(|| anyhow::anyhow!("repo_seq row without a seq"))?,
+            did: row.did,
+            event_type: row.event_type,
+            event: row.event,
+            invalidated: row.invalidated.unwrap_or(0),
+            sequenced_at: row.sequenced_at,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Checkpoint {
+    /// Start of the last hour archived.
+    hour: String,
+    /// Last seq archived.
+    seq: i64,
+}
+
+pub fn encode_evts(evts: &[ArchivedEvt]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    for evt in evts {
+        serde_cbor::to_writer(&mut encoder, evt)?;
+    }
+    encoder.flush()?;
+    Ok(encoder.finish()?)
+}
+
+pub fn decode_evts(bytes: &[u8]) -> Result<Vec<ArchivedEvt>> {
+    let mut buf = Vec::new();
+    GzDecoder::new(bytes).read_to_end(&mut buf)?;
+    Ok(serde_cbor::Deserializer::from_slice(&buf)
+        .into_iter::<ArchivedEvt>()
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+fn parse_time(time: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(time)?.with_timezone(&Utc))
+}
+
+fn start_of_hour(time: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    Ok(time.duration_trunc(Duration::hours(1))?)
+}
+
+#[derive(Debug, Clone)]
+pub struct SeqArchive {
+    client: s3::Client,
+    pub bucket: String,
+    pub prefix: String,
+}
+
+impl SeqArchive {
+    pub fn new(cfg: &SeqArchiveConfig, sdk_config: &SdkConfig) -> Self {
+        let s3_config = s3::config::Builder::from(sdk_config)
+            .force_path_style(env_bool("AWS_S3_FORCE_PATH_STYLE").unwrap_or(false))
+            .build();
+        SeqArchive {
+            client: s3::Client::from_conf(s3_config),
+            bucket: cfg.bucket.clone(),
+            prefix: cfg.prefix.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn hour_key(&self, hour: DateTime<Utc>) -> String {
+        format!("{}/{}.cbor.gz", self.prefix, hour.format("%Y/%m/%d/%H"))
+    }
+
+    fn checkpoint_key(&self) -> String {
+        format!("{}/checkpoint.json", self.prefix)
+    }
+
+    async fn get(&self, key: String) -> Result<Option<Vec<u8>>> {
+        let res = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await;
+        match res {
+            Ok(res) => Ok(Some(res.body.collect().await?.into_bytes().to_vec())),
+            Err(SdkError::ServiceError(e)) if matches!(e.err(), GetObjectError::NoSuchKey(_)) => {
+                Ok(None)
+            }
+            Err(e) => Err(anyhow::Error::new(e.into_service_error())),
+        }
+    }
+
+    async fn put(&self, key: String, bytes: Vec<u8>, content_type: &str) -> Result<()> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .body(ByteStream::from(bytes))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn checkpoint(&self) -> Result<Option<Checkpoint>> {
+        match self.get(self.checkpoint_key()).await? {
+            None => Ok(None),
+            Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        }
+    }
+
+    async fn put_checkpoint(&self, checkpoint: &Checkpoint) -> Result<()> {
+        self.put(
+            self.checkpoint_key(),
+            serde_json::to_vec(checkpoint)?,
+            "application/json",
+        )
+        .await
+    }
+
+    /// Events archived for the hour starting at `hour`, empty if nothing was sequenced then.
+    pub async fn read_hour(&self, hour: DateTime<Utc>) -> Result<Vec<ArchivedEvt>> {
+        match self.get(self.hour_key(hour)).await? {
+            None => Ok(vec![]),
+            Some(bytes) => decode_evts(&bytes),
+        }
+    }
+
+    /// Uploads every hour that has ended since the checkpoint, returning how many files were
+    /// written.
+    pub async fn archive_finished_hours(&self) -> Result<usize> {
+        use crate::schema::pds::repo_seq::dsl as RepoSeqSchema;
+
+        let current_hour = start_of_hour(Utc::now())?;
+        let (mut hour, mut last_seq) = match self.checkpoint().await? {
+            Some(checkpoint) => (
+                parse_time(&checkpoint.hour)? + Duration::hours(1),
+                checkpoint.seq,
+            ),
+            None => {
+                let conn = &mut establish_connection_for_sequencer()?;
+                let earliest: Option<String> = RepoSeqSchema::repo_seq
+                    .select(RepoSeqSchema::sequencedAt)
+                    .order_by(RepoSeqSchema::seq.asc())
+                    .first(conn)
+                    .optional()?;
+                match earliest {
+                    None => return Ok(0),
+                    Some(earliest) => (start_of_hour(parse_time(&earliest)?)?, 0),
+                }
+            }
+        };
+
+        let mut written = 0;
+        // empty hours get no file, only a checkpoint once the walk is over
+        let mut skipped = false;
+        while hour < current_hour {
+            let end = (hour + Duration::hours(1))
+                .format(RFC3339_VARIANT)
+                .to_string();
+            let mut evts: Vec<ArchivedEvt> = Vec::new();
+            let conn = &mut establish_connection_for_sequencer()?;
+            loop {
+                let after = evts.last().map_or(last_seq, |evt| evt.seq);
+                let rows: Vec<models::RepoSeq> = RepoSeqSchema::repo_seq
+                    .filter(RepoSeqSchema::seq.gt(after))
+                    .filter(RepoSeqSchema::sequencedAt.lt(&end))
+                    .select(models::RepoSeq::as_select())
+                    .order_by(RepoSeqSchema::seq.asc())
+                    .limit(PAGE_SIZE)
+                    .load(conn)?;
+                let done = (rows.len() as i64) < PAGE_SIZE;
+                for row in rows {
+                    evts.push(row.try_into()?);
+                }
+                if done {
+                    break;
+                }
+            }
+            match evts.last() {
+                None => skipped = true,
+                Some(last) => {
+                    last_seq = last.seq;
+                    self.put(
+                        self.hour_key(hour),
+                        encode_evts(&evts)?,
+                        "application/octet-stream",
+                    )
+                    .await?;
+                    self.put_checkpoint(&Checkpoint {
+                        hour: hour.format(RFC3339_VARIANT).to_string(),
+                        seq: last_seq,
+                    })
+                    .await?;
+                    tracing::info!(
+                        "@LOG: archived {} events to {}",
+                        evts.len(),
+                        self.hour_key(hour)
+                    );
+                    written += 1;
+                    skipped = false;
+                }
+            }
+            hour = hour + Duration::hours(1);
+        }
+        if skipped {
+            self.put_checkpoint(&Checkpoint {
+                hour: (hour - Duration::hours(1))
+                    .format(RFC3339_VARIANT)
+                    .to_string(),
+                seq: last_seq,
+            })
+            .await?;
+        }
+        Ok(written)
+    }
+}
+
+pub async fn run_archiver(interval: u64, archive: SeqArchive) {
+    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(interval.max(1)));
+    loop {
+        ticker.tick().await;
+        if let Err(error) = archive.archive_finished_hours().await {
+            tracing::error!("@LOG: ERROR: failed to archive sequenced events: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        let evts = (1..=3)
+            .map(|seq| ArchivedEvt {
+                seq,
+                did: "did:example:alice".to_string(),
+                event_type: "append".to_string(),
+                event: vec![seq as u8; 4],
+                invalidated: 0,
+                sequenced_at: "2025-01-01T13:00:00.000Z".to_string(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(decode_evts(&encode_evts(&evts).unwrap()).unwrap(), evts);
+        assert!(decode_evts(&encode_evts(&[]).unwrap()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_start_of_hour() {
+        let time = parse_time("2025-01-01T13:45:12.345Z").unwrap();
+        assert_eq!(
+            start_of_hour(time)
+                .unwrap()
+                .format(RFC3339_VARIANT)
+                .to_string(),
+            "2025-01-01T13:00:00.000Z"
+        );
+    }
+}
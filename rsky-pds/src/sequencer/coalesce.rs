@@ -0,0 +1,109 @@
+//! Coalesces sequencer inserts from concurrent writes. With `PDS_SEQUENCER_COALESCE_MS` set,
+//! [`Sequencer::sequence_evt`](super::Sequencer::sequence_evt) hands its event to a background
+//! writer instead of inserting it. The writer waits up to that long for other events to arrive,
+//! inserts the batch with one statement over a connection it keeps open, and notifies crawlers
+//! once for the lot.
+
+use crate::config::SeqCoalesceConfig;
+use crate::crawlers::Crawlers;
+use crate::db::establish_connection_for_sequencer;
+use crate::models;
+use crate::sequencer::insert_evts;
+use anyhow::{anyhow, Result};
+use diesel::PgConnection;
+use lazy_static::lazy_static;
+use std::future::Future;
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{timeout_at, Instant};
+
+struct PendingEvt {
+    evt: models::RepoSeq,
+    done: oneshot::Sender<Result<i64, String>>,
+}
+
+lazy_static! {
+    static ref SEQ_WRITER: RwLock<Option<mpsc::UnboundedSender<PendingEvt>>> = RwLock::new(None);
+}
+
+/// Installs the writer for the rest of the process, returning the loop that drains it for the
+/// caller to spawn.
+pub fn set_seq_writer(config: SeqCoalesceConfig, crawlers: Crawlers) -> impl Future<Output = ()> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    *SEQ_WRITER.write().unwrap() = Some(tx);
+    run_writer(config, crawlers, rx)
+}
+
+pub fn is_enabled() -> bool {
+    SEQ_WRITER.read().unwrap().is_some()
+}
+
+/// Queues `evt` for the next batch and waits for its sequence number.
+pub async fn submit(evt: models::RepoSeq) -> Result<i64> {
+    let Some(tx) = SEQ_WRITER.read().unwrap().clone() else {
+        return Err(anyhow!("Sequencer writer isn't running"));
+    };
+    let (done, seq) = oneshot::channel();
+    if tx.send(PendingEvt { evt, done }).is_err() {
+        return Err(anyhow!("Sequencer writer has stopped"));
+    }
+    match seq.await {
+        Ok(seq) => seq.map_err(|error| anyhow!(error)),
+        Err(_) => Err(anyhow!("Sequencer writer has stopped")),
+    }
+}
+
+async fn run_writer(
+    config: SeqCoalesceConfig,
+    crawlers: Crawlers,
+    mut rx: mpsc::UnboundedReceiver<PendingEvt>,
+) {
+    let window = Duration::from_millis(config.window);
+    let mut conn: Option<PgConnection> = None;
+    while let Some(first) = rx.recv().await {
+        let deadline = Instant::now() + window;
+        let mut batch = vec![first];
+        while batch.len() < config.max_batch {
+            match timeout_at(deadline, rx.recv()).await {
+                Ok(Some(pending)) => batch.push(pending),
+                _ => break,
+            }
+        }
+        let (evts, waiters): (Vec<_>, Vec<_>) = batch
+            .into_iter()
+            .map(|pending| (pending.evt, pending.done))
+            .unzip();
+
+        let count = evts.len();
+        let inserted = match conn.take() {
+            Some(conn) => Ok(conn),
+            None => establish_connection_for_sequencer(),
+        }
+        .and_then(|mut open| {
+            let seqs = insert_evts(&mut open, evts);
+            // a failed insert may have left the connection unusable, so start over next time
+            if seqs.is_ok() {
+                conn = Some(open);
+            }
+            seqs
+        });
+        match inserted {
+            Ok(seqs) => {
+                tracing::debug!("@LOG: sequenced {count} events in one insert");
+                for (done, seq) in waiters.into_iter().zip(seqs) {
+                    let _ = done.send(Ok(seq));
+                }
+                if let Err(error) = crawlers.notify_of_update().await {
+                    tracing::warn!("@LOG: failed to notify crawlers: {error}");
+                }
+            }
+            Err(error) => {
+                tracing::error!("@LOG: failed to sequence {count} events: {error}");
+                for done in waiters {
+                    let _ = done.send(Err(error.to_string()));
+                }
+            }
+        }
+    }
+}
@@ -13,6 +13,11 @@ use serde::de::Error as DeserializerError;
 use serde::{Deserialize, Deserializer};
 use std::fmt;
 
+/// Commits with more ops than this are sequenced as `tooBig`.
+pub const MAX_COMMIT_OPS: usize = 200;
+/// Commits whose blocks add up to more than this many bytes are sequenced as `tooBig`.
+pub const MAX_COMMIT_BLOCKS_BYTES: usize = 1_000_000;
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum CommitEvtOpAction {
@@ -215,13 +220,36 @@ impl SeqEvt {
     }
 }
 
+/// Whether `commit_data` is past what a single firehose frame carries, in which case its event
+/// is sent without the record blocks and consumers sync the repo to catch up.
+pub fn is_too_big(commit_data: &CommitDataWithOps) -> Result<bool> {
+    if commit_data.ops.len() > MAX_COMMIT_OPS {
+        return Ok(true);
+    }
+    let blocks_bytes = commit_data.commit_data.new_blocks.byte_size()?
+        + commit_data.commit_data.relevant_blocks.byte_size()?;
+    Ok(blocks_bytes > MAX_COMMIT_BLOCKS_BYTES)
+}
+
 pub async fn format_seq_commit(
     did: String,
     commit_data: CommitDataWithOps,
 ) -> Result<models::RepoSeq> {
+    let too_big = is_too_big(&commit_data)?;
     let mut blocks_to_send = BlockMap::new();
-    blocks_to_send.add_map(commit_data.commit_data.new_blocks)?;
-    blocks_to_send.add_map(commit_data.commit_data.relevant_blocks)?;
+    if too_big {
+        // just the signed commit; consumers fetch the rest from the repo
+        if let Some(root) = commit_data
+            .commit_data
+            .new_blocks
+            .get(commit_data.commit_data.cid)
+        {
+            blocks_to_send.set(commit_data.commit_data.cid, root.clone());
+        }
+    } else {
+        blocks_to_send.add_map(commit_data.commit_data.new_blocks)?;
+        blocks_to_send.add_map(commit_data.commit_data.relevant_blocks)?;
+    }
     let ops = commit_data
         .ops
         .iter()
@@ -244,7 +272,7 @@ pub async fn format_seq_commit(
 
     let evt = CommitEvt {
         rebase: false,
-        too_big: false, // always false in Sync 1.1
+        too_big, // always false in Sync 1.1 unless the commit is past the limits above
         repo: did.clone(),
         commit: commit_data.commit_data.cid,
         prev: commit_data.commit_data.prev,
@@ -351,3 +379,50 @@ pub async fn sync_evt_data_from_commit(mut commit_data: CommitDataWithOps) -> Re
         Err(e) => Err(e.into()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsky_repo::cid_set::CidSet;
+    use rsky_repo::types::{CommitData, CommitOp};
+    use std::str::FromStr;
+
+    const CID: &str = "bafyreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm";
+
+    fn commit(ops: usize, block_bytes: usize) -> CommitDataWithOps {
+        let cid = Cid::from_str(CID).unwrap();
+        let mut new_blocks = BlockMap::new();
+        new_blocks.set(cid, vec![0; block_bytes]);
+        let op = CommitOp {
+            action: CommitAction::Create,
+            path: "app.bsky.feed.post/3jzfcijpj2z2a".to_string(),
+            cid: Some(cid),
+            prev: None,
+        };
+        CommitDataWithOps {
+            commit_data: CommitData {
+                cid,
+                rev: "3jzfcijpj2z2a".to_string(),
+                since: None,
+                prev: None,
+                new_blocks,
+                relevant_blocks: BlockMap::new(),
+                removed_cids: CidSet::new(None),
+            },
+            ops: vec![op; ops],
+            prev_data: None,
+        }
+    }
+
+    #[test]
+    fn test_is_too_big_at_op_limit() {
+        assert!(!is_too_big(&commit(MAX_COMMIT_OPS, 1)).unwrap());
+        assert!(is_too_big(&commit(MAX_COMMIT_OPS + 1, 1)).unwrap());
+    }
+
+    #[test]
+    fn test_is_too_big_at_byte_limit() {
+        assert!(!is_too_big(&commit(1, MAX_COMMIT_BLOCKS_BYTES)).unwrap());
+        assert!(is_too_big(&commit(1, MAX_COMMIT_BLOCKS_BYTES + 1)).unwrap());
+    }
+}
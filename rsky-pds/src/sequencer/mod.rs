@@ -12,13 +12,22 @@ use anyhow::Result;
 use diesel::*;
 use events::format_seq_sync_evt;
 use futures::{Stream, StreamExt};
+use lazy_static::lazy_static;
 use rsky_common::time::SECOND;
 use rsky_common::{cbor_to_struct, wait};
 use rsky_repo::types::CommitDataWithOps;
 use std::cmp;
 use std::pin::Pin;
+use std::sync::Mutex;
 use std::task::{Context, Poll, Waker};
 
+lazy_static! {
+    /// Serializes inserts made outside the coalescing writer. Serial values are drawn before
+    /// commit, and the poller only reads past `last_seen`, so a lower seq committing after a
+    /// higher one would never be emitted.
+    static ref SEQ_INSERT_LOCK: Mutex<()> = Mutex::new(());
+}
+
 pub struct RequestSeqRangeOpts {
     pub earliest_seq: Option<i64>,
     pub latest_seq: Option<i64>,
@@ -200,24 +209,23 @@ impl Sequencer {
         }
     }
 
-    pub async fn sequence_evt(&mut self, evt: models::RepoSeq) -> Result<i64> {
-        use crate::schema::pds::repo_seq::dsl as RepoSeqSchema;
-        let conn = &mut establish_connection_for_sequencer()?;
-
-        let res = insert_into(RepoSeqSchema::repo_seq)
-            .values((
-                RepoSeqSchema::did.eq(evt.did),
-                RepoSeqSchema::event.eq(evt.event),
-                RepoSeqSchema::eventType.eq(evt.event_type),
-                RepoSeqSchema::sequencedAt.eq(evt.sequenced_at),
-            ))
-            .get_result::<models::RepoSeq>(conn)?;
+    /// Inserts `evt`, returning its sequence number. Goes through the coalescing writer when
+    /// one is installed, see [`coalesce`], and otherwise inserts under `SEQ_INSERT_LOCK`.
+    pub async fn sequence_evt(&self, evt: models::RepoSeq) -> Result<i64> {
+        if coalesce::is_enabled() {
+            return coalesce::submit(evt).await;
+        }
+        let seqs = {
+            let _guard = SEQ_INSERT_LOCK.lock().unwrap();
+            let conn = &mut establish_connection_for_sequencer()?;
+            insert_evts(conn, vec![evt])?
+        };
         self.crawlers.notify_of_update().await?;
-        Ok(res.seq.expect("Sequence number wasn't updated on insert."))
+        Ok(seqs[0])
     }
 
     pub async fn sequence_commit(
-        &mut self,
+        &self,
         did: String,
         commit_data: CommitDataWithOps,
     ) -> Result<i64> {
@@ -225,30 +233,22 @@ impl Sequencer {
         self.sequence_evt(evt).await
     }
 
-    pub async fn sequence_handle_update(&mut self, did: String, handle: String) -> Result<i64> {
+    pub async fn sequence_handle_update(&self, did: String, handle: String) -> Result<i64> {
         let evt = format_seq_handle_update(did, handle).await?;
         self.sequence_evt(evt).await
     }
 
-    pub async fn sequence_identity_evt(
-        &mut self,
-        did: String,
-        handle: Option<String>,
-    ) -> Result<i64> {
+    pub async fn sequence_identity_evt(&self, did: String, handle: Option<String>) -> Result<i64> {
         let evt = format_seq_identity_evt(did, handle).await?;
         self.sequence_evt(evt).await
     }
 
-    pub async fn sequence_account_evt(
-        &mut self,
-        did: String,
-        status: AccountStatus,
-    ) -> Result<i64> {
+    pub async fn sequence_account_evt(&self, did: String, status: AccountStatus) -> Result<i64> {
         let evt = format_seq_account_evt(did, status).await?;
         self.sequence_evt(evt).await
     }
 
-    pub async fn sequence_sync_evt(&mut self, did: String, data: SyncEvtData) -> Result<i64> {
+    pub async fn sequence_sync_evt(&self, did: String, data: SyncEvtData) -> Result<i64> {
         let evt = format_seq_sync_evt(did, data).await?;
         self.sequence_evt(evt).await
     }
@@ -304,6 +304,31 @@ impl Stream for Sequencer {
     }
 }
 
+/// Inserts `evts` with one statement, returning their sequence numbers in the same order.
+pub(crate) fn insert_evts(conn: &mut PgConnection, evts: Vec<models::RepoSeq>) -> Result<Vec<i64>> {
+    use crate::schema::pds::repo_seq::dsl as RepoSeqSchema;
+
+    let rows: Vec<_> = evts
+        .into_iter()
+        .map(|evt| {
+            (
+                RepoSeqSchema::did.eq(evt.did),
+                RepoSeqSchema::event.eq(evt.event),
+                RepoSeqSchema::eventType.eq(evt.event_type),
+                RepoSeqSchema::sequencedAt.eq(evt.sequenced_at),
+            )
+        })
+        .collect();
+    // Postgres draws the serial for each row in VALUES order and returns them the same way
+    let inserted = insert_into(RepoSeqSchema::repo_seq)
+        .values(rows)
+        .get_results::<models::RepoSeq>(conn)?;
+    Ok(inserted
+        .into_iter()
+        .map(|row| row.seq.expect("Sequence number wasn't updated on insert."))
+        .collect())
+}
+
 pub async fn delete_all_for_user(did: &String, excluding_seqs: Option<Vec<i64>>) -> Result<()> {
     use crate::schema::pds::repo_seq::dsl as RepoSeqSchema;
     let conn = &mut establish_connection_for_sequencer()?;
@@ -319,5 +344,7 @@ pub async fn delete_all_for_user(did: &String, excluding_seqs: Option<Vec<i64>>)
     Ok(())
 }
 
+pub mod archive;
+pub mod coalesce;
 pub mod events;
 pub mod outbox;
@@ -9,11 +9,15 @@ use rsky_common::r#async::{AsyncBuffer, AsyncBufferFullError};
 use std::sync::Arc;
 use tokio::runtime::Runtime;
 use tokio::sync::{Mutex, RwLock};
-use tokio::time::{timeout, Duration};
+use tokio::time::{sleep_until, timeout, Duration, Instant};
 
 #[derive(Debug, Clone)]
 pub struct OutboxOpts {
     pub max_buffer_size: usize,
+    /// Events read per query while backfilling.
+    pub backfill_page_size: i64,
+    /// None sends backfilled events as fast as the subscriber reads them.
+    pub backfill_max_events_per_sec: Option<u32>,
 }
 
 pub struct Outbox {
@@ -23,14 +27,49 @@ pub struct Outbox {
     pub out_buffer: Arc<RwLock<AsyncBuffer<SeqEvt>>>,
     pub sequencer: Sequencer,
     pub backfill_cursor: Option<i64>,
+    page_size: i64,
+    backfill_rate: Option<u32>,
 }
 
-const PAGE_SIZE: i64 = 500;
+// most a backfill that was held up by a slow subscriber may send at once to catch up
+const MAX_PACER_CREDIT: Duration = Duration::from_secs(1);
+
+/// Spaces backfilled events out to at most `per_sec` a second.
+struct BackfillPacer {
+    interval: Duration,
+    next: Instant,
+}
+
+impl BackfillPacer {
+    fn new(per_sec: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / per_sec.max(1),
+            next: Instant::now(),
+        }
+    }
+
+    /// Waits for the next event's turn.
+    async fn tick(&mut self) {
+        let now = Instant::now();
+        let earliest = now.checked_sub(MAX_PACER_CREDIT).unwrap_or(now);
+        self.next = self.next.max(earliest);
+        if self.next > now {
+            sleep_until(self.next).await;
+        }
+        self.next += self.interval;
+    }
+}
 
 impl Outbox {
     pub fn new(sequencer: Sequencer, opts: Option<OutboxOpts>) -> Self {
-        let OutboxOpts { max_buffer_size } = opts.unwrap_or(OutboxOpts {
+        let OutboxOpts {
+            max_buffer_size,
+            backfill_page_size,
+            backfill_max_events_per_sec,
+        } = opts.unwrap_or(OutboxOpts {
             max_buffer_size: 500,
+            backfill_page_size: 500,
+            backfill_max_events_per_sec: None,
         });
         Self {
             sequencer,
@@ -39,6 +78,8 @@ impl Outbox {
             cutover_buffer: Arc::new(Mutex::new(vec![])),
             out_buffer: Arc::new(RwLock::new(AsyncBuffer::new(Some(max_buffer_size)))),
             backfill_cursor: None,
+            page_size: backfill_page_size,
+            backfill_rate: backfill_max_events_per_sec,
         }
     }
 
@@ -72,7 +113,9 @@ impl Outbox {
                 let cutover_buffer = Arc::clone(&cutover_buffer);
 
                 async move {
-                    if *caught_up.lock().await {
+                    // held until the events are placed, so none land after the cutover drains
+                    let caught_up = caught_up.lock().await;
+                    if *caught_up {
                         out_buffer.read().await.push_many(evts);
                     } else {
                         cutover_buffer.lock().await.extend(evts);
@@ -90,24 +133,34 @@ impl Outbox {
             });
 
             if let Some(cursor) = backfill_cursor {
-                let earliest_seq = if self.last_seen > -1 {
-                    Some(self.last_seen)
-                } else {
-                    Some(cursor)
-                };
-                let cutover_evts = self.sequencer.request_seq_range(RequestSeqRangeOpts {
-                    earliest_seq,
-                    latest_seq: None,
-                    earliest_time: None,
-                    limit: Some(PAGE_SIZE),
-                }).await?;
-                {
-                    let out_buffer_lock = self.out_buffer.read().await;
-                    let mut cutover_lock = self.cutover_buffer.lock().await;
-                    out_buffer_lock.push_many(cutover_evts);
-                    out_buffer_lock.push_many(cutover_lock.drain(..).collect());
+                // anything sequenced from here on reaches the cutover buffer, so page through
+                // what arrived while the backfill was paced until a short page reaches the head
+                loop {
+                    let earliest_seq = if self.last_seen > -1 {
+                        Some(self.last_seen)
+                    } else {
+                        Some(cursor)
+                    };
+                    let cutover_evts = self.sequencer.request_seq_range(RequestSeqRangeOpts {
+                        earliest_seq,
+                        latest_seq: None,
+                        earliest_time: None,
+                        limit: Some(self.page_size),
+                    }).await?;
+                    let page_len = cutover_evts.len() as i64;
+                    for evt in cutover_evts {
+                        self.last_seen = evt.seq();
+                        yield evt;
+                    }
+                    if page_len < self.page_size {
+                        break;
+                    }
                 }
                 let mut bool_lock = self.caught_up.lock().await;
+                // overlaps with the pages above are dropped by the seq check below
+                let out_buffer_lock = self.out_buffer.read().await;
+                let mut cutover_lock = self.cutover_buffer.lock().await;
+                out_buffer_lock.push_many(cutover_lock.drain(..).collect());
                 *bool_lock = true;
             } else {
                 let mut bool_lock = self.caught_up.lock().await;
@@ -131,11 +184,14 @@ impl Outbox {
         }
     }
 
+    /// Replays history after `backfill_cursor` a page at a time, paced to the configured rate,
+    /// until a short page shows it has reached the head of the sequence.
     pub async fn get_backfill<'a>(
         &'a mut self,
         backfill_cursor: i64,
     ) -> impl Stream<Item = Result<SeqEvt>> + 'a {
         try_stream! {
+            let mut pacer = self.backfill_rate.map(BackfillPacer::new);
             loop {
                 let earliest_seq = if self.last_seen > -1 {
                     Some(self.last_seen)
@@ -146,23 +202,40 @@ impl Outbox {
                     earliest_seq,
                     latest_seq: None,
                     earliest_time: None,
-                    limit: Some(PAGE_SIZE),
+                    limit: Some(self.page_size),
                 }).await {
                     Ok(res) => res,
                     Err(_) => break
                 };
-                for evt in evts.iter() {
+                let page_len = evts.len() as i64;
+                for evt in evts {
+                    if let Some(pacer) = pacer.as_mut() {
+                        pacer.tick().await;
+                    }
                     self.last_seen = evt.seq();
-                    yield evt.clone();
-                }
-                let seq_cursor = self.sequencer.last_seen.unwrap_or(-1);
-                if seq_cursor - self.last_seen < (PAGE_SIZE / 2)  {
-                    break;
+                    yield evt;
                 }
-                if evts.is_empty() {
+                // `events` pages through whatever was sequenced since, see the cutover there
+                if page_len < self.page_size {
                     break;
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_backfill_pacer() {
+        let started = Instant::now();
+        let mut pacer = BackfillPacer::new(100);
+        for _ in 0..21 {
+            pacer.tick().await;
+        }
+        // the first event goes straight away, the other 20 at 10ms intervals
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,155 @@
+//! Graceful shutdown. Once shutdown is triggered (SIGTERM, ctrl-c or `Shutdown::notify`)
+//! rocket stops accepting connections and runs the fairing below, which:
+//!
+//! 1. waits for in-flight xrpc writes so their commits land,
+//! 2. signals firehose subscribers, which flush what the sequencer emits for those commits
+//!    and close with a `1001 Going Away` frame,
+//! 3. stops background jobs once the sequencer has had a chance to emit.
+//!
+//! Anything still running after `grace + mercy` is cut off by rocket.
+
+use crate::config::ShutdownConfig;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Method;
+use rocket::{Data, Orbit, Request, Response, Rocket};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tokio::sync::Notify;
+use tokio::task::AbortHandle;
+use tokio::time::{sleep, timeout_at, Duration, Instant};
+
+#[derive(Default)]
+pub struct ShutdownState {
+    draining: AtomicBool,
+    in_flight_writes: AtomicUsize,
+    writes_drained: Notify,
+    background_jobs: Mutex<Vec<(&'static str, AbortHandle)>>,
+}
+
+impl ShutdownState {
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    pub fn in_flight_writes(&self) -> usize {
+        self.in_flight_writes.load(Ordering::SeqCst)
+    }
+
+    /// Registers a long-running task to be stopped once shutdown has flushed events.
+    pub fn register_background_job(&self, name: &'static str, handle: AbortHandle) {
+        self.background_jobs.lock().unwrap().push((name, handle));
+    }
+
+    pub fn begin_write(&self) {
+        self.in_flight_writes.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn end_write(&self) {
+        if self.in_flight_writes.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.writes_drained.notify_waiters();
+        }
+    }
+
+    /// Waits until no writes are in flight, returning false if `limit` elapses first.
+    pub async fn wait_for_writes(&self, limit: Duration) -> bool {
+        let deadline = Instant::now() + limit;
+        loop {
+            // registered before the check so a concurrent `end_write` can't be missed
+            let drained = self.writes_drained.notified();
+            if self.in_flight_writes() == 0 {
+                return true;
+            }
+            if timeout_at(deadline, drained).await.is_err() {
+                return false;
+            }
+        }
+    }
+
+    fn stop_background_jobs(&self) {
+        for (name, handle) in self.background_jobs.lock().unwrap().drain(..) {
+            tracing::info!("@LOG: stopping background job {name}");
+            handle.abort();
+        }
+    }
+}
+
+// Every xrpc procedure is a POST; queries can be dropped without losing data.
+fn is_write(request: &Request<'_>) -> bool {
+    request.method() == Method::Post && request.uri().path().starts_with("/xrpc/")
+}
+
+pub struct GracefulShutdown {
+    pub cfg: ShutdownConfig,
+}
+
+#[rocket::async_trait]
+impl Fairing for GracefulShutdown {
+    fn info(&self) -> Info {
+        Info {
+            name: "Drain writes and flush the firehose on shutdown",
+            kind: Kind::Request | Kind::Response | Kind::Shutdown,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        if is_write(request) {
+            if let Some(state) = request.rocket().state::<ShutdownState>() {
+                state.begin_write();
+            }
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, _response: &mut Response<'r>) {
+        if is_write(request) {
+            if let Some(state) = request.rocket().state::<ShutdownState>() {
+                state.end_write();
+            }
+        }
+    }
+
+    async fn on_shutdown(&self, rocket: &Rocket<Orbit>) {
+        let Some(state) = rocket.state::<ShutdownState>() else {
+            return;
+        };
+        state.draining.store(true, Ordering::SeqCst);
+        tracing::info!(
+            "@LOG: shutting down, draining {} in-flight writes",
+            state.in_flight_writes()
+        );
+        if !state
+            .wait_for_writes(Duration::from_secs(self.cfg.grace))
+            .await
+        {
+            tracing::warn!(
+                "@LOG: {} writes still in flight after {}s grace",
+                state.in_flight_writes(),
+                self.cfg.grace
+            );
+        }
+        // give the sequencer a poll cycle to emit the drained commits to subscribers
+        sleep(Duration::from_millis(self.cfg.flush_window_ms)).await;
+        state.stop_background_jobs();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_for_writes() {
+        let state = std::sync::Arc::new(ShutdownState::default());
+        assert!(state.wait_for_writes(Duration::from_millis(10)).await);
+
+        state.begin_write();
+        assert!(!state.wait_for_writes(Duration::from_millis(10)).await);
+
+        let writer = state.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(20)).await;
+            writer.end_write();
+        });
+        assert!(state.wait_for_writes(Duration::from_secs(5)).await);
+        assert_eq!(state.in_flight_writes(), 0);
+    }
+}
@@ -0,0 +1,236 @@
+//! Spam scoring hooks for new accounts and records. Before `createAccount` or `createRecord`
+//! writes anything, the [`SpamSubject`] is handed to the installed [`SpamScorer`], by default an
+//! HTTP service at `PDS_SPAM_SCORING_URL` that answers `{"score": <0..1>}`.
+//!
+//! Scores at or above the block threshold reject the request; scores at or above the flag
+//! threshold let it through and record the account in the moderation audit log. A scorer that
+//! errors or misses `PDS_SPAM_SCORING_TIMEOUT_MS` lets the write through unless
+//! `PDS_SPAM_SCORING_FAIL_OPEN=false`, in which case it's rejected as unavailable.
+
+use crate::account_manager::helpers::moderation::{AuditEventOpts, AUDIT_ACTION_FLAG};
+use crate::account_manager::AccountManager;
+use crate::apis::ApiError;
+use crate::config::SpamScoringConfig;
+use crate::http;
+use anyhow::{bail, Result};
+use lazy_static::lazy_static;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// The `rule` spam flags are recorded under in the moderation audit log.
+pub const SPAM_RULE: &str = "spam";
+
+lazy_static! {
+    static ref SPAM_HOOK: RwLock<Option<SpamHook>> = RwLock::new(None);
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event")]
+pub enum SpamSubject {
+    #[serde(rename = "account.create")]
+    Account {
+        did: String,
+        handle: String,
+        email: String,
+    },
+    #[serde(rename = "record.create")]
+    Record {
+        did: String,
+        uri: String,
+        record: serde_json::Value,
+    },
+}
+
+impl SpamSubject {
+    pub fn did(&self) -> &str {
+        match self {
+            SpamSubject::Account { did, .. } | SpamSubject::Record { did, .. } => did,
+        }
+    }
+}
+
+/// Scores subjects from 0 (fine) to 1 (certainly spam). Deployments with their own scoring can
+/// install an implementation with [`set_spam_hook`] in place of [`HttpSpamScorer`].
+#[rocket::async_trait]
+pub trait SpamScorer: Send + Sync {
+    async fn score(&self, subject: &SpamSubject) -> Result<f64>;
+}
+
+/// POSTs the subject as JSON and reads `score` from the response.
+pub struct HttpSpamScorer {
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScoreResponse {
+    score: f64,
+}
+
+impl HttpSpamScorer {
+    pub fn new(url: String) -> Self {
+        Self { url }
+    }
+}
+
+#[rocket::async_trait]
+impl SpamScorer for HttpSpamScorer {
+    async fn score(&self, subject: &SpamSubject) -> Result<f64> {
+        // bounded by the hook's own timeout
+        let res = http::client()
+            .post(&self.url)
+            .json(subject)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(res.json::<ScoreResponse>().await?.score)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpamVerdict {
+    Allow,
+    /// Let through, but the account should be flagged for review.
+    Flag {
+        score: f64,
+    },
+    Block {
+        score: f64,
+    },
+    /// No score came back and the hook fails closed.
+    Unavailable,
+}
+
+impl SpamVerdict {
+    /// `None` is a scorer that failed or timed out.
+    pub fn from_score(config: &SpamScoringConfig, score: Option<f64>) -> Self {
+        match score {
+            None if config.fail_open => SpamVerdict::Allow,
+            None => SpamVerdict::Unavailable,
+            Some(score) if score >= config.block_threshold => SpamVerdict::Block { score },
+            Some(score) if score >= config.flag_threshold => SpamVerdict::Flag { score },
+            Some(_) => SpamVerdict::Allow,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct SpamHook {
+    config: SpamScoringConfig,
+    scorer: Arc<dyn SpamScorer>,
+}
+
+/// Installs `scorer` for the rest of the process, judged by `config`'s thresholds. Until this
+/// is called everything is allowed.
+pub fn set_spam_hook(config: SpamScoringConfig, scorer: Arc<dyn SpamScorer>) {
+    *SPAM_HOOK.write().unwrap() = Some(SpamHook { config, scorer });
+}
+
+/// Scores `subject`, failing with the error to return to the client if it's to be rejected.
+/// Flag verdicts are left to the caller, since the account may not exist yet; see [`flag`].
+pub async fn check(subject: &SpamSubject) -> Result<SpamVerdict> {
+    let Some(hook) = SPAM_HOOK.read().unwrap().clone() else {
+        return Ok(SpamVerdict::Allow);
+    };
+    let timeout = Duration::from_millis(hook.config.timeout);
+    let score = match tokio::time::timeout(timeout, hook.scorer.score(subject)).await {
+        Ok(Ok(score)) => Some(score),
+        Ok(Err(error)) => {
+            tracing::warn!("@LOG: spam scoring failed for {}: {error}", subject.did());
+            None
+        }
+        Err(_) => {
+            tracing::warn!("@LOG: spam scoring timed out for {}", subject.did());
+            None
+        }
+    };
+    match SpamVerdict::from_score(&hook.config, score) {
+        SpamVerdict::Block { score } => {
+            tracing::info!("@LOG: blocked {} with spam score {score}", subject.did());
+            bail!(ApiError::BadRequest(
+                "SpamDetected".to_string(),
+                "Rejected by spam filter".to_string(),
+            ))
+        }
+        SpamVerdict::Unavailable => bail!(ApiError::ServiceUnavailable(
+            "Spam scoring is unavailable, try again later".to_string()
+        )),
+        verdict => Ok(verdict),
+    }
+}
+
+/// Records a flag verdict against the subject's account for a moderator to review.
+pub async fn flag(
+    subject: &SpamSubject,
+    verdict: SpamVerdict,
+    account_manager: &AccountManager,
+) -> Result<()> {
+    let SpamVerdict::Flag { score } = verdict else {
+        return Ok(());
+    };
+    let comment = match subject {
+        SpamSubject::Account { .. } => format!("account scored {score}"),
+        SpamSubject::Record { uri, .. } => format!("{uri} scored {score}"),
+    };
+    account_manager
+        .record_audit_event(AuditEventOpts {
+            subject_did: subject.did().to_owned(),
+            action: AUDIT_ACTION_FLAG,
+            rule: SPAM_RULE.to_string(),
+            report_count: 0,
+            expires_at: None,
+            created_by: None,
+            comment: Some(comment),
+        })
+        .await?;
+    tracing::info!("@LOG: flagged {} for review: {comment}", subject.did());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdict_from_score() {
+        let mut config = SpamScoringConfig {
+            url: "https://spam.example.com/score".to_string(),
+            timeout: 1000,
+            fail_open: true,
+            block_threshold: 0.9,
+            flag_threshold: 0.5,
+        };
+        assert_eq!(
+            SpamVerdict::from_score(&config, Some(0.1)),
+            SpamVerdict::Allow
+        );
+        assert_eq!(
+            SpamVerdict::from_score(&config, Some(0.5)),
+            SpamVerdict::Flag { score: 0.5 }
+        );
+        assert_eq!(
+            SpamVerdict::from_score(&config, Some(0.95)),
+            SpamVerdict::Block { score: 0.95 }
+        );
+        assert_eq!(SpamVerdict::from_score(&config, None), SpamVerdict::Allow);
+        config.fail_open = false;
+        assert_eq!(
+            SpamVerdict::from_score(&config, None),
+            SpamVerdict::Unavailable
+        );
+
+        let subject = SpamSubject::Account {
+            did: "did:plc:abc".to_string(),
+            handle: "alice.example.com".to_string(),
+            email: "alice@example.com".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&subject).unwrap(),
+            serde_json::json!({
+                "event": "account.create",
+                "did": "did:plc:abc",
+                "handle": "alice.example.com",
+                "email": "alice@example.com",
+            })
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Seeded account fixtures for handler tests, layered on the repo fixtures from
+//! [`rsky_repo::test_helpers`]. The same seed always produces the same did, handle, credentials,
+//! signing key and repo, so CARs served by the sync endpoints can be compared byte for byte.
+
+use anyhow::Result;
+use rsky_lexicon::com::atproto::server::CreateAccountInput;
+pub use rsky_repo::test_helpers::{commit_car, RepoBuilder, RepoFixture};
+
+pub struct AccountBuilder {
+    seed: u64,
+    name: String,
+    domain: String,
+    repo: RepoBuilder,
+}
+
+impl AccountBuilder {
+    pub fn new(seed: u64) -> Self {
+        AccountBuilder {
+            seed,
+            name: format!("user{seed}"),
+            domain: ".test".to_string(),
+            repo: RepoBuilder::new(seed),
+        }
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Handle suffix including the leading dot, as in `service_handle_domains`.
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.domain = domain.to_string();
+        self
+    }
+
+    /// Adds `count` generated records in `collection` to the account's genesis commit.
+    pub fn records(mut self, collection: &str, count: usize) -> Self {
+        self.repo = self.repo.records(collection, count);
+        self
+    }
+
+    pub async fn build(self) -> Result<AccountFixture> {
+        let AccountBuilder {
+            seed,
+            name,
+            domain,
+            repo,
+        } = self;
+        Ok(AccountFixture {
+            handle: format!("{name}{domain}"),
+            email: format!("{name}@example.com"),
+            password: format!("{name}-password-{seed}"),
+            repo: repo.build().await?,
+        })
+    }
+}
+
+pub struct AccountFixture {
+    pub handle: String,
+    pub email: String,
+    pub password: String,
+    pub repo: RepoFixture,
+}
+
+impl AccountFixture {
+    pub fn did(&self) -> &str {
+        &self.repo.did
+    }
+
+    /// Input for `com.atproto.server.createAccount` that claims this fixture's did.
+    pub fn create_account_input(&self, invite_code: Option<String>) -> CreateAccountInput {
+        CreateAccountInput {
+            email: Some(self.email.clone()),
+            handle: self.handle.clone(),
+            did: Some(self.repo.did.clone()),
+            invite_code,
+            verification_code: None,
+            verification_phone: None,
+            password: Some(self.password.clone()),
+            recovery_key: None,
+            plc_op: None,
+            display_name: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsky_repo::car::read_car_with_root;
+
+    #[tokio::test]
+    async fn test_account_fixture_is_seeded() -> Result<()> {
+        let first = AccountBuilder::new(3)
+            .name("alice")
+            .records("app.bsky.feed.post", 3)
+            .build()
+            .await?;
+        let second = AccountBuilder::new(3)
+            .name("alice")
+            .records("app.bsky.feed.post", 3)
+            .build()
+            .await?;
+        assert_eq!(first.did(), second.did());
+        assert_eq!(first.repo.repo_car().await?, second.repo.repo_car().await?);
+
+        let input = first.create_account_input(Some("invite".to_string()));
+        assert_eq!(input.handle, "alice.test");
+        assert_eq!(input.did.as_deref(), Some(first.did()));
+        assert_eq!(input.password, Some(first.password.clone()));
+
+        let car = read_car_with_root(commit_car(&first.repo.genesis).await?).await?;
+        assert_eq!(car.root, first.repo.genesis.cid);
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+//! Outbound webhooks for account lifecycle events, so operators can keep billing or CRM systems
+//! in step with their PDS. Each event is POSTed as JSON to every `PDS_WEBHOOK_URLS` endpoint.
+//!
+//! When `PDS_WEBHOOK_SECRET` is set, requests carry `x-pds-webhook-timestamp` and
+//! `x-pds-webhook-signature: sha256=<hex>`, an HMAC-SHA256 of `<timestamp>.<body>`. Failed
+//! deliveries are retried with exponential backoff, then dropped; delivery never holds up or
+//! fails the request that triggered it.
+
+use crate::config::WebhookConfig;
+use crate::http::{self, Destination};
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use sha2::Sha256;
+use std::sync::RwLock;
+use std::time::Duration;
+
+pub const TIMESTAMP_HEADER: &str = "x-pds-webhook-timestamp";
+pub const SIGNATURE_HEADER: &str = "x-pds-webhook-signature";
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+lazy_static! {
+    static ref WEBHOOKS: RwLock<Option<Webhooks>> = RwLock::new(None);
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event")]
+pub enum WebhookEvent {
+    #[serde(rename = "account.created")]
+    AccountCreated { did: String, handle: String },
+    #[serde(rename = "account.deleted")]
+    AccountDeleted { did: String },
+    /// Sent both when a takedown is applied and when it's lifted.
+    #[serde(rename = "account.takedown")]
+    AccountTakedown {
+        did: String,
+        applied: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        r#ref: Option<String>,
+    },
+    #[serde(rename = "handle.updated")]
+    HandleUpdated { did: String, handle: String },
+}
+
+#[derive(Debug, Serialize)]
+struct WebhookPayload<'a> {
+    #[serde(flatten)]
+    event: &'a WebhookEvent,
+    time: String,
+}
+
+#[derive(Clone)]
+struct Webhooks {
+    config: WebhookConfig,
+}
+
+/// Installs the webhook endpoints for the rest of the process. Nothing is sent until this is
+/// called with at least one URL.
+pub fn set_webhooks(config: WebhookConfig) {
+    let webhooks = match config.urls.is_empty() {
+        true => None,
+        false => Some(Webhooks { config }),
+    };
+    *WEBHOOKS.write().unwrap() = webhooks;
+}
+
+/// `sha256=<hex>` HMAC of `<timestamp>.<body>` under `secret`.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Queues `event` for delivery to every configured endpoint.
+pub fn notify(event: WebhookEvent) {
+    let Some(webhooks) = WEBHOOKS.read().unwrap().clone() else {
+        return;
+    };
+    let payload = WebhookPayload {
+        event: &event,
+        time: rsky_common::now(),
+    };
+    let body = match serde_json::to_vec(&payload) {
+        Ok(body) => body,
+        Err(error) => {
+            tracing::error!("@LOG: ERROR: failed to serialize webhook event: {error}");
+            return;
+        }
+    };
+    for url in webhooks.config.urls.iter().cloned() {
+        let webhooks = webhooks.clone();
+        let body = body.clone();
+        tokio::spawn(async move { webhooks.deliver(url, body).await });
+    }
+}
+
+impl Webhooks {
+    async fn deliver(&self, url: String, body: Vec<u8>) {
+        let mut backoff = INITIAL_BACKOFF;
+        for attempt in 0..=self.config.max_retries {
+            if attempt > 0 {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            match self.send(&url, body.clone()).await {
+                Ok(()) => return,
+                Err(error) => {
+                    tracing::warn!("@LOG: webhook to {url} failed (attempt {attempt}): {error}")
+                }
+            }
+        }
+        tracing::error!(
+            "@LOG: ERROR: giving up on webhook to {url} after {} retries",
+            self.config.max_retries
+        );
+    }
+
+    async fn send(&self, url: &str, body: Vec<u8>) -> reqwest::Result<()> {
+        let mut request = http::post(Destination::Webhook, url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(secret) = &self.config.secret {
+            // signed per attempt, so receivers can reject stale timestamps
+            let timestamp = chrono::Utc::now().timestamp();
+            request = request
+                .header(TIMESTAMP_HEADER, timestamp)
+                .header(SIGNATURE_HEADER, sign(secret, timestamp, &body));
+        }
+        request.body(body).send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_serialize() {
+        let body = br#"{"event":"account.deleted","did":"did:plc:abc"}"#;
+        let signature = sign("whsec", 1700000000, body);
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"whsec").unwrap();
+        mac.update(b"1700000000.");
+        mac.update(body);
+        let expected = hex::encode(mac.finalize().into_bytes());
+        assert_eq!(signature, format!("sha256={expected}"));
+        assert_ne!(signature, sign("other", 1700000000, body));
+
+        let event = WebhookEvent::AccountTakedown {
+            did: "did:plc:abc".to_string(),
+            applied: true,
+            r#ref: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({"event": "account.takedown", "did": "did:plc:abc", "applied": true})
+        );
+    }
+}
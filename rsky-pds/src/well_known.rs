@@ -1,10 +1,13 @@
 use crate::account_manager::AccountManager;
 use crate::config::ServerConfig;
+use crate::oauth::routes::{authorization_server_metadata, protected_resource_metadata};
 use anyhow::Result;
 use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome};
 use rocket::response::status;
+use rocket::serde::json::Json;
 use rocket::{Request, State};
+use serde_json::Value;
 
 pub struct HostHeader(pub String);
 
@@ -58,3 +61,13 @@ pub async fn well_known(
         )),
     }
 }
+
+#[rocket::get("/.well-known/oauth-authorization-server")]
+pub async fn oauth_authorization_server(cfg: &State<ServerConfig>) -> Json<Value> {
+    Json(authorization_server_metadata(cfg))
+}
+
+#[rocket::get("/.well-known/oauth-protected-resource")]
+pub async fn oauth_protected_resource(cfg: &State<ServerConfig>) -> Json<Value> {
+    Json(protected_resource_metadata(cfg))
+}
@@ -58,9 +58,9 @@ where
             let payload = parse_payload(parts_1)?;
             let now = SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)
-                .expect("timestamp in micros since UNIX epoch")
-                .as_micros();
-            if now > payload.exp as u128 {
+                .expect("timestamp in seconds since UNIX epoch")
+                .as_secs();
+            if now > payload.exp {
                 bail!("JwtExpired: jwt expired")
             }
             if own_did.is_some() && payload.aud != own_did.unwrap() {
@@ -114,7 +114,7 @@ where
             Ok(ServiceJwtPayload {
                 iss: payload.iss,
                 aud: payload.aud,
-                exp: Some(Duration::from_micros(payload.exp)),
+                exp: Some(Duration::from_secs(payload.exp)),
             })
         }
         _ => bail!("BadJwt: poorly formatted jwt"),
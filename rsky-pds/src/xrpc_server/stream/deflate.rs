@@ -0,0 +1,303 @@
+//! permessage-deflate (RFC 7692) for outgoing event streams.
+//!
+//! The websocket library underneath rocket_ws can't negotiate extensions, so the handshake and
+//! the compressed frames are handled here. Subscribers only ever send control frames, which are
+//! never compressed, so only the sending half of the extension is needed.
+
+use crate::config::FirehoseCompressionConfig;
+use flate2::{Compress, Compression, FlushCompress, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::{self, Responder};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tungstenite::protocol::frame::coding::{Data, OpCode};
+use tungstenite::protocol::frame::Frame;
+use ws::Message;
+
+const EXTENSION: &str = "permessage-deflate";
+const SYNC_FLUSH_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Subscribers currently holding a compressor, across every stream endpoint.
+static COMPRESSED_CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// A permessage-deflate offer from a client's `Sec-WebSocket-Extensions` header.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeflateOffer {
+    pub server_no_context_takeover: bool,
+    pub client_no_context_takeover: bool,
+}
+
+impl DeflateOffer {
+    /// First offer in `header` the server can honour. Offers that restrict the server's window
+    /// below 15 bits are skipped, since the compressor always uses a 32KiB window.
+    pub fn parse(header: &str) -> Option<DeflateOffer> {
+        header.split(',').find_map(|extension| {
+            let mut params = extension.split(';').map(str::trim);
+            if !params.next()?.eq_ignore_ascii_case(EXTENSION) {
+                return None;
+            }
+            let mut offer = DeflateOffer::default();
+            for param in params {
+                let (name, value) = match param.split_once('=') {
+                    Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                    None => (param, None),
+                };
+                match (name, value) {
+                    ("server_no_context_takeover", None) => offer.server_no_context_takeover = true,
+                    ("client_no_context_takeover", None) => offer.client_no_context_takeover = true,
+                    ("server_max_window_bits", Some("15")) => (),
+                    ("client_max_window_bits", None) => (),
+                    ("client_max_window_bits", Some(bits)) => match bits.parse::<u8>() {
+                        Ok(8..=15) => (),
+                        _ => return None,
+                    },
+                    _ => return None,
+                }
+            }
+            Some(offer)
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for DeflateOffer {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let offer = req
+            .headers()
+            .get("Sec-WebSocket-Extensions")
+            .find_map(DeflateOffer::parse);
+        match offer {
+            Some(offer) => Outcome::Success(offer),
+            None => Outcome::Forward(rocket::http::Status::Ok),
+        }
+    }
+}
+
+/// Per-connection compressor, holding one of the `max_connections` slots until dropped.
+pub struct PerMessageDeflate {
+    compress: Compress,
+    context_takeover: bool,
+    min_frame_size: usize,
+}
+
+impl PerMessageDeflate {
+    /// Accepts `offer` if compression is enabled and a slot is free.
+    pub fn negotiate(
+        offer: Option<DeflateOffer>,
+        cfg: Option<&FirehoseCompressionConfig>,
+    ) -> Option<PerMessageDeflate> {
+        let (offer, cfg) = (offer?, cfg?);
+        let acquired =
+            COMPRESSED_CONNECTIONS.fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                match cfg.max_connections {
+                    Some(max) if count >= max => None,
+                    _ => Some(count + 1),
+                }
+            });
+        if acquired.is_err() {
+            tracing::debug!("compressed subscriber limit reached, streaming uncompressed");
+            return None;
+        }
+        Some(PerMessageDeflate {
+            compress: Compress::new(Compression::new(cfg.level), false),
+            context_takeover: cfg.context_takeover && !offer.server_no_context_takeover,
+            min_frame_size: cfg.min_frame_size,
+        })
+    }
+
+    /// Value for the handshake's `Sec-WebSocket-Extensions` response header.
+    pub fn response_header(&self) -> String {
+        match self.context_takeover {
+            true => EXTENSION.to_string(),
+            false => format!("{EXTENSION}; server_no_context_takeover"),
+        }
+    }
+
+    /// Deflates `data` into a single RSV1 frame. Frames under `min_frame_size` are sent as is.
+    pub fn binary(&mut self, data: Vec<u8>) -> Message {
+        if data.len() < self.min_frame_size {
+            return Message::Binary(data);
+        }
+        match self.deflate(&data) {
+            Ok(payload) => {
+                let mut frame = Frame::message(payload, OpCode::Data(Data::Binary), true);
+                frame.header_mut().rsv1 = true;
+                Message::Frame(frame)
+            }
+            Err(err) => {
+                // a fresh stream never refers back to what the client saw before, so resetting
+                // keeps the connection usable
+                tracing::warn!("failed to deflate frame, sending uncompressed: {err}");
+                self.compress.reset();
+                Message::Binary(data)
+            }
+        }
+    }
+
+    fn deflate(&mut self, data: &[u8]) -> Result<Vec<u8>, flate2::CompressError> {
+        let start = self.compress.total_in();
+        let mut out = Vec::with_capacity(data.len() / 2 + 64);
+        loop {
+            if out.len() == out.capacity() {
+                out.reserve(out.capacity());
+            }
+            let consumed = (self.compress.total_in() - start) as usize;
+            let status =
+                self.compress
+                    .compress_vec(&data[consumed..], &mut out, FlushCompress::Sync)?;
+            let consumed = (self.compress.total_in() - start) as usize;
+            if (consumed == data.len() && out.len() < out.capacity()) || status == Status::StreamEnd
+            {
+                break;
+            }
+        }
+        if out.ends_with(&SYNC_FLUSH_TAIL) {
+            out.truncate(out.len() - SYNC_FLUSH_TAIL.len());
+        }
+        if !self.context_takeover {
+            self.compress.reset();
+        }
+        Ok(out)
+    }
+}
+
+impl Drop for PerMessageDeflate {
+    fn drop(&mut self) {
+        COMPRESSED_CONNECTIONS.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Sends binary frames through the negotiated compressor, if there is one.
+pub struct FrameEncoder(Option<PerMessageDeflate>);
+
+impl FrameEncoder {
+    pub fn new(deflate: Option<PerMessageDeflate>) -> Self {
+        FrameEncoder(deflate)
+    }
+
+    pub fn binary(&mut self, data: Vec<u8>) -> Message {
+        match &mut self.0 {
+            Some(deflate) => deflate.binary(data),
+            None => Message::Binary(data),
+        }
+    }
+}
+
+/// Upgrade response carrying the negotiated `Sec-WebSocket-Extensions` header.
+pub struct WithExtensions<R> {
+    pub inner: R,
+    pub extensions: Option<String>,
+}
+
+impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for WithExtensions<R> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        let mut response = self.inner.respond_to(req)?;
+        if let Some(extensions) = self.extensions {
+            response.set_raw_header("Sec-WebSocket-Extensions", extensions);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{Decompress, FlushDecompress};
+
+    fn cfg(context_takeover: bool) -> FirehoseCompressionConfig {
+        FirehoseCompressionConfig {
+            level: 6,
+            context_takeover,
+            min_frame_size: 0,
+            max_connections: None,
+        }
+    }
+
+    fn inflate(decompress: &mut Decompress, payload: &[u8]) -> Vec<u8> {
+        let mut input = payload.to_vec();
+        input.extend_from_slice(&SYNC_FLUSH_TAIL);
+        let mut out = Vec::with_capacity(4096);
+        decompress
+            .decompress_vec(&input, &mut out, FlushDecompress::Sync)
+            .unwrap();
+        out
+    }
+
+    fn payload(message: Message) -> Vec<u8> {
+        match message {
+            Message::Frame(frame) => {
+                assert!(frame.header().rsv1);
+                frame.into_data()
+            }
+            other => panic!("expected a compressed frame, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_offers() {
+        assert_eq!(
+            DeflateOffer::parse("permessage-deflate; client_max_window_bits"),
+            Some(DeflateOffer::default())
+        );
+        assert_eq!(
+            DeflateOffer::parse(
+                "x-webkit-deflate-frame, permessage-deflate; server_no_context_takeover"
+            ),
+            Some(DeflateOffer {
+                server_no_context_takeover: true,
+                client_no_context_takeover: false,
+            })
+        );
+        assert_eq!(
+            DeflateOffer::parse("permessage-deflate; server_max_window_bits=10"),
+            None
+        );
+        assert_eq!(
+            DeflateOffer::parse(
+                "permessage-deflate; server_max_window_bits=10, permessage-deflate"
+            ),
+            Some(DeflateOffer::default())
+        );
+    }
+
+    #[test]
+    fn round_trips_with_context_takeover() {
+        let mut deflate =
+            PerMessageDeflate::negotiate(Some(DeflateOffer::default()), Some(&cfg(true))).unwrap();
+        assert_eq!(deflate.response_header(), "permessage-deflate");
+        let mut decompress = Decompress::new(false);
+        for _ in 0..3 {
+            let data = b"commit commit commit commit commit".to_vec();
+            let compressed = payload(deflate.binary(data.clone()));
+            assert_eq!(inflate(&mut decompress, &compressed), data);
+        }
+    }
+
+    #[test]
+    fn round_trips_without_context_takeover() {
+        let mut deflate =
+            PerMessageDeflate::negotiate(Some(DeflateOffer::default()), Some(&cfg(false))).unwrap();
+        assert_eq!(
+            deflate.response_header(),
+            "permessage-deflate; server_no_context_takeover"
+        );
+        for _ in 0..3 {
+            let data = b"identity identity identity".to_vec();
+            let compressed = payload(deflate.binary(data.clone()));
+            assert_eq!(inflate(&mut Decompress::new(false), &compressed), data);
+        }
+    }
+
+    #[test]
+    fn skips_small_frames() {
+        let mut cfg = cfg(true);
+        cfg.min_frame_size = 64;
+        let mut deflate =
+            PerMessageDeflate::negotiate(Some(DeflateOffer::default()), Some(&cfg)).unwrap();
+        assert_eq!(
+            deflate.binary(b"tiny".to_vec()),
+            Message::Binary(b"tiny".to_vec())
+        );
+    }
+}
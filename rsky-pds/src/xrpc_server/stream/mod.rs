@@ -1,2 +1,3 @@
+pub mod deflate;
 pub mod frames;
 pub mod types;
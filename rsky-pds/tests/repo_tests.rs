@@ -0,0 +1,71 @@
+use lexicon_cid::Cid;
+use rsky_pds::actor_store::repo::sql_repo::SqlRepoReader;
+use rsky_pds::db::DbConn;
+use rsky_repo::block_map::BlockMap;
+use rsky_repo::cid_set::CidSet;
+use rsky_repo::storage::types::RepoStorage;
+use rsky_repo::types::CommitData;
+use std::sync::Arc;
+
+#[allow(dead_code)]
+mod common;
+
+const DID: &str = "did:plc:khvyd3oiw46vif5gm7hijslk";
+
+/// Applies a commit adding a single block holding `value`, returning that block's cid.
+async fn commit(
+    storage: &SqlRepoReader,
+    rev: &str,
+    since: Option<&str>,
+    value: &str,
+    removed: Vec<Cid>,
+) -> Cid {
+    let mut new_blocks = BlockMap::new();
+    let cid = new_blocks.add(value).unwrap();
+    let commit = CommitData {
+        cid,
+        rev: rev.to_string(),
+        since: since.map(str::to_string),
+        prev: None,
+        new_blocks,
+        relevant_blocks: BlockMap::new(),
+        removed_cids: CidSet::new(Some(removed)),
+    };
+    storage
+        .apply_commit(commit, Some(since.is_none()))
+        .await
+        .unwrap();
+    cid
+}
+
+#[tokio::test]
+async fn test_get_blocks_since() {
+    let postgres = common::get_postgres().await;
+    let client = common::get_client(&postgres).await;
+    let db = DbConn::get_one(client.rocket()).await.unwrap();
+    let storage = SqlRepoReader::new(DID.to_string(), None, Arc::new(db));
+
+    let first = commit(&storage, "3lb1", None, "first", vec![]).await;
+    let second = commit(&storage, "3lb2", Some("3lb1"), "second", vec![]).await;
+    let third = commit(&storage, "3lb3", Some("3lb2"), "third", vec![second]).await;
+
+    // only blocks added after `since` that weren't removed since
+    let blocks = storage
+        .get_blocks_since("3lb1".to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(blocks.cids().unwrap(), vec![third]);
+    assert!(!blocks.has(first));
+
+    let blocks = storage
+        .get_blocks_since("3lb3".to_string())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(blocks.size(), 0);
+
+    // older than anything recorded for the repo, so the caller falls back to a full walk
+    let blocks = storage.get_blocks_since("3la9".to_string()).await.unwrap();
+    assert!(blocks.is_none());
+}
@@ -0,0 +1,5 @@
+mod store;
+mod types;
+
+pub use store::{Store, StoreError};
+pub use types::{AdminAction, AdminActionReceiver, AdminActionSender, Subject, Takedowns};
@@ -0,0 +1,240 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension};
+use thiserror::Error;
+
+use crate::admin::types::{AuditEntry, HostCursor, Subject, Takedown, Takedowns};
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("sqlite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("invalid subject kind: {0}")]
+    InvalidSubject(String),
+}
+
+/// Persistent takedown state and audit log, stored alongside the host cursors in `relay.db`.
+#[derive(Debug)]
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub fn open() -> Result<Self, StoreError> {
+        let conn = Connection::open("relay.db")?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        Self::init(&conn)?;
+        Ok(Self { conn })
+    }
+
+    pub fn init(conn: &Connection) -> Result<(), StoreError> {
+        conn.execute_batch(
+            "
+                CREATE TABLE IF NOT EXISTS takedowns (
+                    kind TEXT NOT NULL,
+                    value TEXT NOT NULL,
+                    reason TEXT,
+                    created_at TEXT NOT NULL,
+                    PRIMARY KEY (kind, value)
+                );
+                CREATE TABLE IF NOT EXISTS audit_log (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    action TEXT NOT NULL,
+                    kind TEXT NOT NULL,
+                    value TEXT NOT NULL,
+                    reason TEXT,
+                    addr TEXT NOT NULL,
+                    created_at TEXT NOT NULL
+                );
+            ",
+        )?;
+        Ok(())
+    }
+
+    pub fn load(conn: &Connection) -> Result<Takedowns, StoreError> {
+        let mut takedowns = Takedowns::default();
+        let mut stmt = conn.prepare_cached("SELECT kind, value FROM takedowns")?;
+        let mut rows = stmt.query(())?;
+        while let Some(row) = rows.next()? {
+            let kind: String = row.get_unwrap("kind");
+            let subject = Subject::from_parts(&kind, row.get_unwrap("value"))
+                .ok_or(StoreError::InvalidSubject(kind))?;
+            takedowns.insert(subject);
+        }
+        Ok(takedowns)
+    }
+
+    /// Records a takedown and its audit entry, returning `false` if it was already active.
+    pub fn takedown(
+        &mut self, subject: &Subject, reason: Option<&str>, addr: &str,
+    ) -> Result<bool, StoreError> {
+        let now = Utc::now();
+        let tx = self.conn.transaction()?;
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO takedowns (kind, value, reason, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            (subject.kind(), subject.value(), reason, now),
+        )? > 0;
+        if inserted {
+            Self::audit(&tx, "takedown", subject, reason, addr, now)?;
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// Lifts a takedown and records the audit entry, returning `false` if none was active.
+    pub fn untakedown(
+        &mut self, subject: &Subject, reason: Option<&str>, addr: &str,
+    ) -> Result<bool, StoreError> {
+        let now = Utc::now();
+        let tx = self.conn.transaction()?;
+        let removed = tx.execute(
+            "DELETE FROM takedowns WHERE kind = ?1 AND value = ?2",
+            (subject.kind(), subject.value()),
+        )? > 0;
+        if removed {
+            Self::audit(&tx, "untakedown", subject, reason, addr, now)?;
+        }
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    pub fn is_taken_down(&self, subject: &Subject) -> Result<bool, StoreError> {
+        let mut stmt =
+            self.conn.prepare_cached("SELECT 1 FROM takedowns WHERE kind = ?1 AND value = ?2")?;
+        Ok(stmt.query_row((subject.kind(), subject.value()), |_| Ok(())).optional()?.is_some())
+    }
+
+    pub fn list(&self, kind: &str) -> Result<Vec<Takedown>, StoreError> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT kind, value, reason, created_at FROM takedowns
+             WHERE kind = ?1 ORDER BY created_at DESC",
+        )?;
+        let mut rows = stmt.query((kind,))?;
+        let mut takedowns = Vec::new();
+        while let Some(row) = rows.next()? {
+            let kind: String = row.get_unwrap("kind");
+            let subject = Subject::from_parts(&kind, row.get_unwrap("value"))
+                .ok_or(StoreError::InvalidSubject(kind))?;
+            takedowns.push(Takedown {
+                subject,
+                reason: row.get_unwrap("reason"),
+                created_at: row.get_unwrap("created_at"),
+            });
+        }
+        Ok(takedowns)
+    }
+
+    pub fn audit_log(
+        &self, limit: u32, before: Option<u64>,
+    ) -> Result<Vec<AuditEntry>, StoreError> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, action, kind, value, reason, addr, created_at FROM audit_log
+             WHERE ?1 IS NULL OR id < ?1 ORDER BY id DESC LIMIT ?2",
+        )?;
+        let mut rows = stmt.query((before, limit))?;
+        let mut entries = Vec::new();
+        while let Some(row) = rows.next()? {
+            let kind: String = row.get_unwrap("kind");
+            let subject = Subject::from_parts(&kind, row.get_unwrap("value"))
+                .ok_or(StoreError::InvalidSubject(kind))?;
+            entries.push(AuditEntry {
+                id: row.get_unwrap("id"),
+                action: row.get_unwrap("action"),
+                subject,
+                reason: row.get_unwrap("reason"),
+                addr: row.get_unwrap("addr"),
+                created_at: row.get_unwrap("created_at"),
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Host cursors as of the validator's last write, for one host or all of them.
+    pub fn cursors(&self, host: Option<&str>) -> Result<Vec<HostCursor>, StoreError> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT host, cursor, latest FROM hosts WHERE ?1 IS NULL OR host = ?1 ORDER BY host",
+        )?;
+        let mut rows = stmt.query((host,))?;
+        let mut cursors = Vec::new();
+        while let Some(row) = rows.next()? {
+            cursors.push(HostCursor {
+                host: row.get_unwrap("host"),
+                cursor: row.get_unwrap("cursor"),
+                latest: row.get_unwrap("latest"),
+            });
+        }
+        Ok(cursors)
+    }
+
+    fn audit(
+        conn: &Connection, action: &str, subject: &Subject, reason: Option<&str>, addr: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), StoreError> {
+        conn.execute(
+            "INSERT INTO audit_log (action, kind, value, reason, addr, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            (action, subject.kind(), subject.value(), reason, addr, now),
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> Store {
+        let conn = Connection::open_in_memory().unwrap();
+        Store::init(&conn).unwrap();
+        Store { conn }
+    }
+
+    #[test]
+    fn takedown_roundtrip() {
+        let mut store = store();
+        let did = Subject::Did("did:plc:abc".to_owned());
+        let host = Subject::Host("pds.example.com".to_owned());
+
+        assert!(store.takedown(&did, Some("spam"), "127.0.0.1:1").unwrap());
+        assert!(!store.takedown(&did, None, "127.0.0.1:1").unwrap());
+        assert!(store.takedown(&host, None, "127.0.0.1:1").unwrap());
+        assert!(store.is_taken_down(&did).unwrap());
+        assert_eq!(store.list("did").unwrap().len(), 1);
+
+        let takedowns = Store::load(&store.conn).unwrap();
+        assert!(takedowns.is_did_taken_down("did:plc:abc"));
+        assert!(takedowns.is_host_taken_down("pds.example.com"));
+
+        assert!(store.untakedown(&did, None, "127.0.0.1:1").unwrap());
+        assert!(!store.untakedown(&did, None, "127.0.0.1:1").unwrap());
+        assert!(!store.is_taken_down(&did).unwrap());
+
+        let log = store.audit_log(10, None).unwrap();
+        assert_eq!(log.len(), 3);
+        assert_eq!(log[0].action, "untakedown");
+        assert_eq!(store.audit_log(10, Some(log[0].id)).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn lists_host_cursors() {
+        let store = store();
+        store
+            .conn
+            .execute_batch(
+                "CREATE TABLE hosts (host TEXT PRIMARY KEY, cursor INTEGER NOT NULL, latest TEXT NOT NULL);
+                 INSERT INTO hosts VALUES ('b.example.com', 7, '2025-01-01T00:00:00Z');
+                 INSERT INTO hosts VALUES ('a.example.com', 42, '2025-01-01T00:00:00Z');",
+            )
+            .unwrap();
+
+        let cursors = store.cursors(None).unwrap();
+        let hosts: Vec<_> = cursors.iter().map(|c| (c.host.as_str(), c.cursor)).collect();
+        assert_eq!(hosts, vec![("a.example.com", 42), ("b.example.com", 7)]);
+        assert_eq!(store.cursors(Some("b.example.com")).unwrap().len(), 1);
+        assert!(store.cursors(Some("c.example.com")).unwrap().is_empty());
+    }
+}
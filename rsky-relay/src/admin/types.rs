@@ -0,0 +1,115 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use hashbrown::HashSet;
+use rtrb::{Consumer, Producer};
+use serde::{Deserialize, Serialize};
+
+pub type AdminActionSender = Producer<AdminAction>;
+pub type AdminActionReceiver = Consumer<AdminAction>;
+
+/// What a relay takedown applies to: a single account, or every event crawled from a host.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Subject {
+    Did(String),
+    Host(String),
+}
+
+impl Subject {
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::Did(_) => "did",
+            Self::Host(_) => "host",
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Did(value) | Self::Host(value) => value,
+        }
+    }
+
+    pub fn from_parts(kind: &str, value: String) -> Option<Self> {
+        match kind {
+            "did" => Some(Self::Did(value)),
+            "host" => Some(Self::Host(value)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.kind(), self.value())
+    }
+}
+
+#[derive(Debug)]
+pub enum AdminAction {
+    Takedown(Subject),
+    Untakedown(Subject),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Takedown {
+    #[serde(flatten)]
+    pub subject: Subject,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: u64,
+    pub action: String,
+    #[serde(flatten)]
+    pub subject: Subject,
+    pub reason: Option<String>,
+    pub addr: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Last seq the validator recorded for a crawled host, as persisted in `relay.db`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostCursor {
+    pub host: String,
+    pub cursor: u64,
+    pub latest: DateTime<Utc>,
+}
+
+/// In-memory view of the active takedowns, consulted for every event in the validator.
+#[derive(Debug, Default)]
+pub struct Takedowns {
+    dids: HashSet<String>,
+    hosts: HashSet<String>,
+}
+
+impl Takedowns {
+    #[inline]
+    pub fn is_did_taken_down(&self, did: &str) -> bool {
+        self.dids.contains(did)
+    }
+
+    #[inline]
+    pub fn is_host_taken_down(&self, host: &str) -> bool {
+        self.hosts.contains(host)
+    }
+
+    pub fn insert(&mut self, subject: Subject) -> bool {
+        match subject {
+            Subject::Did(did) => self.dids.insert(did),
+            Subject::Host(host) => self.hosts.insert(host),
+        }
+    }
+
+    pub fn remove(&mut self, subject: &Subject) -> bool {
+        match subject {
+            Subject::Did(did) => self.dids.remove(did),
+            Subject::Host(host) => self.hosts.remove(host),
+        }
+    }
+}
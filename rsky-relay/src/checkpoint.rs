@@ -0,0 +1,214 @@
+//! Crash-consistent checkpoints of the validator's output. Everything one validator pass writes,
+//! frames, queued messages, repo state and the per-host cursors they advanced, is staged and
+//! committed as one batch, so after an unclean shutdown the crawler resumes each host right after
+//! the last event that made it to disk, never skipping past one that didn't.
+//!
+//! Output seqs are reserved write-ahead in blocks of `SEQ_RESERVE`, synced before any seq in the
+//! block is handed out. Frames published just before a crash can be lost with the journal's tail,
+//! but their seqs stay reserved, so the restarted relay never reuses a seq subscribers have seen.
+//! The firehose can therefore skip up to `SEQ_RESERVE` seqs, and its readers seek to the next
+//! frame instead of stepping through seqs one by one.
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use fjall::{Batch, PartitionCreateOptions, PartitionHandle, PersistMode, Slice};
+use hashbrown::{HashMap, HashSet};
+use thiserror::Error;
+
+use crate::config::SEQ_RESERVE;
+use crate::types::{Cursor, DB};
+
+const SEQ_KEY: &[u8] = b"seq";
+const HOST_PREFIX: &str = "host>";
+
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    #[error("fjall error: {0}")]
+    Fjall(#[from] fjall::Error),
+}
+
+/// Each host's last seq and event time, tracking which hosts moved since the last checkpoint.
+#[derive(Default)]
+pub struct HostCursors {
+    cursors: HashMap<String, (Cursor, DateTime<Utc>)>,
+    dirty: HashSet<String>,
+}
+
+impl HostCursors {
+    pub fn get(&self, host: &str) -> Option<&(Cursor, DateTime<Utc>)> {
+        self.cursors.get(host)
+    }
+
+    pub fn insert(&mut self, host: String, value: (Cursor, DateTime<Utc>)) {
+        self.dirty.insert(host.clone());
+        self.cursors.insert(host, value);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &(Cursor, DateTime<Utc>))> {
+        self.cursors.iter()
+    }
+}
+
+impl fmt::Debug for HostCursors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.cursors.iter()).finish()
+    }
+}
+
+pub struct Checkpoint {
+    partition: PartitionHandle,
+    batch: Option<Batch>,
+    // highest seq durably reserved
+    reserved: u64,
+    // last seq handed out
+    last: u64,
+}
+
+impl Checkpoint {
+    pub fn open() -> Result<Self, CheckpointError> {
+        Self::with_partition(DB.open_partition("checkpoint", PartitionCreateOptions::default())?)
+    }
+
+    fn with_partition(partition: PartitionHandle) -> Result<Self, CheckpointError> {
+        let reserved = partition.get(SEQ_KEY)?.map_or(0, |seq| Cursor::from(seq).get());
+        Ok(Self { partition, batch: None, reserved, last: reserved })
+    }
+
+    /// The seq to resume from: past the last frame on the firehose, and past every seq reserved
+    /// before an unclean shutdown.
+    pub fn resume(&mut self, firehose: Cursor) -> Cursor {
+        self.last = firehose.get().max(self.reserved);
+        self.last.into()
+    }
+
+    /// Advances `cursor` to the next seq, first reserving another block if it's used up.
+    pub fn next(&mut self, cursor: &mut Cursor) -> Result<Cursor, CheckpointError> {
+        if cursor.get() >= self.reserved {
+            let reserved = cursor.get() + SEQ_RESERVE;
+            self.partition.insert(SEQ_KEY, Cursor::from(reserved))?;
+            DB.persist(PersistMode::SyncAll)?;
+            self.reserved = reserved;
+        }
+        let next = cursor.next();
+        self.last = next.get();
+        Ok(next)
+    }
+
+    /// Stages a write for the next commit.
+    pub fn insert<K: Into<Slice>, V: Into<Slice>>(
+        &mut self, partition: &PartitionHandle, key: K, value: V,
+    ) {
+        self.batch.get_or_insert_with(|| DB.batch()).insert(partition, key, value);
+    }
+
+    /// Stages a removal for the next commit.
+    pub fn remove<K: Into<Slice>>(&mut self, partition: &PartitionHandle, key: K) {
+        self.batch.get_or_insert_with(|| DB.batch()).remove(partition, key);
+    }
+
+    /// Commits everything staged, along with the cursors of the hosts that moved since.
+    pub fn commit(&mut self, hosts: &mut HostCursors) -> Result<(), CheckpointError> {
+        for host in hosts.dirty.drain() {
+            if let Some((cursor, _)) = hosts.cursors.get(&host) {
+                let key = format!("{HOST_PREFIX}{host}");
+                self.batch.get_or_insert_with(|| DB.batch()).insert(&self.partition, key, *cursor);
+            }
+        }
+        if let Some(batch) = self.batch.take() {
+            batch.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Hands back the unused part of the reservation on a clean shutdown, so the next start
+    /// resumes without a gap.
+    pub fn release(&mut self) -> Result<(), CheckpointError> {
+        self.partition.insert(SEQ_KEY, Cursor::from(self.last))?;
+        self.reserved = self.last;
+        Ok(())
+    }
+
+    /// Every checkpointed host cursor.
+    pub fn hosts(&self) -> impl Iterator<Item = Result<(String, Cursor), CheckpointError>> {
+        self.partition.prefix(HOST_PREFIX).map(|res| {
+            let (key, value) = res?;
+            let host = String::from_utf8_lossy(&key[HOST_PREFIX.len()..]).into_owned();
+            Ok((host, value.into()))
+        })
+    }
+
+    /// The checkpointed cursor for `host`.
+    pub fn host_cursor(&self, host: &str) -> Result<Option<Cursor>, CheckpointError> {
+        Ok(self.partition.get(format!("{HOST_PREFIX}{host}"))?.map(Into::into))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(name: &str) -> Checkpoint {
+        let partition = DB.open_partition(name, PartitionCreateOptions::default()).unwrap();
+        Checkpoint::with_partition(partition).unwrap()
+    }
+
+    #[test]
+    fn resumes_past_reserved_seqs() {
+        let mut checkpoint = open("checkpoint-test-resume");
+        let mut cursor = checkpoint.resume(10.into());
+        assert_eq!(cursor.get(), 10);
+        assert_eq!(checkpoint.next(&mut cursor).unwrap().get(), 11);
+        assert_eq!(checkpoint.next(&mut cursor).unwrap().get(), 12);
+
+        // unclean shutdown: the firehose lost its tail, but seqs stay reserved
+        let mut restarted = open("checkpoint-test-resume");
+        assert_eq!(restarted.resume(11.into()).get(), 10 + SEQ_RESERVE);
+
+        // clean shutdown: nothing is skipped
+        checkpoint.release().unwrap();
+        let mut restarted = open("checkpoint-test-resume");
+        assert_eq!(restarted.resume(12.into()).get(), 12);
+    }
+
+    #[test]
+    fn readers_cross_the_gap_after_a_crash() {
+        let firehose = DB
+            .open_partition("checkpoint-test-gap-firehose", PartitionCreateOptions::default())
+            .unwrap();
+        let mut hosts = HostCursors::default();
+        let mut checkpoint = open("checkpoint-test-gap");
+        let mut cursor = checkpoint.resume(0.into());
+        let before = checkpoint.next(&mut cursor).unwrap();
+        checkpoint.insert(&firehose, before, b"before".to_vec());
+        checkpoint.commit(&mut hosts).unwrap();
+
+        // crash without releasing the reservation
+        drop(checkpoint);
+        let mut restarted = open("checkpoint-test-gap");
+        let mut cursor = restarted.resume(before);
+        let after = restarted.next(&mut cursor).unwrap();
+        assert!(after.get() > before.get() + 256);
+        restarted.insert(&firehose, after, b"after".to_vec());
+        restarted.commit(&mut hosts).unwrap();
+
+        // a subscriber caught up to `before` still reaches the next frame
+        let (key, value) = firehose.range((before + 1)..).next().unwrap().unwrap();
+        assert_eq!(Cursor::from(key), after);
+        assert_eq!(&*value, b"after");
+    }
+
+    #[test]
+    fn commits_moved_host_cursors() {
+        let mut checkpoint = open("checkpoint-test-hosts");
+        let mut hosts = HostCursors::default();
+        hosts.insert("pds.example.com".to_owned(), (7.into(), Utc::now()));
+        assert_eq!(checkpoint.host_cursor("pds.example.com").unwrap(), None);
+
+        checkpoint.commit(&mut hosts).unwrap();
+        assert_eq!(checkpoint.host_cursor("pds.example.com").unwrap(), Some(7.into()));
+        let all = checkpoint.hosts().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(all, vec![("pds.example.com".to_owned(), 7.into())]);
+        assert!(hosts.dirty.is_empty());
+    }
+}
@@ -8,12 +8,105 @@ pub const CAPACITY_REQS: usize = 1 << 12;
 pub const CAPACITY_STATUS: usize = 1 << 10;
 pub const WORKERS_CRAWLERS: usize = 4;
 pub const WORKERS_PUBLISHERS: usize = 4;
+pub const WORKERS_MAX: usize = 64;
 
 // server
 pub const PORT: u16 = if cfg!(feature = "labeler") { 9001 } else { 9000 };
 pub const HOSTS_RELAY: &str = "relay1.us-west.bsky.network";
 pub const HOSTS_INTERVAL: Duration = Duration::from_secs(60 * 60);
 pub const HOSTS_MIN_ACCOUNTS: u64 = 0;
+pub static UPSTREAMS: LazyLock<Vec<String>> = LazyLock::new(|| {
+    env::var("RELAY_UPSTREAMS")
+        .map(|hosts| {
+            hosts
+                .split(',')
+                .map(str::trim)
+                .filter(|host| !host.is_empty())
+                .map(Into::into)
+                .collect()
+        })
+        .unwrap_or_default()
+});
+
+// subscribers
+pub static SUBSCRIBER_ORIGINS: LazyLock<Vec<String>> = LazyLock::new(|| {
+    env::var("RELAY_SUBSCRIBER_ORIGINS")
+        .map(|origins| {
+            origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(Into::into)
+                .collect()
+        })
+        .unwrap_or_default()
+});
+/// `name:token` pairs; the name identifies the subscriber in logs and connection limits.
+pub static SUBSCRIBER_TOKENS: LazyLock<Vec<(String, String)>> = LazyLock::new(|| {
+    env::var("RELAY_SUBSCRIBER_TOKENS")
+        .map(|tokens| {
+            tokens
+                .split(',')
+                .filter_map(|token| token.trim().split_once(':'))
+                .filter(|(_, token)| !token.is_empty())
+                .map(|(name, token)| (name.to_owned(), token.to_owned()))
+                .collect()
+        })
+        .unwrap_or_default()
+});
+/// This relay's DID, which service JWTs must name as their audience to be accepted.
+pub static SUBSCRIBER_AUDIENCE: LazyLock<Option<String>> =
+    LazyLock::new(|| env::var("RELAY_SUBSCRIBER_AUDIENCE").ok().filter(|did| !did.is_empty()));
+pub static SUBSCRIBER_AUTH_REQUIRED: LazyLock<bool> = LazyLock::new(|| {
+    env::var("RELAY_SUBSCRIBER_AUTH_REQUIRED").is_ok_and(|required| required == "true")
+});
+/// Concurrent connections allowed per subscriber, or per IP address for anonymous ones.
+pub static SUBSCRIBER_MAX_CONNECTIONS: LazyLock<Option<usize>> = LazyLock::new(|| {
+    env::var("RELAY_SUBSCRIBER_MAX_CONNECTIONS").ok().and_then(|max| max.parse().ok())
+});
+
+// admin
+pub static ADMIN_PASSWORD: LazyLock<Option<String>> =
+    LazyLock::new(|| env::var("RELAY_ADMIN_PASSWORD").ok().filter(|password| !password.is_empty()));
+pub const AUDIT_LOG_LIMIT: u32 = 100;
+
+// custody
+pub static CUSTODY: LazyLock<bool> = LazyLock::new(|| env::args().any(|arg| arg == "--custody"));
+pub const CUSTODY_LIMIT: usize = 1000;
+pub const CUSTODY_DISK_SIZE: u64 = 16 * 1024 * 1024 * 1024; // 16 GiB
+
+// sampler
+pub static SAMPLE_RATE: LazyLock<f64> = LazyLock::new(|| {
+    env::var("RELAY_SAMPLE_RATE").ok().and_then(|rate| rate.parse().ok()).unwrap_or(0.0)
+});
+pub static SAMPLE_OUTPUT: LazyLock<Option<String>> =
+    LazyLock::new(|| env::var("RELAY_SAMPLE_OUTPUT").ok().filter(|output| !output.is_empty()));
+pub const CAPACITY_SAMPLES: usize = 1 << 12;
+pub const SAMPLE_RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+// sinks
+pub static NATS_URL: LazyLock<Option<String>> =
+    LazyLock::new(|| env::var("RELAY_NATS_URL").ok().filter(|url| !url.is_empty()));
+pub static NATS_SUBJECT: LazyLock<String> = LazyLock::new(|| {
+    env::var("RELAY_NATS_SUBJECT").unwrap_or_else(|_| "atproto.firehose".to_owned())
+});
+pub static KAFKA_BROKERS: LazyLock<Vec<String>> = LazyLock::new(|| {
+    env::var("RELAY_KAFKA_BROKERS")
+        .map(|brokers| {
+            brokers
+                .split(',')
+                .map(str::trim)
+                .filter(|broker| !broker.is_empty())
+                .map(Into::into)
+                .collect()
+        })
+        .unwrap_or_default()
+});
+pub static KAFKA_TOPIC: LazyLock<String> = LazyLock::new(|| {
+    env::var("RELAY_KAFKA_TOPIC").unwrap_or_else(|_| "atproto.firehose".to_owned())
+});
+pub const SINK_BATCH: usize = 256;
+pub const SINK_RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
 
 // resolver
 pub static DO_PLC_EXPORT: LazyLock<bool> = LazyLock::new(|| {
@@ -24,8 +117,10 @@ pub const CAPACITY_CACHE: usize = 1 << 18;
 
 // validator
 pub const HOSTS_WRITE_INTERVAL: Duration = Duration::from_secs(10);
+pub const CAPACITY_RECENT: usize = 1 << 16;
 
 // firehose
+pub const SEQ_RESERVE: u64 = 1 << 16;
 pub const DISK_SIZE: u64 = 320 * 1024 * 1024 * 1024; // 320 GiB
 pub const TTL_SECONDS: Option<u64> = if cfg!(feature = "labeler") {
     None
@@ -33,7 +128,15 @@ pub const TTL_SECONDS: Option<u64> = if cfg!(feature = "labeler") {
     Some(24 * 60 * 60) // 24 hours
 };
 
+// watchdog
+pub const DISK_RESERVE: u64 = 32 * 1024 * 1024 * 1024; // 32 GiB
+pub const DISK_WARN: u64 = 16 * 1024 * 1024 * 1024; // 16 GiB
+pub const DISK_CRITICAL: u64 = 4 * 1024 * 1024 * 1024; // 4 GiB
+pub const DISK_MIN_SIZE: u64 = 1024 * 1024 * 1024; // 1 GiB
+pub const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
 // fjall db
+pub const DB_PATH: &str = "db";
 pub const CACHE_SIZE: u64 = 1024 * 1024 * 1024; // 1 GiB
 pub const WRITE_BUFFER_SIZE: u64 = 512 * 1024 * 1024; // 512 MiB
 pub const FSYNC_MS: Option<u16> = Some(1000); // 1 second
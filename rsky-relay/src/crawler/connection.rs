@@ -1,6 +1,7 @@
-use std::io;
 use std::os::fd::{AsRawFd, RawFd};
+use std::{fmt, io};
 
+use chrono::Utc;
 use thingbuf::mpsc;
 use thiserror::Error;
 use tungstenite::Message;
@@ -27,6 +28,12 @@ pub struct Connection {
     message_tx: MessageSender,
 }
 
+impl fmt::Debug for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Connection").field("hostname", &self.hostname).finish_non_exhaustive()
+    }
+}
+
 impl AsRawFd for Connection {
     #[inline]
     fn as_raw_fd(&self) -> RawFd {
@@ -100,6 +107,7 @@ impl Connection {
             let mut slot = self.message_tx.send_ref()?;
             slot.data = bytes;
             slot.hostname.clone_from(&self.hostname);
+            slot.received_at = Utc::now();
         }
         Ok(true)
     }
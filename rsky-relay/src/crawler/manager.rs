@@ -10,12 +10,15 @@ use magnetic::buffer::dynamic::DynamicBufferP2;
 use rusqlite::{Connection, ErrorCode, OpenFlags, OptionalExtension};
 use thiserror::Error;
 
-use crate::SHUTDOWN;
-use crate::config::CAPACITY_STATUS;
+use crate::checkpoint::{Checkpoint, CheckpointError};
+use crate::config::{CAPACITY_STATUS, WORKERS_MAX};
 use crate::crawler::RequestCrawl;
-use crate::crawler::types::{Command, CommandSender, RequestCrawlReceiver, Status, StatusReceiver};
+use crate::crawler::types::{
+    Command, CommandSender, RequestCrawlReceiver, Status, StatusReceiver, StatusSender,
+};
 use crate::crawler::worker::{Worker, WorkerError};
 use crate::types::{Cursor, MessageSender};
+use crate::{CRAWLER_WORKERS, SHUTDOWN};
 
 const SLEEP: Duration = Duration::from_millis(10);
 
@@ -29,6 +32,8 @@ pub enum ManagerError {
     Push(#[from] Box<rtrb::PushError<Command>>),
     #[error("sqlite error: {0}")]
     Sqlite(#[from] rusqlite::Error),
+    #[error("checkpoint error: {0}")]
+    Checkpoint(#[from] CheckpointError),
     #[error("join error")]
     Join,
 }
@@ -46,12 +51,21 @@ struct WorkerHandle {
 }
 
 pub struct Manager {
-    workers: Box<[WorkerHandle]>,
+    workers: Vec<WorkerHandle>,
+    /// Workers told to retire, kept until their threads exit.
+    retiring: Vec<WorkerHandle>,
     next_id: usize,
+    /// Released connections go to workers from `adopt_from` on, so after growing the pool they
+    /// land on the new workers.
+    adopt_from: usize,
+    next_adopt: usize,
     hosts: HashMap<String, [BackoffIter; 2]>,
     retries: BTreeMap<Instant, (usize, String)>,
     conn: Connection,
+    checkpoint: Checkpoint,
     request_crawl_rx: RequestCrawlReceiver,
+    message_tx: MessageSender,
+    status_tx: StatusSender,
     status_rx: StatusReceiver,
 }
 
@@ -63,28 +77,27 @@ impl Manager {
         let (status_tx, status_rx) =
             magnetic::mpsc::mpsc_queue(DynamicBufferP2::new(CAPACITY_STATUS).unwrap());
         let workers = (0..n_workers)
-            .map(|worker_id| -> Result<_, ManagerError> {
-                let message_tx = message_tx.clone();
-                let status_tx = status_tx.clone();
-                let (command_tx, command_rx) = rtrb::RingBuffer::new(CAPACITY_STATUS);
-                let thread_handle =
-                    thread::Builder::new().name(format!("rsky-crawl-{worker_id}")).spawn(
-                        move || Worker::new(worker_id, message_tx, command_rx, status_tx)?.run(),
-                    )?;
-                Ok(WorkerHandle { command_tx, thread_handle })
-            })
+            .map(|worker_id| spawn_worker(worker_id, message_tx, &status_tx))
             .collect::<Result<Vec<_>, _>>()?;
+        CRAWLER_WORKERS.store(n_workers, Ordering::Relaxed);
         let conn = Connection::open_with_flags(
             "relay.db",
             OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
         )?;
+        let checkpoint = Checkpoint::open()?;
         Ok(Self {
-            workers: workers.into_boxed_slice(),
+            workers,
+            retiring: Vec::new(),
             next_id: 0,
+            adopt_from: 0,
+            next_adopt: 0,
             hosts: HashMap::new(),
             retries: BTreeMap::new(),
             conn,
+            checkpoint,
             request_crawl_rx,
+            message_tx: message_tx.clone(),
+            status_tx,
             status_rx,
         })
     }
@@ -99,7 +112,7 @@ impl Manager {
 
     pub fn shutdown(self) -> Result<(), ManagerError> {
         SHUTDOWN.store(true, Ordering::Relaxed);
-        for (id, worker) in self.workers.into_iter().enumerate() {
+        for (id, worker) in self.workers.into_iter().chain(self.retiring).enumerate() {
             if let Err(err) = worker.thread_handle.join().map_err(|_| ManagerError::Join)? {
                 tracing::warn!(%id, %err, "crawler worker error");
             }
@@ -112,18 +125,21 @@ impl Manager {
             return Ok(false);
         }
 
+        self.resize()?;
+
         if let Some(entry) = self.retries.first_entry() {
             if *entry.key() < Instant::now() {
                 let (id, hostname) = entry.remove();
                 let prev = self.next_id;
-                self.next_id = id;
+                // the worker may have been retired since
+                self.next_id = id % self.workers.len();
                 self.handle_connect(RequestCrawl { hostname, cursor: None })?;
                 self.next_id = prev;
             }
         }
 
-        if let Ok(status) = self.status_rx.try_pop() {
-            self.handle_status(status);
+        while let Ok(status) = self.status_rx.try_pop() {
+            self.handle_status(status)?;
         }
 
         if let Ok(request_crawl) = self.request_crawl_rx.pop() {
@@ -135,7 +151,42 @@ impl Manager {
         Ok(true)
     }
 
-    fn handle_status(&mut self, status: Status) {
+    /// Grows or shrinks the pool towards [`CRAWLER_WORKERS`], and reaps retired workers.
+    fn resize(&mut self) -> Result<(), ManagerError> {
+        let target = CRAWLER_WORKERS.load(Ordering::Relaxed).clamp(1, WORKERS_MAX);
+        let from = self.workers.len();
+        if target > from {
+            tracing::info!(%from, to = %target, "growing crawler pool");
+            for worker in &mut self.workers {
+                worker.command_tx.push(Command::Rebalance { from, to: target })?;
+            }
+            for worker_id in from..target {
+                self.workers.push(spawn_worker(worker_id, &self.message_tx, &self.status_tx)?);
+            }
+            self.adopt_from = from;
+        } else if target < from {
+            tracing::info!(%from, to = %target, "shrinking crawler pool");
+            for mut worker in self.workers.drain(target..) {
+                worker.command_tx.push(Command::Retire)?;
+                self.retiring.push(worker);
+            }
+            self.adopt_from = 0;
+            self.next_id %= target;
+        }
+        let (finished, retiring) = self
+            .retiring
+            .drain(..)
+            .partition::<Vec<_>, _>(|worker| worker.thread_handle.is_finished());
+        self.retiring = retiring;
+        for worker in finished {
+            if let Err(err) = worker.thread_handle.join().map_err(|_| ManagerError::Join)? {
+                tracing::warn!(%err, "retired crawler worker error");
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_status(&mut self, status: Status) -> Result<(), ManagerError> {
         match status {
             Status::Disconnected { worker_id: id, hostname, connected } => {
                 #[expect(clippy::unwrap_used)]
@@ -145,7 +196,14 @@ impl Manager {
                 let next = Instant::now() + delay;
                 assert!(self.retries.insert(next, (id, hostname)).is_none());
             }
+            Status::Released(conn) => {
+                let adopters = self.workers.len() - self.adopt_from;
+                let id = self.adopt_from + self.next_adopt % adopters;
+                self.next_adopt = self.next_adopt.wrapping_add(1);
+                self.workers[id].command_tx.push(Command::Adopt(conn))?;
+            }
         }
+        Ok(())
     }
 
     fn handle_connect(&mut self, mut request_crawl: RequestCrawl) -> Result<(), ManagerError> {
@@ -176,6 +234,10 @@ impl Manager {
     }
 
     fn get_cursor(&self, host: &str) -> Result<Option<Cursor>, ManagerError> {
+        // committed with the firehose, so never past an event that didn't make it to disk
+        if let Some(cursor) = self.checkpoint.host_cursor(host)? {
+            return Ok(Some(cursor));
+        }
         let mut stmt = self.conn.prepare_cached("SELECT * FROM hosts WHERE host = ?1")?;
         Ok(stmt
             .query_one((&host,), |row| Ok(row.get_unwrap::<_, u64>("cursor")))
@@ -183,3 +245,15 @@ impl Manager {
             .map(Into::into))
     }
 }
+
+fn spawn_worker(
+    worker_id: usize, message_tx: &MessageSender, status_tx: &StatusSender,
+) -> Result<WorkerHandle, ManagerError> {
+    let message_tx = message_tx.clone();
+    let status_tx = status_tx.clone();
+    let (command_tx, command_rx) = rtrb::RingBuffer::new(CAPACITY_STATUS);
+    let thread_handle = thread::Builder::new()
+        .name(format!("rsky-crawl-{worker_id}"))
+        .spawn(move || Worker::new(worker_id, message_tx, command_rx, status_tx)?.run())?;
+    Ok(WorkerHandle { command_tx, thread_handle })
+}
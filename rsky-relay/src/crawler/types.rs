@@ -9,6 +9,7 @@ use tungstenite::handshake::client::Response;
 use tungstenite::stream::MaybeTlsStream;
 use tungstenite::{ClientHandshake, HandshakeError, WebSocket};
 
+use crate::crawler::connection::Connection;
 use crate::types::Cursor;
 
 pub type MaybeTlsTcpStream = MaybeTlsStream<TcpStream>;
@@ -50,9 +51,20 @@ pub struct RequestCrawl {
 #[derive(Debug)]
 pub enum Command {
     Connect(RequestCrawl),
+    /// Take over a live connection another worker released.
+    Adopt(Connection),
+    /// The pool grew from `from` to `to` workers: release all but a `from / to` share of
+    /// connections for the new workers to adopt.
+    Rebalance {
+        from: usize,
+        to: usize,
+    },
+    /// Release every connection and exit.
+    Retire,
 }
 
 #[derive(Debug)]
 pub enum Status {
     Disconnected { worker_id: usize, hostname: String, connected: bool },
+    Released(Connection),
 }
@@ -37,6 +37,7 @@ pub struct Worker {
     status_tx: StatusSender,
     poll: Poll,
     events: Events,
+    retired: bool,
 }
 
 impl Worker {
@@ -55,6 +56,7 @@ impl Worker {
             status_tx,
             poll,
             events,
+            retired: false,
         })
     }
 
@@ -65,6 +67,10 @@ impl Worker {
         while self.update() {
             thread::yield_now();
         }
+        if self.retired {
+            tracing::info!("retired");
+            return Ok(());
+        }
         tracing::info!("shutting down");
         self.shutdown();
         Ok(())
@@ -83,24 +89,64 @@ impl Worker {
                 let res = Connection::connect(&config.hostname, config.cursor);
                 self.handle_connect(Instant::now(), config.hostname, res);
             }
+            Command::Adopt(conn) => {
+                tracing::debug!(host = %conn.hostname, "adopting crawl");
+                self.add(conn);
+            }
+            Command::Rebalance { from, to } => {
+                let active = self.connections.iter().flatten().count();
+                self.release(active * from / to);
+            }
+            Command::Retire => {
+                self.release(0);
+                for (_, hostname, _) in std::mem::take(&mut self.pending) {
+                    #[expect(clippy::expect_used)]
+                    self.status_tx
+                        .push(Status::Disconnected {
+                            worker_id: self.id,
+                            hostname,
+                            connected: false,
+                        })
+                        .expect("unable to send status");
+                }
+                self.retired = true;
+            }
+        }
+    }
+
+    fn add(&mut self, conn: Connection) {
+        let idx = self.connections.iter().position(Option::is_none).unwrap_or_else(|| {
+            let idx = self.connections.len();
+            self.connections.push(None);
+            idx
+        });
+        #[expect(clippy::expect_used)]
+        self.poll
+            .registry()
+            .register(&mut SourceFd(&conn.as_raw_fd()), Token(idx), INTEREST)
+            .expect("unable to register");
+        self.connections[idx] = Some(conn);
+    }
+
+    /// Hands every connection past the first `keep` back to the manager for another worker.
+    fn release(&mut self, keep: usize) {
+        for slot in self.connections.iter_mut().filter(|slot| slot.is_some()).skip(keep) {
+            #[expect(clippy::unwrap_used)]
+            let conn = slot.take().unwrap();
+            #[expect(clippy::expect_used)]
+            self.poll
+                .registry()
+                .deregister(&mut SourceFd(&conn.as_raw_fd()))
+                .expect("failed to deregister");
+            #[expect(clippy::expect_used)]
+            self.status_tx.push(Status::Released(conn)).expect("unable to send status");
         }
     }
 
     fn handle_connect(&mut self, start: Instant, hostname: String, result: HandshakeResult) {
         match result {
             Ok(Ok(client)) => {
-                let idx = self.connections.iter().position(Option::is_none).unwrap_or_else(|| {
-                    let idx = self.connections.len();
-                    self.connections.push(None);
-                    idx
-                });
-                let conn = Connection::new(hostname, client, self.message_tx.clone());
-                #[expect(clippy::expect_used)]
-                self.poll
-                    .registry()
-                    .register(&mut SourceFd(&conn.as_raw_fd()), Token(idx), INTEREST)
-                    .expect("unable to register");
-                self.connections[idx] = Some(conn);
+                self.add(Connection::new(hostname, client, self.message_tx.clone()));
                 return;
             }
             Ok(Err(handshaking)) if start.elapsed() < TIMEOUT => {
@@ -122,7 +168,7 @@ impl Worker {
     }
 
     fn update(&mut self) -> bool {
-        if SHUTDOWN.load(Ordering::Relaxed) {
+        if SHUTDOWN.load(Ordering::Relaxed) || self.retired {
             return false;
         }
 
@@ -0,0 +1,88 @@
+//! Chain-of-custody annotations. With `--custody`, the relay records where each emitted frame
+//! came from and when, keyed by relay seq, and serves them from a side-channel endpoint so
+//! frames on the firehose stay byte-for-byte what subscribers expect. Comparing `receivedAt`
+//! with the event's own `time` and `emittedAt` measures latency upstream and through the relay.
+//!
+//! Frames the relay originates itself, like takedown `#account` events, aren't annotated.
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use fjall::compaction::{Fifo, Strategy};
+use fjall::{PartitionCreateOptions, PartitionHandle};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::checkpoint::Checkpoint;
+use crate::config::{CUSTODY_DISK_SIZE, TTL_SECONDS};
+use crate::types::{Cursor, DB};
+
+#[derive(Debug, Error)]
+pub enum CustodyError {
+    #[error("fjall error: {0}")]
+    Fjall(#[from] fjall::Error),
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Custody {
+    /// Relay seq of the annotated frame.
+    pub seq: u64,
+    /// Host the frame was crawled from.
+    pub host: String,
+    /// Seq the host gave the frame.
+    pub host_seq: u64,
+    /// When the crawler read the frame off the host's socket. Missing for frames that waited in
+    /// the queue for their identity to resolve.
+    pub received_at: Option<DateTime<Utc>>,
+    /// When the validator put the frame on the firehose.
+    pub emitted_at: DateTime<Utc>,
+}
+
+pub struct CustodyLog {
+    partition: PartitionHandle,
+}
+
+impl fmt::Debug for CustodyLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustodyLog").finish_non_exhaustive()
+    }
+}
+
+impl CustodyLog {
+    pub fn open() -> Result<Self, CustodyError> {
+        // expires alongside the firehose it annotates
+        let options = PartitionCreateOptions::default()
+            .compaction_strategy(Strategy::Fifo(Fifo::new(CUSTODY_DISK_SIZE, TTL_SECONDS)));
+        Ok(Self { partition: DB.open_partition("custody", options)? })
+    }
+
+    /// Stages the annotation for `seq` in the same checkpoint as its frame.
+    pub fn record(
+        &self, checkpoint: &mut Checkpoint, seq: Cursor, host: &str, host_seq: Cursor,
+        received_at: Option<DateTime<Utc>>,
+    ) -> Result<(), CustodyError> {
+        let custody = Custody {
+            seq: seq.get(),
+            host: host.to_owned(),
+            host_seq: host_seq.get(),
+            received_at,
+            emitted_at: Utc::now(),
+        };
+        checkpoint.insert(&self.partition, seq, serde_json::to_vec(&custody)?);
+        Ok(())
+    }
+
+    /// Up to `limit` annotations for frames after relay seq `after`, in seq order.
+    pub fn list(&self, after: Option<u64>, limit: usize) -> Result<Vec<Custody>, CustodyError> {
+        let start = Cursor::from(after.map_or(0, |after| after + 1));
+        let mut entries = Vec::new();
+        for res in self.partition.range(start..).take(limit) {
+            let (_, value) = res?;
+            entries.push(serde_json::from_slice(&value)?);
+        }
+        Ok(entries)
+    }
+}
@@ -0,0 +1,213 @@
+//! End-to-end harness for the relay pipeline.
+//!
+//! A [`MockPds`] signs commits with rsky-repo and frames them like `subscribeRepos`. The harness
+//! hands those frames to the validator exactly as a crawler connection would, and serves the
+//! resulting firehose through a real publisher over a loopback websocket. Crawler connections
+//! themselves are skipped since they only dial `wss://` hosts.
+
+// rsky-repo storage and the harness lock aren't `Send`, and the tests don't need them to be
+#![allow(clippy::future_not_send)]
+
+mod pds;
+mod tests;
+
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::Ordering;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use fjall::{PartitionCreateOptions, PartitionHandle};
+use hashbrown::HashSet;
+use rusqlite::Connection;
+use tungstenite::{Message, WebSocket};
+
+pub use pds::MockPds;
+
+use crate::admin::{AdminAction, AdminActionSender};
+use crate::custody::{Custody, CustodyLog};
+use crate::publisher::{MaybeTlsStream, SubscribeRepos, SubscribeReposSender};
+use crate::server::{Identity, SubscriberPermit};
+use crate::types::{Cursor, DB, MessageRecycle, MessageSender};
+use crate::validator::{Resolver, SubscribeReposEvent};
+use crate::{PublisherManager, SHUTDOWN, ValidatorManager};
+
+const CAPACITY: usize = 1 << 10;
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+// the pipeline shares the firehose keyspace and shutdown flag, so harnesses take turns
+static LOCK: Mutex<()> = Mutex::new(());
+
+pub struct Harness {
+    message_tx: MessageSender,
+    admin_tx: AdminActionSender,
+    subscribe_repos_tx: SubscribeReposSender,
+    validator: ValidatorManager,
+    publisher: Option<JoinHandle<()>>,
+    firehose: PartitionHandle,
+    published: HashSet<String>,
+    cursor: Cursor,
+    start: Cursor,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Harness {
+    pub fn new() -> Result<Self> {
+        Self::with_upstreams(&[])
+    }
+
+    /// A harness whose validator treats `upstreams` as relays rather than PDS hosts.
+    pub fn with_upstreams(upstreams: &[&str]) -> Result<Self> {
+        let lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        SHUTDOWN.store(false, Ordering::Relaxed);
+
+        let (message_tx, message_rx) =
+            thingbuf::mpsc::blocking::with_recycle(CAPACITY, MessageRecycle);
+        let (admin_tx, admin_rx) = rtrb::RingBuffer::new(CAPACITY);
+        let (subscribe_repos_tx, subscribe_repos_rx) = rtrb::RingBuffer::new(CAPACITY);
+        let validator = ValidatorManager::with_state(
+            message_rx,
+            admin_rx,
+            Connection::open_in_memory()?,
+            Resolver::in_memory()?,
+            true,
+            &upstreams.iter().copied().map(Into::into).collect::<Vec<_>>(),
+        )?;
+        let publisher = PublisherManager::new(1, subscribe_repos_rx)?;
+        let publisher = thread::spawn(move || {
+            if let Err(err) = publisher.run() {
+                tracing::warn!(%err, "publisher error");
+            }
+        });
+
+        let firehose = DB.open_partition("firehose", PartitionCreateOptions::default())?;
+        let cursor = firehose.last_key_value()?.map(|(k, _)| k.into()).unwrap_or_default();
+        Ok(Self {
+            message_tx,
+            admin_tx,
+            subscribe_repos_tx,
+            validator,
+            publisher: Some(publisher),
+            firehose,
+            published: HashSet::new(),
+            cursor,
+            start: cursor,
+            _lock: lock,
+        })
+    }
+
+    /// Relay seq of the `n`th event published by this harness, counting from 1.
+    pub const fn seq(&self, n: u64) -> u64 {
+        self.start.get() + n
+    }
+
+    /// Publish the pds's accounts to the plc directory, then feed it everything the pds emitted.
+    pub async fn crawl(&mut self, pds: &mut MockPds) -> Result<()> {
+        self.publish(pds)?;
+        let frames = pds.drain();
+        self.ingest(&pds.hostname, &frames).await
+    }
+
+    /// Publish the identities `pds` hosts, without crawling it.
+    pub fn publish(&mut self, pds: &MockPds) -> Result<()> {
+        let endpoint = format!("https://{}", pds.hostname);
+        for (did, key) in pds.identities() {
+            if self.published.insert(did.clone()) {
+                self.validator.resolver().insert(&did, &endpoint, &key)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Feed raw frames to the validator as if crawled from `hostname`, and wait for them all.
+    pub async fn ingest(&mut self, hostname: &str, frames: &[Vec<u8>]) -> Result<()> {
+        for frame in frames {
+            let mut slot = self.message_tx.send_ref()?;
+            slot.data = frame.clone().into();
+            hostname.clone_into(&mut slot.hostname);
+            slot.received_at = Utc::now();
+        }
+        self.settle().await
+    }
+
+    pub async fn admin(&mut self, action: AdminAction) -> Result<()> {
+        self.admin_tx.push(action).map_err(|err| anyhow!("admin queue full: {err}"))?;
+        self.settle().await
+    }
+
+    async fn settle(&mut self) -> Result<()> {
+        loop {
+            if !self.validator.update(&mut self.cursor).await? {
+                return Err(anyhow!("validator shut down"));
+            }
+            if self.message_tx.remaining() == CAPACITY && self.admin_tx.slots() == CAPACITY {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Everything this harness has put on the firehose, in relay seq order.
+    pub fn events(&self) -> Result<Vec<SubscribeReposEvent>> {
+        let mut events = Vec::new();
+        for res in self.firehose.range((self.start + 1)..) {
+            let (_, data) = res?;
+            if let Some(event) = SubscribeReposEvent::parse(&data)? {
+                events.push(event);
+            }
+        }
+        Ok(events)
+    }
+
+    /// Custody annotations for everything this harness has put on the firehose.
+    pub fn custody(&self) -> Result<Vec<Custody>> {
+        Ok(CustodyLog::open()?.list(Some(self.start.get()), usize::MAX)?)
+    }
+
+    /// Connect a `subscribeRepos` client to the publisher.
+    pub fn subscribe(&mut self, cursor: Option<u64>) -> Result<Subscriber> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let stream = TcpStream::connect(listener.local_addr()?)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let (server, addr) = listener.accept()?;
+        self.subscribe_repos_tx
+            .push(SubscribeRepos {
+                addr,
+                stream: MaybeTlsStream::Plain(server),
+                cursor: cursor.map(Into::into),
+                permit: SubscriberPermit::acquire(Identity::Anonymous(addr.ip()), None)?,
+            })
+            .map_err(|err| anyhow!("subscriber queue full: {err}"))?;
+        let (client, _) =
+            tungstenite::client("ws://localhost/xrpc/com.atproto.sync.subscribeRepos", stream)
+                .map_err(|err| anyhow!("handshake failed: {err}"))?;
+        Ok(Subscriber { client })
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        SHUTDOWN.store(true, Ordering::Relaxed);
+        if let Some(publisher) = self.publisher.take() {
+            drop(publisher.join());
+        }
+    }
+}
+
+pub struct Subscriber {
+    client: WebSocket<TcpStream>,
+}
+
+impl Subscriber {
+    /// Next event off the wire, skipping `#info` frames.
+    pub fn recv(&mut self) -> Result<SubscribeReposEvent> {
+        loop {
+            if let Message::Binary(data) = self.client.read()? {
+                if let Some(event) = SubscribeReposEvent::parse(&data)? {
+                    return Ok(event);
+                }
+            }
+        }
+    }
+}
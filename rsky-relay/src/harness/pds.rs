@@ -0,0 +1,234 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use cid::Cid;
+use rsky_common::tid::{TID, s32encode};
+use rsky_crypto::utils::encode_did_key;
+use rsky_repo::block_map::BlockMap;
+use rsky_repo::car::blocks_to_car_file;
+use rsky_repo::repo::Repo;
+use rsky_repo::storage::memory_blockstore::MemoryBlockstore;
+use rsky_repo::types::{
+    CommitData, RecordCreateOrUpdateOp, RecordDeleteOp, RecordWriteEnum, RecordWriteOp,
+    WriteOpAction,
+};
+use secp256k1::{Keypair, Secp256k1, SecretKey};
+use tokio::sync::RwLock;
+
+use crate::validator::{
+    SubscribeReposAccount, SubscribeReposCommit, SubscribeReposCommitOperation,
+    SubscribeReposEvent, SubscribeReposIdentity,
+};
+
+struct Account {
+    did: String,
+    keypair: Keypair,
+    repo: Repo,
+}
+
+/// An in-process PDS. Every write signs a real commit with rsky-repo and queues the
+/// `subscribeRepos` frame a PDS would emit for it, numbered with the PDS's own seq.
+pub struct MockPds {
+    pub hostname: String,
+    accounts: Vec<Account>,
+    seq: u64,
+    rkeys: usize,
+    keys: u8,
+    outbox: Vec<Vec<u8>>,
+}
+
+impl MockPds {
+    pub fn new(hostname: &str) -> Self {
+        Self {
+            hostname: hostname.to_owned(),
+            accounts: Vec::new(),
+            seq: 0,
+            rkeys: 0,
+            keys: 0,
+            outbox: Vec::new(),
+        }
+    }
+
+    /// `(did, did:key)` for every account, as their plc documents would publish them.
+    pub fn identities(&self) -> Vec<(String, String)> {
+        self.accounts
+            .iter()
+            .map(|account| (account.did.clone(), encode_did_key(&account.keypair.public_key())))
+            .collect()
+    }
+
+    /// Take every frame emitted since the last call.
+    pub fn drain(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.outbox)
+    }
+
+    /// Create an account with an empty repo, emitting `#identity`, `#account` and the genesis
+    /// `#commit` like a PDS does on signup.
+    pub async fn create_account(&mut self, name: &str) -> Result<String> {
+        let did = format!("did:plc:{name:a>24}");
+        let keypair = self.next_keypair()?;
+        let storage = Arc::new(RwLock::new(MemoryBlockstore::default()));
+        let genesis = Repo::create_from_records(storage, did.clone(), keypair, Vec::new()).await?;
+
+        let (seq, time) = (self.next_seq(), Utc::now());
+        self.push(SubscribeReposEvent::Identity(SubscribeReposIdentity {
+            seq,
+            did: did.clone(),
+            time,
+            handle: Some(format!("{name}.{}", self.hostname)),
+        }))?;
+        let (seq, time) = (self.next_seq(), Utc::now());
+        self.push(SubscribeReposEvent::Account(SubscribeReposAccount {
+            seq,
+            did: did.clone(),
+            time,
+            active: true,
+            status: None,
+        }))?;
+        let commit = self.commit_event(&did, &genesis.commit, None, genesis.car, Vec::new())?;
+        self.push(SubscribeReposEvent::Commit(commit))?;
+
+        self.accounts.push(Account { did: did.clone(), keypair, repo: genesis.repo });
+        Ok(did)
+    }
+
+    /// Sign future commits for `did` with a fresh key, without publishing it.
+    pub fn rotate_key(&mut self, did: &str) -> Result<()> {
+        let keypair = self.next_keypair()?;
+        self.account(did)?.keypair = keypair;
+        Ok(())
+    }
+
+    /// Create a record and return its rkey.
+    pub async fn create_record(
+        &mut self, did: &str, collection: &str, record: serde_json::Value,
+    ) -> Result<String> {
+        self.rkeys += 1;
+        let rkey = format!("{:2>13}", s32encode(self.rkeys));
+        let record = serde_json::from_value(record)?;
+        self.write(
+            did,
+            RecordWriteOp::Create(RecordCreateOrUpdateOp {
+                action: WriteOpAction::Create,
+                collection: collection.to_owned(),
+                rkey: rkey.clone(),
+                record,
+            }),
+        )
+        .await?;
+        Ok(rkey)
+    }
+
+    pub async fn update_record(
+        &mut self, did: &str, collection: &str, rkey: &str, record: serde_json::Value,
+    ) -> Result<()> {
+        let record = serde_json::from_value(record)?;
+        self.write(
+            did,
+            RecordWriteOp::Update(RecordCreateOrUpdateOp {
+                action: WriteOpAction::Update,
+                collection: collection.to_owned(),
+                rkey: rkey.to_owned(),
+                record,
+            }),
+        )
+        .await
+    }
+
+    pub async fn delete_record(&mut self, did: &str, collection: &str, rkey: &str) -> Result<()> {
+        self.write(
+            did,
+            RecordWriteOp::Delete(RecordDeleteOp {
+                action: WriteOpAction::Delete,
+                collection: collection.to_owned(),
+                rkey: rkey.to_owned(),
+            }),
+        )
+        .await
+    }
+
+    async fn write(&mut self, did: &str, write: RecordWriteOp) -> Result<()> {
+        let path = format!("{}/{}", write.collection(), write.rkey());
+        let account = self.account(did)?;
+        let prev_data = to_cid(account.repo.commit.data)?;
+        let prev = account.repo.data.get(&path).await?;
+        let keypair = account.keypair;
+        let commit =
+            account.repo.format_commit(RecordWriteEnum::Single(write.clone()), keypair).await?;
+        account.repo = account.repo.apply_commit(commit.clone()).await?;
+        let cid = account.repo.data.get(&path).await?;
+
+        let op = match (write, cid) {
+            (RecordWriteOp::Create(_), Some(cid)) => {
+                SubscribeReposCommitOperation::Create { path, cid: to_cid(cid)? }
+            }
+            (RecordWriteOp::Update(_), Some(cid)) => SubscribeReposCommitOperation::Update {
+                path,
+                cid: to_cid(cid)?,
+                prev_data: prev.map(to_cid).transpose()?,
+            },
+            (RecordWriteOp::Delete(_), None) => SubscribeReposCommitOperation::Delete {
+                path,
+                prev_data: prev.map(to_cid).transpose()?,
+            },
+            _ => return Err(anyhow!("write to {path} was not applied")),
+        };
+
+        let mut blocks = BlockMap::new();
+        blocks.add_map(commit.new_blocks.clone())?;
+        blocks.add_map(commit.relevant_blocks.clone())?;
+        let car = blocks_to_car_file(Some(&commit.cid), blocks).await?;
+        let event = self.commit_event(did, &commit, Some(prev_data), car, vec![op])?;
+        self.push(SubscribeReposEvent::Commit(event))
+    }
+
+    fn commit_event(
+        &mut self, did: &str, commit: &CommitData, prev_data: Option<Cid>, blocks: Vec<u8>,
+        ops: Vec<SubscribeReposCommitOperation>,
+    ) -> Result<SubscribeReposCommit> {
+        Ok(SubscribeReposCommit {
+            seq: self.next_seq(),
+            rebase: false,
+            too_big: false,
+            did: did.to_owned(),
+            commit: to_cid(commit.cid)?,
+            rev: TID(commit.rev.clone()),
+            since: commit.since.clone().map(TID),
+            blocks,
+            ops,
+            blobs: Vec::new(),
+            prev_data,
+            time: Utc::now(),
+        })
+    }
+
+    fn push(&mut self, event: SubscribeReposEvent) -> Result<()> {
+        let seq = event.seq();
+        self.outbox.push(event.serialize(0, seq)?);
+        Ok(())
+    }
+
+    fn account(&mut self, did: &str) -> Result<&mut Account> {
+        self.accounts
+            .iter_mut()
+            .find(|account| account.did == did)
+            .ok_or_else(|| anyhow!("unknown account {did}"))
+    }
+
+    const fn next_seq(&mut self) -> u64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    // keys are derived from a counter so runs are reproducible
+    fn next_keypair(&mut self) -> Result<Keypair> {
+        self.keys += 1;
+        let secret = SecretKey::from_slice(&[self.keys; 32])?;
+        Ok(Keypair::from_secret_key(&Secp256k1::new(), &secret))
+    }
+}
+
+fn to_cid(cid: lexicon_cid::Cid) -> Result<Cid> {
+    Ok(Cid::try_from(cid.to_bytes().as_slice())?)
+}
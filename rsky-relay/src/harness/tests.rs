@@ -0,0 +1,214 @@
+use std::sync::atomic::Ordering;
+
+use anyhow::Result;
+use serde_json::json;
+
+use crate::PUBLISHER_WORKERS;
+use crate::admin::{AdminAction, Subject};
+use crate::checkpoint::Checkpoint;
+use crate::harness::{Harness, MockPds};
+use crate::types::Cursor;
+use crate::validator::SubscribeReposEvent;
+
+const POST: &str = "app.bsky.feed.post";
+
+fn post(text: &str) -> serde_json::Value {
+    json!({ "$type": POST, "text": text, "createdAt": "2025-01-01T00:00:00.000Z" })
+}
+
+fn summary(events: &[SubscribeReposEvent]) -> Vec<(u64, &'static str, String)> {
+    events.iter().map(|event| (event.seq().get(), event.type_(), event.did().to_owned())).collect()
+}
+
+#[tokio::test]
+async fn sequences_events_in_crawl_order() -> Result<()> {
+    let mut harness = Harness::new()?;
+    let mut pds = MockPds::new("pds.test");
+    let alice = pds.create_account("alice").await?;
+    let bob = pds.create_account("bob").await?;
+    let rkey = pds.create_record(&alice, POST, post("hello")).await?;
+    pds.update_record(&alice, POST, &rkey, post("hello again")).await?;
+    pds.create_record(&bob, POST, post("hi")).await?;
+    pds.delete_record(&alice, POST, &rkey).await?;
+    harness.crawl(&mut pds).await?;
+
+    let events = harness.events()?;
+    assert_eq!(summary(&events), vec![
+        (harness.seq(1), "#identity", alice.clone()),
+        (harness.seq(2), "#account", alice.clone()),
+        (harness.seq(3), "#commit", alice.clone()),
+        (harness.seq(4), "#identity", bob.clone()),
+        (harness.seq(5), "#account", bob.clone()),
+        (harness.seq(6), "#commit", bob.clone()),
+        (harness.seq(7), "#commit", alice.clone()),
+        (harness.seq(8), "#commit", alice.clone()),
+        (harness.seq(9), "#commit", bob),
+        (harness.seq(10), "#commit", alice),
+    ]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn drops_replayed_host_seqs() -> Result<()> {
+    let mut harness = Harness::new()?;
+    let mut pds = MockPds::new("pds.test");
+    let alice = pds.create_account("alice").await?;
+    harness.crawl(&mut pds).await?;
+    pds.create_record(&alice, POST, post("one")).await?;
+    let frames = pds.drain();
+
+    // a reconnecting crawler may be handed the same frames twice
+    harness.ingest("pds.test", &frames).await?;
+    harness.ingest("pds.test", &frames).await?;
+    assert_eq!(harness.events()?.len(), 4);
+    Ok(())
+}
+
+#[tokio::test]
+async fn rejects_commits_with_unpublished_key() -> Result<()> {
+    let mut harness = Harness::new()?;
+    let mut pds = MockPds::new("pds.test");
+    let alice = pds.create_account("alice").await?;
+    harness.crawl(&mut pds).await?;
+
+    pds.rotate_key(&alice)?;
+    pds.create_record(&alice, POST, post("forged")).await?;
+    harness.crawl(&mut pds).await?;
+    assert_eq!(harness.events()?.len(), 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn holds_commits_from_the_wrong_host() -> Result<()> {
+    let mut harness = Harness::new()?;
+    let mut pds = MockPds::new("pds.test");
+    let alice = pds.create_account("alice").await?;
+    harness.crawl(&mut pds).await?;
+
+    pds.create_record(&alice, POST, post("elsewhere")).await?;
+    let frames = pds.drain();
+    harness.ingest("other.test", &frames).await?;
+    assert_eq!(harness.events()?.len(), 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn takedown_stops_account_events() -> Result<()> {
+    let mut harness = Harness::new()?;
+    let mut pds = MockPds::new("pds.test");
+    let alice = pds.create_account("alice").await?;
+    harness.crawl(&mut pds).await?;
+
+    harness.admin(AdminAction::Takedown(Subject::Did(alice.clone()))).await?;
+    pds.create_record(&alice, POST, post("hidden")).await?;
+    harness.crawl(&mut pds).await?;
+
+    let events = harness.events()?;
+    assert_eq!(summary(&events[3..]), vec![(harness.seq(4), "#account", alice)]);
+    let SubscribeReposEvent::Account(account) = &events[3] else { unreachable!() };
+    assert!(!account.active);
+    Ok(())
+}
+
+#[tokio::test]
+async fn publishes_from_cursor_then_live() -> Result<()> {
+    let mut harness = Harness::new()?;
+    let mut pds = MockPds::new("pds.test");
+    let alice = pds.create_account("alice").await?;
+    pds.create_record(&alice, POST, post("one")).await?;
+    harness.crawl(&mut pds).await?;
+
+    let mut backfill = harness.subscribe(Some(harness.seq(3)))?;
+    assert_eq!(backfill.recv()?.seq().get(), harness.seq(3));
+    assert_eq!(backfill.recv()?.seq().get(), harness.seq(4));
+
+    let mut live = harness.subscribe(None)?;
+    pds.create_record(&alice, POST, post("two")).await?;
+    harness.crawl(&mut pds).await?;
+    for subscriber in [&mut backfill, &mut live] {
+        let event = subscriber.recv()?;
+        assert_eq!((event.seq().get(), event.type_()), (harness.seq(5), "#commit"));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn keeps_subscribers_across_publisher_resizes() -> Result<()> {
+    let mut harness = Harness::new()?;
+    let mut pds = MockPds::new("pds.test");
+    let alice = pds.create_account("alice").await?;
+    harness.crawl(&mut pds).await?;
+    let mut live = harness.subscribe(None)?;
+
+    // growing hands the only subscriber to the new worker, shrinking hands it back
+    for (workers, n) in [(2, 4), (1, 5)] {
+        PUBLISHER_WORKERS.store(workers, Ordering::Relaxed);
+        pds.create_record(&alice, POST, post("hello")).await?;
+        harness.crawl(&mut pds).await?;
+        let event = live.recv()?;
+        assert_eq!((event.seq().get(), event.type_()), (harness.seq(n), "#commit"));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn annotates_frames_with_custody() -> Result<()> {
+    let mut harness = Harness::new()?;
+    let mut pds = MockPds::new("pds.test");
+    let alice = pds.create_account("alice").await?;
+    pds.create_record(&alice, POST, post("one")).await?;
+    harness.crawl(&mut pds).await?;
+    harness.admin(AdminAction::Takedown(Subject::Did(alice))).await?;
+
+    // the takedown #account is the relay's own and carries no annotation
+    let custody = harness.custody()?;
+    let seqs: Vec<_> = custody.iter().map(|entry| (entry.seq, entry.host_seq)).collect();
+    assert_eq!(seqs, vec![
+        (harness.seq(1), 1),
+        (harness.seq(2), 2),
+        (harness.seq(3), 3),
+        (harness.seq(4), 4),
+    ]);
+    for entry in &custody {
+        assert_eq!(entry.host, "pds.test");
+        assert!(entry.received_at.is_some_and(|received_at| received_at <= entry.emitted_at));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn dedupes_events_across_upstream_relays() -> Result<()> {
+    let mut harness = Harness::with_upstreams(&["relay-a.test", "relay-b.test"])?;
+    let mut pds = MockPds::new("pds.test");
+    let alice = pds.create_account("alice").await?;
+    pds.create_record(&alice, POST, post("one")).await?;
+    harness.publish(&pds)?;
+    let frames = pds.drain();
+
+    // both upstreams relay the same frames from one pds
+    harness.ingest("relay-a.test", &frames).await?;
+    harness.ingest("relay-b.test", &frames).await?;
+    let events = harness.events()?;
+    assert_eq!(summary(&events), vec![
+        (harness.seq(1), "#identity", alice.clone()),
+        (harness.seq(2), "#account", alice.clone()),
+        (harness.seq(3), "#commit", alice.clone()),
+        (harness.seq(4), "#commit", alice),
+    ]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn checkpoints_host_cursors_with_frames() -> Result<()> {
+    let mut harness = Harness::new()?;
+    let mut pds = MockPds::new("checkpoint.test");
+    let alice = pds.create_account("alice").await?;
+    pds.create_record(&alice, POST, post("one")).await?;
+    harness.crawl(&mut pds).await?;
+
+    // the crawler resumes from the checkpoint, committed in the same batch as the frames
+    let checkpoint = Checkpoint::open()?;
+    assert_eq!(harness.events()?.len(), 4);
+    assert_eq!(checkpoint.host_cursor("checkpoint.test")?.map(Cursor::get), Some(4));
+    Ok(())
+}
@@ -35,25 +35,37 @@
     clippy::multiple_crate_versions
 )]
 
+mod admin;
+mod checkpoint;
 mod crawler;
+mod custody;
+#[cfg(all(test, not(feature = "labeler")))]
+mod harness;
 mod publisher;
 mod server;
 mod types;
 mod validator;
+mod watchdog;
 
 pub mod config;
 
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
 
 use thiserror::Error;
 
+use crate::config::{WORKERS_CRAWLERS, WORKERS_PUBLISHERS};
+
 pub static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+/// Worker pool sizes the crawler and publisher managers converge on, changed via the admin API.
+pub static CRAWLER_WORKERS: AtomicUsize = AtomicUsize::new(WORKERS_CRAWLERS);
+pub static PUBLISHER_WORKERS: AtomicUsize = AtomicUsize::new(WORKERS_PUBLISHERS);
 
 pub use crawler::Manager as CrawlerManager;
 pub use publisher::Manager as PublisherManager;
 pub use server::Server;
 pub use types::MessageRecycle;
 pub use validator::Manager as ValidatorManager;
+pub use watchdog::Watchdog;
 
 #[derive(Debug, Error)]
 pub enum RelayError {
@@ -65,4 +77,6 @@ pub enum RelayError {
     Validator(#[from] validator::ManagerError),
     #[error("server error: {0}")]
     Server(#[from] server::ServerError),
+    #[error("watchdog error: {0}")]
+    Watchdog(#[from] watchdog::WatchdogError),
 }
@@ -23,7 +23,7 @@ use tracing_subscriber::util::SubscriberInitExt;
 use rsky_relay::config::{CAPACITY_MSGS, CAPACITY_REQS, WORKERS_CRAWLERS, WORKERS_PUBLISHERS};
 use rsky_relay::{
     CrawlerManager, MessageRecycle, PublisherManager, RelayError, SHUTDOWN, Server,
-    ValidatorManager,
+    ValidatorManager, Watchdog,
 };
 
 #[global_allocator]
@@ -40,6 +40,9 @@ pub struct Args {
     #[cfg(not(feature = "labeler"))]
     #[clap(long)]
     no_plc_export: bool,
+    /// Record where and when each frame was received, served from `/custody`.
+    #[clap(long)]
+    custody: bool,
 }
 
 #[tokio::main]
@@ -73,12 +76,18 @@ pub async fn main() -> Result<()> {
         thingbuf::mpsc::blocking::with_recycle(CAPACITY_MSGS, MessageRecycle);
     let (request_crawl_tx, request_crawl_rx) = rtrb::RingBuffer::new(CAPACITY_REQS);
     let (subscribe_repos_tx, subscribe_repos_rx) = rtrb::RingBuffer::new(CAPACITY_REQS);
-    let server =
-        Server::new(args.certs.zip(args.private_key), request_crawl_tx, subscribe_repos_tx)?;
-    let validator = ValidatorManager::new(message_rx)?;
+    let (admin_tx, admin_rx) = rtrb::RingBuffer::new(CAPACITY_REQS);
+    let server = Server::new(
+        args.certs.zip(args.private_key),
+        request_crawl_tx,
+        subscribe_repos_tx,
+        admin_tx,
+    )?;
+    let validator = ValidatorManager::new(message_rx, admin_rx)?;
     let handle = tokio::spawn(validator.run());
     let crawler = CrawlerManager::new(WORKERS_CRAWLERS, &message_tx, request_crawl_rx)?;
     let publisher = PublisherManager::new(WORKERS_PUBLISHERS, subscribe_repos_rx)?;
+    let watchdog = Watchdog::new()?;
     #[expect(clippy::vec_init_then_push)]
     let ret = thread::scope(move |s| {
         let mut handles = Vec::<ScopedJoinHandle<'_, Result<_, RelayError>>>::new();
@@ -97,6 +106,11 @@ pub async fn main() -> Result<()> {
                 .name("rsky-server".into())
                 .spawn_scoped(s, move || server.run().map_err(Into::into))?,
         );
+        handles.push(
+            thread::Builder::new()
+                .name("rsky-disk".into())
+                .spawn_scoped(s, move || watchdog.run().map_err(Into::into))?,
+        );
         #[expect(clippy::expect_used)]
         let mut signals =
             SignalsInfo::<WithOrigin>::new(TERM_SIGNALS).expect("failed to init signals");
@@ -1,6 +1,6 @@
-use std::io;
 use std::net::{SocketAddr, TcpStream};
 use std::os::fd::{AsRawFd, RawFd};
+use std::{fmt, io};
 
 use fjall::PartitionHandle;
 use thiserror::Error;
@@ -10,6 +10,7 @@ use tungstenite::protocol::frame::coding::CloseCode;
 use tungstenite::{Bytes, HandshakeError, Message, ServerHandshake, Utf8Bytes, WebSocket};
 
 use crate::publisher::types::MaybeTlsStream;
+use crate::server::SubscriberPermit;
 use crate::types::Cursor;
 
 const OUTDATED_MSG: &[u8] = b"\xa2ate#infobop\x01\xa2dnamenOutdatedCursorgmessagex8Requested cursor exceeded limit. Possibly missing events.";
@@ -35,6 +36,17 @@ pub struct Connection {
     pub(crate) addr: SocketAddr,
     client: WebSocket<MaybeTlsStream<TcpStream>>,
     pub(crate) cursor: Cursor,
+    // released when the connection closes
+    _permit: SubscriberPermit,
+}
+
+impl fmt::Debug for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Connection")
+            .field("addr", &self.addr)
+            .field("cursor", &self.cursor)
+            .finish_non_exhaustive()
+    }
 }
 
 impl AsRawFd for Connection {
@@ -50,6 +62,7 @@ impl AsRawFd for Connection {
 impl Connection {
     pub fn connect(
         addr: SocketAddr, stream: MaybeTlsStream<TcpStream>, cursor: Cursor,
+        permit: SubscriberPermit,
     ) -> Result<Self, ConnectionError> {
         let client = tungstenite::accept(stream)?;
         match client.get_ref() {
@@ -60,7 +73,7 @@ impl Connection {
                 stream.set_nonblocking(true)?;
             }
         }
-        Ok(Self { addr, client, cursor })
+        Ok(Self { addr, client, cursor, _permit: permit })
     }
 
     pub fn close(&mut self, code: CloseFrame) -> Result<(), ConnectionError> {
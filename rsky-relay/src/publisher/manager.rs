@@ -2,12 +2,17 @@ use std::sync::atomic::Ordering;
 use std::time::Duration;
 use std::{io, thread};
 
+use magnetic::Consumer;
+use magnetic::buffer::dynamic::DynamicBufferP2;
 use thiserror::Error;
 
-use crate::SHUTDOWN;
-use crate::config::CAPACITY_STATUS;
-use crate::publisher::types::{Command, CommandSender, SubscribeReposReceiver};
+use crate::config::{CAPACITY_STATUS, WORKERS_MAX};
+use crate::publisher::sink::{self, SinkError, SinkRunner};
+use crate::publisher::types::{
+    Command, CommandSender, ReleasedReceiver, ReleasedSender, SubscribeReposReceiver,
+};
 use crate::publisher::worker::{Worker, WorkerError};
+use crate::{PUBLISHER_WORKERS, SHUTDOWN};
 
 const SLEEP: Duration = Duration::from_millis(10);
 
@@ -17,6 +22,8 @@ pub enum ManagerError {
     Spawn(#[from] io::Error),
     #[error("worker error: {0}")]
     Worker(#[from] WorkerError),
+    #[error("sink error: {0}")]
+    Sink(#[from] SinkError),
     #[error("rtrb error: {0}")]
     Push(#[from] Box<rtrb::PushError<Command>>),
     #[error("join error")]
@@ -36,25 +43,51 @@ struct WorkerHandle {
 }
 
 pub struct Manager {
-    workers: Box<[WorkerHandle]>,
+    workers: Vec<WorkerHandle>,
+    /// Workers told to retire, kept until their threads exit.
+    retiring: Vec<WorkerHandle>,
+    sinks: Vec<thread::JoinHandle<Result<(), SinkError>>>,
     next_id: usize,
+    /// Released subscribers go to workers from `adopt_from` on, so after growing the pool they
+    /// land on the new workers.
+    adopt_from: usize,
+    next_adopt: usize,
     subscribe_repos_rx: SubscribeReposReceiver,
+    released_tx: ReleasedSender,
+    released_rx: ReleasedReceiver,
 }
 
 impl Manager {
     pub fn new(
         n_workers: usize, subscribe_repos_rx: SubscribeReposReceiver,
     ) -> Result<Self, ManagerError> {
+        #[expect(clippy::unwrap_used)]
+        let (released_tx, released_rx) =
+            magnetic::mpsc::mpsc_queue(DynamicBufferP2::new(CAPACITY_STATUS).unwrap());
         let workers = (0..n_workers)
-            .map(|worker_id| -> Result<_, ManagerError> {
-                let (command_tx, command_rx) = rtrb::RingBuffer::new(CAPACITY_STATUS);
-                let thread_handle = thread::Builder::new()
-                    .name(format!("rsky-pub-{worker_id}"))
-                    .spawn(move || Worker::new(worker_id, command_rx)?.run())?;
-                Ok(WorkerHandle { command_tx, thread_handle })
+            .map(|worker_id| spawn_worker(worker_id, &released_tx))
+            .collect::<Result<Vec<_>, _>>()?;
+        PUBLISHER_WORKERS.store(n_workers, Ordering::Relaxed);
+        let sinks = sink::from_env()
+            .into_iter()
+            .map(|sink| -> Result<_, ManagerError> {
+                let runner = SinkRunner::new(sink)?;
+                Ok(thread::Builder::new()
+                    .name(format!("rsky-sink-{}", runner.name()))
+                    .spawn(move || runner.run())?)
             })
             .collect::<Result<Vec<_>, _>>()?;
-        Ok(Self { workers: workers.into_boxed_slice(), next_id: 0, subscribe_repos_rx })
+        Ok(Self {
+            workers,
+            retiring: Vec::new(),
+            sinks,
+            next_id: 0,
+            adopt_from: 0,
+            next_adopt: 0,
+            subscribe_repos_rx,
+            released_tx,
+            released_rx,
+        })
     }
 
     pub fn run(mut self) -> Result<(), ManagerError> {
@@ -67,11 +100,16 @@ impl Manager {
 
     pub fn shutdown(self) -> Result<(), ManagerError> {
         SHUTDOWN.store(true, Ordering::Relaxed);
-        for (id, worker) in self.workers.into_iter().enumerate() {
+        for (id, worker) in self.workers.into_iter().chain(self.retiring).enumerate() {
             if let Err(err) = worker.thread_handle.join().map_err(|_| ManagerError::Join)? {
                 tracing::warn!(%id, %err, "publisher worker error");
             }
         }
+        for sink in self.sinks {
+            if let Err(err) = sink.join().map_err(|_| ManagerError::Join)? {
+                tracing::warn!(%err, "publisher sink error");
+            }
+        }
         Ok(())
     }
 
@@ -80,6 +118,15 @@ impl Manager {
             return Ok(false);
         }
 
+        self.resize()?;
+
+        while let Ok(conn) = self.released_rx.try_pop() {
+            let adopters = self.workers.len() - self.adopt_from;
+            let id = self.adopt_from + self.next_adopt % adopters;
+            self.next_adopt = self.next_adopt.wrapping_add(1);
+            self.workers[id].command_tx.push(Command::Adopt(conn))?;
+        }
+
         if let Ok(subscribe_repos) = self.subscribe_repos_rx.pop() {
             self.workers[self.next_id].command_tx.push(Command::Connect(subscribe_repos))?;
             self.next_id = (self.next_id + 1) % self.workers.len();
@@ -87,4 +134,50 @@ impl Manager {
 
         Ok(true)
     }
+
+    /// Grows or shrinks the pool towards [`PUBLISHER_WORKERS`], and reaps retired workers.
+    fn resize(&mut self) -> Result<(), ManagerError> {
+        let target = PUBLISHER_WORKERS.load(Ordering::Relaxed).clamp(1, WORKERS_MAX);
+        let from = self.workers.len();
+        if target > from {
+            tracing::info!(%from, to = %target, "growing publisher pool");
+            for worker in &mut self.workers {
+                worker.command_tx.push(Command::Rebalance { from, to: target })?;
+            }
+            for worker_id in from..target {
+                self.workers.push(spawn_worker(worker_id, &self.released_tx)?);
+            }
+            self.adopt_from = from;
+        } else if target < from {
+            tracing::info!(%from, to = %target, "shrinking publisher pool");
+            for mut worker in self.workers.drain(target..) {
+                worker.command_tx.push(Command::Retire)?;
+                self.retiring.push(worker);
+            }
+            self.adopt_from = 0;
+            self.next_id %= target;
+        }
+        let (finished, retiring) = self
+            .retiring
+            .drain(..)
+            .partition::<Vec<_>, _>(|worker| worker.thread_handle.is_finished());
+        self.retiring = retiring;
+        for worker in finished {
+            if let Err(err) = worker.thread_handle.join().map_err(|_| ManagerError::Join)? {
+                tracing::warn!(%err, "retired publisher worker error");
+            }
+        }
+        Ok(())
+    }
+}
+
+fn spawn_worker(
+    worker_id: usize, released_tx: &ReleasedSender,
+) -> Result<WorkerHandle, ManagerError> {
+    let released_tx = released_tx.clone();
+    let (command_tx, command_rx) = rtrb::RingBuffer::new(CAPACITY_STATUS);
+    let thread_handle = thread::Builder::new()
+        .name(format!("rsky-pub-{worker_id}"))
+        .spawn(move || Worker::new(worker_id, command_rx, released_tx)?.run())?;
+    Ok(WorkerHandle { command_tx, thread_handle })
 }
@@ -1,5 +1,6 @@
 mod connection;
 mod manager;
+mod sink;
 mod types;
 mod worker;
 
@@ -0,0 +1,444 @@
+//! Mirrors the firehose into external streaming systems, next to the websocket fanout. Each
+//! configured [`Sink`] gets its own thread that tails the firehose partition and publishes every
+//! event keyed by the account's DID, so per-account ordering survives partitioning downstream.
+//!
+//! A sink only moves its cursor forward once a batch has been accepted, and the cursor is saved
+//! so a restarted relay picks up where each sink left off. When the destination goes away the
+//! thread reconnects and resumes from the first unacknowledged event, as long as it's still on
+//! disk; a slow sink never holds up websocket subscribers or the other sinks.
+
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::TcpStream;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use fjall::{PartitionCreateOptions, PartitionHandle};
+use serde::Serialize;
+use thiserror::Error;
+use url::Url;
+
+use crate::SHUTDOWN;
+use crate::config::{
+    KAFKA_BROKERS, KAFKA_TOPIC, NATS_SUBJECT, NATS_URL, SINK_BATCH, SINK_RECONNECT_INTERVAL,
+};
+use crate::types::{Cursor, DB};
+use crate::validator::SubscribeReposEvent;
+
+const SLEEP: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Error)]
+pub enum SinkError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("fjall error: {0}")]
+    Fjall(#[from] fjall::Error),
+    #[error("invalid url: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("protocol error: {0}")]
+    Protocol(String),
+    #[cfg(feature = "kafka")]
+    #[error("kafka error: {0}")]
+    Kafka(#[from] kafka::Error),
+}
+
+/// A destination the firehose is mirrored into.
+pub trait Sink: Send {
+    /// Name used for the thread and in logs.
+    fn name(&self) -> &'static str;
+
+    /// (Re)establishes the connection. Called before the first event and after any error.
+    fn connect(&mut self) -> Result<(), SinkError>;
+
+    /// Publishes `frame`, the event exactly as websocket subscribers get it, keyed by `did`.
+    /// May buffer until [`Sink::flush`].
+    fn send(&mut self, did: &str, seq: Cursor, frame: &[u8]) -> Result<(), SinkError>;
+
+    /// Returns once everything sent so far has been handed off to the destination.
+    fn flush(&mut self) -> Result<(), SinkError>;
+}
+
+/// The sinks configured by the environment.
+pub fn from_env() -> Vec<Box<dyn Sink>> {
+    let mut sinks = Vec::<Box<dyn Sink>>::new();
+    if let Some(url) = NATS_URL.as_ref() {
+        sinks.push(Box::new(NatsSink::new(url.clone(), NATS_SUBJECT.clone())));
+    }
+    if !KAFKA_BROKERS.is_empty() {
+        #[cfg(feature = "kafka")]
+        sinks.push(Box::new(KafkaSink::new(KAFKA_BROKERS.clone(), KAFKA_TOPIC.clone())));
+        #[cfg(not(feature = "kafka"))]
+        tracing::warn!(topic = %*KAFKA_TOPIC, "built without the kafka feature, not producing");
+    }
+    sinks
+}
+
+pub struct SinkRunner {
+    sink: Box<dyn Sink>,
+    firehose: PartitionHandle,
+    // last acknowledged seq of each sink, keyed by name
+    cursors: PartitionHandle,
+    connected: bool,
+}
+
+impl SinkRunner {
+    pub fn new(sink: Box<dyn Sink>) -> Result<Self, SinkError> {
+        let firehose = DB.open_partition("firehose", PartitionCreateOptions::default())?;
+        let cursors = DB.open_partition("sinks", PartitionCreateOptions::default())?;
+        Ok(Self { sink, firehose, cursors, connected: false })
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.sink.name()
+    }
+
+    pub fn run(mut self) -> Result<(), SinkError> {
+        let span = tracing::info_span!("sink", name = %self.name());
+        let _enter = span.enter();
+        let mut seq = self.resume()?;
+        tracing::info!(%seq, "starting sink");
+        while !SHUTDOWN.load(Ordering::Relaxed) {
+            match self.update(seq) {
+                Ok(Some(next)) => {
+                    seq = next;
+                    if let Err(err) = self.cursors.insert(self.name(), seq) {
+                        tracing::warn!(%seq, %err, "failed to save sink cursor");
+                    }
+                }
+                Ok(None) => thread::sleep(SLEEP),
+                Err(err) => {
+                    tracing::warn!(%seq, %err, "sink error");
+                    self.connected = false;
+                    thread::sleep(SINK_RECONNECT_INTERVAL);
+                }
+            }
+        }
+        tracing::info!(%seq, "shutting down");
+        Ok(())
+    }
+
+    /// The seq to publish after: the saved cursor, or the tip of the firehose for a new sink.
+    fn resume(&self) -> Result<Cursor, SinkError> {
+        let Some(saved) = self.cursors.get(self.name())? else {
+            return Ok(self.firehose.last_key_value()?.map(|(k, _)| k.into()).unwrap_or_default());
+        };
+        let saved = Cursor::from(saved);
+        if let Some((first, _)) = self.firehose.first_key_value()? {
+            let first = Cursor::from(first);
+            if first.get() > saved.get() + 1 {
+                tracing::warn!(
+                    %saved,
+                    %first,
+                    "sink cursor is older than the firehose, events may be skipped"
+                );
+            }
+        }
+        Ok(saved)
+    }
+
+    /// Publishes the events after `seq`, returning the last one sent, if any.
+    fn update(&mut self, seq: Cursor) -> Result<Option<Cursor>, SinkError> {
+        if !self.connected {
+            self.sink.connect()?;
+            self.connected = true;
+        }
+        let mut last = None;
+        // seqs jump ahead after an unclean restart, see `checkpoint`, so seek rather than count
+        for msg in self.firehose.range((seq + 1)..).take(SINK_BATCH) {
+            let (k, v) = msg?;
+            let next: Cursor = k.into();
+            match SubscribeReposEvent::parse(&v) {
+                Ok(Some(event)) => self.sink.send(event.did(), next, &v)?,
+                Ok(None) => {}
+                Err(err) => tracing::debug!(%next, %err, "skipping unparseable event"),
+            }
+            last = Some(next);
+        }
+        if last.is_some() {
+            self.sink.flush()?;
+        }
+        Ok(last)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct NatsConnect<'a> {
+    verbose: bool,
+    pedantic: bool,
+    name: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pass: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auth_token: Option<&'a str>,
+}
+
+/// Publishes to `<subject>.<did>` over the NATS text protocol.
+pub struct NatsSink {
+    url: String,
+    subject: String,
+    conn: Option<(BufReader<TcpStream>, BufWriter<TcpStream>)>,
+}
+
+impl NatsSink {
+    pub const fn new(url: String, subject: String) -> Self {
+        Self { url, subject, conn: None }
+    }
+
+    /// '.' separates subject tokens, so `did:web` hostnames get '_' instead.
+    fn subject(&self, did: &str) -> String {
+        format!("{}.{}", self.subject, did.replace('.', "_"))
+    }
+
+    fn handshake(&self) -> Result<(BufReader<TcpStream>, BufWriter<TcpStream>), SinkError> {
+        let url = Url::parse(&self.url)?;
+        let host = url.host_str().ok_or_else(|| SinkError::Protocol("missing host".into()))?;
+        let stream = TcpStream::connect((host, url.port().unwrap_or(4222)))?;
+        stream.set_nodelay(true)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if !line.starts_with("INFO ") {
+            return Err(SinkError::Protocol(format!("unexpected greeting: {}", line.trim_end())));
+        }
+        let (user, pass, auth_token) = match (url.username(), url.password()) {
+            ("", _) => (None, None, None),
+            (token, None) => (None, None, Some(token)),
+            (user, Some(pass)) => (Some(user), Some(pass), None),
+        };
+        let connect = NatsConnect {
+            verbose: false,
+            pedantic: false,
+            name: "rsky-relay",
+            user,
+            pass,
+            auth_token,
+        };
+        writer.write_all(b"CONNECT ")?;
+        serde_json::to_writer(&mut writer, &connect).map_err(io::Error::from)?;
+        writer.write_all(b"\r\nPING\r\n")?;
+        writer.flush()?;
+        Self::await_pong(&mut reader, &mut writer)?;
+        Ok((reader, writer))
+    }
+
+    /// Reads until the server answers a PING, surfacing `-ERR` and answering its own PINGs.
+    fn await_pong(
+        reader: &mut BufReader<TcpStream>, writer: &mut BufWriter<TcpStream>,
+    ) -> Result<(), SinkError> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            match line.trim_end() {
+                "PONG" => return Ok(()),
+                "PING" => {
+                    writer.write_all(b"PONG\r\n")?;
+                    writer.flush()?;
+                }
+                err if err.starts_with("-ERR") => return Err(SinkError::Protocol(err.into())),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Sink for NatsSink {
+    fn name(&self) -> &'static str {
+        "nats"
+    }
+
+    fn connect(&mut self) -> Result<(), SinkError> {
+        self.conn = None;
+        self.conn = Some(self.handshake()?);
+        Ok(())
+    }
+
+    fn send(&mut self, did: &str, _seq: Cursor, frame: &[u8]) -> Result<(), SinkError> {
+        let subject = self.subject(did);
+        let (_, writer) =
+            self.conn.as_mut().ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+        write!(writer, "PUB {subject} {}\r\n", frame.len())?;
+        writer.write_all(frame)?;
+        writer.write_all(b"\r\n")?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), SinkError> {
+        let (reader, writer) =
+            self.conn.as_mut().ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+        // the round trip confirms the server took everything before it
+        writer.write_all(b"PING\r\n")?;
+        writer.flush()?;
+        Self::await_pong(reader, writer)
+    }
+}
+
+/// Produces to a Kafka topic with the DID as the record key.
+#[cfg(feature = "kafka")]
+pub struct KafkaSink {
+    brokers: Vec<String>,
+    topic: String,
+    producer: Option<kafka::producer::Producer>,
+    pending: Vec<(String, Vec<u8>)>,
+}
+
+#[cfg(feature = "kafka")]
+impl KafkaSink {
+    pub const fn new(brokers: Vec<String>, topic: String) -> Self {
+        Self { brokers, topic, producer: None, pending: Vec::new() }
+    }
+}
+
+#[cfg(feature = "kafka")]
+impl Sink for KafkaSink {
+    fn name(&self) -> &'static str {
+        "kafka"
+    }
+
+    fn connect(&mut self) -> Result<(), SinkError> {
+        use kafka::producer::{Producer, RequiredAcks};
+
+        self.producer = None;
+        self.pending.clear();
+        self.producer = Some(
+            Producer::from_hosts(self.brokers.clone())
+                .with_ack_timeout(Duration::from_secs(5))
+                .with_required_acks(RequiredAcks::One)
+                .create()?,
+        );
+        Ok(())
+    }
+
+    fn send(&mut self, did: &str, _seq: Cursor, frame: &[u8]) -> Result<(), SinkError> {
+        self.pending.push((did.to_owned(), frame.to_vec()));
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), SinkError> {
+        use kafka::producer::Record;
+
+        let producer =
+            self.producer.as_mut().ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+        let records = self
+            .pending
+            .iter()
+            .map(|(did, frame)| {
+                Record::from_key_value(&self.topic, did.as_bytes(), frame.as_slice())
+            })
+            .collect::<Vec<_>>();
+        producer.send_all(&records)?;
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::net::TcpListener;
+    #[cfg(not(feature = "labeler"))]
+    use std::sync::{Arc, Mutex};
+
+    #[cfg(not(feature = "labeler"))]
+    use serde_json::json;
+
+    use super::*;
+    #[cfg(not(feature = "labeler"))]
+    use crate::harness::{Harness, MockPds};
+
+    /// Collects the seqs it's sent, acknowledging every flush.
+    #[cfg(not(feature = "labeler"))]
+    struct RecordingSink(Arc<Mutex<Vec<u64>>>);
+
+    #[cfg(not(feature = "labeler"))]
+    impl Sink for RecordingSink {
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+
+        fn connect(&mut self) -> Result<(), SinkError> {
+            Ok(())
+        }
+
+        fn send(&mut self, _did: &str, seq: Cursor, _frame: &[u8]) -> Result<(), SinkError> {
+            self.0.lock().unwrap().push(seq.get());
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<(), SinkError> {
+            Ok(())
+        }
+    }
+
+    #[cfg(not(feature = "labeler"))]
+    #[tokio::test]
+    async fn resumes_from_saved_cursor() -> anyhow::Result<()> {
+        let mut harness = Harness::new()?;
+        let mut pds = MockPds::new("sink.test");
+        let alice = pds.create_account("alice").await?;
+        let record = json!({
+            "$type": "app.bsky.feed.post",
+            "text": "hello",
+            "createdAt": "2025-01-01T00:00:00.000Z",
+        });
+        pds.create_record(&alice, "app.bsky.feed.post", record).await?;
+        harness.crawl(&mut pds).await?;
+
+        // as if the relay stopped after the sink acknowledged the second event
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut runner = SinkRunner::new(Box::new(RecordingSink(sent.clone())))?;
+        runner.cursors.insert(runner.name(), Cursor::from(harness.seq(2)))?;
+        let seq = runner.resume()?;
+        assert_eq!(seq.get(), harness.seq(2));
+        assert_eq!(runner.update(seq)?.map(Cursor::get), Some(harness.seq(4)));
+        assert_eq!(*sent.lock().unwrap(), vec![harness.seq(3), harness.seq(4)]);
+        Ok(())
+    }
+
+    #[test]
+    fn nats_publishes_keyed_by_did() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            writer.write_all(b"INFO {\"server_id\":\"test\"}\r\n").unwrap();
+            let mut lines = Vec::new();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 {
+                let trimmed = line.trim_end().to_owned();
+                line.clear();
+                if trimmed == "PING" {
+                    writer.write_all(b"PONG\r\n").unwrap();
+                    if lines.len() == 2 {
+                        return lines;
+                    }
+                } else if let Some(len) = trimmed.strip_prefix("PUB ") {
+                    let len: usize = len.rsplit(' ').next().unwrap().parse().unwrap();
+                    let mut payload = vec![0; len + 2];
+                    reader.read_exact(&mut payload).unwrap();
+                    payload.truncate(len);
+                    lines.push((trimmed, payload));
+                }
+            }
+            lines
+        });
+
+        let mut sink = NatsSink::new(format!("nats://{addr}"), "firehose".into());
+        sink.connect().unwrap();
+        sink.send("did:plc:abc", Cursor::from(1), b"one").unwrap();
+        sink.send("did:web:example.com", Cursor::from(2), b"two").unwrap();
+        sink.flush().unwrap();
+
+        let lines = server.join().unwrap();
+        assert_eq!(lines[0], ("PUB firehose.did:plc:abc 3".to_owned(), b"one".to_vec()));
+        assert_eq!(lines[1], ("PUB firehose.did:web:example_com 3".to_owned(), b"two".to_vec()));
+    }
+}
@@ -1,7 +1,11 @@
 use std::net::{SocketAddr, TcpStream};
 
+use magnetic::buffer::dynamic::DynamicBufferP2;
+use magnetic::mpsc::{MPSCConsumer, MPSCProducer};
 use rtrb::{Consumer, Producer};
 
+use crate::publisher::connection::Connection;
+use crate::server::SubscriberPermit;
 use crate::types::Cursor;
 
 pub use maybe_tls_stream::MaybeTlsStream;
@@ -10,17 +14,30 @@ pub type CommandSender = Producer<Command>;
 pub type CommandReceiver = Consumer<Command>;
 pub type SubscribeReposSender = Producer<SubscribeRepos>;
 pub type SubscribeReposReceiver = Consumer<SubscribeRepos>;
+pub type ReleasedSender = MPSCProducer<Connection, DynamicBufferP2<Connection>>;
+pub type ReleasedReceiver = MPSCConsumer<Connection, DynamicBufferP2<Connection>>;
 
 #[derive(Debug)]
 pub struct SubscribeRepos {
     pub addr: SocketAddr,
     pub stream: MaybeTlsStream<TcpStream>,
     pub cursor: Option<Cursor>,
+    pub permit: SubscriberPermit,
 }
 
 #[derive(Debug)]
 pub enum Command {
     Connect(SubscribeRepos),
+    /// Take over a live subscriber another worker released.
+    Adopt(Connection),
+    /// The pool grew from `from` to `to` workers: release all but a `from / to` share of
+    /// subscribers for the new workers to adopt.
+    Rebalance {
+        from: usize,
+        to: usize,
+    },
+    /// Release every subscriber and exit.
+    Retire,
 }
 
 mod maybe_tls_stream {
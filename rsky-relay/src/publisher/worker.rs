@@ -5,13 +5,14 @@ use std::{io, thread};
 
 use bytes::Bytes;
 use fjall::{PartitionCreateOptions, PartitionHandle};
+use magnetic::Producer;
 use mio::unix::SourceFd;
 use mio::{Events, Interest, Poll, Token};
 use thiserror::Error;
 
 use crate::SHUTDOWN;
 use crate::publisher::connection::{Connection, ConnectionError};
-use crate::publisher::types::{Command, CommandReceiver};
+use crate::publisher::types::{Command, CommandReceiver, ReleasedSender};
 use crate::types::{Cursor, DB};
 
 const INTEREST: Interest = Interest::WRITABLE;
@@ -31,17 +32,31 @@ pub struct Worker {
     connections: Vec<Option<Connection>>,
     next_idx: usize,
     command_rx: CommandReceiver,
+    released_tx: ReleasedSender,
     firehose: PartitionHandle,
     poll: Poll,
     events: Events,
+    retired: bool,
 }
 
 impl Worker {
-    pub fn new(id: usize, command_rx: CommandReceiver) -> Result<Self, WorkerError> {
+    pub fn new(
+        id: usize, command_rx: CommandReceiver, released_tx: ReleasedSender,
+    ) -> Result<Self, WorkerError> {
         let firehose = DB.open_partition("firehose", PartitionCreateOptions::default())?;
         let poll = Poll::new()?;
         let events = Events::with_capacity(1024);
-        Ok(Self { id, connections: Vec::new(), next_idx: 0, command_rx, firehose, poll, events })
+        Ok(Self {
+            id,
+            connections: Vec::new(),
+            next_idx: 0,
+            command_rx,
+            released_tx,
+            firehose,
+            poll,
+            events,
+            retired: false,
+        })
     }
 
     pub fn run(mut self) -> Result<(), WorkerError> {
@@ -51,6 +66,10 @@ impl Worker {
         while self.update(&mut seq)? {
             thread::yield_now();
         }
+        if self.retired {
+            tracing::info!("retired");
+            return Ok(());
+        }
         tracing::info!("shutting down");
         self.shutdown();
         Ok(())
@@ -70,32 +89,60 @@ impl Worker {
                     config.addr,
                     config.stream,
                     config.cursor.unwrap_or_else(|| seq.next()),
+                    config.permit,
                 ) {
-                    Ok(conn) => {
-                        let idx = self.connections.iter().position(Option::is_none).unwrap_or_else(
-                            || {
-                                let idx = self.connections.len();
-                                self.connections.push(None);
-                                idx
-                            },
-                        );
-                        #[expect(clippy::expect_used)]
-                        self.poll
-                            .registry()
-                            .register(&mut SourceFd(&conn.as_raw_fd()), Token(idx), INTEREST)
-                            .expect("unable to register");
-                        self.connections[idx] = Some(conn);
-                    }
+                    Ok(conn) => self.add(conn),
                     Err(err) => {
                         tracing::warn!(addr = %config.addr, cursor = ?config.cursor, %err, "unable to subscribeRepos");
                     }
                 }
             }
+            Command::Adopt(conn) => {
+                tracing::debug!(addr = %conn.addr, cursor = %conn.cursor, "adopting publish");
+                self.add(conn);
+            }
+            Command::Rebalance { from, to } => {
+                let active = self.connections.iter().flatten().count();
+                self.release(active * from / to);
+            }
+            Command::Retire => {
+                self.release(0);
+                self.retired = true;
+            }
+        }
+    }
+
+    fn add(&mut self, conn: Connection) {
+        let idx = self.connections.iter().position(Option::is_none).unwrap_or_else(|| {
+            let idx = self.connections.len();
+            self.connections.push(None);
+            idx
+        });
+        #[expect(clippy::expect_used)]
+        self.poll
+            .registry()
+            .register(&mut SourceFd(&conn.as_raw_fd()), Token(idx), INTEREST)
+            .expect("unable to register");
+        self.connections[idx] = Some(conn);
+    }
+
+    /// Hands every subscriber past the first `keep` back to the manager for another worker.
+    fn release(&mut self, keep: usize) {
+        for slot in self.connections.iter_mut().filter(|slot| slot.is_some()).skip(keep) {
+            #[expect(clippy::unwrap_used)]
+            let conn = slot.take().unwrap();
+            #[expect(clippy::expect_used)]
+            self.poll
+                .registry()
+                .deregister(&mut SourceFd(&conn.as_raw_fd()))
+                .expect("failed to deregister");
+            #[expect(clippy::expect_used)]
+            self.released_tx.push(conn).expect("unable to release connection");
         }
     }
 
     fn update(&mut self, seq: &mut Cursor) -> Result<bool, WorkerError> {
-        if SHUTDOWN.load(Ordering::Relaxed) {
+        if SHUTDOWN.load(Ordering::Relaxed) || self.retired {
             return Ok(false);
         }
 
@@ -104,7 +151,8 @@ impl Worker {
                 self.handle_command(command, *seq);
             }
 
-            for msg in self.firehose.range((*seq + 1)..=(*seq + 32)) {
+            // seqs jump ahead after an unclean restart, see `checkpoint`, so seek rather than count
+            for msg in self.firehose.range((*seq + 1)..).take(32) {
                 let (k, v) = msg?;
                 *seq = k.into();
                 self.send(*seq, &Bytes::from_owner(v));
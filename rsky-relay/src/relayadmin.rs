@@ -0,0 +1,173 @@
+//! Operator CLI for a running relay, talking to its admin API over loopback.
+
+use clap::{Parser, Subcommand};
+use color_eyre::Result;
+use color_eyre::eyre::eyre;
+use reqwest::blocking::{Client, RequestBuilder};
+use rustls::crypto::aws_lc_rs::default_provider;
+use serde_json::{Value, json};
+
+use rsky_relay::config::PORT;
+
+#[derive(Debug, Parser)]
+#[command(name = "rsky-relayadmin", about = "Administer a running rsky-relay")]
+pub struct Args {
+    /// Base URL of the relay.
+    #[clap(long, env = "RELAY_URL", default_value_t = format!("http://localhost:{PORT}"))]
+    url: String,
+    /// Admin password the relay was started with.
+    #[clap(long, env = "RELAY_ADMIN_PASSWORD", hide_env_values = true)]
+    password: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Ask the relay to crawl a host.
+    RequestCrawl { hostname: String },
+    /// Manage hosts the relay crawls.
+    #[command(subcommand)]
+    Host(HostCommand),
+    /// Manage individual repos.
+    #[command(subcommand)]
+    Repo(RepoCommand),
+    /// Show the crawler and publisher pool sizes, or resize them.
+    Workers {
+        #[clap(long)]
+        crawlers: Option<usize>,
+        #[clap(long)]
+        publishers: Option<usize>,
+    },
+    /// Show recent takedowns and untakedowns, newest first.
+    AuditLog {
+        #[clap(long)]
+        limit: Option<u32>,
+        /// Audit entry id to page back from.
+        #[clap(long)]
+        cursor: Option<u64>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum HostCommand {
+    /// Stop crawling a host and drop its events.
+    Takedown {
+        host: String,
+        #[clap(long)]
+        reason: Option<String>,
+    },
+    /// Lift a host takedown.
+    Untakedown {
+        host: String,
+        #[clap(long)]
+        reason: Option<String>,
+    },
+    /// List taken down hosts.
+    List,
+    /// Show the last seq relayed from each host, or from one.
+    Cursor { host: Option<String> },
+}
+
+#[derive(Debug, Subcommand)]
+enum RepoCommand {
+    /// Drop a repo's events and announce it as taken down.
+    Takedown {
+        did: String,
+        #[clap(long)]
+        reason: Option<String>,
+    },
+    /// Lift a repo takedown.
+    Untakedown {
+        did: String,
+        #[clap(long)]
+        reason: Option<String>,
+    },
+    /// List taken down repos.
+    List,
+}
+
+struct Relay {
+    client: Client,
+    url: String,
+    password: Option<String>,
+}
+
+impl Relay {
+    fn get(&self, path: &str, query: &[(&str, String)]) -> Result<Value> {
+        self.send(self.client.get(format!("{}{path}", self.url)).query(query))
+    }
+
+    fn post(&self, path: &str, body: &Value) -> Result<Value> {
+        self.send(self.client.post(format!("{}{path}", self.url)).json(body))
+    }
+
+    fn send(&self, mut request: RequestBuilder) -> Result<Value> {
+        if let Some(password) = &self.password {
+            request = request.bearer_auth(password);
+        }
+        let response = request.send()?;
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(eyre!("unauthorized, check RELAY_ADMIN_PASSWORD"));
+        }
+        if !status.is_success() {
+            return Err(eyre!("relay responded with {status}"));
+        }
+        Ok(response.json()?)
+    }
+}
+
+pub fn main() -> Result<()> {
+    color_eyre::install()?;
+
+    #[expect(clippy::unwrap_used)]
+    default_provider().install_default().unwrap();
+
+    let args = Args::parse();
+    let relay = Relay {
+        client: Client::builder().user_agent("rsky-relayadmin").build()?,
+        url: args.url.trim_end_matches('/').to_owned(),
+        password: args.password,
+    };
+    let res = match args.command {
+        Command::RequestCrawl { hostname } => {
+            relay.post("/xrpc/com.atproto.sync.requestCrawl", &json!({ "hostname": hostname }))?
+        }
+        Command::Host(HostCommand::Takedown { host, reason }) => {
+            relay.post("/admin/pds/takedown", &json!({ "host": host, "reason": reason }))?
+        }
+        Command::Host(HostCommand::Untakedown { host, reason }) => {
+            relay.post("/admin/pds/untakedown", &json!({ "host": host, "reason": reason }))?
+        }
+        Command::Host(HostCommand::List) => relay.get("/admin/pds/takedowns", &[])?,
+        Command::Host(HostCommand::Cursor { host }) => {
+            let query: Vec<_> = host.into_iter().map(|host| ("host", host)).collect();
+            relay.get("/admin/pds/cursors", &query)?
+        }
+        Command::Repo(RepoCommand::Takedown { did, reason }) => {
+            relay.post("/admin/repo/takedown", &json!({ "did": did, "reason": reason }))?
+        }
+        Command::Repo(RepoCommand::Untakedown { did, reason }) => {
+            relay.post("/admin/repo/untakedown", &json!({ "did": did, "reason": reason }))?
+        }
+        Command::Repo(RepoCommand::List) => relay.get("/admin/repo/takedowns", &[])?,
+        Command::Workers { crawlers: None, publishers: None } => {
+            relay.get("/admin/workers", &[])?
+        }
+        Command::Workers { crawlers, publishers } => relay
+            .post("/admin/workers", &json!({ "crawlers": crawlers, "publishers": publishers }))?,
+        Command::AuditLog { limit, cursor } => {
+            let mut query = Vec::new();
+            if let Some(limit) = limit {
+                query.push(("limit", limit.to_string()));
+            }
+            if let Some(cursor) = cursor {
+                query.push(("cursor", cursor.to_string()));
+            }
+            relay.get("/admin/auditLog", &query)?
+        }
+    };
+    println!("{}", serde_json::to_string_pretty(&res)?);
+    Ok(())
+}
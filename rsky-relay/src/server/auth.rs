@@ -0,0 +1,287 @@
+//! Subscriber policy for the firehose: an optional `Origin` allowlist for browser clients,
+//! optional authentication with a static token or an atproto service JWT, and a cap on
+//! concurrent connections per identity so one consumer can't hold every publisher slot.
+//!
+//! Credentials come in the `Authorization: Bearer` header or, since browsers can't set headers
+//! on websockets, the `token` query param. Service JWTs must name `RELAY_SUBSCRIBER_AUDIENCE`
+//! as their audience and are checked against the issuer's `#atproto` key in the local plc
+//! mirror, so only `did:plc` issuers the relay has already seen can use them.
+
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::{LazyLock, Mutex, PoisonError};
+
+use chrono::Utc;
+use hashbrown::HashMap;
+use multibase::Base;
+use rusqlite::{Connection, OpenFlags};
+use serde::Deserialize;
+use subtle::ConstantTimeEq;
+use thiserror::Error;
+
+use crate::config::{
+    SUBSCRIBER_AUDIENCE, SUBSCRIBER_AUTH_REQUIRED, SUBSCRIBER_MAX_CONNECTIONS, SUBSCRIBER_ORIGINS,
+    SUBSCRIBER_TOKENS,
+};
+use crate::validator::{DidKey, verify_signature};
+
+static CONNECTIONS: LazyLock<Mutex<HashMap<Identity, usize>>> = LazyLock::new(Mutex::default);
+
+#[derive(Debug, Error)]
+pub enum AuthError {
+    #[error("origin not allowed")]
+    Origin,
+    #[error("authentication required")]
+    Missing,
+    #[error("invalid token")]
+    InvalidToken,
+    #[error("invalid service jwt: {0}")]
+    Jwt(&'static str),
+    #[error("too many connections for {0}")]
+    TooManyConnections(Identity),
+    #[error("sqlite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+impl AuthError {
+    pub const fn status(&self) -> &'static str {
+        match self {
+            Self::Origin => "403 Forbidden",
+            Self::Missing | Self::InvalidToken | Self::Jwt(_) => "401 Unauthorized",
+            Self::TooManyConnections(_) => "429 Too Many Requests",
+            Self::Sqlite(_) => "500 Internal Server Error",
+        }
+    }
+}
+
+/// Who a subscriber is, for logs and connection limits.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Identity {
+    Anonymous(IpAddr),
+    /// The name of a `RELAY_SUBSCRIBER_TOKENS` entry.
+    Token(String),
+    /// The issuer of a service JWT.
+    Did(String),
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Anonymous(ip) => write!(f, "ip:{ip}"),
+            Self::Token(name) => write!(f, "token:{name}"),
+            Self::Did(did) => f.write_str(did),
+        }
+    }
+}
+
+/// Counts towards its identity's connections until dropped.
+#[derive(Debug)]
+pub struct SubscriberPermit {
+    identity: Identity,
+}
+
+impl SubscriberPermit {
+    pub fn acquire(identity: Identity, max: Option<usize>) -> Result<Self, AuthError> {
+        let mut connections = CONNECTIONS.lock().unwrap_or_else(PoisonError::into_inner);
+        let count = connections.get(&identity).copied().unwrap_or_default();
+        if max.is_some_and(|max| count >= max) {
+            return Err(AuthError::TooManyConnections(identity));
+        }
+        connections.insert(identity.clone(), count + 1);
+        drop(connections);
+        Ok(Self { identity })
+    }
+
+    pub const fn identity(&self) -> &Identity {
+        &self.identity
+    }
+}
+
+impl Drop for SubscriberPermit {
+    fn drop(&mut self) {
+        let mut connections = CONNECTIONS.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(count) = connections.get_mut(&self.identity) {
+            *count -= 1;
+            if *count == 0 {
+                connections.remove(&self.identity);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    iss: String,
+    aud: String,
+    exp: i64,
+    lxm: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct SubscriberAuth {
+    /// The method service JWTs must be bound to, if they're bound to one.
+    method: &'static str,
+    /// The plc mirror, opened only when service JWTs are accepted.
+    keys: Option<Connection>,
+}
+
+impl SubscriberAuth {
+    pub fn new(method: &'static str) -> Result<Self, AuthError> {
+        let keys = if SUBSCRIBER_AUDIENCE.is_some() {
+            Some(Connection::open_with_flags(
+                "plc_directory.db",
+                OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            )?)
+        } else {
+            None
+        };
+        Ok(Self { method, keys })
+    }
+
+    /// Applies the policy to a subscription request, returning a permit to hand to the publisher.
+    pub fn authorize(
+        &self, ip: IpAddr, origin: Option<&[u8]>, authorization: Option<&[u8]>, token: Option<&str>,
+    ) -> Result<SubscriberPermit, AuthError> {
+        if let Some(origin) = origin {
+            if !SUBSCRIBER_ORIGINS.is_empty()
+                && !SUBSCRIBER_ORIGINS.iter().any(|allowed| allowed.as_bytes() == origin)
+            {
+                return Err(AuthError::Origin);
+            }
+        }
+        let bearer = authorization
+            .and_then(|authorization| authorization.strip_prefix(b"Bearer "))
+            .and_then(|bearer| std::str::from_utf8(bearer).ok());
+        // credentials are ignored when there's nothing to check them against
+        let configured = !SUBSCRIBER_TOKENS.is_empty() || self.keys.is_some();
+        let identity = match bearer.or(token) {
+            Some(token) if configured => self.authenticate(token)?,
+            _ if *SUBSCRIBER_AUTH_REQUIRED => return Err(AuthError::Missing),
+            _ => Identity::Anonymous(ip),
+        };
+        SubscriberPermit::acquire(identity, *SUBSCRIBER_MAX_CONNECTIONS)
+    }
+
+    fn authenticate(&self, token: &str) -> Result<Identity, AuthError> {
+        if let Some((name, _)) = SUBSCRIBER_TOKENS
+            .iter()
+            .find(|(_, secret)| bool::from(secret.as_bytes().ct_eq(token.as_bytes())))
+        {
+            return Ok(Identity::Token(name.clone()));
+        }
+        let (Some(audience), Some(keys)) = (SUBSCRIBER_AUDIENCE.as_deref(), &self.keys) else {
+            return Err(AuthError::InvalidToken);
+        };
+        let did =
+            verify_service_jwt(token, audience, self.method, Utc::now().timestamp(), |iss| {
+                lookup_key(keys, iss)
+            })?;
+        Ok(Identity::Did(did))
+    }
+}
+
+fn lookup_key(conn: &Connection, did: &str) -> Result<Option<DidKey>, AuthError> {
+    let mut stmt = conn.prepare_cached("SELECT pds_key FROM plc_keys WHERE did = ?1")?;
+    let key = match stmt.query_one([did], |row| row.get::<_, Option<String>>(0)) {
+        Ok(key) => key,
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(err) => Err(err)?,
+    };
+    Ok(key
+        .and_then(|key| multibase::decode(key.trim_start_matches("did:key:")).ok())
+        .and_then(|(_, key)| key.try_into().ok()))
+}
+
+/// Checks a service JWT's claims and signature, returning its issuer.
+fn verify_service_jwt(
+    token: &str, audience: &str, method: &str, now: i64,
+    key_for: impl FnOnce(&str) -> Result<Option<DidKey>, AuthError>,
+) -> Result<String, AuthError> {
+    let (signed, sig) = token.rsplit_once('.').ok_or(AuthError::Jwt("malformed"))?;
+    let (_, payload) = signed.split_once('.').ok_or(AuthError::Jwt("malformed"))?;
+    let payload = Base::Base64Url.decode(payload).map_err(|_| AuthError::Jwt("malformed"))?;
+    let claims: Claims =
+        serde_json::from_slice(&payload).map_err(|_| AuthError::Jwt("malformed"))?;
+    if claims.aud != audience {
+        return Err(AuthError::Jwt("wrong audience"));
+    }
+    if claims.exp <= now {
+        return Err(AuthError::Jwt("expired"));
+    }
+    if claims.lxm.as_deref().is_some_and(|lxm| lxm != method) {
+        return Err(AuthError::Jwt("bound to another method"));
+    }
+    let key = key_for(&claims.iss)?.ok_or(AuthError::Jwt("unknown issuer"))?;
+    let sig = Base::Base64Url.decode(sig).map_err(|_| AuthError::Jwt("malformed"))?;
+    match verify_signature(&key, signed.as_bytes(), &sig) {
+        Ok(true) => Ok(claims.iss),
+        _ => Err(AuthError::Jwt("bad signature")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use k256::ecdsa::signature::Signer;
+    use k256::ecdsa::{Signature, SigningKey};
+
+    use super::*;
+
+    const AUD: &str = "did:web:relay.example.com";
+    const METHOD: &str = "com.atproto.sync.subscribeRepos";
+    const ISS: &str = "did:plc:subscriber";
+
+    fn sign(key: &SigningKey, claims: &serde_json::Value) -> String {
+        let header = Base::Base64Url.encode(br#"{"alg":"ES256K","typ":"JWT"}"#);
+        let payload = Base::Base64Url.encode(claims.to_string());
+        let signed = format!("{header}.{payload}");
+        let sig: Signature = key.sign(signed.as_bytes());
+        format!("{signed}.{}", Base::Base64Url.encode(sig.to_bytes()))
+    }
+
+    fn did_key(key: &SigningKey) -> DidKey {
+        let mut did_key = [0; 35];
+        did_key[..2].copy_from_slice(&[0xe7, 0x01]);
+        did_key[2..].copy_from_slice(&key.verifying_key().to_sec1_bytes());
+        did_key
+    }
+
+    #[test]
+    fn service_jwt() {
+        let key = SigningKey::from_slice(&[7; 32]).unwrap();
+        let verify = |token: &str| {
+            verify_service_jwt(token, AUD, METHOD, 1000, |iss| {
+                Ok((iss == ISS).then(|| did_key(&key)))
+            })
+        };
+
+        let token = sign(&key, &serde_json::json!({ "iss": ISS, "aud": AUD, "exp": 1060 }));
+        assert_eq!(verify(&token).unwrap(), ISS);
+        let token =
+            sign(&key, &serde_json::json!({ "iss": ISS, "aud": AUD, "exp": 1060, "lxm": METHOD }));
+        assert_eq!(verify(&token).unwrap(), ISS);
+
+        for claims in [
+            serde_json::json!({ "iss": ISS, "aud": "did:web:other.example.com", "exp": 1060 }),
+            serde_json::json!({ "iss": ISS, "aud": AUD, "exp": 1000 }),
+            serde_json::json!({ "iss": ISS, "aud": AUD, "exp": 1060, "lxm": "com.atproto.sync.getRepo" }),
+            serde_json::json!({ "iss": "did:plc:stranger", "aud": AUD, "exp": 1060 }),
+        ] {
+            assert!(verify(&sign(&key, &claims)).is_err());
+        }
+        let other = SigningKey::from_slice(&[8; 32]).unwrap();
+        let forged = sign(&other, &serde_json::json!({ "iss": ISS, "aud": AUD, "exp": 1060 }));
+        assert!(verify(&forged).is_err());
+    }
+
+    #[test]
+    fn connection_limit() {
+        let identity = Identity::Token("connection_limit".to_owned());
+        let first = SubscriberPermit::acquire(identity.clone(), Some(2)).unwrap();
+        let second = SubscriberPermit::acquire(identity.clone(), Some(2)).unwrap();
+        assert!(SubscriberPermit::acquire(identity.clone(), Some(2)).is_err());
+        drop(first);
+        let third = SubscriberPermit::acquire(identity.clone(), Some(2)).unwrap();
+        drop((second, third));
+        assert!(!CONNECTIONS.lock().unwrap().contains_key(&identity));
+    }
+}
@@ -1,5 +1,7 @@
+mod auth;
 #[expect(clippy::module_inception)]
 mod server;
 mod types;
 
+pub use auth::{Identity, SubscriberPermit};
 pub use server::{Server, ServerError};
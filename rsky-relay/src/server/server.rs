@@ -14,17 +14,24 @@ use httparse::{EMPTY_HEADER, Status};
 #[cfg(feature = "labeler")]
 use rusqlite::{Connection, OpenFlags};
 use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use subtle::ConstantTimeEq;
 use thiserror::Error;
 use url::Url;
 
-use crate::SHUTDOWN;
-use crate::config::{HOSTS_INTERVAL, PORT};
+use crate::admin::{AdminAction, AdminActionSender, Store, StoreError, Subject};
+use crate::config::{
+    ADMIN_PASSWORD, AUDIT_LOG_LIMIT, CUSTODY, CUSTODY_LIMIT, HOSTS_INTERVAL, PORT, WORKERS_MAX,
+};
 #[cfg(not(feature = "labeler"))]
-use crate::config::{HOSTS_MIN_ACCOUNTS, HOSTS_RELAY};
+use crate::config::{HOSTS_MIN_ACCOUNTS, HOSTS_RELAY, UPSTREAMS};
 use crate::crawler::{RequestCrawl, RequestCrawlSender};
+use crate::custody::{CustodyError, CustodyLog};
 use crate::publisher::{MaybeTlsStream, SubscribeRepos, SubscribeReposSender};
+use crate::server::auth::{AuthError, SubscriberAuth};
 #[cfg(not(feature = "labeler"))]
 use crate::server::types::{HostStatus, ListHosts};
+use crate::server::types::{HostTakedown, RepoTakedown, Workers};
+use crate::{CRAWLER_WORKERS, PUBLISHER_WORKERS, SHUTDOWN, watchdog};
 
 const SLEEP: Duration = Duration::from_millis(10);
 
@@ -42,6 +49,20 @@ const PATH_REQUEST_CRAWL: &str = if cfg!(feature = "labeler") {
     "/xrpc/com.atproto.sync.requestCrawl"
 };
 
+const PATH_ADMIN_REPO_TAKEDOWN: &str = "/admin/repo/takedown";
+const PATH_ADMIN_REPO_UNTAKEDOWN: &str = "/admin/repo/untakedown";
+const PATH_ADMIN_REPO_TAKEDOWNS: &str = "/admin/repo/takedowns";
+const PATH_ADMIN_HOST_TAKEDOWN: &str = "/admin/pds/takedown";
+const PATH_ADMIN_HOST_UNTAKEDOWN: &str = "/admin/pds/untakedown";
+const PATH_ADMIN_HOST_TAKEDOWNS: &str = "/admin/pds/takedowns";
+const PATH_ADMIN_HOST_CURSORS: &str = "/admin/pds/cursors";
+const PATH_ADMIN_AUDIT_LOG: &str = "/admin/auditLog";
+const PATH_ADMIN_WORKERS: &str = "/admin/workers";
+
+const PATH_CUSTODY: &str = "/custody";
+
+const PATH_METRICS: &str = "/metrics";
+
 const INDEX_ASCII: &str = r"
     .------..------..------..------.
     |R.--. ||S.--. ||K.--. ||Y.--. |
@@ -72,6 +93,12 @@ pub enum ServerError {
     PushError(#[from] rtrb::PushError<RequestCrawl>),
     #[error("url parse error: {0}")]
     UrlParse(#[from] url::ParseError),
+    #[error("store error: {0}")]
+    Store(#[from] StoreError),
+    #[error("custody error: {0}")]
+    Custody(#[from] CustodyError),
+    #[error("auth error: {0}")]
+    Auth(#[from] AuthError),
     #[cfg(feature = "labeler")]
     #[error("sqlite error: {0}")]
     Sqlite(#[from] rusqlite::Error),
@@ -101,14 +128,18 @@ pub struct Server {
     last: Instant,
     #[cfg(feature = "labeler")]
     conn: Connection,
+    store: Store,
+    custody: Option<CustodyLog>,
+    auth: SubscriberAuth,
     request_crawl_tx: RequestCrawlSender,
     subscribe_repos_tx: SubscribeReposSender,
+    admin_tx: AdminActionSender,
 }
 
 impl Server {
     pub fn new(
         ssl_configs: Option<(PathBuf, PathBuf)>, request_crawl_tx: RequestCrawlSender,
-        subscribe_repos_tx: SubscribeReposSender,
+        subscribe_repos_tx: SubscribeReposSender, admin_tx: AdminActionSender,
     ) -> Result<Self, ServerError> {
         let tls_config = if let Some((certs, private_key)) = ssl_configs {
             let certs = rustls_pemfile::certs(&mut BufReader::new(&mut File::open(certs)?))
@@ -135,6 +166,9 @@ impl Server {
             "plc_directory.db",
             OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
         )?;
+        let store = Store::open()?;
+        let custody = if *CUSTODY { Some(CustodyLog::open()?) } else { None };
+        let auth = SubscriberAuth::new(PATH_SUBSCRIBE.trim_start_matches("/xrpc/"))?;
         Ok(Self {
             listener,
             tls_config,
@@ -143,8 +177,12 @@ impl Server {
             last,
             #[cfg(feature = "labeler")]
             conn,
+            store,
+            custody,
+            auth,
             request_crawl_tx,
             subscribe_repos_tx,
+            admin_tx,
         })
     }
 
@@ -228,16 +266,35 @@ impl Server {
             }
             ("GET", PATH_SUBSCRIBE) => {
                 let mut cursor = None;
+                let mut token = None;
                 for (key, value) in url.query_pairs() {
-                    if key == "cursor" {
-                        cursor = u64::from_str(&value).ok();
+                    match key.as_ref() {
+                        "cursor" => cursor = u64::from_str(&value).ok(),
+                        "token" => token = Some(value),
+                        _ => {}
                     }
                 }
+                #[expect(clippy::unwrap_used)]
+                let mut stream = stream.0.take().unwrap();
+                let permit = match self.auth.authorize(
+                    addr.ip(),
+                    header(parser.headers, "origin"),
+                    header(parser.headers, "authorization"),
+                    token.as_deref(),
+                ) {
+                    Ok(permit) => permit,
+                    Err(err) => {
+                        tracing::info!(%addr, %err, "subscriber rejected");
+                        let body = serde_json::json!({ "message": err.to_string() }).to_string();
+                        return write_response(stream, err.status(), &body);
+                    }
+                };
+                tracing::debug!(%addr, identity = %permit.identity(), "subscriber accepted");
                 self.subscribe_repos_tx.push(SubscribeRepos {
                     addr,
-                    #[expect(clippy::unwrap_used)]
-                    stream: stream.0.take().unwrap(),
+                    stream,
                     cursor: cursor.map(Into::into),
+                    permit,
                 })?;
                 Ok(())
             }
@@ -246,24 +303,190 @@ impl Server {
                     if let Ok(request_crawl) =
                         serde_json::from_reader::<_, RequestCrawl>(&self.buf[offset..len])
                     {
+                        let host = Subject::Host(request_crawl.hostname.clone());
+                        if self.store.is_taken_down(&host)? {
+                            return Err(eyre!("host taken down"));
+                        }
                         self.request_crawl_tx.push(request_crawl)?;
                         #[expect(clippy::unwrap_used)]
-                        let mut stream = stream.0.take().unwrap();
-                        stream.write_all(b"HTTP/1.1 200 OK\n")?;
-                        stream.flush()?;
-                        stream.shutdown()?;
-                        return Ok(());
+                        let stream = stream.0.take().unwrap();
+                        return write_response(stream, "200 OK", "{}");
                     }
                 }
 
                 Err(eyre!("unknown hostname"))
             }
+            ("GET", PATH_METRICS) => {
+                let body = watchdog::metrics();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\n\
+                     Content-Type: text/plain; version=0.0.4\r\n\
+                     Content-Length: {}\r\n\
+                     Connection: close\r\n\
+                     \r\n\
+                     {}",
+                    body.len(),
+                    body
+                );
+
+                #[expect(clippy::unwrap_used)]
+                let mut stream = stream.0.take().unwrap();
+                stream.write_all(response.as_bytes())?;
+                stream.flush()?;
+                stream.shutdown()?;
+                Ok(())
+            }
+            ("GET", PATH_CUSTODY) => {
+                #[expect(clippy::unwrap_used)]
+                let stream = stream.0.take().unwrap();
+                let Some(custody) = &self.custody else {
+                    return write_response(stream, "404 Not Found", "{}");
+                };
+                let mut limit = CUSTODY_LIMIT;
+                let mut after = None;
+                for (key, value) in url.query_pairs() {
+                    match key.as_ref() {
+                        "limit" => {
+                            limit = usize::from_str(&value)
+                                .map_or(limit, |limit| limit.clamp(1, CUSTODY_LIMIT));
+                        }
+                        "cursor" => after = u64::from_str(&value).ok(),
+                        _ => {}
+                    }
+                }
+                let entries = custody.list(after, limit)?;
+                let cursor = entries.last().map(|entry| entry.seq.to_string());
+                let body = serde_json::json!({ "cursor": cursor, "entries": entries }).to_string();
+                write_response(stream, "200 OK", &body)
+            }
+            (
+                "GET" | "POST",
+                PATH_ADMIN_REPO_TAKEDOWN
+                | PATH_ADMIN_REPO_UNTAKEDOWN
+                | PATH_ADMIN_REPO_TAKEDOWNS
+                | PATH_ADMIN_HOST_TAKEDOWN
+                | PATH_ADMIN_HOST_UNTAKEDOWN
+                | PATH_ADMIN_HOST_TAKEDOWNS
+                | PATH_ADMIN_HOST_CURSORS
+                | PATH_ADMIN_AUDIT_LOG
+                | PATH_ADMIN_WORKERS,
+            ) => {
+                let authorization = header(parser.headers, "authorization");
+                #[expect(clippy::unwrap_used)]
+                let stream = stream.0.take().unwrap();
+                if !is_admin(authorization) {
+                    return write_response(stream, "401 Unauthorized", "{}");
+                }
+                let body = match res {
+                    Status::Complete(offset) => self.buf[offset..len].to_vec(),
+                    Status::Partial => Vec::new(),
+                };
+                let method = method.to_owned();
+                self.handle_admin(stream, &method, &url, &body, addr)
+            }
             _ => Err(eyre!("unknown request")),
         }
     }
 
+    fn handle_admin(
+        &mut self, stream: MaybeTlsStream<TcpStream>, method: &str, url: &Url, body: &[u8],
+        addr: SocketAddr,
+    ) -> Result<()> {
+        let addr = addr.to_string();
+        let response = match (method, url.path()) {
+            ("POST", PATH_ADMIN_REPO_TAKEDOWN) => {
+                let req: RepoTakedown = serde_json::from_slice(body)?;
+                self.takedown(Subject::Did(req.did), req.reason.as_deref(), &addr)?
+            }
+            ("POST", PATH_ADMIN_REPO_UNTAKEDOWN) => {
+                let req: RepoTakedown = serde_json::from_slice(body)?;
+                self.untakedown(Subject::Did(req.did), req.reason.as_deref(), &addr)?
+            }
+            ("POST", PATH_ADMIN_HOST_TAKEDOWN) => {
+                let req: HostTakedown = serde_json::from_slice(body)?;
+                self.takedown(Subject::Host(req.host), req.reason.as_deref(), &addr)?
+            }
+            ("POST", PATH_ADMIN_HOST_UNTAKEDOWN) => {
+                let req: HostTakedown = serde_json::from_slice(body)?;
+                self.untakedown(Subject::Host(req.host), req.reason.as_deref(), &addr)?
+            }
+            ("GET", PATH_ADMIN_REPO_TAKEDOWNS) => {
+                serde_json::json!({ "takedowns": self.store.list("did")? }).to_string()
+            }
+            ("GET", PATH_ADMIN_HOST_TAKEDOWNS) => {
+                serde_json::json!({ "takedowns": self.store.list("host")? }).to_string()
+            }
+            ("GET", PATH_ADMIN_HOST_CURSORS) => {
+                let host = url.query_pairs().find(|(key, _)| key == "host").map(|(_, host)| host);
+                serde_json::json!({ "cursors": self.store.cursors(host.as_deref())? }).to_string()
+            }
+            ("GET", PATH_ADMIN_AUDIT_LOG) => {
+                let mut limit = AUDIT_LOG_LIMIT;
+                let mut before = None;
+                for (key, value) in url.query_pairs() {
+                    match key.as_ref() {
+                        "limit" => {
+                            limit = u32::from_str(&value)
+                                .map_or(limit, |limit| limit.clamp(1, AUDIT_LOG_LIMIT));
+                        }
+                        "cursor" => before = u64::from_str(&value).ok(),
+                        _ => {}
+                    }
+                }
+                let entries = self.store.audit_log(limit, before)?;
+                let cursor = entries.last().map(|entry| entry.id.to_string());
+                serde_json::json!({ "cursor": cursor, "entries": entries }).to_string()
+            }
+            ("POST", PATH_ADMIN_WORKERS) => {
+                let req: Workers = serde_json::from_slice(body)?;
+                for (pool, workers, size) in [
+                    ("crawler", &CRAWLER_WORKERS, req.crawlers),
+                    ("publisher", &PUBLISHER_WORKERS, req.publishers),
+                ] {
+                    if let Some(size) = size {
+                        let size = size.clamp(1, WORKERS_MAX);
+                        tracing::info!(%pool, %size, %addr, "admin resize");
+                        workers.store(size, Ordering::Relaxed);
+                    }
+                }
+                workers()
+            }
+            ("GET", PATH_ADMIN_WORKERS) => workers(),
+            _ => return write_response(stream, "405 Method Not Allowed", "{}"),
+        };
+        write_response(stream, "200 OK", &response)
+    }
+
+    fn takedown(&mut self, subject: Subject, reason: Option<&str>, addr: &str) -> Result<String> {
+        let applied = self.store.takedown(&subject, reason, addr)?;
+        tracing::info!(%subject, ?reason, %addr, %applied, "admin takedown");
+        if applied {
+            self.admin_tx.push(AdminAction::Takedown(subject))?;
+        }
+        Ok(serde_json::json!({ "applied": applied }).to_string())
+    }
+
+    fn untakedown(&mut self, subject: Subject, reason: Option<&str>, addr: &str) -> Result<String> {
+        let applied = self.store.untakedown(&subject, reason, addr)?;
+        tracing::info!(%subject, ?reason, %addr, %applied, "admin untakedown");
+        if applied {
+            self.admin_tx.push(AdminAction::Untakedown(subject))?;
+        }
+        Ok(serde_json::json!({ "applied": applied }).to_string())
+    }
+
     #[cfg(not(feature = "labeler"))]
     fn query_hosts(&mut self) -> Result<()> {
+        // in federation mode, relay the upstream relays instead of crawling every pds
+        if !UPSTREAMS.is_empty() {
+            for hostname in UPSTREAMS.iter() {
+                if !self.store.is_taken_down(&Subject::Host(hostname.clone()))? {
+                    self.request_crawl_tx
+                        .push(RequestCrawl { hostname: hostname.clone(), cursor: None })?;
+                }
+            }
+            return Ok(());
+        }
         let client = reqwest::blocking::Client::builder()
             .user_agent("rsky-relay")
             .https_only(true)
@@ -281,6 +504,7 @@ impl Server {
             for host in hosts.hosts.into_iter().rev() {
                 if host.account_count > HOSTS_MIN_ACCOUNTS
                     && matches!(host.status, HostStatus::Active | HostStatus::Idle)
+                    && !self.store.is_taken_down(&Subject::Host(host.hostname.clone()))?
                 {
                     self.request_crawl_tx
                         .push(RequestCrawl { hostname: host.hostname, cursor: None })?;
@@ -300,6 +524,9 @@ impl Server {
             self.conn.prepare_cached("SELECT DISTINCT labeler_endpoint FROM plc_labelers")?;
         for res in stmt.query_map([], |row| row.get::<_, String>(0))? {
             if let Some(hostname) = res?.strip_prefix("https://").map(|x| x.trim_end_matches('/')) {
+                if self.store.is_taken_down(&Subject::Host(hostname.to_owned()))? {
+                    continue;
+                }
                 self.request_crawl_tx
                     .push(RequestCrawl { hostname: hostname.to_owned(), cursor: None })?;
             }
@@ -308,3 +535,42 @@ impl Server {
         Ok(())
     }
 }
+
+fn workers() -> String {
+    serde_json::json!({
+        "crawlers": CRAWLER_WORKERS.load(Ordering::Relaxed),
+        "publishers": PUBLISHER_WORKERS.load(Ordering::Relaxed),
+    })
+    .to_string()
+}
+
+fn header<'a>(headers: &[httparse::Header<'a>], name: &str) -> Option<&'a [u8]> {
+    headers.iter().find(|header| header.name.eq_ignore_ascii_case(name)).map(|header| header.value)
+}
+
+fn is_admin(authorization: Option<&[u8]>) -> bool {
+    let (Some(password), Some(authorization)) = (ADMIN_PASSWORD.as_deref(), authorization) else {
+        return false;
+    };
+    authorization
+        .strip_prefix(b"Bearer ")
+        .is_some_and(|token| bool::from(token.ct_eq(password.as_bytes())))
+}
+
+fn write_response(mut stream: MaybeTlsStream<TcpStream>, status: &str, body: &str) -> Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\n\
+         Content-Type: application/json; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes())?;
+    stream.flush()?;
+    stream.shutdown()?;
+    Ok(())
+}
@@ -24,3 +24,22 @@ pub enum HostStatus {
     Throttled,
     Banned,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct RepoTakedown {
+    pub did: String,
+    pub reason: Option<String>,
+}
+
+/// Worker pool sizes to scale to; pools left out keep their size.
+#[derive(Debug, Deserialize)]
+pub struct Workers {
+    pub crawlers: Option<usize>,
+    pub publishers: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HostTakedown {
+    pub host: String,
+    pub reason: Option<String>,
+}
@@ -1,38 +1,49 @@
 use std::fmt;
 use std::ops::{Add, Sub};
+use std::path::Path;
 use std::sync::LazyLock;
 
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use fjall::compaction::{Fifo, Strategy};
 use fjall::{Keyspace, PartitionCreateOptions, Slice};
 use thingbuf::{Recycle, mpsc};
 
 use crate::config::{
-    BLOCK_SIZE, CACHE_SIZE, DISK_SIZE, FSYNC_MS, MEMTABLE_SIZE, TTL_SECONDS, WRITE_BUFFER_SIZE,
+    BLOCK_SIZE, CACHE_SIZE, DB_PATH, FSYNC_MS, MEMTABLE_SIZE, TTL_SECONDS, WRITE_BUFFER_SIZE,
 };
+use crate::watchdog::firehose_disk_size;
 
 pub type MessageSender = mpsc::blocking::Sender<Message, MessageRecycle>;
 pub type MessageReceiver = mpsc::blocking::Receiver<Message, MessageRecycle>;
 
 #[expect(clippy::unwrap_used)]
 pub static DB: LazyLock<Keyspace> = LazyLock::new(|| {
-    let db = fjall::Config::new("db")
+    #[cfg(not(test))]
+    let path = Path::new(DB_PATH).to_owned();
+    // tests share one throwaway keyspace per process
+    #[cfg(test)]
+    let path = std::env::temp_dir().join(format!("rsky-relay-test-{}", std::process::id()));
+    let config = fjall::Config::new(&path);
+    #[cfg(test)]
+    let config = config.temporary(true);
+    let db = config
         .cache_size(CACHE_SIZE)
         .max_write_buffer_size(WRITE_BUFFER_SIZE)
         .fsync_ms(FSYNC_MS)
         .open()
         .unwrap();
-    db.open_partition("firehose", firehose_options()).unwrap();
+    db.open_partition("firehose", firehose_options(&path)).unwrap();
     db.open_partition("queue", PartitionCreateOptions::default()).unwrap();
     #[cfg(not(feature = "labeler"))]
     db.open_partition("repos", PartitionCreateOptions::default()).unwrap();
     db
 });
 
-fn firehose_options() -> PartitionCreateOptions {
+fn firehose_options(path: &Path) -> PartitionCreateOptions {
     PartitionCreateOptions::default()
         .manual_journal_persist(true)
-        .compaction_strategy(Strategy::Fifo(Fifo::new(DISK_SIZE, TTL_SECONDS)))
+        .compaction_strategy(Strategy::Fifo(Fifo::new(firehose_disk_size(path), TTL_SECONDS)))
         .max_memtable_size(MEMTABLE_SIZE)
         .block_size(BLOCK_SIZE)
 }
@@ -41,6 +52,7 @@ fn firehose_options() -> PartitionCreateOptions {
 pub struct Message {
     pub data: Bytes,
     pub hostname: String,
+    pub received_at: DateTime<Utc>,
 }
 
 #[derive(Debug)]
@@ -48,7 +60,7 @@ pub struct MessageRecycle;
 
 impl Recycle<Message> for MessageRecycle {
     fn new_element(&self) -> Message {
-        Message { data: Bytes::new(), hostname: String::new() }
+        Message { data: Bytes::new(), hostname: String::new(), received_at: DateTime::UNIX_EPOCH }
     }
 
     fn recycle(&self, _: &mut Message) {}
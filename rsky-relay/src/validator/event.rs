@@ -10,6 +10,7 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use vec1::Vec1;
 
+use rsky_common::cbor::CanonicalCborError;
 use rsky_common::tid::TID;
 
 use crate::types::Cursor;
@@ -31,6 +32,8 @@ pub enum ParseError {
     MissingRoot(rs_car_sync::Cid),
     #[error("commit error: {0}")]
     Commit(#[from] serde_ipld_dagcbor::DecodeError<Infallible>),
+    #[error("non-canonical block {0}: {1}")]
+    NonCanonical(Cid, CanonicalCborError),
     #[error("unknown type: {0}")]
     UnknownType(String),
 }
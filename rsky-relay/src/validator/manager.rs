@@ -1,21 +1,34 @@
+use std::collections::TryReserveError;
 use std::convert::Infallible;
+use std::num::NonZeroUsize;
 use std::sync::atomic::Ordering;
 use std::thread;
 use std::time::{Duration, Instant, SystemTimeError};
 
 use chrono::{DateTime, Utc};
-use fjall::{Batch, PartitionCreateOptions, PartitionHandle, PersistMode};
+use fjall::{PartitionCreateOptions, PartitionHandle, PersistMode};
+#[cfg(not(feature = "labeler"))]
 use hashbrown::HashMap;
+use hashbrown::HashSet;
 #[cfg(not(feature = "labeler"))]
 use hashbrown::hash_map::Entry;
+use lru::LruCache;
+#[cfg(not(feature = "labeler"))]
+use rsky_common::tid::TID;
 use rusqlite::Connection;
 use thiserror::Error;
 
 use crate::SHUTDOWN;
-use crate::config::HOSTS_WRITE_INTERVAL;
+use crate::admin::{AdminAction, AdminActionReceiver, Store, StoreError, Subject, Takedowns};
+use crate::checkpoint::{Checkpoint, CheckpointError, HostCursors};
+use crate::config::{CAPACITY_RECENT, CUSTODY, HOSTS_WRITE_INTERVAL, UPSTREAMS};
+use crate::custody::{CustodyError, CustodyLog};
 use crate::types::{Cursor, DB, MessageReceiver};
+#[cfg(not(feature = "labeler"))]
+use crate::validator::event::{AccountStatus, SubscribeReposAccount};
 use crate::validator::event::{ParseError, SerializeError, SubscribeReposEvent};
 use crate::validator::resolver::{Resolver, ResolverError};
+use crate::validator::sampler::Sampler;
 #[cfg(not(feature = "labeler"))]
 use crate::validator::types::RepoState;
 use crate::validator::utils;
@@ -36,31 +49,61 @@ pub enum ManagerError {
     Sqlite(#[from] rusqlite::Error),
     #[error("fjall error: {0}")]
     Fjall(#[from] fjall::Error),
+    #[error("store error: {0}")]
+    Store(#[from] StoreError),
+    #[error("custody error: {0}")]
+    Custody(#[from] CustodyError),
+    #[error("checkpoint error: {0}")]
+    Checkpoint(#[from] CheckpointError),
     #[error("decode error: {0}")]
     DecodeError(#[from] serde_ipld_dagcbor::DecodeError<Infallible>),
+    #[error("encode error: {0}")]
+    EncodeError(#[from] serde_ipld_dagcbor::EncodeError<TryReserveError>),
 }
 
 pub struct Manager {
     message_rx: MessageReceiver,
-    hosts: HashMap<String, (Cursor, DateTime<Utc>)>,
+    admin_rx: AdminActionReceiver,
+    takedowns: Takedowns,
+    hosts: HostCursors,
     #[cfg(not(feature = "labeler"))]
     repos: HashMap<String, RepoState>,
+    #[cfg(not(feature = "labeler"))]
+    repos_tree: PartitionHandle,
     resolver: Resolver,
     last: Instant,
     conn: Connection,
     queue: PartitionHandle,
     firehose: PartitionHandle,
+    checkpoint: Checkpoint,
+    custody: Option<CustodyLog>,
+    sampler: Option<Sampler>,
+    upstreams: HashSet<String>,
+    // `#identity`/`#account` events already taken from an upstream relay
+    recent: LruCache<(String, &'static str, DateTime<Utc>), ()>,
 }
 
 impl Manager {
-    pub fn new(message_rx: MessageReceiver) -> Result<Self, ManagerError> {
-        let hosts = HashMap::new();
+    pub fn new(
+        message_rx: MessageReceiver, admin_rx: AdminActionReceiver,
+    ) -> Result<Self, ManagerError> {
+        let conn = Connection::open("relay.db")?;
+        let resolver = Resolver::new()?;
+        let mut manager =
+            Self::with_state(message_rx, admin_rx, conn, resolver, *CUSTODY, &UPSTREAMS)?;
+        manager.sampler = Sampler::from_env();
+        Ok(manager)
+    }
+
+    pub(crate) fn with_state(
+        message_rx: MessageReceiver, admin_rx: AdminActionReceiver, conn: Connection,
+        resolver: Resolver, custody: bool, upstreams: &[String],
+    ) -> Result<Self, ManagerError> {
+        let hosts = HostCursors::default();
         #[cfg(not(feature = "labeler"))]
         let repos = HashMap::new();
-        let resolver = Resolver::new()?;
         let now = Instant::now();
         let last = now.checked_sub(HOSTS_WRITE_INTERVAL).unwrap_or(now);
-        let conn = Connection::open("relay.db")?;
         conn.execute(
             "CREATE TABLE IF NOT EXISTS hosts (
                 host TEXT PRIMARY KEY,
@@ -69,23 +112,39 @@ impl Manager {
             )",
             (),
         )?;
+        Store::init(&conn)?;
+        let takedowns = Store::load(&conn)?;
         let queue = DB.open_partition("queue", PartitionCreateOptions::default())?;
         let firehose = DB.open_partition("firehose", PartitionCreateOptions::default())?;
+        #[cfg(not(feature = "labeler"))]
+        let repos_tree = DB.open_partition("repos", PartitionCreateOptions::default())?;
+        let checkpoint = Checkpoint::open()?;
+        let custody = if custody { Some(CustodyLog::open()?) } else { None };
+        #[expect(clippy::unwrap_used)]
+        let recent = LruCache::new(NonZeroUsize::new(CAPACITY_RECENT).unwrap());
         Ok(Self {
             message_rx,
+            admin_rx,
+            takedowns,
             hosts,
             #[cfg(not(feature = "labeler"))]
             repos,
+            #[cfg(not(feature = "labeler"))]
+            repos_tree,
             resolver,
             last,
             conn,
             queue,
             firehose,
+            checkpoint,
+            custody,
+            sampler: None,
+            upstreams: upstreams.iter().cloned().collect(),
+            recent,
         })
     }
 
     pub async fn run(mut self) -> Result<(), ManagerError> {
-        let mut hosts = 0;
         {
             let mut stmt = self.conn.prepare_cached("SELECT host, cursor FROM hosts")?;
             let mut rows = stmt.query(())?;
@@ -93,17 +152,21 @@ impl Manager {
                 let host = row.get_unwrap("host");
                 let cursor: u64 = row.get_unwrap("cursor");
                 self.hosts.insert(host, (cursor.into(), DateTime::UNIX_EPOCH));
-                hosts += 1;
             }
         }
+        // sqlite is written apart from the firehose, so the checkpoint wins where it has a cursor
+        for res in self.checkpoint.hosts() {
+            let (host, cursor) = res?;
+            self.hosts.insert(host, (cursor, DateTime::UNIX_EPOCH));
+        }
+        let hosts = self.hosts.iter().count();
         #[allow(unused_mut)]
         let mut repos = 0;
         #[cfg(not(feature = "labeler"))]
         {
             // TODO: move this to sqlite
-            let handle = DB.open_partition("repos", PartitionCreateOptions::default())?;
-            self.repos.reserve(handle.approximate_len());
-            for res in handle.iter() {
+            self.repos.reserve(self.repos_tree.approximate_len());
+            for res in self.repos_tree.iter() {
                 let (did, state) = res?;
                 #[expect(clippy::unwrap_used)]
                 let did = String::from_utf8(did.to_vec()).unwrap();
@@ -113,7 +176,8 @@ impl Manager {
             }
         }
 
-        let mut cursor = self.firehose.last_key_value()?.map(|(k, _)| k.into()).unwrap_or_default();
+        let last = self.firehose.last_key_value()?.map(|(k, _)| k.into()).unwrap_or_default();
+        let mut cursor = self.checkpoint.resume(last);
         let mut queue_drained = 0;
         let mut queue_pending = 0;
         for res in self.queue.keys() {
@@ -129,6 +193,7 @@ impl Manager {
                 queue_pending += 1;
             }
         }
+        self.checkpoint.commit(&mut self.hosts)?;
 
         tracing::info!(%hosts, %repos, %queue_drained, %queue_pending, %cursor, "loaded state");
         while self.update(&mut cursor).await? {}
@@ -148,7 +213,7 @@ impl Manager {
                 DO UPDATE SET cursor = excluded.cursor, latest = excluded.latest
             ",
         )?;
-        for (host, (cursor, time)) in &self.hosts {
+        for (host, (cursor, time)) in self.hosts.iter() {
             if *time != DateTime::UNIX_EPOCH {
                 stmt.execute((host, cursor.get(), time))?;
             }
@@ -159,8 +224,13 @@ impl Manager {
         Ok(())
     }
 
+    #[cfg(all(test, not(feature = "labeler")))]
+    pub(crate) const fn resolver(&mut self) -> &mut Resolver {
+        &mut self.resolver
+    }
+
     #[expect(clippy::too_many_lines)]
-    async fn update(&mut self, cursor: &mut Cursor) -> Result<bool, ManagerError> {
+    pub(crate) async fn update(&mut self, cursor: &mut Cursor) -> Result<bool, ManagerError> {
         if SHUTDOWN.load(Ordering::Relaxed) {
             return Ok(false);
         }
@@ -171,6 +241,17 @@ impl Manager {
             self.last = now;
         }
 
+        while let Ok(action) = self.admin_rx.pop() {
+            let Some((subject, active)) = self.handle_admin(action) else {
+                continue;
+            };
+            tracing::info!(%subject, %active, "applied admin action");
+            #[cfg(not(feature = "labeler"))]
+            if let Subject::Did(did) = subject {
+                self.announce_account(cursor, did, active)?;
+            }
+        }
+
         for _ in 0..1024 {
             let msg = match self.message_rx.try_recv_ref() {
                 Ok(msg) => msg,
@@ -183,6 +264,10 @@ impl Manager {
             };
 
             let host = &msg.hostname;
+            if self.takedowns.is_host_taken_down(host) {
+                continue;
+            }
+            let upstream = self.upstreams.contains(host);
             let span = tracing::info_span!("msg_recv", %host, len = %msg.data.len());
             let _enter = span.enter();
             let event = match SubscribeReposEvent::parse(&msg.data) {
@@ -201,6 +286,11 @@ impl Manager {
             let did = event.did();
             let span = tracing::debug_span!("msg_data", type = %type_, %seq, %time, %did);
             let _enter = span.enter();
+            if self.takedowns.is_did_taken_down(did) {
+                tracing::trace!("repo taken down");
+                self.hosts.insert(host.clone(), (seq, time));
+                continue;
+            }
             if let Some((prev, old)) = self.hosts.get(host) {
                 time = time.max(*old);
                 let prev: u64 = (*prev).into();
@@ -238,11 +328,32 @@ impl Manager {
                     (commit, head)
                 }
                 Ok(None) => {
+                    // every upstream relays the same events, so keep only the first copy
+                    if upstream
+                        && self.recent.put((did.to_owned(), type_, event.time()), ()).is_some()
+                    {
+                        tracing::trace!("already relayed");
+                        self.hosts.insert(host.clone(), (seq, time));
+                        continue;
+                    }
                     if let SubscribeReposEvent::Identity(_) = &event {
                         self.resolver.expire(did, event.time());
                     }
-                    let data = event.serialize(msg.data.len(), cursor.next())?;
-                    self.firehose.insert(*cursor, data)?;
+                    let next = self.checkpoint.next(cursor)?;
+                    if let Some(sampler) = &mut self.sampler {
+                        sampler.offer(next, host, &event);
+                    }
+                    let data = event.serialize(msg.data.len(), next)?;
+                    self.checkpoint.insert(&self.firehose, *cursor, data);
+                    if let Some(custody) = &self.custody {
+                        custody.record(
+                            &mut self.checkpoint,
+                            *cursor,
+                            host,
+                            seq,
+                            Some(msg.received_at),
+                        )?;
+                    }
                     self.hosts.insert(host.clone(), (seq, time));
                     continue;
                 }
@@ -252,18 +363,39 @@ impl Manager {
                 }
             };
 
+            #[cfg(not(feature = "labeler"))]
+            if upstream && self.is_relayed(did, &commit.rev) {
+                tracing::trace!("already relayed");
+                self.hosts.insert(host.clone(), (seq, time));
+                continue;
+            }
+
             // resolve identity & check pds
             let Some((pds, key)) = self.resolver.resolve(did)? else {
-                self.queue.insert(format!("{did}>{host}>{seq}"), msg.data.to_vec())?;
+                self.checkpoint.insert(
+                    &self.queue,
+                    format!("{did}>{host}>{seq}"),
+                    msg.data.to_vec(),
+                );
                 self.hosts.insert(host.clone(), (seq, time));
                 continue;
             };
 
             if let Some(pds) = pds {
-                if host != pds {
+                if upstream {
+                    // an upstream relays for every pds, so apply their takedowns here
+                    if self.takedowns.is_host_taken_down(pds) {
+                        self.hosts.insert(host.clone(), (seq, time));
+                        continue;
+                    }
+                } else if host != pds {
                     // expire the identity & queue message in case the user has migrated
                     self.resolver.expire(did, time);
-                    self.queue.insert(format!("{did}>{host}>{seq}"), msg.data.to_vec())?;
+                    self.checkpoint.insert(
+                        &self.queue,
+                        format!("{did}>{host}>{seq}"),
+                        msg.data.to_vec(),
+                    );
                     self.hosts.insert(host.clone(), (seq, time));
                     continue;
                 }
@@ -300,12 +432,24 @@ impl Manager {
                 }
             }
 
-            let msg = event.serialize(msg.data.len(), cursor.next())?;
-            self.firehose.insert(*cursor, msg)?;
+            let next = self.checkpoint.next(cursor)?;
+            if let Some(sampler) = &mut self.sampler {
+                sampler.offer(next, host, &event);
+            }
+            let frame = event.serialize(msg.data.len(), next)?;
+            self.checkpoint.insert(&self.firehose, *cursor, frame);
+            if let Some(custody) = &self.custody {
+                custody.record(&mut self.checkpoint, *cursor, host, seq, Some(msg.received_at))?;
+            }
             #[cfg(not(feature = "labeler"))]
-            entry.insert(RepoState { rev, data, head });
+            {
+                let state = entry.insert(RepoState { rev, data, head });
+                let value = serde_ipld_dagcbor::to_vec(state.get())?;
+                self.checkpoint.insert(&self.repos_tree, state.key().clone(), value);
+            }
             self.hosts.insert(host.clone(), (seq, time));
         }
+        self.checkpoint.commit(&mut self.hosts)?;
 
         for did in self.resolver.poll().await? {
             self.scan_did(cursor, &did)?;
@@ -316,14 +460,17 @@ impl Manager {
 
     fn scan_did(&mut self, cursor: &mut Cursor, did: &str) -> Result<(), ManagerError> {
         let Some((pds, key)) = self.resolver.resolve(did)? else { unreachable!("{did}") };
+        let taken_down = self.takedowns.is_did_taken_down(did);
 
-        let mut batch: Option<Batch> = None;
         for res in self.queue.prefix(&did) {
             let (k, input) = res?;
-            batch.get_or_insert_with(|| DB.batch()).remove(&self.queue, k.clone());
+            self.checkpoint.remove(&self.queue, k.clone());
 
             #[expect(clippy::unwrap_used)]
             let host = std::str::from_utf8(&k).unwrap().split('>').nth(1).unwrap();
+            if taken_down || self.takedowns.is_host_taken_down(host) {
+                continue;
+            }
             let span = tracing::debug_span!("msg_read", %host, len = %input.len());
             let _enter = span.enter();
 
@@ -346,7 +493,16 @@ impl Manager {
             let span = tracing::debug_span!("validate", n_labels = commit.len());
             let _enter = span.enter();
 
-            if let Some(pds) = pds {
+            if self.upstreams.contains(host) {
+                if self.takedowns.is_host_taken_down(pds.unwrap_or_default()) {
+                    continue;
+                }
+                #[cfg(not(feature = "labeler"))]
+                if self.is_relayed(did, &commit.rev) {
+                    tracing::trace!("already relayed");
+                    continue;
+                }
+            } else if let Some(pds) = pds {
                 if host != pds {
                     tracing::debug!(%pds, "hostname pds mismatch");
                     continue;
@@ -384,15 +540,60 @@ impl Manager {
                 }
             }
 
-            let msg = event.serialize(input.len(), cursor.next())?;
-            self.firehose.insert(*cursor, msg)?;
+            let next = self.checkpoint.next(cursor)?;
+            if let Some(sampler) = &mut self.sampler {
+                sampler.offer(next, host, &event);
+            }
+            let msg = event.serialize(input.len(), next)?;
+            self.checkpoint.insert(&self.firehose, *cursor, msg);
+            if let Some(custody) = &self.custody {
+                custody.record(&mut self.checkpoint, *cursor, host, seq, None)?;
+            }
             #[cfg(not(feature = "labeler"))]
-            entry.insert(RepoState { rev, data, head });
+            {
+                let state = entry.insert(RepoState { rev, data, head });
+                let value = serde_ipld_dagcbor::to_vec(state.get())?;
+                self.checkpoint.insert(&self.repos_tree, state.key().clone(), value);
+            }
         }
-        if let Some(batch) = batch {
-            batch.commit()?;
+        self.checkpoint.commit(&mut self.hosts)?;
+
+        Ok(())
+    }
+
+    /// Whether a commit at `rev` or later for `did` is already on the firehose.
+    #[cfg(not(feature = "labeler"))]
+    fn is_relayed(&self, did: &str, rev: &TID) -> bool {
+        self.repos.get(did).is_some_and(|prev| !prev.rev.older_than(rev))
+    }
+
+    /// Apply an admin action, returning the subject and its new status if it changed anything.
+    fn handle_admin(&mut self, action: AdminAction) -> Option<(Subject, bool)> {
+        match action {
+            AdminAction::Takedown(subject) => {
+                self.takedowns.insert(subject.clone()).then_some((subject, false))
+            }
+            AdminAction::Untakedown(subject) => {
+                self.takedowns.remove(&subject).then_some((subject, true))
+            }
         }
+    }
 
+    /// Announce a relay-level account status change downstream.
+    #[cfg(not(feature = "labeler"))]
+    fn announce_account(
+        &mut self, cursor: &mut Cursor, did: String, active: bool,
+    ) -> Result<(), ManagerError> {
+        let next = self.checkpoint.next(cursor)?;
+        let event = SubscribeReposEvent::Account(SubscribeReposAccount {
+            seq: next.get(),
+            did,
+            time: Utc::now(),
+            active,
+            status: (!active).then_some(AccountStatus::Takendown),
+        });
+        let data = event.serialize(0, next)?;
+        self.checkpoint.insert(&self.firehose, *cursor, data);
         Ok(())
     }
 }
@@ -401,31 +602,15 @@ impl Drop for Manager {
     fn drop(&mut self) {
         SHUTDOWN.store(true, Ordering::Relaxed);
 
-        if let Err(err) = self.persist() {
-            tracing::warn!(%err, "unable to persist host state\n{:#?}", self.hosts);
+        if let Err(err) = self.checkpoint.commit(&mut self.hosts) {
+            tracing::warn!(%err, "unable to commit checkpoint");
+        }
+        if let Err(err) = self.checkpoint.release() {
+            tracing::warn!(%err, "unable to release reserved seqs");
         }
 
-        #[cfg(not(feature = "labeler"))]
-        match DB.open_partition("repos", PartitionCreateOptions::default()) {
-            Ok(repos) => {
-                let len = self.repos.len();
-                let mut batch = Batch::with_capacity(DB.clone(), len);
-                for (did, state) in self.repos.drain() {
-                    #[expect(clippy::unwrap_used)]
-                    batch.insert(
-                        &repos,
-                        did.into_bytes(),
-                        serde_ipld_dagcbor::to_vec(&state).unwrap(),
-                    );
-                }
-                tracing::info!(%len, "persisting repos");
-                if let Err(err) = batch.commit() {
-                    tracing::warn!(%err, "unable to persist repo state");
-                }
-            }
-            Err(err) => {
-                tracing::warn!(%err, "unable to open repos tree");
-            }
+        if let Err(err) = self.persist() {
+            tracing::warn!(%err, "unable to persist host state\n{:#?}", self.hosts);
         }
 
         if let Err(err) = DB.persist(PersistMode::SyncAll) {
@@ -1,8 +1,18 @@
 mod event;
 mod manager;
 mod resolver;
+mod sampler;
 #[cfg(not(feature = "labeler"))]
 mod types;
 mod utils;
 
+pub(crate) use event::{DidKey, SubscribeReposEvent};
+#[cfg(all(test, not(feature = "labeler")))]
+pub(crate) use event::{
+    SubscribeReposAccount, SubscribeReposCommit, SubscribeReposCommitOperation,
+    SubscribeReposIdentity,
+};
 pub use manager::{Manager, ManagerError};
+#[cfg(all(test, not(feature = "labeler")))]
+pub(crate) use resolver::Resolver;
+pub(crate) use utils::verify_signature;
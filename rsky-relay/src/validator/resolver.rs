@@ -50,6 +50,7 @@ pub enum ResolverError {
 pub struct Resolver {
     cache: LruCache<String, (DidEndpoint, DidKey)>,
     conn: Connection,
+    export: bool,
     last: Instant,
     after: Option<String>,
     client: Client,
@@ -59,8 +60,6 @@ pub struct Resolver {
 
 impl Resolver {
     pub fn new() -> Result<Self, ResolverError> {
-        #[expect(clippy::unwrap_used)]
-        let cache = LruCache::new(NonZeroUsize::new(CAPACITY_CACHE).unwrap());
         let flag = if *DO_PLC_EXPORT {
             OpenFlags::SQLITE_OPEN_READ_WRITE
         } else {
@@ -79,13 +78,51 @@ impl Resolver {
             conn.execute("PRAGMA incremental_vacuum", [])?;
             conn.execute("PRAGMA optimize = 0x10002", [])?;
         }
+        Self::with_connection(conn, *DO_PLC_EXPORT)
+    }
+
+    /// A resolver backed by an in-memory plc directory that never reaches out to plc.directory.
+    #[cfg(all(test, not(feature = "labeler")))]
+    pub fn in_memory() -> Result<Self, ResolverError> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute(
+            "CREATE TABLE plc_operations (
+                cid TEXT NOT NULL PRIMARY KEY,
+                did TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                nullified BOOLEAN NOT NULL,
+                operation BLOB NOT NULL
+            )",
+            (),
+        )?;
+        conn.execute(
+            "CREATE TABLE plc_keys (
+                did TEXT PRIMARY KEY,
+                created_at TEXT NOT NULL,
+                pds_endpoint TEXT,
+                pds_key TEXT,
+                labeler_endpoint TEXT,
+                labeler_key TEXT
+            )",
+            (),
+        )?;
+        Self::with_connection(conn, false)
+    }
+
+    fn with_connection(conn: Connection, export: bool) -> Result<Self, ResolverError> {
+        #[expect(clippy::unwrap_used)]
+        let cache = LruCache::new(NonZeroUsize::new(CAPACITY_CACHE).unwrap());
         let now = Instant::now();
         let last = now.checked_sub(PLC_EXPORT_INTERVAL).unwrap_or(now);
-        let after = conn.query_one(
+        let after = match conn.query_one(
             "SELECT created_at FROM plc_operations ORDER BY created_at DESC LIMIT 1",
             [],
             |row| Ok(Some(row.get("created_at")?)),
-        )?;
+        ) {
+            Ok(after) => after,
+            Err(rusqlite::Error::QueryReturnedNoRows) => None,
+            Err(err) => Err(err)?,
+        };
         let client = Client::builder()
             .user_agent("rsky-relay")
             .timeout(REQ_TIMEOUT)
@@ -94,7 +131,19 @@ impl Resolver {
             .build()?;
         let inflight = HashSet::new();
         let futures = FuturesUnordered::new();
-        Ok(Self { cache, conn, last, after, client, inflight, futures })
+        Ok(Self { cache, conn, export, last, after, client, inflight, futures })
+    }
+
+    /// Publish `did`'s pds endpoint and `did:key` as if it had come from a plc export.
+    #[cfg(all(test, not(feature = "labeler")))]
+    pub fn insert(&mut self, did: &str, endpoint: &str, key: &str) -> Result<(), ResolverError> {
+        self.cache.pop(did);
+        self.conn.execute(
+            "INSERT OR REPLACE INTO plc_keys (did, created_at, pds_endpoint, pds_key, labeler_endpoint, labeler_key)
+             VALUES (?1, ?2, ?3, ?4, ?3, ?4)",
+            (did, Utc::now().to_rfc3339(), endpoint, key),
+        )?;
+        Ok(())
     }
 
     pub fn expire(&mut self, did: &str, time: DateTime<Utc>) {
@@ -147,7 +196,7 @@ impl Resolver {
     pub fn request(&mut self, did: &str) {
         self.inflight.insert(did.to_owned());
         if let Some(plc) = did.strip_prefix("did:plc:") {
-            let plc = if *DO_PLC_EXPORT { None } else { Some(plc) };
+            let plc = if self.export { None } else { Some(plc) };
             self.send_req(None, plc);
         } else if let Some(web) = did.strip_prefix("did:web:") {
             let Ok(web) = urlencoding::decode(web) else {
@@ -240,7 +289,7 @@ impl Resolver {
                     tracing::debug!(%err, "fetch error");
                 }
             }
-        } else if *DO_PLC_EXPORT && self.last.elapsed() > PLC_EXPORT_INTERVAL {
+        } else if self.export && self.last.elapsed() > PLC_EXPORT_INTERVAL {
             self.send_req(None, None);
         }
         Ok(Vec::new())
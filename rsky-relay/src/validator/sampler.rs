@@ -0,0 +1,206 @@
+//! Analytics sampling. With `RELAY_SAMPLE_RATE` and `RELAY_SAMPLE_OUTPUT` set, a fraction of
+//! the validated events is written as JSON lines to a file, or to a `tcp://` or `unix://`
+//! socket, for pipelines that don't need the whole firehose. Feed Kafka and the like through a
+//! forwarder listening on the socket.
+//!
+//! Events are picked by hashing their relay seq, so the sample is spread evenly over hosts and
+//! time. The validator only pushes a small summary onto a bounded queue; encoding and writing
+//! happen on their own thread, and when the sink falls behind or goes away samples are dropped
+//! rather than holding up the firehose.
+
+use std::fs::OpenOptions;
+use std::io::{self, BufWriter, Write};
+use std::net::TcpStream;
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::SHUTDOWN;
+use crate::config::{CAPACITY_SAMPLES, SAMPLE_OUTPUT, SAMPLE_RATE, SAMPLE_RECONNECT_INTERVAL};
+use crate::types::Cursor;
+use crate::validator::event::{SubscribeReposCommitOperation, SubscribeReposEvent};
+
+const SLEEP: Duration = Duration::from_millis(10);
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sample {
+    /// Relay seq of the event.
+    pub seq: u64,
+    #[serde(rename = "type")]
+    pub type_: &'static str,
+    pub did: String,
+    pub time: DateTime<Utc>,
+    /// Host the event was crawled from.
+    pub host: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ops: Vec<SampleOp>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SampleOp {
+    pub action: &'static str,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cid: Option<String>,
+}
+
+impl Sample {
+    fn new(seq: Cursor, host: &str, event: &SubscribeReposEvent) -> Self {
+        let (rev, ops) = match event {
+            SubscribeReposEvent::Commit(commit) => {
+                let ops = commit
+                    .ops
+                    .iter()
+                    .map(|op| match op {
+                        SubscribeReposCommitOperation::Create { path, cid } => SampleOp {
+                            action: "create",
+                            path: path.clone(),
+                            cid: Some(cid.to_string()),
+                        },
+                        SubscribeReposCommitOperation::Update { path, cid, .. } => SampleOp {
+                            action: "update",
+                            path: path.clone(),
+                            cid: Some(cid.to_string()),
+                        },
+                        SubscribeReposCommitOperation::Delete { path, .. } => {
+                            SampleOp { action: "delete", path: path.clone(), cid: None }
+                        }
+                    })
+                    .collect();
+                (Some(commit.rev.0.clone()), ops)
+            }
+            SubscribeReposEvent::Sync(sync) => (Some(sync.rev.0.clone()), Vec::new()),
+            _ => (None, Vec::new()),
+        };
+        Self {
+            seq: seq.get(),
+            type_: event.type_().trim_start_matches('#'),
+            did: event.did().to_owned(),
+            time: event.time(),
+            host: host.to_owned(),
+            rev,
+            ops,
+        }
+    }
+}
+
+pub struct Sampler {
+    threshold: u64,
+    tx: rtrb::Producer<Sample>,
+}
+
+impl Sampler {
+    /// Starts the sampler configured by the environment, if any.
+    pub fn from_env() -> Option<Self> {
+        let output = SAMPLE_OUTPUT.as_ref()?;
+        (*SAMPLE_RATE > 0.0).then(|| Self::spawn(*SAMPLE_RATE, output.clone()))
+    }
+
+    pub fn spawn(rate: f64, output: String) -> Self {
+        let (tx, rx) = rtrb::RingBuffer::new(CAPACITY_SAMPLES);
+        let spawned = thread::Builder::new()
+            .name("rsky-sample".into())
+            .spawn(move || write_loop(&output, rx));
+        if let Err(err) = spawned {
+            tracing::warn!(%err, "unable to start sampler");
+        }
+        Self { threshold: threshold(rate), tx }
+    }
+
+    /// Queues `event`, about to go out as relay seq `seq`, if it falls in the sample.
+    pub fn offer(&mut self, seq: Cursor, host: &str, event: &SubscribeReposEvent) {
+        if !picked(seq, self.threshold) {
+            return;
+        }
+        if self.tx.push(Sample::new(seq, host, event)).is_err() {
+            tracing::trace!(%seq, "sample queue full");
+        }
+    }
+}
+
+#[expect(clippy::cast_possible_truncation, clippy::cast_precision_loss, clippy::cast_sign_loss)]
+fn threshold(rate: f64) -> u64 {
+    if rate >= 1.0 { u64::MAX } else { (rate.max(0.0) * u64::MAX as f64) as u64 }
+}
+
+/// splitmix64 of `seq`, so neighbouring seqs land far apart.
+const fn picked(seq: Cursor, threshold: u64) -> bool {
+    let mut z = seq.get().wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    threshold == u64::MAX || z < threshold
+}
+
+fn open(output: &str) -> io::Result<Box<dyn Write + Send>> {
+    if let Some(addr) = output.strip_prefix("tcp://") {
+        Ok(Box::new(TcpStream::connect(addr)?))
+    } else if let Some(path) = output.strip_prefix("unix://") {
+        Ok(Box::new(UnixStream::connect(path)?))
+    } else {
+        Ok(Box::new(OpenOptions::new().create(true).append(true).open(output)?))
+    }
+}
+
+fn write_loop(output: &str, mut rx: rtrb::Consumer<Sample>) {
+    let mut sink: Option<BufWriter<Box<dyn Write + Send>>> = None;
+    let mut opened_at: Option<Instant> = None;
+    let mut flushed_at = Instant::now();
+    while !SHUTDOWN.load(Ordering::Relaxed) {
+        let Ok(sample) = rx.pop() else {
+            if let Some(writer) = &mut sink {
+                if flushed_at.elapsed() >= FLUSH_INTERVAL {
+                    flushed_at = Instant::now();
+                    if let Err(err) = writer.flush() {
+                        tracing::warn!(%err, %output, "unable to write samples");
+                        sink = None;
+                    }
+                }
+            }
+            thread::sleep(SLEEP);
+            continue;
+        };
+        if sink.is_none() && opened_at.is_none_or(|at| at.elapsed() >= SAMPLE_RECONNECT_INTERVAL) {
+            opened_at = Some(Instant::now());
+            match open(output) {
+                Ok(writer) => sink = Some(BufWriter::new(writer)),
+                Err(err) => tracing::warn!(%err, %output, "unable to open sample output"),
+            }
+        }
+        let Some(writer) = &mut sink else { continue };
+        let res = serde_json::to_writer(&mut *writer, &sample)
+            .map_err(io::Error::from)
+            .and_then(|()| writer.write_all(b"\n"));
+        if let Err(err) = res {
+            tracing::warn!(%err, %output, "unable to write samples");
+            sink = None;
+        }
+    }
+    if let Some(mut writer) = sink {
+        if let Err(err) = writer.flush() {
+            tracing::warn!(%err, %output, "unable to write samples");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_roughly_at_rate() {
+        let one_percent = threshold(0.01);
+        let count = (0..100_000).filter(|seq| picked(Cursor::from(*seq), one_percent)).count();
+        assert!((800..1200).contains(&count), "{count}");
+        assert!((0..1000).all(|seq| picked(Cursor::from(seq), threshold(1.0))));
+        assert!(!(0..1000).any(|seq| picked(Cursor::from(seq), threshold(0.0))));
+    }
+}
@@ -11,6 +11,7 @@ use serde_ipld_dagcbor::codec::DagCborCodec;
 use sha2::{Digest, Sha256};
 use thiserror::Error;
 
+use rsky_common::cbor::check_canonical_cbor;
 use rsky_common::tid::TID;
 
 use crate::validator::event::{
@@ -31,6 +32,14 @@ pub struct RepoState {
     pub head: Cid,
 }
 
+/// Rejects DAG-CBOR blocks that aren't canonically encoded before they're decoded or relayed.
+fn check_block(cid: &Cid, block: &[u8]) -> Result<(), ParseError> {
+    if cid.codec() != <DagCborCodec as Codec<()>>::CODE {
+        return Ok(());
+    }
+    check_canonical_cbor(block).map_err(|err| ParseError::NonCanonical(*cid, err))
+}
+
 impl SubscribeReposEvent {
     pub fn validate(&self, commit: &Commit, head: &Cid) -> bool {
         let rev = match &self {
@@ -103,6 +112,7 @@ impl SubscribeReposEvent {
         let root_cid = reader.header.roots[0];
         for next in reader {
             let (cid, block) = next?;
+            check_block(&cid, &block)?;
             if cid == root_cid {
                 return Ok(Some((serde_ipld_dagcbor::from_slice(&block)?, cid)));
             }
@@ -117,6 +127,7 @@ impl SubscribeReposCommit {
         let mut block_map = HashMap::new();
         for next in CarReader::new(&mut blocks, true)? {
             let (cid, block) = next?;
+            check_block(&cid, &block)?;
             block_map.insert(cid, block);
         }
 
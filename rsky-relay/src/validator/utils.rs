@@ -5,6 +5,7 @@ use cid::Cid;
 use p256::ecdsa::signature::Verifier;
 use thiserror::Error;
 
+use crate::validator::event::DidKey;
 #[cfg(feature = "labeler")]
 use crate::validator::event::SubscribeLabel;
 #[cfg(not(feature = "labeler"))]
@@ -23,6 +24,23 @@ pub enum VerificationError {
     Key(#[from] p256::ecdsa::Error),
 }
 
+/// Verifies a raw `r || s` signature over `msg` with a multicodec-prefixed `did:key`.
+pub fn verify_signature(key: &DidKey, msg: &[u8], sig: &[u8]) -> Result<bool, VerificationError> {
+    match &key[0..2] {
+        P256_DID_PREFIX => {
+            let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(&key[2..])?;
+            let sig = p256::ecdsa::Signature::from_slice(sig)?;
+            Ok(key.verify(msg, &sig).is_ok())
+        }
+        K256_DID_PREFIX => {
+            let key = k256::ecdsa::VerifyingKey::from_sec1_bytes(&key[2..])?;
+            let sig = k256::ecdsa::Signature::from_slice(sig)?;
+            Ok(key.verify(msg, &sig).is_ok())
+        }
+        _ => Ok(false),
+    }
+}
+
 #[cfg(feature = "labeler")]
 pub fn verify_commit_sig(
     labels: &[SubscribeLabel], key: &[u8; 35],
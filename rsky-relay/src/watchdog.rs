@@ -0,0 +1,218 @@
+//! Disk usage watchdog. The firehose is a FIFO capped at `DISK_SIZE`, but on a smaller or
+//! shared volume the disk fills before the cap is reached, and the relay dies on the first write
+//! that fails. Instead, the cap is tightened on open to what the volume can hold alongside the
+//! reserve, and while running the watchdog keeps tightening it as free space runs low, dropping
+//! the oldest firehose segments so the relay keeps serving. Disk usage and the current cap are
+//! exported as gauges on `/metrics`.
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use std::{fs, io, mem, thread};
+
+use fjall::compaction::Fifo;
+use fjall::{AbstractTree, PartitionCreateOptions, PartitionHandle};
+use thiserror::Error;
+
+use crate::SHUTDOWN;
+use crate::config::{
+    DB_PATH, DISK_CHECK_INTERVAL, DISK_CRITICAL, DISK_MIN_SIZE, DISK_RESERVE, DISK_SIZE, DISK_WARN,
+    TTL_SECONDS,
+};
+use crate::types::DB;
+
+const SLEEP: Duration = Duration::from_millis(10);
+
+/// Bytes available on the volume holding the db, as of the last check.
+pub static DISK_AVAILABLE: AtomicU64 = AtomicU64::new(0);
+/// Bytes the firehose partition holds, as of the last check.
+pub static FIREHOSE_USED: AtomicU64 = AtomicU64::new(0);
+/// The firehose FIFO cap currently enforced.
+pub static FIREHOSE_LIMIT: AtomicU64 = AtomicU64::new(DISK_SIZE);
+
+#[derive(Debug, Error)]
+pub enum WatchdogError {
+    #[error("fjall error: {0}")]
+    Fjall(#[from] fjall::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiskLevel {
+    Ok,
+    Low,
+    Critical,
+}
+
+impl DiskLevel {
+    const fn of(available: u64) -> Self {
+        if available < DISK_CRITICAL {
+            Self::Critical
+        } else if available < DISK_WARN {
+            Self::Low
+        } else {
+            Self::Ok
+        }
+    }
+}
+
+pub struct Watchdog {
+    path: PathBuf,
+    firehose: PartitionHandle,
+    level: DiskLevel,
+}
+
+impl Watchdog {
+    pub fn new() -> Result<Self, WatchdogError> {
+        let firehose = DB.open_partition("firehose", PartitionCreateOptions::default())?;
+        Ok(Self { path: PathBuf::from(DB_PATH), firehose, level: DiskLevel::Ok })
+    }
+
+    pub fn run(mut self) -> Result<(), WatchdogError> {
+        // the first check waits an interval, giving the tightened FIFO time to compact
+        let mut checked_at = Instant::now();
+        while !SHUTDOWN.load(Ordering::Relaxed) {
+            if checked_at.elapsed() >= DISK_CHECK_INTERVAL {
+                checked_at = Instant::now();
+                self.check()?;
+            }
+            thread::sleep(SLEEP);
+        }
+        tracing::info!("shutting down watchdog");
+        Ok(())
+    }
+
+    fn check(&mut self) -> Result<(), WatchdogError> {
+        let available = match available_bytes(&self.path) {
+            Ok(available) => available,
+            Err(err) => {
+                tracing::warn!(%err, "failed to read disk usage");
+                return Ok(());
+            }
+        };
+        let used = dir_size(&self.path.join("partitions").join("firehose"));
+        DISK_AVAILABLE.store(available, Ordering::Relaxed);
+        FIREHOSE_USED.store(used, Ordering::Relaxed);
+        let level = DiskLevel::of(available);
+        if level != self.level {
+            match level {
+                DiskLevel::Ok => tracing::info!(%available, "disk usage back to normal"),
+                DiskLevel::Low => tracing::warn!(%available, "disk space running low"),
+                DiskLevel::Critical => tracing::error!(%available, "disk almost full"),
+            }
+            self.level = level;
+        }
+        if level != DiskLevel::Ok {
+            self.tighten(used, available)?;
+        }
+        Ok(())
+    }
+
+    /// Lowers the firehose cap to what fits alongside the reserve and drops the oldest segments
+    /// past it. The cap only ever shrinks until the next restart re-derives it.
+    fn tighten(&self, used: u64, available: u64) -> Result<(), WatchdogError> {
+        let limit = firehose_limit(used, available);
+        let prev = FIREHOSE_LIMIT.fetch_min(limit, Ordering::Relaxed);
+        let limit = limit.min(prev);
+        if limit < prev {
+            tracing::warn!(%limit, %used, %available, "tightened firehose retention to fit disk");
+        }
+        // fjall can't swap the strategy of an open partition, so run the tightened FIFO directly
+        self.firehose.tree.compact(Arc::new(Fifo::new(limit, TTL_SECONDS)), 0)?;
+        Ok(())
+    }
+}
+
+/// The disk gauges in the Prometheus text format.
+pub fn metrics() -> String {
+    let mut body = String::new();
+    for (name, help, gauge) in [
+        ("relay_disk_available_bytes", "Bytes free on the db volume", &DISK_AVAILABLE),
+        ("relay_firehose_used_bytes", "Bytes held by the firehose", &FIREHOSE_USED),
+        ("relay_firehose_limit_bytes", "Current firehose retention cap", &FIREHOSE_LIMIT),
+    ] {
+        let value = gauge.load(Ordering::Relaxed);
+        body.push_str(&format!("# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}\n"));
+    }
+    body
+}
+
+/// The FIFO limit for the firehose partition at `db_path`: at most `DISK_SIZE`, and otherwise
+/// what it already holds plus what's free, less `DISK_RESERVE`.
+pub fn firehose_disk_size(db_path: &Path) -> u64 {
+    let used = dir_size(&db_path.join("partitions").join("firehose"));
+    match available_bytes(db_path) {
+        Ok(available) => {
+            let limit = firehose_limit(used, available);
+            FIREHOSE_LIMIT.store(limit, Ordering::Relaxed);
+            if limit < DISK_SIZE {
+                tracing::warn!(%limit, %used, %available, "tightened firehose retention to fit disk");
+            }
+            limit
+        }
+        Err(err) => {
+            tracing::warn!(%err, "failed to read disk usage");
+            DISK_SIZE
+        }
+    }
+}
+
+const fn firehose_limit(used: u64, available: u64) -> u64 {
+    let limit = used.saturating_add(available).saturating_sub(DISK_RESERVE);
+    if limit < DISK_MIN_SIZE {
+        DISK_MIN_SIZE
+    } else if limit > DISK_SIZE {
+        DISK_SIZE
+    } else {
+        limit
+    }
+}
+
+/// Bytes available to unprivileged writers on the volume holding `path`.
+fn available_bytes(path: &Path) -> io::Result<u64> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: `statvfs` is plain old data, and is only read after the call fills it in
+    let mut stat: libc::statvfs = unsafe { mem::zeroed() };
+    // SAFETY: `path` is a valid C string and `stat` outlives the call
+    if unsafe { libc::statvfs(path.as_ptr(), &raw mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // the field types vary across targets
+    #[allow(clippy::useless_conversion)]
+    Ok(u64::from(stat.f_bavail).saturating_mul(u64::from(stat.f_frsize)))
+}
+
+/// Total size of the files under `path`, skipping anything that can't be read.
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn firehose_limit_fits_disk() {
+        let gib = 1024 * 1024 * 1024;
+        assert_eq!(firehose_limit(0, 1024 * gib), DISK_SIZE);
+        assert_eq!(firehose_limit(100 * gib, 50 * gib), 150 * gib - DISK_RESERVE);
+        assert_eq!(firehose_limit(0, DISK_RESERVE), DISK_MIN_SIZE);
+        assert_eq!(DiskLevel::of(DISK_WARN), DiskLevel::Ok);
+        assert_eq!(DiskLevel::of(DISK_WARN - 1), DiskLevel::Low);
+        assert_eq!(DiskLevel::of(DISK_CRITICAL - 1), DiskLevel::Critical);
+        assert!(available_bytes(&std::env::temp_dir()).unwrap() > 0);
+        assert!(metrics().contains("# TYPE relay_firehose_limit_bytes gauge\n"));
+    }
+}
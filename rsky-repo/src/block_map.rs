@@ -1,5 +1,5 @@
+use crate::error::RepoResult;
 use crate::types::CidAndBytes;
-use anyhow::Result;
 use lexicon_cid::Cid;
 use rsky_common;
 use rsky_common::ipld;
@@ -26,15 +26,22 @@ impl BlockMap {
         }
     }
 
-    pub fn add<T: Serialize>(&mut self, value: T) -> Result<Cid> {
-        let cid = ipld::cid_for_cbor(&value)?;
-        self.set(
-            cid,
-            rsky_common::struct_to_cbor(&value)?, //bytes
-        );
+    pub fn add<T: Serialize>(&mut self, value: T) -> RepoResult<Cid> {
+        let (cid, bytes) = ipld::CidHasher::new().cid_for_cbor(&value)?;
+        self.set(cid, bytes);
         Ok(cid)
     }
 
+    /// Adds many values at once, hashing them in parallel. CIDs are returned in input order.
+    pub fn add_batch<T: Serialize + Sync>(&mut self, values: &[T]) -> RepoResult<Vec<Cid>> {
+        let mut cids = Vec::with_capacity(values.len());
+        for (cid, bytes) in ipld::cids_for_cbor_batch(values)? {
+            self.set(cid, bytes);
+            cids.push(cid);
+        }
+        Ok(cids)
+    }
+
     pub fn set(&mut self, cid: Cid, bytes: Vec<u8>) -> () {
         self.map.insert(cid.to_string(), Bytes(bytes));
         ()
@@ -43,12 +50,12 @@ impl BlockMap {
     pub fn get(&self, cid: Cid) -> Option<&Vec<u8>> {
         self.map.get(&cid.to_string()).map(|bytes| &bytes.0)
     }
-    pub fn delete(&mut self, cid: Cid) -> Result<()> {
+    pub fn delete(&mut self, cid: Cid) -> RepoResult<()> {
         self.map.remove(&cid.to_string());
         Ok(())
     }
 
-    pub fn get_many(&mut self, cids: Vec<Cid>) -> Result<BlocksAndMissing> {
+    pub fn get_many(&mut self, cids: Vec<Cid>) -> RepoResult<BlocksAndMissing> {
         let mut missing: Vec<Cid> = Vec::new();
         let mut blocks = BlockMap::new();
         for cid in cids {
@@ -71,14 +78,14 @@ impl BlockMap {
     }
 
     // Not really using. Issues with closures
-    pub fn for_each(&self, cb: impl Fn(&Vec<u8>, Cid) -> ()) -> Result<()> {
+    pub fn for_each(&self, cb: impl Fn(&Vec<u8>, Cid) -> ()) -> RepoResult<()> {
         for (key, val) in self.map.iter() {
             cb(&val.0, Cid::from_str(&key)?);
         }
         Ok(())
     }
 
-    pub fn entries(&self) -> Result<Vec<CidAndBytes>> {
+    pub fn entries(&self) -> RepoResult<Vec<CidAndBytes>> {
         let mut entries: Vec<CidAndBytes> = Vec::new();
         for (cid, bytes) in self.map.iter() {
             entries.push(CidAndBytes {
@@ -89,11 +96,11 @@ impl BlockMap {
         Ok(entries)
     }
 
-    pub fn cids(&self) -> Result<Vec<Cid>> {
+    pub fn cids(&self) -> RepoResult<Vec<Cid>> {
         Ok(self.entries()?.into_iter().map(|e| e.cid).collect())
     }
 
-    pub fn add_map(&mut self, to_add: BlockMap) -> Result<()> {
+    pub fn add_map(&mut self, to_add: BlockMap) -> RepoResult<()> {
         let results = for (cid, bytes) in to_add.map.iter() {
             self.set(Cid::from_str(cid)?, bytes.0.clone());
         };
@@ -104,7 +111,7 @@ impl BlockMap {
         self.map.len()
     }
 
-    pub fn byte_size(&self) -> Result<usize> {
+    pub fn byte_size(&self) -> RepoResult<usize> {
         let mut size = 0;
         for (_, bytes) in self.map.iter() {
             size += bytes.0.len();
@@ -112,7 +119,7 @@ impl BlockMap {
         Ok(size)
     }
 
-    pub fn equals(&self, other: BlockMap) -> Result<bool> {
+    pub fn equals(&self, other: BlockMap) -> RepoResult<bool> {
         if self.size() != other.size() {
             return Ok(false);
         }
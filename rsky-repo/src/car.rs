@@ -1,10 +1,14 @@
 use crate::block_map::BlockMap;
+use crate::error::{RepoError, RepoResult};
+use crate::types::Commit;
 use crate::util::stream_to_buffer;
-use anyhow::{bail, Result};
+use anyhow::{anyhow, Result};
 use async_stream::stream;
 use futures::{pin_mut, Stream, StreamExt};
 use iroh_car::{CarHeader, CarReader, CarWriter};
 use lexicon_cid::Cid;
+use rsky_common::cbor::find_noncanonical_block;
+use rsky_common::ipld::find_mismatched_cids;
 use std::future::Future;
 use tokio::io::AsyncRead;
 use tokio::{
@@ -13,7 +17,11 @@ use tokio::{
 };
 
 pub struct CarWithRoot {
+    /// The root selected from the CAR.
     pub root: Cid,
+    /// Every root in the CAR header, in order. Exports sometimes carry auxiliary roots next to
+    /// the repo commit.
+    pub roots: Vec<Cid>,
     pub blocks: BlockMap,
 }
 
@@ -90,7 +98,7 @@ where
                             yield Ok(Vec::from(&buf[..n]));
                         }
                         Err(e) => {
-                            yield Err(e.into());
+                            yield Err(RepoError::StorageIo(e).into());
                             break;
                         }
                     }
@@ -124,7 +132,7 @@ pub fn blocks_to_car_stream(
     })
 }
 
-pub async fn blocks_to_car_file(root: Option<&Cid>, blocks: BlockMap) -> Result<Vec<u8>> {
+pub async fn blocks_to_car_file(root: Option<&Cid>, blocks: BlockMap) -> RepoResult<Vec<u8>> {
     let car_stream = blocks_to_car_stream(root, blocks);
     pin_mut!(car_stream);
     stream_to_buffer(car_stream).await
@@ -132,37 +140,89 @@ pub async fn blocks_to_car_file(root: Option<&Cid>, blocks: BlockMap) -> Result<
 
 pub async fn car_to_blocks<R: AsyncRead + Send + Unpin>(
     car: CarReader<R>,
-) -> Result<CarToBlocksOutput> {
+) -> RepoResult<CarToBlocksOutput> {
     let roots = car.header().roots().to_vec();
-    let mut blocks = BlockMap::new();
+    let mut read: Vec<(Cid, Vec<u8>)> = Vec::new();
     let mut stream = Box::pin(car.stream());
     while let Some(Ok((cid, bytes))) = stream.next().await {
+        read.push((cid, bytes));
+    }
+    let to_verify: Vec<(Cid, &[u8])> = read
+        .iter()
+        .map(|(cid, bytes)| (*cid, bytes.as_slice()))
+        .collect();
+    if let Some(cid) = find_mismatched_cids(&to_verify)?.first() {
+        return Err(RepoError::CorruptBlock(*cid));
+    }
+    if let Some((cid, error)) = find_noncanonical_block(&to_verify) {
+        return Err(RepoError::NonCanonicalBlock(cid, error));
+    }
+    let mut blocks = BlockMap::new();
+    for (cid, bytes) in read {
         blocks.set(cid, bytes);
     }
     Ok(CarToBlocksOutput { roots, blocks })
 }
 
-pub async fn read_car(bytes: Vec<u8>) -> Result<CarToBlocksOutput> {
+pub async fn read_car(bytes: Vec<u8>) -> RepoResult<CarToBlocksOutput> {
     read_stream_car(bytes.as_slice()).await
 }
 
-pub async fn read_car_with_root(bytes: Vec<u8>) -> Result<CarWithRoot> {
-    read_stream_car_with_root(bytes.as_slice()).await
+pub async fn read_car_with_root(bytes: Vec<u8>) -> RepoResult<CarWithRoot> {
+    read_stream_car_with_root(bytes.as_slice(), None).await
 }
 
+/// Reads a CAR and selects its root: `root` if given, which needn't be listed in the header as
+/// long as its block is in the CAR, or otherwise the header's only root or, of several, the
+/// first that's a repo commit.
 pub async fn read_stream_car_with_root<R: AsyncRead + Send + Unpin>(
     bytes: R,
-) -> Result<CarWithRoot> {
+    root: Option<Cid>,
+) -> RepoResult<CarWithRoot> {
     let CarToBlocksOutput { roots, blocks } = read_stream_car(bytes).await?;
-    if roots.len() != 1 {
-        bail!("Expected one root, got {}", roots.len());
+    let root = select_root(&roots, &blocks, root)?;
+    Ok(CarWithRoot {
+        root,
+        roots,
+        blocks,
+    })
+}
+
+fn select_root(roots: &[Cid], blocks: &BlockMap, root: Option<Cid>) -> RepoResult<Cid> {
+    if let Some(root) = root {
+        if !roots.contains(&root) && !blocks.has(root) {
+            return Err(RepoError::MissingBlock(root));
+        }
+        return Ok(root);
+    }
+    match roots {
+        [] => Err(RepoError::Other(anyhow!(
+            "CAR has no roots and none was given"
+        ))),
+        [root] => Ok(*root),
+        _ => roots
+            .iter()
+            .find(|root| {
+                blocks.get(**root).is_some_and(|bytes| {
+                    serde_ipld_dagcbor::from_slice::<Commit>(bytes.as_slice()).is_ok()
+                })
+            })
+            .copied()
+            .ok_or_else(|| {
+                RepoError::Other(anyhow!(
+                    "None of the CAR's {} roots is a commit",
+                    roots.len()
+                ))
+            }),
     }
-    let root = roots[0];
-    Ok(CarWithRoot { root, blocks })
 }
 
-pub async fn read_stream_car<R: AsyncRead + Send + Unpin>(bytes: R) -> Result<CarToBlocksOutput> {
-    let car = CarReader::new(bytes).await?;
+pub async fn read_stream_car<R: AsyncRead + Send + Unpin>(
+    bytes: R,
+) -> RepoResult<CarToBlocksOutput> {
+    let car = CarReader::new(bytes)
+        .await
+        .map_err(|error| RepoError::Other(error.into()))?;
     car_to_blocks(car).await
 }
 
@@ -406,9 +466,14 @@ mod tests {
         file.read_to_end(&mut bytes).unwrap();
         let car_with_root = read_car_with_root(bytes).await.expect("Failed to read car");
         let (root, blocks) = fetch_valid_repo();
-        let expected = CarWithRoot { root, blocks };
+        let expected = CarWithRoot {
+            roots: vec![root],
+            root,
+            blocks,
+        };
 
         assert_eq!(car_with_root.root, expected.root);
+        assert_eq!(car_with_root.roots, expected.roots);
         assert_eq!(car_with_root.blocks, expected.blocks);
     }
 
@@ -417,13 +482,82 @@ mod tests {
         let file = tokio::fs::File::open(test_case!("valid_repo.car"))
             .await
             .unwrap();
-        let car_with_root = read_stream_car_with_root(file)
+        let car_with_root = read_stream_car_with_root(file, None)
             .await
             .expect("Failed to read car");
         let (root, blocks) = fetch_valid_repo();
-        let expected = CarWithRoot { root, blocks };
+        let expected = CarWithRoot {
+            roots: vec![root],
+            root,
+            blocks,
+        };
 
         assert_eq!(car_with_root.root, expected.root);
+        assert_eq!(car_with_root.roots, expected.roots);
         assert_eq!(car_with_root.blocks, expected.blocks);
     }
+
+    /// `car` with its header swapped for one listing `roots`.
+    fn with_roots(car: &[u8], roots: Vec<Cid>) -> Vec<u8> {
+        #[derive(Serialize)]
+        struct Header {
+            roots: Vec<Cid>,
+            version: u64,
+        }
+        fn varint(mut n: usize, out: &mut Vec<u8>) {
+            while n >= 0x80 {
+                out.push((n as u8) | 0x80);
+                n >>= 7;
+            }
+            out.push(n as u8);
+        }
+        let (mut header_len, mut offset) = (0usize, 0);
+        loop {
+            let byte = car[offset];
+            header_len |= ((byte & 0x7f) as usize) << (7 * offset);
+            offset += 1;
+            if byte < 0x80 {
+                break;
+            }
+        }
+        let header = serde_ipld_dagcbor::to_vec(&Header { roots, version: 1 }).unwrap();
+        let mut out = Vec::new();
+        varint(header.len(), &mut out);
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&car[offset + header_len..]);
+        out
+    }
+
+    #[tokio::test]
+    async fn test_read_car_with_root_selection() {
+        let mut file = std::fs::File::open(test_case!("valid_repo.car")).unwrap();
+        let mut bytes: Vec<u8> = Vec::new();
+        file.read_to_end(&mut bytes).unwrap();
+        let (root, blocks) = fetch_valid_repo();
+        let aux = blocks
+            .cids()
+            .unwrap()
+            .into_iter()
+            .find(|cid| *cid != root)
+            .unwrap();
+
+        // the commit is picked out from auxiliary roots
+        let multi = with_roots(&bytes, vec![aux, root]);
+        let car = read_car_with_root(multi.clone()).await.unwrap();
+        assert_eq!(car.root, root);
+        assert_eq!(car.roots, vec![aux, root]);
+        let car = read_stream_car_with_root(multi.as_slice(), Some(aux))
+            .await
+            .unwrap();
+        assert_eq!(car.root, aux);
+
+        // rootless CARs need the root given
+        let rootless = with_roots(&bytes, vec![]);
+        assert!(read_car_with_root(rootless.clone()).await.is_err());
+        let car = read_stream_car_with_root(rootless.as_slice(), Some(root))
+            .await
+            .unwrap();
+        assert_eq!(car.root, root);
+        assert!(car.roots.is_empty());
+    }
 }
@@ -1,8 +1,8 @@
 use crate::block_map::BlockMap;
 use crate::cid_set::CidSet;
+use crate::error::RepoResult;
 use crate::mst::diff::mst_diff;
 use crate::mst::{NodeEntry, MST};
-use anyhow::Result;
 use lexicon_cid::Cid;
 use std::collections::HashMap;
 use std::fmt::Debug;
@@ -51,11 +51,11 @@ impl DataDiff {
         }
     }
 
-    pub async fn of(curr: &mut MST, prev: Option<&mut MST>) -> Result<DataDiff> {
+    pub async fn of(curr: &mut MST, prev: Option<&mut MST>) -> RepoResult<DataDiff> {
         mst_diff(curr, prev).await
     }
 
-    pub async fn node_add(&mut self, node: NodeEntry) -> Result<()> {
+    pub async fn node_add(&mut self, node: NodeEntry) -> RepoResult<()> {
         match node {
             NodeEntry::Leaf(node) => self.leaf_add(&node.key, node.value),
             NodeEntry::MST(node) => {
@@ -66,7 +66,7 @@ impl DataDiff {
         Ok(())
     }
 
-    pub async fn node_delete(&mut self, node: NodeEntry) -> Result<()> {
+    pub async fn node_delete(&mut self, node: NodeEntry) -> RepoResult<()> {
         match node {
             NodeEntry::Leaf(node) => {
                 let key = node.key;
@@ -145,7 +145,7 @@ impl DataDiff {
         ()
     }
 
-    pub fn tree_delete(&mut self, cid: Cid) -> Result<()> {
+    pub fn tree_delete(&mut self, cid: Cid) -> RepoResult<()> {
         if self.new_mst_blocks.has(cid) {
             self.new_mst_blocks.delete(cid)?;
         } else {
@@ -1,28 +1,60 @@
+use crate::sync::consumer::ConsumerError;
 use lexicon_cid::Cid;
+use rsky_common::cbor::CanonicalCborError;
 use thiserror::Error;
 
+/// Failure modes of reading, writing and verifying repos, returned across the public API so
+/// callers can match on them. Storage backends still report errors as `anyhow::Error`; those
+/// that wrap a `RepoError` are unwrapped back into it, and anything else is carried as `Other`.
 #[derive(Error, Debug)]
-pub enum DataStoreError {
+pub enum RepoError {
     #[error("missing block `{0}`")]
-    MissingBlock(String),
+    MissingBlock(Cid),
     #[error("missing `{0}` blocks: `{1:?}`")]
     MissingBlocks(String, Vec<Cid>),
     #[error("unexpected object at `{0}`")]
     UnexpectedObject(Cid),
-    #[error("unknown data store error")]
-    Unknown,
-}
-
-#[derive(Error, Debug)]
-pub enum RepoError {
+    #[error("corrupt block `{0}`: bytes don't match the CID")]
+    CorruptBlock(Cid),
+    #[error("non-canonical DAG-CBOR block `{0}`: {1}")]
+    NonCanonicalBlock(Cid, CanonicalCborError),
+    #[error("invalid key `{0}`")]
+    InvalidKey(String),
+    #[error("invalid repo did `{0}`")]
+    InvalidDid(String),
+    #[error("invalid signature on commit `{0}`")]
+    BadSignature(Cid),
+    #[error("invalid cid: {0}")]
+    InvalidCid(#[from] lexicon_cid::Error),
+    #[error("invalid DAG-CBOR: {0}")]
+    InvalidCbor(String),
+    #[error("invalid MST: {0}")]
+    InvalidMst(String),
+    #[error("storage io error: {0}")]
+    StorageIo(#[from] std::io::Error),
     #[error("Commit was at`{0}`")]
     BadCommitSwapError(Cid),
     #[error("Record was at`{0:?}`")]
     BadRecordSwapError(Option<Cid>),
     #[error("Invalid record error")]
     InvalidRecordError,
+    #[error(transparent)]
+    Verification(#[from] ConsumerError),
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl From<anyhow::Error> for RepoError {
+    fn from(error: anyhow::Error) -> Self {
+        match error.downcast::<RepoError>() {
+            Ok(error) => error,
+            Err(error) => RepoError::Other(error),
+        }
+    }
 }
 
+pub type RepoResult<T> = std::result::Result<T, RepoError>;
+
 #[derive(Error, Debug)]
 pub enum BlobError {
     #[error("Blob not found")]
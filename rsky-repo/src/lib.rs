@@ -14,5 +14,7 @@ pub mod readable_repo;
 pub mod repo;
 pub mod storage;
 pub mod sync;
+#[cfg(any(test, feature = "test_helpers"))]
+pub mod test_helpers;
 pub mod types;
 pub mod util;
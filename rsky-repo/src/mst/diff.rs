@@ -1,10 +1,10 @@
 use crate::data_diff::DataDiff;
+use crate::error::{RepoError, RepoResult};
 use crate::mst::walker::{MstWalker, WalkerStatus};
 use crate::mst::{NodeEntry, MST};
-use anyhow::{bail, Result};
 use futures::StreamExt;
 
-pub async fn null_diff(tree: MST) -> Result<DataDiff> {
+pub async fn null_diff(tree: MST) -> RepoResult<DataDiff> {
     let mut diff = DataDiff::new();
     let mut stream = Box::pin(tree.walk());
     while let Some(entry) = stream.next().await {
@@ -13,7 +13,7 @@ pub async fn null_diff(tree: MST) -> Result<DataDiff> {
     Ok(diff)
 }
 
-pub async fn mst_diff(curr: &mut MST, prev: Option<&mut MST>) -> Result<DataDiff> {
+pub async fn mst_diff(curr: &mut MST, prev: Option<&mut MST>) -> RepoResult<DataDiff> {
     curr.get_pointer().await?;
     return if let Some(prev) = prev {
         prev.get_pointer().await?;
@@ -129,12 +129,14 @@ pub async fn mst_diff(curr: &mut MST, prev: Option<&mut MST>) -> Result<DataDiff
                         continue;
                     }
 
-                    bail!("Unidentifiable case in diff walk");
+                    return Err(RepoError::InvalidMst(
+                        "Unidentifiable case in diff walk".to_string(),
+                    ));
                 }
             }
         }
         Ok(diff)
     } else {
-        Ok(null_diff(curr.clone()).await?)
+        null_diff(curr.clone()).await
     };
 }
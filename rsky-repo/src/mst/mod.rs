@@ -13,17 +13,16 @@
  * (2-bits of zero per layer).
  */
 use crate::block_map::BlockMap;
-use crate::cid_set::CidSet;
-use crate::error::DataStoreError;
+use crate::error::{RepoError, RepoResult};
 use crate::parse;
 use crate::storage::types::RepoStorage;
 use crate::storage::ObjAndBytes;
 use crate::types::CidAndBytes;
-use anyhow::{anyhow, Result};
+use anyhow::anyhow;
 use async_recursion::async_recursion;
-use async_stream::stream;
+use async_stream::{stream, try_stream};
 use async_trait::async_trait;
-use futures::{Stream, StreamExt};
+use futures::{future, Stream, StreamExt, TryStreamExt};
 use iroh_car::CarWriter;
 use lexicon_cid::Cid;
 use rsky_common;
@@ -149,7 +148,7 @@ impl Default for NodeIterReachable {
 }
 
 impl NodeIterReachable {
-    pub fn into_stream(self) -> impl Stream<Item = Result<NodeEntry>> {
+    pub fn into_stream(self) -> impl Stream<Item = RepoResult<NodeEntry>> {
         stream! {
             let mut current = self;
             loop {
@@ -163,7 +162,7 @@ impl NodeIterReachable {
     }
 
     #[async_recursion(Sync)]
-    async fn next(&mut self) -> Option<Result<NodeEntry>> {
+    async fn next(&mut self) -> Option<RepoResult<NodeEntry>> {
         match self.entries.first() {
             None => {
                 if let Some(this) = self.this.take() {
@@ -192,15 +191,13 @@ impl NodeIterReachable {
                         // Asynchronously fetch child entries
                         subtree.get_entries().await
                     }
-                    _ => Err(anyhow::Error::new(DataStoreError::MissingBlock(
-                        "Missing Blocks".to_string(),
-                    ))),
+                    _ => unreachable!("entry was matched as a subtree"),
                 };
 
                 match entries {
                     Err(e) => {
-                        match e.downcast_ref() {
-                            Some(DataStoreError::MissingBlock(_)) => self.next().await, // Don't iterate
+                        match e {
+                            RepoError::MissingBlock(_) => self.next().await, // Don't iterate
                             _ => return Some(Err(e)),
                         }
                     }
@@ -467,7 +464,7 @@ pub struct UnstoredBlocks {
 /// MerkleSearchTree values are immutable. Methods return copies with changes.
 #[derive(Clone)]
 pub struct MST {
-    pub entries: Arc<RwLock<Option<Vec<NodeEntry>>>>,
+    pub entries: Arc<RwLock<Option<Arc<Vec<NodeEntry>>>>>,
     pub layer: Option<u32>,
     pub pointer: Arc<RwLock<Cid>>,
     pub outdated_pointer: Arc<RwLock<bool>>,
@@ -554,7 +551,7 @@ impl MST {
     ) -> Self {
         Self {
             storage,
-            entries: Arc::new(RwLock::new(entries)),
+            entries: Arc::new(RwLock::new(entries.map(Arc::new))),
             layer,
             pointer: Arc::new(RwLock::new(pointer)),
             outdated_pointer: Arc::new(RwLock::new(false)),
@@ -565,7 +562,7 @@ impl MST {
         storage: Arc<RwLock<dyn RepoStorage>>,
         entries: Option<Vec<NodeEntry>>,
         layer: Option<u32>,
-    ) -> Result<Self> {
+    ) -> RepoResult<Self> {
         let entries = entries.unwrap_or(Vec::new());
         let pointer = util::cid_for_entries(entries.as_slice()).await?;
         Ok(MST::new(storage, pointer, Some(entries), layer))
@@ -575,7 +572,7 @@ impl MST {
         storage: Arc<RwLock<dyn RepoStorage>>,
         data: &NodeData,
         layer: Option<u32>,
-    ) -> Result<Self> {
+    ) -> RepoResult<Self> {
         let entries = util::deserialize_node_data(storage.clone(), data, layer)?;
         let pointer = ipld::cid_for_cbor(&data)?;
         Ok(MST::new(storage, pointer, Some(entries), layer))
@@ -587,7 +584,7 @@ impl MST {
         storage: Arc<RwLock<dyn RepoStorage>>,
         cid: Cid,
         layer: Option<u32>,
-    ) -> Result<Self> {
+    ) -> RepoResult<Self> {
         Ok(MST::new(storage, cid, None, layer))
     }
 
@@ -595,7 +592,7 @@ impl MST {
     // -------------------
 
     /// We never mutate an MST, we just return a new MST with updated values
-    pub async fn new_tree(&mut self, entries: Vec<NodeEntry>) -> Result<Self> {
+    pub async fn new_tree(&mut self, entries: Vec<NodeEntry>) -> RepoResult<Self> {
         let mut mst = MST::new(
             self.storage.clone(),
             self.pointer.read().await.clone(),
@@ -609,7 +606,13 @@ impl MST {
     // === "Getters (lazy load)" ===
 
     /// "We don't want to load entries of every subtree, just the ones we need"
-    pub async fn get_entries(&self) -> Result<Vec<NodeEntry>> {
+    pub async fn get_entries(&self) -> RepoResult<Vec<NodeEntry>> {
+        Ok(self.get_entries_shared().await?.as_ref().clone())
+    }
+
+    /// Same as `get_entries` but hands back the node's entry vector itself rather than a copy.
+    /// Trees derived from this node only copy the entries they actually keep.
+    pub async fn get_entries_shared(&self) -> RepoResult<Arc<Vec<NodeEntry>>> {
         // If `self.entries` is not populated, hydrate it first\
         {
             let mut entries = self.entries.write().await;
@@ -628,7 +631,8 @@ impl MST {
                         }),
                     )
                     .await?;
-                let data: NodeData = serde_cbor::value::from_value(data)?;
+                let data: NodeData = serde_cbor::value::from_value(data)
+                    .map_err(|_| RepoError::UnexpectedObject(*pointer))?;
 
                 // Compute the layer
                 let first_leaf = data.e.get(0);
@@ -638,11 +642,11 @@ impl MST {
                 };
 
                 // Deserialize into self.entries
-                *entries = Some(util::deserialize_node_data(
+                *entries = Some(Arc::new(util::deserialize_node_data(
                     self.storage.clone(),
                     &data,
                     layer,
-                )?);
+                )?));
             }
         }
 
@@ -650,14 +654,14 @@ impl MST {
 
         guard
             .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("No entries present"))
-            .map(|v| v.clone())
+            .cloned()
+            .ok_or_else(|| RepoError::InvalidMst("No entries present".to_string()))
     }
 
     // We don't hash the node on every mutation for performance reasons
     // Instead we keep track of whether the pointer is outdated and only (recursively) calculate when needed
     #[async_recursion(Sync)]
-    pub async fn get_pointer(&self) -> Result<Cid> {
+    pub async fn get_pointer(&self) -> RepoResult<Cid> {
         let mut outdated = self.outdated_pointer.write().await;
         if !*outdated {
             return Ok(*self.pointer.read().await);
@@ -670,10 +674,10 @@ impl MST {
         Ok(serialized.cid)
     }
 
-    pub async fn serialize(&self) -> Result<CidAndBytes> {
-        let mut entries = self.get_entries().await?;
+    pub async fn serialize(&self) -> RepoResult<CidAndBytes> {
+        let mut entries = self.get_entries_shared().await?;
         let mut outdated: Vec<Self> = Vec::new();
-        for entry in &entries {
+        for entry in entries.iter() {
             if let NodeEntry::MST(ref mst) = entry {
                 let is_outdated = *mst.outdated_pointer.read().await;
                 if is_outdated {
@@ -686,7 +690,7 @@ impl MST {
             for outdated_entry in &outdated {
                 let _ = outdated_entry.get_pointer().await?;
             }
-            entries = self.get_entries().await?
+            entries = self.get_entries_shared().await?
         }
         let data = util::serialize_node_data(entries.as_slice()).await?;
         Ok(CidAndBytes {
@@ -699,7 +703,7 @@ impl MST {
     /// In the case of the topmost node in the tree, we look for a key in the node & determine the layer
     /// In the case where we don't find one, we recurse down until we do.
     /// If we still can't find one, then we have an empty tree and the node is layer 0
-    pub async fn get_layer(&mut self) -> Result<u32> {
+    pub async fn get_layer(&mut self) -> RepoResult<u32> {
         self.layer = self.attempt_get_layer().await?;
         if self.layer.is_none() {
             self.layer = Some(0);
@@ -708,7 +712,7 @@ impl MST {
     }
 
     #[async_recursion(Sync)]
-    pub async fn attempt_get_layer(&mut self) -> Result<Option<u32>> {
+    pub async fn attempt_get_layer(&mut self) -> RepoResult<Option<u32>> {
         if self.layer.is_some() {
             return Ok(self.layer);
         };
@@ -735,7 +739,7 @@ impl MST {
 
     /// Return the necessary blocks to persist the MST to repo storage
     #[async_recursion(Sync)]
-    pub async fn get_unstored_blocks(&self) -> Result<UnstoredBlocks> {
+    pub async fn get_unstored_blocks(&self) -> RepoResult<UnstoredBlocks> {
         let mut blocks = BlockMap::new();
         let pointer = self.get_pointer().await?;
         let already_has = {
@@ -748,7 +752,7 @@ impl MST {
                 blocks,
             });
         }
-        let entries = self.get_entries().await?;
+        let entries = self.get_entries_shared().await?;
         let data: NodeData = util::serialize_node_data(entries.as_slice()).await?;
         let _ = blocks.add(data)?;
         for entry in entries.iter() {
@@ -766,7 +770,12 @@ impl MST {
     /// Adds a new leaf for the given key/value pair
     /// Throws if a leaf with that key already exists
     #[async_recursion(Sync)]
-    pub async fn add(&mut self, key: &str, value: Cid, known_zeros: Option<u32>) -> Result<Self> {
+    pub async fn add(
+        &mut self,
+        key: &str,
+        value: Cid,
+        known_zeros: Option<u32>,
+    ) -> RepoResult<Self> {
         util::ensure_valid_mst_key(&key)?;
         let key_zeros: u32;
         if let Some(z) = known_zeros {
@@ -788,7 +797,10 @@ impl MST {
             let found = self.at_index(index).await?;
             if let Some(NodeEntry::Leaf(l)) = found {
                 if l.key == *key {
-                    return Err(anyhow!("There is already a value at key: {}", key));
+                    return Err(RepoError::Other(anyhow!(
+                        "There is already a value at key: {}",
+                        key
+                    )));
                 }
             }
             let prev_node = self.at_index(index - 1).await?;
@@ -865,7 +877,7 @@ impl MST {
 
     /// Gets the value at the given key
     #[async_recursion(Sync)]
-    pub async fn get(&mut self, key: &String) -> Result<Option<Cid>> {
+    pub async fn get(&mut self, key: &String) -> RepoResult<Option<Cid>> {
         let index = self.find_gt_or_equal_leaf_index(key).await?;
         let found = self.at_index(index).await?;
         if let Some(NodeEntry::Leaf(f)) = found {
@@ -883,7 +895,7 @@ impl MST {
     /// Edits the value at the given key
     /// Throws if the given key does not exist
     #[async_recursion(Sync)]
-    pub async fn update(&mut self, key: &str, value: Cid) -> Result<Self> {
+    pub async fn update(&mut self, key: &str, value: Cid) -> RepoResult<Self> {
         util::ensure_valid_mst_key(key)?;
         let index = self.find_gt_or_equal_leaf_index(key).await?;
         let found = self.at_index(index).await?;
@@ -907,17 +919,20 @@ impl MST {
                 .update_entry(index - 1, NodeEntry::MST(updated_tree))
                 .await;
         }
-        Err(anyhow!("Could not find a record with key: {}", key))
+        Err(RepoError::Other(anyhow!(
+            "Could not find a record with key: {}",
+            key
+        )))
     }
 
     /// Deletes the value at the given key
-    pub async fn delete(&mut self, key: &String) -> Result<Self> {
+    pub async fn delete(&mut self, key: &String) -> RepoResult<Self> {
         let altered = self.delete_recurse(key).await?;
         Ok(altered.trim_top().await?)
     }
 
     #[async_recursion(Sync)]
-    pub async fn delete_recurse(&mut self, key: &String) -> Result<Self> {
+    pub async fn delete_recurse(&mut self, key: &String) -> RepoResult<Self> {
         let index = self.find_gt_or_equal_leaf_index(key).await?;
         let found = self.at_index(index).await?;
         // if found, remove it on this level
@@ -928,12 +943,13 @@ impl MST {
                 return match (prev, next) {
                     (Some(NodeEntry::MST(mut p)), Some(NodeEntry::MST(n))) => {
                         let merged = p.append_merge(n).await?;
-                        let mut new_tree_entries: Vec<NodeEntry> = Vec::new();
-                        new_tree_entries
-                            .append(&mut self.slice(Some(0), Some(index - 1)).await?.to_vec());
-                        new_tree_entries.push(NodeEntry::MST(merged));
-                        new_tree_entries
-                            .append(&mut self.slice(Some(index + 2), None).await?.to_vec());
+                        let entries = self.get_entries_shared().await?;
+                        let new_tree_entries = util::splice_entries(
+                            &entries,
+                            index - 1,
+                            [NodeEntry::MST(merged)],
+                            index + 2,
+                        );
                         self.new_tree(new_tree_entries).await
                     }
                     (_, _) => self.remove_entry(index).await,
@@ -944,7 +960,7 @@ impl MST {
         let prev = self.at_index(index - 1).await?;
         return if let Some(NodeEntry::MST(mut p)) = prev {
             let subtree = &mut p.delete_recurse(key).await?;
-            let subtree_entries = subtree.get_entries().await?;
+            let subtree_entries = subtree.get_entries_shared().await?;
             if subtree_entries.len() == 0 {
                 self.remove_entry(index - 1).await
             } else {
@@ -952,7 +968,10 @@ impl MST {
                     .await
             }
         } else {
-            Err(anyhow!("Could not find a record with key: {}", key))
+            Err(RepoError::Other(anyhow!(
+                "Could not find a record with key: {}",
+                key
+            )))
         };
     }
 
@@ -960,126 +979,58 @@ impl MST {
     // -------------------
 
     /// update entry in place
-    pub async fn update_entry(&mut self, index: isize, entry: NodeEntry) -> Result<Self> {
-        let mut update = Vec::new();
-        for e in self.slice(Some(0), Some(index)).await?.to_vec() {
-            update.push(e);
-        }
-        update.push(entry);
-        for e in self.slice(Some(index + 1), None).await?.to_vec() {
-            update.push(e);
-        }
+    pub async fn update_entry(&mut self, index: isize, entry: NodeEntry) -> RepoResult<Self> {
+        let entries = self.get_entries_shared().await?;
+        let update = util::splice_entries(&entries, index, [entry], index + 1);
         self.new_tree(update).await
     }
 
     /// remove entry at index
-    pub async fn remove_entry(&mut self, index: isize) -> Result<Self> {
-        let mut updated = Vec::new();
-        updated.append(&mut self.slice(Some(0), Some(index)).await?.to_vec());
-        updated.append(&mut self.slice(Some(index + 1), None).await?.to_vec());
-
-        self.new_tree(updated).await
+    pub async fn remove_entry(&mut self, index: isize) -> RepoResult<Self> {
+        let entries = self.get_entries_shared().await?;
+        let update = util::splice_entries(&entries, index, [], index + 1);
+        self.new_tree(update).await
     }
 
     /// append entry to end of the node / Vec is allowed here.
-    pub async fn append(&mut self, entry: NodeEntry) -> Result<Self> {
-        let mut entries = self.get_entries().await?.to_vec();
-        entries.push(entry);
-        self.new_tree(entries).await
+    pub async fn append(&mut self, entry: NodeEntry) -> RepoResult<Self> {
+        let entries = self.get_entries_shared().await?;
+        let end = entries.len() as isize;
+        let update = util::splice_entries(&entries, end, [entry], end);
+        self.new_tree(update).await
     }
 
     /// prepend entry to end of the node
-    pub async fn prepend(&mut self, entry: NodeEntry) -> Result<Self> {
-        let mut entries = self.get_entries().await?.to_vec();
-        entries.splice(0..0, vec![entry]);
-        self.new_tree(entries).await
+    pub async fn prepend(&mut self, entry: NodeEntry) -> RepoResult<Self> {
+        let entries = self.get_entries_shared().await?;
+        let update = util::splice_entries(&entries, 0, [entry], 0);
+        self.new_tree(update).await
     }
 
     /// returns entry at index
-    pub async fn at_index(&mut self, index: isize) -> Result<Option<NodeEntry>> {
-        let entries = self.get_entries().await?;
-        if index >= 0 {
-            Ok(entries
-                .into_iter()
-                .nth(index as usize)
-                .map(|entry| entry.clone()))
-        } else {
-            Ok(None)
+    pub async fn at_index(&mut self, index: isize) -> RepoResult<Option<NodeEntry>> {
+        if index < 0 {
+            return Ok(None);
         }
+        let entries = self.get_entries_shared().await?;
+        Ok(entries.get(index as usize).cloned())
     }
 
     /// returns a slice of the node
-    pub async fn slice(&self, start: Option<isize>, end: Option<isize>) -> Result<Vec<NodeEntry>> {
-        let entries = self.get_entries().await?;
-        let entry_len = entries.len() as isize;
-        match (start, end) {
-            (Some(start), Some(end)) => {
-                // Adapted from Javascript Array.prototype.slice()
-                // https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/Array/slice
-                let start: usize = if start < 0 && start >= (-1 * entry_len) {
-                    (start + entry_len) as usize
-                } else if start < (-1 * entry_len) {
-                    0
-                } else if start >= entry_len {
-                    return Ok(vec![]);
-                } else {
-                    start as usize
-                };
-
-                let end: usize = if end < 0 && end >= (-1 * entry_len) {
-                    (end + entry_len) as usize
-                } else if end < (-1 * entry_len) {
-                    0
-                } else if end >= entry_len {
-                    entries.len()
-                } else if end <= start as isize {
-                    return Ok(vec![]);
-                } else {
-                    end as usize
-                };
-
-                Ok(entries[start..end].to_vec())
-            }
-            (Some(start), None) => {
-                let start: usize = if start < 0 && start >= (-1 * entry_len) {
-                    (start + entry_len) as usize
-                } else if start < (-1 * entry_len) {
-                    0
-                } else if start >= entry_len {
-                    return Ok(vec![]);
-                } else {
-                    start as usize
-                };
-                Ok(entries[start..].to_vec())
-            }
-            (None, Some(end)) => {
-                let end: usize = if end < 0 && end >= (-1 * entry_len) {
-                    (end + entry_len) as usize
-                } else if end < (-1 * entry_len) {
-                    0
-                } else if end >= entry_len {
-                    entries.len()
-                } else if end <= 0 {
-                    return Ok(vec![]);
-                } else {
-                    end as usize
-                };
-                Ok(entries[..end].to_vec())
-            }
-            (None, None) => Ok(entries),
-        }
+    pub async fn slice(
+        &self,
+        start: Option<isize>,
+        end: Option<isize>,
+    ) -> RepoResult<Vec<NodeEntry>> {
+        let entries = self.get_entries_shared().await?;
+        let range = util::slice_range(entries.len(), start, end);
+        Ok(entries[range].to_vec())
     }
 
     /// inserts entry at index
-    pub async fn splice_in(&mut self, entry: NodeEntry, index: isize) -> Result<Self> {
-        let mut update = Vec::new();
-        for e in self.slice(Some(0), Some(index)).await?.to_vec() {
-            update.push(e);
-        }
-        update.push(entry);
-        for e in self.slice(Some(index), None).await?.to_vec() {
-            update.push(e);
-        }
+    pub async fn splice_in(&mut self, entry: NodeEntry, index: isize) -> RepoResult<Self> {
+        let entries = self.get_entries_shared().await?;
+        let update = util::splice_entries(&entries, index, [entry], index);
         self.new_tree(update).await
     }
 
@@ -1090,28 +1041,23 @@ impl MST {
         left: Option<Self>,
         leaf: Leaf,
         right: Option<Self>,
-    ) -> Result<Self> {
-        let update = self.slice(Some(0), Some(index)).await?;
-        let mut update = update.to_vec();
-        if let Some(l) = left {
-            update.push(NodeEntry::MST(l));
-        }
-        update.push(NodeEntry::Leaf(leaf));
-        if let Some(r) = right {
-            update.push(NodeEntry::MST(r));
-        }
-        let remainder = self.slice(Some(index + 1), None).await?;
-        let remainder = &mut remainder.to_vec();
-        update.append(remainder);
+    ) -> RepoResult<Self> {
+        let entries = self.get_entries_shared().await?;
+        let replacement = left
+            .map(NodeEntry::MST)
+            .into_iter()
+            .chain([NodeEntry::Leaf(leaf)])
+            .chain(right.map(NodeEntry::MST));
+        let update = util::splice_entries(&entries, index, replacement, index + 1);
         self.new_tree(update).await
     }
 
     /// if the topmost node in the tree only points to another tree, trim the top and return the subtree
     #[async_recursion(Sync)]
-    pub async fn trim_top(self) -> Result<Self> {
-        let entries = self.get_entries().await?;
+    pub async fn trim_top(self) -> RepoResult<Self> {
+        let entries = self.get_entries_shared().await?;
         return if entries.len() == 1 {
-            match entries.into_iter().nth(0) {
+            match entries.first() {
                 Some(NodeEntry::MST(n)) => Ok(n.clone().trim_top().await?),
                 _ => Ok(self),
             }
@@ -1125,28 +1071,18 @@ impl MST {
 
     /// Recursively splits a subtree around a given key
     #[async_recursion(Sync)]
-    pub async fn split_around(&mut self, key: &str) -> Result<(Option<Self>, Option<Self>)> {
+    pub async fn split_around(&mut self, key: &str) -> RepoResult<(Option<Self>, Option<Self>)> {
         let index = self.find_gt_or_equal_leaf_index(key).await?;
         // split tree around key
-        let left_data = {
-            let tmp = self.slice(Some(0), Some(index)).await?;
-            tmp.to_vec()
-        };
-        let right_data = {
-            let tmp = self.slice(Some(index), None).await?;
-            tmp.to_vec()
-        };
-        let mut left = self.new_tree(left_data.clone()).await?;
-        let mut right = self.new_tree(right_data).await?;
+        let left_data = self.slice(Some(0), Some(index)).await?;
+        let right_data = self.slice(Some(index), None).await?;
 
         // if the far right of the left side is a subtree,
         // we need to split it on the key as well
         let left_len = left_data.len();
-        let last_in_left: Option<NodeEntry> = if let [.., last] = left_data.as_slice() {
-            Some(last.clone())
-        } else {
-            None
-        };
+        let last_in_left: Option<NodeEntry> = left_data.last().cloned();
+        let mut left = self.new_tree(left_data).await?;
+        let mut right = self.new_tree(right_data).await?;
         if let Some(NodeEntry::MST(mut last)) = last_in_left {
             left = left.remove_entry(left_len as isize - 1).await?;
             let split = last.split_around(key).await?;
@@ -1159,12 +1095,12 @@ impl MST {
         }
 
         let left_output: Option<Self>;
-        match left.get_entries().await?.len() {
+        match left.get_entries_shared().await?.len() {
             0 => left_output = None,
             _ => left_output = Some(left),
         };
         let right_output: Option<Self>;
-        match right.get_entries().await?.len() {
+        match right.get_entries_shared().await?.len() {
             0 => right_output = None,
             _ => right_output = Some(right),
         };
@@ -1174,30 +1110,27 @@ impl MST {
     /// The simple merge case where every key in the right tree is greater than every key in the left tree
     /// (used primarily for deletes)
     #[async_recursion(Sync)]
-    pub async fn append_merge(&mut self, mut to_merge: Self) -> Result<Self> {
+    pub async fn append_merge(&mut self, mut to_merge: Self) -> RepoResult<Self> {
         if self.get_layer().await? != to_merge.get_layer().await? {
-            return Err(anyhow!(
-                "Trying to merge two nodes from different layers of the MST"
+            return Err(RepoError::InvalidMst(
+                "Trying to merge two nodes from different layers of the MST".to_string(),
             ));
         }
-        let mut self_entries = self.get_entries().await?.to_vec();
-        let mut to_merge_entries = to_merge.get_entries().await?.to_vec();
-        let last_in_left = self_entries.last();
-        let first_in_right = to_merge_entries.first();
-        let mut new_tree_entries: Vec<NodeEntry> = Vec::new();
-        match (last_in_left, first_in_right) {
-            (Some(NodeEntry::MST(l)), Some(NodeEntry::MST(r))) => {
+        let self_entries = self.get_entries_shared().await?;
+        let to_merge_entries = to_merge.get_entries_shared().await?;
+        let mut new_tree_entries: Vec<NodeEntry> =
+            Vec::with_capacity(self_entries.len() + to_merge_entries.len());
+        match (self_entries.split_last(), to_merge_entries.split_first()) {
+            (Some((NodeEntry::MST(l), left_rest)), Some((NodeEntry::MST(r), right_rest))) => {
                 let mut new_l = l.clone();
                 let merged = new_l.append_merge(r.clone()).await?;
-                self_entries.pop();
-                new_tree_entries.append(&mut self_entries);
+                new_tree_entries.extend_from_slice(left_rest);
                 new_tree_entries.push(NodeEntry::MST(merged));
-                to_merge_entries.remove(0);
-                new_tree_entries.append(&mut to_merge_entries);
+                new_tree_entries.extend_from_slice(right_rest);
             }
             (_, _) => {
-                new_tree_entries.append(&mut self_entries);
-                new_tree_entries.append(&mut to_merge_entries);
+                new_tree_entries.extend_from_slice(&self_entries);
+                new_tree_entries.extend_from_slice(&to_merge_entries);
             }
         };
         self.new_tree(new_tree_entries).await
@@ -1206,12 +1139,12 @@ impl MST {
     // Create relatives
     // -------------------
 
-    pub async fn create_child(&mut self) -> Result<Self> {
+    pub async fn create_child(&mut self) -> RepoResult<Self> {
         let layer = self.get_layer().await?;
         MST::create(self.storage.clone(), Some(Vec::new()), Some(layer - 1)).await
     }
 
-    pub async fn create_parent(mut self) -> Result<Self> {
+    pub async fn create_parent(mut self) -> RepoResult<Self> {
         let layer = self.get_layer().await?;
         let mut parent = MST::create(
             self.storage.clone(),
@@ -1227,8 +1160,8 @@ impl MST {
     // -------------------
 
     /// finds index of first leaf node that is greater than or equal to the value
-    pub async fn find_gt_or_equal_leaf_index(&mut self, key: &str) -> Result<isize> {
-        let entries = self.get_entries().await?;
+    pub async fn find_gt_or_equal_leaf_index(&mut self, key: &str) -> RepoResult<isize> {
+        let entries = self.get_entries_shared().await?;
         let maybe_index = entries.iter().position(|entry| match entry {
             NodeEntry::MST(_) => false,
             NodeEntry::Leaf(entry) => entry.key >= key.to_string(),
@@ -1244,71 +1177,76 @@ impl MST {
     // List operations (partial tree traversal)
     // -------------------
 
-    /// Walk tree starting at key
-    /// @Rudy Note: This may be suboptimal since we always traverse the tree even though external
-    /// controls might stop earlier.
-    #[async_recursion(Sync)]
-    pub async fn walk_leaves_from(&mut self, key: &String) -> impl Iterator<Item = Leaf> {
-        let mut iter: Vec<Leaf> = Vec::new();
-        let index = self.find_gt_or_equal_leaf_index(key).await.unwrap() as usize;
-        let entries = self.get_entries().await.unwrap();
-        if let Some(prev_index) = index.checked_sub(1) {
-            let prev = entries.get(prev_index);
-            if let Some(NodeEntry::MST(p)) = prev {
-                let mut p = p.clone();
-                for leaf in p.walk_leaves_from(key).await {
-                    iter.push(leaf);
+    /// Walk leaves in key order starting at key (inclusive). Subtrees are only hydrated once the
+    /// walk reaches them, so a consumer that stops early never loads the rest of the tree.
+    pub fn walk_leaves_from(&self, key: &str) -> impl Stream<Item = RepoResult<Leaf>> {
+        let root = self.clone();
+        let key = key.to_owned();
+        try_stream! {
+            // each frame is a node's entries along with the index of the next entry to emit
+            let mut stack: Vec<(Arc<Vec<NodeEntry>>, usize)> = Vec::new();
+            let mut node = root;
+            // descend along the left edge of the range, seeking to key at every layer
+            loop {
+                let entries = node.get_entries_shared().await?;
+                let index = entries
+                    .iter()
+                    .position(|entry| match entry {
+                        NodeEntry::MST(_) => false,
+                        NodeEntry::Leaf(leaf) => leaf.key.as_str() >= key.as_str(),
+                    })
+                    .unwrap_or(entries.len());
+                let prev = match index.checked_sub(1).and_then(|i| entries.get(i)) {
+                    Some(NodeEntry::MST(prev)) => Some(prev.clone()),
+                    _ => None,
+                };
+                stack.push((entries, index));
+                match prev {
+                    Some(prev) => node = prev,
+                    None => break,
                 }
             }
-        }
-        for i in index..entries.len() {
-            let entry = entries[i].clone();
-            match entry {
-                NodeEntry::Leaf(e) => iter.push(e),
-                NodeEntry::MST(mut e) => {
-                    for leaf in e.walk_leaves_from(key).await {
-                        iter.push(leaf);
+            while let Some((entries, index)) = stack.last_mut() {
+                let Some(entry) = entries.get(*index).cloned() else {
+                    stack.pop();
+                    continue;
+                };
+                *index += 1;
+                match entry {
+                    NodeEntry::Leaf(leaf) => yield leaf,
+                    NodeEntry::MST(subtree) => {
+                        stack.push((subtree.get_entries_shared().await?, 0));
                     }
                 }
             }
         }
-        iter.into_iter()
     }
 
-    pub async fn list(
-        &mut self,
+    /// Leaves strictly after `after` and strictly before `before`, up to count
+    pub fn list(
+        &self,
         count: Option<usize>,
         after: Option<String>,
         before: Option<String>,
-    ) -> Result<Vec<Leaf>> {
-        let mut vals: Vec<Leaf> = Vec::new();
-        let after = after.unwrap_or("".to_owned());
-        for leaf in self.walk_leaves_from(&after).await {
-            if leaf.key == after {
-                continue;
-            }
-            if vals.len() >= count.unwrap_or(usize::MAX) {
-                break;
-            }
-            if let Some(b) = &before {
-                if leaf.key >= *b {
-                    break;
-                }
-            }
-            vals.push(leaf);
-        }
-        Ok(vals)
-    }
-
-    pub async fn list_with_prefix(&mut self, prefix: &String, count: usize) -> Result<Vec<Leaf>> {
-        let mut vals: Vec<Leaf> = Vec::new();
-        for leaf in self.walk_leaves_from(prefix).await {
-            if vals.len() >= count || !leaf.key.starts_with(prefix) {
-                break;
-            }
-            vals.push(leaf);
-        }
-        Ok(vals)
+    ) -> impl Stream<Item = RepoResult<Leaf>> {
+        let after = after.unwrap_or_default();
+        self.walk_leaves_from(&after)
+            .try_skip_while(move |leaf| future::ready(Ok(leaf.key == after)))
+            .try_take_while(move |leaf| {
+                future::ready(Ok(before.as_ref().map_or(true, |b| leaf.key < *b)))
+            })
+            .take(count.unwrap_or(usize::MAX))
+    }
+
+    pub fn list_with_prefix(
+        &self,
+        prefix: &str,
+        count: usize,
+    ) -> impl Stream<Item = RepoResult<Leaf>> {
+        let prefix_owned = prefix.to_owned();
+        self.walk_leaves_from(prefix)
+            .try_take_while(move |leaf| future::ready(Ok(leaf.key.starts_with(&prefix_owned))))
+            .take(count)
     }
 
     // Full tree traversal
@@ -1321,7 +1259,7 @@ impl MST {
 
     /// Walk full tree & emit nodes, consumer can bail at any point by returning None
     #[async_recursion(Sync)]
-    pub async fn paths(self) -> Result<Vec<Vec<NodeEntry>>> {
+    pub async fn paths(self) -> RepoResult<Vec<Vec<NodeEntry>>> {
         let mut paths: Vec<Vec<NodeEntry>> = Vec::new();
         let mut stream = Box::pin(self.walk());
         while let Some(entry) = stream.next().await {
@@ -1345,7 +1283,7 @@ impl MST {
     }
 
     /// Walks tree & returns all nodes
-    pub async fn all_nodes(self) -> Result<Vec<NodeEntry>> {
+    pub async fn all_nodes(self) -> RepoResult<Vec<NodeEntry>> {
         let mut nodes: Vec<NodeEntry> = Vec::new();
         let mut stream = Box::pin(self.walk());
         while let Some(entry) = stream.next().await {
@@ -1357,40 +1295,39 @@ impl MST {
         Ok(nodes)
     }
 
-    /// Walks tree & returns all cids
-    #[async_recursion(Sync)]
-    pub async fn all_cids(self) -> Result<CidSet> {
-        let mut cids = CidSet::new(None);
-        let mut stream = Box::pin(self.clone().walk());
-        while let Some(entry) = stream.next().await {
-            match entry {
-                NodeEntry::Leaf(leaf) => cids.add(leaf.value),
-                NodeEntry::MST(m) => {
-                    let subtree_cids = m.all_cids().await?;
-                    let _ = &cids.add_set(subtree_cids);
+    /// Walks tree & emits all cids: the pointer of every node followed by its leaf values
+    pub fn all_cids(&self) -> impl Stream<Item = RepoResult<Cid>> {
+        let root = self.clone();
+        try_stream! {
+            let mut stack: Vec<MST> = vec![root];
+            while let Some(node) = stack.pop() {
+                yield node.get_pointer().await?;
+                let entries = node.get_entries_shared().await?;
+                // pushed in reverse so subtrees are visited left to right
+                for entry in entries.iter().rev() {
+                    if let NodeEntry::MST(subtree) = entry {
+                        stack.push(subtree.clone());
+                    }
+                }
+                for entry in entries.iter() {
+                    if let NodeEntry::Leaf(leaf) = entry {
+                        yield leaf.value;
+                    }
                 }
             }
         }
-        cids.add(self.clone().get_pointer().await?);
-        Ok(cids)
     }
 
-    /// Walks tree & returns all leaves
-    pub async fn leaves(self) -> Result<Vec<Leaf>> {
-        let mut leaves: Vec<Leaf> = Vec::new();
-        let mut stream = Box::pin(self.walk());
-        while let Some(entry) = stream.next().await {
-            if let NodeEntry::Leaf(leaf) = entry {
-                leaves.push(leaf);
-            }
-        }
-        Ok(leaves)
+    /// Walks tree & emits all leaves in key order
+    pub fn leaves(&self) -> impl Stream<Item = RepoResult<Leaf>> {
+        self.walk_leaves_from("")
     }
 
     /// Returns total leaf count
-    pub async fn leaf_count(self) -> Result<usize> {
-        let leaves = self.leaves().await?;
-        Ok(leaves.len())
+    pub async fn leaf_count(self) -> RepoResult<usize> {
+        self.leaves()
+            .try_fold(0, |count, _| future::ready(Ok(count + 1)))
+            .await
     }
 
     // Reachable tree traversal
@@ -1398,11 +1335,11 @@ impl MST {
 
     /// Walk reachable branches of tree & emit nodes, consumer can bail at any point
     /// by returning false
-    pub fn walk_reachable(self) -> impl Stream<Item = Result<NodeEntry>> {
+    pub fn walk_reachable(self) -> impl Stream<Item = RepoResult<NodeEntry>> {
         NodeEntry::MST(self).iter_reachable().into_stream()
     }
 
-    pub async fn reachable_leaves(self) -> Result<Vec<Leaf>> {
+    pub async fn reachable_leaves(self) -> RepoResult<Vec<Leaf>> {
         let mut leaves: Vec<Leaf> = Vec::new();
         let mut stream = Box::pin(self.walk_reachable());
         while let Some(entry) = stream.next().await {
@@ -1417,7 +1354,7 @@ impl MST {
     pub async fn write_to_car_stream(
         &mut self,
         mut car: CarWriter<DuplexStream>,
-    ) -> Result<CarWriter<DuplexStream>> {
+    ) -> RepoResult<CarWriter<DuplexStream>> {
         let mut leaves = CidSet::new(None);
         let mut to_fetch = CidSet::new(None);
         to_fetch.add(self.get_pointer().await?);
@@ -1428,10 +1365,10 @@ impl MST {
                 storage_guard.get_blocks(to_fetch.to_list()).await?
             };
             if fetched.missing.len() > 0 {
-                return Err(anyhow::Error::new(DataStoreError::MissingBlocks(
+                return Err(RepoError::MissingBlocks(
                     "mst node".to_owned(),
                     fetched.missing,
-                )));
+                ));
             }
             for cid in to_fetch.to_list() {
                 let found: ObjAndBytes =
@@ -1441,8 +1378,11 @@ impl MST {
                             Err(_) => false,
                         }
                     })?;
-                car.write(cid, found.bytes).await?;
-                let node_data: NodeData = serde_cbor::value::from_value(found.obj)?;
+                car.write(cid, found.bytes)
+                    .await
+                    .map_err(|error| RepoError::Other(error.into()))?;
+                let node_data: NodeData = serde_cbor::value::from_value(found.obj)
+                    .map_err(|_| RepoError::UnexpectedObject(cid))?;
                 let entries = util::deserialize_node_data(self.storage.clone(), &node_data, None)?;
 
                 for entry in entries {
@@ -1459,19 +1399,21 @@ impl MST {
             storage_guard.get_blocks(leaves.to_list()).await?
         };
         if leaf_data.missing.len() > 0 {
-            return Err(anyhow::Error::new(DataStoreError::MissingBlocks(
+            return Err(RepoError::MissingBlocks(
                 "mst leaf".to_owned(),
                 leaf_data.missing,
-            )));
+            ));
         }
         for leaf in leaf_data.blocks.entries()? {
-            car.write(leaf.cid, leaf.bytes).await?;
+            car.write(leaf.cid, leaf.bytes)
+                .await
+                .map_err(|error| RepoError::Other(error.into()))?;
         }
         Ok(car)
     }
 
     #[async_recursion(Sync)]
-    pub async fn cids_for_path(&mut self, key: String) -> Result<Vec<Cid>> {
+    pub async fn cids_for_path(&mut self, key: String) -> RepoResult<Vec<Cid>> {
         let mut cids: Vec<Cid> = vec![self.get_pointer().await?];
         let index = self.find_gt_or_equal_leaf_index(&key).await?;
         let found = self.at_index(index).await?;
@@ -1490,7 +1432,11 @@ impl MST {
     }
 
     #[async_recursion(Sync)]
-    pub async fn add_blocks_for_path(&mut self, key: String, blocks: &mut BlockMap) -> Result<()> {
+    pub async fn add_blocks_for_path(
+        &mut self,
+        key: String,
+        blocks: &mut BlockMap,
+    ) -> RepoResult<()> {
         let serialized = self.serialize().await?;
         blocks.set(serialized.cid, serialized.bytes);
         let index = self.find_gt_or_equal_leaf_index(&key).await?;
@@ -1506,7 +1452,7 @@ impl MST {
         }
     }
 
-    pub async fn save_mst(&self) -> Result<Cid> {
+    pub async fn save_mst(&self) -> RepoResult<Cid> {
         let diff = self.get_unstored_blocks().await?;
         let storage = self.storage.read().await;
         storage
@@ -1640,6 +1586,99 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn lists_leaves_as_stream() -> Result<()> {
+        let mut storage = MemoryBlockstore::default();
+        let mapping = generate_bulk_data_keys(200, Some(&mut storage)).await?;
+        let mut mst = MST::create(Arc::new(RwLock::new(storage)), None, None).await?;
+        for (key, cid) in &mapping {
+            mst = mst.add(key, *cid, None).await?;
+        }
+        let mut keys = mapping.keys().cloned().collect::<Vec<String>>();
+        keys.sort();
+
+        let leaves: Vec<Leaf> = mst.leaves().try_collect().await?;
+        assert_eq!(
+            leaves.iter().map(|l| l.key.clone()).collect::<Vec<_>>(),
+            keys
+        );
+
+        let page: Vec<Leaf> = mst
+            .list(Some(5), Some(keys[10].clone()), None)
+            .try_collect()
+            .await?;
+        assert_eq!(
+            page.into_iter().map(|l| l.key).collect::<Vec<_>>(),
+            keys[11..16].to_vec()
+        );
+
+        let bounded: Vec<Leaf> = mst
+            .list(None, Some(keys[20].clone()), Some(keys[25].clone()))
+            .try_collect()
+            .await?;
+        assert_eq!(
+            bounded.into_iter().map(|l| l.key).collect::<Vec<_>>(),
+            keys[21..25].to_vec()
+        );
+
+        let cids: Vec<Cid> = mst.all_cids().try_collect().await?;
+        assert_eq!(cids.first(), Some(&mst.get_pointer().await?));
+        for cid in mapping.values() {
+            assert!(cids.contains(cid));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn slices_like_javascript() {
+        assert_eq!(slice_range(5, None, None), 0..5);
+        assert_eq!(slice_range(5, Some(-2), None), 3..5);
+        assert_eq!(slice_range(5, None, Some(-1)), 0..4);
+        assert_eq!(slice_range(5, Some(-9), Some(2)), 0..2);
+        assert_eq!(slice_range(5, Some(4), Some(2)), 0..0);
+        assert_eq!(slice_range(5, Some(7), None), 0..0);
+    }
+
+    #[tokio::test]
+    async fn node_mutations_leave_source_untouched() -> Result<()> {
+        let mut no_storage: Option<&mut dyn RepoStorage> = None;
+        let cid = random_cid(&mut no_storage, None).await?;
+        let leaf = |key: &str| {
+            NodeEntry::Leaf(Leaf {
+                key: key.to_string(),
+                value: cid,
+            })
+        };
+        let keys = |entries: Vec<NodeEntry>| {
+            entries
+                .into_iter()
+                .map(|entry| match entry {
+                    NodeEntry::Leaf(l) => l.key,
+                    NodeEntry::MST(_) => "*".to_string(),
+                })
+                .collect::<Vec<String>>()
+        };
+        let storage = Arc::new(RwLock::new(MemoryBlockstore::default()));
+        let mut node = MST::create(storage, Some(vec![leaf("a"), leaf("c")]), Some(0)).await?;
+
+        let spliced = node.splice_in(leaf("b"), 1).await?;
+        let updated = node.update_entry(1, leaf("d")).await?;
+        let removed = node.remove_entry(0).await?;
+        let appended = node.append(leaf("e")).await?;
+        let prepended = node.prepend(leaf("0")).await?;
+
+        assert_eq!(keys(spliced.get_entries().await?), vec!["a", "b", "c"]);
+        assert_eq!(keys(updated.get_entries().await?), vec!["a", "d"]);
+        assert_eq!(keys(removed.get_entries().await?), vec!["c"]);
+        assert_eq!(keys(appended.get_entries().await?), vec!["a", "c", "e"]);
+        assert_eq!(keys(prepended.get_entries().await?), vec!["0", "a", "c"]);
+        assert_eq!(keys(node.get_entries().await?), vec!["a", "c"]);
+        assert!(*spliced.outdated_pointer.read().await);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn deletes_records() -> Result<()> {
         let mut storage = MemoryBlockstore::default();
@@ -1951,6 +1990,32 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_leading_zeros_for_keys() -> Result<()> {
+        let keys = [
+            "app.bsky.feed.post/454397e440ec",
+            "blue",
+            "app.bsky.feed.post/9adeb165882c",
+            "blue",
+        ];
+        let zeros = leading_zeros_for_keys(keys)?;
+        assert_eq!(zeros.len(), 3);
+        assert_eq!(zeros["app.bsky.feed.post/454397e440ec"], 4);
+        assert_eq!(zeros["app.bsky.feed.post/9adeb165882c"], 8);
+        assert_eq!(zeros["blue"], 1);
+
+        // cached results match a fresh hash, inside a batch and outside one
+        with_zeros_cache(async {
+            assert_eq!(leading_zeros_on_hash(b"blue")?, 1);
+            assert_eq!(leading_zeros_on_hash(b"blue")?, 1);
+            Ok::<_, anyhow::Error>(())
+        })
+        .await?;
+        assert_eq!(leading_zeros_on_hash(b"blue")?, 1);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_prefix_len() -> Result<()> {
         let msg = "length of common prefix between strings";
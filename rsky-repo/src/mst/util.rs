@@ -1,6 +1,7 @@
 use super::{Leaf, NodeData, NodeEntry, TreeEntry, MST};
+use crate::block_map::BlockMap;
+use crate::error::{RepoError, RepoResult};
 use crate::storage::types::RepoStorage;
-use anyhow::{anyhow, Result};
 use lazy_static::lazy_static;
 use lexicon_cid::Cid;
 use rand::{thread_rng, Rng};
@@ -10,7 +11,10 @@ use rsky_common::ipld::cid_for_cbor;
 use rsky_common::tid::Ticker;
 use serde_json::json;
 use sha2::{Digest, Sha256};
-use std::collections::BTreeMap;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::ops::Range;
 use std::str;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -26,7 +30,7 @@ fn is_valid_chars(input: &str) -> bool {
 // alphanumeric (A-Za-z0-9), period, dash, underscore, colon, or tilde (.-_:~)
 // * Must have at least 1 and at most 512 characters
 // * The specific record key values . and .. are not allowed
-pub fn is_valid_repo_mst_path(key: &str) -> Result<bool> {
+pub fn is_valid_repo_mst_path(key: &str) -> RepoResult<bool> {
     let split: Vec<&str> = key.split("/").collect();
 
     return if key.len() <= 256
@@ -42,20 +46,19 @@ pub fn is_valid_repo_mst_path(key: &str) -> Result<bool> {
     };
 }
 
-pub fn ensure_valid_mst_key(key: &str) -> Result<()> {
-    let result = is_valid_repo_mst_path(key)?;
-    match result {
-        true => Ok(()),
-        _ => Err(anyhow!("Invalid MST Key: {}", key)),
+pub fn ensure_valid_mst_key(key: &str) -> RepoResult<()> {
+    match is_valid_repo_mst_path(key) {
+        Ok(true) => Ok(()),
+        _ => Err(RepoError::InvalidKey(key.to_string())),
     }
 }
 
-pub async fn cid_for_entries(entries: &[NodeEntry]) -> Result<Cid> {
+pub async fn cid_for_entries(entries: &[NodeEntry]) -> RepoResult<Cid> {
     let data = serialize_node_data(entries).await?;
-    cid_for_cbor(&data)
+    Ok(cid_for_cbor(&data)?)
 }
 
-pub fn count_prefix_len(a: String, b: String) -> Result<usize> {
+pub fn count_prefix_len(a: String, b: String) -> RepoResult<usize> {
     let mut x = 0;
     for i in 0..a.len() {
         match (a.chars().nth(i), b.chars().nth(i)) {
@@ -66,7 +69,7 @@ pub fn count_prefix_len(a: String, b: String) -> Result<usize> {
     Ok(x)
 }
 
-pub async fn serialize_node_data(entries: &[NodeEntry]) -> Result<NodeData> {
+pub async fn serialize_node_data(entries: &[NodeEntry]) -> RepoResult<NodeData> {
     let mut data = NodeData {
         l: None,
         e: Vec::new(),
@@ -95,14 +98,16 @@ pub async fn serialize_node_data(entries: &[NodeEntry]) -> Result<NodeData> {
             ensure_valid_mst_key(&l.key)?;
             let prefix_len = count_prefix_len(last_key.to_owned(), l.key.to_owned())?;
             data.e.push(TreeEntry {
-                p: u8::try_from(prefix_len)?,
+                p: u8::try_from(prefix_len).map_err(|_| RepoError::InvalidKey(l.key.clone()))?,
                 k: l.key[prefix_len..].to_owned().into_bytes(),
                 v: l.value,
                 t: subtree,
             });
             last_key = &l.key;
         } else {
-            return Err(anyhow!("Not a valid node: two subtrees next to each other"));
+            return Err(RepoError::InvalidMst(
+                "Not a valid node: two subtrees next to each other".to_string(),
+            ));
         }
     }
     Ok(data)
@@ -112,7 +117,7 @@ pub fn deserialize_node_data(
     storage: Arc<RwLock<dyn RepoStorage>>,
     data: &NodeData,
     layer: Option<u32>,
-) -> Result<Vec<NodeEntry>> {
+) -> RepoResult<Vec<NodeEntry>> {
     let mut entries: Vec<NodeEntry> = Vec::new();
     if let Some(l) = data.l {
         let new_layer: Option<u32>;
@@ -127,8 +132,9 @@ pub fn deserialize_node_data(
     }
     let mut last_key: String = "".to_owned();
     for entry in &data.e {
-        let key_str = str::from_utf8(entry.k.as_ref())?;
-        let p = usize::try_from(entry.p)?;
+        let key_str = str::from_utf8(entry.k.as_ref())
+            .map_err(|_| RepoError::InvalidMst("non-utf8 key".to_string()))?;
+        let p = usize::from(entry.p);
         let key = format!("{}{}", &last_key[0..p], key_str);
         ensure_valid_mst_key(&key)?;
         entries.push(NodeEntry::Leaf(Leaf {
@@ -151,7 +157,7 @@ pub fn deserialize_node_data(
     Ok(entries)
 }
 
-pub fn layer_for_entries(entries: &[NodeEntry]) -> Result<Option<u32>> {
+pub fn layer_for_entries(entries: &[NodeEntry]) -> RepoResult<Option<u32>> {
     let first_leaf = entries.into_iter().find(|entry| entry.is_leaf());
     if let Some(f) = first_leaf {
         match f {
@@ -163,7 +169,98 @@ pub fn layer_for_entries(entries: &[NodeEntry]) -> Result<Option<u32>> {
     }
 }
 
-pub fn leading_zeros_on_hash(key: &[u8]) -> Result<u32> {
+/// Resolves `start`/`end` to a range the same way Javascript Array.prototype.slice() does
+/// https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/Array/slice
+pub fn slice_range(len: usize, start: Option<isize>, end: Option<isize>) -> Range<usize> {
+    let resolve = |index: isize| -> usize {
+        if index < 0 {
+            (index + len as isize).max(0) as usize
+        } else {
+            (index as usize).min(len)
+        }
+    };
+    let start = start.map_or(0, resolve);
+    let end = end.map_or(len, resolve);
+    if end <= start {
+        0..0
+    } else {
+        start..end
+    }
+}
+
+/// Builds `entries[..head_end] + insert + entries[tail_start..]` in a single allocation,
+/// cloning only the entries that are kept
+pub fn splice_entries<I>(
+    entries: &[NodeEntry],
+    head_end: isize,
+    insert: I,
+    tail_start: isize,
+) -> Vec<NodeEntry>
+where
+    I: IntoIterator<Item = NodeEntry>,
+{
+    let head = slice_range(entries.len(), None, Some(head_end));
+    let tail = slice_range(entries.len(), Some(tail_start), None);
+    let insert = insert.into_iter();
+    let mut spliced = Vec::with_capacity(head.len() + insert.size_hint().0 + tail.len());
+    spliced.extend_from_slice(&entries[head]);
+    spliced.extend(insert);
+    spliced.extend_from_slice(&entries[tail]);
+    spliced
+}
+
+/// Most keys [`leading_zeros_on_hash`] remembers before starting over.
+const ZEROS_CACHE_LIMIT: usize = 65_536;
+
+tokio::task_local! {
+    /// Keys hashed within the current [`with_zeros_cache`] batch. Splits and merges re-derive a
+    /// node's layer from its first key over and over within one write batch, so the same
+    /// handful of keys get hashed many times. Task-local so it follows the batch across `.await`
+    /// points and is dropped with it, however the batch returns.
+    static ZEROS_CACHE: RefCell<HashMap<Vec<u8>, u32>>;
+}
+
+pub fn leading_zeros_on_hash(key: &[u8]) -> RepoResult<u32> {
+    let cached = ZEROS_CACHE
+        .try_with(|cache| cache.borrow().get(key).copied())
+        .ok()
+        .flatten();
+    if let Some(zeros) = cached {
+        return Ok(zeros);
+    }
+    let zeros = hash_leading_zeros(key);
+    // outside a batch there's nothing to remember it in
+    let _ = ZEROS_CACHE.try_with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if cache.len() >= ZEROS_CACHE_LIMIT {
+            cache.clear();
+        }
+        cache.insert(key.to_vec(), zeros);
+    });
+    Ok(zeros)
+}
+
+/// Leading zeros for a batch of keys, hashing each distinct key once. Hand the results to
+/// [`MST::add`] as `known_zeros`.
+pub fn leading_zeros_for_keys<'a, I>(keys: I) -> RepoResult<HashMap<&'a str, u32>>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut zeros = HashMap::new();
+    for key in keys {
+        if !zeros.contains_key(key) {
+            zeros.insert(key, leading_zeros_on_hash(key.as_bytes())?);
+        }
+    }
+    Ok(zeros)
+}
+
+/// Runs a write batch with its own cache of hashed keys, dropped when the batch finishes.
+pub async fn with_zeros_cache<F: Future>(batch: F) -> F::Output {
+    ZEROS_CACHE.scope(RefCell::new(HashMap::new()), batch).await
+}
+
+fn hash_leading_zeros(key: &[u8]) -> u32 {
     let digest = Sha256::digest(&*key);
     let hash: &[u8] = digest.as_ref();
     let mut leading_zeros = 0;
@@ -183,7 +280,7 @@ pub fn leading_zeros_on_hash(key: &[u8]) -> Result<u32> {
             break;
         }
     }
-    Ok(leading_zeros)
+    leading_zeros
 }
 
 pub type IdMapping = BTreeMap<String, Cid>;
@@ -191,7 +288,7 @@ pub type IdMapping = BTreeMap<String, Cid>;
 pub async fn random_cid(
     storage: &mut Option<&mut (dyn RepoStorage + '_)>,
     rev: Option<String>,
-) -> Result<Cid> {
+) -> RepoResult<Cid> {
     let record = json!({ "test": random_str(50) });
     let cid = cid_for_cbor(&record)?;
     let bytes = rsky_common::struct_to_cbor(&record)?;
@@ -205,13 +302,19 @@ pub async fn random_cid(
 
 pub async fn generate_bulk_data_keys(
     count: usize,
-    mut blockstore: Option<&mut (dyn RepoStorage + '_)>,
-) -> Result<IdMapping> {
+    blockstore: Option<&mut (dyn RepoStorage + '_)>,
+) -> RepoResult<IdMapping> {
     let mut obj: IdMapping = BTreeMap::new();
+    let mut blocks = BlockMap::new();
+    let mut rev = Ticker::new().next(None).to_string();
     for _ in 0..count {
-        let rev = Ticker::new().next(None).to_string();
+        rev = Ticker::new().next(None).to_string();
         let key = format!("com.example.record/{}", rev);
-        obj.insert(key, random_cid(&mut blockstore, Some(rev)).await?);
+        let cid = blocks.add(json!({ "test": random_str(50) }))?;
+        obj.insert(key, cid);
+    }
+    if let Some(blockstore) = blockstore {
+        blockstore.put_many(blocks, rev).await?;
     }
     Ok(obj)
 }
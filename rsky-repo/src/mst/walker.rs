@@ -1,5 +1,5 @@
+use crate::error::{RepoError, RepoResult};
 use crate::mst::{NodeEntry, MST};
-use anyhow::{bail, Result};
 use async_recursion::async_recursion;
 use std::fmt::Debug;
 
@@ -40,9 +40,11 @@ impl MstWalker {
     }
 
     /// return the current layer of the node you are walking
-    pub fn layer(&mut self) -> Result<usize> {
+    pub fn layer(&mut self) -> RepoResult<usize> {
         match self.status {
-            WalkerStatus::WalkerStatusDone(_) => bail!("Walk is done"),
+            WalkerStatus::WalkerStatusDone(_) => {
+                return Err(RepoError::InvalidMst("Walk is done".to_string()))
+            }
             WalkerStatus::WalkerStatusProgress(ref p) => {
                 if let Some(ref mst) = p.walking {
                     return Ok(mst.layer.unwrap_or(0) as usize);
@@ -54,12 +56,14 @@ impl MstWalker {
                 }
             }
         }
-        bail!("Could not identify layer of walk")
+        Err(RepoError::InvalidMst(
+            "Could not identify layer of walk".to_string(),
+        ))
     }
 
     /// move to the next node in the subtree, skipping over the subtree
     #[async_recursion(Sync)]
-    pub async fn step_over(&mut self) -> Result<()> {
+    pub async fn step_over(&mut self) -> RepoResult<()> {
         match self.status {
             WalkerStatus::WalkerStatusDone(_) => return Ok(()),
             WalkerStatus::WalkerStatusProgress(ref mut p) => {
@@ -88,7 +92,7 @@ impl MstWalker {
     }
 
     /// step into a subtree, throws if currently pointed at a leaf
-    pub async fn step_into(&mut self) -> Result<()> {
+    pub async fn step_into(&mut self) -> RepoResult<()> {
         let clone_of_current_status = self.status.clone();
         match self.status {
             WalkerStatus::WalkerStatusDone(_) => return Ok(()),
@@ -102,10 +106,15 @@ impl MstWalker {
                             p.curr = next.clone(); // Changes current to be this node
                             p.index = 0;
                         } else {
-                            bail!("Tried to step into a node with 0 entries which is invalid");
+                            return Err(RepoError::InvalidMst(
+                                "Tried to step into a node with 0 entries which is invalid"
+                                    .to_string(),
+                            ));
                         }
                     } else {
-                        bail!("No tree at pointer, cannot step into");
+                        return Err(RepoError::InvalidMst(
+                            "No tree at pointer, cannot step into".to_string(),
+                        ));
                     }
                 } else {
                     if let NodeEntry::MST(ref mut mst) = p.curr {
@@ -122,7 +131,9 @@ impl MstWalker {
                             self.status = WalkerStatus::WalkerStatusDone(WalkerStatusDone(true));
                         }
                     } else {
-                        bail!("The root of the tree cannot be a leaf");
+                        return Err(RepoError::InvalidMst(
+                            "The root of the tree cannot be a leaf".to_string(),
+                        ));
                     }
                 }
             }
@@ -132,7 +143,7 @@ impl MstWalker {
 
     /// advance the pointer to the next node in the tree,
     /// stepping into the current node if necessary
-    pub async fn advance(&mut self) -> Result<()> {
+    pub async fn advance(&mut self) -> RepoResult<()> {
         match self.status {
             WalkerStatus::WalkerStatusDone(_) => return Ok(()),
             WalkerStatus::WalkerStatusProgress(ref mut p) => {
@@ -1,9 +1,8 @@
 use crate::block_map::BlockMap;
-use crate::error::DataStoreError;
+use crate::error::{RepoError, RepoResult};
 use crate::storage::ObjAndBytes;
 use crate::types::RepoRecord;
 use crate::util::cbor_to_lex_record;
-use anyhow::Result;
 use lexicon_cid::Cid;
 use serde_cbor::Value as CborValue;
 
@@ -12,7 +11,7 @@ pub struct RecordAndBytes {
     pub bytes: Vec<u8>,
 }
 
-pub fn get_and_parse_record(blocks: &BlockMap, cid: Cid) -> Result<RecordAndBytes> {
+pub fn get_and_parse_record(blocks: &BlockMap, cid: Cid) -> RepoResult<RecordAndBytes> {
     let bytes = blocks.get(cid);
     return if let Some(b) = bytes {
         let record = cbor_to_lex_record(b.clone())?;
@@ -21,9 +20,7 @@ pub fn get_and_parse_record(blocks: &BlockMap, cid: Cid) -> Result<RecordAndByte
             bytes: b.clone(),
         })
     } else {
-        Err(anyhow::Error::new(DataStoreError::MissingBlock(
-            cid.to_string(),
-        )))
+        Err(RepoError::MissingBlock(cid))
     };
 }
 
@@ -31,14 +28,12 @@ pub fn get_and_parse_by_kind(
     blocks: &BlockMap,
     cid: Cid,
     check: impl FnOnce(CborValue) -> bool,
-) -> Result<ObjAndBytes> {
+) -> RepoResult<ObjAndBytes> {
     let bytes = blocks.get(cid);
     return if let Some(b) = bytes {
-        Ok(parse_obj_by_kind(b.clone(), cid, check)?)
+        parse_obj_by_kind(b.clone(), cid, check)
     } else {
-        Err(anyhow::Error::new(DataStoreError::MissingBlock(
-            cid.to_string(),
-        )))
+        Err(RepoError::MissingBlock(cid))
     };
 }
 
@@ -46,13 +41,12 @@ pub fn parse_obj_by_kind(
     bytes: Vec<u8>,
     cid: Cid,
     check: impl FnOnce(CborValue) -> bool,
-) -> Result<ObjAndBytes> {
-    let obj: CborValue = serde_ipld_dagcbor::from_slice(bytes.as_slice()).map_err(|error| {
-        anyhow::Error::new(DataStoreError::UnexpectedObject(cid)).context(error)
-    })?;
+) -> RepoResult<ObjAndBytes> {
+    let obj: CborValue = serde_ipld_dagcbor::from_slice(bytes.as_slice())
+        .map_err(|_| RepoError::UnexpectedObject(cid))?;
     if check(obj.clone()) {
         Ok(ObjAndBytes { obj, bytes })
     } else {
-        Err(anyhow::Error::new(DataStoreError::UnexpectedObject(cid)))
+        Err(RepoError::UnexpectedObject(cid))
     }
 }
@@ -1,8 +1,8 @@
+use crate::error::{RepoError, RepoResult};
 use crate::mst::MST;
 use crate::storage::types::RepoStorage;
 use crate::types::{Commit, VersionedCommit};
 use crate::util::ensure_v3_commit;
-use anyhow::Result;
 use lexicon_cid::Cid;
 use serde_cbor::Value as CborValue;
 use std::sync::Arc;
@@ -26,7 +26,7 @@ impl ReadableRepo {
         }
     }
 
-    pub async fn load(storage: Arc<RwLock<dyn RepoStorage>>, commit_cid: Cid) -> Result<Self> {
+    pub async fn load(storage: Arc<RwLock<dyn RepoStorage>>, commit_cid: Cid) -> RepoResult<Self> {
         let commit: CborValue = {
             let storage_guard = storage.read().await;
             storage_guard
@@ -41,7 +41,8 @@ impl ReadableRepo {
                 )
                 .await?
         };
-        let commit: VersionedCommit = serde_cbor::value::from_value(commit)?;
+        let commit: VersionedCommit = serde_cbor::value::from_value(commit)
+            .map_err(|_| RepoError::UnexpectedObject(commit_cid))?;
         let data = MST::load(storage.clone(), commit.data(), None)?;
         Ok(Self::new(
             storage,
@@ -1,15 +1,19 @@
 use crate::block_map::BlockMap;
+use crate::car::blocks_to_car_file;
 use crate::cid_set::CidSet;
 use crate::data_diff::DataDiff;
-use crate::error::DataStoreError;
-use crate::mst::MST;
+use crate::error::{RepoError, RepoResult};
+use crate::mst::util::{leading_zeros_for_keys, with_zeros_cache};
+use crate::mst::{Leaf, NodeEntry, MST};
 use crate::storage::types::RepoStorage;
 use crate::types::{
     CollectionContents, Commit, CommitData, RecordCreateOrUpdateOp, RecordWriteEnum, RecordWriteOp,
     RepoContents, RepoRecord, UnsignedCommit,
 };
 use crate::util;
-use anyhow::{bail, Result};
+use anyhow::anyhow;
+use async_stream::try_stream;
+use futures::{Stream, TryStreamExt};
 use lexicon_cid::Cid;
 use rsky_common;
 use rsky_common::ipld::cid_for_cbor;
@@ -27,6 +31,13 @@ pub struct CommitRecord {
     record: RepoRecord,
 }
 
+/// A freshly created repo along with its genesis commit and that commit's blocks as a CAR.
+pub struct GenesisRepo {
+    pub repo: Repo,
+    pub commit: CommitData,
+    pub car: Vec<u8>,
+}
+
 pub struct Repo {
     pub storage: Arc<RwLock<dyn RepoStorage>>, // get ipld blocks from db
     pub data: MST,
@@ -46,7 +57,7 @@ impl Repo {
     }
 
     // static
-    pub async fn load(storage: Arc<RwLock<dyn RepoStorage>>, cid: Option<Cid>) -> Result<Self> {
+    pub async fn load(storage: Arc<RwLock<dyn RepoStorage>>, cid: Option<Cid>) -> RepoResult<Self> {
         let commit_cid = if let Some(cid) = cid {
             Some(cid)
         } else {
@@ -59,14 +70,17 @@ impl Repo {
                     let storage_guard = storage.read().await;
                     match storage_guard.get_bytes(&commit_cid).await? {
                         Some(res) => res,
-                        None => bail!("Missing blocks for commit cid {commit_cid}"),
+                        None => return Err(RepoError::MissingBlock(commit_cid)),
                     }
                 };
-                let commit: Commit = serde_ipld_dagcbor::from_slice(commit_bytes.as_slice())?;
+                let commit: Commit = serde_ipld_dagcbor::from_slice(commit_bytes.as_slice())
+                    .map_err(|error| RepoError::InvalidCbor(error.to_string()))?;
                 let data = MST::load(storage.clone(), commit.data, None)?;
                 Ok(Repo::new(storage, data, commit, commit_cid))
             }
-            None => bail!("No cid provided and none in storage"),
+            None => Err(RepoError::Other(anyhow!(
+                "No cid provided and none in storage"
+            ))),
         }
     }
 
@@ -78,34 +92,35 @@ impl Repo {
         self.commit.version
     }
 
-    pub async fn walk_records(
-        &mut self,
+    pub fn walk_records(
+        &self,
         from: Option<String>,
-    ) -> impl Iterator<Item = CommitRecord> {
-        let mut iter: Vec<CommitRecord> = Vec::new();
-        for leaf in self
-            .data
-            .walk_leaves_from(&from.unwrap_or("".to_owned()))
-            .await
-        {
-            let path = util::parse_data_key(&leaf.key).unwrap();
-            let storage_guard = self.storage.read().await;
-            let record = storage_guard.read_record(&leaf.value).await.unwrap();
-            iter.push(CommitRecord {
-                collection: path.collection,
-                rkey: path.rkey,
-                cid: leaf.value,
-                record,
-            })
+    ) -> impl Stream<Item = RepoResult<CommitRecord>> {
+        let storage = self.storage.clone();
+        let leaves = self.data.walk_leaves_from(&from.unwrap_or_default());
+        try_stream! {
+            for await leaf in leaves {
+                let leaf = leaf?;
+                let path = util::parse_data_key(&leaf.key)?;
+                let record = {
+                    let storage_guard = storage.read().await;
+                    storage_guard.read_record(&leaf.value).await?
+                };
+                yield CommitRecord {
+                    collection: path.collection,
+                    rkey: path.rkey,
+                    cid: leaf.value,
+                    record,
+                };
+            }
         }
-        iter.into_iter()
     }
 
     pub async fn get_record(
         &mut self,
         collection: String,
         rkey: String,
-    ) -> Result<Option<CborValue>> {
+    ) -> RepoResult<Option<CborValue>> {
         let data_key = format!("{}/{}", collection, rkey);
         let cid = self.data.get(&data_key).await?;
         let storage_guard = self.storage.read().await;
@@ -119,8 +134,8 @@ impl Repo {
         }
     }
 
-    pub async fn get_contents(&mut self) -> Result<RepoContents> {
-        let entries = self.data.list(None, None, None).await?;
+    pub async fn get_contents(&mut self) -> RepoResult<RepoContents> {
+        let entries: Vec<Leaf> = self.data.list(None, None, None).try_collect().await?;
         let cids = entries
             .clone()
             .into_iter()
@@ -129,10 +144,10 @@ impl Repo {
         let storage_guard = self.storage.read().await;
         let found = storage_guard.get_blocks(cids).await?;
         if found.missing.len() > 0 {
-            return Err(anyhow::Error::new(DataStoreError::MissingBlocks(
+            return Err(RepoError::MissingBlocks(
                 "getContents record".to_owned(),
                 found.missing,
-            )));
+            ));
         }
         let mut contents: RepoContents = BTreeMap::new();
         for entry in entries {
@@ -154,14 +169,26 @@ impl Repo {
         did: String,
         keypair: Keypair,
         initial_writes: Option<Vec<RecordCreateOrUpdateOp>>,
-    ) -> Result<CommitData> {
+    ) -> RepoResult<CommitData> {
         let mut new_blocks = BlockMap::new();
         let mut data = MST::create(storage, None, None).await?;
-        for record in initial_writes.unwrap_or(Vec::new()) {
-            let cid = new_blocks.add(record.record)?;
-            let data_key = util::format_data_key(record.collection, record.rkey);
-            data = data.add(&data_key, cid, None).await?;
-        }
+        let initial_writes = initial_writes.unwrap_or(Vec::new());
+        let records: Vec<&RepoRecord> = initial_writes.iter().map(|write| &write.record).collect();
+        let cids = new_blocks.add_batch(&records)?;
+        let data_keys: Vec<String> = initial_writes
+            .into_iter()
+            .map(|record| util::format_data_key(record.collection, record.rkey))
+            .collect();
+        let key_zeros = leading_zeros_for_keys(data_keys.iter().map(String::as_str))?;
+        with_zeros_cache(async {
+            for (data_key, cid) in data_keys.iter().zip(cids) {
+                data = data
+                    .add(data_key, cid, key_zeros.get(data_key.as_str()).copied())
+                    .await?;
+            }
+            Ok::<_, RepoError>(())
+        })
+        .await?;
         let data_cid: Cid = data.get_pointer().await?;
         let diff = DataDiff::of(&mut data, None).await?;
         new_blocks.add_map(diff.new_mst_blocks)?;
@@ -192,7 +219,7 @@ impl Repo {
     pub async fn create_from_commit(
         storage: Arc<RwLock<dyn RepoStorage>>,
         commit: CommitData,
-    ) -> Result<Self> {
+    ) -> RepoResult<Self> {
         {
             let storage_guard = storage.read().await;
             storage_guard.apply_commit(commit.clone(), None).await?;
@@ -206,42 +233,81 @@ impl Repo {
         did: String,
         keypair: Keypair,
         initial_writes: Option<Vec<RecordCreateOrUpdateOp>>,
-    ) -> Result<Self> {
+    ) -> RepoResult<Self> {
         let commit =
             Self::format_init_commit(storage.clone(), did, keypair, initial_writes).await?;
         Self::create_from_commit(storage, commit).await
     }
 
+    // static
+    /// Builds the full MST for `records` and signs it as the genesis commit in one pass, rather
+    /// than replaying a commit per record.
+    pub async fn create_from_records(
+        storage: Arc<RwLock<dyn RepoStorage>>,
+        did: String,
+        keypair: Keypair,
+        records: Vec<RecordCreateOrUpdateOp>,
+    ) -> RepoResult<GenesisRepo> {
+        let commit = Self::format_init_commit(storage.clone(), did, keypair, Some(records)).await?;
+        let car = blocks_to_car_file(Some(&commit.cid), commit.new_blocks.clone()).await?;
+        let repo = Self::create_from_commit(storage, commit.clone()).await?;
+        Ok(GenesisRepo { repo, commit, car })
+    }
+
     pub async fn format_commit(
         &mut self,
         to_write: RecordWriteEnum,
         keypair: Keypair,
-    ) -> Result<CommitData> {
+    ) -> RepoResult<CommitData> {
         let writes = match to_write {
             RecordWriteEnum::List(to_write) => to_write,
             RecordWriteEnum::Single(to_write) => vec![to_write],
         };
         let mut leaves = BlockMap::new();
+        let records: Vec<&RepoRecord> = writes
+            .iter()
+            .filter_map(|write| match write {
+                RecordWriteOp::Create(write) | RecordWriteOp::Update(write) => Some(&write.record),
+                RecordWriteOp::Delete(_) => None,
+            })
+            .collect();
+        let mut record_cids = leaves.add_batch(&records)?.into_iter();
+        let create_keys: Vec<String> = writes
+            .iter()
+            .filter_map(|write| match write {
+                RecordWriteOp::Create(write) => Some(util::format_data_key(
+                    write.collection.clone(),
+                    write.rkey.clone(),
+                )),
+                _ => None,
+            })
+            .collect();
+        let key_zeros = leading_zeros_for_keys(create_keys.iter().map(String::as_str))?;
 
         let mut data = self.data.clone(); // @TODO: Confirm if this should be clone
-        for write in writes.clone() {
-            match write {
-                RecordWriteOp::Create(write) => {
-                    let cid = leaves.add(write.record)?;
-                    let data_key = util::format_data_key(write.collection, write.rkey);
-                    data = data.add(&data_key, cid, None).await?;
-                }
-                RecordWriteOp::Update(write) => {
-                    let cid = leaves.add(write.record)?;
-                    let data_key = util::format_data_key(write.collection, write.rkey);
-                    data = data.update(&data_key, cid).await?;
-                }
-                RecordWriteOp::Delete(write) => {
-                    let data_key = util::format_data_key(write.collection, write.rkey);
-                    data = data.delete(&data_key).await?;
+        with_zeros_cache(async {
+            for write in writes.clone() {
+                match write {
+                    RecordWriteOp::Create(write) => {
+                        let cid = record_cids.next().expect("a cid for every record");
+                        let data_key = util::format_data_key(write.collection, write.rkey);
+                        let known_zeros = key_zeros.get(data_key.as_str()).copied();
+                        data = data.add(&data_key, cid, known_zeros).await?;
+                    }
+                    RecordWriteOp::Update(write) => {
+                        let cid = record_cids.next().expect("a cid for every record");
+                        let data_key = util::format_data_key(write.collection, write.rkey);
+                        data = data.update(&data_key, cid).await?;
+                    }
+                    RecordWriteOp::Delete(write) => {
+                        let data_key = util::format_data_key(write.collection, write.rkey);
+                        data = data.delete(&data_key).await?;
+                    }
                 }
             }
-        }
+            Ok::<_, RepoError>(())
+        })
+        .await?;
 
         let data_cid = data.get_pointer().await?;
         let diff = DataDiff::of(&mut data, Some(&mut self.data.clone())).await?;
@@ -260,7 +326,10 @@ impl Repo {
 
         let added_leaves = leaves.get_many(diff.new_leaf_cids.to_list())?;
         if added_leaves.missing.len() > 0 {
-            bail!("Missing leaf blocks: {:?}", added_leaves.missing);
+            return Err(RepoError::MissingBlocks(
+                "commit leaf".to_owned(),
+                added_leaves.missing,
+            ));
         }
         new_blocks.add_map(added_leaves.blocks.clone())?;
         relevant_blocks.add_map(added_leaves.blocks)?;
@@ -297,7 +366,7 @@ impl Repo {
         })
     }
 
-    pub async fn apply_commit(&self, commit_data: CommitData) -> Result<Self> {
+    pub async fn apply_commit(&self, commit_data: CommitData) -> RepoResult<Self> {
         let commit_data_cid = commit_data.cid.clone();
         {
             let storage_guard = self.storage.read().await;
@@ -310,12 +379,12 @@ impl Repo {
         &mut self,
         to_write: RecordWriteEnum,
         keypair: Keypair,
-    ) -> Result<Self> {
+    ) -> RepoResult<Self> {
         let commit = self.format_commit(to_write, keypair).await?;
         self.apply_commit(commit).await
     }
 
-    pub fn format_resign_commit(&self, rev: String, keypair: Keypair) -> Result<CommitData> {
+    pub fn format_resign_commit(&self, rev: String, keypair: Keypair) -> RepoResult<CommitData> {
         let commit = util::sign_commit(
             UnsignedCommit {
                 did: self.did(),
@@ -339,24 +408,86 @@ impl Repo {
         })
     }
 
-    pub async fn resign_commit(&mut self, rev: String, keypair: Keypair) -> Result<Self> {
+    pub async fn resign_commit(&mut self, rev: String, keypair: Keypair) -> RepoResult<Self> {
         let formatted = self.format_resign_commit(rev, keypair)?;
         self.apply_commit(formatted).await
     }
+
+    /// Builds a fresh MST out of `entries` (data keys and the record CIDs they point at) and
+    /// signs it as a genesis-style commit at `rev`, so a tree with damaged or stray nodes can
+    /// be replaced wholesale from an independent listing of the records. Tree nodes and
+    /// records the rebuilt tree doesn't reference are removed along with the old commit.
+    pub async fn format_squash_commit(
+        &self,
+        rev: String,
+        keypair: Keypair,
+        mut entries: Vec<(String, Cid)>,
+    ) -> RepoResult<CommitData> {
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut data = MST::create(self.storage.clone(), None, None).await?;
+        let key_zeros = leading_zeros_for_keys(entries.iter().map(|(key, _)| key.as_str()))?;
+        with_zeros_cache(async {
+            for (key, cid) in entries.iter() {
+                data = data
+                    .add(key, *cid, key_zeros.get(key.as_str()).copied())
+                    .await?;
+            }
+            Ok::<_, RepoError>(())
+        })
+        .await?;
+        let data_cid: Cid = data.get_pointer().await?;
+        let diff = DataDiff::of(&mut data, None).await?;
+        let mut new_blocks = diff.new_mst_blocks;
+        let mut removed_cids = CidSet::new(Some(vec![self.cid]));
+        // only what's still reachable, since the old tree may be missing blocks
+        let prev_entries: Vec<NodeEntry> = self.data.clone().walk_reachable().try_collect().await?;
+        for entry in prev_entries {
+            let cid = match entry {
+                NodeEntry::MST(subtree) => subtree.get_pointer().await?,
+                NodeEntry::Leaf(leaf) => leaf.value,
+            };
+            if !new_blocks.has(cid) && !diff.new_leaf_cids.has(cid) {
+                removed_cids.add(cid);
+            }
+        }
+        let commit = util::sign_commit(
+            UnsignedCommit {
+                did: self.did(),
+                version: 3,
+                rev: rev.clone(),
+                prev: None, // added for backwards compatibility with v2
+                data: data_cid,
+            },
+            keypair,
+        )?;
+        let commit_cid = new_blocks.add(commit)?;
+        Ok(CommitData {
+            cid: commit_cid,
+            rev,
+            since: None,
+            prev: None,
+            new_blocks: new_blocks.clone(),
+            relevant_blocks: new_blocks,
+            removed_cids,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::car::{blocks_to_car_file, read_car_with_root};
+    use crate::car::read_car_with_root;
     use crate::mst::util::{random_cid, random_str};
     use crate::parse::get_and_parse_record;
     use crate::storage::memory_blockstore::MemoryBlockstore;
-    use crate::sync::consumer::{verify_proofs, verify_records, verify_repo, ConsumerError};
+    use crate::sync::consumer::{
+        verify_commit_slice, verify_proofs, verify_records, verify_repo, CommitSliceOp,
+        ConsumerError,
+    };
     use crate::sync::provider::{get_full_repo, get_records};
     use crate::types::{RecordCidClaim, RecordDeleteOp, RecordPath, WriteOpAction};
     use crate::util::{stream_to_buffer, verify_commit_sig};
-    use anyhow::Result;
+    use anyhow::{bail, Result};
     use futures::pin_mut;
     use rand::prelude::SliceRandom;
     use rand::thread_rng;
@@ -378,29 +509,37 @@ mod tests {
             .expect("Simple object failed to serialize")
     }
 
-    pub async fn fill_repo(
-        mut repo: Repo,
-        keypair: Keypair,
+    pub fn generate_records(
         items_per_collection: usize,
-    ) -> Result<FillRepoOutput> {
+    ) -> (Vec<RecordCreateOrUpdateOp>, RepoContents) {
         let mut repo_data: RepoContents = Default::default();
-        let mut writes: Vec<RecordWriteOp> = Default::default();
+        let mut records: Vec<RecordCreateOrUpdateOp> = Default::default();
         for coll_name in TEST_COLLECTIONS {
             let mut coll_data: CollectionContents = Default::default();
             for _ in 0..items_per_collection {
                 let object = generate_object();
                 let rkey = Ticker::new().next(None).to_string();
                 coll_data.insert(rkey.clone(), object.clone());
-                writes.push(RecordWriteOp::Create(RecordCreateOrUpdateOp {
+                records.push(RecordCreateOrUpdateOp {
                     action: WriteOpAction::Create,
                     collection: coll_name.to_string(),
                     rkey,
                     record: object,
-                }));
+                });
             }
             repo_data.insert(coll_name.to_string(), coll_data);
         }
-        let writes = RecordWriteEnum::List(writes);
+        (records, repo_data)
+    }
+
+    pub async fn fill_repo(
+        mut repo: Repo,
+        keypair: Keypair,
+        items_per_collection: usize,
+    ) -> Result<FillRepoOutput> {
+        let (records, repo_data) = generate_records(items_per_collection);
+        let writes =
+            RecordWriteEnum::List(records.into_iter().map(RecordWriteOp::Create).collect());
         let updated = repo.apply_writes(writes, keypair).await?;
         Ok(FillRepoOutput {
             repo: updated,
@@ -451,7 +590,7 @@ mod tests {
                 )
                 .await?;
         }
-        Repo::load(repo.storage.clone(), Some(commit_cid)).await
+        Ok(Repo::load(repo.storage.clone(), Some(commit_cid)).await?)
     }
 
     pub async fn contents_to_claims(contents: RepoContents) -> Result<Vec<RecordCidClaim>> {
@@ -736,6 +875,43 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn squashes_repo() -> Result<()> {
+        let storage = MemoryBlockstore::default();
+        let secp = Secp256k1::new();
+        let keypair = Keypair::new(&secp, &mut thread_rng());
+        let did_key = encode_did_key(&keypair.public_key());
+        let mut repo = Repo::create(
+            Arc::new(RwLock::new(storage)),
+            did_key.clone(),
+            keypair,
+            None,
+        )
+        .await?;
+        let filled = fill_repo(repo, keypair, 20).await?;
+        repo = filled.repo;
+        let rev = Ticker::new().next(Some(TID(repo.commit.rev.clone())));
+        // rebuilt from the records themselves rather than the tree's leaves
+        let mut entries = Vec::new();
+        for (collection, records) in filled.data.iter() {
+            for (rkey, record) in records.iter() {
+                let cid = BlockMap::new().add(record)?;
+                entries.push((util::format_data_key(collection.clone(), rkey.clone()), cid));
+            }
+        }
+        let commit = repo
+            .format_squash_commit(rev.0.clone(), keypair, entries)
+            .await?;
+        assert!(commit.removed_cids.has(repo.cid));
+        let prev_data = repo.commit.data;
+        repo = repo.apply_commit(commit).await?;
+        assert_eq!(repo.commit.rev, rev.0);
+        assert_eq!(repo.commit.data, prev_data);
+        assert_eq!(repo.get_contents().await?, filled.data);
+        assert!(verify_commit_sig(repo.commit, &did_key)?);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn sets_correct_did() -> Result<()> {
         let storage = MemoryBlockstore::default();
@@ -871,13 +1047,7 @@ mod tests {
                 &did_key,
             )
             .await;
-            assert!(matches!(
-                result
-                    .unwrap_err()
-                    .downcast_ref::<DataStoreError>()
-                    .unwrap(),
-                DataStoreError::MissingBlock(_)
-            ));
+            assert!(matches!(result.unwrap_err(), RepoError::MissingBlock(_)));
         }
 
         for rkey in keys {
@@ -1196,12 +1366,120 @@ mod tests {
         .await?;
         let result = verify_proofs(proofs, claims.clone(), repo_did, &did_key).await;
         assert!(matches!(
-            result.unwrap_err().downcast_ref::<ConsumerError>().unwrap(),
-            ConsumerError::RepoVerificationError(_)
+            result.unwrap_err(),
+            RepoError::BadSignature(cid) if cid == bad_repo.cid
+        ));
+        Ok(())
+    }
+
+    async fn commit_slice(
+        repo: &Repo,
+        claims: &[RecordCidClaim],
+    ) -> Result<(Vec<u8>, Vec<CommitSliceOp>)> {
+        let paths: Vec<RecordPath> = claims
+            .iter()
+            .map(|c| RecordPath {
+                collection: c.collection.clone(),
+                rkey: c.rkey.clone(),
+            })
+            .collect();
+        let ops = claims
+            .iter()
+            .map(|c| CommitSliceOp {
+                action: WriteOpAction::Create,
+                path: format!("{}/{}", c.collection, c.rkey),
+                cid: c.cid,
+            })
+            .collect();
+        let blocks = get_records(repo.storage.clone(), repo.cid, paths).await?;
+        Ok((blocks, ops))
+    }
+
+    #[tokio::test]
+    async fn verifies_a_commit_slice() -> Result<()> {
+        let storage = MemoryBlockstore::default();
+        let secp = Secp256k1::new();
+        let keypair = Keypair::new(&secp, &mut thread_rng());
+        let repo_did = "did:example:test";
+        let repo = Repo::create(
+            Arc::new(RwLock::new(storage)),
+            repo_did.to_string(),
+            keypair,
+            None,
+        )
+        .await?;
+        let did_key = encode_did_key(&keypair.public_key());
+        let filled = fill_repo(repo, keypair, 5).await?;
+        let claims = contents_to_claims(filled.data).await?;
+        let (blocks, ops) = commit_slice(&filled.repo, &claims[..3]).await?;
+
+        let verified =
+            verify_commit_slice(blocks, filled.repo.cid, repo_did, &did_key, &ops).await?;
+        assert_eq!(verified.cid, filled.repo.cid);
+        assert_eq!(verified.commit.rev, filled.repo.commit.rev);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rejects_a_commit_slice_op_it_does_not_prove() -> Result<()> {
+        let storage = MemoryBlockstore::default();
+        let secp = Secp256k1::new();
+        let keypair = Keypair::new(&secp, &mut thread_rng());
+        let repo_did = "did:example:test";
+        let repo = Repo::create(
+            Arc::new(RwLock::new(storage)),
+            repo_did.to_string(),
+            keypair,
+            None,
+        )
+        .await?;
+        let did_key = encode_did_key(&keypair.public_key());
+        let filled = fill_repo(repo, keypair, 5).await?;
+        let claims = contents_to_claims(filled.data).await?;
+        let (blocks, mut ops) = commit_slice(&filled.repo, &claims[..2]).await?;
+        // both record blocks are in the slice, but the first path holds the first record
+        ops[0].cid = claims[1].cid;
+
+        let result = verify_commit_slice(blocks, filled.repo.cid, repo_did, &did_key, &ops).await;
+        assert!(matches!(
+            result.unwrap_err(),
+            RepoError::Verification(ConsumerError::RepoVerificationError(_))
         ));
         Ok(())
     }
 
+    #[tokio::test]
+    async fn creates_repo_from_records() -> Result<()> {
+        let secp = Secp256k1::new();
+        let keypair = Keypair::new(&secp, &mut thread_rng());
+        let repo_did = "did:example:test";
+        let did_key = encode_did_key(&keypair.public_key());
+        let (records, repo_data) = generate_records(50);
+        let genesis = Repo::create_from_records(
+            Arc::new(RwLock::new(MemoryBlockstore::default())),
+            repo_did.to_string(),
+            keypair,
+            records,
+        )
+        .await?;
+        let mut repo = genesis.repo;
+        assert_eq!(repo.cid, genesis.commit.cid);
+        assert_eq!(repo.get_contents().await?, repo_data);
+
+        let mut car = read_car_with_root(genesis.car).await?;
+        assert_eq!(car.root, repo.cid);
+        let verified = verify_repo(
+            &mut car.blocks,
+            car.root,
+            Some(&repo_did.to_string()),
+            Some(&did_key),
+            None,
+        )
+        .await?;
+        assert_eq!(verified.creates.len(), 100);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn sync_a_full_repo() -> Result<()> {
         let storage = MemoryBlockstore::default();
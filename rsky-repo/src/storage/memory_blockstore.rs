@@ -1,12 +1,13 @@
 use crate::block_map::{BlockMap, BlocksAndMissing};
+use crate::error::RepoResult;
 use crate::storage::readable_blockstore::ReadableBlockstore;
 use crate::storage::types::RepoStorage;
+use crate::storage::verify_blocks;
 use crate::types::CommitData;
 use anyhow::Result;
 use lexicon_cid::Cid;
 use std::future::Future;
 use std::pin::Pin;
-use std::str::FromStr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -15,6 +16,8 @@ pub struct MemoryBlockstore {
     pub blocks: Arc<RwLock<BlockMap>>,
     pub root: Arc<RwLock<Option<Cid>>>,
     pub rev: Arc<RwLock<Option<String>>>,
+    /// Re-hash blocks against their CIDs as they're read.
+    pub verify_on_read: bool,
 }
 
 impl Default for MemoryBlockstore {
@@ -23,12 +26,13 @@ impl Default for MemoryBlockstore {
             blocks: Arc::new(RwLock::new(BlockMap::new())),
             root: Arc::new(RwLock::new(None)),
             rev: Arc::new(RwLock::new(None)),
+            verify_on_read: false,
         }
     }
 }
 
 impl MemoryBlockstore {
-    pub async fn new(blocks: Option<BlockMap>) -> Result<Self> {
+    pub async fn new(blocks: Option<BlockMap>) -> RepoResult<Self> {
         let this = Self::default();
         if let Some(blocks) = blocks {
             let mut block_guard = this.blocks.write().await;
@@ -36,6 +40,11 @@ impl MemoryBlockstore {
         }
         Ok(this)
     }
+
+    pub fn with_verify_on_read(mut self, verify_on_read: bool) -> Self {
+        self.verify_on_read = verify_on_read;
+        self
+    }
 }
 
 impl ReadableBlockstore for MemoryBlockstore {
@@ -47,7 +56,12 @@ impl ReadableBlockstore for MemoryBlockstore {
             let block_guard = self.blocks.read().await;
             match block_guard.get(*cid) {
                 None => Ok(None),
-                Some(res) => Ok(Some(res.clone())),
+                Some(res) => {
+                    if self.verify_on_read {
+                        verify_blocks(&[(*cid, res.as_slice())])?;
+                    }
+                    Ok(Some(res.clone()))
+                }
             }
         })
     }
@@ -68,7 +82,16 @@ impl ReadableBlockstore for MemoryBlockstore {
     ) -> Pin<Box<dyn Future<Output = Result<BlocksAndMissing>> + Send + Sync + 'a>> {
         Box::pin(async move {
             let mut block_guard = self.blocks.write().await;
-            block_guard.get_many(cids)
+            let got = block_guard.get_many(cids)?;
+            if self.verify_on_read {
+                let entries = got.blocks.entries()?;
+                let to_verify: Vec<(Cid, &[u8])> = entries
+                    .iter()
+                    .map(|entry| (entry.cid, entry.bytes.as_slice()))
+                    .collect();
+                verify_blocks(&to_verify)?;
+            }
+            Ok(got)
         })
     }
 }
@@ -100,7 +123,7 @@ impl RepoStorage for MemoryBlockstore {
     ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + Sync + 'a>> {
         Box::pin(async move {
             let mut block_guard = self.blocks.write().await;
-            block_guard.add_map(to_put)
+            Ok(block_guard.add_map(to_put)?)
         })
     }
 
@@ -133,10 +156,37 @@ impl RepoStorage for MemoryBlockstore {
             for cid in rm_cids {
                 block_guard.delete(cid)?;
             }
-            for (cid, bytes) in commit.new_blocks.map.iter() {
-                block_guard.set(Cid::from_str(cid)?, bytes.0.clone());
-            }
-            Ok(())
+            Ok(block_guard.add_map(commit.new_blocks)?)
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::RepoError;
+
+    #[tokio::test]
+    async fn test_verify_on_read() {
+        let mut blocks = BlockMap::new();
+        let cid = blocks.add("hello").unwrap();
+        let other = blocks.add("world").unwrap();
+        // bit-rot: the bytes stored under `cid` are someone else's
+        blocks.set(cid, blocks.get(other).unwrap().clone());
+
+        let store = MemoryBlockstore::new(Some(blocks.clone())).await.unwrap();
+        assert!(store.get_bytes(&cid).await.unwrap().is_some());
+
+        let store = MemoryBlockstore::new(Some(blocks))
+            .await
+            .unwrap()
+            .with_verify_on_read(true);
+        assert!(store.get_bytes(&other).await.unwrap().is_some());
+        let error = store.get_bytes(&cid).await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<RepoError>(),
+            Some(RepoError::CorruptBlock(corrupt)) if *corrupt == cid
+        ));
+        assert!(store.get_blocks(vec![cid, other]).await.is_err());
+    }
+}
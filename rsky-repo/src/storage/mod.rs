@@ -1,4 +1,6 @@
+use crate::error::{RepoError, RepoResult};
 use lexicon_cid::Cid;
+use rsky_common::ipld::find_mismatched_cids;
 use serde_cbor::Value as CborValue;
 use serde_json::Value as JsonValue;
 use std::collections::BTreeMap;
@@ -42,6 +44,15 @@ pub enum RepoRootError {
     RepoRootNotFoundError,
 }
 
+/// Re-hashes `blocks` against their CIDs, for storage that verifies on read. Fails with
+/// [`RepoError::CorruptBlock`] for the first block whose bytes don't match.
+pub fn verify_blocks(blocks: &[(Cid, &[u8])]) -> RepoResult<()> {
+    match find_mismatched_cids(blocks)?.first() {
+        None => Ok(()),
+        Some(cid) => Err(RepoError::CorruptBlock(*cid)),
+    }
+}
+
 pub mod memory_blockstore;
 pub mod readable_blockstore;
 pub mod sync_storage;
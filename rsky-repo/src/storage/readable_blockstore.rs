@@ -1,5 +1,5 @@
 use crate::block_map::BlocksAndMissing;
-use crate::error::DataStoreError;
+use crate::error::{RepoError, RepoResult};
 use crate::parse;
 use crate::storage::ObjAndBytes;
 use crate::types::RepoRecord;
@@ -28,15 +28,13 @@ pub trait ReadableBlockstore: Send + Sync {
         &'a self,
         cid: &'a Cid,
         check: Box<dyn Fn(CborValue) -> bool + Send + Sync>,
-    ) -> Pin<Box<dyn Future<Output = Result<Option<ObjAndBytes>>> + Send + Sync + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = RepoResult<Option<ObjAndBytes>>> + Send + Sync + 'a>> {
         Box::pin(async move {
             let bytes = match self.get_bytes(cid).await {
                 Ok(Some(bytes)) => bytes,
                 _ => return Ok(None),
             };
-            parse::parse_obj_by_kind(bytes, *cid, move |v| check(v))
-                .map(Some)
-                .map_err(Into::into)
+            parse::parse_obj_by_kind(bytes, *cid, move |v| check(v)).map(Some)
         })
     }
 
@@ -44,11 +42,11 @@ pub trait ReadableBlockstore: Send + Sync {
         &'a self,
         cid: &'a Cid,
         check: Box<dyn Fn(CborValue) -> bool + Send + Sync>,
-    ) -> Pin<Box<dyn Future<Output = Result<ObjAndBytes>> + Send + Sync + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = RepoResult<ObjAndBytes>> + Send + Sync + 'a>> {
         Box::pin(async move {
             self.attempt_read(cid, check)
                 .await?
-                .ok_or_else(|| DataStoreError::MissingBlock(cid.to_string()).into())
+                .ok_or(RepoError::MissingBlock(*cid))
         })
     }
 
@@ -56,7 +54,7 @@ pub trait ReadableBlockstore: Send + Sync {
         &'a self,
         cid: &'a Cid,
         check: Box<dyn Fn(CborValue) -> bool + Send + Sync>,
-    ) -> Pin<Box<dyn Future<Output = Result<CborValue>> + Send + Sync + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = RepoResult<CborValue>> + Send + Sync + 'a>> {
         Box::pin(async move { Ok(self.read_obj_and_bytes(cid, check).await?.obj) })
     }
 
@@ -75,13 +73,13 @@ pub trait ReadableBlockstore: Send + Sync {
     fn read_record<'a>(
         &'a self,
         cid: &'a Cid,
-    ) -> Pin<Box<dyn Future<Output = Result<RepoRecord>> + Send + Sync + 'a>> {
+    ) -> Pin<Box<dyn Future<Output = RepoResult<RepoRecord>> + Send + Sync + 'a>> {
         Box::pin(async move {
             let bytes = self.get_bytes(cid).await?;
             bytes
                 .map(|bytes| cbor_to_lex_record(bytes))
                 .transpose()?
-                .ok_or_else(|| DataStoreError::MissingBlock(cid.to_string()).into())
+                .ok_or(RepoError::MissingBlock(*cid))
         })
     }
 }
@@ -1,6 +1,7 @@
 use crate::block_map::BlockMap;
 use crate::car::read_car_with_root;
 use crate::data_diff::DataDiff;
+use crate::error::{RepoError, RepoResult};
 use crate::mst::MST;
 use crate::readable_repo::ReadableRepo;
 use crate::repo::Repo;
@@ -10,10 +11,10 @@ use crate::storage::sync_storage::SyncStorage;
 use crate::storage::types::RepoStorage;
 use crate::types::{
     Commit, CommitData, RecordCidClaim, RecordClaim, RecordPath, VerifiedDiff, VerifiedRepo,
+    WriteOpAction,
 };
 use crate::util;
 use crate::util::{ensure_creates, parse_data_key, verify_commit_sig};
-use anyhow::{bail, Result};
 use lexicon_cid::Cid;
 use serde_cbor::Value as CborValue;
 use std::sync::Arc;
@@ -30,6 +31,21 @@ pub struct VerifyRepoInput {
     pub ensure_leaves: Option<bool>,
 }
 
+/// One op from a firehose `#commit` frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitSliceOp {
+    pub action: WriteOpAction,
+    pub path: String,
+    /// The record's new cid; `None` for deletes.
+    pub cid: Option<Cid>,
+}
+
+#[derive(Debug)]
+pub struct VerifiedCommitSlice {
+    pub cid: Cid,
+    pub commit: Commit,
+}
+
 #[derive(Error, Debug)]
 pub enum ConsumerError {
     #[error("RepoVerificationError: {0}")]
@@ -42,7 +58,7 @@ pub async fn verify_repo(
     did: Option<&String>,
     signing_key: Option<&String>,
     opts: Option<VerifyRepoInput>,
-) -> Result<VerifiedRepo> {
+) -> RepoResult<VerifiedRepo> {
     let diff = verify_diff(None, blocks, head, did, signing_key, opts).await?;
     let creates = ensure_creates(diff.writes)?;
     Ok(VerifiedRepo {
@@ -58,7 +74,7 @@ pub async fn verify_diff(
     did: Option<&String>,
     signing_key: Option<&String>,
     opts: Option<VerifyRepoInput>,
-) -> Result<VerifiedDiff> {
+) -> RepoResult<VerifiedDiff> {
     let ensure_leaves = match opts {
         None => true,
         Some(opts) => opts.ensure_leaves.unwrap_or(true),
@@ -81,7 +97,7 @@ pub async fn verify_diff(
     let mut new_blocks = diff.new_mst_blocks;
     let leaves = update_blocks.get_many(diff.new_leaf_cids.to_list())?;
     if leaves.missing.len() > 0 && ensure_leaves {
-        bail!("missing leaf blocks: {:?}", leaves.missing);
+        return Err(RepoError::MissingBlocks("leaf".to_owned(), leaves.missing));
     }
     new_blocks.add_map(leaves.blocks)?;
     let mut removed_cids = diff.removed_cids;
@@ -119,25 +135,17 @@ pub async fn verify_repo_root(
     head: Cid,
     did: Option<&String>,
     signing_key: Option<&String>,
-) -> Result<ReadableRepo> {
+) -> RepoResult<ReadableRepo> {
     let repo = ReadableRepo::load(storage, head).await?;
     if let Some(did) = did {
         if repo.did() != did {
-            return Err(ConsumerError::RepoVerificationError(format!(
-                "Invalid repo did: {}",
-                repo.did()
-            ))
-            .into());
+            return Err(RepoError::InvalidDid(repo.did().to_string()));
         }
     }
     if let Some(signing_key) = signing_key {
         let valid_sig = verify_commit_sig(repo.commit.clone(), signing_key)?;
         if !valid_sig {
-            return Err(ConsumerError::RepoVerificationError(format!(
-                "Invalid signature on commit: {}",
-                repo.cid.to_string()
-            ))
-            .into());
+            return Err(RepoError::BadSignature(repo.cid));
         }
     }
     Ok(repo)
@@ -148,7 +156,7 @@ pub async fn verify_proofs(
     claims: Vec<RecordCidClaim>,
     did: &str,
     did_key: &String,
-) -> Result<VerifyProofsOutput> {
+) -> RepoResult<VerifyProofsOutput> {
     let car = read_car_with_root(proofs).await?;
     let blockstore = MemoryBlockstore::new(Some(car.blocks)).await?;
     let data: CborValue = blockstore
@@ -162,21 +170,14 @@ pub async fn verify_proofs(
             ),
         )
         .await?;
-    let commit: Commit = serde_cbor::value::from_value(data)?;
+    let commit: Commit =
+        serde_cbor::value::from_value(data).map_err(|_| RepoError::UnexpectedObject(car.root))?;
     if commit.did != did {
-        return Err(ConsumerError::RepoVerificationError(format!(
-            "Invalid repo did: {}",
-            commit.did
-        ))
-        .into());
+        return Err(RepoError::InvalidDid(commit.did.clone()));
     }
     match verify_commit_sig(commit.clone(), did_key)? {
         false => {
-            return Err(ConsumerError::RepoVerificationError(format!(
-                "Invalid signature on commit: {}",
-                car.root.to_string()
-            ))
-            .into());
+            return Err(RepoError::BadSignature(car.root));
         }
         true => {
             let mut mst = MST::load(Arc::new(RwLock::new(blockstore)), commit.data, None)?;
@@ -223,7 +224,7 @@ pub async fn verify_records(
     proofs: Vec<u8>,
     did: &str,
     signing_key: &String,
-) -> Result<Vec<RecordClaim>> {
+) -> RepoResult<Vec<RecordClaim>> {
     let car = read_car_with_root(proofs).await?;
     let blockstore = MemoryBlockstore::new(Some(car.blocks)).await?;
     let data: CborValue = blockstore
@@ -237,21 +238,14 @@ pub async fn verify_records(
             ),
         )
         .await?;
-    let commit: Commit = serde_cbor::value::from_value(data)?;
+    let commit: Commit =
+        serde_cbor::value::from_value(data).map_err(|_| RepoError::UnexpectedObject(car.root))?;
     if commit.did != did {
-        return Err(ConsumerError::RepoVerificationError(format!(
-            "Invalid repo did: {}",
-            commit.did
-        ))
-        .into());
+        return Err(RepoError::InvalidDid(commit.did.clone()));
     }
     match verify_commit_sig(commit.clone(), signing_key)? {
         false => {
-            return Err(ConsumerError::RepoVerificationError(format!(
-                "Invalid signature on commit: {}",
-                car.root.to_string()
-            ))
-            .into());
+            return Err(RepoError::BadSignature(car.root));
         }
         true => {
             let mst = MST::load(Arc::new(RwLock::new(blockstore)), commit.data, None)?;
@@ -274,3 +268,91 @@ pub async fn verify_records(
         }
     }
 }
+
+/// Verifies a firehose `#commit` frame on its own, without the previous state of the repo. The
+/// CAR slice must be rooted at `commit_cid`, hold a commit for `did` signed by `signing_key`, and
+/// carry enough of the MST to prove each op's path now resolves to the op's cid, or to nothing
+/// for deletes. Record blocks for creates and updates must be included too.
+pub async fn verify_commit_slice(
+    blocks: Vec<u8>,
+    commit_cid: Cid,
+    did: &str,
+    signing_key: &String,
+    ops: &[CommitSliceOp],
+) -> RepoResult<VerifiedCommitSlice> {
+    let car = read_car_with_root(blocks).await?;
+    if car.root != commit_cid {
+        return Err(ConsumerError::RepoVerificationError(format!(
+            "Commit slice is rooted at {} rather than commit {commit_cid}",
+            car.root
+        ))
+        .into());
+    }
+    for op in ops {
+        if op.action == WriteOpAction::Delete {
+            continue;
+        }
+        match op.cid {
+            None => {
+                return Err(ConsumerError::RepoVerificationError(format!(
+                    "Missing cid for {} of {}",
+                    op.action, op.path
+                ))
+                .into())
+            }
+            Some(cid) if !car.blocks.has(cid) => {
+                return Err(ConsumerError::RepoVerificationError(format!(
+                    "Commit slice is missing record block {cid} for {}",
+                    op.path
+                ))
+                .into())
+            }
+            Some(_) => (),
+        }
+    }
+    let blockstore = MemoryBlockstore::new(Some(car.blocks)).await?;
+    let data: CborValue = blockstore
+        .read_obj(
+            &car.root,
+            Box::new(
+                |obj: CborValue| match serde_cbor::value::from_value::<Commit>(obj.clone()) {
+                    Ok(_) => true,
+                    Err(_) => false,
+                },
+            ),
+        )
+        .await?;
+    let commit: Commit =
+        serde_cbor::value::from_value(data).map_err(|_| RepoError::UnexpectedObject(car.root))?;
+    if commit.did != did {
+        return Err(RepoError::InvalidDid(commit.did.clone()));
+    }
+    if !verify_commit_sig(commit.clone(), signing_key)? {
+        return Err(RepoError::BadSignature(commit_cid));
+    }
+    let mut mst = MST::load(Arc::new(RwLock::new(blockstore)), commit.data, None)?;
+    for op in ops {
+        // a missing MST block on the way to the key means the slice doesn't prove this op
+        let found = mst.get(&op.path).await.map_err(|error| {
+            ConsumerError::RepoVerificationError(format!(
+                "Commit slice does not cover {}: {error}",
+                op.path
+            ))
+        })?;
+        let expected = match op.action {
+            WriteOpAction::Delete => None,
+            _ => op.cid,
+        };
+        if found != expected {
+            return Err(ConsumerError::RepoVerificationError(format!(
+                "Expected {expected:?} at {} but the commit has {found:?}",
+                op.path
+            ))
+            .into());
+        }
+    }
+    Ok(VerifiedCommitSlice {
+        cid: commit_cid,
+        commit,
+    })
+}
@@ -2,7 +2,7 @@ use crate::block_map::BlockMap;
 use crate::car;
 use crate::car::blocks_to_car_file;
 use crate::cid_set::CidSet;
-use crate::error::DataStoreError;
+use crate::error::{RepoError, RepoResult};
 use crate::mst::MST;
 use crate::storage::types::RepoStorage;
 use crate::types::{Commit, RecordPath};
@@ -19,7 +19,7 @@ use tokio::sync::RwLock;
 pub async fn get_full_repo(
     storage: Arc<RwLock<dyn RepoStorage>>,
     commit_cid: Cid,
-) -> Result<impl Stream<Item = Result<Vec<u8>>> + Send + 'static> {
+) -> RepoResult<impl Stream<Item = Result<Vec<u8>>> + Send + 'static> {
     Ok(car::write_car(
         Some(&commit_cid),
         move |mut car: CarWriter<DuplexStream>| {
@@ -44,7 +44,7 @@ pub async fn get_full_repo(
 
                 // Load the MST and write it to the CAR stream:
                 let mut mst = MST::load(storage.clone(), data.data, None)?;
-                mst.write_to_car_stream(car).await
+                Ok(mst.write_to_car_stream(car).await?)
             }
         },
     )
@@ -55,7 +55,7 @@ pub async fn get_records(
     storage: Arc<RwLock<dyn RepoStorage>>,
     commit_cid: Cid,
     paths: Vec<RecordPath>,
-) -> Result<Vec<u8>> {
+) -> RepoResult<Vec<u8>> {
     let mut car = BlockMap::new();
     let commit = {
         let storage_guard = storage.read().await;
@@ -71,18 +71,17 @@ pub async fn get_records(
             )
             .await?
     };
-    let data: Commit = serde_cbor::value::from_value(commit.obj)?;
+    let data: Commit = serde_cbor::value::from_value(commit.obj)
+        .map_err(|_| RepoError::UnexpectedObject(commit_cid))?;
     car.set(commit_cid, commit.bytes);
     let mst = MST::load(storage.clone(), data.data, None)?;
     let cids_for_paths = stream::iter(paths)
         .then(|p| {
             let mut mst_clone = mst.clone();
             async move {
-                Ok::<Vec<Cid>, anyhow::Error>(
-                    mst_clone
-                        .cids_for_path(util::format_data_key(p.collection, p.rkey))
-                        .await?,
-                )
+                mst_clone
+                    .cids_for_path(util::format_data_key(p.collection, p.rkey))
+                    .await
             }
         })
         .collect::<Vec<_>>()
@@ -99,10 +98,10 @@ pub async fn get_records(
     let storage_guard = storage.read().await;
     let found = storage_guard.get_blocks(all_cids.to_list()).await?;
     if found.missing.len() > 0 {
-        return Err(anyhow::Error::new(DataStoreError::MissingBlocks(
+        return Err(RepoError::MissingBlocks(
             "writeRecordsToCarStream".to_owned(),
             found.missing,
-        )));
+        ));
     }
     for block in found.blocks.entries()? {
         car.set(block.cid, block.bytes)
@@ -0,0 +1,332 @@
+//! Seeded fixtures for repo tests. Builders created from the same seed always yield the same
+//! signing key, records, rkeys and revs, so commit CIDs and CAR bytes are stable across runs.
+//!
+//! Available to this crate's tests and, through the `test_helpers` feature, to downstream ones.
+
+use crate::car::blocks_to_car_file;
+use crate::repo::Repo;
+use crate::storage::memory_blockstore::MemoryBlockstore;
+use crate::sync::provider::{get_full_repo, get_records};
+use crate::types::{
+    Commit, CommitData, RecordCreateOrUpdateOp, RecordDeleteOp, RecordPath, RecordWriteEnum,
+    RecordWriteOp, RepoRecord, UnsignedCommit, WriteOpAction,
+};
+use crate::util::{sign_commit, stream_to_buffer};
+use anyhow::{bail, Result};
+use futures::pin_mut;
+use rand::distributions::Alphanumeric;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rsky_common::ipld::cid_for_cbor;
+use rsky_common::tid::TID;
+use rsky_common::{cbor_to_struct, struct_to_cbor};
+use rsky_crypto::utils::encode_did_key;
+use secp256k1::{Keypair, Secp256k1, SecretKey};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+// 2023-11-14T22:13:20Z in microseconds; fixture revs and rkeys count up from here
+const EPOCH_MICROS: usize = 1_700_000_000_000_000;
+const BASE32_SORTABLE: &[u8] = b"234567abcdefghijklmnopqrstuvwxyz";
+
+/// Everything a fixture draws from its seed: keys, record contents and TIDs.
+struct Seeded {
+    rng: StdRng,
+    clock: usize,
+    clock_id: usize,
+}
+
+impl Seeded {
+    fn new(seed: u64) -> Self {
+        Seeded {
+            rng: StdRng::seed_from_u64(seed),
+            clock: EPOCH_MICROS,
+            clock_id: (seed % 1024) as usize,
+        }
+    }
+
+    fn next_tid(&mut self) -> TID {
+        self.clock += 1;
+        TID::from_time(self.clock, self.clock_id)
+    }
+
+    fn keypair(&mut self) -> Keypair {
+        loop {
+            let bytes: [u8; 32] = self.rng.gen();
+            if let Ok(secret_key) = SecretKey::from_slice(&bytes) {
+                return Keypair::from_secret_key(&Secp256k1::new(), &secret_key);
+            }
+        }
+    }
+
+    fn did_plc(&mut self) -> String {
+        let id: String = (0..24)
+            .map(|_| BASE32_SORTABLE[self.rng.gen_range(0..BASE32_SORTABLE.len())] as char)
+            .collect();
+        format!("did:plc:{id}")
+    }
+
+    fn record(&mut self, collection: &str) -> RepoRecord {
+        let text: String = (&mut self.rng)
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        serde_json::from_value(json!({ "$type": collection, "text": text }))
+            .expect("Simple object failed to serialize")
+    }
+}
+
+/// Builds the genesis commit of a [`RepoFixture`].
+pub struct RepoBuilder {
+    seeded: Seeded,
+    did: String,
+    keypair: Keypair,
+    records: Vec<RecordCreateOrUpdateOp>,
+}
+
+impl RepoBuilder {
+    pub fn new(seed: u64) -> Self {
+        let mut seeded = Seeded::new(seed);
+        let keypair = seeded.keypair();
+        let did = seeded.did_plc();
+        RepoBuilder {
+            seeded,
+            did,
+            keypair,
+            records: Vec::new(),
+        }
+    }
+
+    /// Overrides the `did:plc` derived from the seed.
+    pub fn did(mut self, did: &str) -> Self {
+        self.did = did.to_string();
+        self
+    }
+
+    /// Adds `count` generated records in `collection` to the genesis commit.
+    pub fn records(mut self, collection: &str, count: usize) -> Self {
+        for _ in 0..count {
+            let rkey = self.seeded.next_tid().to_string();
+            let record = self.seeded.record(collection);
+            self = self.record(collection, &rkey, record);
+        }
+        self
+    }
+
+    pub fn record(mut self, collection: &str, rkey: &str, record: RepoRecord) -> Self {
+        self.records.push(RecordCreateOrUpdateOp {
+            action: WriteOpAction::Create,
+            collection: collection.to_string(),
+            rkey: rkey.to_string(),
+            record,
+        });
+        self
+    }
+
+    pub async fn build(mut self) -> Result<RepoFixture> {
+        let storage = Arc::new(RwLock::new(MemoryBlockstore::default()));
+        let commit = Repo::format_init_commit(
+            storage.clone(),
+            self.did.clone(),
+            self.keypair,
+            Some(self.records),
+        )
+        .await?;
+        let genesis = resign(commit, self.seeded.next_tid(), self.keypair)?;
+        let repo = Repo::create_from_commit(storage, genesis.clone()).await?;
+        Ok(RepoFixture {
+            did: self.did,
+            keypair: self.keypair,
+            repo,
+            genesis,
+            seeded: self.seeded,
+        })
+    }
+}
+
+/// An in-memory repo whose every commit is signed with a seed-derived key and rev.
+pub struct RepoFixture {
+    pub did: String,
+    pub keypair: Keypair,
+    pub repo: Repo,
+    pub genesis: CommitData,
+    seeded: Seeded,
+}
+
+impl RepoFixture {
+    pub fn did_key(&self) -> String {
+        encode_did_key(&self.keypair.public_key())
+    }
+
+    pub fn next_rkey(&mut self) -> String {
+        self.seeded.next_tid().to_string()
+    }
+
+    pub fn generate_record(&mut self, collection: &str) -> RepoRecord {
+        self.seeded.record(collection)
+    }
+
+    /// Signs `writes` as the next commit and applies it to the repo.
+    pub async fn commit(&mut self, writes: Vec<RecordWriteOp>) -> Result<CommitData> {
+        let commit = self
+            .repo
+            .format_commit(RecordWriteEnum::List(writes), self.keypair)
+            .await?;
+        let commit = resign(commit, self.seeded.next_tid(), self.keypair)?;
+        self.repo = self.repo.apply_commit(commit.clone()).await?;
+        Ok(commit)
+    }
+
+    /// Commits a generated record and returns its rkey alongside the commit.
+    pub async fn create_record(&mut self, collection: &str) -> Result<(String, CommitData)> {
+        let rkey = self.next_rkey();
+        let record = self.generate_record(collection);
+        let commit = self
+            .commit(vec![RecordWriteOp::Create(RecordCreateOrUpdateOp {
+                action: WriteOpAction::Create,
+                collection: collection.to_string(),
+                rkey: rkey.clone(),
+                record,
+            })])
+            .await?;
+        Ok((rkey, commit))
+    }
+
+    pub async fn update_record(&mut self, collection: &str, rkey: &str) -> Result<CommitData> {
+        let record = self.generate_record(collection);
+        self.commit(vec![RecordWriteOp::Update(RecordCreateOrUpdateOp {
+            action: WriteOpAction::Update,
+            collection: collection.to_string(),
+            rkey: rkey.to_string(),
+            record,
+        })])
+        .await
+    }
+
+    pub async fn delete_record(&mut self, collection: &str, rkey: &str) -> Result<CommitData> {
+        self.commit(vec![RecordWriteOp::Delete(RecordDeleteOp {
+            action: WriteOpAction::Delete,
+            collection: collection.to_string(),
+            rkey: rkey.to_string(),
+        })])
+        .await
+    }
+
+    /// The whole repo at its current head, as `com.atproto.sync.getRepo` serves it.
+    pub async fn repo_car(&self) -> Result<Vec<u8>> {
+        let stream = get_full_repo(self.repo.storage.clone(), self.repo.cid).await?;
+        pin_mut!(stream);
+        Ok(stream_to_buffer(stream).await?)
+    }
+
+    /// The commit and inclusion proofs for `paths`, as `com.atproto.sync.getRecord` serves them.
+    pub async fn records_car(&self, paths: Vec<RecordPath>) -> Result<Vec<u8>> {
+        Ok(get_records(self.repo.storage.clone(), self.repo.cid, paths).await?)
+    }
+}
+
+/// The blocks `commit` introduced, rooted at the commit, as a firehose `#commit` carries them.
+pub async fn commit_car(commit: &CommitData) -> Result<Vec<u8>> {
+    Ok(blocks_to_car_file(Some(&commit.cid), commit.new_blocks.clone()).await?)
+}
+
+// `Repo` stamps commits with a wall-clock rev, so swap in the fixture's before anything sees it
+fn resign(mut commit: CommitData, rev: TID, keypair: Keypair) -> Result<CommitData> {
+    let Some(bytes) = commit.new_blocks.get(commit.cid) else {
+        bail!("Commit block missing from new blocks")
+    };
+    let formatted: Commit = cbor_to_struct(bytes.clone())?;
+    let signed = sign_commit(
+        UnsignedCommit {
+            did: formatted.did,
+            rev: rev.0.clone(),
+            data: formatted.data,
+            prev: None,
+            version: formatted.version,
+        },
+        keypair,
+    )?;
+    let signed_bytes = struct_to_cbor(&signed)?;
+    let signed_cid = cid_for_cbor(&signed)?;
+
+    commit.new_blocks.delete(commit.cid)?;
+    commit.relevant_blocks.delete(commit.cid)?;
+    commit.new_blocks.set(signed_cid, signed_bytes.clone());
+    commit.relevant_blocks.set(signed_cid, signed_bytes);
+    commit.cid = signed_cid;
+    commit.rev = rev.0;
+    Ok(commit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::car::read_car_with_root;
+    use crate::sync::consumer::{verify_proofs, verify_repo};
+    use crate::types::RecordCidClaim;
+
+    const COLL_NAME: &str = "com.example.posts";
+
+    #[tokio::test]
+    async fn same_seed_builds_same_repo() -> Result<()> {
+        let mut first = RepoBuilder::new(7).records(COLL_NAME, 10).build().await?;
+        let mut second = RepoBuilder::new(7).records(COLL_NAME, 10).build().await?;
+        assert_eq!(first.did, second.did);
+        assert_eq!(first.genesis.cid, second.genesis.cid);
+
+        let (_, first_commit) = first.create_record(COLL_NAME).await?;
+        let (_, second_commit) = second.create_record(COLL_NAME).await?;
+        assert_eq!(first_commit.cid, second_commit.cid);
+        assert_eq!(
+            commit_car(&first_commit).await?,
+            commit_car(&second_commit).await?
+        );
+        assert_eq!(first.repo_car().await?, second.repo_car().await?);
+
+        let other = RepoBuilder::new(8).records(COLL_NAME, 10).build().await?;
+        assert_ne!(first.genesis.cid, other.genesis.cid);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fixture_cars_verify() -> Result<()> {
+        let mut fixture = RepoBuilder::new(1).records(COLL_NAME, 5).build().await?;
+        let (rkey, created) = fixture.create_record(COLL_NAME).await?;
+        let updated = fixture.update_record(COLL_NAME, &rkey).await?;
+        assert_eq!(updated.since, Some(created.rev.clone()));
+        assert!(TID(updated.rev).newer_than(&TID(created.rev)));
+
+        let mut car = read_car_with_root(fixture.repo_car().await?).await?;
+        assert_eq!(car.root, fixture.repo.cid);
+        let verified = verify_repo(
+            &mut car.blocks,
+            car.root,
+            Some(&fixture.did),
+            Some(&fixture.did_key()),
+            None,
+        )
+        .await?;
+        assert_eq!(verified.creates.len(), 6);
+
+        let cid = fixture
+            .repo
+            .data
+            .get(&format!("{COLL_NAME}/{rkey}"))
+            .await?;
+        let path = RecordPath {
+            collection: COLL_NAME.to_string(),
+            rkey: rkey.clone(),
+        };
+        let proofs = fixture.records_car(vec![path]).await?;
+        let claim = RecordCidClaim {
+            collection: COLL_NAME.to_string(),
+            rkey,
+            cid,
+        };
+        let result = verify_proofs(proofs, vec![claim], &fixture.did, &fixture.did_key()).await?;
+        assert_eq!(result.verified.len(), 1);
+        Ok(())
+    }
+}
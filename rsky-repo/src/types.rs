@@ -1,5 +1,6 @@
 use crate::block_map::BlockMap;
 use crate::cid_set::CidSet;
+use crate::error::RepoResult;
 use crate::storage::Ipld;
 use anyhow::{bail, Result};
 use lexicon_cid::Cid;
@@ -250,7 +251,7 @@ impl RecordWriteOp {
     }
 }
 
-pub fn create_write_to_op(write: PreparedCreateOrUpdate) -> Result<RecordWriteOp> {
+pub fn create_write_to_op(write: PreparedCreateOrUpdate) -> RepoResult<RecordWriteOp> {
     let write_at_uri: AtUri = write.uri.try_into()?;
     Ok(RecordWriteOp::Create {
         0: RecordCreateOrUpdateOp {
@@ -262,7 +263,7 @@ pub fn create_write_to_op(write: PreparedCreateOrUpdate) -> Result<RecordWriteOp
     })
 }
 
-pub fn update_write_to_op(write: PreparedCreateOrUpdate) -> Result<RecordWriteOp> {
+pub fn update_write_to_op(write: PreparedCreateOrUpdate) -> RepoResult<RecordWriteOp> {
     let write_at_uri: AtUri = write.uri.try_into()?;
     Ok(RecordWriteOp::Update {
         0: RecordCreateOrUpdateOp {
@@ -274,7 +275,7 @@ pub fn update_write_to_op(write: PreparedCreateOrUpdate) -> Result<RecordWriteOp
     })
 }
 
-pub fn delete_write_to_op(write: PreparedDelete) -> Result<RecordWriteOp> {
+pub fn delete_write_to_op(write: PreparedDelete) -> RepoResult<RecordWriteOp> {
     let write_at_uri: AtUri = write.uri.try_into()?;
     Ok(RecordWriteOp::Delete {
         0: RecordDeleteOp {
@@ -285,7 +286,7 @@ pub fn delete_write_to_op(write: PreparedDelete) -> Result<RecordWriteOp> {
     })
 }
 
-pub fn write_to_op(write: PreparedWrite) -> Result<RecordWriteOp> {
+pub fn write_to_op(write: PreparedWrite) -> RepoResult<RecordWriteOp> {
     match write {
         PreparedWrite::Create(c) => create_write_to_op(c),
         PreparedWrite::Update(u) => update_write_to_op(u),
@@ -1,10 +1,11 @@
 use crate::data_diff::DataDiff;
+use crate::error::{RepoError, RepoResult};
 use crate::storage::Ipld;
 use crate::types::{
     Commit, Lex, RecordCreateOrDeleteDescript, RecordPath, RecordUpdateDescript,
     RecordWriteDescript, RepoRecord, UnsignedCommit, VersionedCommit, WriteOpAction,
 };
-use anyhow::{bail, Result};
+use anyhow::{anyhow, Result};
 use futures::{stream, Stream, StreamExt, TryStreamExt};
 use lexicon_cid::Cid;
 use rsky_common::sign::sign_without_indexmap;
@@ -17,7 +18,7 @@ use std::fmt::Display;
 use std::str::FromStr;
 use tokio::try_join;
 
-pub fn sign_commit(unsigned: UnsignedCommit, keypair: Keypair) -> Result<Commit> {
+pub fn sign_commit(unsigned: UnsignedCommit, keypair: Keypair) -> RepoResult<Commit> {
     let commit_sig = sign_without_indexmap(&unsigned, &keypair.secret_key())?;
     Ok(Commit {
         did: unsigned.did,
@@ -29,7 +30,7 @@ pub fn sign_commit(unsigned: UnsignedCommit, keypair: Keypair) -> Result<Commit>
     })
 }
 
-pub fn verify_commit_sig(commit: Commit, did_key: &String) -> Result<bool> {
+pub fn verify_commit_sig(commit: Commit, did_key: &String) -> RepoResult<bool> {
     let sig = commit.sig;
     let rest = UnsignedCommit {
         did: commit.did,
@@ -38,9 +39,15 @@ pub fn verify_commit_sig(commit: Commit, did_key: &String) -> Result<bool> {
         prev: commit.prev,
         version: commit.version,
     };
-    let encoded = serde_ipld_dagcbor::to_vec(&rest)?;
+    let encoded =
+        serde_ipld_dagcbor::to_vec(&rest).map_err(|error| RepoError::Other(error.into()))?;
     let hash = Sha256::digest(&*encoded);
-    rsky_crypto::verify::verify_signature(did_key, hash.as_ref(), sig.as_slice(), None)
+    Ok(rsky_crypto::verify::verify_signature(
+        did_key,
+        hash.as_ref(),
+        sig.as_slice(),
+        None,
+    )?)
 }
 
 pub fn format_data_key<T: FromStr + Display>(collection: T, rkey: T) -> String {
@@ -99,39 +106,46 @@ pub fn ipld_to_lex(val: Ipld) -> Lex {
     }
 }
 
-pub fn cbor_to_lex(val: Vec<u8>) -> Result<Lex> {
-    let obj: Ipld = serde_ipld_dagcbor::from_slice(val.as_slice())?; //cbordecode
+pub fn cbor_to_lex(val: Vec<u8>) -> RepoResult<Lex> {
+    let obj: Ipld = serde_ipld_dagcbor::from_slice(val.as_slice())
+        .map_err(|error| RepoError::InvalidCbor(error.to_string()))?; //cbordecode
     Ok(ipld_to_lex(obj))
 }
 
-pub fn cbor_to_lex_record(val: Vec<u8>) -> Result<RepoRecord> {
+pub fn cbor_to_lex_record(val: Vec<u8>) -> RepoResult<RepoRecord> {
     let parsed = cbor_to_lex(val)?;
     match parsed {
         Lex::Map(map) => Ok(map),
-        _ => bail!("Lexicon record should be a json object"),
+        // lexicon records are always json objects
+        _ => Err(RepoError::InvalidRecordError),
     }
 }
 
 pub fn ensure_creates(
     descripts: Vec<RecordWriteDescript>,
-) -> Result<Vec<RecordCreateOrDeleteDescript>> {
+) -> RepoResult<Vec<RecordCreateOrDeleteDescript>> {
     let mut creates: Vec<RecordCreateOrDeleteDescript> = Default::default();
     for descript in descripts {
         match descript {
             RecordWriteDescript::Create(create) => creates.push(create),
-            _ => bail!("Unexpected action: {}", descript.action()),
+            _ => {
+                return Err(RepoError::Other(anyhow!(
+                    "Unexpected action: {}",
+                    descript.action()
+                )))
+            }
         }
     }
     Ok(creates)
 }
 
-pub async fn diff_to_write_descripts(diff: &DataDiff) -> Result<Vec<RecordWriteDescript>> {
+pub async fn diff_to_write_descripts(diff: &DataDiff) -> RepoResult<Vec<RecordWriteDescript>> {
     let (add_list, update_list, delete_list) = try_join!(
         // Process add_list
         stream::iter(diff.add_list())
             .then(|add| async move {
                 let RecordPath { collection, rkey } = parse_data_key(&add.key)?;
-                Ok::<RecordWriteDescript, anyhow::Error>(RecordWriteDescript::Create(
+                Ok::<RecordWriteDescript, RepoError>(RecordWriteDescript::Create(
                     RecordCreateOrDeleteDescript {
                         action: WriteOpAction::Create,
                         collection,
@@ -145,7 +159,7 @@ pub async fn diff_to_write_descripts(diff: &DataDiff) -> Result<Vec<RecordWriteD
         stream::iter(diff.update_list())
             .then(|upd| async move {
                 let RecordPath { collection, rkey } = parse_data_key(&upd.key)?;
-                Ok::<RecordWriteDescript, anyhow::Error>(RecordWriteDescript::Update(
+                Ok::<RecordWriteDescript, RepoError>(RecordWriteDescript::Update(
                     RecordUpdateDescript {
                         action: WriteOpAction::Update,
                         collection,
@@ -160,7 +174,7 @@ pub async fn diff_to_write_descripts(diff: &DataDiff) -> Result<Vec<RecordWriteD
         stream::iter(diff.delete_list())
             .then(|del| async move {
                 let RecordPath { collection, rkey } = parse_data_key(&del.key)?;
-                Ok::<RecordWriteDescript, anyhow::Error>(RecordWriteDescript::Delete(
+                Ok::<RecordWriteDescript, RepoError>(RecordWriteDescript::Delete(
                     RecordCreateOrDeleteDescript {
                         action: WriteOpAction::Delete,
                         collection,
@@ -174,10 +188,10 @@ pub async fn diff_to_write_descripts(diff: &DataDiff) -> Result<Vec<RecordWriteD
     Ok([add_list, update_list, delete_list].concat())
 }
 
-pub fn parse_data_key(key: &String) -> Result<RecordPath> {
+pub fn parse_data_key(key: &String) -> RepoResult<RecordPath> {
     let parts: Vec<&str> = key.split("/").collect();
     if parts.len() != 2 {
-        bail!("Invalid record key: `{key:?}`");
+        return Err(RepoError::InvalidKey(key.clone()));
     }
     Ok(RecordPath {
         collection: parts[0].to_owned(),
@@ -217,7 +231,7 @@ pub fn flatten_u8_arrays(chunks: &[Vec<u8>]) -> Vec<u8> {
 }
 
 /// Collects a stream of byte chunks into a single buffer
-pub async fn stream_to_buffer<S>(mut stream: S) -> Result<Vec<u8>>
+pub async fn stream_to_buffer<S>(mut stream: S) -> RepoResult<Vec<u8>>
 where
     S: Stream<Item = Result<Vec<u8>>> + Unpin,
 {